@@ -0,0 +1,60 @@
+use std::time::Instant;
+
+use microsat::batched::BatchedClauses;
+use microsat::benchmarks::random_k_sat;
+use microsat::cnf::{to_variable, Assignment, Clause, CNF};
+use microsat::expression::Expression;
+use microsat::rng::Rng;
+
+extern crate microsat;
+
+/// Compares checking many models clause by clause with the batched backend
+fn main() {
+    let num_models = std::env::var("MICROSAT_BENCH_SAMPLES")
+        .ok()
+        .and_then(|samples| samples.parse().ok())
+        .unwrap_or(256);
+
+    for num_clauses in [10_000, 100_000, 1_000_000] {
+        // Random clauses, each fixed to be satisfied by a planted model, so that checking a model
+        // goes through every clause as in verification
+        let mut rng = Rng::new(0);
+        let planted: Assignment = (1..=1000)
+            .map(|variable| (variable, rng.next_bool()))
+            .collect();
+        let mut expression = Expression::new();
+        for clause in random_k_sat(1000, num_clauses, 3, 0).get_clauses() {
+            let mut literals = clause.literals().clone();
+            if !literals
+                .iter()
+                .any(|literal| planted[&to_variable(*literal)] == (*literal > 0))
+            {
+                literals[0] = -literals[0];
+            }
+            let mut fixed = Clause::new();
+            for literal in literals {
+                fixed.insert(literal);
+            }
+            expression.add_clause(fixed);
+        }
+        let models: Vec<Assignment> = vec![planted; num_models];
+
+        let start = Instant::now();
+        let scalar: Vec<bool> = models
+            .iter()
+            .map(|model| expression.is_satisfied_by(model))
+            .collect();
+        let scalar_time = start.elapsed();
+
+        let batched = BatchedClauses::new(&expression);
+        let start = Instant::now();
+        let results = batched.check_models(&models);
+        let batched_time = start.elapsed();
+        assert_eq!(results, scalar);
+
+        println!(
+            "{:>8} clauses {:>5} models    scalar {:>12?}    batched {:>12?}",
+            num_clauses, num_models, scalar_time, batched_time
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use microsat::bench::run_standard_suite;
+
+extern crate microsat;
+
+/// Times the standard benchmark suite under every heuristic
+fn main() {
+    let samples = std::env::var("MICROSAT_BENCH_SAMPLES")
+        .ok()
+        .and_then(|samples| samples.parse().ok())
+        .unwrap_or(10);
+
+    for result in run_standard_suite(samples) {
+        println!("{}", result);
+    }
+}
@@ -0,0 +1,17 @@
+use std::time::Instant;
+
+use microsat::{benchmarks::standard_suite, solver::solve};
+
+extern crate microsat;
+
+/// Solves the built-in standard benchmark suite, printing the result and time of each instance
+fn main() {
+    for benchmark in standard_suite() {
+        let start = Instant::now();
+        let result = solve(benchmark.expression, false, true);
+        let elapsed = start.elapsed();
+
+        let status = if result.is_some() { "SAT" } else { "UNSAT" };
+        println!("{:<24} {:<6} {:?}", benchmark.name, status, elapsed);
+    }
+}
@@ -0,0 +1,74 @@
+use microsat::packages::{resolve, Dependency, Release, Repository, Version};
+
+extern crate microsat;
+
+fn version(version: &str) -> Version {
+    Version::parse(version).unwrap()
+}
+
+/// A small web stack, whose newest framework needs a runtime the legacy driver cannot run on
+fn repository() -> Repository {
+    let mut repository = Repository::new();
+    for (framework, runtime) in [("2.3", "1.0"), ("3.0", "2.0"), ("3.1", "2.0")] {
+        let major = version(runtime).major;
+        repository.add_release(
+            Release::new("framework", version(framework))
+                .depends_on(Dependency::range(
+                    "runtime",
+                    version(runtime),
+                    Version::new(major + 1, 0, 0),
+                ))
+                .depends_on(Dependency::any("http")),
+        );
+    }
+    for runtime in ["1.0", "1.2", "2.0", "2.4"] {
+        repository.add_release(Release::new("runtime", version(runtime)));
+    }
+    repository.add_release(Release::new("http", version("0.9")).depends_on(Dependency::any("tls")));
+    repository.add_release(
+        Release::new("http", version("1.0")).depends_on(Dependency::range(
+            "tls",
+            version("2.0"),
+            version("3.0"),
+        )),
+    );
+    repository.add_release(Release::new("tls", version("1.1")));
+    repository.add_release(Release::new("tls", version("2.0")));
+    repository.add_release(
+        Release::new("legacy-driver", version("0.5")).conflicts_with(Dependency::range(
+            "runtime",
+            version("2.0"),
+            version("3.0"),
+        )),
+    );
+    repository
+}
+
+/// Resolves a few sets of requirements against a small repository, printing the install plans
+/// and the requirements that conflict
+fn main() {
+    let repository = repository();
+    let cases = [
+        vec![Dependency::any("framework")],
+        vec![
+            Dependency::any("framework"),
+            Dependency::any("legacy-driver"),
+        ],
+        vec![
+            Dependency::any("tls"),
+            Dependency::range("framework", version("3.0"), version("4.0")),
+            Dependency::any("legacy-driver"),
+        ],
+        vec![Dependency::range("tls", version("3.0"), version("4.0"))],
+    ];
+
+    for requirements in &cases {
+        let names: Vec<String> = requirements.iter().map(|r| r.to_string()).collect();
+        println!("requirements: {}", names.join(", "));
+        match resolve(&repository, requirements) {
+            Ok(plan) => println!("{}\n(cost {})", plan, plan.cost),
+            Err(error) => println!("cannot resolve: {}", error),
+        }
+        println!();
+    }
+}
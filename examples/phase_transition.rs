@@ -0,0 +1,11 @@
+use microsat::experiments::{
+    run_phase_transition, write_phase_transition_csv, PhaseTransitionConfig,
+};
+
+extern crate microsat;
+
+/// Sweeps random 3-SAT over clause/variable ratios and prints the results as CSV
+fn main() -> std::io::Result<()> {
+    let points = run_phase_transition(&PhaseTransitionConfig::default());
+    write_phase_transition_csv(&points, &mut std::io::stdout())
+}
@@ -1,4 +1,4 @@
-use microsat::{expression::Expression, solver::solve};
+use microsat::{dimacs_parser::parse_dimacs_or_panic, solver::solve};
 
 extern crate microsat;
 
@@ -12,7 +12,7 @@ fn main() -> std::io::Result<()>
 
         println!("Solving file: {}", path);
 
-        let expression = Expression::from_cnf_file(&path);
+        let expression = parse_dimacs_or_panic(path);
 
         // Solve the expression
         let result = solve(expression, true, true);
@@ -1,4 +1,4 @@
-use microsat::{expression::Expression, solver::solve};
+use microsat::{dimacs_parser::parse_dimacs_or_panic, solver::solve};
 
 extern crate microsat;
 
@@ -7,7 +7,7 @@ fn main()
 {
     // Load the first argument as the filename
     let filename = std::env::args().nth(1).expect("No filename provided");
-    let expression = Expression::from_cnf_file(&filename);
+    let expression = parse_dimacs_or_panic(&filename);
 
     // Solve the expression
     let result = solve(expression, true, true);
@@ -0,0 +1,757 @@
+//! The AIGER format of and-inverter graphs, the circuits of the hardware model checking
+//! competitions, in its ASCII (`aag`) and binary (`aig`) variants, and their conversion to CNF.
+//!
+//! An AIGER literal is twice a variable, plus one if it is negated; `0` is false and `1` is true.
+//! Every variable is an input, a latch or the output of an and gate of two literals. In the
+//! binary variant the inputs and latches are numbered first, the gates follow in order, and each
+//! gate is stored as two variable-length differences, which keeps multi-million gate circuits
+//! small. Both variants end with an optional symbol table and comment section.
+//!
+//! The conversion is the Tseitin transformation of the gates, three clauses each, and keeps the
+//! variables of the circuit: AIGER variable `v` is CNF variable `v`. Latches are left free, as
+//! the current state of one step of the circuit, and the literals of the outputs, the next states
+//! and the bad states come back with the [`Expression`], to assert or to solve under assumptions.
+//!
+//! The invariant constraints, justice and fairness sections of AIGER 1.9 are not supported.
+
+use std::fmt;
+
+use hashbrown::HashMap;
+
+use crate::cnf::{Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+
+/// A literal of an and-inverter graph, see the module documentation.
+pub type AigerLiteral = u32;
+
+/// A latch, which holds `next` from one step of the circuit to the following one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Latch {
+    pub literal: AigerLiteral,
+    pub next: AigerLiteral,
+    /// The value at the first step, or `None` if it is left undefined.
+    pub init: Option<bool>,
+}
+
+/// An and gate, whose output `lhs` is the conjunction of `rhs0` and `rhs1`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AndGate {
+    pub lhs: AigerLiteral,
+    pub rhs0: AigerLiteral,
+    pub rhs1: AigerLiteral,
+}
+
+/// The kinds of symbols of the symbol table.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SymbolKind {
+    Input,
+    Latch,
+    Output,
+    Bad,
+}
+
+/// An and-inverter graph read from an AIGER file.
+#[derive(Clone, Debug, Default)]
+pub struct Aiger {
+    max_variable: Variable,
+    inputs: Vec<AigerLiteral>,
+    latches: Vec<Latch>,
+    outputs: Vec<AigerLiteral>,
+    bad: Vec<AigerLiteral>,
+    /// In topological order, so every gate follows the gates it reads.
+    ands: Vec<AndGate>,
+    symbols: HashMap<(SymbolKind, usize), String>,
+    comments: Vec<String>,
+}
+
+/// The CNF of an and-inverter graph, with the CNF literal of every part of the circuit, in the
+/// order of the file.
+#[derive(Clone)]
+pub struct AigerCnf {
+    pub expression: Expression,
+    pub inputs: Vec<Literal>,
+    /// The current states of the latches.
+    pub latches: Vec<Literal>,
+    pub next_states: Vec<Literal>,
+    pub outputs: Vec<Literal>,
+    pub bad: Vec<Literal>,
+}
+
+impl Aiger {
+    /// Returns the largest variable of the circuit, `M` in the header.
+    pub fn get_max_variable(&self) -> Variable {
+        self.max_variable
+    }
+
+    pub fn get_inputs(&self) -> &[AigerLiteral] {
+        &self.inputs
+    }
+
+    pub fn get_latches(&self) -> &[Latch] {
+        &self.latches
+    }
+
+    pub fn get_outputs(&self) -> &[AigerLiteral] {
+        &self.outputs
+    }
+
+    /// Returns the bad state properties of AIGER 1.9, which a safe circuit never reaches.
+    pub fn get_bad(&self) -> &[AigerLiteral] {
+        &self.bad
+    }
+
+    /// Returns the and gates, every one after the gates it reads.
+    pub fn get_ands(&self) -> &[AndGate] {
+        &self.ands
+    }
+
+    /// Returns the name of the input, latch, output or bad state property of the index, if the
+    /// symbol table has one.
+    pub fn get_name(&self, kind: SymbolKind, index: usize) -> Option<&str> {
+        self.symbols.get(&(kind, index)).map(|name| name.as_str())
+    }
+
+    pub fn get_comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Simulates one step of the circuit, returning the values of the outputs. Inputs and latches
+    /// past the end of the given values are false.
+    pub fn evaluate(&self, inputs: &[bool], latches: &[bool]) -> Vec<bool> {
+        let values = self.simulate(inputs, latches);
+        self.outputs
+            .iter()
+            .map(|literal| literal_value(&values, *literal))
+            .collect()
+    }
+
+    /// Simulates one step of the circuit, returning the values of the next states of the latches.
+    pub fn next_state(&self, inputs: &[bool], latches: &[bool]) -> Vec<bool> {
+        let values = self.simulate(inputs, latches);
+        self.latches
+            .iter()
+            .map(|latch| literal_value(&values, latch.next))
+            .collect()
+    }
+
+    /// Returns the value of every variable in one step of the circuit.
+    fn simulate(&self, inputs: &[bool], latches: &[bool]) -> Vec<bool> {
+        let mut values = vec![false; self.max_variable as usize + 1];
+        for (literal, value) in self.inputs.iter().zip(inputs) {
+            values[(*literal >> 1) as usize] = *value;
+        }
+        for (latch, value) in self.latches.iter().zip(latches) {
+            values[(latch.literal >> 1) as usize] = *value;
+        }
+        for gate in &self.ands {
+            values[(gate.lhs >> 1) as usize] =
+                literal_value(&values, gate.rhs0) && literal_value(&values, gate.rhs1);
+        }
+        values
+    }
+
+    /// Converts the circuit to CNF with the Tseitin transformation, see the module documentation.
+    /// A constant in the circuit takes one more variable, after those of the circuit.
+    pub fn to_cnf(&self) -> AigerCnf {
+        let mut expression = Expression::new();
+        let true_variable = self.max_variable as Literal + 1;
+        let mut uses_constant = false;
+        let mut to_literal = |literal: AigerLiteral| {
+            let variable = match literal >> 1 {
+                0 => {
+                    uses_constant = true;
+                    true_variable
+                }
+                variable => variable as Literal,
+            };
+            // The constants are the literals of a true variable, so 0 is its negation
+            if (literal & 1 == 1) != (literal >> 1 == 0) {
+                -variable
+            } else {
+                variable
+            }
+        };
+
+        let mut clauses = Vec::with_capacity(3 * self.ands.len());
+        for gate in &self.ands {
+            let (lhs, rhs0, rhs1) = (
+                to_literal(gate.lhs),
+                to_literal(gate.rhs0),
+                to_literal(gate.rhs1),
+            );
+            clauses.push(vec![-lhs, rhs0]);
+            clauses.push(vec![-lhs, rhs1]);
+            clauses.push(vec![lhs, -rhs0, -rhs1]);
+        }
+        let inputs = self.inputs.iter().map(|input| to_literal(*input)).collect();
+        let latches = self
+            .latches
+            .iter()
+            .map(|latch| to_literal(latch.literal))
+            .collect();
+        let next_states = self
+            .latches
+            .iter()
+            .map(|latch| to_literal(latch.next))
+            .collect();
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| to_literal(*output))
+            .collect();
+        let bad = self.bad.iter().map(|bad| to_literal(*bad)).collect();
+        if uses_constant {
+            clauses.push(vec![true_variable]);
+        }
+
+        for literals in clauses {
+            let mut clause = Clause::new();
+            for literal in literals {
+                clause.insert_checked(literal);
+            }
+            expression.add_clause(clause);
+        }
+        AigerCnf {
+            expression,
+            inputs,
+            latches,
+            next_states,
+            outputs,
+            bad,
+        }
+    }
+}
+
+fn literal_value(values: &[bool], literal: AigerLiteral) -> bool {
+    values[(literal >> 1) as usize] != (literal & 1 == 1)
+}
+
+/// A problem in an AIGER file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AigerParseError {
+    /// The 1-based line number of the problem, or of the start of the binary and gates, or 0 if
+    /// the file cannot be read.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AigerParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}", self.message);
+        }
+        write!(f, "Invalid AIGER on line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AigerParseError {}
+
+/// Parses an and-inverter graph from an AIGER file, in either variant.
+pub fn parse_aiger(filename: &str) -> Result<Aiger, AigerParseError> {
+    let file = std::fs::read(filename).map_err(|error| AigerParseError {
+        line: 0,
+        message: format!("Cannot read {}: {}", filename, error),
+    })?;
+    parse_aiger_bytes(&file)
+}
+
+/// Parses an and-inverter graph from the contents of an AIGER file, in the ASCII variant if the
+/// header starts with `aag` and in the binary one if it starts with `aig`.
+pub fn parse_aiger_bytes(contents: &[u8]) -> Result<Aiger, AigerParseError> {
+    let mut reader = Reader {
+        contents,
+        position: 0,
+        line: 0,
+    };
+    let header = reader.read_line()?.unwrap_or_default();
+    let error = |message: String| Err(AigerParseError { line: 1, message });
+    let mut tokens = header.split_whitespace();
+    let binary = match tokens.next() {
+        Some("aag") => false,
+        Some("aig") => true,
+        _ => return error("Expected a header starting with aag or aig".to_string()),
+    };
+    let mut counts = Vec::new();
+    for token in tokens {
+        match token.parse::<usize>() {
+            Ok(count) => counts.push(count),
+            Err(_) => return error(format!("Invalid count {}", token)),
+        }
+    }
+    if !(5..=9).contains(&counts.len()) {
+        return error("Expected the counts M I L O A, optionally followed by B C J F".to_string());
+    }
+    counts.resize(9, 0);
+    let [max_variable, num_inputs, num_latches, num_outputs, num_ands, num_bad] = [
+        counts[0], counts[1], counts[2], counts[3], counts[4], counts[5],
+    ];
+    if counts[6..].iter().any(|count| *count > 0) {
+        return error("Invariant constraints, justice and fairness are not supported".to_string());
+    }
+    // The conversion to CNF may take one more variable for the constants, and with 64-bit
+    // literals the AIGER literals are the narrower ones
+    if max_variable >= Literal::MAX as usize || max_variable > (AigerLiteral::MAX / 2) as usize {
+        return error(format!(
+            "{} variables are more than are supported",
+            max_variable
+        ));
+    }
+    let num_defined = num_inputs
+        .checked_add(num_latches)
+        .and_then(|sum| sum.checked_add(num_ands));
+    if binary && num_defined != Some(max_variable) {
+        return error("M must be I + L + A in the binary variant".to_string());
+    }
+
+    let mut aiger = Aiger {
+        max_variable: max_variable as Variable,
+        ..Aiger::default()
+    };
+    let max_literal = 2 * max_variable as AigerLiteral + 1;
+    let literal = |token: &str, line: usize| match token.parse::<AigerLiteral>() {
+        Ok(literal) if literal <= max_literal => Ok(literal),
+        _ => Err(AigerParseError {
+            line,
+            message: format!("Invalid literal {}", token),
+        }),
+    };
+
+    // Inputs and latches are positive literals of variables other than the constant
+    let definable = |literal: AigerLiteral, line: usize| match literal {
+        2.. if literal & 1 == 0 => Ok(literal),
+        _ => Err(AigerParseError {
+            line,
+            message: format!("The literal {} cannot be defined", literal),
+        }),
+    };
+    for index in 0..num_inputs {
+        if binary {
+            aiger.inputs.push(2 * (index as AigerLiteral + 1));
+            continue;
+        }
+        let (line, tokens) = reader.read_tokens(1, 1, "an input")?;
+        aiger
+            .inputs
+            .push(definable(literal(tokens[0], line)?, line)?);
+    }
+    for index in 0..num_latches {
+        let (line, tokens) = if binary {
+            reader.read_tokens(1, 2, "a latch")?
+        } else {
+            reader.read_tokens(2, 3, "a latch")?
+        };
+        let (current, rest) = if binary {
+            (2 * (num_inputs + index + 1) as AigerLiteral, &tokens[..])
+        } else {
+            (definable(literal(tokens[0], line)?, line)?, &tokens[1..])
+        };
+        let init = match rest.get(1) {
+            None => Some(false),
+            Some(token) => match literal(token, line)? {
+                0 => Some(false),
+                1 => Some(true),
+                init if init == current => None,
+                _ => {
+                    return Err(AigerParseError {
+                        line,
+                        message: format!("Invalid initial value {}", token),
+                    })
+                }
+            },
+        };
+        aiger.latches.push(Latch {
+            literal: current,
+            next: literal(rest[0], line)?,
+            init,
+        });
+    }
+    for _ in 0..num_outputs {
+        let (line, tokens) = reader.read_tokens(1, 1, "an output")?;
+        aiger.outputs.push(literal(tokens[0], line)?);
+    }
+    for _ in 0..num_bad {
+        let (line, tokens) = reader.read_tokens(1, 1, "a bad state property")?;
+        aiger.bad.push(literal(tokens[0], line)?);
+    }
+    let first_and_line = reader.line + 1;
+    for index in 0..num_ands {
+        if binary {
+            // At most M, which is I + L + A
+            let lhs = 2 * (num_inputs + num_latches + index + 1) as AigerLiteral;
+            let (delta0, delta1) = (reader.read_number()?, reader.read_number()?);
+            let rhs0 = lhs.checked_sub(delta0);
+            let rhs1 = rhs0.and_then(|rhs0| rhs0.checked_sub(delta1));
+            match (rhs0, rhs1) {
+                (Some(rhs0), Some(rhs1)) => aiger.ands.push(AndGate { lhs, rhs0, rhs1 }),
+                _ => {
+                    return Err(AigerParseError {
+                        line: first_and_line,
+                        message: format!("Invalid differences of the and gate {}", lhs),
+                    })
+                }
+            }
+            continue;
+        }
+        let (line, tokens) = reader.read_tokens(3, 3, "an and gate")?;
+        aiger.ands.push(AndGate {
+            lhs: literal(tokens[0], line)?,
+            rhs0: literal(tokens[1], line)?,
+            rhs1: literal(tokens[2], line)?,
+        });
+    }
+
+    while let Some(line) = reader.read_line()? {
+        let kind = match line.chars().next() {
+            Some('c') => {
+                while let Some(comment) = reader.read_line()? {
+                    aiger.comments.push(comment.to_string());
+                }
+                break;
+            }
+            Some('i') => (SymbolKind::Input, num_inputs),
+            Some('l') => (SymbolKind::Latch, num_latches),
+            Some('o') => (SymbolKind::Output, num_outputs),
+            Some('b') => (SymbolKind::Bad, num_bad),
+            _ => {
+                return Err(AigerParseError {
+                    line: reader.line,
+                    message: format!("Invalid symbol {}", line),
+                })
+            }
+        };
+        let (index, name) = line[1..].split_once(' ').unwrap_or((&line[1..], ""));
+        match index.parse::<usize>() {
+            Ok(index) if index < kind.1 && !name.is_empty() => {
+                aiger.symbols.insert((kind.0, index), name.to_string());
+            }
+            _ => {
+                return Err(AigerParseError {
+                    line: reader.line,
+                    message: format!("Invalid symbol {}", line),
+                })
+            }
+        }
+    }
+
+    sort_gates(&mut aiger, first_and_line)?;
+    Ok(aiger)
+}
+
+/// Checks that every variable is defined once, as an input, a latch or a gate, and that every
+/// literal read is defined, and sorts the gates so that every one follows the gates it reads.
+fn sort_gates(aiger: &mut Aiger, first_and_line: usize) -> Result<(), AigerParseError> {
+    let error = |message: String| {
+        Err(AigerParseError {
+            line: first_and_line,
+            message,
+        })
+    };
+    const UNDEFINED: usize = usize::MAX;
+    const DEFINED: usize = usize::MAX - 1;
+    // The gate defining every variable, or whether an input or latch does
+    let mut definitions = vec![UNDEFINED; aiger.max_variable as usize + 1];
+    definitions[0] = DEFINED;
+    let mut define = |literal: AigerLiteral, definition: usize| {
+        let variable = (literal >> 1) as usize;
+        if literal & 1 == 1 || variable == 0 || definitions[variable] != UNDEFINED {
+            return Err(format!("The literal {} cannot be defined", literal));
+        }
+        definitions[variable] = definition;
+        Ok(())
+    };
+    for literal in &aiger.inputs {
+        define(*literal, DEFINED).or_else(error)?;
+    }
+    for latch in &aiger.latches {
+        define(latch.literal, DEFINED).or_else(error)?;
+    }
+    for (index, gate) in aiger.ands.iter().enumerate() {
+        define(gate.lhs, index).or_else(error)?;
+    }
+
+    let read = aiger.outputs.iter().chain(&aiger.bad).copied();
+    let read = read.chain(aiger.latches.iter().map(|latch| latch.next));
+    let read = read.chain(aiger.ands.iter().flat_map(|gate| [gate.rhs0, gate.rhs1]));
+    for literal in read.collect::<Vec<_>>() {
+        if definitions[(literal >> 1) as usize] == UNDEFINED {
+            return error(format!("The literal {} is not defined", literal));
+        }
+    }
+
+    // A depth-first search from every gate, emitting a gate once the gates it reads are
+    const VISITING: u8 = 1;
+    const DONE: u8 = 2;
+    let mut states = vec![0u8; aiger.ands.len()];
+    let mut sorted = Vec::with_capacity(aiger.ands.len());
+    for root in 0..aiger.ands.len() {
+        let mut stack = vec![(root, false)];
+        while let Some((index, expanded)) = stack.pop() {
+            if expanded {
+                states[index] = DONE;
+                sorted.push(aiger.ands[index]);
+                continue;
+            }
+            match states[index] {
+                DONE => continue,
+                VISITING => {
+                    return error(format!(
+                        "The gate {} depends on itself",
+                        aiger.ands[index].lhs
+                    ))
+                }
+                _ => {}
+            }
+            states[index] = VISITING;
+            stack.push((index, true));
+            let gate = aiger.ands[index];
+            for rhs in [gate.rhs0, gate.rhs1] {
+                let definition = definitions[(rhs >> 1) as usize];
+                if definition < DEFINED && states[definition] != DONE {
+                    stack.push((definition, false));
+                }
+            }
+        }
+    }
+    aiger.ands = sorted;
+    Ok(())
+}
+
+/// Reads the lines and the variable-length numbers of an AIGER file.
+struct Reader<'a> {
+    contents: &'a [u8],
+    position: usize,
+    /// The number of lines read so far.
+    line: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Returns the next line without its line break, or `None` at the end of the file.
+    fn read_line(&mut self) -> Result<Option<&'a str>, AigerParseError> {
+        if self.position >= self.contents.len() {
+            return Ok(None);
+        }
+        let rest = &self.contents[self.position..];
+        let length = rest
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .unwrap_or(rest.len());
+        self.position += length + 1;
+        self.line += 1;
+        match std::str::from_utf8(&rest[..length]) {
+            Ok(line) => Ok(Some(line.trim_end_matches('\r'))),
+            Err(_) => Err(AigerParseError {
+                line: self.line,
+                message: "Invalid UTF-8".to_string(),
+            }),
+        }
+    }
+
+    /// Returns the next line, split into between `min` and `max` tokens, with its number.
+    fn read_tokens(
+        &mut self,
+        min: usize,
+        max: usize,
+        what: &str,
+    ) -> Result<(usize, Vec<&'a str>), AigerParseError> {
+        let Some(line) = self.read_line()? else {
+            return Err(AigerParseError {
+                line: self.line + 1,
+                message: format!("Expected {} instead of the end of the file", what),
+            });
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() < min || tokens.len() > max {
+            return Err(AigerParseError {
+                line: self.line,
+                message: format!("Expected {} instead of '{}'", what, line),
+            });
+        }
+        Ok((self.line, tokens))
+    }
+
+    /// Reads a number stored seven bits at a time, lowest first, with the high bit set on every
+    /// byte but the last.
+    fn read_number(&mut self) -> Result<AigerLiteral, AigerParseError> {
+        let mut number: AigerLiteral = 0;
+        for shift in (0..32).step_by(7) {
+            let Some(byte) = self.contents.get(self.position) else {
+                break;
+            };
+            self.position += 1;
+            number |= ((byte & 0x7f) as AigerLiteral) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(number);
+            }
+        }
+        Err(AigerParseError {
+            line: self.line + 1,
+            message: "Truncated and gates".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpll::solve_dpll;
+    use crate::rng::Rng;
+
+    const HALF_ADDER: &str =
+        "aag 7 2 0 2 3\n2\n4\n6\n12\n6 13 15\n12 2 4\n14 3 5\ni0 x\ni1 y\no0 s\no1 c\nc\nhalf adder\n";
+
+    /// Checks that the models of the CNF, with the inputs and latches fixed, have the outputs and
+    /// next states of the simulation.
+    fn check(aiger: &Aiger) {
+        let cnf = aiger.to_cnf();
+        let num_free = aiger.inputs.len() + aiger.latches.len();
+        for mask in 0..1u32 << num_free {
+            let values: Vec<bool> = (0..num_free).map(|bit| mask >> bit & 1 == 1).collect();
+            let (inputs, latches) = values.split_at(aiger.inputs.len());
+            let mut fixed = cnf.expression.clone();
+            for (literal, value) in cnf.inputs.iter().chain(&cnf.latches).zip(&values) {
+                let mut clause = Clause::new();
+                clause.insert(if *value { *literal } else { -literal });
+                fixed.add_clause(clause);
+            }
+            fixed.optimize();
+            let model = solve_dpll(&mut fixed).unwrap();
+            let value = |literal: &Literal| {
+                let value = model.get(&literal.unsigned_abs()).copied().unwrap_or(false);
+                value == (*literal > 0)
+            };
+            let outputs: Vec<bool> = cnf.outputs.iter().map(value).collect();
+            let next_states: Vec<bool> = cnf.next_states.iter().map(value).collect();
+            assert_eq!(outputs, aiger.evaluate(inputs, latches), "{:b}", mask);
+            assert_eq!(next_states, aiger.next_state(inputs, latches), "{:b}", mask);
+        }
+    }
+
+    #[test]
+    fn test_parse_aiger_ascii() {
+        let aiger = parse_aiger_bytes(HALF_ADDER.as_bytes()).unwrap();
+        assert_eq!(aiger.get_inputs(), &[2, 4]);
+        assert_eq!(aiger.get_outputs(), &[6, 12]);
+        // The sum gate reads the other two, so it comes last
+        assert_eq!(aiger.get_ands()[2].lhs, 6);
+        assert_eq!(aiger.get_name(SymbolKind::Output, 1), Some("c"));
+        assert_eq!(aiger.get_comments(), &["half adder".to_string()]);
+        for (x, y) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(aiger.evaluate(&[x, y], &[]), vec![x != y, x && y]);
+        }
+        check(&aiger);
+
+        // A toggle flip-flop with an enable and a reset, and a constant output
+        let toggle = "aag 7 2 1 3 4\n2\n4\n6 14\n6\n7\n0\n8 3 6\n10 2 7\n12 9 11\n14 12 5\n";
+        let aiger = parse_aiger_bytes(toggle.as_bytes()).unwrap();
+        assert_eq!(aiger.get_latches()[0].init, Some(false));
+        check(&aiger);
+
+        for (contents, line) in [
+            ("aag 1 1 0 0 0\n3\n", 2),
+            ("aag 2 1 0 1 0\n2\n4\n", 4),
+            ("aag 3 1 0 1 2\n2\n4\n4 6 2\n6 4 2\n", 4),
+            ("aag 2 2 0 0 0\n2\n2\n", 4),
+            ("aag 1 1 0 0 0 0 1\n2\n", 1),
+            ("aag 2147483648 0 0 0 0\n", 1),
+            ("aag 1 1 0 1 0\n2\n", 3),
+            ("aag 1 1 0 0 0\n2\nx0 name\n", 3),
+        ] {
+            assert_eq!(
+                parse_aiger_bytes(contents.as_bytes()).unwrap_err().line,
+                line,
+                "{}",
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_aiger_binary() {
+        let mut contents = b"aig 5 2 0 2 3\n10\n6\n".to_vec();
+        contents.extend([2, 2, 3, 2, 1, 2]);
+        contents.extend(b"i0 x\ni1 y\no0 s\no1 c\nc\nhalf adder\n");
+        let aiger = parse_aiger_bytes(&contents).unwrap();
+        assert_eq!(
+            aiger.get_ands(),
+            &[
+                AndGate {
+                    lhs: 6,
+                    rhs0: 4,
+                    rhs1: 2
+                },
+                AndGate {
+                    lhs: 8,
+                    rhs0: 5,
+                    rhs1: 3
+                },
+                AndGate {
+                    lhs: 10,
+                    rhs0: 9,
+                    rhs1: 7
+                },
+            ]
+        );
+        assert_eq!(aiger.get_name(SymbolKind::Input, 0), Some("x"));
+        for (x, y) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(aiger.evaluate(&[x, y], &[]), vec![x != y, x && y]);
+        }
+        check(&aiger);
+
+        // A difference of more than seven bits, and one running past the end
+        let mut contents = b"aig 130 129 0 1 1\n260\n".to_vec();
+        contents.extend([0x80 | 2, 0x01, 2]);
+        let aiger = parse_aiger_bytes(&contents).unwrap();
+        assert_eq!(aiger.get_ands()[0].rhs0, 260 - 130);
+        let mut contents = b"aig 3 2 0 1 1\n6\n".to_vec();
+        contents.push(0x82);
+        assert!(parse_aiger_bytes(&contents).is_err());
+
+        // Counts adding up beyond the largest number
+        let contents = b"aig 5 18446744073709551615 1 0 0\n";
+        assert_eq!(parse_aiger_bytes(contents).unwrap_err().line, 1);
+        assert_eq!(parse_aiger("examples/missing.aig").unwrap_err().line, 0);
+    }
+
+    #[test]
+    fn test_random_circuits() {
+        let mut rng = Rng::new(9);
+        for _ in 0..20 {
+            let (num_inputs, num_latches, num_ands) = (3, 2, 12);
+            let max_variable = num_inputs + num_latches + num_ands;
+            let mut contents = format!(
+                "aag {} {} {} 3 {}\n",
+                max_variable, num_inputs, num_latches, num_ands
+            );
+            let mut random_literal = |below: usize| rng.next_below(2 * below as u64 + 2) as usize;
+            for input in 1..=num_inputs {
+                contents.push_str(&format!("{}\n", 2 * input));
+            }
+            let mut gates = Vec::new();
+            for gate in 0..num_ands {
+                let below = num_inputs + num_latches + gate;
+                gates.push(format!(
+                    "{} {} {}\n",
+                    2 * (below + 1),
+                    random_literal(below),
+                    random_literal(below)
+                ));
+            }
+            for latch in 1..=num_latches {
+                contents.push_str(&format!(
+                    "{} {}\n",
+                    2 * (num_inputs + latch),
+                    random_literal(max_variable)
+                ));
+            }
+            for _ in 0..3 {
+                contents.push_str(&format!("{}\n", random_literal(max_variable)));
+            }
+            // In reverse, which the parser sorts back
+            gates.reverse();
+            contents.extend(gates);
+
+            let aiger = parse_aiger_bytes(contents.as_bytes()).unwrap();
+            check(&aiger);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! An archive of the learned clauses deleted by the CDCL solver, kept compressed aside from the
+//! clause database so the clauses can be brought back instead of learned again.
+//!
+//! Every archived clause is stored as its literals sorted by watch index (twice the variable, plus
+//! one if negative), written as the differences between consecutive indices in LEB128, so short
+//! clauses over nearby variables take a byte or two per literal. The archive holds a bounded
+//! number of bytes and drops its oldest clauses to make room for new ones.
+//!
+//! Enable it with [`CdclSolver::set_archive`](crate::cdcl::CdclSolver::set_archive). At every
+//! restart, the solver re-imports the archived clauses whose variables all took part in a conflict
+//! since the previous restart: the search has come back to the part of the space they constrain.
+
+use std::collections::VecDeque;
+
+use crate::cnf::{to_variable, Literal};
+
+/// The limits of the archive of deleted learned clauses.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ArchivePolicy {
+    /// The most bytes the archived clauses take. The oldest clauses are dropped to make room.
+    pub max_bytes: usize,
+}
+
+impl Default for ArchivePolicy {
+    fn default() -> Self {
+        ArchivePolicy { max_bytes: 1 << 20 }
+    }
+}
+
+/// Counters describing the use of the archive, over the life of the solver.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ArchiveStatistics {
+    pub num_archived: usize,
+    /// The archived clauses brought back into the clause database.
+    pub num_reimported: usize,
+    /// The archived clauses dropped to make room, or because they could not be re-imported.
+    pub num_dropped: usize,
+    /// The clauses in the archive now.
+    pub num_clauses: usize,
+    /// The bytes the clauses in the archive take now.
+    pub num_bytes: usize,
+}
+
+impl ArchiveStatistics {
+    /// Returns the fraction of the archived clauses that were re-imported, or 0 if none were
+    /// archived.
+    pub fn get_hit_rate(&self) -> f64 {
+        if self.num_archived == 0 {
+            0.0
+        } else {
+            self.num_reimported as f64 / self.num_archived as f64
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ArchivedClause {
+    bytes: Box<[u8]>,
+    lbd: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ClauseArchive {
+    policy: ArchivePolicy,
+    clauses: VecDeque<ArchivedClause>,
+    statistics: ArchiveStatistics,
+}
+
+fn watch_index(literal: Literal) -> u32 {
+    2 * to_variable(literal) as u32 + (literal < 0) as u32
+}
+
+fn encode(literals: &[Literal]) -> Box<[u8]> {
+    let mut indices: Vec<u32> = literals
+        .iter()
+        .map(|literal| watch_index(*literal))
+        .collect();
+    indices.sort_unstable();
+    let mut bytes = Vec::with_capacity(indices.len() + 1);
+    let mut previous = 0;
+    for index in indices {
+        let mut delta = index - previous;
+        previous = index;
+        while delta >= 0x80 {
+            bytes.push((delta & 0x7f) as u8 | 0x80);
+            delta >>= 7;
+        }
+        bytes.push(delta as u8);
+    }
+    bytes.into_boxed_slice()
+}
+
+fn decode(bytes: &[u8]) -> Vec<Literal> {
+    let mut literals = Vec::new();
+    let mut index = 0;
+    let mut delta = 0;
+    let mut shift = 0;
+    for byte in bytes {
+        delta |= ((byte & 0x7f) as u32) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            index += delta;
+            let variable = (index / 2) as Literal;
+            literals.push(if index % 2 == 1 { -variable } else { variable });
+            delta = 0;
+            shift = 0;
+        }
+    }
+    literals
+}
+
+impl ClauseArchive {
+    pub fn new(policy: ArchivePolicy) -> ClauseArchive {
+        ClauseArchive {
+            policy,
+            ..ClauseArchive::default()
+        }
+    }
+
+    pub fn get_statistics(&self) -> ArchiveStatistics {
+        self.statistics
+    }
+
+    /// Archives the clause with its LBD, and returns the clauses dropped to make room, or the
+    /// clause itself if it is larger than the archive.
+    pub fn push(&mut self, literals: &[Literal], lbd: usize) -> Vec<Vec<Literal>> {
+        let bytes = encode(literals);
+        self.statistics.num_archived += 1;
+        if bytes.len() > self.policy.max_bytes {
+            self.statistics.num_dropped += 1;
+            return vec![literals.to_vec()];
+        }
+
+        let mut dropped = Vec::new();
+        while self.statistics.num_bytes + bytes.len() > self.policy.max_bytes {
+            let oldest = self.clauses.pop_front().unwrap();
+            self.statistics.num_bytes -= oldest.bytes.len();
+            self.statistics.num_clauses -= 1;
+            self.statistics.num_dropped += 1;
+            dropped.push(decode(&oldest.bytes));
+        }
+        self.statistics.num_bytes += bytes.len();
+        self.statistics.num_clauses += 1;
+        self.clauses.push_back(ArchivedClause { bytes, lbd });
+        dropped
+    }
+
+    /// Removes the clauses the predicate accepts from the archive, and returns them with their
+    /// LBDs, oldest first. The caller counts them as re-imported or dropped.
+    pub fn take(
+        &mut self,
+        mut accept: impl FnMut(&[Literal]) -> bool,
+    ) -> Vec<(Vec<Literal>, usize)> {
+        let mut taken = Vec::new();
+        let statistics = &mut self.statistics;
+        self.clauses.retain(|clause| {
+            let literals = decode(&clause.bytes);
+            if !accept(&literals) {
+                return true;
+            }
+            statistics.num_bytes -= clause.bytes.len();
+            statistics.num_clauses -= 1;
+            taken.push((literals, clause.lbd));
+            false
+        });
+        taken
+    }
+
+    pub(crate) fn count_reimported(&mut self, num_reimported: usize, num_dropped: usize) {
+        self.statistics.num_reimported += num_reimported;
+        self.statistics.num_dropped += num_dropped;
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.clauses.capacity() * std::mem::size_of::<ArchivedClause>() + self.statistics.num_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clause_archive() {
+        let bytes = encode(&[-3, 1, 200, -199]);
+        // Only the difference between the indices 7 and 399 takes a second byte
+        assert_eq!(bytes.len(), 5);
+        assert_eq!(decode(&bytes), vec![1, -3, -199, 200]);
+
+        let mut archive = ClauseArchive::new(ArchivePolicy { max_bytes: 7 });
+        assert!(archive.push(&[1, 2, 3], 3).is_empty());
+        assert!(archive.push(&[-4, 5], 2).is_empty());
+        // The oldest clause makes room for the new one
+        assert_eq!(archive.push(&[6, 7, -8], 3), vec![vec![1, 2, 3]]);
+        assert_eq!(archive.push(&(1..10).collect::<Vec<_>>(), 9).len(), 1);
+
+        let taken = archive.take(|literals| literals.contains(&-8));
+        assert_eq!(taken, vec![(vec![6, 7, -8], 3)]);
+        archive.count_reimported(1, 0);
+        let statistics = archive.get_statistics();
+        assert_eq!(
+            statistics,
+            ArchiveStatistics {
+                num_archived: 4,
+                num_reimported: 1,
+                num_dropped: 2,
+                num_clauses: 1,
+                num_bytes: 2,
+            }
+        );
+        assert_eq!(statistics.get_hit_rate(), 0.25);
+    }
+}
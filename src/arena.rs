@@ -0,0 +1,283 @@
+//! Contiguous storage for the literals of many clauses, allocated through a pluggable allocator.
+//!
+//! Every literal of a [`ClauseArena`] lives in one buffer, which is the only memory the arena
+//! allocates for them: it grows by allocating a larger buffer from its [`ClauseAlloc`] and moving
+//! the literals over. Embedders can supply their own allocator (a bump allocator, huge pages,
+//! or memory placed near a NUMA node) to the clause databases built on the arena, such as the
+//! [`Propagator`](crate::propagation::Propagator). The positions of the clauses are kept in an
+//! ordinary vector, which is small next to the literals.
+
+use std::alloc::{handle_alloc_error, Layout};
+use std::fmt;
+use std::ptr::NonNull;
+
+use crate::cnf::Literal;
+
+/// An allocator for the buffers of a [`ClauseArena`].
+///
+/// # Safety
+///
+/// `allocate` must return null, or a block valid for reads and writes of `layout.size()` bytes
+/// and aligned to `layout.align()`, which stays valid until it is passed to `deallocate`.
+pub unsafe trait ClauseAlloc {
+    /// Allocates a block for the layout, whose size is never zero, or returns null on failure.
+    fn allocate(&self, layout: Layout) -> *mut u8;
+
+    /// Frees a block.
+    ///
+    /// # Safety
+    ///
+    /// `pointer` was returned by `allocate` on this allocator with the same layout, and is not
+    /// used afterwards.
+    unsafe fn deallocate(&self, pointer: *mut u8, layout: Layout);
+}
+
+/// The global allocator.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemAlloc;
+
+unsafe impl ClauseAlloc for SystemAlloc {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        unsafe { std::alloc::alloc(layout) }
+    }
+
+    unsafe fn deallocate(&self, pointer: *mut u8, layout: Layout) {
+        std::alloc::dealloc(pointer, layout)
+    }
+}
+
+/// Clauses stored one after the other in a single buffer. Clauses keep their length once added,
+/// but their literals can be reordered in place.
+pub struct ClauseArena<A: ClauseAlloc = SystemAlloc> {
+    alloc: A,
+    buffer: NonNull<Literal>,
+    capacity: usize,
+    len: usize,
+    /// The start and length of every clause in the buffer.
+    spans: Vec<(usize, usize)>,
+}
+
+// The arena owns its buffer like a `Vec` does
+unsafe impl<A: ClauseAlloc + Send> Send for ClauseArena<A> {}
+unsafe impl<A: ClauseAlloc + Sync> Sync for ClauseArena<A> {}
+
+impl ClauseArena {
+    pub fn new() -> ClauseArena {
+        ClauseArena::with_allocator(SystemAlloc)
+    }
+}
+
+impl<A: ClauseAlloc> ClauseArena<A> {
+    pub fn with_allocator(alloc: A) -> ClauseArena<A> {
+        ClauseArena {
+            alloc,
+            buffer: NonNull::dangling(),
+            capacity: 0,
+            len: 0,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Appends a clause and returns its index. Indices are assigned consecutively from 0.
+    pub fn push(&mut self, literals: &[Literal]) -> usize {
+        if self.len + literals.len() > self.capacity {
+            self.grow(self.len + literals.len());
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                literals.as_ptr(),
+                self.buffer.as_ptr().add(self.len),
+                literals.len(),
+            );
+        }
+        self.spans.push((self.len, literals.len()));
+        self.len += literals.len();
+        self.spans.len() - 1
+    }
+
+    /// Moves the literals to a buffer for at least `required` literals.
+    fn grow(&mut self, required: usize) {
+        let capacity = required.max(2 * self.capacity).max(64);
+        let layout = Layout::array::<Literal>(capacity).expect("The clause arena is too large");
+        let Some(buffer) = NonNull::new(self.alloc.allocate(layout) as *mut Literal) else {
+            handle_alloc_error(layout);
+        };
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.buffer.as_ptr(), buffer.as_ptr(), self.len);
+        }
+        self.release();
+        self.buffer = buffer;
+        self.capacity = capacity;
+    }
+
+    fn release(&mut self) {
+        if self.capacity > 0 {
+            let layout = Layout::array::<Literal>(self.capacity).unwrap();
+            unsafe {
+                self.alloc
+                    .deallocate(self.buffer.as_ptr() as *mut u8, layout);
+            }
+        }
+    }
+
+    /// Returns the number of clauses.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &[Literal] {
+        let (start, len) = self.spans[index];
+        unsafe { std::slice::from_raw_parts(self.buffer.as_ptr().add(start), len) }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut [Literal] {
+        let (start, len) = self.spans[index];
+        unsafe { std::slice::from_raw_parts_mut(self.buffer.as_ptr().add(start), len) }
+    }
+
+    /// Keeps the clauses whose index `keep` accepts, moving them together in order, and returns
+    /// the new index of every clause, or `None` for the removed ones. The buffer keeps its size.
+    pub fn retain(&mut self, mut keep: impl FnMut(usize) -> bool) -> Vec<Option<usize>> {
+        let mut indices = Vec::with_capacity(self.spans.len());
+        let mut num_kept = 0;
+        let mut len = 0;
+        for index in 0..self.spans.len() {
+            if !keep(index) {
+                indices.push(None);
+                continue;
+            }
+            let (start, length) = self.spans[index];
+            // The kept literals only move towards the start, over the removed ones
+            unsafe {
+                std::ptr::copy(
+                    self.buffer.as_ptr().add(start),
+                    self.buffer.as_ptr().add(len),
+                    length,
+                );
+            }
+            self.spans[num_kept] = (len, length);
+            indices.push(Some(num_kept));
+            num_kept += 1;
+            len += length;
+        }
+        self.spans.truncate(num_kept);
+        self.len = len;
+        indices
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[Literal]> {
+        (0..self.len()).map(|index| self.get(index))
+    }
+
+    /// Returns the bytes allocated for the literals and the positions of the clauses.
+    pub fn get_allocated_bytes(&self) -> usize {
+        self.capacity * std::mem::size_of::<Literal>()
+            + self.spans.capacity() * std::mem::size_of::<(usize, usize)>()
+    }
+}
+
+impl<A: ClauseAlloc> Drop for ClauseArena<A> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl<A: ClauseAlloc + Clone> Clone for ClauseArena<A> {
+    fn clone(&self) -> Self {
+        let mut arena = ClauseArena::with_allocator(self.alloc.clone());
+        if self.len > 0 {
+            arena.grow(self.len);
+        }
+        for clause in self.iter() {
+            arena.push(clause);
+        }
+        arena
+    }
+}
+
+impl<A: ClauseAlloc + Default> Default for ClauseArena<A> {
+    fn default() -> Self {
+        ClauseArena::with_allocator(A::default())
+    }
+}
+
+impl<A: ClauseAlloc> fmt::Debug for ClauseArena<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::propagation::Propagator;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Counts the bytes allocated and not yet freed.
+    #[derive(Clone, Default)]
+    struct CountingAlloc {
+        allocated: Rc<Cell<usize>>,
+    }
+
+    unsafe impl ClauseAlloc for CountingAlloc {
+        fn allocate(&self, layout: Layout) -> *mut u8 {
+            self.allocated.set(self.allocated.get() + layout.size());
+            SystemAlloc.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, pointer: *mut u8, layout: Layout) {
+            self.allocated.set(self.allocated.get() - layout.size());
+            SystemAlloc.deallocate(pointer, layout)
+        }
+    }
+
+    #[test]
+    fn test_clause_arena() {
+        let alloc = CountingAlloc::default();
+        let mut arena = ClauseArena::with_allocator(alloc.clone());
+        for length in 0..100 {
+            let clause: Vec<Literal> = (1..=length).collect();
+            assert_eq!(arena.push(&clause), length as usize);
+        }
+        assert_eq!(arena.len(), 100);
+        assert_eq!(arena.get(3), &[1, 2, 3]);
+        arena.get_mut(3).swap(0, 2);
+        assert_eq!(arena.get(3), &[3, 2, 1]);
+        assert!(arena.get(0).is_empty());
+
+        let copy = arena.clone();
+        assert_eq!(copy.get(99), arena.get(99));
+        assert!(alloc.allocated.get() >= 2 * 4950 * std::mem::size_of::<Literal>());
+
+        let indices = arena.retain(|index| index % 3 == 0);
+        assert_eq!(arena.len(), 34);
+        assert_eq!(indices[6], Some(2));
+        assert_eq!(indices[7], None);
+        assert_eq!(arena.get(1), &[3, 2, 1]);
+        assert_eq!(arena.get(33), copy.get(99));
+        assert_eq!(arena.push(&[5]), 34);
+        drop(arena);
+        drop(copy);
+        assert_eq!(alloc.allocated.get(), 0);
+    }
+
+    #[test]
+    fn test_propagator_with_allocator() {
+        let alloc = CountingAlloc::default();
+        let mut propagator = Propagator::with_allocator(alloc.clone());
+        // Binary clauses stay out of the arena
+        propagator.add_clause(&[-1, 2]);
+        assert_eq!(alloc.allocated.get(), 0);
+        propagator.add_clause(&[1]);
+        assert!(alloc.allocated.get() > 0);
+        assert_eq!(propagator.propagate_units(), None);
+        assert_eq!(propagator.get_trail(), &[1, 2]);
+        assert_eq!(propagator.get_clause(0), &[2, -1]);
+    }
+}
@@ -0,0 +1,338 @@
+//! An experimental backend that evaluates whole batches of clauses at once, for counting and
+//! verification workloads over large clause sets rather than for the search itself.
+//!
+//! Enabled with the `batched` feature. The clauses are stored flat, and are evaluated in chunks
+//! spread over the available threads. Models are checked 64 at a time, one per bit of a word, so
+//! every literal is looked at once per 64 models. Propagation runs in rounds: each round evaluates
+//! every clause against the assignment of the previous round, which suits many clauses and few
+//! rounds, unlike the watched literals of [`crate::propagation`].
+//!
+//! `cargo bench --features bench,batched --bench batched` compares it with the scalar checks.
+
+use crate::cnf::{to_variable, Assignment, Literal, Variable};
+use crate::expression::Expression;
+
+/// The number of models checked together, one per bit of a word.
+const LANES: usize = 64;
+
+/// The most variables [`BatchedClauses::count_models`] enumerates.
+pub const MAX_COUNT_VARIABLES: usize = 32;
+
+/// The clauses of an expression laid out contiguously for batch evaluation.
+#[derive(Clone, Debug, Default)]
+pub struct BatchedClauses {
+    literals: Vec<Literal>,
+    /// The clause `i` has the literals `offsets[i]..offsets[i + 1]`.
+    offsets: Vec<usize>,
+    max_variable: Variable,
+    num_threads: usize,
+}
+
+impl BatchedClauses {
+    pub fn new(expression: &Expression) -> BatchedClauses {
+        let mut batched = BatchedClauses {
+            offsets: vec![0],
+            max_variable: expression.get_max_variable(),
+            num_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            ..BatchedClauses::default()
+        };
+        for clause in expression.get_clauses() {
+            batched.literals.extend(clause.literals());
+            batched.offsets.push(batched.literals.len());
+        }
+        batched
+    }
+
+    /// Sets the number of threads the clauses are spread over, 1 to evaluate them in the calling
+    /// thread. Defaults to the available parallelism.
+    pub fn set_num_threads(&mut self, num_threads: usize) {
+        self.num_threads = num_threads.max(1);
+    }
+
+    pub fn get_num_clauses(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn clause(&self, index: usize) -> &[Literal] {
+        &self.literals[self.offsets[index]..self.offsets[index + 1]]
+    }
+
+    /// Splits the clauses into one range per thread, and combines the results of `evaluate` on
+    /// each range with `combine`.
+    fn map_chunks<T: Send>(
+        &self,
+        evaluate: impl Fn(std::ops::Range<usize>) -> T + Sync,
+        combine: impl Fn(T, T) -> T,
+        empty: T,
+    ) -> T {
+        let num_clauses = self.get_num_clauses();
+        // Small batches are not worth the threads
+        let num_threads = self.num_threads.min(num_clauses.div_ceil(4096)).max(1);
+        if num_threads == 1 {
+            return combine(empty, evaluate(0..num_clauses));
+        }
+
+        let chunk = num_clauses.div_ceil(num_threads);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..num_threads)
+                .map(|thread| {
+                    let evaluate = &evaluate;
+                    let range =
+                        (thread * chunk).min(num_clauses)..((thread + 1) * chunk).min(num_clauses);
+                    scope.spawn(move || evaluate(range))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .fold(empty, combine)
+        })
+    }
+
+    /// Returns the lanes satisfying every clause, where `positive[v]` and `negative[v]` have the
+    /// bits of the lanes in which the variable `v` is true and false.
+    fn satisfied_lanes(&self, positive: &[u64], negative: &[u64]) -> u64 {
+        self.map_chunks(
+            |range| {
+                let mut satisfied = u64::MAX;
+                for index in range {
+                    let mut clause = 0;
+                    for literal in self.clause(index) {
+                        let variable = to_variable(*literal) as usize;
+                        clause |= if *literal > 0 {
+                            positive[variable]
+                        } else {
+                            negative[variable]
+                        };
+                    }
+                    satisfied &= clause;
+                    if satisfied == 0 {
+                        break;
+                    }
+                }
+                satisfied
+            },
+            |a, b| a & b,
+            u64::MAX,
+        )
+    }
+
+    /// Returns whether each model satisfies every clause. Variables a model does not assign
+    /// satisfy no literal.
+    pub fn check_models(&self, models: &[Assignment]) -> Vec<bool> {
+        let num_variables = self.max_variable as usize + 1;
+        let mut results = Vec::with_capacity(models.len());
+        for batch in models.chunks(LANES) {
+            let mut positive = vec![0u64; num_variables];
+            let mut negative = vec![0u64; num_variables];
+            for (lane, model) in batch.iter().enumerate() {
+                for (variable, value) in model {
+                    if (*variable as usize) < num_variables {
+                        let words = if *value { &mut positive } else { &mut negative };
+                        words[*variable as usize] |= 1 << lane;
+                    }
+                }
+            }
+
+            let satisfied = self.satisfied_lanes(&positive, &negative);
+            results.extend((0..batch.len()).map(|lane| satisfied >> lane & 1 == 1));
+        }
+        results
+    }
+
+    /// Counts the models over the variables occurring in the clauses by checking every
+    /// assignment, or returns `None` if there are more than [`MAX_COUNT_VARIABLES`] of them.
+    pub fn count_models(&self) -> Option<u64> {
+        let mut occurring = vec![false; self.max_variable as usize + 1];
+        for literal in &self.literals {
+            occurring[to_variable(*literal) as usize] = true;
+        }
+        let variables: Vec<usize> = (1..occurring.len()).filter(|v| occurring[*v]).collect();
+        if variables.len() > MAX_COUNT_VARIABLES {
+            return None;
+        }
+
+        // The first (up to) six variables take every combination of values across the lanes
+        const PATTERNS: [u64; 6] = [
+            0xAAAA_AAAA_AAAA_AAAA,
+            0xCCCC_CCCC_CCCC_CCCC,
+            0xF0F0_F0F0_F0F0_F0F0,
+            0xFF00_FF00_FF00_FF00,
+            0xFFFF_0000_FFFF_0000,
+            0xFFFF_FFFF_0000_0000,
+        ];
+        let num_inner = variables.len().min(PATTERNS.len());
+        let lanes: u64 = if num_inner == PATTERNS.len() {
+            u64::MAX
+        } else {
+            (1 << (1 << num_inner)) - 1
+        };
+
+        let mut positive = vec![0u64; occurring.len()];
+        let mut negative = vec![0u64; occurring.len()];
+        let mut count = 0;
+        for outer in 0u64..1 << (variables.len() - num_inner) {
+            for (index, variable) in variables.iter().enumerate() {
+                let word = if index < num_inner {
+                    PATTERNS[index]
+                } else if outer >> (index - num_inner) & 1 == 1 {
+                    u64::MAX
+                } else {
+                    0
+                };
+                positive[*variable] = word;
+                negative[*variable] = !word;
+            }
+            count += (self.satisfied_lanes(&positive, &negative) & lanes).count_ones() as u64;
+        }
+        Some(count)
+    }
+
+    /// Extends the assignment by unit propagation. Returns the number of variables assigned, or
+    /// the index of a clause all of whose literals are false.
+    pub fn propagate(&self, assignment: &mut Assignment) -> Result<usize, usize> {
+        let mut values: Vec<Option<bool>> = vec![None; self.max_variable as usize + 1];
+        for (variable, value) in assignment.iter() {
+            if (*variable as usize) < values.len() {
+                values[*variable as usize] = Some(*value);
+            }
+        }
+
+        let mut num_assigned = 0;
+        loop {
+            let round = self.map_chunks(
+                |range| {
+                    let mut units: Vec<Literal> = Vec::new();
+                    for index in range {
+                        let mut unassigned = None;
+                        let mut num_unassigned = 0;
+                        let mut satisfied = false;
+                        for literal in self.clause(index) {
+                            match values[to_variable(*literal) as usize] {
+                                Some(value) if value == (*literal > 0) => {
+                                    satisfied = true;
+                                    break;
+                                }
+                                Some(_) => {}
+                                None => {
+                                    num_unassigned += 1;
+                                    unassigned = Some(*literal);
+                                }
+                            }
+                        }
+                        match (satisfied, num_unassigned) {
+                            (false, 0) => return Err(index),
+                            (false, 1) => units.push(unassigned.unwrap()),
+                            _ => {}
+                        }
+                    }
+                    Ok(units)
+                },
+                |a, b| match (a, b) {
+                    (Err(index), _) | (_, Err(index)) => Err(index),
+                    (Ok(mut a), Ok(b)) => {
+                        a.extend(b);
+                        Ok(a)
+                    }
+                },
+                Ok(Vec::new()),
+            );
+
+            let units = round?;
+            if units.is_empty() {
+                return Ok(num_assigned);
+            }
+            for literal in units {
+                let variable = to_variable(literal);
+                if values[variable as usize].is_none() {
+                    // Opposite units of the same round show up as a conflict in the next one
+                    values[variable as usize] = Some(literal > 0);
+                    assignment.insert(variable, literal > 0);
+                    num_assigned += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::random_k_sat;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::enumerate::enumerate_models;
+
+    #[test]
+    fn test_check_and_count_models() {
+        for (seed, num_threads) in [(1, 1), (2, 4), (3, 1)] {
+            let expression = random_k_sat(8, 20, 3, seed);
+            let mut batched = BatchedClauses::new(&expression);
+            batched.set_num_threads(num_threads);
+
+            let models = enumerate_models(&expression, None);
+            assert_eq!(batched.count_models(), Some(models.len() as u64));
+            assert!(batched.check_models(&models).iter().all(|valid| *valid));
+
+            let mut flipped = models.clone();
+            for model in flipped.iter_mut() {
+                for value in model.values_mut() {
+                    *value = !*value;
+                }
+            }
+            let expected: Vec<bool> = flipped
+                .iter()
+                .map(|m| expression.is_satisfied_by(m))
+                .collect();
+            assert_eq!(batched.check_models(&flipped), expected);
+        }
+
+        let small = BatchedClauses::new(&parse_dimacs_str("p cnf 2 1\n1 2 0\n"));
+        assert_eq!(small.count_models(), Some(3));
+    }
+
+    #[test]
+    fn test_threads() {
+        // Enough clauses to be split over the threads
+        let expression = random_k_sat(20, 20000, 2, 0);
+        let mut batched = BatchedClauses::new(&expression);
+        batched.set_num_threads(4);
+        let models: Vec<Assignment> = (0..100)
+            .map(|seed: Variable| {
+                (1..=20)
+                    .map(|v| (v, (seed * 7 + v).is_multiple_of(3)))
+                    .collect()
+            })
+            .collect();
+        let expected: Vec<bool> = models
+            .iter()
+            .map(|m| expression.is_satisfied_by(m))
+            .collect();
+        assert_eq!(batched.check_models(&models), expected);
+
+        let mut assignment: Assignment = [(1, true)].into();
+        let mut single = batched.clone();
+        single.set_num_threads(1);
+        let mut expected = assignment.clone();
+        assert_eq!(
+            batched.propagate(&mut assignment),
+            single.propagate(&mut expected)
+        );
+    }
+
+    #[test]
+    fn test_propagate() {
+        let expression = parse_dimacs_str("p cnf 4 4\n-1 2 0\n-2 3 0\n-3 4 -1 0\n2 4 0\n");
+        let batched = BatchedClauses::new(&expression);
+
+        let mut assignment: Assignment = [(1, true)].into();
+        assert_eq!(batched.propagate(&mut assignment), Ok(3));
+        assert_eq!(
+            assignment,
+            [(1, true), (2, true), (3, true), (4, true)].into()
+        );
+
+        let mut conflicting: Assignment = [(1, true), (4, false)].into();
+        // 2 and -3 are propagated together, then falsify -2 | 3
+        assert_eq!(batched.propagate(&mut conflicting), Err(1));
+    }
+}
@@ -0,0 +1,137 @@
+//! A small harness for timing the solver on the instances of the [benchmark registry](crate::benchmarks).
+//!
+//! Enabled with the `bench` feature. `cargo bench --features bench` runs the standard suite
+//! under every heuristic and prints a table of the results.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::benchmarks::{generate, standard_suite_names};
+use crate::dpll::{solve_dpll_traced, SearchTrace};
+use crate::expression::SolverHeuristic;
+
+pub const HEURISTICS: [SolverHeuristic; 5] = [
+    SolverHeuristic::MostLiteralOccurances,
+    SolverHeuristic::MostVariableOccurances,
+    SolverHeuristic::MinimizeClauseLength,
+    SolverHeuristic::Random,
+    SolverHeuristic::Vsids,
+];
+
+/// The timings and search statistics of repeatedly solving one instance with one heuristic.
+#[derive(Clone, Debug)]
+pub struct BenchmarkResult {
+    pub name: String,
+    pub heuristic: SolverHeuristic,
+    pub satisfiable: bool,
+    /// The duration of every run, in the order they were run.
+    pub samples: Vec<Duration>,
+    pub num_decisions: usize,
+    pub num_propagations: usize,
+    pub num_conflicts: usize,
+}
+
+impl BenchmarkResult {
+    pub fn min(&self) -> Duration {
+        self.samples.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.iter().max().copied().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    pub fn median(&self) -> Duration {
+        let mut samples = self.samples.clone();
+        samples.sort_unstable();
+        samples.get(samples.len() / 2).copied().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<24} {:<24} {:<6} median {:>12?} min {:>12?} max {:>12?} decisions {:>8} conflicts {:>8}",
+            self.name,
+            format!("{:?}", self.heuristic),
+            if self.satisfiable { "SAT" } else { "UNSAT" },
+            self.median(),
+            self.min(),
+            self.max(),
+            self.num_decisions,
+            self.num_conflicts
+        )
+    }
+}
+
+/// Solves the named benchmark `samples` times with the heuristic, or returns `None` if
+/// the benchmark does not exist. Instance generation is not included in the timings.
+pub fn run_benchmark(
+    name: &str,
+    heuristic: SolverHeuristic,
+    samples: usize,
+) -> Option<BenchmarkResult> {
+    let benchmark = generate(name)?;
+    let mut result = BenchmarkResult {
+        name: benchmark.name,
+        heuristic,
+        satisfiable: false,
+        samples: Vec::with_capacity(samples),
+        num_decisions: 0,
+        num_propagations: 0,
+        num_conflicts: 0,
+    };
+
+    for _ in 0..samples {
+        let mut expression = benchmark.expression.clone();
+        expression.optimize();
+        expression.set_heuristic(heuristic);
+        let mut trace = SearchTrace::new();
+
+        let start = Instant::now();
+        let solution = solve_dpll_traced(&mut expression, &mut trace);
+        result.samples.push(start.elapsed());
+
+        // The search is deterministic, so every run has the same statistics
+        result.satisfiable = solution.is_some();
+        result.num_decisions = trace.num_decisions;
+        result.num_propagations = trace.num_propagations;
+        result.num_conflicts = trace.num_conflicts;
+    }
+
+    Some(result)
+}
+
+/// Runs every benchmark of the standard suite under every heuristic.
+pub fn run_standard_suite(samples: usize) -> Vec<BenchmarkResult> {
+    let mut results = Vec::new();
+    for name in standard_suite_names() {
+        for heuristic in HEURISTICS {
+            results.push(run_benchmark(name, heuristic, samples).unwrap());
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_benchmark() {
+        let result =
+            run_benchmark("pigeonhole-3", SolverHeuristic::MostLiteralOccurances, 3).unwrap();
+
+        assert!(!result.satisfiable);
+        assert_eq!(result.samples.len(), 3);
+        assert!(result.min() <= result.median() && result.median() <= result.max());
+        assert!(run_benchmark("missing", SolverHeuristic::MostLiteralOccurances, 1).is_none());
+    }
+}
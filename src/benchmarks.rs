@@ -0,0 +1,242 @@
+use crate::cnf::{Clause, Literal, Variable, CNF};
+use crate::dimacs_parser::parse_dimacs_str;
+use crate::expression::Expression;
+use crate::formula::Formula;
+use crate::rng::Rng;
+use crate::template::instantiate;
+
+/// Small instances bundled into the crate, as (name, expected satisfiability, contents).
+const BUNDLED: [(&str, bool, &str); 5] = [
+    (
+        "simple_v3_c2",
+        true,
+        include_str!("../examples/cnf/simple_v3_c2.cnf"),
+    ),
+    ("quinn", true, include_str!("../examples/cnf/quinn.cnf")),
+    (
+        "aim-50-1_6-yes1-4",
+        true,
+        include_str!("../examples/cnf/aim-50-1_6-yes1-4.cnf"),
+    ),
+    (
+        "dubois20",
+        false,
+        include_str!("../examples/cnf/dubois20.cnf"),
+    ),
+    ("hole6", false, include_str!("../examples/cnf/hole6.cnf")),
+];
+
+/// The names making up the standard suite, roughly ordered from easiest to hardest. It is kept small
+/// enough to run in tests, so harder bundled instances such as `dubois20` are only available by name.
+const STANDARD_SUITE: [&str; 18] = [
+    "simple_v3_c2",
+    "quinn",
+    "pigeonhole-3",
+    "parity-8",
+    "parity-unsat-6",
+    "random-3sat-20-3.0",
+    "random-3sat-20-4.26",
+    "random-3sat-20-6.0",
+    "pigeonhole-4",
+    "aim-50-1_6-yes1-4",
+    "parity-16",
+    "random-3sat-40-3.0",
+    "random-3sat-40-4.26",
+    "random-3sat-40-6.0",
+    "parity-unsat-10",
+    "hole6",
+    // Many variables and few conflicts, so the search is dominated by its decisions
+    "parity-200",
+    "random-3sat-1000-2.5",
+];
+
+/// A named instance from the benchmark registry.
+#[derive(Clone)]
+pub struct Benchmark {
+    pub name: String,
+    /// Whether the instance is satisfiable, if that is known without solving it.
+    pub expected: Option<bool>,
+    pub expression: Expression,
+}
+
+/// Returns the names of the instances in the standard suite.
+pub fn standard_suite_names() -> Vec<&'static str> {
+    STANDARD_SUITE.to_vec()
+}
+
+/// Generates every instance of the standard suite.
+pub fn standard_suite() -> Vec<Benchmark> {
+    STANDARD_SUITE
+        .iter()
+        .map(|name| generate(name).unwrap())
+        .collect()
+}
+
+/// Builds a benchmark by name, or returns `None` if the name is not recognized.
+///
+/// Besides the bundled instances, the following families are available:
+/// - `pigeonhole-<holes>`: `holes + 1` pigeons in `holes` holes (unsatisfiable).
+/// - `random-3sat-<variables>-<ratio>[-<seed>]`: uniform random 3-SAT with
+///   `round(variables * ratio)` clauses. The seed defaults to 1.
+/// - `parity-<n>`: the XOR of `n > 0` variables is true (satisfiable).
+/// - `parity-unsat-<n>`: the XOR of `n` variables is both true and false, chained in opposite
+///   orders (unsatisfiable).
+pub fn generate(name: &str) -> Option<Benchmark> {
+    let (expected, mut expression) = if let Some((_, expected, contents)) =
+        BUNDLED.iter().find(|(bundled, _, _)| *bundled == name)
+    {
+        (Some(*expected), parse_dimacs_str(contents))
+    } else if let Some(holes) = name.strip_prefix("pigeonhole-") {
+        (Some(false), pigeonhole(holes.parse().ok()?))
+    } else if let Some(parameters) = name.strip_prefix("random-3sat-") {
+        let parts: Vec<&str> = parameters.split('-').collect();
+        if parts.len() != 2 && parts.len() != 3 {
+            return None;
+        }
+        // Every clause needs three distinct variables, each a literal
+        let num_variables = parts[0]
+            .parse()
+            .ok()
+            .filter(|n| (3..=Literal::MAX as Variable).contains(n))?;
+        let ratio: f64 = parts[1].parse().ok()?;
+        let seed = match parts.get(2) {
+            Some(seed) => seed.parse().ok()?,
+            None => 1,
+        };
+        let num_clauses = (num_variables as f64 * ratio).round() as usize;
+        (None, random_k_sat(num_variables, num_clauses, 3, seed))
+    } else if let Some(n) = name.strip_prefix("parity-unsat-") {
+        (Some(false), parity(n.parse().ok()?, false))
+    } else if let Some(n) = name.strip_prefix("parity-") {
+        // The XOR of no variables is false
+        (Some(true), parity(n.parse().ok().filter(|n| *n > 0)?, true))
+    } else {
+        return None;
+    };
+
+    expression.set_metadata("benchmark", name);
+    Some(Benchmark {
+        name: name.to_string(),
+        expected,
+        expression,
+    })
+}
+
+/// The pigeonhole principle for `holes + 1` pigeons and `holes` holes.
+/// The element `sits[pigeon][hole]` of its template is true if the pigeon sits in the hole.
+pub fn pigeonhole(holes: usize) -> Expression {
+    let pigeons = holes + 1;
+    let (expression, _) = instantiate(|builder| {
+        let sits = builder.add_array("sits", &[pigeons, holes]);
+        for pigeon in 0..pigeons {
+            let placements: Vec<Literal> =
+                (0..holes).map(|hole| sits.at(&[pigeon, hole])).collect();
+            builder.add_clause(&placements);
+        }
+        for hole in 0..holes {
+            let occupants: Vec<Literal> = (0..pigeons)
+                .map(|pigeon| sits.at(&[pigeon, hole]))
+                .collect();
+            builder.add_at_most_one(&occupants);
+        }
+    });
+
+    expression
+}
+
+/// Uniform random k-SAT: each clause has `k` distinct variables with random polarities.
+pub fn random_k_sat(
+    num_variables: Variable,
+    num_clauses: usize,
+    k: usize,
+    seed: u64,
+) -> Expression {
+    assert!(
+        k <= num_variables as usize,
+        "Clauses cannot have more distinct variables than the formula"
+    );
+
+    let mut rng = Rng::new(seed);
+    let mut expression = Expression::new();
+    for _ in 0..num_clauses {
+        let mut clause = Clause::new();
+        while clause.len() < k {
+            let variable = rng.next_below(num_variables as u64) as Literal + 1;
+            if clause.contains(variable) || clause.contains(-variable) {
+                continue;
+            }
+            clause.insert(if rng.next_bool() { variable } else { -variable });
+        }
+        expression.add_clause(clause);
+    }
+
+    expression
+}
+
+/// The XOR of variables `1..=n` is `value`. For `value = false`, it also requires the XOR to be
+/// true with the chain built in the opposite order, so the instance is unsatisfiable.
+fn parity(n: Variable, value: bool) -> Expression {
+    let chain = |variables: Vec<Variable>| {
+        variables
+            .into_iter()
+            .map(Formula::Variable)
+            .reduce(|left, right| Formula::Xor(Box::new(left), Box::new(right)))
+            .unwrap_or(Formula::Constant(false))
+    };
+
+    let forward = chain((1..=n).collect());
+    let formula = if value {
+        forward
+    } else {
+        Formula::And(vec![
+            Formula::Not(Box::new(forward)),
+            chain((1..=n).rev().collect()),
+        ])
+    };
+
+    formula.to_expression(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::solve;
+
+    #[test]
+    fn test_standard_suite_matches_expected() {
+        for benchmark in standard_suite() {
+            let result = solve(benchmark.expression.clone(), false, true);
+            if let Some(expected) = benchmark.expected {
+                assert_eq!(result.is_some(), expected, "{}", benchmark.name);
+            }
+            assert_eq!(
+                benchmark.expression.get_metadata_value("benchmark"),
+                Some(benchmark.name.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_is_reproducible() {
+        let a = generate("random-3sat-30-4.26-7").unwrap();
+        let b = generate("random-3sat-30-4.26-7").unwrap();
+        let c = generate("random-3sat-30-4.26-8").unwrap();
+
+        assert_eq!(a.expression.get_clauses().len(), 128);
+        assert_eq!(a.expression.content_hash(), b.expression.content_hash());
+        assert_ne!(a.expression.content_hash(), c.expression.content_hash());
+    }
+
+    #[test]
+    fn test_generate_unknown_names() {
+        assert!(generate("pigeonhole-x").is_none());
+        assert!(generate("random-3sat-20").is_none());
+        assert!(generate("random-3sat-2-4.0").is_none());
+        assert!(generate("parity-0").is_none());
+        let empty = generate("parity-unsat-0").unwrap();
+        assert_eq!(empty.expected, Some(false));
+        assert!(solve(empty.expression, false, true).is_none());
+        assert!(generate("not-a-benchmark").is_none());
+        assert_eq!(pigeonhole(3).get_clauses().len(), 4 + 3 * 6);
+    }
+}
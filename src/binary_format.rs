@@ -0,0 +1,307 @@
+//! A compact, versioned binary format for expressions, which loads faster than DIMACS text.
+//!
+//! A file starts with the magic bytes `MSAT`, a version byte and a flags byte, followed by the
+//! payload and the FNV-1a hash of the payload (8 bytes, little endian). The payload holds the
+//! largest variable, the comments and metadata, and the clauses, all as LEB128 varints:
+//!
+//! ```text
+//! max_variable  num_comments (length bytes)*  num_metadata (length bytes length bytes)*
+//! num_clauses   (length literal*)*
+//! ```
+//!
+//! A literal `l` over the variable `v` is stored as `2v` if it is positive and `2v + 1`
+//! otherwise. Bit 0 of the flags marks a zstd-compressed payload, which this build cannot read and
+//! never writes; the other bits are reserved.
+//!
+//! Loading skips the tokenizing of DIMACS and builds the occurrence lists of the expression in one
+//! pass, sized from the literal counts, which is where most of the time of parsing goes.
+
+use std::fmt;
+use std::hash::Hasher;
+use std::io::Write;
+
+use crate::cnf::{to_variable, Clause, Literal, Variable};
+use crate::expression::Expression;
+use crate::hash::FnvHasher;
+
+const MAGIC: &[u8; 4] = b"MSAT";
+pub const VERSION: u8 = 1;
+const FLAG_ZSTD: u8 = 1;
+
+/// An error encountered while loading a binary instance.
+#[derive(Debug)]
+pub enum BinaryError {
+    Io(std::io::Error),
+    /// The data does not start with the magic bytes of the format.
+    NotBinary,
+    UnsupportedVersion(u8),
+    /// The payload is compressed, or uses flags this version does not know.
+    UnsupportedFlags(u8),
+    /// The data ends in the middle of the payload.
+    Truncated,
+    /// The payload does not match its hash, or holds values out of range.
+    Corrupt(String),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::Io(error) => write!(f, "{}", error),
+            BinaryError::NotBinary => write!(f, "not a binary microsat instance"),
+            BinaryError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            BinaryError::UnsupportedFlags(flags) if flags & FLAG_ZSTD != 0 => {
+                write!(f, "compressed instances are not supported by this build")
+            }
+            BinaryError::UnsupportedFlags(flags) => write!(f, "unsupported flags {:#04x}", flags),
+            BinaryError::Truncated => write!(f, "unexpected end of data"),
+            BinaryError::Corrupt(message) => write!(f, "corrupt instance: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+impl From<std::io::Error> for BinaryError {
+    fn from(error: std::io::Error) -> Self {
+        BinaryError::Io(error)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    write_varint(bytes, value.len() as u64);
+    bytes.extend_from_slice(value);
+}
+
+/// Returns the expression in the binary format.
+pub fn to_binary(expression: &Expression) -> Vec<u8> {
+    let clauses = expression.get_clauses();
+    let num_literals: usize = clauses.iter().map(|clause| clause.len()).sum();
+    let mut payload = Vec::with_capacity(2 * num_literals + clauses.len() + 64);
+
+    write_varint(&mut payload, expression.get_max_variable() as u64);
+    write_varint(&mut payload, expression.get_comments().len() as u64);
+    for comment in expression.get_comments() {
+        write_bytes(&mut payload, comment.as_bytes());
+    }
+    write_varint(&mut payload, expression.get_metadata().len() as u64);
+    for (key, value) in expression.get_metadata() {
+        write_bytes(&mut payload, key.as_bytes());
+        write_bytes(&mut payload, value.as_bytes());
+    }
+    write_varint(&mut payload, clauses.len() as u64);
+    for clause in &clauses {
+        write_varint(&mut payload, clause.len() as u64);
+        for literal in clause.literals() {
+            let code = 2 * to_variable(*literal) as u64 + (*literal < 0) as u64;
+            write_varint(&mut payload, code);
+        }
+    }
+
+    let mut hasher = FnvHasher::new();
+    hasher.write(&payload);
+    let mut bytes = Vec::with_capacity(payload.len() + 14);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.push(0);
+    bytes.extend_from_slice(&payload);
+    bytes.extend_from_slice(&hasher.finish().to_le_bytes());
+    bytes
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn varint(&mut self) -> Result<u64, BinaryError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.position)
+                .ok_or(BinaryError::Truncated)?;
+            self.position += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BinaryError::Corrupt("varint too long".to_string()))
+    }
+
+    /// Reads a count, which cannot be larger than the number of remaining bytes.
+    fn count(&mut self) -> Result<usize, BinaryError> {
+        let count = self.varint()?;
+        if count > (self.bytes.len() - self.position) as u64 {
+            return Err(BinaryError::Truncated);
+        }
+        Ok(count as usize)
+    }
+
+    fn text(&mut self) -> Result<String, BinaryError> {
+        let length = self.count()?;
+        let bytes = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        String::from_utf8(bytes.to_vec()).map_err(|error| BinaryError::Corrupt(error.to_string()))
+    }
+}
+
+/// Reads an expression in the binary format.
+pub fn from_binary(bytes: &[u8]) -> Result<Expression, BinaryError> {
+    if bytes.len() < 6 || &bytes[..4] != MAGIC {
+        return Err(BinaryError::NotBinary);
+    }
+    if bytes[4] != VERSION {
+        return Err(BinaryError::UnsupportedVersion(bytes[4]));
+    }
+    if bytes[5] != 0 {
+        return Err(BinaryError::UnsupportedFlags(bytes[5]));
+    }
+    if bytes.len() < 14 {
+        return Err(BinaryError::Truncated);
+    }
+
+    let (payload, hash) = bytes[6..].split_at(bytes.len() - 14);
+    let mut hasher = FnvHasher::new();
+    hasher.write(payload);
+    if hasher.finish().to_le_bytes() != hash {
+        return Err(BinaryError::Corrupt("hash mismatch".to_string()));
+    }
+
+    let mut reader = Reader {
+        bytes: payload,
+        position: 0,
+    };
+    let max_variable = reader.varint()?;
+    if max_variable > Literal::MAX as u64 {
+        return Err(BinaryError::Corrupt(format!(
+            "variable {} is out of range",
+            max_variable
+        )));
+    }
+
+    let mut comments = Vec::new();
+    for _ in 0..reader.count()? {
+        comments.push(reader.text()?);
+    }
+    let mut metadata = Vec::new();
+    for _ in 0..reader.count()? {
+        metadata.push((reader.text()?, reader.text()?));
+    }
+    let num_clauses = reader.count()?;
+    let mut clauses = Vec::with_capacity(num_clauses);
+    for _ in 0..num_clauses {
+        let mut clause = Clause::new();
+        for _ in 0..reader.count()? {
+            let code = reader.varint()?;
+            let variable = code / 2;
+            if variable == 0 || variable > max_variable {
+                return Err(BinaryError::Corrupt(format!(
+                    "variable {} is out of range",
+                    variable
+                )));
+            }
+            let literal = variable as Literal;
+            clause.insert(if code % 2 == 0 { literal } else { -literal });
+        }
+        clauses.push(clause);
+    }
+
+    if reader.position != payload.len() {
+        return Err(BinaryError::Corrupt(
+            "unexpected data after the clauses".to_string(),
+        ));
+    }
+
+    let mut expression = Expression::from_clauses_sized(clauses, max_variable as Variable);
+    for comment in comments {
+        expression.add_comment(comment);
+    }
+    for (key, value) in metadata {
+        expression.set_metadata(&key, &value);
+    }
+    Ok(expression)
+}
+
+/// Writes the expression to a binary file on disk.
+pub fn save_binary(expression: &Expression, file_name: &str) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(file_name)?;
+    file.write_all(&to_binary(expression))?;
+    file.flush()
+}
+
+/// Loads an expression from a binary file on disk.
+pub fn load_binary(file_name: &str) -> Result<Expression, BinaryError> {
+    from_binary(&std::fs::read(file_name)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mut expression = parse_dimacs_str("c an example\np cnf 300 3\n1 -2 0\n-300 5 2 0\n0\n");
+        expression.set_metadata("family", "test");
+        let bytes = to_binary(&expression);
+        let loaded = from_binary(&bytes).unwrap();
+        assert_eq!(loaded.get_clauses(), expression.get_clauses());
+        assert_eq!(loaded.get_comments(), expression.get_comments());
+        assert_eq!(loaded.get_metadata(), expression.get_metadata());
+
+        let hole6 = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        let file = std::env::temp_dir().join("microsat_test_binary_round_trip.bin");
+        let file = file.to_str().unwrap();
+        save_binary(&hole6, file).unwrap();
+        assert_eq!(
+            load_binary(file).unwrap().get_clauses(),
+            hole6.get_clauses()
+        );
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_binary() {
+        let bytes = to_binary(&parse_dimacs_str("p cnf 2 1\n1 -2 0\n"));
+        assert!(matches!(
+            from_binary(b"p cnf 1 1"),
+            Err(BinaryError::NotBinary)
+        ));
+
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert!(matches!(
+            from_binary(&newer),
+            Err(BinaryError::UnsupportedVersion(2))
+        ));
+        let mut compressed = bytes.clone();
+        compressed[5] = FLAG_ZSTD;
+        assert!(matches!(
+            from_binary(&compressed),
+            Err(BinaryError::UnsupportedFlags(1))
+        ));
+
+        let mut flipped = bytes.clone();
+        flipped[8] ^= 1;
+        assert!(matches!(
+            from_binary(&flipped),
+            Err(BinaryError::Corrupt(_))
+        ));
+        assert!(matches!(
+            from_binary(&bytes[..10]),
+            Err(BinaryError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+//! An interface for external models to choose the branching variables of the search, for
+//! experimenting with learned heuristics without changing the decision loop.
+//!
+//! A model installed with [`Expression::set_branching_model`] replaces the heuristic of the
+//! expression: at every decision, it scores a snapshot of each unassigned variable, and the search
+//! branches on the variable with the highest score.
+//!
+//! [`Expression::set_branching_model`]: crate::expression::Expression::set_branching_model
+
+use crate::cnf::Variable;
+
+/// What a branching model sees of an unassigned variable at a decision. The occurrences only count
+/// the clauses that are not satisfied yet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VariableSnapshot {
+    pub variable: Variable,
+    pub positive_occurrences: usize,
+    pub negative_occurrences: usize,
+    /// The occurrences in binary clauses, of either sign.
+    pub binary_occurrences: usize,
+    /// The number of variables assigned so far.
+    pub depth: usize,
+    /// The score of the built-in heuristic: the number of occurrences of the variable.
+    pub base_score: f64,
+}
+
+impl VariableSnapshot {
+    /// The number of entries of [`VariableSnapshot::to_vec`].
+    pub const NUM_FEATURES: usize = 5;
+
+    /// Returns the numeric entries of the snapshot, for scoring by a generic model.
+    pub fn to_vec(&self) -> [f64; Self::NUM_FEATURES] {
+        [
+            self.positive_occurrences as f64,
+            self.negative_occurrences as f64,
+            self.binary_occurrences as f64,
+            self.depth as f64,
+            self.base_score,
+        ]
+    }
+}
+
+/// Scores the variables to branch on. Models are shared between the clones of an expression, and
+/// the solver can run them on several threads.
+pub trait BranchingModel: Send + Sync {
+    /// Returns the score of the variable; the search branches on the highest one, taking the
+    /// first one in case of ties.
+    fn score(&self, snapshot: &VariableSnapshot) -> f64;
+
+    /// Returns the value to try first for the chosen variable. By default, the one satisfying
+    /// more clauses.
+    fn value(&self, snapshot: &VariableSnapshot) -> bool {
+        snapshot.positive_occurrences >= snapshot.negative_occurrences
+    }
+}
+
+/// A linear model over the entries of the snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearModel {
+    pub weights: [f64; VariableSnapshot::NUM_FEATURES],
+    pub bias: f64,
+}
+
+impl LinearModel {
+    pub fn new(weights: [f64; VariableSnapshot::NUM_FEATURES], bias: f64) -> LinearModel {
+        LinearModel { weights, bias }
+    }
+}
+
+impl BranchingModel for LinearModel {
+    fn score(&self, snapshot: &VariableSnapshot) -> f64 {
+        let features = snapshot.to_vec();
+        self.bias
+            + self
+                .weights
+                .iter()
+                .zip(features.iter())
+                .map(|(weight, feature)| weight * feature)
+                .sum::<f64>()
+    }
+}
+
+/// Blends the scores of a model with the score of the built-in heuristic, as
+/// `weight * model + (1 - weight) * base`, so a model can adjust the heuristic rather than
+/// replace it. The values come from the model.
+#[derive(Clone, Debug)]
+pub struct BlendedModel<M: BranchingModel> {
+    pub model: M,
+    pub weight: f64,
+}
+
+impl<M: BranchingModel> BlendedModel<M> {
+    pub fn new(model: M, weight: f64) -> BlendedModel<M> {
+        BlendedModel { model, weight }
+    }
+}
+
+impl<M: BranchingModel> BranchingModel for BlendedModel<M> {
+    fn score(&self, snapshot: &VariableSnapshot) -> f64 {
+        self.weight * self.model.score(snapshot) + (1.0 - self.weight) * snapshot.base_score
+    }
+
+    fn value(&self, snapshot: &VariableSnapshot) -> bool {
+        self.model.value(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::dpll::{solve_dpll, solve_dpll_traced, SearchEvent, SearchTrace};
+    use crate::expression::Expression;
+    use std::sync::Arc;
+
+    /// Branches on the largest variable, trying false first.
+    struct Largest;
+
+    impl BranchingModel for Largest {
+        fn score(&self, snapshot: &VariableSnapshot) -> f64 {
+            snapshot.variable as f64
+        }
+
+        fn value(&self, _: &VariableSnapshot) -> bool {
+            false
+        }
+    }
+
+    fn decisions(expression: &Expression) -> Vec<(Variable, bool)> {
+        let mut working = expression.clone();
+        working.optimize();
+        let mut trace = SearchTrace::with_events();
+        solve_dpll_traced(&mut working, &mut trace);
+        trace
+            .get_events()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event {
+                SearchEvent::Decision(variable, value) => Some((*variable, *value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_branching_model() {
+        let mut expression = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        expression.set_branching_model(Arc::new(Largest));
+        let decisions = decisions(&expression);
+        assert_eq!(decisions[0], (42, false));
+        assert_eq!(decisions[1], (41, false));
+    }
+
+    #[test]
+    fn test_blended_model() {
+        let snapshot = VariableSnapshot {
+            variable: 3,
+            positive_occurrences: 2,
+            negative_occurrences: 1,
+            binary_occurrences: 3,
+            depth: 0,
+            base_score: 3.0,
+        };
+        let model = LinearModel::new([0.0, 0.0, 1.0, 0.0, 0.0], 1.0);
+        assert_eq!(model.score(&snapshot), 4.0);
+        assert_eq!(BlendedModel::new(model.clone(), 0.5).score(&snapshot), 3.5);
+
+        // With a weight of zero, only the base score counts
+        let mut expression = parse_dimacs_str("p cnf 4 4\n1 2 0\n-1 3 0\n-3 -2 4 0\n-4 -1 0\n");
+        expression.set_branching_model(Arc::new(BlendedModel::new(model, 0.0)));
+        let mut working = expression.clone();
+        working.optimize();
+        let assignment = solve_dpll(&mut working).unwrap();
+        assert!(expression.is_satisfied_by(&assignment));
+    }
+}
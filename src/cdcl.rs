@@ -0,0 +1,1933 @@
+//! A conflict-driven clause learning solver, as an alternative to the DPLL search of
+//! [`crate::dpll`].
+//!
+//! Where the DPLL search backtracks chronologically and forgets why a branch failed, the CDCL
+//! solver analyzes every conflict into a learned clause (the first unique implication point),
+//! jumps back to the decision level where that clause becomes unit, and keeps it, so the same
+//! conflict is never explored twice. Learned clauses are minimized by dropping the literals the
+//! others imply through the reasons on the trail. The clauses and the trail are kept by a
+//! [`Propagator`], which watches two literals per clause, so the clauses are never rewritten
+//! during the search. Each binary clause is only kept in the implication lists of its two
+//! literals, so it takes no room in the clause database and propagating it never reads the
+//! database. The search restarts from
+//! the empty assignment after a number of conflicts set by a [`RestartPolicy`], keeping its
+//! learned clauses, so it does not get stuck in a subtree its first decisions led it into.
+//! Decisions go to the unassigned variable most active in the recent conflicts, and with
+//! [`PolarityMode::Saved`] to the value it had before the search last backtracked over it.
+//!
+//! Every learned clause remembers its literal block distance (LBD, or glue): the number of
+//! decision levels among its literals when it was learned. Clauses with few levels link the
+//! decisions tightly and tend to be used again, so the solver periodically deletes the learned
+//! clauses with the highest LBD, as set by a [`DeletionPolicy`], to bound its memory.
+//!
+//! The search can also be run one step at a time with [`CdclSolver::step`], which reports each
+//! propagation, conflict, decision, restart and deletion and leaves the solver open to inspection
+//! in between.
+//!
+//! Select it for [`solve_with_config`](crate::solver::solve_with_config) with
+//! [`Engine::Cdcl`](crate::solver::Engine::Cdcl).
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::archive::{ArchivePolicy, ArchiveStatistics, ClauseArchive};
+use crate::clause_database::{ClauseDatabase, ClauseRecord};
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable};
+use crate::control::{Progress, SolverControl};
+use crate::dpll::{SearchBudget, SearchResult};
+use crate::drat::ProofLogger;
+use crate::expression::{Expression, PolarityMode, ACTIVITY_DECAY};
+use crate::hash::FnvHashSet;
+use crate::memory::{vec_bytes, MemoryUsage};
+use crate::propagation::{watch_index, Propagator};
+use crate::trail::Trail;
+
+/// Counters describing the work done by a CDCL solve.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CdclStatistics {
+    pub num_decisions: usize,
+    pub num_propagations: usize,
+    pub num_conflicts: usize,
+    pub num_learned_clauses: usize,
+    /// The literals removed from the learned clauses by minimization.
+    pub num_minimized_literals: usize,
+    pub num_restarts: usize,
+    /// The learned clauses deleted for their high LBD.
+    pub num_deleted_clauses: usize,
+}
+
+/// When the CDCL search restarts, counted in conflicts since the previous restart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RestartPolicy {
+    /// Never restart.
+    None,
+    /// Restart after `unit` times the next term of the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, ...
+    /// conflicts.
+    Luby { unit: usize },
+    /// Restart after `first` conflicts, then after `factor` times as many as the time before.
+    Geometric { first: usize, factor: f64 },
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::Luby { unit: 100 }
+    }
+}
+
+impl RestartPolicy {
+    /// Returns the number of conflicts between restart `index` and the next one, or `None` if the
+    /// search never restarts.
+    pub fn get_interval(&self, index: usize) -> Option<usize> {
+        match *self {
+            RestartPolicy::None => None,
+            RestartPolicy::Luby { unit } => Some(unit.max(1) * luby(index)),
+            RestartPolicy::Geometric { first, factor } => Some(
+                (first.max(1) as f64 * factor.powi(index as i32)).min(usize::MAX as f64) as usize,
+            ),
+        }
+    }
+}
+
+/// How often the CDCL solver deletes learned clauses, and how many it keeps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeletionPolicy {
+    /// The number of conflicts between deletions, or `None` to keep every learned clause.
+    pub interval: Option<usize>,
+    /// The fraction of the learned clauses kept by a deletion, from 0 to 1. The clauses with the
+    /// lowest LBD are kept, and so are the clauses of LBD 2 or less and the clauses that are the
+    /// reason of an assignment, even past the fraction.
+    pub keep_ratio: f64,
+}
+
+impl Default for DeletionPolicy {
+    fn default() -> Self {
+        DeletionPolicy {
+            interval: Some(2000),
+            keep_ratio: 0.5,
+        }
+    }
+}
+
+/// The outcome of unit propagation under assumptions, without search.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PropagationResult {
+    /// The assumptions and the literals they imply, in the order they were assigned.
+    Implied(Vec<Literal>),
+    /// Propagation made every literal of the clause false. The clause is empty if the clauses are
+    /// unsatisfiable by themselves.
+    Conflict(Vec<Literal>),
+    /// The assumption was already false.
+    FailedAssumption(Literal),
+}
+
+/// A step of the CDCL search, see [`CdclSolver::step`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SolverEvent {
+    /// Unit propagation assigned this many literals, the last ones of the trail.
+    Propagated(usize),
+    /// Propagation made every literal of the clause with this id false. The next step analyzes
+    /// the conflict, or finishes the search if it needs no decision.
+    Conflict(usize),
+    /// The conflict was analyzed into a learned clause, the last of
+    /// [`CdclSolver::get_learned_clauses`], and the search jumped back to the decision level where
+    /// it is unit and assigned its first literal.
+    Learned { backjump_level: usize },
+    /// The search went back to decision level 0, as the restart policy asked.
+    Restarted,
+    /// This many learned clauses of high LBD were deleted, which renumbers the clauses.
+    DeletedClauses(usize),
+    /// The search decided the literal, an assumption or its own choice, at a new decision level.
+    Decision(Literal),
+    /// The search is over.
+    Finished(SearchResult),
+}
+
+/// The state of the search between steps.
+#[derive(Clone, Debug, Default)]
+struct Stepping {
+    /// The conflict found by the last step, to be analyzed by the next one.
+    conflict: Option<usize>,
+    restart: bool,
+    deletion: bool,
+    finished: Option<SearchResult>,
+}
+
+/// A learned clause, with its literal block distance.
+#[derive(Clone, Copy, Debug)]
+struct Learned {
+    id: usize,
+    lbd: usize,
+}
+
+/// The conflicts before a clause was added, and the conflict it last took part in.
+#[derive(Clone, Copy, Debug)]
+struct ClauseStamps {
+    added: usize,
+    last_used: Option<usize>,
+}
+
+/// The activities and phases the decisions follow.
+///
+/// As in [`SolverHeuristic::Vsids`](crate::expression::SolverHeuristic::Vsids), every conflict
+/// bumps the activity of the variables analyzing it went through, and the bump grows by
+/// `1 / ACTIVITY_DECAY` instead of every activity decaying. The heap is lazy: unassigned variables
+/// are pushed again, and entries of assigned variables or with an old activity are dropped as
+/// they reach the top.
+#[derive(Clone, Debug)]
+struct Branching {
+    /// Indexed by variable.
+    activities: Vec<f64>,
+    /// What a conflict adds to the activity of its variables.
+    bump: f64,
+    /// The activities as bits, which order like the non-negative activities themselves. Ties go
+    /// to the smaller variable.
+    heap: BinaryHeap<(u64, Reverse<Variable>)>,
+    /// For every variable, the value that made its more frequent literal true when the solver was
+    /// created, or true for the variables added later.
+    phases: Vec<bool>,
+    /// For every variable, the value it had when the search last backtracked over it, while
+    /// saving them with [`PolarityMode::Saved`].
+    saved_phases: Vec<Option<bool>>,
+}
+
+impl Branching {
+    fn new() -> Branching {
+        Branching {
+            activities: Vec::new(),
+            bump: 1.0,
+            heap: BinaryHeap::new(),
+            phases: Vec::new(),
+            saved_phases: Vec::new(),
+        }
+    }
+
+    fn reserve(&mut self, variable: Variable) {
+        let len = variable as usize + 1;
+        if len > self.activities.len() {
+            self.activities.resize(len, 0.0);
+            self.phases.resize(len, true);
+        }
+    }
+
+    fn push(&mut self, variable: Variable) {
+        self.heap.push((
+            self.activities[variable as usize].to_bits(),
+            Reverse(variable),
+        ));
+    }
+
+    fn bump(&mut self, variable: Variable) {
+        self.activities[variable as usize] += self.bump;
+        if self.activities[variable as usize] > 1e100 {
+            for activity in &mut self.activities {
+                *activity *= 1e-100;
+            }
+            self.bump *= 1e-100;
+            let variables: Vec<Variable> = self
+                .heap
+                .drain()
+                .map(|(_, Reverse(variable))| variable)
+                .collect();
+            for variable in variables {
+                self.push(variable);
+            }
+        }
+    }
+
+    /// Makes the next conflicts count for more than the earlier ones.
+    fn decay(&mut self) {
+        self.bump /= ACTIVITY_DECAY;
+    }
+
+    /// Returns the value to decide the variable on.
+    fn get_phase(&self, variable: Variable) -> bool {
+        let index = variable as usize;
+        self.saved_phases
+            .get(index)
+            .copied()
+            .flatten()
+            .unwrap_or(self.phases[index])
+    }
+
+    fn memory_bytes(&self) -> usize {
+        vec_bytes(&self.activities)
+            + self.heap.capacity() * std::mem::size_of::<(u64, Reverse<Variable>)>()
+            + vec_bytes(&self.phases)
+            + vec_bytes(&self.saved_phases)
+    }
+}
+
+/// Returns the term of the Luby sequence at the index, counting from 0.
+fn luby(mut index: usize) -> usize {
+    // Find the complete subsequence of length 2^k - 1 containing the index, then the terms it
+    // repeats, until the index is the last term of one
+    let mut length = 1;
+    let mut exponent = 0;
+    while length < index + 1 {
+        exponent += 1;
+        length = 2 * length + 1;
+    }
+    while length - 1 != index {
+        length = (length - 1) >> 1;
+        exponent -= 1;
+        index %= length;
+    }
+    1 << exponent
+}
+
+/// A CDCL solver over the clauses of an expression.
+#[derive(Clone, Debug)]
+pub struct CdclSolver {
+    /// The clauses, original and learned, with the assignment of the search. The reason of an
+    /// implied literal is a clause whose first literal is the implied one.
+    propagator: Propagator,
+    /// The learned clauses that were not deleted, in the order they were learned.
+    learned: Vec<Learned>,
+    /// The stamps of every clause, by id.
+    stamps: Vec<ClauseStamps>,
+    /// The variables occurring in the clauses, which are the ones a model assigns.
+    variables: Vec<Variable>,
+    /// For every variable, whether it is one of `variables`.
+    occurs: Vec<bool>,
+    /// How the variables to decide on are picked, starting from the most frequent ones.
+    branching: Branching,
+
+    /// An empty clause was given or derived.
+    unsatisfiable: bool,
+    /// The assumptions the last unsatisfiable solve was refuted under.
+    failed_assumptions: Vec<Literal>,
+    /// The assumptions of the current search.
+    assumptions: Vec<Literal>,
+    stepping: Stepping,
+    minimize: bool,
+    polarity_mode: PolarityMode,
+    restarts: RestartPolicy,
+    /// The conflicts since the last restart.
+    num_restart_conflicts: usize,
+    deletion: DeletionPolicy,
+    /// The conflicts since the last deletion of learned clauses.
+    num_deletion_conflicts: usize,
+
+    budget: SearchBudget,
+    control: Option<SolverControl>,
+    control_version: Option<u64>,
+    progress_interval: Option<usize>,
+    proof: Option<ProofLogger>,
+    /// The deleted learned clauses, if they are archived.
+    archive: Option<ClauseArchive>,
+    /// For every variable, the conflicts before the last one it took part in, while the archive
+    /// is on.
+    last_conflicts: Vec<usize>,
+    /// The conflicts before the last restart, when the archive was last looked through.
+    num_archive_conflicts: usize,
+    statistics: CdclStatistics,
+}
+
+impl CdclSolver {
+    pub fn new(expression: &Expression) -> CdclSolver {
+        // The engine only propagates clauses
+        if !expression.get_cardinality_constraints().is_empty() {
+            return CdclSolver::new(&expression.encode_cardinality_constraints());
+        }
+        #[cfg(feature = "xor")]
+        if !expression.get_xor_constraints().is_empty() {
+            return CdclSolver::new(&expression.encode_xor_constraints());
+        }
+        let num_variables = expression.get_max_variable() as usize + 1;
+        let mut propagator = Propagator::new();
+        propagator.reserve(expression.get_max_variable());
+        let mut branching = Branching::new();
+        branching.reserve(expression.get_max_variable());
+        let mut solver = CdclSolver {
+            propagator,
+            learned: Vec::new(),
+            stamps: Vec::new(),
+            variables: Vec::new(),
+            occurs: vec![false; num_variables],
+            branching,
+            unsatisfiable: false,
+            failed_assumptions: Vec::new(),
+            assumptions: Vec::new(),
+            stepping: Stepping::default(),
+            minimize: true,
+            polarity_mode: PolarityMode::default(),
+            restarts: RestartPolicy::default(),
+            num_restart_conflicts: 0,
+            deletion: DeletionPolicy::default(),
+            num_deletion_conflicts: 0,
+            budget: SearchBudget::default(),
+            control: None,
+            control_version: None,
+            progress_interval: None,
+            proof: None,
+            archive: None,
+            last_conflicts: Vec::new(),
+            num_archive_conflicts: 0,
+            statistics: CdclStatistics::default(),
+        };
+
+        let mut occurrences = vec![0usize; 2 * num_variables];
+        for clause in expression.get_clauses() {
+            let mut literals: Vec<Literal> = Vec::with_capacity(clause.len());
+            for literal in clause.literals() {
+                if !literals.contains(literal) {
+                    literals.push(*literal);
+                }
+            }
+            for literal in &literals {
+                occurrences[watch_index(*literal)] += 1;
+            }
+            if literals.iter().any(|literal| literals.contains(&-literal)) {
+                continue;
+            }
+            solver.insert_clause(&literals);
+        }
+
+        // Until the first conflict, the variables are decided by decreasing number of occurrences,
+        // towards their more frequent literal
+        let max_occurrences = occurrences.iter().max().copied().unwrap_or(0);
+        for variable in 1..num_variables as Variable {
+            let positive = occurrences[watch_index(variable as Literal)];
+            let negative = occurrences[watch_index(-(variable as Literal))];
+            if positive + negative > 0 {
+                solver.variables.push(variable);
+                solver.occurs[variable as usize] = true;
+                let branching = &mut solver.branching;
+                branching.activities[variable as usize] =
+                    (positive + negative) as f64 / (2 * max_occurrences + 1) as f64;
+                branching.phases[variable as usize] = positive >= negative;
+                branching.push(variable);
+            }
+        }
+        solver
+    }
+
+    /// Adds a clause between solves. The learned clauses are kept, since they follow from the
+    /// clauses before the addition. Variables the solver has not seen yet start with no activity.
+    pub fn add_clause(&mut self, literals: &[Literal]) {
+        self.backtrack(0);
+        self.stepping = Stepping::default();
+        // Even the variables of a satisfied clause get a value in the models
+        for literal in literals {
+            self.add_variable(to_variable(*literal));
+        }
+        // The assignments of level 0 are permanent, so they simplify the clause
+        let mut clause: Vec<Literal> = Vec::with_capacity(literals.len());
+        for literal in literals {
+            match self.value(*literal) {
+                Some(true) => return,
+                Some(false) => {}
+                None if clause.contains(&-literal) => return,
+                None if clause.contains(literal) => {}
+                None => clause.push(*literal),
+            }
+        }
+        self.insert_clause(&clause);
+    }
+
+    /// Makes room for the variable, if it is new.
+    fn add_variable(&mut self, variable: Variable) {
+        if variable as usize >= self.propagator.get_variable_capacity() {
+            self.propagator.reserve(variable);
+            self.branching.reserve(variable);
+            self.occurs.resize(variable as usize + 1, false);
+            if self.archive.is_some() {
+                self.last_conflicts.resize(variable as usize + 1, 0);
+            }
+        }
+        if !self.occurs[variable as usize] {
+            self.occurs[variable as usize] = true;
+            self.variables.push(variable);
+            self.branching.push(variable);
+        }
+    }
+
+    /// Adds a clause without duplicate literals at decision level 0.
+    fn insert_clause(&mut self, literals: &[Literal]) {
+        match literals.len() {
+            0 => self.refute(),
+            1 => match self.value(literals[0]) {
+                Some(true) => {}
+                Some(false) => self.refute(),
+                None => {
+                    let id = self.push_clause(literals);
+                    self.enqueue(literals[0], Some(id));
+                }
+            },
+            _ => {
+                self.push_clause(literals);
+            }
+        }
+    }
+
+    /// Stores and watches the clause, stamped with the conflicts so far, and returns its id.
+    fn push_clause(&mut self, literals: &[Literal]) -> usize {
+        self.stamps.push(ClauseStamps {
+            added: self.statistics.num_conflicts,
+            last_used: None,
+        });
+        self.propagator.add_clause(literals)
+    }
+
+    /// Sets whether to minimize the learned clauses, which is on by default: a literal is left
+    /// out if the other literals of the clause imply it through the reasons of the trail.
+    pub fn set_minimize(&mut self, minimize: bool) {
+        self.minimize = minimize;
+    }
+
+    /// Sets which value the search decides a variable on, see [`PolarityMode`]. Leaving
+    /// [`PolarityMode::Saved`] forgets the saved values.
+    pub fn set_polarity_mode(&mut self, mode: PolarityMode) {
+        self.polarity_mode = mode;
+        if mode != PolarityMode::Saved {
+            self.branching.saved_phases = Vec::new();
+        }
+    }
+
+    /// Sets when the search restarts, which is [`RestartPolicy::default`] unless set.
+    pub fn set_restarts(&mut self, restarts: RestartPolicy) {
+        self.restarts = restarts;
+    }
+
+    /// Sets when the learned clauses are deleted, which is [`DeletionPolicy::default`] unless set.
+    pub fn set_deletion(&mut self, deletion: DeletionPolicy) {
+        self.deletion = deletion;
+    }
+
+    /// Archives the learned clauses deleted from now on, within the limits of the policy, or
+    /// stops archiving them with `None`, which drops the archive. Archived clauses come back at a
+    /// restart once all their variables took part in a conflict since the previous one.
+    pub fn set_archive(&mut self, archive: Option<ArchivePolicy>) {
+        self.archive = archive.map(ClauseArchive::new);
+        self.last_conflicts = match self.archive {
+            Some(_) => vec![0; self.propagator.get_variable_capacity()],
+            None => Vec::new(),
+        };
+    }
+
+    /// Returns the use of the archive, which is all zero if it is off.
+    pub fn get_archive_statistics(&self) -> ArchiveStatistics {
+        self.archive
+            .as_ref()
+            .map(ClauseArchive::get_statistics)
+            .unwrap_or_default()
+    }
+
+    /// Limits the search, which then ends with `BudgetExhausted`.
+    pub fn set_budget(&mut self, budget: SearchBudget) {
+        self.budget = budget;
+    }
+
+    /// Steers the search with the control, whose budget replaces the one set with
+    /// [`CdclSolver::set_budget`].
+    pub fn set_control(&mut self, control: &SolverControl) {
+        self.control = Some(control.clone());
+        self.control_version = None;
+    }
+
+    /// Writes a DRAT proof of the search to the logger: every learned clause is added, and
+    /// deleted again with the learned clauses of high LBD, and the empty clause is added once the
+    /// clauses are refuted. The proof refers to the clauses of the expression together with the
+    /// ones added by [`CdclSolver::add_clause`], and is only complete for a solve without
+    /// assumptions, since a refutation under assumptions does not derive the empty clause.
+    pub fn set_proof(&mut self, proof: ProofLogger) {
+        if self.unsatisfiable {
+            proof.add(&[]);
+        }
+        self.proof = Some(proof);
+    }
+
+    /// Marks the clauses as unsatisfiable, which the proof shows by the empty clause.
+    fn refute(&mut self) {
+        self.unsatisfiable = true;
+        if let Some(proof) = &self.proof {
+            proof.add(&[]);
+        }
+    }
+
+    pub fn get_statistics(&self) -> CdclStatistics {
+        self.statistics
+    }
+
+    /// Returns the assumptions that the last solve, if it was unsatisfiable, refuted together: the
+    /// clauses imply that they cannot all hold, so the solve stays unsatisfiable under them alone.
+    /// They are in the order they were assumed, and empty if the clauses are unsatisfiable by
+    /// themselves. The subset is not necessarily minimal.
+    pub fn get_failed_assumptions(&self) -> &[Literal] {
+        &self.failed_assumptions
+    }
+
+    /// Returns the learned clauses that were not deleted, in the order they were learned.
+    pub fn get_learned_clauses(&self) -> impl Iterator<Item = &[Literal]> {
+        self.learned
+            .iter()
+            .map(|learned| self.propagator.get_clause(learned.id))
+    }
+
+    /// Returns the literals fixed at decision level 0, which hold in every model of the clauses.
+    pub fn get_fixed_literals(&self) -> &[Literal] {
+        let literals = self.get_trail().get_literals();
+        let len = literals
+            .iter()
+            .take_while(|literal| self.decision_level(to_variable(**literal)) == Some(0))
+            .count();
+        &literals[..len]
+    }
+
+    /// Returns the clauses simplified by the fixed literals, for persisting the progress of a
+    /// solve that ran out of budget or handing it to another solver: the satisfied clauses are
+    /// left out and the false literals removed, and the learned clauses are included with
+    /// `with_learned`. The residual is satisfiable exactly when the clauses are, and a model of
+    /// it together with [`CdclSolver::get_fixed_literals`] (and any value for the variables
+    /// neither assigns) is a model of the clauses. It is a single empty clause once the clauses
+    /// are refuted.
+    pub fn residual(&self, with_learned: bool) -> Expression {
+        if self.unsatisfiable {
+            return Expression::from_clauses(vec![Clause::new()]);
+        }
+        let learned: FnvHashSet<usize> = self.learned.iter().map(|learned| learned.id).collect();
+        let fixed = |literal: Literal| {
+            self.decision_level(to_variable(literal)) == Some(0)
+                && self.value(literal) == Some(true)
+        };
+        let mut clauses = Vec::new();
+        for id in 0..self.propagator.get_num_clauses() {
+            let literals = self.propagator.get_clause(id);
+            if (!with_learned && learned.contains(&id)) || literals.iter().any(|l| fixed(*l)) {
+                continue;
+            }
+            let literals: Vec<Literal> = literals
+                .iter()
+                .copied()
+                .filter(|literal| !fixed(-*literal))
+                .collect();
+            clauses.push(Clause::from_literals(&literals));
+        }
+        Expression::from_clauses(clauses)
+    }
+
+    /// Returns a copy of the clauses with when they were added and last used, in conflicts. See
+    /// [`crate::clause_database`].
+    pub fn get_clause_database(&self) -> ClauseDatabase {
+        let mut lbds = vec![None; self.propagator.get_num_clauses()];
+        for learned in &self.learned {
+            lbds[learned.id] = Some(learned.lbd);
+        }
+        let clauses = (0..self.propagator.get_num_clauses())
+            .map(|id| ClauseRecord {
+                id,
+                literals: self.propagator.get_clause(id).to_vec(),
+                lbd: lbds[id],
+                added: self.stamps[id].added,
+                last_used: self.stamps[id].last_used,
+            })
+            .collect();
+        ClauseDatabase {
+            num_conflicts: self.statistics.num_conflicts,
+            clauses,
+        }
+    }
+
+    /// Returns the value of the literal under the current assignment.
+    pub fn value(&self, literal: Literal) -> Option<bool> {
+        self.get_trail().literal_value(literal)
+    }
+
+    /// Returns the current decision level: the number of decisions on the trail.
+    pub fn get_decision_level(&self) -> usize {
+        self.get_trail().get_decision_level()
+    }
+
+    /// Returns the clause that implied the value of the variable, or `None` if the variable is a
+    /// decision or unassigned. The implied literal is the first one of the clause.
+    pub fn reason(&self, variable: Variable) -> Option<usize> {
+        self.get_trail().reason(variable)
+    }
+
+    /// Returns the decision level at which the variable was assigned, or `None` if it is
+    /// unassigned.
+    pub fn decision_level(&self, variable: Variable) -> Option<usize> {
+        self.get_trail().decision_level(variable)
+    }
+
+    /// Returns the literals of the clause, original or learned. Their order changes as the watched
+    /// literals move, and the ids change when learned clauses are deleted.
+    pub fn get_clause(&self, id: usize) -> &[Literal] {
+        self.propagator.get_clause(id)
+    }
+
+    /// Returns the current assignment. After a satisfiable solve, the trail holds the model, with
+    /// its reasons and decision levels.
+    pub fn get_trail(&self) -> &Trail {
+        self.propagator.get_assignment()
+    }
+
+    fn enqueue(&mut self, literal: Literal, reason: Option<usize>) {
+        self.propagator.enqueue(literal, reason);
+    }
+
+    /// Propagates the assignments that have not been propagated yet, returning the clause all of
+    /// whose literals are false on a conflict.
+    fn propagate(&mut self) -> Option<usize> {
+        let len = self.get_trail().len();
+        let conflict = self.propagator.propagate();
+        self.statistics.num_propagations += self.get_trail().len() - len;
+        conflict
+    }
+
+    /// Derives the first-UIP clause of the conflict: the clause implied by the conflict that has
+    /// a single literal of the current decision level. Returns the clause, with that literal
+    /// first and a literal of the highest remaining level second, and the level to jump back to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Literal>, usize) {
+        let level = self.get_decision_level();
+        let mut seen = vec![false; self.propagator.get_variable_capacity()];
+        let mut learned: Vec<Literal> = vec![0];
+        let mut num_pending = 0;
+        let assignment = self.propagator.get_assignment();
+        let trail = assignment.get_literals();
+        let mut index = trail.len();
+        let mut clause = self.propagator.get_clause(conflict);
+        let mut skip = 0;
+        self.stamps[conflict].last_used = Some(self.statistics.num_conflicts);
+
+        loop {
+            // The first literal of a reason is the literal it implied, which is already resolved
+            for literal in &clause[skip..] {
+                let variable = to_variable(*literal);
+                let literal_level = assignment.decision_level(variable).unwrap();
+                if seen[variable as usize] || literal_level == 0 {
+                    continue;
+                }
+                seen[variable as usize] = true;
+                self.branching.bump(variable);
+                if let Some(last_conflict) = self.last_conflicts.get_mut(variable as usize) {
+                    *last_conflict = self.statistics.num_conflicts;
+                }
+                if literal_level == level {
+                    num_pending += 1;
+                } else {
+                    learned.push(*literal);
+                }
+            }
+
+            // Resolve on the most recent literal of the current level in the clause
+            loop {
+                index -= 1;
+                if seen[to_variable(trail[index]) as usize] {
+                    break;
+                }
+            }
+            let literal = trail[index];
+            num_pending -= 1;
+            if num_pending == 0 {
+                learned[0] = -literal;
+                break;
+            }
+            let reason = assignment.reason(to_variable(literal)).unwrap();
+            self.stamps[reason].last_used = Some(self.statistics.num_conflicts);
+            clause = self.propagator.get_clause(reason);
+            skip = 1;
+        }
+        self.branching.decay();
+
+        if self.minimize {
+            let num_literals = learned.len();
+            self.minimize_learned(&mut learned, &mut seen);
+            self.statistics.num_minimized_literals += num_literals - learned.len();
+        }
+
+        let mut backjump_level = 0;
+        for position in 1..learned.len() {
+            let position_level = self.decision_level(to_variable(learned[position])).unwrap();
+            if position_level > backjump_level {
+                backjump_level = position_level;
+                learned.swap(1, position);
+            }
+        }
+        (learned, backjump_level)
+    }
+
+    /// Removes the literals of the learned clause implied by its other literals, following the
+    /// reasons back until they reach literals of the clause. `seen` marks the variables of the
+    /// clause, and every variable shown to be implied on the way.
+    fn minimize_learned(&self, learned: &mut Vec<Literal>, seen: &mut [bool]) {
+        // A literal can only be implied by the clause if its reasons stay within the levels of
+        // the clause, which a bit per level (modulo 64) rules out quickly
+        let abstract_level =
+            |literal: Literal| 1u64 << (self.decision_level(to_variable(literal)).unwrap() % 64);
+        let levels = learned[1..]
+            .iter()
+            .fold(0, |levels, literal| levels | abstract_level(*literal));
+
+        let mut stack = Vec::new();
+        let mut implied = Vec::new();
+        let mut position = 1;
+        while position < learned.len() {
+            let literal = learned[position];
+            let redundant = self.reason(to_variable(literal)).is_some() && {
+                stack.clear();
+                stack.push(literal);
+                let num_implied = implied.len();
+                let mut redundant = true;
+                while let Some(next) = stack.pop() {
+                    let reason = self.reason(to_variable(next)).unwrap();
+                    for antecedent in &self.propagator.get_clause(reason)[1..] {
+                        let variable = to_variable(*antecedent);
+                        if seen[variable as usize] || self.decision_level(variable) == Some(0) {
+                            continue;
+                        }
+                        if self.reason(variable).is_none()
+                            || abstract_level(*antecedent) & levels == 0
+                        {
+                            redundant = false;
+                            break;
+                        }
+                        seen[variable as usize] = true;
+                        stack.push(*antecedent);
+                        implied.push(variable);
+                    }
+                    if !redundant {
+                        break;
+                    }
+                }
+                if !redundant {
+                    for variable in implied.drain(num_implied..) {
+                        seen[variable as usize] = false;
+                    }
+                }
+                redundant
+            };
+
+            if redundant {
+                learned.swap_remove(position);
+            } else {
+                position += 1;
+            }
+        }
+    }
+
+    /// Undoes the assignments above the decision level, saving the values they undo with
+    /// [`PolarityMode::Saved`].
+    fn backtrack(&mut self, level: usize) {
+        if self.get_decision_level() <= level {
+            return;
+        }
+        // The levels only grow along the trail, since every backjump comes before the assignments
+        let assignment = self.propagator.get_assignment();
+        for literal in assignment.get_literals().iter().rev() {
+            let variable = to_variable(*literal);
+            if assignment.decision_level(variable).unwrap() <= level {
+                break;
+            }
+            if self.polarity_mode == PolarityMode::Saved {
+                let saved_phases = &mut self.branching.saved_phases;
+                if variable as usize >= saved_phases.len() {
+                    saved_phases.resize(variable as usize + 1, None);
+                }
+                saved_phases[variable as usize] = Some(*literal > 0);
+            }
+            self.branching.push(variable);
+        }
+        self.propagator.backtrack(level);
+    }
+
+    /// Adds the learned clause, whose first literal is unit after backjumping, and assigns it.
+    fn learn(&mut self, learned: Vec<Literal>) {
+        if let Some(proof) = &self.proof {
+            proof.add(&learned);
+        }
+        // The first literal was alone at the level of the conflict
+        let mut levels: Vec<usize> = learned[1..]
+            .iter()
+            .map(|literal| self.decision_level(to_variable(*literal)).unwrap())
+            .collect();
+        levels.sort_unstable();
+        levels.dedup();
+
+        let id = self.push_clause(&learned);
+        self.learned.push(Learned {
+            id,
+            lbd: levels.len() + 1,
+        });
+        self.statistics.num_learned_clauses += 1;
+        self.enqueue(learned[0], Some(id));
+    }
+
+    /// Deletes the learned clauses with the highest LBD, down to the fraction of the policy, and
+    /// moves the other clauses together, renumbering them.
+    fn delete_learned_clauses(&mut self) {
+        let locked = |id: usize| {
+            let first = self.propagator.get_clause(id)[0];
+            self.reason(to_variable(first)) == Some(id)
+        };
+        let mut candidates: Vec<usize> = (0..self.learned.len())
+            .filter(|index| self.learned[*index].lbd > 2 && !locked(self.learned[*index].id))
+            .collect();
+        // The highest LBD goes first, and the oldest among equal ones
+        candidates.sort_by_key(|index| (std::cmp::Reverse(self.learned[*index].lbd), *index));
+        let num_kept =
+            (self.learned.len() as f64 * self.deletion.keep_ratio.clamp(0.0, 1.0)).ceil();
+        let num_deleted = (self.learned.len() - num_kept as usize).min(candidates.len());
+        if num_deleted == 0 {
+            return;
+        }
+
+        let mut deleted = vec![false; self.propagator.get_num_clauses()];
+        for index in &candidates[..num_deleted] {
+            let id = self.learned[*index].id;
+            deleted[id] = true;
+            // An archived clause stays in the proof until the archive drops it
+            let dropped = match &mut self.archive {
+                Some(archive) => {
+                    archive.push(self.propagator.get_clause(id), self.learned[*index].lbd)
+                }
+                None => vec![self.propagator.get_clause(id).to_vec()],
+            };
+            if let Some(proof) = &self.proof {
+                for clause in &dropped {
+                    proof.delete(clause);
+                }
+            }
+        }
+        let ids = self.propagator.remove_clauses(&deleted);
+        let mut id = 0;
+        self.stamps.retain(|_| {
+            id += 1;
+            !deleted[id - 1]
+        });
+        self.learned.retain_mut(|learned| match ids[learned.id] {
+            Some(new_id) => {
+                learned.id = new_id;
+                true
+            }
+            None => false,
+        });
+        self.statistics.num_deleted_clauses += num_deleted;
+    }
+
+    /// Brings back the archived clauses whose variables all took part in a conflict since the last
+    /// restart, or are fixed, simplified by the fixed literals. Runs at decision level 0.
+    fn reimport_archived(&mut self) {
+        let Some(archive) = &mut self.archive else {
+            return;
+        };
+        let since = self.num_archive_conflicts;
+        self.num_archive_conflicts = self.statistics.num_conflicts;
+        let (trail, last_conflicts) = (self.propagator.get_assignment(), &self.last_conflicts);
+        let taken = archive.take(|literals| {
+            literals.iter().all(|literal| {
+                let variable = to_variable(*literal);
+                trail.value(variable).is_some() || last_conflicts[variable as usize] > since
+            })
+        });
+
+        let mut num_reimported = 0;
+        for (literals, lbd) in &taken {
+            let mut clause: Vec<Literal> = Vec::with_capacity(literals.len());
+            let mut satisfied = false;
+            for literal in literals {
+                match self.value(*literal) {
+                    Some(true) => satisfied = true,
+                    Some(false) => {}
+                    None => clause.push(*literal),
+                }
+            }
+            if satisfied {
+                if let Some(proof) = &self.proof {
+                    proof.delete(literals);
+                }
+                continue;
+            }
+            if !clause.is_empty() && clause.len() < literals.len() {
+                if let Some(proof) = &self.proof {
+                    proof.add(&clause);
+                    proof.delete(literals);
+                }
+            }
+            match clause.len() {
+                0 => {
+                    self.refute();
+                    break;
+                }
+                1 => {
+                    let id = self.push_clause(&clause);
+                    self.enqueue(clause[0], Some(id));
+                }
+                _ => {
+                    self.push_clause(&clause);
+                }
+            }
+            let id = self.propagator.get_num_clauses() - 1;
+            self.learned.push(Learned {
+                id,
+                lbd: (*lbd).min(clause.len()),
+            });
+            num_reimported += 1;
+        }
+        if let Some(archive) = &mut self.archive {
+            archive.count_reimported(num_reimported, taken.len() - num_reimported);
+        }
+    }
+
+    /// Returns the assumptions that imply the negation of the failed one, which is among them, by
+    /// following the reasons on the trail back to the decisions. The decisions of the current
+    /// levels are all assumptions.
+    fn analyze_final(&self, failed: Literal, assumptions: &[Literal]) -> Vec<Literal> {
+        let mut seen = vec![false; self.propagator.get_variable_capacity()];
+        seen[to_variable(failed) as usize] = true;
+        let mut decisions: FnvHashSet<Literal> = FnvHashSet::default();
+        for literal in self.get_trail().get_literals().iter().rev() {
+            let variable = to_variable(*literal);
+            if !seen[variable as usize] || self.decision_level(variable) == Some(0) {
+                continue;
+            }
+            match self.reason(variable) {
+                Some(id) => {
+                    for other in &self.get_clause(id)[1..] {
+                        seen[to_variable(*other) as usize] = true;
+                    }
+                }
+                None => {
+                    decisions.insert(*literal);
+                }
+            }
+        }
+
+        let mut core: Vec<Literal> = Vec::new();
+        for literal in assumptions {
+            if (*literal == failed || decisions.contains(literal)) && !core.contains(literal) {
+                core.push(*literal);
+            }
+        }
+        core
+    }
+
+    /// Returns the next decision, the most active unassigned variable in its phase, or `None` if
+    /// every variable is assigned.
+    fn pick_branch_literal(&mut self) -> Option<Literal> {
+        let assignment = self.propagator.get_assignment();
+        let branching = &mut self.branching;
+        if branching.heap.len() > 4 * self.variables.len() + 64 {
+            branching.heap.clear();
+            for variable in &self.variables {
+                if !assignment.is_assigned(*variable) {
+                    branching.push(*variable);
+                }
+            }
+        }
+
+        while let Some((bits, Reverse(variable))) = branching.heap.pop() {
+            if assignment.is_assigned(variable)
+                || bits != branching.activities[variable as usize].to_bits()
+            {
+                continue;
+            }
+            let literal = variable as Literal;
+            return Some(if branching.get_phase(variable) {
+                literal
+            } else {
+                -literal
+            });
+        }
+        None
+    }
+
+    /// Returns true if the search has used up its budget or was cancelled.
+    fn is_exhausted(&mut self) -> bool {
+        if let Some(control) = &self.control {
+            let version = control.get_version();
+            if self.control_version != Some(version) {
+                let settings = control.get_settings();
+                self.budget = settings.budget;
+                self.progress_interval = settings.progress_interval;
+                self.control_version = Some(version);
+            }
+            if control.is_cancelled() {
+                return true;
+            }
+        }
+
+        let over = |limit: Option<usize>, count: usize| limit.is_some_and(|limit| count >= limit);
+        over(self.budget.max_decisions, self.statistics.num_decisions)
+            || over(self.budget.max_conflicts, self.statistics.num_conflicts)
+    }
+
+    fn report_progress(&self) {
+        if let (Some(control), Some(interval)) = (&self.control, self.progress_interval) {
+            if self
+                .statistics
+                .num_decisions
+                .is_multiple_of(interval.max(1))
+            {
+                control.set_progress(Progress {
+                    num_decisions: self.statistics.num_decisions,
+                    num_conflicts: self.statistics.num_conflicts,
+                    coverage: self.estimate_coverage(),
+                });
+            }
+        }
+    }
+
+    /// Estimates the fraction of the search space covered, as MiniSat does: the assignments at
+    /// level `i` count for `n^-i` of the space each, out of the `n` variables. The variables fixed
+    /// at level 0 dominate, so the estimate grows as the learned clauses fix more of them.
+    fn estimate_coverage(&self) -> f64 {
+        let num_variables = self.variables.len().max(1) as f64;
+        let mut levels = vec![0usize; self.get_decision_level() + 1];
+        for literal in self.get_trail().get_literals() {
+            levels[self.decision_level(to_variable(*literal)).unwrap()] += 1;
+        }
+        let mut coverage = 0.0;
+        let mut weight = 1.0;
+        for num_assigned in levels {
+            coverage += weight * num_assigned as f64;
+            weight /= num_variables;
+        }
+        coverage / num_variables
+    }
+
+    /// Searches for a model. The learned clauses are kept, so solving again after running out of
+    /// budget continues from them.
+    pub fn solve(&mut self) -> SearchResult {
+        self.solve_with_assumptions(&[])
+    }
+
+    /// Searches for a model in which the assumed literals are true, deciding them first. Returns
+    /// `Unsatisfiable` if there is none, which leaves the solver usable with other assumptions.
+    /// The clauses learned under assumptions follow from the clauses alone, so they are kept for
+    /// the next solves.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Literal]) -> SearchResult {
+        self.start(assumptions);
+        loop {
+            if let SolverEvent::Finished(result) = self.step() {
+                return result;
+            }
+        }
+    }
+
+    /// Starts a search under the assumptions, to be run with [`CdclSolver::step`]. The search
+    /// starts from decision level 0, and adding a clause or propagating assumptions starts it
+    /// over.
+    pub fn start(&mut self, assumptions: &[Literal]) {
+        self.backtrack(0);
+        self.failed_assumptions.clear();
+        self.assumptions = assumptions.to_vec();
+        self.stepping = Stepping::default();
+        for literal in assumptions {
+            self.add_variable(to_variable(*literal));
+        }
+    }
+
+    /// Advances the search started by [`CdclSolver::start`], or a search without assumptions, by
+    /// one step, and returns what it did. The assignment, the clauses and the statistics can be
+    /// inspected between steps, for visualizers, teaching tools or outer loops of their own:
+    /// after a `Conflict` the conflicting assignment is still on the trail, and it is analyzed by
+    /// the next step. Once the search is finished, every step returns the same result.
+    pub fn step(&mut self) -> SolverEvent {
+        if let Some(result) = &self.stepping.finished {
+            return SolverEvent::Finished(result.clone());
+        }
+        if self.unsatisfiable {
+            return self.finish(SearchResult::Unsatisfiable);
+        }
+
+        if let Some(conflict) = self.stepping.conflict.take() {
+            let (learned, backjump_level) = self.analyze(conflict);
+            self.backtrack(backjump_level);
+            self.learn(learned);
+            if self.is_exhausted() {
+                self.stepping.finished = Some(SearchResult::BudgetExhausted);
+                return SolverEvent::Learned { backjump_level };
+            }
+            self.num_restart_conflicts += 1;
+            let interval = self.restarts.get_interval(self.statistics.num_restarts);
+            self.stepping.restart =
+                interval.is_some_and(|interval| self.num_restart_conflicts >= interval);
+            self.num_deletion_conflicts += 1;
+            let interval = self.deletion.interval;
+            self.stepping.deletion =
+                interval.is_some_and(|interval| self.num_deletion_conflicts >= interval);
+            return SolverEvent::Learned { backjump_level };
+        }
+        if std::mem::take(&mut self.stepping.restart) {
+            // The assumptions, if any, are decided again from the first level
+            self.backtrack(0);
+            self.reimport_archived();
+            self.num_restart_conflicts = 0;
+            self.statistics.num_restarts += 1;
+            return SolverEvent::Restarted;
+        }
+        if std::mem::take(&mut self.stepping.deletion) {
+            let num_deleted = self.statistics.num_deleted_clauses;
+            self.delete_learned_clauses();
+            self.num_deletion_conflicts = 0;
+            return SolverEvent::DeletedClauses(self.statistics.num_deleted_clauses - num_deleted);
+        }
+
+        if !self.propagator.is_propagated() {
+            let len = self.get_trail().len();
+            let Some(conflict) = self.propagate() else {
+                return SolverEvent::Propagated(self.get_trail().len() - len);
+            };
+            self.statistics.num_conflicts += 1;
+            if self.get_decision_level() == 0 {
+                self.refute();
+                self.stepping.finished = Some(SearchResult::Unsatisfiable);
+            } else {
+                self.stepping.conflict = Some(conflict);
+            }
+            return SolverEvent::Conflict(conflict);
+        }
+
+        let literal = loop {
+            let level = self.get_decision_level();
+            match self
+                .assumptions
+                .get(level)
+                .map(|literal| self.value(*literal))
+            {
+                // An assumption that already holds gets an empty decision level, so the level
+                // keeps indexing the assumptions
+                Some(Some(true)) => self.propagator.new_decision_level(),
+                Some(Some(false)) => {
+                    let failed = self.assumptions[level];
+                    self.failed_assumptions = self.analyze_final(failed, &self.assumptions);
+                    return self.finish(SearchResult::Unsatisfiable);
+                }
+                Some(None) => break self.assumptions[level],
+                None => match self.pick_branch_literal() {
+                    Some(literal) => break literal,
+                    None => {
+                        let model = self.construct_assignment();
+                        return self.finish(SearchResult::Satisfiable(model));
+                    }
+                },
+            }
+        };
+        if self.is_exhausted() {
+            return self.finish(SearchResult::BudgetExhausted);
+        }
+        self.statistics.num_decisions += 1;
+        self.report_progress();
+        self.propagator.new_decision_level();
+        self.enqueue(literal, None);
+        SolverEvent::Decision(literal)
+    }
+
+    fn finish(&mut self, result: SearchResult) -> SolverEvent {
+        self.stepping.finished = Some(result.clone());
+        SolverEvent::Finished(result)
+    }
+
+    /// Runs unit propagation with the assumed literals true, over the clauses and the learned
+    /// clauses, without deciding anything else. The literals fixed at decision level 0 are not
+    /// among the implied ones, since no assumption is needed for them (see
+    /// [`CdclSolver::get_fixed_literals`]). The assignment is undone afterwards.
+    pub fn propagate_assumptions(&mut self, assumptions: &[Literal]) -> PropagationResult {
+        self.backtrack(0);
+        self.stepping = Stepping::default();
+        if self.unsatisfiable {
+            return PropagationResult::Conflict(Vec::new());
+        }
+        if let Some(conflict) = self.propagate() {
+            self.refute();
+            return PropagationResult::Conflict(self.propagator.get_clause(conflict).to_vec());
+        }
+        let start = self.get_trail().len();
+        let mut result = None;
+        for literal in assumptions {
+            self.add_variable(to_variable(*literal));
+            match self.value(*literal) {
+                Some(true) => {}
+                Some(false) => {
+                    result = Some(PropagationResult::FailedAssumption(*literal));
+                    break;
+                }
+                None => {
+                    self.propagator.new_decision_level();
+                    self.enqueue(*literal, None);
+                    if let Some(conflict) = self.propagate() {
+                        let clause = self.propagator.get_clause(conflict).to_vec();
+                        result = Some(PropagationResult::Conflict(clause));
+                        break;
+                    }
+                }
+            }
+        }
+        let result = result.unwrap_or_else(|| {
+            PropagationResult::Implied(self.get_trail().get_literals()[start..].to_vec())
+        });
+        self.backtrack(0);
+        result
+    }
+
+    fn construct_assignment(&self) -> Assignment {
+        self.variables
+            .iter()
+            .map(|variable| (*variable, self.get_trail().value(*variable).unwrap()))
+            .collect()
+    }
+
+    /// Returns the approximate memory held by the solver.
+    pub fn memory_report(&self) -> MemoryUsage {
+        // The learned binary clauses are in the implication lists
+        let learned_literals: usize = self
+            .get_learned_clauses()
+            .filter(|clause| clause.len() != 2)
+            .map(|clause| clause.len())
+            .sum();
+        let learned = learned_literals * std::mem::size_of::<Literal>();
+        let propagation = self.propagator.memory_report();
+        MemoryUsage {
+            clause_database: propagation.clause_database - learned,
+            occurrence_lists: propagation.occurrence_lists,
+            learned_clauses: learned,
+            trail: propagation.trail,
+            other: vec_bytes(&self.variables)
+                + vec_bytes(&self.occurs)
+                + self.branching.memory_bytes()
+                + vec_bytes(&self.learned)
+                + vec_bytes(&self.stamps)
+                + vec_bytes(&self.last_conflicts)
+                + self.archive.as_ref().map_or(0, ClauseArchive::memory_bytes),
+        }
+    }
+}
+
+/// Solves the expression with the CDCL solver, like [`solve_dpll`](crate::dpll::solve_dpll).
+pub fn solve_cdcl(expression: &Expression) -> Option<Assignment> {
+    match CdclSolver::new(expression).solve() {
+        SearchResult::Satisfiable(assignment) => Some(assignment),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::{generate, random_k_sat};
+    use crate::cnf::{Clause, CNF};
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::dpll::solve_dpll;
+    use crate::drat::{check_drat, trim, Proof};
+    use crate::rng::Rng;
+    use crate::solver::solve_with_assumptions;
+
+    #[test]
+    fn test_cdcl_agrees_with_dpll() {
+        for seed in 0..40 {
+            let expression = random_k_sat(20, 90, 3, seed);
+            let mut working = expression.clone();
+            working.optimize();
+            let expected = solve_dpll(&mut working).is_some();
+
+            match solve_cdcl(&expression) {
+                Some(assignment) => {
+                    assert!(expected);
+                    assert!(expression.is_satisfied_by(&assignment));
+                    assert_eq!(assignment.len(), 20);
+                }
+                None => assert!(!expected),
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimize_learned_clauses() {
+        let mut num_minimized_literals = 0;
+        for seed in 0..10 {
+            let expression = random_k_sat(30, 135, 3, seed);
+            let mut plain = CdclSolver::new(&expression);
+            plain.set_minimize(false);
+            let mut minimized = CdclSolver::new(&expression);
+            let expected = matches!(plain.solve(), SearchResult::Satisfiable(_));
+            assert_eq!(
+                matches!(minimized.solve(), SearchResult::Satisfiable(_)),
+                expected
+            );
+            assert_eq!(plain.get_statistics().num_minimized_literals, 0);
+            num_minimized_literals += minimized.get_statistics().num_minimized_literals;
+
+            // Every learned clause follows from the expression
+            for clause in minimized.get_learned_clauses() {
+                let negation: Vec<Literal> = clause.iter().map(|literal| -literal).collect();
+                assert!(solve_with_assumptions(&expression, &negation).is_none());
+            }
+        }
+        assert!(num_minimized_literals > 0);
+    }
+
+    #[test]
+    fn test_cdcl_instances() {
+        let hole6 = generate("hole6").unwrap().expression;
+        let mut solver = CdclSolver::new(&hole6);
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+        let statistics = solver.get_statistics();
+        assert!(statistics.num_learned_clauses > 0);
+        assert_eq!(
+            statistics.num_learned_clauses,
+            solver.get_learned_clauses().count()
+        );
+        assert!(solver.memory_report().learned_clauses > 0);
+
+        let aim = Expression::from_cnf_file("examples/cnf/aim-50-1_6-yes1-4.cnf").unwrap();
+        assert!(aim.is_satisfied_by(&solve_cdcl(&aim).unwrap()));
+
+        // Empty clauses, tautologies, duplicate literals and conflicting units
+        assert!(solve_cdcl(&parse_dimacs_str("p cnf 2 2\n1 -1 0\n2 2 0\n")).is_some());
+        assert!(solve_cdcl(&parse_dimacs_str("p cnf 1 2\n1 0\n-1 0\n")).is_none());
+        let mut empty = parse_dimacs_str("p cnf 1 1\n1 0\n");
+        empty.add_clause(Clause::new());
+        assert!(solve_cdcl(&empty).is_none());
+    }
+
+    #[test]
+    fn test_reasons_and_levels() {
+        let expression = parse_dimacs_str("p cnf 5 4\n1 0\n-1 2 0\n-3 -2 4 0\n5 -4 0\n");
+        let mut solver = CdclSolver::new(&expression);
+        let model = match solver.solve() {
+            SearchResult::Satisfiable(model) => model,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert!(expression.is_satisfied_by(&model));
+
+        // 1 and 2 follow at level 0, then deciding 4 implies 5, and 3 is decided last
+        assert_eq!(solver.decision_level(1), Some(0));
+        assert_eq!(solver.get_clause(solver.reason(2).unwrap()), &[2, -1]);
+        assert_eq!(solver.reason(3), None);
+        assert_eq!(solver.decision_level(3), Some(2));
+        assert_eq!(solver.decision_level(5), Some(1));
+        let reason = solver.get_clause(solver.reason(5).unwrap());
+        assert_eq!(reason[0], 5);
+        for literal in solver.get_trail().get_literals() {
+            let variable = to_variable(*literal);
+            if let Some(id) = solver.reason(variable) {
+                assert_eq!(solver.get_clause(id)[0], *literal);
+                let level = solver.decision_level(variable).unwrap();
+                assert!(solver.get_clause(id)[1..]
+                    .iter()
+                    .all(|l| solver.value(*l) == Some(false)
+                        && solver.decision_level(to_variable(*l)).unwrap() <= level));
+            }
+        }
+        assert_eq!(solver.decision_level(6), None);
+        assert_eq!(solver.reason(6), None);
+    }
+
+    #[test]
+    fn test_binary_implications() {
+        let expression = parse_dimacs_str("p cnf 6 4\n-1 2 0\n3 -2 0\n-3 4 0\n5 -4 -6 0\n");
+        let mut solver = CdclSolver::new(&expression);
+        let model = match solver.solve_with_assumptions(&[1, 6]) {
+            SearchResult::Satisfiable(model) => model,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert!(expression.is_satisfied_by(&model));
+
+        // The binary clauses imply 2, 3 and 4 in turn, with the implied literal first
+        assert_eq!(solver.get_clause(solver.reason(2).unwrap()), &[2, -1]);
+        assert_eq!(solver.get_clause(solver.reason(3).unwrap()), &[3, -2]);
+        assert_eq!(solver.get_clause(solver.reason(4).unwrap()), &[4, -3]);
+        assert_eq!(solver.get_clause(solver.reason(5).unwrap())[0], 5);
+        assert_eq!(solver.get_statistics().num_propagations, 4);
+
+        // A binary clause can also be the conflict
+        solver.add_clause(&[-4, -1]);
+        assert_eq!(
+            solver.solve_with_assumptions(&[1]),
+            SearchResult::Unsatisfiable
+        );
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(model) if !model[&1]));
+    }
+
+    #[test]
+    fn test_assumptions_and_added_clauses() {
+        let expression = parse_dimacs_str("p cnf 3 2\n1 2 0\n-1 3 0\n");
+        let mut solver = CdclSolver::new(&expression);
+        assert_eq!(
+            solver.solve_with_assumptions(&[-2, -3]),
+            SearchResult::Unsatisfiable
+        );
+        let SearchResult::Satisfiable(model) = solver.solve_with_assumptions(&[-3]) else {
+            panic!("expected a model");
+        };
+        assert!(!model[&1] && model[&2] && !model[&3]);
+
+        // A new variable, and a clause that makes the assumptions contradict the clauses
+        solver.add_clause(&[-2, 4]);
+        solver.add_clause(&[-4, 3]);
+        assert_eq!(
+            solver.solve_with_assumptions(&[-3]),
+            SearchResult::Unsatisfiable
+        );
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(model) if model[&3]));
+        solver.add_clause(&[-3]);
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+
+        // The variables of a clause already satisfied at level 0 are still assigned
+        let mut solver = CdclSolver::new(&parse_dimacs_str("p cnf 1 1\n1 0\n"));
+        solver.add_clause(&[1, 2]);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(model) if model.len() == 2));
+    }
+
+    #[test]
+    fn test_failed_assumptions() {
+        // 1 and 2 imply 3 and then 4, against -4, while 5 plays no part
+        let expression = parse_dimacs_str("p cnf 6 3\n-1 -2 3 0\n-3 4 0\n5 6 0\n");
+        let mut solver = CdclSolver::new(&expression);
+        let assumptions = [5, 1, 2, -4];
+        assert_eq!(
+            solver.solve_with_assumptions(&assumptions),
+            SearchResult::Unsatisfiable
+        );
+        assert_eq!(solver.get_failed_assumptions(), &[1, 2, -4]);
+        assert_eq!(
+            solver.solve_with_assumptions(&[1, 2, -4]),
+            SearchResult::Unsatisfiable
+        );
+        assert!(matches!(
+            solver.solve_with_assumptions(&[5, 1, 2]),
+            SearchResult::Satisfiable(_)
+        ));
+        assert!(solver.get_failed_assumptions().is_empty());
+
+        // Contradicting assumptions, and an assumption refuted at level 0
+        solver.solve_with_assumptions(&[6, 1, -1]);
+        assert_eq!(solver.get_failed_assumptions(), &[1, -1]);
+        solver.add_clause(&[-6]);
+        solver.solve_with_assumptions(&[1, 6]);
+        assert_eq!(solver.get_failed_assumptions(), &[6]);
+
+        // Every failed subset is refuted by itself
+        for seed in 0..10 {
+            let expression = random_k_sat(30, 120, 3, seed);
+            let mut solver = CdclSolver::new(&expression);
+            let mut rng = Rng::new(seed);
+            let assumptions: Vec<Literal> = (0..12)
+                .map(|_| {
+                    let variable = rng.next_below(30) as Literal + 1;
+                    if rng.next_bool() {
+                        variable
+                    } else {
+                        -variable
+                    }
+                })
+                .collect();
+            if solver.solve_with_assumptions(&assumptions) == SearchResult::Unsatisfiable {
+                let failed = solver.get_failed_assumptions().to_vec();
+                assert!(failed.iter().all(|literal| assumptions.contains(literal)));
+                assert!(solve_with_assumptions(&expression, &failed).is_none());
+            }
+        }
+    }
+
+    /// A sink whose contents stay readable after it is handed to a logger.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_proof_logging() {
+        let mut instances = vec![generate("hole6").unwrap().expression];
+        instances.extend((0..3).map(|seed| random_k_sat(60, 300, 3, seed)));
+        for expression in instances {
+            let buffer = SharedBuffer::default();
+            let mut solver = CdclSolver::new(&expression);
+            // Deleting often puts deletions in the proof
+            solver.set_deletion(DeletionPolicy {
+                interval: Some(20),
+                keep_ratio: 0.5,
+            });
+            solver.set_proof(ProofLogger::new(buffer.clone()));
+            assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+            solver.proof.as_ref().unwrap().finish().unwrap();
+
+            let contents = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+            assert!(contents.ends_with("\n0\n"));
+            assert!(contents.contains("\nd "));
+            let proof = Proof::parse(&contents).unwrap();
+            assert!(trim(&expression, &proof).is_ok());
+            assert_eq!(check_drat(&expression, &proof), Ok(()));
+        }
+
+        // An expression refuted before the search gets its empty clause too
+        let buffer = SharedBuffer::default();
+        let mut solver = CdclSolver::new(&parse_dimacs_str("p cnf 1 2\n1 0\n-1 0\n"));
+        solver.set_proof(ProofLogger::new(buffer.clone()));
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+        assert_eq!(buffer.0.lock().unwrap().as_slice(), b"0\n");
+    }
+
+    #[test]
+    fn test_restarts() {
+        let luby: Vec<usize> = (0..15).map(luby).collect();
+        assert_eq!(luby, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+        let geometric = RestartPolicy::Geometric {
+            first: 10,
+            factor: 1.5,
+        };
+        assert_eq!(geometric.get_interval(2), Some(22));
+        assert_eq!(RestartPolicy::None.get_interval(0), None);
+
+        // Restarting at every conflict still finishes, and keeps deciding the assumptions first
+        let hole6 = generate("hole6").unwrap().expression;
+        let mut solver = CdclSolver::new(&hole6);
+        solver.set_restarts(RestartPolicy::Luby { unit: 1 });
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+        assert!(solver.get_statistics().num_restarts > 10);
+
+        let expression = random_k_sat(30, 120, 3, 4);
+        for seed in 0..10 {
+            let assumptions = [seed as Literal + 1, -(seed as Literal) - 11];
+            let mut solver = CdclSolver::new(&expression);
+            solver.set_restarts(RestartPolicy::Luby { unit: 1 });
+            let expected = solve_with_assumptions(&expression, &assumptions);
+            match solver.solve_with_assumptions(&assumptions) {
+                SearchResult::Satisfiable(model) => {
+                    assert!(expected.is_some() && expression.is_satisfied_by(&model));
+                    assert!(model[&(seed + 1)] && !model[&(seed + 11)]);
+                }
+                result => {
+                    assert_eq!(result, SearchResult::Unsatisfiable);
+                    assert!(expected.is_none());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_delete_learned_clauses() {
+        for seed in 0..3 {
+            let expression = random_k_sat(80, 344, 3, seed);
+            let mut solver = CdclSolver::new(&expression);
+            solver.set_deletion(DeletionPolicy {
+                interval: None,
+                keep_ratio: 1.0,
+            });
+            let expected = solver.solve();
+
+            let mut solver = CdclSolver::new(&expression);
+            solver.set_deletion(DeletionPolicy {
+                interval: Some(20),
+                keep_ratio: 0.5,
+            });
+            let result = solver.solve();
+            assert_eq!(
+                std::mem::discriminant(&result),
+                std::mem::discriminant(&expected)
+            );
+            if let SearchResult::Satisfiable(model) = result {
+                assert!(expression.is_satisfied_by(&model));
+            }
+
+            let statistics = solver.get_statistics();
+            assert!(statistics.num_deleted_clauses > 0);
+            assert_eq!(
+                solver.learned.len() + statistics.num_deleted_clauses,
+                statistics.num_learned_clauses
+            );
+            for learned in &solver.learned {
+                assert!(learned.lbd >= 1 && learned.lbd <= solver.get_clause(learned.id).len());
+            }
+            for learned in solver.get_learned_clauses() {
+                let negation: Vec<Literal> = learned.iter().map(|literal| -literal).collect();
+                assert_eq!(
+                    CdclSolver::new(&expression).solve_with_assumptions(&negation),
+                    SearchResult::Unsatisfiable
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_archive() {
+        let mut instances = vec![generate("hole6").unwrap().expression];
+        instances.extend((0..3).map(|seed| random_k_sat(60, 300, 3, seed)));
+        let mut num_reimported = 0;
+        for (expression, max_bytes) in instances.iter().zip([1 << 20, 64, 1 << 20, 64]) {
+            let expected = CdclSolver::new(expression).solve();
+            let buffer = SharedBuffer::default();
+            let mut solver = CdclSolver::new(expression);
+            solver.set_deletion(DeletionPolicy {
+                interval: Some(20),
+                keep_ratio: 0.5,
+            });
+            solver.set_restarts(RestartPolicy::Luby { unit: 4 });
+            solver.set_archive(Some(ArchivePolicy { max_bytes }));
+            solver.set_proof(ProofLogger::new(buffer.clone()));
+            let result = solver.solve();
+            assert_eq!(
+                std::mem::discriminant(&result),
+                std::mem::discriminant(&expected)
+            );
+
+            let statistics = solver.get_archive_statistics();
+            assert_eq!(
+                statistics.num_archived,
+                solver.get_statistics().num_deleted_clauses
+            );
+            assert_eq!(
+                statistics.num_archived,
+                statistics.num_reimported + statistics.num_dropped + statistics.num_clauses
+            );
+            assert!(statistics.num_bytes <= max_bytes);
+            num_reimported += statistics.num_reimported;
+            for learned in solver.get_learned_clauses() {
+                let negation: Vec<Literal> = learned.iter().map(|literal| -literal).collect();
+                assert_eq!(
+                    CdclSolver::new(expression).solve_with_assumptions(&negation),
+                    SearchResult::Unsatisfiable
+                );
+            }
+
+            if result == SearchResult::Unsatisfiable {
+                solver.proof.as_ref().unwrap().finish().unwrap();
+                let contents = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+                let proof = Proof::parse(&contents).unwrap();
+                assert_eq!(check_drat(expression, &proof), Ok(()));
+            }
+        }
+        assert!(num_reimported > 0);
+    }
+
+    #[test]
+    fn test_cdcl_budget() {
+        let hole6 = generate("hole6").unwrap().expression;
+        let mut solver = CdclSolver::new(&hole6);
+        solver.set_budget(SearchBudget {
+            max_decisions: None,
+            max_conflicts: Some(10),
+        });
+        assert_eq!(solver.solve(), SearchResult::BudgetExhausted);
+        assert_eq!(solver.get_statistics().num_conflicts, 10);
+
+        // Solving again continues with the clauses learned so far
+        solver.set_budget(SearchBudget::default());
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut num_deletions = 0;
+        for seed in 0..4 {
+            let expression = random_k_sat(30, 135, 3, seed);
+            let mut solver = CdclSolver::new(&expression);
+            solver.set_restarts(RestartPolicy::Luby { unit: 2 });
+            solver.set_deletion(DeletionPolicy {
+                interval: Some(5),
+                keep_ratio: 0.5,
+            });
+            let mut counts = [0usize; 3];
+            let result = loop {
+                let level = solver.get_decision_level();
+                match solver.step() {
+                    SolverEvent::Decision(literal) => {
+                        assert_eq!(solver.get_decision_level(), level + 1);
+                        assert_eq!(solver.value(literal), Some(true));
+                        counts[0] += 1;
+                    }
+                    SolverEvent::Conflict(id) => {
+                        assert!(solver
+                            .get_clause(id)
+                            .iter()
+                            .all(|literal| solver.value(*literal) == Some(false)));
+                        counts[1] += 1;
+                        match solver.step() {
+                            SolverEvent::Learned { .. } => {}
+                            SolverEvent::Finished(result) => {
+                                assert_eq!(level, 0);
+                                break result;
+                            }
+                            event => panic!("{:?} follows a conflict", event),
+                        }
+                    }
+                    SolverEvent::Restarted => {
+                        assert_eq!(solver.get_decision_level(), 0);
+                        counts[2] += 1;
+                    }
+                    SolverEvent::DeletedClauses(_) => num_deletions += 1,
+                    SolverEvent::Propagated(_) => {}
+                    SolverEvent::Learned { .. } => {
+                        panic!("A clause was learned without a conflict")
+                    }
+                    SolverEvent::Finished(result) => break result,
+                }
+            };
+            let statistics = solver.get_statistics();
+            assert_eq!(
+                counts,
+                [
+                    statistics.num_decisions,
+                    statistics.num_conflicts,
+                    statistics.num_restarts
+                ]
+            );
+            assert_eq!(solver.step(), SolverEvent::Finished(result.clone()));
+            assert_eq!(
+                result.clone() == SearchResult::Unsatisfiable,
+                solve_dpll(&mut expression.clone()).is_none()
+            );
+
+            // Stepping takes the same steps as solving
+            let mut other = CdclSolver::new(&expression);
+            other.set_restarts(RestartPolicy::Luby { unit: 2 });
+            other.set_deletion(DeletionPolicy {
+                interval: Some(5),
+                keep_ratio: 0.5,
+            });
+            assert_eq!(other.solve(), result);
+            assert_eq!(other.get_statistics(), statistics);
+        }
+        assert!(num_deletions > 0);
+    }
+
+    #[test]
+    fn test_branching() {
+        // Before any conflict, the most frequent variable is decided towards its more frequent
+        // literal
+        let expression = parse_dimacs_str("p cnf 3 3\n1 -2 0\n-2 3 0\n-2 -3 0\n");
+        let mut solver = CdclSolver::new(&expression);
+        assert_eq!(solver.step(), SolverEvent::Decision(-2));
+
+        // Every variable the analysis went through is bumped past the initial activities
+        let hole6 = generate("hole6").unwrap().expression;
+        let mut solver = CdclSolver::new(&hole6);
+        loop {
+            if let SolverEvent::Conflict(id) = solver.step() {
+                let clause = solver.get_clause(id).to_vec();
+                solver.step();
+                for literal in clause {
+                    assert!(solver.branching.activities[to_variable(literal) as usize] >= 1.0);
+                }
+                break;
+            }
+        }
+
+        // Saved phases lead the search back to the model it found
+        for seed in 0..4 {
+            let expression = random_k_sat(30, 120, 3, seed);
+            let mut solver = CdclSolver::new(&expression);
+            solver.set_polarity_mode(PolarityMode::Saved);
+            let SearchResult::Satisfiable(model) = solver.solve() else {
+                panic!("The instance is satisfiable");
+            };
+            let num_conflicts = solver.get_statistics().num_conflicts;
+            solver.add_clause(&[31, 32]);
+            let SearchResult::Satisfiable(again) = solver.solve() else {
+                panic!("Adding the clause of new variables keeps the expression satisfiable");
+            };
+            assert_eq!(solver.get_statistics().num_conflicts, num_conflicts);
+            assert!(model
+                .iter()
+                .all(|(variable, value)| again[variable] == *value));
+        }
+    }
+
+    #[test]
+    fn test_propagate_assumptions() {
+        let expression =
+            parse_dimacs_str("p cnf 6 6\n1 2 0\n-1 3 0\n3 -2 0\n-3 4 5 0\n-4 -2 0\n6 0\n");
+        let mut solver = CdclSolver::new(&expression);
+        assert_eq!(
+            solver.propagate_assumptions(&[-2]),
+            PropagationResult::Implied(vec![-2, 1, 3])
+        );
+        assert_eq!(
+            solver.propagate_assumptions(&[1, 4]),
+            PropagationResult::Implied(vec![1, 3, 4, -2])
+        );
+        let PropagationResult::Conflict(mut clause) = solver.propagate_assumptions(&[-3]) else {
+            panic!("The assumptions do not conflict");
+        };
+        clause.sort_unstable();
+        assert_eq!(clause, vec![1, 2]);
+        assert_eq!(
+            solver.propagate_assumptions(&[1, -4, -5]),
+            PropagationResult::FailedAssumption(-5)
+        );
+        assert_eq!(solver.get_decision_level(), 0);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+
+        solver.add_clause(&[-6]);
+        assert_eq!(
+            solver.propagate_assumptions(&[1]),
+            PropagationResult::Conflict(Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_residual() {
+        let expression = parse_dimacs_str("p cnf 4 3\n1 0\n-1 2 3 0\n-2 -3 4 0\n");
+        let solver = CdclSolver::new(&expression);
+        assert_eq!(solver.get_fixed_literals(), &[1]);
+        let clauses: Vec<Vec<Literal>> = solver
+            .residual(false)
+            .get_clauses()
+            .iter()
+            .map(|clause| clause.literals().clone())
+            .collect();
+        assert_eq!(clauses, vec![vec![2, 3], vec![-2, -3, 4]]);
+
+        for seed in 0..6 {
+            let expression = random_k_sat(60, 255, 3, seed);
+            let mut solver = CdclSolver::new(&expression);
+            solver.set_budget(SearchBudget {
+                max_decisions: None,
+                max_conflicts: Some(30),
+            });
+            if solver.solve() != SearchResult::BudgetExhausted {
+                continue;
+            }
+            let expected = solve_cdcl(&expression).is_some();
+            for with_learned in [false, true] {
+                let residual = solver.residual(with_learned);
+                let Some(mut model) = solve_cdcl(&residual) else {
+                    assert!(!expected);
+                    continue;
+                };
+                assert!(expected);
+                for literal in solver.get_fixed_literals() {
+                    model.insert(to_variable(*literal), *literal > 0);
+                }
+                for variable in 1..=expression.get_max_variable() {
+                    model.entry(variable).or_insert(false);
+                }
+                assert!(expression.is_satisfied_by(&model));
+            }
+        }
+
+        // The units found before running out of budget simplify the residual
+        let mut hole6 = generate("hole6").unwrap().expression;
+        for literals in [&[50][..], &[-50, 51], &[-51, 52, 53]] {
+            hole6.add_clause(Clause::from_literals(literals));
+        }
+        let mut solver = CdclSolver::new(&hole6);
+        solver.set_budget(SearchBudget {
+            max_decisions: None,
+            max_conflicts: Some(10),
+        });
+        assert_eq!(solver.solve(), SearchResult::BudgetExhausted);
+        assert_eq!(solver.get_fixed_literals(), &[50, 51]);
+        let residual = solver.residual(false);
+        assert_eq!(residual.get_clauses().len(), hole6.get_clauses().len() - 2);
+        assert!(residual
+            .get_clauses()
+            .iter()
+            .any(|clause| clause.literals() == &vec![52, 53]));
+
+        solver.set_budget(SearchBudget::default());
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+        let residual = solver.residual(true);
+        assert_eq!(residual.get_clauses().len(), 1);
+        assert!(residual.get_clauses()[0].is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+//! Snapshots of the clause database of a [`CdclSolver`](crate::cdcl::CdclSolver), for
+//! understanding how the database grows and what the deletion of learned clauses keeps during a
+//! long solve.
+//!
+//! The solver stamps every clause with the number of conflicts when it was added or learned, and
+//! again whenever the clause takes part in the analysis of a conflict, as the conflicting clause
+//! or the reason of a resolved literal.
+//! [`CdclSolver::get_clause_database`](crate::cdcl::CdclSolver::get_clause_database) copies the
+//! clauses with their stamps at any point between solves, and the snapshot is written as CSV or
+//! JSON for plotting or scripting. The age of a clause and the time since it was last used are
+//! both counted in conflicts, which unlike time is the same on every run.
+
+use std::fmt::Write;
+
+use crate::cnf::Literal;
+
+/// A clause of the database with its stamps, in conflicts since the solver was created.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClauseRecord {
+    /// The id of the clause, which changes when learned clauses are deleted.
+    pub id: usize,
+    pub literals: Vec<Literal>,
+    /// The literal block distance of a learned clause, or `None` for a clause that was given.
+    pub lbd: Option<usize>,
+    /// The conflicts before the clause was added, including the one it was learned from.
+    pub added: usize,
+    /// The conflict the clause last took part in, or `None` if it never did.
+    pub last_used: Option<usize>,
+}
+
+impl ClauseRecord {
+    pub fn is_learned(&self) -> bool {
+        self.lbd.is_some()
+    }
+
+    /// Returns the conflicts since the clause was added.
+    pub fn get_age(&self, num_conflicts: usize) -> usize {
+        num_conflicts - self.added
+    }
+
+    /// Returns the conflicts since the clause was last used, or since it was added if it never
+    /// was.
+    pub fn get_idle(&self, num_conflicts: usize) -> usize {
+        num_conflicts - self.last_used.unwrap_or(self.added)
+    }
+}
+
+/// The clauses of a solver at one point of its search.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClauseDatabase {
+    /// The conflicts of the solver so far, which the ages are counted up to.
+    pub num_conflicts: usize,
+    /// The clauses by id, the given ones first.
+    pub clauses: Vec<ClauseRecord>,
+}
+
+impl ClauseDatabase {
+    pub fn get_num_learned(&self) -> usize {
+        self.clauses
+            .iter()
+            .filter(|record| record.is_learned())
+            .count()
+    }
+
+    /// Writes one line per clause, after a header, with the literals separated by spaces. Missing
+    /// values are left empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("id,learned,lbd,added,last_used,age,idle,size,literals\n");
+        for record in &self.clauses {
+            let literals: Vec<String> = record.literals.iter().map(Literal::to_string).collect();
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{}",
+                record.id,
+                record.is_learned(),
+                optional(record.lbd, ""),
+                record.added,
+                optional(record.last_used, ""),
+                record.get_age(self.num_conflicts),
+                record.get_idle(self.num_conflicts),
+                record.literals.len(),
+                literals.join(" ")
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    /// Writes the database as one JSON object, with a `clauses` array holding the columns of
+    /// [`ClauseDatabase::to_csv`]. Missing values are `null`.
+    pub fn to_json(&self) -> String {
+        let mut clauses = Vec::new();
+        for record in &self.clauses {
+            let literals: Vec<String> = record.literals.iter().map(Literal::to_string).collect();
+            clauses.push(format!(
+                "{{\"id\":{},\"learned\":{},\"lbd\":{},\"added\":{},\"last_used\":{},\"age\":{},\"idle\":{},\"size\":{},\"literals\":[{}]}}",
+                record.id,
+                record.is_learned(),
+                optional(record.lbd, "null"),
+                record.added,
+                optional(record.last_used, "null"),
+                record.get_age(self.num_conflicts),
+                record.get_idle(self.num_conflicts),
+                record.literals.len(),
+                literals.join(",")
+            ));
+        }
+
+        format!(
+            "{{\"num_conflicts\":{},\"num_clauses\":{},\"num_learned\":{},\"clauses\":[{}]}}",
+            self.num_conflicts,
+            self.clauses.len(),
+            self.get_num_learned(),
+            clauses.join(",")
+        )
+    }
+}
+
+fn optional(value: Option<usize>, missing: &str) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => missing.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::generate;
+    use crate::cdcl::{CdclSolver, DeletionPolicy};
+    use crate::dpll::SearchResult;
+
+    #[test]
+    fn test_clause_database() {
+        let hole6 = generate("hole6").unwrap().expression;
+        let num_given = hole6.get_clauses().len();
+        let mut solver = CdclSolver::new(&hole6);
+        solver.set_deletion(DeletionPolicy {
+            interval: Some(20),
+            keep_ratio: 0.5,
+        });
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+
+        let database = solver.get_clause_database();
+        let num_conflicts = solver.get_statistics().num_conflicts;
+        assert_eq!(database.num_conflicts, num_conflicts);
+        assert_eq!(
+            database.get_num_learned(),
+            solver.get_learned_clauses().count()
+        );
+        assert_eq!(
+            database.clauses.len(),
+            num_given + database.get_num_learned()
+        );
+        for (id, record) in database.clauses.iter().enumerate() {
+            assert_eq!(record.id, id);
+            assert_eq!(record.is_learned(), id >= num_given);
+            assert!(record.last_used.unwrap_or(record.added) <= num_conflicts);
+            assert!(record.get_idle(num_conflicts) <= record.get_age(num_conflicts));
+        }
+        // Learned clauses are stamped with the conflict they come from, in order
+        let learned: Vec<&ClauseRecord> = database.clauses[num_given..].iter().collect();
+        assert!(learned.iter().all(|record| record.added > 0));
+        assert!(learned.windows(2).all(|pair| pair[0].added < pair[1].added));
+        assert!(database
+            .clauses
+            .iter()
+            .any(|record| record.last_used.is_some()));
+
+        let csv = database.to_csv();
+        assert_eq!(csv.lines().count(), database.clauses.len() + 1);
+        assert!(
+            csv.starts_with("id,learned,lbd,added,last_used,age,idle,size,literals\n0,false,,0,")
+        );
+        let json = database.to_json();
+        assert!(json.starts_with(&format!("{{\"num_conflicts\":{},", num_conflicts)));
+        assert_eq!(json.matches("\"id\":").count(), database.clauses.len());
+    }
+}
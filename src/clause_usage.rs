@@ -0,0 +1,284 @@
+//! Statistics of how the DPLL search used each clause of an expression, for users debugging a
+//! slow encoding who want to know which of their constraints the solver fights with most.
+//!
+//! [`analyze_clause_usage`] solves the expression with a hook that mirrors the assignment: every
+//! clause keeps the number of its literals that are true and false, updated through the
+//! occurrences of each literal that is assigned or unassigned. A clause is counted when the
+//! search falsifies it (a conflict), when it propagates its last literal, and when something
+//! else satisfies it first. Clauses that keep being satisfied early rarely constrain the search;
+//! the ones that keep propagating and conflicting are where it spends its effort.
+
+use std::fmt;
+
+use crate::cnf::{to_variable, Literal};
+use crate::dpll::{
+    solve_dpll_with_hook_and_budget, SearchBudget, SearchEvent, SearchHook, SearchResult,
+};
+use crate::expression::Expression;
+use crate::trail::Trail;
+
+/// How the search used a clause of the expression.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClauseUsage {
+    /// The index of the clause in [`Expression::get_clauses`].
+    pub clause: usize,
+    pub literals: Vec<Literal>,
+    /// The conflicts in which every literal of the clause was false.
+    pub num_conflicts: usize,
+    /// The times the clause was unit and assigned its last literal.
+    pub num_propagations: usize,
+    /// The times an assignment satisfied the clause other than its own propagation.
+    pub num_satisfied: usize,
+}
+
+/// The usage of every clause in one solve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClauseUsageReport {
+    pub result: SearchResult,
+    /// Indexed like [`Expression::get_clauses`].
+    pub clauses: Vec<ClauseUsage>,
+}
+
+impl ClauseUsageReport {
+    /// Returns up to `limit` clauses that took part in a conflict or propagation, the most
+    /// troublesome first: by conflicts, then by propagations, then by how rarely they were
+    /// satisfied early.
+    pub fn get_troublesome(&self, limit: usize) -> Vec<&ClauseUsage> {
+        let mut clauses: Vec<&ClauseUsage> = self
+            .clauses
+            .iter()
+            .filter(|usage| usage.num_conflicts + usage.num_propagations > 0)
+            .collect();
+        clauses.sort_by_key(|usage| {
+            (
+                std::cmp::Reverse(usage.num_conflicts),
+                std::cmp::Reverse(usage.num_propagations),
+                usage.num_satisfied,
+                usage.clause,
+            )
+        });
+        clauses.truncate(limit);
+        clauses
+    }
+}
+
+/// Formats the troublesome clauses as a table, one line per clause.
+impl fmt::Display for ClauseUsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>8} {:>10} {:>12} {:>10}  literals",
+            "clause", "conflicts", "propagations", "satisfied"
+        )?;
+        for usage in self.get_troublesome(self.clauses.len()) {
+            let literals: Vec<String> = usage.literals.iter().map(Literal::to_string).collect();
+            writeln!(
+                f,
+                "{:>8} {:>10} {:>12} {:>10}  {}",
+                usage.clause,
+                usage.num_conflicts,
+                usage.num_propagations,
+                usage.num_satisfied,
+                literals.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[inline]
+fn literal_index(literal: Literal) -> usize {
+    2 * to_variable(literal) as usize + (literal < 0) as usize
+}
+
+/// Counts the uses of the clauses while mirroring the assignment of the search.
+struct UsageHook {
+    usages: Vec<ClauseUsage>,
+    /// The number of distinct literals of every clause.
+    sizes: Vec<usize>,
+    num_true: Vec<usize>,
+    num_false: Vec<usize>,
+    /// The clauses with each literal, by [`literal_index`].
+    occurrences: Vec<Vec<usize>>,
+    /// The clauses all of whose literals are false.
+    falsified: Vec<usize>,
+    /// The assignment as of the last step.
+    assigned: Vec<Literal>,
+}
+
+impl UsageHook {
+    fn new(expression: &Expression) -> UsageHook {
+        let num_literals = 2 * expression.get_max_variable() as usize + 2;
+        let mut hook = UsageHook {
+            usages: Vec::new(),
+            sizes: Vec::new(),
+            num_true: Vec::new(),
+            num_false: Vec::new(),
+            occurrences: vec![Vec::new(); num_literals],
+            falsified: Vec::new(),
+            assigned: Vec::new(),
+        };
+        for (clause, literals) in expression.get_clauses().iter().enumerate() {
+            let mut unique: Vec<Literal> = Vec::with_capacity(literals.len());
+            for literal in literals.literals() {
+                if !unique.contains(literal) {
+                    unique.push(*literal);
+                    hook.occurrences[literal_index(*literal)].push(clause);
+                }
+            }
+            if unique.is_empty() {
+                hook.falsified.push(clause);
+            }
+            hook.usages.push(ClauseUsage {
+                clause,
+                literals: literals.literals().clone(),
+                ..ClauseUsage::default()
+            });
+            hook.sizes.push(unique.len());
+            hook.num_true.push(0);
+            hook.num_false.push(0);
+        }
+        hook
+    }
+
+    fn is_falsified(&self, clause: usize) -> bool {
+        self.num_true[clause] == 0 && self.num_false[clause] == self.sizes[clause]
+    }
+
+    /// Counts the clauses the literal satisfies, unless it is the propagation of the clause.
+    fn assign(&mut self, literal: Literal, reason: Option<usize>) {
+        for position in 0..self.occurrences[literal_index(literal)].len() {
+            let clause = self.occurrences[literal_index(literal)][position];
+            self.num_true[clause] += 1;
+            if self.num_true[clause] == 1 && reason != Some(clause) {
+                self.usages[clause].num_satisfied += 1;
+            }
+        }
+        for position in 0..self.occurrences[literal_index(-literal)].len() {
+            let clause = self.occurrences[literal_index(-literal)][position];
+            self.num_false[clause] += 1;
+            if self.is_falsified(clause) {
+                self.falsified.push(clause);
+            }
+        }
+    }
+
+    fn unassign(&mut self, literal: Literal) {
+        for position in 0..self.occurrences[literal_index(literal)].len() {
+            let clause = self.occurrences[literal_index(literal)][position];
+            self.num_true[clause] -= 1;
+        }
+        for position in 0..self.occurrences[literal_index(-literal)].len() {
+            let clause = self.occurrences[literal_index(-literal)][position];
+            if self.is_falsified(clause) {
+                self.falsified.retain(|falsified| *falsified != clause);
+            }
+            self.num_false[clause] -= 1;
+        }
+    }
+}
+
+impl SearchHook for UsageHook {
+    fn on_event(&mut self, event: SearchEvent, trail: &Trail) {
+        for index in self.assigned.len()..trail.len() {
+            let literal = trail.get_literals()[index];
+            self.assigned.push(literal);
+            self.assign(literal, trail.reason(to_variable(literal)));
+        }
+        match event {
+            SearchEvent::UnitPropagation(clause) => {
+                self.usages[clause as usize].num_propagations += 1;
+            }
+            SearchEvent::Conflict => {
+                for clause in &self.falsified {
+                    self.usages[*clause].num_conflicts += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_backtrack(&mut self, trail: &Trail) {
+        while self.assigned.len() > trail.len() {
+            let literal = self.assigned.pop().unwrap();
+            self.unassign(literal);
+        }
+    }
+}
+
+/// Solves the expression with the DPLL search, giving up once the budget is exhausted, and
+/// reports how the search used each of its clauses until then. The search is the one of
+/// [`solve_dpll`](crate::dpll::solve_dpll), with the heuristic set on the expression, so a
+/// budget that stops a slow solve still shows where its effort went.
+pub fn analyze_clause_usage(expression: &Expression, budget: &SearchBudget) -> ClauseUsageReport {
+    let mut hook = UsageHook::new(expression);
+    let mut working = expression.clone();
+    working.set_heuristic(expression.heuristic);
+    working.optimize();
+    let result = solve_dpll_with_hook_and_budget(&mut working, &mut hook, budget);
+    ClauseUsageReport {
+        result,
+        clauses: hook.usages,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::pigeonhole;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::dpll::{solve_dpll_traced, SearchTrace};
+
+    #[test]
+    fn test_clause_usage() {
+        // Every assignment of 1 and 2 falsifies one of the first four clauses, while the last
+        // one is satisfied by whatever the search decides first
+        let expression = parse_dimacs_str("p cnf 3 5\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n1 2 3 0\n");
+        let report = analyze_clause_usage(&expression, &SearchBudget::default());
+        assert_eq!(report.result, SearchResult::Unsatisfiable);
+        assert_eq!(report.clauses.len(), 5);
+        let num_conflicts: usize = report.clauses.iter().map(|usage| usage.num_conflicts).sum();
+        assert!(num_conflicts >= 2);
+        assert_eq!(report.clauses[4].num_conflicts, 0);
+        assert_eq!(report.clauses[4].num_propagations, 0);
+        assert!(report.clauses[4].num_satisfied > 0);
+
+        let troublesome = report.get_troublesome(10);
+        assert_eq!(troublesome.len(), 4);
+        assert!(troublesome.iter().all(|usage| usage.clause < 4));
+        assert!(troublesome
+            .windows(2)
+            .all(|pair| pair[0].num_conflicts >= pair[1].num_conflicts));
+        assert_eq!(report.get_troublesome(1)[0], troublesome[0]);
+        let table = report.to_string();
+        assert_eq!(table.lines().count(), 5);
+        assert!(table.starts_with("  clause  conflicts propagations  satisfied  literals\n"));
+    }
+
+    #[test]
+    fn test_clause_usage_budget() {
+        let budget = SearchBudget {
+            max_conflicts: Some(10),
+            ..SearchBudget::default()
+        };
+        let report = analyze_clause_usage(&pigeonhole(6), &budget);
+        assert_eq!(report.result, SearchResult::BudgetExhausted);
+        let num_conflicts: usize = report.clauses.iter().map(|usage| usage.num_conflicts).sum();
+        assert!(num_conflicts >= 10);
+
+        // The search is the same as without the hook: every propagation is counted once, and
+        // every conflict falsifies at least one clause
+        let mut expression = pigeonhole(4);
+        let report = analyze_clause_usage(&expression, &SearchBudget::default());
+        let mut trace = SearchTrace::new();
+        expression.optimize();
+        solve_dpll_traced(&mut expression, &mut trace);
+        let total =
+            |count: fn(&ClauseUsage) -> usize| report.clauses.iter().map(count).sum::<usize>();
+        assert_eq!(
+            total(|usage| usage.num_propagations),
+            trace.num_propagations
+        );
+        assert!(total(|usage| usage.num_conflicts) >= trace.num_conflicts);
+    }
+}
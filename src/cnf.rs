@@ -6,14 +6,38 @@ pub enum Action {
     RemoveLiteralFromClausesStart(),
     RemoveLiteralFromClause(ClauseId),
     RemoveLiteralFromClausesEnd(Literal),
-    AssignVariable(Variable),
+    /// The literal was made true, and counted in the cardinality constraints over its variable.
+    CountCardinalityLiteral(Literal),
 }
 
 pub type Assignment = HashMap<Variable, bool>;
-pub type ClauseId = u16;
+pub type ClauseId = u32;
+
+// The width of literals and variables is chosen at compile time, 32 bits unless the literals-16
+// or literals-64 feature asks otherwise. The wider one wins if both do. Clause identifiers stay
+// 32 bits at every width, since the number of clauses does not depend on that of variables.
+#[cfg(all(feature = "literals-16", not(feature = "literals-64")))]
 pub type Literal = i16;
+#[cfg(all(feature = "literals-16", not(feature = "literals-64")))]
 pub type Variable = u16;
-pub type ActionState = usize;
+
+#[cfg(not(any(feature = "literals-16", feature = "literals-64")))]
+pub type Literal = i32;
+#[cfg(not(any(feature = "literals-16", feature = "literals-64")))]
+pub type Variable = u32;
+
+#[cfg(feature = "literals-64")]
+pub type Literal = i64;
+#[cfg(feature = "literals-64")]
+pub type Variable = u64;
+
+/// A point of the search to return to with [`CNF::restore_action_state`]: the number of actions
+/// to keep, and the number of assignments of the trail to keep.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ActionState {
+    pub(crate) actions: usize,
+    pub(crate) trail: usize,
+}
 
 #[derive(Debug, Default, Eq, PartialEq, Clone)]
 pub struct Clause {
@@ -50,6 +74,51 @@ pub trait CNF {
     fn branch_variable(&mut self, variable: Variable, value: bool);
 }
 
+/// Conversions of an [`Assignment`] to smaller or more compact forms.
+pub trait AssignmentExt {
+    /// Returns the values of the given variables only, leaving out the ones it does not assign.
+    fn project(&self, variables: &[Variable]) -> Assignment;
+
+    /// Returns the assignment as signed literals (true variables positive, false ones negative)
+    /// sorted by variable, as on a DIMACS `v` line.
+    fn to_literal_vec(&self) -> Vec<Literal>;
+
+    /// Builds the assignment making the literals true, the inverse of
+    /// [`AssignmentExt::to_literal_vec`].
+    fn from_literal_vec(literals: &[Literal]) -> Assignment;
+}
+
+impl AssignmentExt for Assignment {
+    fn project(&self, variables: &[Variable]) -> Assignment {
+        variables
+            .iter()
+            .filter_map(|variable| self.get(variable).map(|value| (*variable, *value)))
+            .collect()
+    }
+
+    fn to_literal_vec(&self) -> Vec<Literal> {
+        let mut literals: Vec<Literal> = self
+            .iter()
+            .map(|(variable, value)| {
+                if *value {
+                    *variable as Literal
+                } else {
+                    -(*variable as Literal)
+                }
+            })
+            .collect();
+        literals.sort_unstable_by_key(|literal| to_variable(*literal));
+        literals
+    }
+
+    fn from_literal_vec(literals: &[Literal]) -> Assignment {
+        literals
+            .iter()
+            .map(|literal| (to_variable(*literal), *literal > 0))
+            .collect()
+    }
+}
+
 impl Clause {
     pub fn new() -> Clause {
         Clause {
@@ -57,6 +126,15 @@ impl Clause {
         }
     }
 
+    /// Returns the clause of the literals, keeping each of them once.
+    pub fn from_literals(literals: &[Literal]) -> Clause {
+        let mut clause = Clause::new();
+        for literal in literals {
+            clause.insert_checked(*literal);
+        }
+        clause
+    }
+
     #[inline]
     pub fn insert_checked(&mut self, variable: Literal) {
         if !self.variables.contains(&variable) {
@@ -92,7 +170,7 @@ impl Clause {
     #[inline]
     pub fn get(&self, index: usize) -> Literal {
         unsafe {
-            return *self.variables.get_unchecked(index);
+            *self.variables.get_unchecked(index)
         }
     }
 
@@ -106,6 +184,15 @@ impl Clause {
             }
         }
     }
+
+    /// Returns true if the assignment makes one of the literals true. A variable missing from
+    /// the assignment makes none of its literals true, so a partial assignment satisfies only the
+    /// clauses it already decides.
+    pub fn is_satisfied_by(&self, assignment: &Assignment) -> bool {
+        self.variables
+            .iter()
+            .any(|literal| assignment.get(&to_variable(*literal)) == Some(&(*literal > 0)))
+    }
 }
 
 #[inline]
@@ -125,4 +212,55 @@ pub fn to_positive(variable: Literal) -> Literal {
     } else {
         -variable
     }
-}
\ No newline at end of file
+}
+
+/// Parses a literal in the decimal notation of DIMACS, rejecting those out of range, including
+/// `Literal::MIN`, whose negation overflows.
+pub fn parse_literal(token: &str) -> Option<Literal> {
+    token
+        .parse::<Literal>()
+        .ok()
+        .filter(|literal| *literal != Literal::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assignment_conversions() {
+        let assignment: Assignment = [(3, false), (1, true), (4, true), (2, false)].into();
+        assert_eq!(assignment.to_literal_vec(), vec![1, -2, -3, 4]);
+        assert_eq!(
+            Assignment::from_literal_vec(&assignment.to_literal_vec()),
+            assignment
+        );
+
+        let projected = assignment.project(&[4, 2, 7]);
+        assert_eq!(projected, [(2, false), (4, true)].into());
+        assert_eq!(projected.to_literal_vec(), vec![-2, 4]);
+    }
+
+    #[test]
+    fn test_clause_is_satisfied_by() {
+        let clause = Clause::from_literals(&[1, -2]);
+        assert!(clause.is_satisfied_by(&[(1, true)].into()));
+        assert!(clause.is_satisfied_by(&[(1, false), (2, false)].into()));
+        assert!(!clause.is_satisfied_by(&[(1, false), (2, true)].into()));
+
+        // Unassigned variables satisfy none of their literals
+        assert!(!clause.is_satisfied_by(&[(1, false)].into()));
+        assert!(!clause.is_satisfied_by(&Assignment::new()));
+        assert!(!Clause::new().is_satisfied_by(&[(1, true)].into()));
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(parse_literal("-30000"), Some(-30000));
+        assert_eq!(parse_literal(&Literal::MAX.to_string()), Some(Literal::MAX));
+        assert_eq!(parse_literal(&Literal::MIN.to_string()), None);
+        let beyond = Literal::MAX as i128 + 1;
+        assert_eq!(parse_literal(&beyond.to_string()), None);
+        assert_eq!(parse_literal("1.5"), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! A handle for steering a solve while it runs: cancelling it, adjusting its tunable settings,
+//! and polling its progress from another thread.
+//!
+//! The search reads the settings again whenever they change, at its next decision, which is the
+//! boundary at which the DPLL search can change course without losing its work.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::dpll::SearchBudget;
+use crate::solver::SolverConfig;
+
+/// The settings of a solve that can be changed while it runs.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TunableSettings {
+    /// Lowering the budget below the work done so far stops the search at its next decision.
+    pub budget: SearchBudget,
+    /// The number of decisions between updates of the progress, or `None` to not report progress.
+    pub progress_interval: Option<usize>,
+}
+
+/// The work done by a running solve, as of its last progress update.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Progress {
+    pub num_decisions: usize,
+    pub num_conflicts: usize,
+    /// The fraction of the search space covered so far, from 0 to 1, for showing the progress of
+    /// long refutations. The DPLL search reports the exact fraction of the assignment tree it has
+    /// refuted; the CDCL solver, which jumps around the tree, reports an estimate from its trail.
+    pub coverage: f64,
+}
+
+#[derive(Debug)]
+struct Shared {
+    cancelled: AtomicBool,
+    /// Incremented on every change of the settings, so the search only locks them when needed.
+    version: AtomicU64,
+    settings: Mutex<TunableSettings>,
+    progress: Mutex<Progress>,
+}
+
+/// A shared handle to a solve. Clones refer to the same solve, so one can be given to the solver
+/// while another steers it from a different thread.
+#[derive(Clone, Debug)]
+pub struct SolverControl {
+    shared: Arc<Shared>,
+}
+
+impl SolverControl {
+    pub fn new(settings: TunableSettings) -> SolverControl {
+        SolverControl {
+            shared: Arc::new(Shared {
+                cancelled: AtomicBool::new(false),
+                version: AtomicU64::new(0),
+                settings: Mutex::new(settings),
+                progress: Mutex::new(Progress::default()),
+            }),
+        }
+    }
+
+    /// Creates a control starting from the budget of the configuration.
+    pub fn from_config(config: &SolverConfig) -> SolverControl {
+        SolverControl::new(TunableSettings {
+            budget: config.budget,
+            ..TunableSettings::default()
+        })
+    }
+
+    /// Stops the solve at its next decision. It then ends as if its budget ran out.
+    pub fn cancel(&self) {
+        self.shared.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn get_settings(&self) -> TunableSettings {
+        *self.shared.settings.lock().unwrap()
+    }
+
+    /// Changes the settings, which the solve picks up at its next decision.
+    pub fn update_settings(&self, update: impl FnOnce(&mut TunableSettings)) {
+        let mut settings = self.shared.settings.lock().unwrap();
+        update(&mut settings);
+        self.shared.version.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn get_progress(&self) -> Progress {
+        *self.shared.progress.lock().unwrap()
+    }
+
+    pub(crate) fn get_version(&self) -> u64 {
+        self.shared.version.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_progress(&self, progress: Progress) {
+        *self.shared.progress.lock().unwrap() = progress;
+    }
+}
+
+impl Default for SolverControl {
+    fn default() -> Self {
+        SolverControl::new(TunableSettings::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::generate;
+    use crate::dpll::SearchResult;
+    use crate::solver::{solve_with_control, Engine};
+
+    #[test]
+    fn test_update_settings() {
+        let expression = generate("hole6").unwrap().expression;
+        for engine in [Engine::Dpll, Engine::Cdcl] {
+            let control = SolverControl::new(TunableSettings {
+                budget: SearchBudget::default(),
+                progress_interval: Some(1),
+            });
+            control.update_settings(|settings| settings.budget.max_conflicts = Some(25));
+
+            let config = SolverConfig {
+                engine,
+                ..SolverConfig::default()
+            };
+            let (result, statistics) = solve_with_control(&expression, &config, &control);
+            assert_eq!(result, SearchResult::BudgetExhausted);
+            assert_eq!(statistics.num_conflicts, 25);
+            let progress = control.get_progress();
+            assert_eq!(progress.num_decisions, statistics.num_decisions);
+            assert!(progress.coverage > 0.0 && progress.coverage < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        let expression = generate("hole6").unwrap().expression;
+        let control = SolverControl::from_config(&SolverConfig::default());
+        let steering = control.clone();
+        steering.cancel();
+        let handle = std::thread::spawn(move || {
+            solve_with_control(&expression, &SolverConfig::default(), &control)
+        });
+
+        let (result, statistics) = handle.join().unwrap();
+        assert_eq!(result, SearchResult::BudgetExhausted);
+        assert_eq!(statistics.num_decisions, 0);
+    }
+}
@@ -0,0 +1,204 @@
+//! Approximate model counting by hashing, after ApproxMC (Chakraborty, Meel and Vardi).
+//!
+//! Random XOR constraints split the models into cells of about equal size: each one holds for
+//! half of the assignments, independently of the others, so `m` of them leave about a `2^m`-th of
+//! the models. The counter adds XOR constraints one at a time until a cell is small enough to
+//! enumerate, and multiplies its size back up. Repeating this with fresh constraints and taking
+//! the median makes the estimate lie within a factor `1 + epsilon` of the true count with
+//! probability at least `1 - delta`.
+//!
+//! An XOR constraint is encoded into clauses by [`encodings::add_xor`], as a chain of parities of
+//! three variables, and the cells are enumerated with [`Solver::models`], so the clauses learned
+//! in one cell carry over to the next constraint of the same round.
+
+use crate::cnf::{to_variable, Literal, Variable};
+use crate::encodings::{self, VariableAllocator};
+use crate::expression::Expression;
+use crate::rng::Rng;
+use crate::solver::Solver;
+
+/// The accuracy and the seed of [`approximate_model_count`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CountingConfig {
+    /// The tolerated relative error: the estimate lies between the count divided by `1 + epsilon`
+    /// and the count multiplied by it.
+    pub epsilon: f64,
+    /// The tolerated probability of an estimate outside of the tolerance.
+    pub delta: f64,
+    pub seed: u64,
+}
+
+impl Default for CountingConfig {
+    fn default() -> Self {
+        CountingConfig {
+            epsilon: 0.8,
+            delta: 0.2,
+            seed: 1,
+        }
+    }
+}
+
+/// A model count of the form `cell_count * 2^num_hashes`, which is exact if no hash was needed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ModelCount {
+    pub cell_count: usize,
+    /// The XOR constraints the counted cell was cut out with.
+    pub num_hashes: usize,
+}
+
+impl ModelCount {
+    pub fn is_exact(&self) -> bool {
+        self.num_hashes == 0
+    }
+
+    /// Returns the count as a float, which is infinite past the range of `f64`.
+    pub fn to_f64(&self) -> f64 {
+        self.cell_count as f64 * 2f64.powi(self.num_hashes as i32)
+    }
+
+    /// Returns the base 2 logarithm of the count, which stays finite for any count but 0.
+    pub fn get_log2(&self) -> f64 {
+        (self.cell_count as f64).log2() + self.num_hashes as f64
+    }
+}
+
+/// Adds the clauses forcing the XOR of the variables to the parity, with the auxiliary variables
+/// of [`encodings::add_xor`] taken from the solver. The variables must already be known to the
+/// solver, from its clauses or from [`Solver::new_var`], so the fresh ones come after them.
+pub fn add_xor(solver: &mut Solver, variables: &[Variable], parity: bool) {
+    let literals: Vec<Literal> = variables
+        .iter()
+        .map(|variable| *variable as Literal)
+        .collect();
+    let mut allocator = VariableAllocator::new(solver.get_num_variables());
+    encodings::add_xor(solver, &mut allocator, &literals, parity);
+}
+
+/// Returns the number of models of the expression over the variables its clauses use, exact if
+/// there are few of them, and estimated within the tolerance of the configuration otherwise (see
+/// the module documentation). The same seed gives the same estimate.
+pub fn approximate_model_count(expression: &Expression, config: &CountingConfig) -> ModelCount {
+    assert!(config.epsilon > 0.0, "epsilon must be positive");
+    assert!(
+        config.delta > 0.0 && config.delta < 1.0,
+        "delta must be between 0 and 1"
+    );
+    let epsilon = config.epsilon;
+    let threshold = (1.0 + 9.84 * (1.0 + epsilon / (1.0 + epsilon)) * (1.0 + 1.0 / epsilon).powi(2))
+        .ceil() as usize;
+    let num_rounds = (17.0 * (3.0 / config.delta).log2()).ceil() as usize;
+
+    let mut base = Solver::new();
+    let mut variables: Vec<Variable> = Vec::new();
+    for clause in expression.get_clauses() {
+        let literals = clause.literals().clone();
+        variables.extend(literals.iter().map(|literal| to_variable(*literal)));
+        base.add_clause(&literals);
+    }
+    variables.sort_unstable();
+    variables.dedup();
+
+    let cell_count = base.models().take(threshold).count();
+    if cell_count < threshold {
+        return ModelCount {
+            cell_count,
+            num_hashes: 0,
+        };
+    }
+
+    let mut rng = Rng::new(config.seed);
+    let mut estimates: Vec<ModelCount> = Vec::with_capacity(num_rounds);
+    for _ in 0..num_rounds {
+        let mut solver = base.clone();
+        let mut estimate = ModelCount {
+            cell_count,
+            num_hashes: 0,
+        };
+        // Dependent constraints can leave a large cell, which then counts for what it is
+        while estimate.cell_count >= threshold && estimate.num_hashes < variables.len() {
+            let hashed: Vec<Variable> = variables
+                .iter()
+                .copied()
+                .filter(|_| rng.next_bool())
+                .collect();
+            add_xor(&mut solver, &hashed, rng.next_bool());
+            estimate = ModelCount {
+                cell_count: solver.models().take(threshold).count(),
+                num_hashes: estimate.num_hashes + 1,
+            };
+        }
+        estimates.push(estimate);
+    }
+
+    // An empty cell has a logarithm of minus infinity, so it sorts first
+    estimates.sort_by(|left, right| left.get_log2().total_cmp(&right.get_log2()));
+    estimates[estimates.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::random_k_sat;
+    use crate::cnf::Assignment;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_add_xor() {
+        for num_variables in 0..8 {
+            for parity in [false, true] {
+                let mut solver = Solver::new();
+                let variables: Vec<Variable> =
+                    (0..num_variables).map(|_| solver.new_var()).collect();
+                add_xor(&mut solver, &variables, parity);
+                let mut num_models = 0;
+                for model in solver.models() {
+                    let ones = variables.iter().filter(|variable| model[*variable]).count();
+                    assert_eq!(ones % 2 == 1, parity);
+                    num_models += 1;
+                }
+                // Every assignment of the variables but the last has one completion
+                let expected = match num_variables {
+                    0 => (!parity) as usize,
+                    _ => 1 << (num_variables - 1),
+                };
+                assert_eq!(num_models, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_approximate_model_count() {
+        // Few models are counted exactly
+        let expression = parse_dimacs_str("p cnf 3 2\n1 2 0\n-1 -2 3 0\n");
+        let count = approximate_model_count(&expression, &CountingConfig::default());
+        assert_eq!(
+            count,
+            ModelCount {
+                cell_count: 5,
+                num_hashes: 0
+            }
+        );
+        assert!(count.is_exact());
+
+        let config = CountingConfig {
+            delta: 0.5,
+            ..CountingConfig::default()
+        };
+        for seed in 0..2 {
+            let expression = random_k_sat(12, 16, 3, seed);
+            let exact = (0..1 << 12)
+                .filter(|mask| {
+                    let model: Assignment = (1..=12)
+                        .map(|variable| (variable, mask >> (variable - 1) & 1 == 1))
+                        .collect();
+                    expression.is_satisfied_by(&model)
+                })
+                .count() as f64;
+            let count = approximate_model_count(&expression, &config);
+            assert!(!count.is_exact());
+            let estimate = count.to_f64();
+            assert!(estimate >= exact / (1.0 + config.epsilon));
+            assert!(estimate <= exact * (1.0 + config.epsilon));
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Runs an external SAT solver (such as minisat, kissat or cadical) on the same instance as this
+//! crate, and compares the answers and verifies the models of both.
+
+use std::fmt;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::cnf::{to_variable, Assignment, Literal};
+use crate::dimacs_parser::parse_dimacs_str;
+use crate::solver::solve;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Status {
+    Satisfiable,
+    Unsatisfiable,
+    Unknown,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Satisfiable => write!(f, "SATISFIABLE"),
+            Status::Unsatisfiable => write!(f, "UNSATISFIABLE"),
+            Status::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+/// The answer of a solver, as read from its output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolverOutput {
+    pub status: Status,
+    /// The model from the `v` lines, if the solver printed any.
+    pub model: Option<Assignment>,
+}
+
+#[derive(Debug)]
+pub enum CrosscheckError {
+    /// The instance or the solver's result file could not be read, or the solver could not be started.
+    Io(std::io::Error),
+    /// The solver's output could not be understood.
+    Output(String),
+}
+
+impl fmt::Display for CrosscheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrosscheckError::Io(error) => write!(f, "{}", error),
+            CrosscheckError::Output(message) => write!(f, "invalid solver output: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CrosscheckError {}
+
+impl From<std::io::Error> for CrosscheckError {
+    fn from(error: std::io::Error) -> Self {
+        CrosscheckError::Io(error)
+    }
+}
+
+/// Parses solver output in the SAT competition format (`s SATISFIABLE` and `v 1 -2 0` lines), or
+/// in the format of minisat's result file (`SAT` followed by the model, or `UNSAT`).
+pub fn parse_solver_output(output: &str) -> Result<SolverOutput, CrosscheckError> {
+    let mut status = None;
+    let mut literals: Option<Vec<Literal>> = None;
+    let mut result_file_format = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+        let values = if let Some(rest) = line.strip_prefix("s ") {
+            status = Some(parse_status(rest.trim())?);
+            continue;
+        } else if let Some(rest) = line.strip_prefix("v ") {
+            rest
+        } else if status.is_none() && matches!(line, "SAT" | "UNSAT" | "INDET") {
+            status = Some(parse_status(line)?);
+            result_file_format = true;
+            continue;
+        } else if result_file_format && !line.is_empty() {
+            line
+        } else {
+            continue;
+        };
+
+        let literals = literals.get_or_insert_with(Vec::new);
+        for value in values.split_whitespace() {
+            let literal = value
+                .parse::<Literal>()
+                .map_err(|_| CrosscheckError::Output(format!("invalid literal '{}'", value)))?;
+            if literal != 0 {
+                literals.push(literal);
+            }
+        }
+    }
+
+    let status = status.ok_or_else(|| CrosscheckError::Output("no status line".to_string()))?;
+    let model = literals.map(|literals| {
+        literals
+            .iter()
+            .map(|literal| (to_variable(*literal), *literal > 0))
+            .collect()
+    });
+
+    Ok(SolverOutput { status, model })
+}
+
+fn parse_status(status: &str) -> Result<Status, CrosscheckError> {
+    match status {
+        "SATISFIABLE" | "SAT" => Ok(Status::Satisfiable),
+        "UNSATISFIABLE" | "UNSAT" => Ok(Status::Unsatisfiable),
+        "UNKNOWN" | "INDET" => Ok(Status::Unknown),
+        _ => Err(CrosscheckError::Output(format!(
+            "unknown status '{}'",
+            status
+        ))),
+    }
+}
+
+/// An external solver binary, called as `program [args...] file.cnf`.
+#[derive(Clone, Debug)]
+pub struct ExternalSolver {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Whether the solver writes its answer to a result file given as the last argument, like
+    /// minisat does, instead of printing it.
+    pub result_file: bool,
+}
+
+static RESULT_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl ExternalSolver {
+    /// A solver printing competition-format output, such as kissat or cadical.
+    pub fn new(program: &str) -> ExternalSolver {
+        ExternalSolver {
+            program: program.to_string(),
+            args: Vec::new(),
+            result_file: false,
+        }
+    }
+
+    /// minisat, which only prints its model to a result file.
+    pub fn minisat(program: &str) -> ExternalSolver {
+        ExternalSolver {
+            program: program.to_string(),
+            args: vec!["-verb=0".to_string()],
+            result_file: true,
+        }
+    }
+
+    /// Runs the solver on the DIMACS file and parses its answer.
+    pub fn run(&self, file: &str) -> Result<SolverOutput, CrosscheckError> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).arg(file);
+
+        let result_path = self.result_file.then(|| {
+            std::env::temp_dir().join(format!(
+                "microsat-crosscheck-{}-{}.out",
+                std::process::id(),
+                RESULT_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ))
+        });
+        if let Some(path) = &result_path {
+            command.arg(path);
+        }
+
+        let output = command.output()?;
+        let text = match &result_path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path);
+                let _ = std::fs::remove_file(path);
+                text?
+            }
+            None => String::from_utf8_lossy(&output.stdout).into_owned(),
+        };
+
+        match parse_solver_output(&text) {
+            // Fall back to the conventional exit codes if nothing was printed
+            Err(_) if output.status.code() == Some(10) => Ok(SolverOutput {
+                status: Status::Satisfiable,
+                model: None,
+            }),
+            Err(_) if output.status.code() == Some(20) => Ok(SolverOutput {
+                status: Status::Unsatisfiable,
+                model: None,
+            }),
+            result => result,
+        }
+    }
+}
+
+/// The answers of this crate and of an external solver on the same instance.
+#[derive(Clone, Debug)]
+pub struct CrosscheckReport {
+    pub ours: Status,
+    pub theirs: Status,
+    /// Whether our model satisfies the instance, or `None` if there is no model.
+    pub our_model_verified: Option<bool>,
+    /// Whether the external model satisfies the instance, or `None` if it printed no model.
+    pub their_model_verified: Option<bool>,
+    pub our_time: Duration,
+    pub their_time: Duration,
+}
+
+impl CrosscheckReport {
+    /// Returns true if both solvers gave the same definite answer, and every model verifies.
+    pub fn agrees(&self) -> bool {
+        self.ours == self.theirs
+            && self.ours != Status::Unknown
+            && self.our_model_verified != Some(false)
+            && self.their_model_verified != Some(false)
+    }
+}
+
+impl fmt::Display for CrosscheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |verified: Option<bool>| match verified {
+            Some(true) => " (model verified)",
+            Some(false) => " (MODEL DOES NOT VERIFY)",
+            None => "",
+        };
+
+        writeln!(
+            f,
+            "microsat: {}{} in {:?}",
+            self.ours,
+            describe(self.our_model_verified),
+            self.our_time
+        )?;
+        writeln!(
+            f,
+            "external: {}{} in {:?}",
+            self.theirs,
+            describe(self.their_model_verified),
+            self.their_time
+        )?;
+        write!(
+            f,
+            "result: {}",
+            if self.agrees() { "agree" } else { "DISAGREE" }
+        )
+    }
+}
+
+/// Solves the DIMACS file with this crate and with the external solver, and compares the results.
+pub fn crosscheck(
+    file: &str,
+    solver: &ExternalSolver,
+) -> Result<CrosscheckReport, CrosscheckError> {
+    let expression = parse_dimacs_str(&std::fs::read_to_string(file)?);
+
+    let start = Instant::now();
+    let our_model = solve(expression.clone(), true, false);
+    let our_time = start.elapsed();
+
+    let start = Instant::now();
+    let theirs = solver.run(file)?;
+    let their_time = start.elapsed();
+
+    Ok(CrosscheckReport {
+        ours: if our_model.is_some() {
+            Status::Satisfiable
+        } else {
+            Status::Unsatisfiable
+        },
+        theirs: theirs.status,
+        our_model_verified: our_model.map(|model| expression.is_satisfied_by(&model)),
+        their_model_verified: theirs.model.map(|model| expression.is_satisfied_by(&model)),
+        our_time,
+        their_time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_solver_output() {
+        let output = parse_solver_output("c kissat\ns SATISFIABLE\nv 1 -2\nv 3 0\n").unwrap();
+        assert_eq!(output.status, Status::Satisfiable);
+        let model = output.model.unwrap();
+        assert_eq!((model[&1], model[&2], model[&3]), (true, false, true));
+
+        let output = parse_solver_output("SAT\n-1 2 0\n").unwrap();
+        assert_eq!(output.status, Status::Satisfiable);
+        assert!(!output.model.unwrap()[&1]);
+
+        let output = parse_solver_output("s UNSATISFIABLE\n").unwrap();
+        assert_eq!(output.status, Status::Unsatisfiable);
+        assert!(output.model.is_none());
+
+        assert!(parse_solver_output("c nothing\n").is_err());
+        assert!(parse_solver_output("s MAYBE\n").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crosscheck_with_script() {
+        let script = |output: &str| ExternalSolver {
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("printf '{}'", output),
+                "sh".to_string(),
+            ],
+            result_file: false,
+        };
+
+        // x1 = true, x2 = true, x3 = false
+        let file = "examples/cnf/simple_v3_c2.cnf";
+        let report = crosscheck(file, &script("s SATISFIABLE\\nv 1 2 -3 0\\n")).unwrap();
+        assert!(report.agrees(), "{}", report);
+
+        let report = crosscheck(file, &script("s UNSATISFIABLE\\n")).unwrap();
+        assert!(!report.agrees());
+    }
+}
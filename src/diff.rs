@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::cnf::{Clause, Literal};
+use crate::expression::Expression;
+
+/// The differences between two CNF expressions, computed after normalizing both
+/// (literals sorted and deduplicated within clauses, tautologies and duplicate clauses dropped).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CnfDiff {
+    /// Clauses only present in the second expression.
+    pub added: Vec<Clause>,
+    /// Clauses only present in the first expression.
+    pub removed: Vec<Clause>,
+    /// Pairs of (old, new) clauses that differ by at most two literals, such as a flipped
+    /// polarity or a single added/removed literal.
+    pub changed: Vec<(Clause, Clause)>,
+}
+
+impl CnfDiff {
+    /// Returns true if both expressions are equal after normalization.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for CnfDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for clause in &self.removed {
+            writeln!(f, "- {}", format_clause(clause))?;
+        }
+        for clause in &self.added {
+            writeln!(f, "+ {}", format_clause(clause))?;
+        }
+        for (old, new) in &self.changed {
+            writeln!(f, "~ {} -> {}", format_clause(old), format_clause(new))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn format_clause(clause: &Clause) -> String {
+    let mut literals: Vec<String> = clause.literals().iter().map(|l| l.to_string()).collect();
+    literals.push("0".to_string());
+    literals.join(" ")
+}
+
+/// Computes the clauses added, removed and changed going from `a` to `b`.
+pub fn diff(a: &Expression, b: &Expression) -> CnfDiff {
+    let a_clauses = normalized_clauses(a);
+    let b_clauses = normalized_clauses(b);
+
+    // Both lists are sorted, so the set difference is a single merge pass
+    let mut removed: Vec<Vec<Literal>> = Vec::new();
+    let mut added: Vec<Vec<Literal>> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a_clauses.len() || j < b_clauses.len() {
+        if j == b_clauses.len() || (i < a_clauses.len() && a_clauses[i] < b_clauses[j]) {
+            removed.push(a_clauses[i].clone());
+            i += 1;
+        } else if i == a_clauses.len() || b_clauses[j] < a_clauses[i] {
+            added.push(b_clauses[j].clone());
+            j += 1;
+        } else {
+            i += 1;
+            j += 1;
+        }
+    }
+
+    // Greedily pair up removed and added clauses that are close to each other
+    let mut changed = Vec::new();
+    let mut remaining_removed = Vec::new();
+    for old in removed {
+        let closest = added
+            .iter()
+            .enumerate()
+            .map(|(index, new)| (index, symmetric_difference(&old, new)))
+            .filter(|(index, distance)| *distance <= 2 && shares_variable(&old, &added[*index]))
+            .min_by_key(|(_, distance)| *distance);
+
+        match closest {
+            Some((index, _)) => {
+                let new = added.remove(index);
+                changed.push((Clause::from_literals(&old), Clause::from_literals(&new)));
+            }
+            None => remaining_removed.push(old),
+        }
+    }
+
+    CnfDiff {
+        added: added
+            .iter()
+            .map(|clause| Clause::from_literals(clause))
+            .collect(),
+        removed: remaining_removed
+            .iter()
+            .map(|clause| Clause::from_literals(clause))
+            .collect(),
+        changed,
+    }
+}
+
+fn normalized_clauses(expression: &Expression) -> Vec<Vec<Literal>> {
+    expression
+        .normalize()
+        .get_clauses()
+        .into_iter()
+        .map(|clause| clause.literals().clone())
+        .collect()
+}
+
+fn symmetric_difference(a: &[Literal], b: &[Literal]) -> usize {
+    a.iter().filter(|literal| !b.contains(literal)).count()
+        + b.iter().filter(|literal| !a.contains(literal)).count()
+}
+
+fn shares_variable(a: &[Literal], b: &[Literal]) -> bool {
+    a.iter()
+        .any(|x| b.iter().any(|y| x.unsigned_abs() == y.unsigned_abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_diff_equal_after_normalization() {
+        let a = parse_dimacs_str("p cnf 3 3\n1 2 0\n-3 1 0\n2 -2 0\n");
+        let b = parse_dimacs_str("p cnf 3 3\n1 -3 0\n2 1 0\n2 1 0\n");
+
+        assert!(diff(&a, &b).is_empty());
+    }
+
+    #[test]
+    fn test_diff_added_removed_changed() {
+        let a = parse_dimacs_str("p cnf 6 3\n1 2 0\n3 4 0\n5 6 0\n");
+        let b = parse_dimacs_str("p cnf 6 3\n1 -2 0\n5 6 0\n-1 -5 0\n");
+        let result = diff(&a, &b);
+
+        assert_eq!(result.changed.len(), 1);
+        assert_eq!(result.changed[0].0.literals(), &vec![1, 2]);
+        assert_eq!(result.changed[0].1.literals(), &vec![1, -2]);
+        assert_eq!(result.removed.len(), 1);
+        assert_eq!(result.removed[0].literals(), &vec![3, 4]);
+        assert_eq!(result.added.len(), 1);
+        assert_eq!(result.added[0].literals(), &vec![-1, -5]);
+    }
+}
@@ -0,0 +1,316 @@
+//! Parses large DIMACS files without copying them: the file is memory-mapped, and a byte-level
+//! scanner reads the clauses straight from the mapping, without validating it as UTF-8 or
+//! splitting it into strings.
+//!
+//! The scanner is a fast path for plain DIMACS. At the first line it does not handle exactly as
+//! the [`dimacs_parser`](crate::dimacs_parser) would, such as one that would raise a warning, an
+//! `x` line or a literal out of range, it gives up and the whole file is parsed again by the
+//! line-based parser, so both always return the same expression and warnings. Outside of 64-bit
+//! Unix, the file is read into memory instead of mapped.
+
+use crate::cnf::{Clause, Literal, CNF};
+use crate::dimacs_parser::{
+    add_comment, parse_dimacs_str_with_options, HeaderCheck, ParseError, ParseOptions, ParseWarning,
+};
+use crate::expression::Expression;
+
+/// Parses a CNF expression from a memory-mapped DIMACS file, tolerating the usual deviations
+/// from the format.
+pub fn parse_dimacs_mmap(filename: &str) -> Result<Expression, ParseError> {
+    Ok(parse_dimacs_mmap_with_options(filename, &ParseOptions::default())?.0)
+}
+
+/// Parses a CNF expression from a memory-mapped DIMACS file, returning the warnings recorded
+/// for the input that had to be skipped or repaired.
+pub fn parse_dimacs_mmap_with_options(
+    filename: &str,
+    options: &ParseOptions,
+) -> Result<(Expression, Vec<ParseWarning>), ParseError> {
+    let mapping = Mapping::open(filename).map_err(|error| ParseError::Unreadable {
+        filename: filename.to_string(),
+        message: error.to_string(),
+    })?;
+    parse_dimacs_bytes_with_options(mapping.bytes(), options)
+}
+
+/// Parses a CNF expression from the bytes of a DIMACS file, with the fast path for plain DIMACS.
+pub fn parse_dimacs_bytes_with_options(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<(Expression, Vec<ParseWarning>), ParseError> {
+    if let Some(expression) = scan(bytes) {
+        return Ok((expression, Vec::new()));
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(contents) => parse_dimacs_str_with_options(contents, options),
+        Err(error) => Err(ParseError::Read {
+            line: bytes[..error.valid_up_to()]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count()
+                + 1,
+            message: "stream did not contain valid UTF-8".to_string(),
+        }),
+    }
+}
+
+/// Whether the byte separates tokens, as in `str::split_whitespace`.
+fn is_separator(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | 0x0b | 0x0c)
+}
+
+/// Parses plain DIMACS, returning `None` at anything the line-based parser would do more with.
+fn scan(bytes: &[u8]) -> Option<Expression> {
+    let mut cnf = Expression::new();
+    let mut in_variable_map = false;
+    // The clause being read, which may span lines
+    let mut clause = Clause::new();
+    let mut header = HeaderCheck::default();
+    for line in bytes.split(|byte| *byte == b'\n') {
+        let Some(start) = line.iter().position(|byte| !is_separator(*byte)) else {
+            continue;
+        };
+        match line[start] {
+            b'c' => {
+                let line = std::str::from_utf8(line).ok()?.trim();
+                add_comment(&mut cnf, &line[1..], &mut in_variable_map);
+            }
+            // The counts of the header only need checking, and any problem with them a warning
+            b'p' => {
+                let line = std::str::from_utf8(line).ok()?.trim();
+                if header.read(line).is_some() {
+                    return None;
+                }
+            }
+            b'-' | b'0'..=b'9' => {
+                scan_literals(&line[start..], &mut clause, &mut header, &mut cnf)?
+            }
+            _ => return None,
+        }
+    }
+
+    // A clause without its zero needs a warning
+    clause.literals().is_empty().then_some(cnf)
+}
+
+/// Adds the literals of the line to the clause, adding the clause to the expression at every
+/// terminating zero.
+fn scan_literals(
+    line: &[u8],
+    clause: &mut Clause,
+    header: &mut HeaderCheck,
+    cnf: &mut Expression,
+) -> Option<()> {
+    let mut index = 0;
+    loop {
+        while index < line.len() && is_separator(line[index]) {
+            index += 1;
+        }
+        if index == line.len() {
+            return Some(());
+        }
+
+        let negative = line[index] == b'-';
+        index += negative as usize;
+        let digits = index;
+        let mut value: Literal = 0;
+        while index < line.len() && line[index].is_ascii_digit() {
+            value = value
+                .checked_mul(10)?
+                .checked_add((line[index] - b'0') as Literal)?;
+            index += 1;
+        }
+        if index == digits || (index < line.len() && !is_separator(line[index])) {
+            return None;
+        }
+
+        if value == 0 {
+            // A '-0' needs a warning
+            if negative || header.check_constraint().is_some() {
+                return None;
+            }
+            cnf.add_clause(std::mem::replace(clause, Clause::new()));
+            continue;
+        }
+        let literal = if negative { -value } else { value };
+        if header.check_literal(literal).is_some() {
+            return None;
+        }
+        clause.insert_checked(literal);
+    }
+}
+
+/// The contents of a file, mapped read-only into memory.
+#[cfg(all(unix, target_pointer_width = "64"))]
+struct Mapping {
+    pointer: *mut std::os::raw::c_void,
+    length: usize,
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+mod sys {
+    use std::os::raw::{c_int, c_void};
+
+    pub const PROT_READ: c_int = 1;
+    pub const MAP_PRIVATE: c_int = 2;
+    pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    extern "C" {
+        pub fn mmap(
+            address: *mut c_void,
+            length: usize,
+            protection: c_int,
+            flags: c_int,
+            descriptor: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        pub fn munmap(address: *mut c_void, length: usize) -> c_int;
+    }
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+impl Mapping {
+    fn open(filename: &str) -> std::io::Result<Mapping> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = std::fs::File::open(filename)?;
+        let length = file.metadata()?.len() as usize;
+        // An empty mapping is invalid, and there is nothing to map anyway
+        if length == 0 {
+            return Ok(Mapping {
+                pointer: std::ptr::null_mut(),
+                length,
+            });
+        }
+        // SAFETY: the mapping is private and read-only, and outlives the file descriptor on its
+        // own. Like every reader of the file, it assumes nobody truncates it meanwhile.
+        let pointer = unsafe {
+            sys::mmap(
+                std::ptr::null_mut(),
+                length,
+                sys::PROT_READ,
+                sys::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if pointer == sys::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Mapping { pointer, length })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        if self.length == 0 {
+            return &[];
+        }
+        // SAFETY: the pointer maps `length` readable bytes until the mapping is dropped
+        unsafe { std::slice::from_raw_parts(self.pointer as *const u8, self.length) }
+    }
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if self.length > 0 {
+            // SAFETY: the mapping came from mmap with this length and is not used again
+            unsafe {
+                sys::munmap(self.pointer, self.length);
+            }
+        }
+    }
+}
+
+/// The contents of a file, read into memory where mapping it is not supported.
+#[cfg(not(all(unix, target_pointer_width = "64")))]
+struct Mapping(Vec<u8>);
+
+#[cfg(not(all(unix, target_pointer_width = "64")))]
+impl Mapping {
+    fn open(filename: &str) -> std::io::Result<Mapping> {
+        std::fs::read(filename).map(Mapping)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::{parse_dimacs, parse_dimacs_str};
+
+    #[test]
+    fn test_parse_dimacs_mmap() {
+        for file in [
+            "simple_v3_c2",
+            "quinn",
+            "hole6",
+            "aim-50-1_6-yes1-4",
+            "dubois20",
+            "zebra_v155_c1135",
+        ] {
+            let filename = format!("examples/cnf/{}.cnf", file);
+            let bytes = std::fs::read(&filename).unwrap();
+            assert!(scan(&bytes).is_some(), "{}", file);
+
+            let fast = parse_dimacs_mmap(&filename).unwrap();
+            let slow = parse_dimacs(&filename).unwrap();
+            assert_eq!(fast.get_clauses(), slow.get_clauses(), "{}", file);
+            assert_eq!(fast.get_comments(), slow.get_comments(), "{}", file);
+        }
+        assert!(matches!(
+            parse_dimacs_mmap("examples/cnf/missing.cnf"),
+            Err(ParseError::Unreadable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_dimacs_bytes_falls_back() {
+        // Every input that needs the line-based parser gives the same result as it
+        for contents in [
+            "c meta seed=3\np cnf 2 1\n\t-1   2 0 trailing\r\n",
+            "p cnf 2 2\n1 -2 0\n0\n2 0\n%\n0\n",
+            "p cnf 2 1\n1 x -2 0\n",
+            "p cnf 2 1\n1 -2\n",
+            "p cnf 3 2\n1 -2\n0 -1 3\n  2 0 -3\n",
+            "p cnf 2 2\n1 -2 0 0\n",
+            "p cnf 2 1\n1 -2 0\n2 0\n",
+            "p cnf 1 1\n1 -2 0\n",
+            "p cnf 2 1\n1 -2 -0\n",
+            "p cnf two 1\n1 -2 0\n",
+            "p cnf 3 1\nx1 2 3 0\n",
+            "p cnf 1 1\n+1 0\n",
+            "p cnf 1 1\n40000 0\n",
+            "c caf\u{e9}\n1 0\n",
+            "",
+        ] {
+            let fast =
+                parse_dimacs_bytes_with_options(contents.as_bytes(), &ParseOptions::default());
+            let slow = parse_dimacs_str_with_options(contents, &ParseOptions::default());
+            let ((fast, fast_warnings), (slow, slow_warnings)) = match (fast, slow) {
+                (Ok(fast), Ok(slow)) => (fast, slow),
+                (fast, slow) => {
+                    assert_eq!(fast.err(), slow.err(), "{:?}", contents);
+                    continue;
+                }
+            };
+            assert_eq!(fast.get_clauses(), slow.get_clauses(), "{:?}", contents);
+            assert_eq!(fast.get_comments(), slow.get_comments(), "{:?}", contents);
+            assert_eq!(
+                fast.get_metadata_value("seed"),
+                slow.get_metadata_value("seed")
+            );
+            assert_eq!(fast_warnings, slow_warnings, "{:?}", contents);
+        }
+        assert_eq!(
+            parse_dimacs_str("1\n-2 0 2\nc split\n0").get_clauses(),
+            scan(b"1\n-2 0 2\nc split\n0").unwrap().get_clauses()
+        );
+        assert!(matches!(
+            parse_dimacs_bytes_with_options(b"1 0\n2 \xff 0\n", &ParseOptions::default()),
+            Err(ParseError::Read { line: 2, .. })
+        ));
+    }
+}
@@ -1,39 +1,979 @@
-use crate::cnf::{Clause, Literal, CNF};
+#[cfg(not(feature = "xor"))]
+use crate::cnf::{to_variable, Variable};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::cnf::{parse_literal, Clause, Literal, CNF};
+#[cfg(not(feature = "xor"))]
+use crate::encodings::{add_xor, VariableAllocator};
 use crate::expression::Expression;
+use crate::qbf::{QuantifiedExpression, Quantifier, QuantifierBlock};
+use crate::variable_map::{VARIABLE_MAP_BEGIN, VARIABLE_MAP_END};
+use crate::weighted_expression::{Weight, WeightedExpression};
+
+/// Options controlling how the DIMACS parser treats malformed input.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+    /// Fail on any deviation from plain DIMACS instead of recording a warning.
+    /// By default the parser tolerates the SATLIB conventions (a `%` end marker followed by a
+    /// stray `0` line), DOS line endings and tabs. A token that is not a literal, or is beyond
+    /// the largest variable, fails in either mode, since skipping it would change its clause.
+    pub strict: bool,
+    /// How to report a body that does not fit the `p cnf` header.
+    pub header: HeaderPolicy,
+}
+
+/// How the parser reports a body with more variables or clauses than its `p cnf` header
+/// declares, a literal of variable 0 (`-0`), or an invalid header. Such a file is often
+/// truncated or concatenated by mistake.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HeaderPolicy {
+    /// Trust the body.
+    Ignore,
+    /// Record a warning, or fail in strict mode.
+    #[default]
+    Warn,
+    /// Fail even in tolerant mode.
+    Error,
+}
+
+/// A recoverable problem encountered by the parser in tolerant mode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseWarning {
+    /// The 1-based line number of the problem.
+    pub line: usize,
+    /// The 1-based column of the problem on its line.
+    pub column: usize,
+    pub message: String,
+}
+
+/// A problem that stops the parser: a file it cannot read, a token that is not a literal, or in
+/// strict mode any input that would otherwise be a warning.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    Unreadable {
+        filename: String,
+        message: String,
+    },
+    /// Reading the input failed partway, or it is not UTF-8.
+    Read {
+        /// The 1-based line number that could not be read.
+        line: usize,
+        message: String,
+    },
+    Invalid {
+        /// The 1-based line number of the problem.
+        line: usize,
+        /// The 1-based column of the problem on its line.
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Unreadable { filename, message } => {
+                write!(f, "Cannot read {}: {}", filename, message)
+            }
+            ParseError::Read { line, message } => {
+                write!(f, "Cannot read line {}: {}", line, message)
+            }
+            ParseError::Invalid {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Invalid DIMACS on line {}, column {}: {}",
+                line, column, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
 
-pub fn parse_dimacs(filename: &str) -> Expression {
+/// Parses a CNF expression from a DIMACS file, tolerating the usual deviations from the format.
+pub fn parse_dimacs(filename: &str) -> Result<Expression, ParseError> {
+    Ok(parse_dimacs_with_options(filename, &ParseOptions::default())?.0)
+}
 
-    // Read the file from disk
+/// Parses a CNF expression from a DIMACS file, panicking with the error if there is one.
+pub fn parse_dimacs_or_panic(filename: &str) -> Expression {
+    parse_dimacs(filename).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Parses a CNF expression from the contents of a DIMACS file, panicking with the error if a
+/// token is not a literal, the only input the default options do not tolerate.
+pub fn parse_dimacs_str(contents: &str) -> Expression {
+    parse_dimacs_str_with_options(contents, &ParseOptions::default())
+        .unwrap_or_else(|error| panic!("{}", error))
+        .0
+}
+
+pub fn parse_dimacs_with_options(
+    filename: &str,
+    options: &ParseOptions,
+) -> Result<(Expression, Vec<ParseWarning>), ParseError> {
+    // Stream the file from disk
+    let file = File::open(filename).map_err(|error| ParseError::Unreadable {
+        filename: filename.to_string(),
+        message: error.to_string(),
+    })?;
+    parse_dimacs_reader_with_options(BufReader::new(file), options)
+}
+
+/// Parses a CNF expression from DIMACS read a line at a time, such as from stdin or a socket,
+/// tolerating the usual deviations from the format.
+pub fn parse_dimacs_reader<R: BufRead>(reader: R) -> Result<Expression, ParseError> {
+    Ok(parse_dimacs_reader_with_options(reader, &ParseOptions::default())?.0)
+}
+
+/// Parses a CNF expression from DIMACS read a line at a time, returning the warnings recorded
+/// for the input that had to be skipped or repaired.
+pub fn parse_dimacs_reader_with_options<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<(Expression, Vec<ParseWarning>), ParseError> {
+    let lines = reader.lines().enumerate().map(|(index, line)| {
+        line.map_err(|error| ParseError::Read {
+            line: index + 1,
+            message: error.to_string(),
+        })
+    });
+    parse_dimacs_lines(lines, options)
+}
+
+/// Parses a CNF expression from the contents of a DIMACS file, returning the warnings
+/// recorded for the input that had to be skipped or repaired.
+pub fn parse_dimacs_str_with_options(
+    contents: &str,
+    options: &ParseOptions,
+) -> Result<(Expression, Vec<ParseWarning>), ParseError> {
+    parse_dimacs_lines(contents.lines().map(Ok), options)
+}
+
+/// Parses the lines of a DIMACS file, which may fail to be read.
+fn parse_dimacs_lines<L: AsRef<str>>(
+    lines: impl Iterator<Item = Result<L, ParseError>>,
+    options: &ParseOptions,
+) -> Result<(Expression, Vec<ParseWarning>), ParseError> {
     let mut cnf = Expression::new();
-    let file = std::fs::read_to_string(filename).unwrap();
+    let mut warnings = Vec::new();
+    let mut warn = |line: usize, column: usize, message: String| {
+        if options.strict {
+            return Err(ParseError::Invalid {
+                line,
+                column,
+                message,
+            });
+        }
+        warnings.push(ParseWarning {
+            line,
+            column,
+            message,
+        });
+        Ok(())
+    };
 
     // Read each line of the file
-    for line in file.lines() {
-        // If the line starts with 'c', then it is a comment, so skip it
-        if line.starts_with('c') || line.is_empty() {
+    let mut in_variable_map = false;
+    let mut xor_lines: Vec<Vec<Literal>> = Vec::new();
+    // The clause being read, and the position after its last token until its zero
+    let mut clause = Clause::new();
+    let mut unterminated: Option<(usize, usize)> = None;
+    let mut header = HeaderCheck::default();
+    let mut lines = lines.enumerate();
+    while let Some((index, raw_line)) = lines.next() {
+        let line_number = index + 1;
+        let raw_line = raw_line?;
+        let raw_line = raw_line.as_ref();
+        let line = raw_line.trim();
+        // The 1-based column of a token of the line
+        let column = |token: &str| column_of(raw_line, token);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // If the line starts with 'c', then it is a comment, which is kept on the expression
+        if let Some(comment) = line.strip_prefix('c') {
+            add_comment(&mut cnf, comment, &mut in_variable_map);
             continue;
         }
 
         // If the line starts with 'p', then it is a problem line
+        if line.starts_with('p') {
+            if let Some(message) = header.read(line) {
+                report_header(options, &mut warn, line_number, column(line), message)?;
+            }
+            continue;
+        }
+
+        // SATLIB files end with a '%' line, usually followed by a stray '0'
+        if line.starts_with('%') {
+            warn(
+                line_number,
+                column(line),
+                "Found '%' end marker, ignoring the rest of the file".to_string(),
+            )?;
+            let mut trailing = 0;
+            for (_, rest) in lines.by_ref() {
+                let rest = rest?;
+                let rest = rest.as_ref().trim();
+                trailing += (!rest.is_empty() && rest != "0") as usize;
+            }
+            if trailing > 0 {
+                warn(
+                    line_number,
+                    column(line),
+                    format!("Ignored {} lines after the end marker", trailing),
+                )?;
+            }
+            break;
+        }
+
+        // An 'x' line is a parity constraint in the extension of CryptoMiniSat: an odd number
+        // of its literals hold. Unlike a clause, it ends with its line
+        if let Some(rest) = line.strip_prefix('x') {
+            let mut literals = Vec::new();
+            let mut terminated = false;
+            for token in rest.split_whitespace() {
+                match parse_literal_strict(raw_line, line_number, token)? {
+                    0 => {
+                        terminated = true;
+                        break;
+                    }
+                    value => {
+                        if let Some(message) = header.check_literal(value) {
+                            report_header(options, &mut warn, line_number, column(token), message)?;
+                        }
+                        literals.push(value);
+                    }
+                }
+            }
+            if !terminated {
+                let end = raw_line.trim_end().chars().count() + 1;
+                warn(line_number, end, "Missing the terminating 0".to_string())?;
+            }
+            if let Some(message) = header.check_constraint() {
+                report_header(options, &mut warn, line_number, column(line), message)?;
+            }
+            xor_lines.push(literals);
+            continue;
+        }
+
+        // Otherwise, the tokens are the literals of clauses, which may span lines, each ending
+        // with a zero
+        for token in line.split_whitespace() {
+            match parse_literal_strict(raw_line, line_number, token)? {
+                0 => {
+                    // A '-0' is more likely a corrupted literal than a terminator
+                    if token.starts_with('-') {
+                        let message = "Found a literal of variable 0".to_string();
+                        report_header(options, &mut warn, line_number, column(token), message)?;
+                    }
+                    if let Some(message) = header.check_constraint() {
+                        report_header(options, &mut warn, line_number, column(token), message)?;
+                    }
+                    cnf.add_clause(std::mem::replace(&mut clause, Clause::new()));
+                    unterminated = None;
+                    continue;
+                }
+                value => {
+                    if let Some(message) = header.check_literal(value) {
+                        report_header(options, &mut warn, line_number, column(token), message)?;
+                    }
+                    clause.insert_checked(value);
+                }
+            }
+            unterminated = Some((line_number, column(token) + token.chars().count()));
+        }
+    }
+
+    // The last clause may be missing its zero
+    if let Some((line, column)) = unterminated {
+        warn(line, column, "Missing the terminating 0".to_string())?;
+        if let Some(message) = header.check_constraint() {
+            report_header(options, &mut warn, line, column, message)?;
+        }
+        cnf.add_clause(clause);
+    }
+    add_xor_lines(&mut cnf, xor_lines);
+
+    Ok((cnf, warnings))
+}
+
+/// Returns true if the token is an integer, however large.
+fn is_integer(token: &str) -> bool {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// The counts of the `p cnf` header, checked against the body as it is read.
+#[derive(Default)]
+pub(crate) struct HeaderCheck {
+    /// The numbers of variables and clauses the header declares, if it was read.
+    counts: Option<(usize, usize)>,
+    num_constraints: usize,
+    /// Whether the excess variables and clauses were reported, which happens once for each.
+    reported_variable: bool,
+    reported_constraint: bool,
+}
+
+impl HeaderCheck {
+    /// Reads the counts of the problem line, returning a problem to report if it is invalid.
+    pub(crate) fn read(&mut self, line: &str) -> Option<String> {
+        let mut parts = line.split_whitespace().skip(1); // Skip the 'p'
+        let counts = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("cnf"), Some(variables), Some(clauses), None) => {
+                variables.parse().ok().zip(clauses.parse().ok())
+            }
+            _ => None,
+        };
+        self.counts = counts;
+        match counts {
+            Some(_) => None,
+            None => Some(format!("Invalid problem line '{}'", line)),
+        }
+    }
+
+    /// Returns a problem to report if the literal's variable is beyond those declared.
+    pub(crate) fn check_literal(&mut self, literal: Literal) -> Option<String> {
+        let (num_variables, _) = self.counts?;
+        let variable = literal.unsigned_abs() as usize;
+        if variable <= num_variables || self.reported_variable {
+            return None;
+        }
+        self.reported_variable = true;
+        Some(format!(
+            "Variable {} exceeds the {} declared in the header",
+            variable, num_variables
+        ))
+    }
+
+    /// Counts a clause or parity constraint, returning a problem to report if it is beyond
+    /// those declared.
+    pub(crate) fn check_constraint(&mut self) -> Option<String> {
+        self.num_constraints += 1;
+        let (_, num_clauses) = self.counts?;
+        if self.num_constraints <= num_clauses || self.reported_constraint {
+            return None;
+        }
+        self.reported_constraint = true;
+        Some(format!(
+            "Found more than the {} clauses declared in the header",
+            num_clauses
+        ))
+    }
+}
+
+/// Reports a mismatch between the header and the body as the options ask.
+fn report_header(
+    options: &ParseOptions,
+    warn: &mut impl FnMut(usize, usize, String) -> Result<(), ParseError>,
+    line: usize,
+    column: usize,
+    message: String,
+) -> Result<(), ParseError> {
+    match options.header {
+        HeaderPolicy::Ignore => Ok(()),
+        HeaderPolicy::Warn => warn(line, column, message),
+        HeaderPolicy::Error => Err(ParseError::Invalid {
+            line,
+            column,
+            message,
+        }),
+    }
+}
+
+/// Adds the parity constraints of the `x` lines to the expression, as native constraints, or
+/// without the `xor` feature as clauses over auxiliary variables after those of the file.
+fn add_xor_lines(cnf: &mut Expression, xor_lines: Vec<Vec<Literal>>) {
+    #[cfg(feature = "xor")]
+    for literals in xor_lines {
+        cnf.add_xor(&literals, true);
+    }
+
+    #[cfg(not(feature = "xor"))]
+    {
+        let num_variables = xor_lines
+            .iter()
+            .flatten()
+            .map(|literal| to_variable(*literal))
+            .fold(cnf.get_max_variable(), Variable::max);
+        let mut allocator = VariableAllocator::new(num_variables);
+        for literals in xor_lines {
+            add_xor(cnf, &mut allocator, &literals, true);
+        }
+    }
+}
+
+/// Adds the comment of a line, after its 'c', to the expression as a comment or metadata.
+pub(crate) fn add_comment(cnf: &mut Expression, comment: &str, in_variable_map: &mut bool) {
+    let comment = comment.strip_prefix([' ', '\t']).unwrap_or(comment);
+
+    // The variable map block is re-emitted by the writer from a VariableMap instead
+    if comment.trim() == VARIABLE_MAP_BEGIN {
+        *in_variable_map = true;
+    }
+    if !*in_variable_map {
+        match parse_metadata(comment) {
+            Some((key, value)) => cnf.set_metadata(key, value),
+            None => cnf.add_comment(comment.to_string()),
+        }
+    }
+    if comment.trim() == VARIABLE_MAP_END {
+        *in_variable_map = false;
+    }
+}
+
+/// Parses a `meta key=value` comment into its key and value.
+fn parse_metadata(comment: &str) -> Option<(&str, &str)> {
+    let (key, value) = comment.trim().strip_prefix("meta ")?.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((key, value.trim()))
+}
+
+/// Returns the 1-based column of a token of the line.
+fn column_of(line: &str, token: &str) -> usize {
+    let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
+
+/// Returns the error of an invalid token of the line.
+fn invalid_token(line: &str, line_number: usize, token: &str, message: String) -> ParseError {
+    ParseError::Invalid {
+        line: line_number,
+        column: column_of(line, token),
+        message,
+    }
+}
+
+/// Parses a literal, failing if it is invalid or beyond the largest variable.
+fn parse_literal_strict(
+    line: &str,
+    line_number: usize,
+    token: &str,
+) -> Result<Literal, ParseError> {
+    parse_literal(token).ok_or_else(|| {
+        let message = if is_integer(token) {
+            format!(
+                "Literal '{}' is beyond the largest variable {}",
+                token,
+                Literal::MAX
+            )
+        } else {
+            format!("Invalid literal '{}'", token)
+        };
+        invalid_token(line, line_number, token, message)
+    })
+}
+
+/// Parses the literals of a clause, stopping at the terminating zero.
+fn parse_clause<'a>(
+    literals: impl Iterator<Item = &'a str>,
+    line: &str,
+    line_number: usize,
+) -> Result<Clause, ParseError> {
+    let mut clause = Clause::new();
+    for literal in literals {
+        let value = parse_literal_strict(line, line_number, literal)?;
+        if value == 0 {
+            break;
+        }
+        clause.insert_checked(value);
+    }
+
+    Ok(clause)
+}
+
+/// Reads a whole file for the parsers of the other formats.
+fn read_file(filename: &str) -> Result<String, ParseError> {
+    std::fs::read_to_string(filename).map_err(|error| ParseError::Unreadable {
+        filename: filename.to_string(),
+        message: error.to_string(),
+    })
+}
+
+/// Parses a quantified boolean formula from a QDIMACS file.
+pub fn parse_qdimacs(filename: &str) -> Result<QuantifiedExpression, ParseError> {
+    parse_qdimacs_str(&read_file(filename)?)
+}
+
+/// Parses a quantified boolean formula in the QDIMACS format: a DIMACS file whose clauses are
+/// preceded by quantifier lines (`a 1 2 0` for universal, `e 3 4 0` for existential blocks).
+pub fn parse_qdimacs_str(contents: &str) -> Result<QuantifiedExpression, ParseError> {
+    let mut prefix: Vec<QuantifierBlock> = Vec::new();
+    let mut matrix = Expression::new();
+
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = raw_line.trim();
+        if line.starts_with('c') || line.starts_with('p') || line.is_empty() {
+            continue;
+        }
+
+        let quantifier = if line.starts_with('a') {
+            Some(Quantifier::Forall)
+        } else if line.starts_with('e') {
+            Some(Quantifier::Exists)
+        } else {
+            None
+        };
+
+        match quantifier {
+            Some(quantifier) => {
+                let mut variables = Vec::new();
+                for token in line.split_whitespace().skip(1) {
+                    match parse_literal_strict(raw_line, line_number, token)? {
+                        0 => break,
+                        value if value > 0 => variables.push(value.unsigned_abs()),
+                        _ => {
+                            let message = format!("Invalid quantified variable '{}'", token);
+                            return Err(invalid_token(raw_line, line_number, token, message));
+                        }
+                    }
+                }
+
+                // Adjacent blocks with the same quantifier are merged into one
+                match prefix.last_mut() {
+                    Some(block) if block.quantifier == quantifier => {
+                        block.variables.extend(variables)
+                    }
+                    _ => prefix.push(QuantifierBlock {
+                        quantifier,
+                        variables,
+                    }),
+                }
+            }
+            None => matrix.add_clause(parse_clause(
+                line.split_whitespace(),
+                raw_line,
+                line_number,
+            )?),
+        }
+    }
+
+    Ok(QuantifiedExpression::new(prefix, matrix))
+}
+
+/// Parses a weighted MaxSAT instance from a WCNF file.
+/// See [`parse_wcnf_str`] for the supported formats.
+pub fn parse_wcnf(filename: &str) -> Result<WeightedExpression, ParseError> {
+    parse_wcnf_str(&read_file(filename)?)
+}
+
+/// Parses a weighted MaxSAT instance, auto-detecting between the two WCNF dialects:
+///
+/// - The classic format, which starts with a `p wcnf <variables> <clauses> [<top>]` line and
+///   prefixes every clause with its weight. Clauses weighing at least `top` are hard.
+/// - The MaxSAT Evaluation 2022+ format, which has no problem line, marks hard clauses with a
+///   leading `h`, and prefixes soft clauses with their weight.
+/// - Unweighted MaxSAT in plain DIMACS, with a `p cnf` problem line, where every clause is soft
+///   with a weight of 1.
+pub fn parse_wcnf_str(contents: &str) -> Result<WeightedExpression, ParseError> {
+    let mut expression = WeightedExpression::new();
+
+    // Only set if the file was in the classic format and declared a top weight
+    let mut top: Option<Weight> = None;
+    let mut unweighted = false;
+
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = raw_line.trim();
+        if line.starts_with('c') || line.is_empty() {
+            continue;
+        }
+        let weight = |token: &str, kind: &str| {
+            token.parse::<Weight>().map_err(|_| {
+                let message = format!("Invalid {} weight '{}'", kind, token);
+                invalid_token(raw_line, line_number, token, message)
+            })
+        };
+
+        // A problem line means the file is in the classic format
         if line.starts_with('p') {
             let mut parts = line.split_whitespace();
             let _ = parts.next(); // Skip the 'p'
-            let _ = parts.next(); // Skip the 'cnf'
+            unweighted = parts.next() == Some("cnf");
+            let _ = parts.next(); // Skip the number of variables
+            let _ = parts.next(); // Skip the number of clauses
+            top = parts.next().map(|value| weight(value, "top")).transpose()?;
             continue;
         }
 
-        // Otherwise, the line is a clause
-        let mut clause = Clause::new();
-        for literal in line.split_whitespace() {
-            let value = literal.parse::<Literal>().unwrap();
-            if value == 0 {
-                break;
+        if unweighted {
+            let clause = parse_clause(line.split_whitespace(), raw_line, line_number)?;
+            expression.add_soft_clause(clause, 1);
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let first = parts.next().unwrap();
+        let hard = first == "h";
+        let weight = if hard { 0 } else { weight(first, "clause")? };
+
+        let clause = parse_clause(parts, raw_line, line_number)?;
+
+        match top {
+            _ if hard => expression.add_hard_clause(clause),
+            Some(top) if weight >= top => expression.add_hard_clause(clause),
+            _ => expression.add_soft_clause(clause, weight),
+        }
+    }
+
+    Ok(expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpll::solve_dpll;
+
+    #[test]
+    fn test_parse_dimacs_tolerates_satlib_conventions() {
+        let contents = "c SATLIB style\r\np cnf 3 2\r\n1\t-2 0\r\n2 3 0\r\n%\r\n0\r\n\r\n";
+        let (expression, warnings) =
+            parse_dimacs_str_with_options(contents, &ParseOptions::default()).unwrap();
+
+        assert_eq!(expression.get_clauses().len(), 2);
+        assert_eq!(expression.get_clauses()[0].literals(), &vec![1, -2]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 5);
+    }
+
+    #[test]
+    fn test_parse_dimacs_empty_clause() {
+        // A lone zero outside the SATLIB footer is an empty clause
+        let (mut expression, warnings) =
+            parse_dimacs_str_with_options("p cnf 1 2\n1 0\n0\n", &ParseOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(expression.get_clauses().len(), 2);
+        assert_eq!(solve_dpll(&mut expression), None);
+    }
+
+    #[test]
+    fn test_parse_dimacs_reader() {
+        let contents = "c streamed\np cnf 3 2\n1 -2 0\n2 3 0\n%\n0\n";
+        let expression = parse_dimacs_reader(contents.as_bytes()).unwrap();
+        assert_eq!(
+            expression.get_clauses(),
+            parse_dimacs_str(contents).get_clauses()
+        );
+        assert_eq!(expression.get_comments(), &vec!["streamed".to_string()]);
+
+        // A line that is not UTF-8 cannot be read
+        let contents: &[u8] = b"1 -2 0\n2 \xff 0\n";
+        match parse_dimacs_reader(contents) {
+            Err(ParseError::Read { line, .. }) => assert_eq!(line, 2),
+            result => panic!("Expected a read error, got {:?}", result.err()),
+        }
+    }
+
+    #[test]
+    fn test_parse_dimacs_multi_line_clauses() {
+        let contents = "p cnf 3 3\n1 2\nc between the lines\n-3 0 2\n3 0 -1 -2\n0\n";
+        let (expression, warnings) = parse_dimacs_str_with_options(
+            contents,
+            &ParseOptions {
+                strict: true,
+                ..ParseOptions::default()
+            },
+        )
+        .unwrap();
+        let clauses: Vec<_> = expression
+            .get_clauses()
+            .iter()
+            .map(|clause| clause.literals().clone())
+            .collect();
+        assert_eq!(clauses, vec![vec![1, 2, -3], vec![2, 3], vec![-1, -2]]);
+        assert!(warnings.is_empty());
+
+        let (expression, warnings) = parse_dimacs_with_options(
+            "examples/cnf/zebra_v155_c1135.cnf",
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(expression.get_clauses().len(), 1135);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_dimacs_checks_header() {
+        let positions = |contents: &str, header: HeaderPolicy| {
+            let options = ParseOptions {
+                header,
+                ..ParseOptions::default()
+            };
+            let (_, warnings) = parse_dimacs_str_with_options(contents, &options).unwrap();
+            warnings
+                .iter()
+                .map(|warning| (warning.line, warning.column))
+                .collect::<Vec<_>>()
+        };
+        let contents = "p cnf 2 1\n1 -3 0\n2 -0\n-4 0";
+        assert_eq!(
+            positions(contents, HeaderPolicy::Warn),
+            vec![(2, 3), (3, 3), (3, 3)]
+        );
+        assert!(positions(contents, HeaderPolicy::Ignore).is_empty());
+        assert_eq!(
+            positions("p cnf 2\n1 0\n", HeaderPolicy::Warn),
+            vec![(1, 1)]
+        );
+
+        let options = ParseOptions {
+            header: HeaderPolicy::Error,
+            ..ParseOptions::default()
+        };
+        match parse_dimacs_str_with_options(contents, &options) {
+            Err(ParseError::Invalid { line, message, .. }) => {
+                assert_eq!(line, 2);
+                assert_eq!(message, "Variable 3 exceeds the 2 declared in the header");
             }
-            clause.insert_checked(value);
+            result => panic!("Expected an error, got {:?}", result.err()),
         }
+    }
 
-        cnf.add_clause(clause);
+    #[test]
+    fn test_parse_dimacs_keeps_comments_and_metadata() {
+        let contents =
+            "c generated by hand\nc meta seed = 42\nc meta expected=sat\nc\np cnf 1 1\n1 0\n";
+        let expression = parse_dimacs_str(contents);
+
+        assert_eq!(
+            expression.get_comments(),
+            &vec!["generated by hand".to_string(), String::new()]
+        );
+        assert_eq!(expression.get_metadata_value("seed"), Some("42"));
+        assert_eq!(expression.get_metadata_value("expected"), Some("sat"));
+    }
+
+    #[test]
+    fn test_parse_dimacs_rejects_invalid_tokens() {
+        // Even tolerant parsing fails rather than drop a literal from its clause
+        for (contents, expected) in [
+            ("p cnf 2 1\n1 x -2 0\n", (2, 3)),
+            ("p cnf 3 1\nx1 2y 3 0\n", (2, 4)),
+        ] {
+            match parse_dimacs_str_with_options(contents, &ParseOptions::default()) {
+                Err(ParseError::Invalid {
+                    line,
+                    column,
+                    message,
+                }) => {
+                    assert_eq!((line, column), expected, "{}", contents);
+                    assert!(message.starts_with("Invalid literal"), "{}", message);
+                }
+                result => panic!(
+                    "Expected an error for {:?}, got {:?}",
+                    contents,
+                    result.err()
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_dimacs_large_variables() {
+        // Literals just beyond the width of a literal, whichever that is
+        let beyond = (Literal::MAX as i128 + 1).to_string();
+        let contents = "p cnf 30000 1\n1 -30000 0\n";
+        let expression = parse_dimacs_str(contents);
+        assert_eq!(expression.get_max_variable(), 30000);
+
+        let contents = format!("p cnf 30000 2\n1 -30000 0\n2 -{} 0\n", beyond);
+        match parse_dimacs_str_with_options(&contents, &ParseOptions::default()) {
+            Err(ParseError::Invalid {
+                line,
+                column,
+                message,
+            }) => {
+                assert_eq!((line, column), (3, 3));
+                assert!(message.contains("beyond the largest variable"));
+            }
+            result => panic!("Expected an error, got {:?}", result.err()),
+        }
+    }
+
+    #[test]
+    fn test_parse_literal_bounds() {
+        let (largest, beyond) = (Literal::MAX, Literal::MAX as i128 + 1);
+        let strict = ParseOptions {
+            strict: true,
+            header: HeaderPolicy::Ignore,
+        };
+        for contents in [
+            format!("1 {} 0\n", beyond),
+            format!("1 {} 0\n", Literal::MIN),
+        ] {
+            assert!(matches!(
+                parse_dimacs_str_with_options(&contents, &strict),
+                Err(ParseError::Invalid { column: 3, .. })
+            ));
+        }
+
+        // The other formats fail on them too
+        let weighted = parse_wcnf_str(&format!("h 1 -{} 0\n", largest)).unwrap();
+        assert_eq!(
+            weighted.get_hard_clauses()[0].literals(),
+            &vec![1, -largest]
+        );
+        for contents in [
+            format!("h 1 {} 0\n", beyond),
+            format!("3 -{} 0\n", beyond),
+            "p cnf 2 1\n1 x2 0\n".to_string(),
+            "18446744073709551616 1 0\n".to_string(),
+            "p wcnf 1 1 -1\n1 1 0\n".to_string(),
+        ] {
+            assert!(
+                matches!(parse_wcnf_str(&contents), Err(ParseError::Invalid { .. })),
+                "{}",
+                contents
+            );
+        }
+
+        let quantified = parse_qdimacs_str(&format!("a {} 0\n1 0\n", largest)).unwrap();
+        assert_eq!(
+            quantified.get_prefix()[0].variables,
+            vec![largest.unsigned_abs()]
+        );
+        for contents in [
+            format!("a {} 0\n", beyond),
+            "e -1 0\n".to_string(),
+            format!("a 1 0\n{} 0\n", Literal::MIN),
+        ] {
+            assert!(
+                matches!(
+                    parse_qdimacs_str(&contents),
+                    Err(ParseError::Invalid { .. })
+                ),
+                "{}",
+                contents
+            );
+        }
+        assert!(matches!(
+            parse_wcnf("examples/cnf/missing.wcnf"),
+            Err(ParseError::Unreadable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_dimacs_xor_lines() {
+        // 1 ⊕ 2 ⊕ ¬3 ⊕ 4 and 2 ⊕ 4, natively or through auxiliary variables
+        let contents = "p cnf 4 3\nx1 2 -3 4 0\nx 2 4 0\n-1 0\n";
+        let expression = parse_dimacs_str(contents);
+        let mut num_models = 0;
+        for mask in 0..1u32 << 4 {
+            let value = |variable: u32| mask >> (variable - 1) & 1 == 1;
+            let mut fixed = expression.clone();
+            for variable in 1..=4 {
+                let literal = variable as Literal;
+                let mut clause = Clause::new();
+                clause.insert(if value(variable) { literal } else { -literal });
+                fixed.add_clause(clause);
+            }
+            let expected =
+                !value(1) && value(1) ^ value(2) ^ !value(3) ^ value(4) && value(2) ^ value(4);
+            assert_eq!(solve_dpll(&mut fixed).is_some(), expected, "{:b}", mask);
+            num_models += expected as usize;
+        }
+        assert_eq!(num_models, 2);
     }
 
-    cnf
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_dimacs_strict() {
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+        for (contents, expected) in [
+            ("p cnf 2 1\n1 -2 0\n%\n0\n", (3, 1)),
+            ("p cnf 2 1\n  1 -2 0q 0\n", (2, 8)),
+            ("p cnf 2 2\n1 -2 0\n1 2\n", (3, 4)),
+        ] {
+            match parse_dimacs_str_with_options(contents, &strict) {
+                Err(ParseError::Invalid { line, column, .. }) => {
+                    assert_eq!((line, column), expected, "{}", contents)
+                }
+                result => panic!(
+                    "Expected an error for {:?}, got {:?}",
+                    contents,
+                    result.err()
+                ),
+            }
+        }
+
+        // Without the strict option, the missing zero is only a warning
+        let (expression, warnings) =
+            parse_dimacs_str_with_options("1 -2 0\n1 2\n", &ParseOptions::default()).unwrap();
+        assert_eq!(expression.get_clauses().len(), 2);
+        assert_eq!((warnings[0].line, warnings[0].column), (2, 4));
+
+        match parse_dimacs("examples/cnf/missing.cnf") {
+            Err(error @ ParseError::Unreadable { .. }) => assert!(error
+                .to_string()
+                .starts_with("Cannot read examples/cnf/missing.cnf")),
+            result => panic!("Expected the file to be unreadable, got {:?}", result.err()),
+        }
+    }
+
+    #[test]
+    fn test_parse_qdimacs() {
+        let contents = "p cnf 4 2\na 1 0\na 2 0\ne 3 4 0\n1 3 0\n-2 -4 0\n";
+        let expression = parse_qdimacs_str(contents).unwrap();
+
+        assert_eq!(expression.get_prefix().len(), 2);
+        assert_eq!(expression.get_prefix()[0].quantifier, Quantifier::Forall);
+        assert_eq!(expression.get_prefix()[0].variables, vec![1, 2]);
+        assert_eq!(expression.get_prefix()[1].variables, vec![3, 4]);
+        assert_eq!(expression.get_matrix().get_clauses().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_wcnf_classic() {
+        let contents = "c classic\np wcnf 2 3 10\n10 1 2 0\n3 -1 0\n5 -2 0\n";
+        let expression = parse_wcnf_str(contents).unwrap();
+
+        assert_eq!(expression.get_hard_clauses().len(), 1);
+        assert_eq!(expression.get_soft_clauses().len(), 2);
+        assert_eq!(expression.total_soft_weight(), 8);
+    }
+
+    #[test]
+    fn test_parse_wcnf_classic_without_top() {
+        let contents = "p wcnf 2 2\n1 1 2 0\n2 -1 0\n";
+        let expression = parse_wcnf_str(contents).unwrap();
+
+        assert!(expression.get_hard_clauses().is_empty());
+        assert_eq!(expression.get_soft_clauses().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_wcnf_unweighted() {
+        let contents = "p cnf 2 3\n1 2 0\n-1 0\n-2 0\n";
+        let expression = parse_wcnf_str(contents).unwrap();
+
+        assert!(expression.get_hard_clauses().is_empty());
+        assert_eq!(expression.get_soft_clauses()[0].0.literals(), &vec![1, 2]);
+        assert_eq!(expression.total_soft_weight(), 3);
+    }
+
+    #[test]
+    fn test_parse_wcnf_new_format() {
+        let contents = "c new format\nh 1 2 0\nh -1 -2 0\n4 1 0\n7 2 0\n";
+        let expression = parse_wcnf_str(contents).unwrap();
+
+        assert_eq!(expression.get_hard_clauses().len(), 2);
+        assert_eq!(expression.get_soft_clauses()[0].1, 4);
+        assert_eq!(expression.get_soft_clauses()[1].1, 7);
+        assert_eq!(expression.get_max_variable(), 2);
+    }
+}
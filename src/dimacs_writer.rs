@@ -0,0 +1,157 @@
+use std::io::Write;
+
+use crate::cnf::Literal;
+use crate::expression::Expression;
+use crate::variable_map::VariableMap;
+
+/// Writes the expression in the DIMACS CNF format, starting with its comments and metadata. The
+/// parity constraints follow the clauses as the `x` lines of CryptoMiniSat.
+pub fn write_dimacs<W: Write>(expression: &Expression, writer: &mut W) -> std::io::Result<()> {
+    for comment in expression.get_comments() {
+        if comment.is_empty() {
+            writeln!(writer, "c")?;
+        } else {
+            writeln!(writer, "c {}", comment)?;
+        }
+    }
+    for (key, value) in expression.get_metadata() {
+        writeln!(writer, "c meta {}={}", key, value)?;
+    }
+
+    let clauses = expression.get_clauses();
+    let xor_constraints = expression.get_xor_constraints();
+    writeln!(
+        writer,
+        "p cnf {} {}",
+        expression.get_max_variable(),
+        clauses.len() + xor_constraints.len()
+    )?;
+    for clause in &clauses {
+        for literal in clause.literals() {
+            write!(writer, "{} ", literal)?;
+        }
+        writeln!(writer, "0")?;
+    }
+    for (variables, parity) in &xor_constraints {
+        write!(writer, "x")?;
+        for (i, variable) in variables.iter().enumerate() {
+            // An even parity is written by negating the first variable
+            let literal = *variable as Literal;
+            let literal = if i == 0 && !parity { -literal } else { literal };
+            write!(writer, "{} ", literal)?;
+        }
+        writeln!(writer, "0")?;
+    }
+
+    Ok(())
+}
+
+/// Writes the expression in the DIMACS CNF format, preceded by a comment block recording the
+/// variable map, so that models of the written formula can be mapped back to the original variables.
+pub fn write_dimacs_with_map<W: Write>(
+    expression: &Expression,
+    map: &VariableMap,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    writeln!(writer, "{}", map.to_dimacs_comments())?;
+    write_dimacs(expression, writer)
+}
+
+/// Writes the expression to a DIMACS file on disk, optionally including the variable map.
+pub fn write_dimacs_file(
+    expression: &Expression,
+    map: Option<&VariableMap>,
+    file_name: &str,
+) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(file_name)?);
+    match map {
+        Some(map) => write_dimacs_with_map(expression, map, &mut file)?,
+        None => write_dimacs(expression, &mut file)?,
+    }
+    file.flush()
+}
+
+/// Renders the expression as a DIMACS string.
+pub fn to_dimacs_string(expression: &Expression) -> String {
+    let mut buffer = Vec::new();
+    write_dimacs(expression, &mut buffer).expect("Writing to memory cannot fail");
+    String::from_utf8(buffer).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cnf::{Assignment, Clause};
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_write_dimacs_round_trip() {
+        let expression = parse_dimacs_str("p cnf 3 2\n1 -3 0\n2 3 -1 0\n");
+        let written = to_dimacs_string(&expression);
+
+        assert_eq!(written, "p cnf 3 2\n1 -3 0\n2 3 -1 0\n");
+        assert_eq!(
+            parse_dimacs_str(&written).get_clauses(),
+            expression.get_clauses()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xor")]
+    fn test_write_dimacs_round_trip_xor_constraints() {
+        let expression = parse_dimacs_str("p cnf 4 3\n1 -3 0\nx1 -2 3 0\nx 2 4 0\n");
+        assert_eq!(
+            expression.get_xor_constraints(),
+            vec![(vec![1, 2, 3], false), (vec![2, 4], true)]
+        );
+        let written = to_dimacs_string(&expression);
+
+        assert_eq!(written, "p cnf 4 3\n1 -3 0\nx-1 2 3 0\nx2 4 0\n");
+        assert_eq!(
+            parse_dimacs_str(&written).get_xor_constraints(),
+            expression.get_xor_constraints()
+        );
+    }
+
+    #[test]
+    fn test_write_dimacs_keeps_comments_and_metadata() {
+        let contents = "c from a generator\nc meta seed=7\np cnf 2 1\n1 2 0\n";
+        let expression = parse_dimacs_str(contents);
+        assert_eq!(to_dimacs_string(&expression), contents);
+
+        let reparsed = parse_dimacs_str(&to_dimacs_string(&expression));
+        assert_eq!(reparsed.get_metadata(), expression.get_metadata());
+    }
+
+    #[test]
+    fn test_write_dimacs_with_map_round_trip() {
+        let expression = parse_dimacs_str("p cnf 9 2\n4 -9 0\n9 7 0\n");
+        let (compacted, mut map) = VariableMap::compact(&expression);
+
+        // Pretend variable 12 was eliminated and must be true whenever 4 is false
+        let mut clause = Clause::new();
+        clause.insert(12);
+        clause.insert(4);
+        map.add_reconstruction_clause(clause);
+
+        let mut buffer = Vec::new();
+        write_dimacs_with_map(&compacted, &map, &mut buffer).unwrap();
+        let written = String::from_utf8(buffer).unwrap();
+
+        assert!(written.contains("p cnf 3 2\n1 -3 0\n3 2 0\n"));
+
+        let read_map = VariableMap::from_dimacs_comments(&written).unwrap();
+        assert_eq!(read_map, map);
+
+        let mut model = Assignment::new();
+        model.insert(1, false);
+        model.insert(2, true);
+        model.insert(3, false);
+
+        let original = read_map.map_model_back(&model);
+        assert!(!original[&4]);
+        assert!(original[&7]);
+        assert!(!original[&9]);
+        assert!(original[&12]);
+    }
+}
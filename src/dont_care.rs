@@ -0,0 +1,132 @@
+//! Finds the variables of a model whose values do not matter.
+//!
+//! The solver returns total models: variables left unassigned once every clause is satisfied are
+//! simply set to true. This analysis tells such arbitrary values apart from the ones the model
+//! relies on, so downstream consumers know which parts of a model they are free to change.
+
+use crate::cnf::{to_variable, Assignment, Literal, Variable};
+use crate::expression::Expression;
+
+/// A model split into the values that satisfy the expression and the values that are arbitrary.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PartialModel {
+    /// The values that matter: every clause has a true literal among them.
+    pub assignment: Assignment,
+    /// The variables up to the largest variable of the expression whose values are arbitrary:
+    /// the expression stays satisfied whatever values they take together, including variables
+    /// that occur in no clause. Sorted in increasing order.
+    pub dont_cares: Vec<Variable>,
+}
+
+/// Splits the model into the values that satisfy the expression and the don't-care variables.
+/// Returns `None` if the model does not satisfy the expression.
+///
+/// The variables are dropped greedily in increasing order, as long as every clause keeps a true
+/// literal, so the result is minimal (no other variable can be dropped) but not necessarily the
+/// smallest possible.
+pub fn find_dont_cares(expression: &Expression, model: &Assignment) -> Option<PartialModel> {
+    let max_variable = expression.get_max_variable() as usize;
+    let mut clauses: Vec<Vec<Literal>> = Vec::new();
+    let mut occurrences: Vec<Vec<usize>> = vec![Vec::new(); max_variable + 1];
+    let mut constrained = vec![false; max_variable + 1];
+    for clause in expression.get_clauses() {
+        let mut literals = clause.literals().clone();
+        literals.sort_unstable();
+        literals.dedup();
+        for literal in &literals {
+            constrained[to_variable(*literal) as usize] = true;
+        }
+        if literals.iter().any(|literal| literals.contains(&-literal)) {
+            // Tautologies are satisfied by any values
+            continue;
+        }
+
+        for literal in &literals {
+            occurrences[to_variable(*literal) as usize].push(clauses.len());
+        }
+        clauses.push(literals);
+    }
+
+    let is_true = |literal: Literal| model.get(&to_variable(literal)) == Some(&(literal > 0));
+    let mut num_true: Vec<usize> = clauses
+        .iter()
+        .map(|literals| literals.iter().filter(|l| is_true(**l)).count())
+        .collect();
+    if num_true.contains(&0) {
+        return None;
+    }
+
+    let mut partial = PartialModel {
+        assignment: Assignment::new(),
+        dont_cares: Vec::new(),
+    };
+    for variable in 1..=max_variable {
+        if !constrained[variable] {
+            partial.dont_cares.push(variable as Variable);
+            continue;
+        }
+        let Some(value) = model.get(&(variable as Variable)).copied() else {
+            // Every clause of the variable is satisfied by another one
+            partial.dont_cares.push(variable as Variable);
+            continue;
+        };
+
+        // The clauses satisfied by this variable, which must stay satisfied without it
+        let supported: Vec<usize> = occurrences[variable]
+            .iter()
+            .copied()
+            .filter(|clause| {
+                clauses[*clause]
+                    .iter()
+                    .any(|l| to_variable(*l) as usize == variable && (*l > 0) == value)
+            })
+            .collect();
+        if supported.iter().all(|clause| num_true[*clause] >= 2) {
+            for clause in supported {
+                num_true[clause] -= 1;
+            }
+            partial.dont_cares.push(variable as Variable);
+        } else {
+            partial.assignment.insert(variable as Variable, value);
+        }
+    }
+
+    Some(partial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::solver::solve;
+
+    #[test]
+    fn test_find_dont_cares() {
+        // Variable 4 occurs in no clause, and either of 1 and 2 satisfies the first clause
+        let expression = parse_dimacs_str("p cnf 5 3\n1 2 0\n-3 0\n5 -5 0\n");
+        let model: Assignment = [(1, true), (2, true), (3, false), (5, true)].into();
+
+        let partial = find_dont_cares(&expression, &model).unwrap();
+        assert_eq!(partial.dont_cares, vec![1, 4, 5]);
+        assert_eq!(partial.assignment, [(2, true), (3, false)].into());
+
+        let invalid: Assignment = [(1, false), (2, false), (3, false), (5, true)].into();
+        assert_eq!(find_dont_cares(&expression, &invalid), None);
+    }
+
+    #[test]
+    fn test_dont_cares_can_take_any_value() {
+        let expression = Expression::from_cnf_file("examples/cnf/quinn.cnf").unwrap();
+        let model = solve(expression.clone(), false, true).unwrap();
+        let partial = find_dont_cares(&expression, &model).unwrap();
+        assert!(!partial.dont_cares.is_empty());
+
+        for value in [false, true] {
+            let mut flipped = partial.assignment.clone();
+            for variable in &partial.dont_cares {
+                flipped.insert(*variable, value);
+            }
+            assert!(expression.is_satisfied_by(&flipped));
+        }
+    }
+}
@@ -1,58 +1,500 @@
+use std::hash::Hasher;
+
 use crate::{
-    cnf::{ActionState, Assignment, CNF},
+    cnf::{ActionState, Assignment, ClauseId, Literal, Variable, CNF},
+    control::{Progress, SolverControl},
     expression::Expression,
+    hash::FnvHasher,
+    profile::{start_timer, Phase, PhaseTimes},
+    trail::Trail,
 };
 
+/// A step taken by the DPLL search.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchEvent {
+    /// The unit clause with this id was used to assign its literal.
+    UnitPropagation(ClauseId),
+    /// A cardinality constraint with as many true literals as its bound made the literal true.
+    CardinalityPropagation(Literal),
+    /// The parity constraints together implied the literal.
+    XorPropagation(Literal),
+    /// The pure literal was assigned true.
+    PureLiteral(Literal),
+    /// The search guessed a value for the variable.
+    Decision(Variable, bool),
+    /// An empty clause was derived, so the search backtracks.
+    Conflict,
+}
+
+/// Records the sequence of steps of a solve as a running digest, and optionally as a full list.
+/// The digest hashes every event with FNV-1a, so it is the same on every platform.
+///
+/// The search is deterministic for a given expression and heuristic, so the digest changes only if
+/// the search itself does. Comparing digests before and after a refactoring shows whether it
+/// (unintentionally) changed the search behavior.
+#[derive(Clone, Debug)]
+pub struct SearchTrace {
+    hasher: FnvHasher,
+    events: Option<Vec<SearchEvent>>,
+    pub num_propagations: usize,
+    pub num_pure_literals: usize,
+    pub num_decisions: usize,
+    pub num_conflicts: usize,
+}
+
+impl SearchTrace {
+    /// Creates a trace that keeps only the digest and counters.
+    pub fn new() -> SearchTrace {
+        SearchTrace {
+            hasher: FnvHasher::new(),
+            events: None,
+            num_propagations: 0,
+            num_pure_literals: 0,
+            num_decisions: 0,
+            num_conflicts: 0,
+        }
+    }
+
+    /// Creates a trace that also keeps every event, for debugging where two searches diverge.
+    pub fn with_events() -> SearchTrace {
+        SearchTrace {
+            events: Some(Vec::new()),
+            ..SearchTrace::new()
+        }
+    }
+
+    pub fn record(&mut self, event: SearchEvent) {
+        match event {
+            SearchEvent::UnitPropagation(clause_id) => {
+                self.num_propagations += 1;
+                self.hasher.write_u8(1);
+                self.hasher.write(&clause_id.to_le_bytes());
+            }
+            SearchEvent::PureLiteral(literal) => {
+                self.num_pure_literals += 1;
+                self.hasher.write_u8(2);
+                self.hasher.write(&literal.to_le_bytes());
+            }
+            SearchEvent::Decision(variable, value) => {
+                self.num_decisions += 1;
+                self.hasher.write_u8(3);
+                self.hasher.write(&variable.to_le_bytes());
+                self.hasher.write_u8(value as u8);
+            }
+            SearchEvent::Conflict => {
+                self.num_conflicts += 1;
+                self.hasher.write_u8(4);
+            }
+            SearchEvent::CardinalityPropagation(literal) => {
+                self.num_propagations += 1;
+                self.hasher.write_u8(5);
+                self.hasher.write(&literal.to_le_bytes());
+            }
+            SearchEvent::XorPropagation(literal) => {
+                self.num_propagations += 1;
+                self.hasher.write_u8(6);
+                self.hasher.write(&literal.to_le_bytes());
+            }
+        }
+
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+
+    pub fn digest(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    /// Returns the recorded events, if the trace was created with [`SearchTrace::with_events`].
+    pub fn get_events(&self) -> Option<&Vec<SearchEvent>> {
+        self.events.as_ref()
+    }
+}
+
+impl Default for SearchTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Observes a DPLL search as it runs, see [`solve_dpll_with_hook`].
+pub trait SearchHook {
+    /// Called after every step of the search, with the assignment once it reflects the step: a
+    /// decision or propagation is on the trail, and a conflict has not been backtracked yet.
+    fn on_event(&mut self, event: SearchEvent, trail: &Trail);
+
+    /// Called after the search backtracks, with the assignment that is left, which is a prefix
+    /// of the previous one.
+    fn on_backtrack(&mut self, _trail: &Trail) {}
+}
+
+/// Limits on the amount of search, after which the solver gives up.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SearchBudget {
+    pub max_decisions: Option<usize>,
+    pub max_conflicts: Option<usize>,
+}
+
+/// The outcome of a search with a budget.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SearchResult {
+    Satisfiable(Assignment),
+    Unsatisfiable,
+    /// The budget ran out before the search finished.
+    BudgetExhausted,
+}
+
 pub fn solve_dpll(cnf: &mut Expression) -> Option<Assignment> {
-    // Track where we are in the action stack
-    let action_state: ActionState = cnf.get_action_state();
+    Search::new(None, SearchBudget::default(), None, None).run(cnf)
+}
+
+/// Solves the expression like [`solve_dpll`], recording each step of the search in the trace.
+pub fn solve_dpll_traced(cnf: &mut Expression, trace: &mut SearchTrace) -> Option<Assignment> {
+    Search::new(Some(trace), SearchBudget::default(), None, None).run(cnf)
+}
+
+/// Solves the expression like [`solve_dpll`], reporting every step and every backtrack to the
+/// hook.
+pub fn solve_dpll_with_hook(cnf: &mut Expression, hook: &mut dyn SearchHook) -> Option<Assignment> {
+    let mut search = Search::new(None, SearchBudget::default(), None, None);
+    search.hook = Some(hook);
+    search.run(cnf)
+}
+
+/// Solves the expression like [`solve_dpll_with_hook`], giving up once the budget is exhausted.
+pub fn solve_dpll_with_hook_and_budget(
+    cnf: &mut Expression,
+    hook: &mut dyn SearchHook,
+    budget: &SearchBudget,
+) -> SearchResult {
+    let mut search = Search::new(None, *budget, None, None);
+    search.hook = Some(hook);
+    search.solve(cnf)
+}
+
+/// Solves the expression, giving up once the budget is exhausted. The trace records the steps
+/// taken until then, and if given, the time spent in each phase is added to `phases`.
+/// If the budget runs out, the expression is left partially assigned.
+pub fn solve_dpll_with_budget(
+    cnf: &mut Expression,
+    trace: &mut SearchTrace,
+    budget: &SearchBudget,
+    phases: Option<&mut PhaseTimes>,
+) -> SearchResult {
+    Search::new(Some(trace), *budget, phases, None).solve(cnf)
+}
+
+/// Solves the expression like [`solve_dpll_with_budget`], taking the budget from the settings of
+/// the control and following their changes, and reporting progress to the control. Cancelling
+/// the control exhausts the budget.
+pub fn solve_dpll_with_control(
+    cnf: &mut Expression,
+    trace: &mut SearchTrace,
+    control: &SolverControl,
+    phases: Option<&mut PhaseTimes>,
+) -> SearchResult {
+    let budget = control.get_settings().budget;
+    Search::new(Some(trace), budget, phases, Some(control)).solve(cnf)
+}
+
+struct Search<'a> {
+    trace: Option<&'a mut SearchTrace>,
+    hook: Option<&'a mut dyn SearchHook>,
+    budget: SearchBudget,
+    phases: Option<&'a mut PhaseTimes>,
+    control: Option<&'a SolverControl>,
+    /// The version of the settings of the control last read.
+    control_version: Option<u64>,
+    progress_interval: Option<usize>,
+    num_decisions: usize,
+    num_conflicts: usize,
+    /// The weight of the refuted subtrees of the assignment tree, where a conflict after `d`
+    /// decisions refutes a subtree of weight `2^-d`. It reaches 1 when the search is exhausted.
+    coverage: f64,
+    exhausted: bool,
+}
 
-    // Try to do as much inference as we can before branching
-    while cnf.is_inference_possible() {
-        // Next, remove all of the unit clauses
-        while cnf.remove_unit_clause().is_some() {}
+impl<'a> Search<'a> {
+    fn new(
+        trace: Option<&'a mut SearchTrace>,
+        budget: SearchBudget,
+        phases: Option<&'a mut PhaseTimes>,
+        control: Option<&'a SolverControl>,
+    ) -> Search<'a> {
+        Search {
+            trace,
+            hook: None,
+            budget,
+            phases,
+            control,
+            control_version: None,
+            progress_interval: None,
+            num_decisions: 0,
+            num_conflicts: 0,
+            coverage: 0.0,
+            exhausted: false,
+        }
+    }
+
+    fn solve(&mut self, cnf: &mut Expression) -> SearchResult {
+        match self.run(cnf) {
+            Some(assignment) => SearchResult::Satisfiable(assignment),
+            None if self.exhausted => SearchResult::BudgetExhausted,
+            None => SearchResult::Unsatisfiable,
+        }
+    }
+
+    fn record(&mut self, event: SearchEvent, cnf: &Expression) {
+        match event {
+            SearchEvent::Decision(_, _) => {
+                self.num_decisions += 1;
+                if let (Some(control), Some(interval)) = (self.control, self.progress_interval) {
+                    if self.num_decisions.is_multiple_of(interval.max(1)) {
+                        control.set_progress(Progress {
+                            num_decisions: self.num_decisions,
+                            num_conflicts: self.num_conflicts,
+                            coverage: self.coverage,
+                        });
+                    }
+                }
+            }
+            SearchEvent::Conflict => {
+                self.num_conflicts += 1;
+                let depth = cnf.get_trail().get_decision_level();
+                self.coverage += 0.5f64.powi(depth as i32);
+            }
+            _ => {}
+        }
+
+        if let Some(trace) = &mut self.trace {
+            trace.record(event);
+        }
+        if let Some(hook) = &mut self.hook {
+            hook.on_event(event, cnf.get_trail());
+        }
+    }
+
+    fn restore(&mut self, cnf: &mut Expression, state: ActionState) {
+        cnf.restore_action_state(state);
+        if let Some(hook) = &mut self.hook {
+            hook.on_backtrack(cnf.get_trail());
+        }
+    }
+
+    /// Returns true (and remembers it) if the search has used up its budget.
+    fn is_exhausted(&mut self) -> bool {
+        if let Some(control) = self.control {
+            let version = control.get_version();
+            if self.control_version != Some(version) {
+                let settings = control.get_settings();
+                self.budget = settings.budget;
+                self.progress_interval = settings.progress_interval;
+                self.control_version = Some(version);
+            }
+            if control.is_cancelled() {
+                self.exhausted = true;
+            }
+        }
+
+        let over = |limit: Option<usize>, count: usize| limit.is_some_and(|limit| count >= limit);
+        if over(self.budget.max_decisions, self.num_decisions)
+            || over(self.budget.max_conflicts, self.num_conflicts)
+        {
+            self.exhausted = true;
+        }
+        self.exhausted
+    }
+
+    fn start_timer(&self) -> Option<std::time::Instant> {
+        start_timer(self.phases.is_some())
+    }
+
+    fn stop_timer(&mut self, phase: Phase, start: Option<std::time::Instant>) {
+        if let Some(phases) = &mut self.phases {
+            phases.stop_timer(phase, start);
+        }
+    }
+
+    fn run(&mut self, cnf: &mut Expression) -> Option<Assignment> {
+        // Track where we are in the action stack
+        let action_state: ActionState = cnf.get_action_state();
+
+        // Try to do as much inference as we can before branching
+        let timer = self.start_timer();
+        while cnf.is_inference_possible() {
+            // Next, remove all of the unit clauses
+            while let Some(clause_id) = cnf.remove_unit_clause() {
+                self.record(SearchEvent::UnitPropagation(clause_id), cnf);
+            }
+            while let Some(literal) = cnf.remove_cardinality_implication() {
+                self.record(SearchEvent::CardinalityPropagation(literal), cnf);
+            }
+            while let Some(literal) = cnf.remove_xor_implication() {
+                self.record(SearchEvent::XorPropagation(literal), cnf);
+            }
+
+            // If the CNF is satisfied, then we are done
+            if cnf.is_unsatisfiable() {
+                self.stop_timer(Phase::Propagation, timer);
+                self.record(SearchEvent::Conflict, cnf);
+                // Restore the action state (undo branching)
+                let timer = self.start_timer();
+                self.restore(cnf, action_state);
+                self.stop_timer(Phase::Backtracking, timer);
+                return None;
+            }
+
+            while let Some(literal) = cnf.remove_pure_literal() {
+                self.record(SearchEvent::PureLiteral(literal), cnf);
+            }
+        }
+        self.stop_timer(Phase::Propagation, timer);
+
+        if cnf.is_satisfied() {
+            return Some(cnf.construct_assignment());
+        }
 
-        // If the CNF is satisfied, then we are done
         if cnf.is_unsatisfiable() {
-            // Restore the action state (undo branching)
-            cnf.restore_action_state(action_state);
+            self.record(SearchEvent::Conflict, cnf);
+            let timer = self.start_timer();
+            self.restore(cnf, action_state);
+            self.stop_timer(Phase::Backtracking, timer);
+            return None;
+        }
+
+        // Pick some variable to branch on ("guess") to keep searching
+        let timer = self.start_timer();
+        let branch_action_state = cnf.get_action_state();
+        let (branch_variable, branch_value) = cnf.get_branch_variable();
+        self.stop_timer(Phase::Decision, timer);
+
+        // Try the first branch value
+        if self.is_exhausted() {
             return None;
         }
+        let timer = self.start_timer();
+        cnf.branch_variable(branch_variable, branch_value);
+        self.stop_timer(Phase::Decision, timer);
+        self.record(SearchEvent::Decision(branch_variable, branch_value), cnf);
 
-        while cnf.remove_pure_literal().is_some() {}
+        let branch_result = self.run(cnf);
+        if branch_result.is_some() {
+            return branch_result;
+        }
+
+        let timer = self.start_timer();
+        self.restore(cnf, branch_action_state);
+        self.stop_timer(Phase::Backtracking, timer);
+
+        // Try the other branch value
+        if self.is_exhausted() {
+            return None;
+        }
+        let timer = self.start_timer();
+        cnf.branch_variable(branch_variable, !branch_value);
+        self.stop_timer(Phase::Decision, timer);
+        self.record(SearchEvent::Decision(branch_variable, !branch_value), cnf);
+
+        let branch_result = self.run(cnf);
+        if branch_result.is_some() {
+            return branch_result;
+        }
+
+        let timer = self.start_timer();
+        self.restore(cnf, action_state);
+        self.stop_timer(Phase::Backtracking, timer);
+        None
     }
+}
 
-    if cnf.is_satisfied() {
-        return Some(cnf.construct_assignment());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::generate;
+    use crate::expression::SolverHeuristic;
+
+    fn trace(name: &str, heuristic: SolverHeuristic) -> SearchTrace {
+        let mut expression = generate(name).unwrap().expression;
+        expression.set_heuristic(heuristic);
+        let mut trace = SearchTrace::with_events();
+        solve_dpll_traced(&mut expression, &mut trace);
+        trace
     }
 
-    if cnf.is_unsatisfiable() {
-        cnf.restore_action_state(action_state);
-        return None;
+    #[test]
+    fn test_search_trace_is_deterministic() {
+        for heuristic in [
+            SolverHeuristic::MostLiteralOccurances,
+            SolverHeuristic::MostVariableOccurances,
+            SolverHeuristic::MinimizeClauseLength,
+        ] {
+            let a = trace("random-3sat-40-4.26", heuristic);
+            let b = trace("random-3sat-40-4.26", heuristic);
+
+            assert_eq!(a.digest(), b.digest());
+            assert_eq!(a.get_events(), b.get_events());
+            assert!(a.num_decisions > 0 && a.num_conflicts > 0);
+        }
     }
 
-    // Pick some variable to branch on ("guess") to keep searching
-    let branch_action_state = cnf.get_action_state();
-    let (branch_variable, branch_value) = cnf.get_branch_variable();
+    #[test]
+    fn test_search_budget() {
+        let mut expression = generate("hole6").unwrap().expression;
+        let budget = SearchBudget {
+            max_decisions: None,
+            max_conflicts: Some(10),
+        };
+        let mut trace = SearchTrace::new();
 
-    // Try the first branch value
-    cnf.branch_variable(branch_variable, branch_value);
+        let result = solve_dpll_with_budget(&mut expression, &mut trace, &budget, None);
+        assert_eq!(result, SearchResult::BudgetExhausted);
+        assert_eq!(trace.num_conflicts, 10);
 
-    let branch_result = solve_dpll(cnf);
-    if branch_result.is_some() {
-        return branch_result;
+        let mut expression = generate("pigeonhole-3").unwrap().expression;
+        let result = solve_dpll_with_budget(
+            &mut expression,
+            &mut SearchTrace::new(),
+            &SearchBudget::default(),
+            None,
+        );
+        assert_eq!(result, SearchResult::Unsatisfiable);
     }
 
-    cnf.restore_action_state(branch_action_state);
-
-    // Try the other branch value
-    cnf.branch_variable(branch_variable, !branch_value);
+    #[test]
+    fn test_search_coverage() {
+        let mut expression = generate("hole6").unwrap().expression;
+        let budget = SearchBudget {
+            max_decisions: None,
+            max_conflicts: Some(100),
+        };
+        let mut search = Search::new(None, budget, None, None);
+        assert_eq!(search.solve(&mut expression), SearchResult::BudgetExhausted);
+        assert!(search.coverage > 0.0 && search.coverage < 1.0);
 
-    let branch_result = solve_dpll(cnf);
-    if branch_result.is_some() {
-        return branch_result;
+        // The refuted subtrees make up the whole tree once the search is exhausted
+        let mut expression = generate("hole6").unwrap().expression;
+        let mut search = Search::new(None, SearchBudget::default(), None, None);
+        assert_eq!(search.solve(&mut expression), SearchResult::Unsatisfiable);
+        assert_eq!(search.coverage, 1.0);
     }
 
-    cnf.restore_action_state(action_state);
-    None
-}
\ No newline at end of file
+    #[test]
+    fn test_search_trace_counts_events() {
+        let trace = trace("pigeonhole-3", SolverHeuristic::MostLiteralOccurances);
+        let events = trace.get_events().unwrap();
+
+        let decisions = events
+            .iter()
+            .filter(|event| matches!(event, SearchEvent::Decision(_, _)))
+            .count();
+        assert_eq!(decisions, trace.num_decisions);
+        assert_eq!(
+            events.len(),
+            trace.num_propagations
+                + trace.num_pure_literals
+                + trace.num_decisions
+                + trace.num_conflicts
+        );
+    }
+}
@@ -0,0 +1,544 @@
+//! DRAT proofs of unsatisfiability, with a checker and an in-memory trimmer in the spirit of
+//! drat-trim.
+//!
+//! A proof is a sequence of lemmas added to, and clauses deleted from, the formula, ending in the
+//! empty clause. [`check_drat`] verifies every lemma in order by reverse unit propagation (or as
+//! a resolution asymmetric tautology). Trimming checks the proof backwards from the empty clause
+//! instead, verifying only the lemmas that are actually used, and returns the reduced proof
+//! together with the unsatisfiable core: the original clauses the refutation depends on.
+//!
+//! Solvers write their proofs through a [`ProofLogger`] as they search, so the proof of a long
+//! run goes straight to a file instead of being kept in memory.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::cnf::{parse_literal, Literal};
+use crate::expression::Expression;
+use crate::hash::FnvHashMap;
+use crate::propagation::Propagator;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ProofStep {
+    /// Adds a lemma, which must be RUP or RAT (on its first literal) with respect to the clauses
+    /// present before it.
+    Add(Vec<Literal>),
+    /// Deletes a clause of the formula or a previously added lemma.
+    Delete(Vec<Literal>),
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Proof {
+    pub steps: Vec<ProofStep>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DratError {
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// The proof neither contains the empty clause nor ends in a formula refuted by propagation.
+    NoRefutation,
+    /// The lemma of this (0-based) step is neither RUP nor RAT.
+    InvalidLemma {
+        step: usize,
+    },
+    /// The deadline passed before the check was done.
+    TimedOut,
+}
+
+impl fmt::Display for DratError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DratError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            DratError::NoRefutation => write!(f, "the proof does not derive the empty clause"),
+            DratError::InvalidLemma { step } => {
+                write!(f, "the lemma of step {} is neither RUP nor RAT", step + 1)
+            }
+            DratError::TimedOut => write!(f, "the check timed out"),
+        }
+    }
+}
+
+impl std::error::Error for DratError {}
+
+impl Proof {
+    pub fn new() -> Proof {
+        Proof::default()
+    }
+
+    pub fn add(&mut self, literals: Vec<Literal>) {
+        self.steps.push(ProofStep::Add(literals));
+    }
+
+    pub fn delete(&mut self, literals: Vec<Literal>) {
+        self.steps.push(ProofStep::Delete(literals));
+    }
+
+    /// Parses a proof in the textual DRAT format: one lemma per line terminated by `0`, with
+    /// deletions prefixed by `d`, and comment lines starting with `c`.
+    pub fn parse(contents: &str) -> Result<Proof, DratError> {
+        let mut proof = Proof::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('c') {
+                continue;
+            }
+
+            let error = |message: String| DratError::Parse {
+                line: index + 1,
+                message,
+            };
+            let (deletion, rest) = match line.strip_prefix('d') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            let mut literals = Vec::new();
+            let mut terminated = false;
+            for token in rest.split_whitespace() {
+                if terminated {
+                    return Err(error(format!("unexpected '{}' after the final 0", token)));
+                }
+                match parse_literal(token) {
+                    Some(0) => terminated = true,
+                    Some(literal) => literals.push(literal),
+                    None => return Err(error(format!("invalid literal '{}'", token))),
+                }
+            }
+            if !terminated {
+                return Err(error("the clause is not terminated by 0".to_string()));
+            }
+
+            if deletion {
+                proof.delete(literals);
+            } else {
+                proof.add(literals);
+            }
+        }
+
+        Ok(proof)
+    }
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            let (prefix, literals) = match step {
+                ProofStep::Add(literals) => ("", literals),
+                ProofStep::Delete(literals) => ("d ", literals),
+            };
+            write!(f, "{}", prefix)?;
+            for literal in literals {
+                write!(f, "{} ", literal)?;
+            }
+            writeln!(f, "0")?;
+        }
+
+        Ok(())
+    }
+}
+
+struct LoggerState {
+    sink: Box<dyn Write + Send>,
+    /// The first error of a write, reported by [`ProofLogger::finish`].
+    error: Option<io::Error>,
+}
+
+/// Writes the steps of a proof in the textual DRAT format, as a solver takes them. Clones write
+/// to the same sink.
+///
+/// Writing must not interrupt the search, so a failed write only stops the logging, and the error
+/// is returned by [`ProofLogger::finish`].
+#[derive(Clone)]
+pub struct ProofLogger {
+    state: Arc<Mutex<LoggerState>>,
+}
+
+impl fmt::Debug for ProofLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofLogger").finish_non_exhaustive()
+    }
+}
+
+impl ProofLogger {
+    pub fn new(sink: impl Write + Send + 'static) -> ProofLogger {
+        ProofLogger {
+            state: Arc::new(Mutex::new(LoggerState {
+                sink: Box::new(sink),
+                error: None,
+            })),
+        }
+    }
+
+    /// Creates (or truncates) the file and writes the proof to it.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<ProofLogger> {
+        Ok(ProofLogger::new(BufWriter::new(File::create(path)?)))
+    }
+
+    fn write(&self, prefix: &str, literals: &[Literal]) {
+        let mut state = self.state.lock().unwrap();
+        if state.error.is_some() {
+            return;
+        }
+        let mut line = String::from(prefix);
+        for literal in literals {
+            line.push_str(&literal.to_string());
+            line.push(' ');
+        }
+        line.push_str("0\n");
+        if let Err(error) = state.sink.write_all(line.as_bytes()) {
+            state.error = Some(error);
+        }
+    }
+
+    pub fn add(&self, literals: &[Literal]) {
+        self.write("", literals);
+    }
+
+    pub fn delete(&self, literals: &[Literal]) {
+        self.write("d ", literals);
+    }
+
+    /// Flushes the sink, returning the first error of a write or of the flush.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match state.error.take() {
+            Some(error) => Err(error),
+            None => state.sink.flush(),
+        }
+    }
+}
+
+/// The result of trimming a proof.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrimmedProof {
+    /// The used lemmas (and their deletions), ending in the empty clause. It is a valid proof for
+    /// the core alone.
+    pub proof: Proof,
+    /// The indices into [`Expression::get_clauses`] of the original clauses used by the proof.
+    pub core: Vec<usize>,
+}
+
+impl TrimmedProof {
+    /// Returns the unsatisfiable core as an expression of its own.
+    pub fn core_expression(&self, expression: &Expression) -> Expression {
+        let clauses = expression.get_clauses();
+        Expression::from_clauses(
+            self.core
+                .iter()
+                .map(|index| clauses[*index].clone())
+                .collect(),
+        )
+    }
+}
+
+fn sorted(literals: &[Literal]) -> Vec<Literal> {
+    let mut key = literals.to_vec();
+    key.sort_unstable();
+    key.dedup();
+    key
+}
+
+/// Checks the proof of unsatisfiability of the expression, and removes the lemmas the refutation
+/// does not need. Steps after the first empty clause are ignored; if the proof has no empty clause,
+/// the clauses remaining at its end must be refuted by unit propagation.
+///
+/// Deletions are applied as written, including deletions of unit clauses (which drat-trim ignores
+/// by default), and deletions of clauses that are not present are skipped.
+pub fn trim(expression: &Expression, proof: &Proof) -> Result<TrimmedProof, DratError> {
+    let originals = expression.get_clauses();
+    let mut propagator = Propagator::new();
+    let mut ids_by_clause: FnvHashMap<Vec<Literal>, Vec<usize>> = FnvHashMap::default();
+    for clause in &originals {
+        let id = propagator.add_clause(clause.literals());
+        ids_by_clause
+            .entry(sorted(clause.literals()))
+            .or_default()
+            .push(id);
+    }
+
+    // Replay the proof up to the empty clause, recording the clause each step added or deleted
+    let mut step_ids: Vec<Option<usize>> = Vec::new();
+    let mut empty_clause = None;
+    for step in &proof.steps {
+        match step {
+            ProofStep::Add(literals) => {
+                let id = propagator.add_clause(literals);
+                ids_by_clause.entry(sorted(literals)).or_default().push(id);
+                step_ids.push(Some(id));
+                if literals.is_empty() {
+                    empty_clause = Some(id);
+                    break;
+                }
+            }
+            ProofStep::Delete(literals) => {
+                let id = ids_by_clause
+                    .get_mut(&sorted(literals))
+                    .and_then(|ids| ids.pop());
+                if let Some(id) = id {
+                    propagator.set_active(id, false);
+                }
+                step_ids.push(id);
+            }
+        }
+    }
+    let implicit = empty_clause.is_none();
+    let empty_clause = match empty_clause {
+        Some(id) => id,
+        None => {
+            let id = propagator.add_clause(&[]);
+            step_ids.push(Some(id));
+            id
+        }
+    };
+
+    // Check the used lemmas backwards, undoing each step before checking the lemma it added
+    let mut used = vec![false; propagator.get_num_clauses()];
+    used[empty_clause] = true;
+    for index in (0..step_ids.len()).rev() {
+        let Some(id) = step_ids[index] else {
+            continue;
+        };
+        let lemma: &[Literal] = match proof.steps.get(index) {
+            Some(ProofStep::Delete(_)) => {
+                propagator.set_active(id, true);
+                continue;
+            }
+            Some(ProofStep::Add(literals)) => literals,
+            None => &[],
+        };
+
+        propagator.set_active(id, false);
+        if used[id] {
+            let Some(dependencies) = check_lemma(&mut propagator, lemma) else {
+                return Err(if implicit && id == empty_clause {
+                    DratError::NoRefutation
+                } else {
+                    DratError::InvalidLemma { step: index }
+                });
+            };
+            for id in dependencies {
+                used[id] = true;
+            }
+        }
+    }
+
+    let mut trimmed = Proof::new();
+    for (step, id) in proof.steps.iter().zip(&step_ids) {
+        if id.is_some_and(|id| used[id]) {
+            trimmed.steps.push(step.clone());
+        }
+    }
+    if implicit {
+        trimmed.add(Vec::new());
+    }
+
+    Ok(TrimmedProof {
+        proof: trimmed,
+        core: (0..originals.len()).filter(|id| used[*id]).collect(),
+    })
+}
+
+/// Checks the proof of unsatisfiability of the expression forwards, verifying every lemma against
+/// the clauses present before it, whether the refutation needs it or not. This is stricter than
+/// [`trim`], which only verifies the lemmas the refutation uses, but finds no core. Steps after
+/// the first empty clause are ignored, and a proof without one is handled, and deletions applied,
+/// as by [`trim`].
+pub fn check_drat(expression: &Expression, proof: &Proof) -> Result<(), DratError> {
+    check_drat_with_deadline(expression, proof, None)
+}
+
+/// Checks the proof like [`check_drat`], giving up with [`DratError::TimedOut`] if the deadline
+/// passes first. The deadline is looked at before every step.
+pub fn check_drat_with_deadline(
+    expression: &Expression,
+    proof: &Proof,
+    deadline: Option<Instant>,
+) -> Result<(), DratError> {
+    let mut propagator = Propagator::new();
+    let mut ids_by_clause: FnvHashMap<Vec<Literal>, Vec<usize>> = FnvHashMap::default();
+    for clause in expression.get_clauses() {
+        let id = propagator.add_clause(clause.literals());
+        ids_by_clause
+            .entry(sorted(clause.literals()))
+            .or_default()
+            .push(id);
+    }
+
+    for (index, step) in proof.steps.iter().enumerate() {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(DratError::TimedOut);
+        }
+        match step {
+            ProofStep::Add(literals) => {
+                if check_lemma(&mut propagator, literals).is_none() {
+                    return Err(DratError::InvalidLemma { step: index });
+                }
+                if literals.is_empty() {
+                    return Ok(());
+                }
+                let id = propagator.add_clause(literals);
+                ids_by_clause.entry(sorted(literals)).or_default().push(id);
+            }
+            ProofStep::Delete(literals) => {
+                let id = ids_by_clause
+                    .get_mut(&sorted(literals))
+                    .and_then(|ids| ids.pop());
+                if let Some(id) = id {
+                    propagator.set_active(id, false);
+                }
+            }
+        }
+    }
+
+    match propagator.propagate_units() {
+        Some(_) => Ok(()),
+        None => Err(DratError::NoRefutation),
+    }
+}
+
+/// Returns the clauses used to show the clause is RUP: asserting its negation leads to a conflict
+/// by unit propagation.
+fn check_rup(propagator: &mut Propagator, literals: &[Literal]) -> Option<Vec<usize>> {
+    let conflict = propagator.propagate_units().or_else(|| {
+        literals
+            .iter()
+            .find_map(|literal| propagator.assume(-literal))
+    });
+    conflict.map(|conflict| propagator.analyze(conflict))
+}
+
+/// Checks that the lemma is RUP, or RAT on its first literal, returning the clauses it depends on.
+fn check_lemma(propagator: &mut Propagator, lemma: &[Literal]) -> Option<Vec<usize>> {
+    if let Some(involved) = check_rup(propagator, lemma) {
+        return Some(involved);
+    }
+
+    // Every resolvent on the pivot with an active clause must be RUP
+    let pivot = lemma.first().copied()?;
+    let mut dependencies = Vec::new();
+    for id in 0..propagator.get_num_clauses() {
+        if !propagator.is_active(id) || !propagator.get_clause(id).contains(&-pivot) {
+            continue;
+        }
+
+        let mut resolvent = lemma.to_vec();
+        resolvent.extend(propagator.get_clause(id).iter().filter(|l| **l != -pivot));
+        let involved = check_rup(propagator, &resolvent)?;
+        dependencies.push(id);
+        dependencies.extend(involved);
+    }
+    Some(dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    /// Every clause over three variables, plus a clause the refutation does not need.
+    const CUBE: &str = "p cnf 5 9
+1 2 3 0
+1 2 -3 0
+1 -2 3 0
+1 -2 -3 0
+-1 2 3 0
+-1 2 -3 0
+-1 -2 3 0
+-1 -2 -3 0
+4 5 0
+";
+
+    #[test]
+    fn test_parse_and_write_proof() {
+        let proof = Proof::parse("c a comment\n1 -2 0\nd 1 -2 0\n0\n").unwrap();
+        assert_eq!(
+            proof.steps,
+            vec![
+                ProofStep::Add(vec![1, -2]),
+                ProofStep::Delete(vec![1, -2]),
+                ProofStep::Add(vec![]),
+            ]
+        );
+        assert_eq!(proof.to_string(), "1 -2 0\nd 1 -2 0\n0\n");
+
+        assert_eq!(
+            Proof::parse("1 2\n"),
+            Err(DratError::Parse {
+                line: 1,
+                message: "the clause is not terminated by 0".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_trim_removes_unused_lemmas() {
+        let expression = parse_dimacs_str(CUBE);
+        // The first lemma is RAT but not RUP, the next two are not needed
+        let proof = Proof::parse("1 0\n4 5 1 0\n1 2 0\nd 4 5 1 0\n2 0\n0\n3 0\n").unwrap();
+
+        let trimmed = trim(&expression, &proof).unwrap();
+        assert_eq!(trimmed.proof.to_string(), "1 0\n2 0\n0\n");
+        assert_eq!(trimmed.core, (0..8).collect::<Vec<_>>());
+
+        let core = trimmed.core_expression(&expression);
+        let retrimmed = trim(&core, &trimmed.proof).unwrap();
+        assert_eq!(retrimmed.proof, trimmed.proof);
+    }
+
+    #[test]
+    fn test_trim_rejects_invalid_proofs() {
+        let expression = parse_dimacs_str(CUBE);
+
+        // Without the empty clause, the remaining clauses must be refuted by propagation
+        let trimmed = trim(&expression, &Proof::parse("1 0\n2 0\n").unwrap()).unwrap();
+        assert_eq!(trimmed.proof.to_string(), "1 0\n2 0\n0\n");
+        assert_eq!(
+            trim(&expression, &Proof::parse("1 0\n").unwrap()),
+            Err(DratError::NoRefutation)
+        );
+
+        assert_eq!(
+            trim(&expression, &Proof::parse("4 0\n0\n").unwrap()),
+            Err(DratError::InvalidLemma { step: 1 })
+        );
+        // Deleting the lemma makes the empty clause underivable
+        assert_eq!(
+            trim(&expression, &Proof::parse("1 0\n2 0\nd 2 0\n0\n").unwrap()),
+            Err(DratError::InvalidLemma { step: 3 })
+        );
+    }
+
+    #[test]
+    fn test_check_drat() {
+        let expression = parse_dimacs_str(CUBE);
+        let proof = Proof::parse("1 0\n4 5 1 0\n1 2 0\nd 4 5 1 0\n2 0\n0\n3 0\n").unwrap();
+        assert_eq!(check_drat(&expression, &proof), Ok(()));
+        assert_eq!(
+            check_drat(&expression, &Proof::parse("1 0\n2 0\n").unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            check_drat(&expression, &Proof::parse("1 0\n").unwrap()),
+            Err(DratError::NoRefutation)
+        );
+
+        // The refutation does not need the invalid lemma, so only trimming accepts the proof
+        let proof = Proof::parse("1 0\n-4 0\n2 0\n0\n").unwrap();
+        assert!(trim(&expression, &proof).is_ok());
+        assert_eq!(
+            check_drat(&expression, &proof),
+            Err(DratError::InvalidLemma { step: 1 })
+        );
+        assert_eq!(
+            check_drat_with_deadline(&expression, &proof, Some(Instant::now())),
+            Err(DratError::TimedOut)
+        );
+    }
+}
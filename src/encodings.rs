@@ -0,0 +1,730 @@
+//! Encodings of cardinality constraints, the building blocks of scheduling and assignment
+//! problems, emitted as clauses into an [`Expression`] or an incremental [`Solver`] (see
+//! [`ClauseSink`]).
+//!
+//! The encodings trade clauses for auxiliary variables, which are taken from a
+//! [`VariableAllocator`] so they never collide with the variables of the problem or of other
+//! encodings. For at most one of the literals:
+//!
+//! - [`AmoEncoding::Pairwise`]: a binary clause for every pair, `n(n-1)/2` clauses and no
+//!   auxiliary variables. The best choice up to a handful of literals.
+//! - [`AmoEncoding::Sequential`]: the sequential counter of Sinz, a chain of `n - 1` variables
+//!   telling whether one of the literals so far holds, with `3n - 4` clauses.
+//! - [`AmoEncoding::Commander`]: the commander encoding of Klieber and Kwon, which splits the
+//!   literals into groups of three, encodes every group pairwise, and recursively allows at most
+//!   one group, through a commander variable implied by each literal of its group.
+//! - [`AmoEncoding::Product`]: the product encoding of Chen, which places the literals on a grid
+//!   of about `√n` by `√n`, implies the variables of a literal's row and column, and recursively
+//!   allows at most one row and one column, for about `2n + 4√n` clauses.
+//!
+//! For at most or at least `k`, both encodings count the true literals in unary, with outputs
+//! `o1, o2, ...` where `oj` is implied by `j` of the literals holding. At most `k` is then `¬o(k+1)`,
+//! and at least `k` is at most `n - k` of the negations:
+//!
+//! - A [`Totalizer`] (Bailleux and Boufkhad) sums the literals up a binary tree of unary
+//!   counters, with `O(n log n)` variables and up to `O(n²)` clauses, fewer when the bounds of
+//!   interest are small. It is incremental: the outputs stay in place, so the bound can be
+//!   tightened by adding or assuming a single literal, and more literals can be counted later.
+//! - A [`SortingNetwork`] sorts the literals with the odd-even merge sort of Batcher, with
+//!   `O(n log² n)` comparators of three clauses each, whatever the bound.
+//!
+//! Parity constraints, which take `2^(n - 1)` clauses over their own variables, are split by
+//! [`add_xor`] into a chain of parities of three variables, linked by `n - 3` auxiliary ones.
+
+use crate::cnf::{Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+use crate::solver::Solver;
+
+/// Hands out fresh variables after those of a problem.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VariableAllocator {
+    num_variables: Variable,
+}
+
+impl VariableAllocator {
+    /// Creates an allocator whose first fresh variable follows the variables `1..=num_variables`.
+    pub fn new(num_variables: Variable) -> VariableAllocator {
+        VariableAllocator { num_variables }
+    }
+
+    /// Creates an allocator whose fresh variables follow the variables of the expression.
+    pub fn after(expression: &Expression) -> VariableAllocator {
+        VariableAllocator::new(expression.get_max_variable())
+    }
+
+    /// Returns the number of variables taken so far, including those of the problem.
+    pub fn get_num_variables(&self) -> Variable {
+        self.num_variables
+    }
+
+    /// Returns the positive literal of a fresh variable. Panics if the variables run out.
+    pub fn fresh(&mut self) -> Literal {
+        assert!(
+            self.num_variables < Literal::MAX as Variable,
+            "No variables are left to allocate"
+        );
+        self.num_variables += 1;
+        self.num_variables as Literal
+    }
+
+    /// Returns the positive literals of `count` fresh variables, in increasing order.
+    pub fn fresh_many(&mut self, count: usize) -> Vec<Literal> {
+        (0..count).map(|_| self.fresh()).collect()
+    }
+}
+
+/// How [`add_at_most_one`] encodes the constraint, see the module documentation.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AmoEncoding {
+    Pairwise,
+    #[default]
+    Sequential,
+    Commander,
+    Product,
+}
+
+/// Up to this many literals, the recursive encodings fall back to the pairwise one, which is
+/// then no larger.
+const PAIRWISE_LIMIT: usize = 4;
+
+/// Takes the clauses of the encodings.
+pub trait ClauseSink {
+    fn emit(&mut self, literals: &[Literal]);
+}
+
+impl ClauseSink for Expression {
+    fn emit(&mut self, literals: &[Literal]) {
+        self.add_clause(Clause::from_literals(literals));
+    }
+}
+
+/// Encodes into the incremental solver, whose variables grow to cover the auxiliary ones.
+impl ClauseSink for Solver {
+    fn emit(&mut self, literals: &[Literal]) {
+        self.add_clause(literals);
+    }
+}
+
+/// Adds clauses allowing at most one of the literals to hold, with the
+/// auxiliary variables of the encoding taken from the allocator.
+pub fn add_at_most_one(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+    encoding: AmoEncoding,
+) {
+    if literals.len() <= 1 {
+        return;
+    }
+    match encoding {
+        AmoEncoding::Pairwise => add_pairwise(sink, literals),
+        AmoEncoding::Sequential => add_sequential(sink, allocator, literals),
+        AmoEncoding::Commander => add_commander(sink, allocator, literals),
+        AmoEncoding::Product => add_product(sink, allocator, literals),
+    }
+}
+
+/// Adds the clause that one of the literals holds, and the encoding of at most one.
+pub fn add_exactly_one(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+    encoding: AmoEncoding,
+) {
+    sink.emit(literals);
+    add_at_most_one(sink, allocator, literals, encoding);
+}
+
+fn add_pairwise(sink: &mut impl ClauseSink, literals: &[Literal]) {
+    for (position, first) in literals.iter().enumerate() {
+        for second in &literals[position + 1..] {
+            sink.emit(&[-first, -second]);
+        }
+    }
+}
+
+fn add_sequential(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+) {
+    // seen[i] holds if one of the literals up to the i-th does
+    let seen = allocator.fresh_many(literals.len() - 1);
+    for (i, literal) in literals.iter().enumerate() {
+        if i + 1 < literals.len() {
+            sink.emit(&[-literal, seen[i]]);
+        }
+        if i > 0 {
+            sink.emit(&[-literal, -seen[i - 1]]);
+            if i + 1 < literals.len() {
+                sink.emit(&[-seen[i - 1], seen[i]]);
+            }
+        }
+    }
+}
+
+fn add_commander(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+) {
+    if literals.len() <= PAIRWISE_LIMIT {
+        add_pairwise(sink, literals);
+        return;
+    }
+    let mut commanders = Vec::new();
+    for group in literals.chunks(3) {
+        add_pairwise(sink, group);
+        let commander = allocator.fresh();
+        for literal in group {
+            sink.emit(&[-literal, commander]);
+        }
+        commanders.push(commander);
+    }
+    add_commander(sink, allocator, &commanders);
+}
+
+fn add_product(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+) {
+    if literals.len() <= PAIRWISE_LIMIT {
+        add_pairwise(sink, literals);
+        return;
+    }
+    let num_columns = (1..).find(|p| p * p >= literals.len()).unwrap();
+    let num_rows = literals.len().div_ceil(num_columns);
+    let rows = allocator.fresh_many(num_rows);
+    let columns = allocator.fresh_many(num_columns);
+    for (position, literal) in literals.iter().enumerate() {
+        sink.emit(&[-literal, rows[position / num_columns]]);
+        sink.emit(&[-literal, columns[position % num_columns]]);
+    }
+    // Two literals differ in their row or their column
+    add_product(sink, allocator, &rows);
+    add_product(sink, allocator, &columns);
+}
+
+/// How [`add_at_most_k`] and [`add_at_least_k`] count the literals, see the module documentation.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CardinalityEncoding {
+    #[default]
+    Totalizer,
+    SortingNetwork,
+}
+
+/// Returns the literal asserting at most `bound` of the counted literals hold, given the unary
+/// outputs, or `None` if the bound always holds. Outputs beyond those built are not needed.
+fn at_most_literal(outputs: &[Literal], bound: usize) -> Option<Literal> {
+    outputs.get(bound).map(|output| -output)
+}
+
+/// Adds clauses allowing at most `bound` of the literals to hold.
+pub fn add_at_most_k(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+    bound: usize,
+    encoding: CardinalityEncoding,
+) {
+    let literal = match encoding {
+        CardinalityEncoding::Totalizer => {
+            Totalizer::new(sink, allocator, literals, bound + 1).at_most(bound)
+        }
+        CardinalityEncoding::SortingNetwork => {
+            SortingNetwork::new(sink, allocator, literals).at_most(bound)
+        }
+    };
+    if let Some(literal) = literal {
+        sink.emit(&[literal]);
+    }
+}
+
+/// Adds clauses requiring at least `bound` of the literals to hold, as at most all but `bound` of
+/// their negations. Adds the empty clause if there are fewer than `bound` literals.
+pub fn add_at_least_k(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+    bound: usize,
+    encoding: CardinalityEncoding,
+) {
+    let Some(bound) = literals.len().checked_sub(bound) else {
+        sink.emit(&[]);
+        return;
+    };
+    let negations: Vec<Literal> = literals.iter().map(|literal| -literal).collect();
+    add_at_most_k(sink, allocator, &negations, bound, encoding);
+}
+
+/// An incremental totalizer over some literals, see the module documentation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Totalizer {
+    /// `outputs[j]` is implied by more than `j` of the literals holding.
+    outputs: Vec<Literal>,
+    num_literals: usize,
+    /// The number of outputs kept, which is all the bounds below it need.
+    cap: usize,
+}
+
+impl Totalizer {
+    /// Builds the totalizer over the literals, with the outputs up to `cap`, which tell apart the
+    /// counts up to `cap` and are enough for the bounds up to `cap - 1`.
+    pub fn new(
+        sink: &mut impl ClauseSink,
+        allocator: &mut VariableAllocator,
+        literals: &[Literal],
+        cap: usize,
+    ) -> Totalizer {
+        assert!(cap > 0, "The totalizer needs at least one output");
+        let mut totalizer = Totalizer {
+            outputs: Vec::new(),
+            num_literals: 0,
+            cap,
+        };
+        totalizer.extend(sink, allocator, literals);
+        totalizer
+    }
+
+    /// Counts the literals too, merging a totalizer over them with the one so far. The bounds
+    /// already added or assumed keep counting the literals before.
+    pub fn extend(
+        &mut self,
+        sink: &mut impl ClauseSink,
+        allocator: &mut VariableAllocator,
+        literals: &[Literal],
+    ) {
+        if literals.is_empty() {
+            return;
+        }
+        let added = build_totalizer(sink, allocator, literals, self.cap);
+        self.outputs = merge_counts(sink, allocator, &self.outputs, &added, self.cap);
+        self.num_literals += literals.len();
+    }
+
+    /// Returns the outputs, where `get_outputs()[j]` is implied by more than `j` of the literals
+    /// holding.
+    pub fn get_outputs(&self) -> &[Literal] {
+        &self.outputs
+    }
+
+    pub fn get_num_literals(&self) -> usize {
+        self.num_literals
+    }
+
+    /// Returns the literal to add or assume for at most `bound` of the literals to hold, or
+    /// `None` if they always do. Panics if the bound needs outputs beyond the cap.
+    pub fn at_most(&self, bound: usize) -> Option<Literal> {
+        assert!(
+            bound < self.cap || bound >= self.num_literals,
+            "The totalizer only counts up to {}",
+            self.cap
+        );
+        at_most_literal(&self.outputs, bound)
+    }
+
+    /// Adds the clause that at most `bound` of the literals hold, which stays for every later
+    /// solve, unlike assuming [`Totalizer::at_most`].
+    pub fn add_at_most(&self, sink: &mut impl ClauseSink, bound: usize) {
+        if let Some(literal) = self.at_most(bound) {
+            sink.emit(&[literal]);
+        }
+    }
+}
+
+/// Returns the outputs of a totalizer tree over the literals.
+fn build_totalizer(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+    cap: usize,
+) -> Vec<Literal> {
+    if literals.len() == 1 {
+        return literals.to_vec();
+    }
+    let (left, right) = literals.split_at(literals.len() / 2);
+    let left = build_totalizer(sink, allocator, left, cap);
+    let right = build_totalizer(sink, allocator, right, cap);
+    merge_counts(sink, allocator, &left, &right, cap)
+}
+
+/// Returns the unary sum of two unary counts, keeping the outputs up to the cap, the last of
+/// which counts everything beyond too.
+fn merge_counts(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    left: &[Literal],
+    right: &[Literal],
+    cap: usize,
+) -> Vec<Literal> {
+    if left.is_empty() || right.is_empty() {
+        let outputs = if left.is_empty() { right } else { left };
+        return outputs[..outputs.len().min(cap)].to_vec();
+    }
+    let outputs = allocator.fresh_many((left.len() + right.len()).min(cap));
+    for i in 0..=left.len() {
+        for j in 0..=right.len() {
+            if i + j == 0 {
+                continue;
+            }
+            // More than i - 1 on the left and j - 1 on the right make more than i + j - 1
+            let mut clause = Vec::with_capacity(3);
+            if i > 0 {
+                clause.push(-left[i - 1]);
+            }
+            if j > 0 {
+                clause.push(-right[j - 1]);
+            }
+            clause.push(outputs[(i + j).min(outputs.len()) - 1]);
+            sink.emit(&clause);
+        }
+    }
+    outputs
+}
+
+/// A sorting network over some literals, see the module documentation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SortingNetwork {
+    /// The literals sorted true first: `outputs[j]` is implied by more than `j` holding.
+    outputs: Vec<Literal>,
+}
+
+impl SortingNetwork {
+    pub fn new(
+        sink: &mut impl ClauseSink,
+        allocator: &mut VariableAllocator,
+        literals: &[Literal],
+    ) -> SortingNetwork {
+        // Padded to a power of two with literals known to be false, which sort last for free
+        let mut wires: Vec<Option<Literal>> = literals.iter().copied().map(Some).collect();
+        wires.resize(literals.len().next_power_of_two(), None);
+        let sorted = sort(sink, allocator, &wires);
+        SortingNetwork {
+            outputs: sorted.into_iter().take(literals.len()).flatten().collect(),
+        }
+    }
+
+    /// Returns the outputs, where `get_outputs()[j]` is implied by more than `j` of the literals
+    /// holding.
+    pub fn get_outputs(&self) -> &[Literal] {
+        &self.outputs
+    }
+
+    /// Returns the literal to add or assume for at most `bound` of the literals to hold, or
+    /// `None` if they always do.
+    pub fn at_most(&self, bound: usize) -> Option<Literal> {
+        at_most_literal(&self.outputs, bound)
+    }
+}
+
+/// Sorts the wires, whose number is a power of two, true first. `None` is a wire known to be
+/// false.
+fn sort(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    wires: &[Option<Literal>],
+) -> Vec<Option<Literal>> {
+    if wires.len() <= 1 {
+        return wires.to_vec();
+    }
+    let (left, right) = wires.split_at(wires.len() / 2);
+    let left = sort(sink, allocator, left);
+    let right = sort(sink, allocator, right);
+    odd_even_merge(sink, allocator, &left, &right)
+}
+
+/// Merges two sorted sequences of the same power of two length, by Batcher's odd-even merge.
+fn odd_even_merge(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    left: &[Option<Literal>],
+    right: &[Option<Literal>],
+) -> Vec<Option<Literal>> {
+    if left.len() == 1 {
+        let (high, low) = compare(sink, allocator, left[0], right[0]);
+        return vec![high, low];
+    }
+    let every_other = |wires: &[Option<Literal>], start: usize| -> Vec<Option<Literal>> {
+        wires.iter().skip(start).step_by(2).copied().collect()
+    };
+    let evens = odd_even_merge(
+        sink,
+        allocator,
+        &every_other(left, 0),
+        &every_other(right, 0),
+    );
+    let odds = odd_even_merge(
+        sink,
+        allocator,
+        &every_other(left, 1),
+        &every_other(right, 1),
+    );
+    let mut merged = vec![evens[0]];
+    for i in 1..evens.len() {
+        let (high, low) = compare(sink, allocator, odds[i - 1], evens[i]);
+        merged.push(high);
+        merged.push(low);
+    }
+    merged.push(odds[odds.len() - 1]);
+    merged
+}
+
+/// Returns the larger and the smaller of two wires, implied by the inputs: the first holds if
+/// either does, and the second if both do.
+fn compare(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    first: Option<Literal>,
+    second: Option<Literal>,
+) -> (Option<Literal>, Option<Literal>) {
+    let (Some(first), Some(second)) = (first, second) else {
+        return (first.or(second), None);
+    };
+    let high = allocator.fresh();
+    let low = allocator.fresh();
+    sink.emit(&[-first, high]);
+    sink.emit(&[-second, high]);
+    sink.emit(&[-first, -second, low]);
+    (Some(high), Some(low))
+}
+
+/// Adds clauses requiring an odd number of the literals to hold if `parity` is true, and an even
+/// number otherwise. A literal appearing twice cancels out, so the empty clause is added if all
+/// of them do and `parity` is true.
+pub fn add_xor(
+    sink: &mut impl ClauseSink,
+    allocator: &mut VariableAllocator,
+    literals: &[Literal],
+    parity: bool,
+) {
+    let mut parity = parity;
+    let mut links: Vec<Literal> = Vec::with_capacity(literals.len());
+    for literal in literals {
+        parity ^= *literal < 0;
+        let variable = literal.abs();
+        match links.iter().position(|other| *other == variable) {
+            Some(position) => {
+                links.remove(position);
+            }
+            None => links.push(variable),
+        }
+    }
+    if links.is_empty() {
+        if parity {
+            sink.emit(&[]);
+        }
+        return;
+    }
+
+    // The literals now hold an odd number of times
+    if !parity {
+        links[0] = -links[0];
+    }
+    while links.len() > 3 {
+        let link = allocator.fresh();
+        // The link is the parity of the first two literals
+        add_odd(sink, &[links[0], links[1], -link]);
+        links.splice(0..2, [link]);
+    }
+    add_odd(sink, &links);
+}
+
+/// Forbids every assignment of the literals with an even number of them true.
+fn add_odd(sink: &mut impl ClauseSink, literals: &[Literal]) {
+    let mut clause = Vec::with_capacity(literals.len());
+    for mask in 0..1usize << literals.len() {
+        if mask.count_ones() % 2 == 1 {
+            continue;
+        }
+        clause.clear();
+        for (i, literal) in literals.iter().enumerate() {
+            clause.push(if mask >> i & 1 == 1 {
+                -literal
+            } else {
+                *literal
+            });
+        }
+        sink.emit(&clause);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cnf::Assignment;
+    use crate::dpll::{solve_dpll, SearchResult};
+
+    const ENCODINGS: [AmoEncoding; 4] = [
+        AmoEncoding::Pairwise,
+        AmoEncoding::Sequential,
+        AmoEncoding::Commander,
+        AmoEncoding::Product,
+    ];
+
+    /// Checks that the expression over the literals `1..=n` and auxiliary variables has a model
+    /// extending exactly the assignments of the literals the count of true ones allows.
+    fn check(expression: &Expression, n: usize, allowed: impl Fn(usize) -> bool) {
+        for mask in 0..1u32 << n {
+            let mut fixed = expression.clone();
+            for variable in 1..=n {
+                let literal = variable as Literal;
+                let value = mask >> (variable - 1) & 1 == 1;
+                fixed.emit(&[if value { literal } else { -literal }]);
+            }
+            fixed.optimize();
+            assert_eq!(
+                solve_dpll(&mut fixed).is_some(),
+                allowed(mask.count_ones() as usize),
+                "{:b}",
+                mask
+            );
+        }
+    }
+
+    #[test]
+    fn test_at_most_one() {
+        for encoding in ENCODINGS {
+            for n in 0..=8 {
+                let literals: Vec<Literal> = (1..=n as Literal).collect();
+                let mut expression = Expression::new();
+                let mut allocator = VariableAllocator::new(n as Variable);
+                add_at_most_one(&mut expression, &mut allocator, &literals, encoding);
+                check(&expression, n, |count| count <= 1);
+
+                let mut expression = Expression::new();
+                let mut allocator = VariableAllocator::new(n as Variable);
+                add_exactly_one(&mut expression, &mut allocator, &literals, encoding);
+                check(&expression, n, |count| count == 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_xor() {
+        for n in 1..=7usize {
+            for parity in [false, true] {
+                let literals: Vec<Literal> = (1..=n as Literal).collect();
+                let mut expression = Expression::new();
+                let mut allocator = VariableAllocator::new(n as Variable);
+                add_xor(&mut expression, &mut allocator, &literals, parity);
+                assert_eq!(
+                    allocator.get_num_variables() as usize,
+                    n + n.saturating_sub(3)
+                );
+                check(&expression, n, |count| (count % 2 == 1) == parity);
+            }
+        }
+
+        // Negations flip the parity, and repeated literals cancel out
+        let mut expression = Expression::new();
+        let mut allocator = VariableAllocator::new(4);
+        add_xor(&mut expression, &mut allocator, &[1, -2, 3, 3, 4, -4], true);
+        assert_eq!(expression.get_max_variable(), 2);
+        check(&expression, 2, |count| count == 1);
+    }
+
+    #[test]
+    fn test_variable_allocator() {
+        let mut expression = Expression::new();
+        expression.emit(&[1, -3]);
+        let mut allocator = VariableAllocator::after(&expression);
+        assert_eq!(allocator.fresh(), 4);
+        assert_eq!(allocator.fresh_many(2), vec![5, 6]);
+
+        // The sequential counter takes n - 1 variables, and the others fewer clauses than pairs
+        let literals: Vec<Literal> = (1..=30).collect();
+        let mut allocator = VariableAllocator::new(30);
+        add_at_most_one(
+            &mut Expression::new(),
+            &mut allocator,
+            &literals,
+            AmoEncoding::Sequential,
+        );
+        assert_eq!(allocator.get_num_variables(), 59);
+        for encoding in [AmoEncoding::Commander, AmoEncoding::Product] {
+            let mut expression = Expression::new();
+            let mut allocator = VariableAllocator::new(30);
+            add_at_most_one(&mut expression, &mut allocator, &literals, encoding);
+            assert!(
+                expression.get_clauses().len() < 30 * 29 / 2 / 2,
+                "{:?}",
+                encoding
+            );
+        }
+    }
+
+    #[test]
+    fn test_at_most_k() {
+        for encoding in [
+            CardinalityEncoding::Totalizer,
+            CardinalityEncoding::SortingNetwork,
+        ] {
+            for n in 0..=6 {
+                let literals: Vec<Literal> = (1..=n as Literal).collect();
+                for bound in 0..=n + 1 {
+                    let mut expression = Expression::new();
+                    let mut allocator = VariableAllocator::new(n as Variable);
+                    add_at_most_k(&mut expression, &mut allocator, &literals, bound, encoding);
+                    check(&expression, n, |count| count <= bound);
+
+                    let mut expression = Expression::new();
+                    let mut allocator = VariableAllocator::new(n as Variable);
+                    add_at_least_k(&mut expression, &mut allocator, &literals, bound, encoding);
+                    check(&expression, n, |count| count >= bound);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_totalizer_incremental() {
+        let mut solver = Solver::new();
+        let literals: Vec<Literal> = (1..=8).collect();
+        // At least five of the literals, the first four odd ones among them
+        solver.add_clause(&[1]);
+        solver.add_clause(&[3]);
+        let mut allocator = VariableAllocator::new(8);
+        add_at_least_k(
+            &mut solver,
+            &mut allocator,
+            &literals,
+            5,
+            CardinalityEncoding::Totalizer,
+        );
+        let mut allocator = VariableAllocator::new(solver.get_num_variables());
+        let mut totalizer = Totalizer::new(&mut solver, &mut allocator, &literals[..6], 7);
+        let count = |model: &Assignment, literals: &[Literal]| {
+            literals
+                .iter()
+                .filter(|literal| model[&(**literal as Variable)])
+                .count()
+        };
+
+        // Tightening the bound on the first six one assumption at a time
+        for bound in (0..=6).rev() {
+            let assumptions: Vec<Literal> = totalizer.at_most(bound).into_iter().collect();
+            match solver.solve_with_assumptions(&assumptions) {
+                SearchResult::Satisfiable(model) => {
+                    assert!(bound >= 3);
+                    assert!(count(&model, &literals[..6]) <= bound);
+                }
+                _ => assert!(bound < 3),
+            }
+        }
+
+        // Counting the last two as well, at most four of eight leaves no room for five
+        totalizer.extend(&mut solver, &mut allocator, &literals[6..]);
+        assert_eq!(totalizer.get_num_literals(), 8);
+        let assumptions = [totalizer.at_most(5).unwrap()];
+        let SearchResult::Satisfiable(model) = solver.solve_with_assumptions(&assumptions) else {
+            panic!("Five of eight are allowed");
+        };
+        assert_eq!(count(&model, &literals), 5);
+        totalizer.add_at_most(&mut solver, 4);
+        assert_eq!(
+            solver.solve_with_assumptions(&[]),
+            SearchResult::Unsatisfiable
+        );
+    }
+}
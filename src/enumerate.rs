@@ -0,0 +1,274 @@
+//! Enumerates the models of an expression (AllSAT) by adding a blocking clause for every model
+//! found, optionally reporting only one representative model per orbit of its symmetries, or
+//! only a few models that differ from each other as much as possible.
+
+use crate::cnf::{to_variable, Assignment, AssignmentExt, Clause, Literal, Variable, CNF};
+use crate::dpll::solve_dpll;
+use crate::expression::Expression;
+use crate::hash::FnvHashSet;
+use crate::symmetry::{break_symmetries, find_symmetries, Symmetry};
+
+/// Returns the true literals of the model over the variables up to `max_variable`, sorted by
+/// variable.
+fn to_literals(model: &Assignment, max_variable: Variable) -> Vec<Literal> {
+    let mut literals = model.to_literal_vec();
+    literals.retain(|literal| to_variable(*literal) <= max_variable);
+    literals
+}
+
+/// Adds the clause excluding exactly the model given by its true literals.
+fn block(expression: &mut Expression, literals: &[Literal]) {
+    let mut clause = Clause::new();
+    for literal in literals {
+        clause.insert(-literal);
+    }
+    expression.add_clause(clause);
+}
+
+fn next_model(expression: &Expression) -> Option<Assignment> {
+    let mut working = expression.clone();
+    working.optimize();
+    solve_dpll(&mut working)
+}
+
+/// Returns the models of the expression, up to `limit` of them if given. Every model assigns all
+/// the variables of the expression, so models differing only in don't-care variables are
+/// reported separately. The search assigns pure literals as set on the expression, see
+/// [`Expression::set_pure_literal_mode`].
+pub fn enumerate_models(expression: &Expression, limit: Option<usize>) -> Vec<Assignment> {
+    let max_variable = expression.get_max_variable();
+    let mut blocked = expression.clone();
+    let mut models = Vec::new();
+    while limit.is_none_or(|limit| models.len() < limit) {
+        let Some(model) = next_model(&blocked) else {
+            break;
+        };
+        block(&mut blocked, &to_literals(&model, max_variable));
+        models.push(model);
+    }
+
+    models
+}
+
+/// Returns up to `k` models of the expression that differ pairwise in as many variables as the
+/// search can manage, for a spread of example solutions.
+///
+/// The models are found greedily: every search after the first prefers, for each variable, the
+/// value that fewer of the models so far give it (the opposite of the last model on a tie), and
+/// the models so far are blocked. This is a heuristic, so the models are spread out but the
+/// smallest pairwise distance is not guaranteed to be the largest possible.
+pub fn diverse_models(expression: &Expression, k: usize) -> Vec<Assignment> {
+    let max_variable = expression.get_max_variable();
+    let mut blocked = expression.clone();
+    // The number of models so far giving each variable the value true, minus those giving false
+    let mut balance = vec![0i64; max_variable as usize + 1];
+    let mut models: Vec<Assignment> = Vec::new();
+    while models.len() < k {
+        if let Some(last) = models.last() {
+            for variable in 1..=max_variable {
+                let preferred = match balance[variable as usize] {
+                    0 => !last[&variable],
+                    balance => balance < 0,
+                };
+                blocked.set_phase(variable, preferred);
+            }
+        }
+        let Some(model) = next_model(&blocked) else {
+            break;
+        };
+        let literals = to_literals(&model, max_variable);
+        for literal in &literals {
+            balance[to_variable(*literal) as usize] += if *literal > 0 { 1 } else { -1 };
+        }
+        block(&mut blocked, &literals);
+        models.push(model);
+    }
+
+    models
+}
+
+/// A set of models that the symmetries of the expression map onto each other.
+#[derive(Clone, Debug)]
+pub struct Orbit {
+    /// A model of the orbit, the lexicographically smallest one for each of the symmetries.
+    pub representative: Assignment,
+    /// The models of the orbit as true literals, sorted by variable.
+    members: Vec<Vec<Literal>>,
+}
+
+impl Orbit {
+    /// Returns the number of models in the orbit.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns every model of the orbit, starting with the representative.
+    pub fn expand(&self) -> Vec<Assignment> {
+        self.members
+            .iter()
+            .map(|literals| Assignment::from_literal_vec(literals))
+            .collect()
+    }
+}
+
+/// Returns the images of the model under the group generated by the symmetries.
+fn orbit_of(literals: Vec<Literal>, symmetries: &[Symmetry]) -> Vec<Vec<Literal>> {
+    let mut seen: FnvHashSet<Vec<Literal>> = FnvHashSet::default();
+    seen.insert(literals.clone());
+    let mut members = vec![literals];
+    let mut index = 0;
+    while index < members.len() {
+        for symmetry in symmetries {
+            let mut image: Vec<Literal> =
+                members[index].iter().map(|l| symmetry.apply(*l)).collect();
+            image.sort_unstable_by_key(|literal| to_variable(*literal));
+            if seen.insert(image.clone()) {
+                members.push(image);
+            }
+        }
+        index += 1;
+    }
+
+    members
+}
+
+/// Returns one representative model per orbit of the symmetries of the expression, up to `limit`
+/// orbits if given. Together, the orbits contain exactly the models of [`enumerate_models`].
+///
+/// The search runs on the expression with symmetry-breaking constraints, which rules out most
+/// non-representative models up front. The breaking constraints are only added for generators of
+/// the symmetry group, so the rest of each orbit is blocked as soon as its representative is found.
+pub fn enumerate_orbits(expression: &Expression, limit: Option<usize>) -> Vec<Orbit> {
+    let symmetries = find_symmetries(expression);
+    let max_variable = expression.get_max_variable();
+    let mut blocked = break_symmetries(expression, &symmetries);
+    let mut orbits = Vec::new();
+    while limit.is_none_or(|limit| orbits.len() < limit) {
+        let Some(model) = next_model(&blocked) else {
+            break;
+        };
+        let literals = to_literals(&model, max_variable);
+        let members = orbit_of(literals.clone(), &symmetries);
+        for member in &members {
+            block(&mut blocked, member);
+        }
+
+        orbits.push(Orbit {
+            representative: Assignment::from_literal_vec(&literals),
+            members,
+        });
+    }
+
+    orbits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::expression::PureLiteralMode;
+
+    /// As many pigeons as holes, so the models are the permutations.
+    fn permutations(n: usize) -> Expression {
+        let variable = |pigeon: usize, hole: usize| (pigeon * n + hole + 1) as Literal;
+        let mut expression = Expression::new();
+        for pigeon in 0..n {
+            let mut clause = Clause::new();
+            for hole in 0..n {
+                clause.insert(variable(pigeon, hole));
+            }
+            expression.add_clause(clause);
+        }
+        for hole in 0..n {
+            for first in 0..n {
+                for second in (first + 1)..n {
+                    let mut clause = Clause::new();
+                    clause.insert(-variable(first, hole));
+                    clause.insert(-variable(second, hole));
+                    expression.add_clause(clause);
+                }
+            }
+        }
+        expression
+    }
+
+    #[test]
+    fn test_enumerate_models() {
+        let expression = parse_dimacs_str("p cnf 3 2\n1 2 3 0\n-1 -2 0\n");
+        let models = enumerate_models(&expression, None);
+        assert_eq!(models.len(), 5);
+        assert!(models.iter().all(|model| expression.is_satisfied_by(model)));
+
+        assert_eq!(enumerate_models(&expression, Some(2)).len(), 2);
+        assert_eq!(enumerate_models(&permutations(4), None).len(), 24);
+
+        // Blocking clauses make the model found by a pure literal impure, so every mode finds all
+        for mode in [PureLiteralMode::Disabled, PureLiteralMode::Preprocessing] {
+            let mut expression = expression.clone();
+            expression.set_pure_literal_mode(mode);
+            assert_eq!(enumerate_models(&expression, None).len(), 5);
+        }
+    }
+
+    #[test]
+    fn test_diverse_models() {
+        let distance = |first: &Assignment, second: &Assignment| {
+            first
+                .iter()
+                .filter(|(variable, value)| second[*variable] != **value)
+                .count()
+        };
+
+        // Any 1 | 2 | ... | 6 model but the one with a single true variable has a complement
+        let expression = parse_dimacs_str("p cnf 6 1\n1 2 3 4 5 6 0\n");
+        let models = diverse_models(&expression, 2);
+        assert_eq!(models.len(), 2);
+        assert!(models.iter().all(|model| expression.is_satisfied_by(model)));
+        assert!(distance(&models[0], &models[1]) >= 5);
+
+        // Two permutations of four differ in two to eight variables, and the models are spread
+        // further than the first ones enumerated in order
+        let expression = permutations(4);
+        let models = diverse_models(&expression, 4);
+        assert_eq!(models.len(), 4);
+        let total = |models: &[Assignment]| {
+            let mut total = 0;
+            for (index, first) in models.iter().enumerate() {
+                for second in &models[index + 1..] {
+                    assert!(distance(first, second) > 0);
+                    total += distance(first, second);
+                }
+            }
+            total
+        };
+        assert!(total(&enumerate_models(&expression, Some(4))) < total(&models));
+
+        // Asking for more models than there are returns them all
+        let expression = parse_dimacs_str("p cnf 3 2\n1 2 3 0\n-1 -2 0\n");
+        assert_eq!(diverse_models(&expression, 10).len(), 5);
+        assert!(diverse_models(&expression, 0).is_empty());
+    }
+
+    #[test]
+    fn test_enumerate_orbits() {
+        // The models of 1 | 2 | 3 have one, two or three true variables
+        let expression = parse_dimacs_str("p cnf 3 1\n1 2 3 0\n");
+        let orbits = enumerate_orbits(&expression, None);
+        let mut sizes: Vec<usize> = orbits.iter().map(|orbit| orbit.len()).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 3, 3]);
+
+        // Every permutation is a symmetric image of any other
+        let expression = permutations(4);
+        let orbits = enumerate_orbits(&expression, None);
+        assert_eq!(orbits.len(), 1);
+        let models = orbits[0].expand();
+        assert_eq!(models.len(), 24);
+        assert_eq!(models[0], orbits[0].representative);
+        assert!(models.iter().all(|model| expression.is_satisfied_by(model)));
+    }
+}
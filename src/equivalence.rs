@@ -0,0 +1,247 @@
+//! Reports of the equivalences and definitions hidden in an expression, for encoder authors who
+//! want to simplify their encodings upstream.
+//!
+//! Equivalences are found by failed-literal probing: every variable `x` is assumed true and then
+//! false, and a variable that is propagated to opposite values under the two assumptions is
+//! equivalent to `x` or to its negation. This finds every equivalence of the binary implication
+//! graph (the strongly connected components of the binary clauses), and the ones that only follow
+//! from longer clauses. Definitions are found syntactically, as the clauses of an AND gate.
+
+use std::fmt;
+
+use crate::cnf::{to_variable, Literal, Variable};
+use crate::expression::Expression;
+use crate::hash::FnvHashSet;
+use crate::propagation::Propagator;
+
+/// The variable always has the same value as the literal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Equivalence {
+    pub variable: Variable,
+    pub literal: Literal,
+}
+
+/// The output always has the value of the conjunction of the inputs. A negative output is the
+/// disjunction of the negated inputs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Definition {
+    pub output: Literal,
+    pub inputs: Vec<Literal>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EquivalenceReport {
+    /// Every variable equivalent to a smaller one, with a literal of the smallest variable it is
+    /// equivalent to, ordered by variable.
+    pub equivalences: Vec<Equivalence>,
+    /// The AND gates with at least two inputs, ordered by output.
+    pub definitions: Vec<Definition>,
+}
+
+impl fmt::Display for EquivalenceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for equivalence in &self.equivalences {
+            writeln!(f, "{} = {}", equivalence.variable, equivalence.literal)?;
+        }
+        for definition in &self.definitions {
+            let inputs: Vec<String> = definition.inputs.iter().map(Literal::to_string).collect();
+            writeln!(f, "{} = {}", definition.output, inputs.join(" & "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Groups the variables into classes of equivalent variables, each remembering whether it is
+/// equivalent to its parent or to the negation of its parent.
+struct Classes {
+    parents: Vec<Variable>,
+    negated: Vec<bool>,
+}
+
+impl Classes {
+    fn new(max_variable: Variable) -> Classes {
+        Classes {
+            parents: (0..=max_variable).collect(),
+            negated: vec![false; max_variable as usize + 1],
+        }
+    }
+
+    /// Returns the smallest variable of the class, and whether the variable is its negation.
+    fn find(&mut self, variable: Variable) -> (Variable, bool) {
+        let parent = self.parents[variable as usize];
+        if parent == variable {
+            return (variable, false);
+        }
+        let (root, negated) = self.find(parent);
+        self.parents[variable as usize] = root;
+        self.negated[variable as usize] ^= negated;
+        (root, self.negated[variable as usize])
+    }
+
+    fn merge(&mut self, variable: Variable, literal: Literal) {
+        let (first, first_negated) = self.find(variable);
+        let (second, second_negated) = self.find(to_variable(literal));
+        if first != second {
+            let (root, child) = (first.min(second), first.max(second));
+            self.parents[child as usize] = root;
+            self.negated[child as usize] = first_negated ^ second_negated ^ (literal < 0);
+        }
+    }
+}
+
+/// Finds the equivalences and definitions of the expression.
+///
+/// Variables fixed by unit propagation are left out, as is everything when unit propagation alone
+/// refutes the expression. Probing takes two propagations per variable, which is cheap next to
+/// solving but can dominate on very large expressions.
+pub fn find_equivalences(expression: &Expression) -> EquivalenceReport {
+    let clauses = expression.get_clauses();
+    let max_variable = expression.get_max_variable();
+    let mut propagator = Propagator::new();
+    for clause in &clauses {
+        propagator.add_clause(clause.literals());
+    }
+    if propagator.propagate_units().is_some() {
+        return EquivalenceReport::default();
+    }
+    let fixed = propagator.get_trail().len();
+
+    let mut classes = Classes::new(max_variable);
+    let mut values: Vec<Option<bool>> = vec![None; max_variable as usize + 1];
+    for variable in 1..=max_variable {
+        let literal = variable as Literal;
+        if propagator.value(literal).is_some() {
+            continue;
+        }
+        if propagator.assume(literal).is_some() {
+            propagator.propagate_units();
+            continue;
+        }
+        let implied = propagator.get_trail()[fixed..].to_vec();
+        for literal in &implied {
+            values[to_variable(*literal) as usize] = Some(*literal > 0);
+        }
+
+        propagator.propagate_units();
+        if propagator.assume(-literal).is_none() {
+            for other in &propagator.get_trail()[fixed + 1..] {
+                // The other literal is true exactly when the probed variable is false
+                if values[to_variable(*other) as usize] == Some(*other < 0) {
+                    let equivalent = if *other > 0 { -literal } else { literal };
+                    classes.merge(to_variable(*other), equivalent);
+                }
+            }
+        }
+        for literal in &implied {
+            values[to_variable(*literal) as usize] = None;
+        }
+        propagator.propagate_units();
+    }
+
+    let mut report = EquivalenceReport::default();
+    for variable in 1..=max_variable {
+        let (root, negated) = classes.find(variable);
+        if root != variable {
+            let literal = if negated {
+                -(root as Literal)
+            } else {
+                root as Literal
+            };
+            report.equivalences.push(Equivalence { variable, literal });
+        }
+    }
+
+    // The output `o` of `o = a & b` has the clauses -o | a, -o | b and o | -a | -b
+    let binary: FnvHashSet<(Literal, Literal)> = clauses
+        .iter()
+        .filter(|clause| clause.len() == 2)
+        .map(|clause| {
+            let literals = clause.literals();
+            (literals[0].min(literals[1]), literals[0].max(literals[1]))
+        })
+        .collect();
+    let implies = |from: Literal, to: Literal| binary.contains(&((-from).min(to), (-from).max(to)));
+    for clause in clauses.iter().filter(|clause| clause.len() > 2) {
+        let literals = clause.literals();
+        for output in literals {
+            let mut inputs: Vec<Literal> = literals
+                .iter()
+                .filter(|literal| *literal != output)
+                .map(|literal| -literal)
+                .collect();
+            if inputs.iter().all(|input| implies(*output, *input)) {
+                inputs.sort_unstable();
+                report.definitions.push(Definition {
+                    output: *output,
+                    inputs,
+                });
+            }
+        }
+    }
+    report.definitions.sort_unstable_by(|first, second| {
+        (to_variable(first.output), first.output, &first.inputs).cmp(&(
+            to_variable(second.output),
+            second.output,
+            &second.inputs,
+        ))
+    });
+    report.definitions.dedup();
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_find_equivalences() {
+        // 1 = -2 through the binary clauses, and 3 = 1 only through the ternary ones
+        let expression =
+            parse_dimacs_str("p cnf 5 6\n1 2 0\n-1 -2 0\n-1 4 0\n-1 -4 3 0\n1 -5 0\n1 5 -3 0\n");
+        let report = find_equivalences(&expression);
+        assert_eq!(
+            report.equivalences,
+            vec![
+                Equivalence {
+                    variable: 2,
+                    literal: -1
+                },
+                Equivalence {
+                    variable: 3,
+                    literal: 1
+                },
+            ]
+        );
+        assert!(report.definitions.is_empty());
+        assert_eq!(report.to_string(), "2 = -1\n3 = 1\n");
+
+        // Fixed variables are not reported
+        let expression = parse_dimacs_str("p cnf 2 3\n1 0\n-1 2 0\n1 -2 0\n");
+        assert_eq!(find_equivalences(&expression), EquivalenceReport::default());
+    }
+
+    #[test]
+    fn test_find_definitions() {
+        // 3 = 1 & 2, and -4 = -1 & -2, so 4 = 1 | 2
+        let expression =
+            parse_dimacs_str("p cnf 4 6\n-3 1 0\n-3 2 0\n3 -1 -2 0\n4 -1 0\n4 -2 0\n-4 1 2 0\n");
+        let report = find_equivalences(&expression);
+        assert!(report.equivalences.is_empty());
+        assert_eq!(
+            report.definitions,
+            vec![
+                Definition {
+                    output: 3,
+                    inputs: vec![1, 2]
+                },
+                Definition {
+                    output: -4,
+                    inputs: vec![-2, -1]
+                },
+            ]
+        );
+        assert_eq!(report.to_string(), "3 = 1 & 2\n-4 = -2 & -1\n");
+    }
+}
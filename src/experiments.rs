@@ -0,0 +1,172 @@
+//! Experiments on families of generated instances.
+
+use std::io::Write;
+use std::time::Duration;
+
+use crate::benchmarks::random_k_sat;
+use crate::cnf::Variable;
+use crate::dpll::{SearchBudget, SearchResult};
+use crate::rng::Rng;
+use crate::solver::{solve_with_config, SolverConfig};
+
+/// A sweep over clause/variable ratios of uniform random k-SAT.
+#[derive(Clone, Debug)]
+pub struct PhaseTransitionConfig {
+    pub k: usize,
+    pub num_variables: Variable,
+    pub ratios: Vec<f64>,
+    /// The number of instances generated for every ratio.
+    pub samples_per_ratio: usize,
+    /// The instances are generated from `solver.seed`, so one seed reproduces the whole sweep.
+    pub solver: SolverConfig,
+}
+
+impl Default for PhaseTransitionConfig {
+    /// 3-SAT over 30 variables, for ratios from 2 to 7 in steps of 0.25, with at most 10000
+    /// decisions per instance.
+    fn default() -> Self {
+        PhaseTransitionConfig {
+            k: 3,
+            num_variables: 30,
+            ratios: (0..=20).map(|step| 2.0 + step as f64 * 0.25).collect(),
+            samples_per_ratio: 50,
+            solver: SolverConfig {
+                budget: SearchBudget {
+                    max_decisions: Some(10000),
+                    max_conflicts: None,
+                },
+                ..SolverConfig::default()
+            },
+        }
+    }
+}
+
+/// The results of all samples at one ratio.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhaseTransitionPoint {
+    pub ratio: f64,
+    pub num_satisfiable: usize,
+    pub num_unsatisfiable: usize,
+    /// Samples for which the budget ran out.
+    pub num_unknown: usize,
+    pub median_decisions: usize,
+    pub median_conflicts: usize,
+    pub median_time: Duration,
+}
+
+impl PhaseTransitionPoint {
+    /// The fraction of the solved samples that are satisfiable.
+    pub fn sat_probability(&self) -> f64 {
+        let solved = self.num_satisfiable + self.num_unsatisfiable;
+        if solved == 0 {
+            return 0.0;
+        }
+        self.num_satisfiable as f64 / solved as f64
+    }
+}
+
+fn median<T: Copy + Ord + Default>(mut values: Vec<T>) -> T {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied().unwrap_or_default()
+}
+
+/// Runs the sweep, returning one point per ratio.
+pub fn run_phase_transition(config: &PhaseTransitionConfig) -> Vec<PhaseTransitionPoint> {
+    let mut rng = Rng::new(config.solver.seed);
+    let mut points = Vec::new();
+    for ratio in &config.ratios {
+        let num_clauses = (config.num_variables as f64 * ratio).round() as usize;
+        let mut point = PhaseTransitionPoint {
+            ratio: *ratio,
+            num_satisfiable: 0,
+            num_unsatisfiable: 0,
+            num_unknown: 0,
+            median_decisions: 0,
+            median_conflicts: 0,
+            median_time: Duration::ZERO,
+        };
+        let mut decisions = Vec::new();
+        let mut conflicts = Vec::new();
+        let mut times = Vec::new();
+
+        for _ in 0..config.samples_per_ratio {
+            let expression =
+                random_k_sat(config.num_variables, num_clauses, config.k, rng.next_u64());
+
+            let (result, statistics) = solve_with_config(&expression, &config.solver);
+            match result {
+                SearchResult::Satisfiable(_) => point.num_satisfiable += 1,
+                SearchResult::Unsatisfiable => point.num_unsatisfiable += 1,
+                SearchResult::BudgetExhausted => point.num_unknown += 1,
+            }
+            decisions.push(statistics.num_decisions);
+            conflicts.push(statistics.num_conflicts);
+            times.push(statistics.time);
+        }
+
+        point.median_decisions = median(decisions);
+        point.median_conflicts = median(conflicts);
+        point.median_time = median(times);
+        points.push(point);
+    }
+
+    points
+}
+
+/// Writes the points as CSV, with a header line.
+pub fn write_phase_transition_csv<W: Write>(
+    points: &[PhaseTransitionPoint],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "ratio,sat_probability,satisfiable,unsatisfiable,unknown,median_decisions,median_conflicts,median_time_ms"
+    )?;
+    for point in points {
+        writeln!(
+            writer,
+            "{},{:.4},{},{},{},{},{},{:.4}",
+            point.ratio,
+            point.sat_probability(),
+            point.num_satisfiable,
+            point.num_unsatisfiable,
+            point.num_unknown,
+            point.median_decisions,
+            point.median_conflicts,
+            point.median_time.as_secs_f64() * 1000.0
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_transition() {
+        let config = PhaseTransitionConfig {
+            num_variables: 20,
+            ratios: vec![2.0, 4.26, 8.0],
+            samples_per_ratio: 10,
+            ..PhaseTransitionConfig::default()
+        };
+        let points = run_phase_transition(&config);
+
+        assert_eq!(points.len(), 3);
+        assert!(points[0].sat_probability() > 0.8);
+        assert!(points[2].sat_probability() < 0.2);
+        for point in &points {
+            assert_eq!(
+                point.num_satisfiable + point.num_unsatisfiable + point.num_unknown,
+                10
+            );
+        }
+
+        let mut csv = Vec::new();
+        write_phase_transition_csv(&points, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.lines().nth(1).unwrap().starts_with("2,"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Explains why unit propagation forces a literal, for debugging an encoding: which clauses,
+//! propagated in which order, make a variable take the value it does.
+//!
+//! The clauses on the reasons of the literal are found by propagating the expression, then
+//! reduced one at a time, dropping every clause without which the literal is still forced, so no
+//! clause of the explanation can be left out.
+
+use crate::cnf::{to_variable, ClauseId, Literal};
+use crate::expression::Expression;
+use crate::propagation::Propagator;
+
+/// Propagates the active clauses with the assumptions true, returning the reasons leading to the
+/// literal in the order they propagated, or `None` if the literal is not forced or the
+/// propagation runs into a conflict.
+fn find_reasons(
+    propagator: &mut Propagator,
+    literal: Literal,
+    assumptions: &[Literal],
+) -> Option<Vec<usize>> {
+    if propagator.propagate_units().is_some() {
+        return None;
+    }
+    for assumption in assumptions {
+        if propagator.assume(*assumption).is_some() {
+            return None;
+        }
+    }
+    if propagator.value(literal) != Some(true) {
+        return None;
+    }
+
+    let trail = propagator.get_trail();
+    let max_variable = trail.iter().map(|literal| to_variable(*literal)).max()?;
+    let mut positions = vec![usize::MAX; max_variable as usize + 1];
+    for (position, literal) in trail.iter().enumerate() {
+        positions[to_variable(*literal) as usize] = position;
+    }
+    let mut reasons = Vec::new();
+    let mut seen = vec![false; positions.len()];
+    let mut pending = vec![to_variable(literal)];
+    while let Some(variable) = pending.pop() {
+        if std::mem::replace(&mut seen[variable as usize], true) {
+            continue;
+        }
+        if let Some(reason) = propagator.reason(variable) {
+            reasons.push(reason);
+            let clause = propagator.get_clause(reason);
+            pending.extend(clause.iter().map(|literal| to_variable(*literal)));
+        }
+    }
+    // The first literal of a reason is the one it implied
+    reasons.sort_by_key(|reason| {
+        let implied = propagator.get_clause(*reason)[0];
+        positions[to_variable(implied) as usize]
+    });
+    Some(reasons)
+}
+
+/// Returns the clauses, as indices into [`Expression::get_clauses`], whose unit propagation
+/// forces the literal with the assumptions true, in the order they propagate: each clause is unit
+/// once the assumptions and the clauses before it are propagated. Leaving out any of the clauses
+/// stops the literal from being forced. The explanation is empty if the literal is among the
+/// assumptions, and `None` if propagation does not force it, or runs into a conflict first.
+pub fn explain(
+    expression: &Expression,
+    literal: Literal,
+    assumptions: &[Literal],
+) -> Option<Vec<ClauseId>> {
+    let mut propagator = Propagator::new();
+    for clause in expression.get_clauses() {
+        propagator.add_clause(clause.literals());
+    }
+    let reasons = find_reasons(&mut propagator, literal, assumptions)?;
+
+    let mut needed = vec![false; propagator.get_num_clauses()];
+    for reason in &reasons {
+        needed[*reason] = true;
+    }
+    for (id, needed) in needed.into_iter().enumerate() {
+        propagator.set_active(id, needed);
+    }
+    for reason in reasons {
+        propagator.set_active(reason, false);
+        if find_reasons(&mut propagator, literal, assumptions).is_none() {
+            propagator.set_active(reason, true);
+        }
+    }
+
+    let reasons = find_reasons(&mut propagator, literal, assumptions).unwrap();
+    Some(reasons.into_iter().map(|id| id as ClauseId).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_explain() {
+        // 4 follows from 1 either through 2 or through 3, and 5 needs both
+        let expression = parse_dimacs_str(
+            "p cnf 6 7\n1 0\n-1 2 0\n-1 3 0\n-2 4 0\n-3 4 0\n-2 -3 5 0\n-6 -5 0\n",
+        );
+        assert_eq!(explain(&expression, 4, &[]), Some(vec![0, 1, 3]));
+        assert_eq!(explain(&expression, 5, &[]), Some(vec![0, 1, 2, 5]));
+        assert_eq!(explain(&expression, -6, &[]), Some(vec![0, 1, 2, 5, 6]));
+        assert_eq!(explain(&expression, 6, &[]), None);
+        assert_eq!(explain(&expression, 1, &[]), Some(vec![0]));
+
+        // Under assumptions, which explain themselves
+        let expression = parse_dimacs_str("p cnf 4 3\n-1 2 0\n-2 -3 4 0\n1 3 0\n");
+        assert_eq!(explain(&expression, 4, &[]), None);
+        assert_eq!(explain(&expression, 4, &[1, 3]), Some(vec![0, 1]));
+        assert_eq!(explain(&expression, 3, &[-1]), Some(vec![2]));
+        assert_eq!(explain(&expression, 3, &[3]), Some(vec![]));
+        // Nothing is explained once the assumptions conflict
+        assert_eq!(explain(&expression, 2, &[1, -2]), None);
+    }
+}
@@ -1,43 +1,317 @@
 use core::panic;
-use hashbrown::{HashMap, HashSet};
-use std::cmp::{max, min, Ordering};
+#[cfg(feature = "xor")]
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::collections::BinaryHeap;
+use std::cmp::{max, min, Ordering, Reverse};
 use std::fmt::Debug;
+use std::hash::Hasher;
 use std::sync::{Arc, RwLock};
 
+use crate::arena::ClauseArena;
+use crate::branching::{BranchingModel, VariableSnapshot};
 use crate::cnf::{
     negate, to_variable, Action, ActionState, Assignment, Clause, ClauseId, Literal, Variable, CNF,
 };
-use crate::dimacs_parser::parse_dimacs;
+use crate::dimacs_parser::{parse_dimacs, ParseError};
+use crate::dpll::solve_dpll;
+#[cfg(feature = "xor")]
+use crate::encodings::{add_xor, VariableAllocator};
+use crate::hash::{FnvHashMap, FnvHashSet, FnvHasher};
+use crate::memory::{map_bytes, set_bytes, vec_bytes, MemoryUsage};
+use crate::paranoid::{Divergence, ParanoidPolicy, ParanoidState};
+use crate::rng::Rng;
 use crate::stack::Stack;
+use crate::trail::Trail;
 
 #[derive(Clone, Copy, Debug)]
 pub enum SolverHeuristic {
     MostLiteralOccurances,
     MostVariableOccurances,
     MinimizeClauseLength,
+    /// Branches on a uniformly random unassigned variable with a random value, drawn from the
+    /// expression's seeded generator (see [`Expression::set_seed`]).
+    Random,
+    /// Branches on the unassigned variable with the highest activity, towards its more frequent
+    /// literal. Every conflict bumps the activity of the variables of the clause that became
+    /// empty, and bumps by more after each conflict, so older conflicts count for less.
+    Vsids,
+}
+
+/// When the search assigns pure literals, the literals whose negation occurs in no remaining
+/// clause.
+///
+/// Assigning a pure literal keeps the expression satisfiable if it was, but the assignment is not
+/// implied by the clauses: it discards the models with the other value. The search and the
+/// enumeration of models (which solves again after blocking each model) stay complete either way.
+/// A pure literal cannot explain a conflict, though, so the CDCL engine, whose learned clauses
+/// must follow from the clauses, never applies the rule and ignores this setting.
+///
+/// Keeping the set of pure literals up to date costs a check on every change to the occurrence
+/// lists, which `Preprocessing` only pays until the first decision and `Disabled` never pays.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PureLiteralMode {
+    Disabled,
+    /// Assigns the pure literals only before the first decision.
+    Preprocessing,
+    /// Assigns the pure literals that arise anywhere in the search.
+    #[default]
+    Dynamic,
+}
+
+/// Which value the search tries first when it branches on a variable. The preferred values given
+/// with [`Expression::set_phase`] take precedence over both. The
+/// [`CdclSolver`](crate::cdcl::CdclSolver) takes the mode too, but no preferred values.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PolarityMode {
+    /// The value the heuristic proposes, which is to make the more frequent literal true.
+    #[default]
+    Heuristic,
+    /// Phase saving: the value the variable last had before the search backtracked over it, or
+    /// the one the heuristic proposes if it was never assigned. This returns to the parts of an
+    /// assignment that satisfied their clauses before an unrelated conflict undid them.
+    Saved,
 }
 
 pub struct Expression {
     clauses: Vec<Clause>,
-    variables: HashSet<Variable>,
+    variables: FnvHashSet<Variable>,
     actions: Arc<RwLock<Stack<Action>>>,
-    assignments: HashMap<Variable, bool>,
-
-    literal_to_clause: HashMap<Literal, HashSet<ClauseId>>,
-    unit_clauses: HashSet<ClauseId>,
-    pure_literals: HashSet<Literal>,
-    num_active_clauses: u16,
+    /// The reason of a unit propagation is the clause that became unit. Decisions and pure
+    /// literals have no reason.
+    trail: Trail,
+
+    literal_to_clause: FnvHashMap<Literal, FnvHashSet<ClauseId>>,
+    unit_clauses: FnvHashSet<ClauseId>,
+    pure_literals: FnvHashSet<Literal>,
+    pure_literal_mode: PureLiteralMode,
+    /// Whether `pure_literals` is kept up to date, which stops at the first decision in
+    /// `Preprocessing` mode.
+    tracks_pure_literals: bool,
+    num_active_clauses: u32,
     num_empty_clauses: usize,
     max_clause_length: usize,
     pub heuristic: SolverHeuristic,
+    rng: RefCell<Rng>,
+    phases: FnvHashMap<Variable, bool>,
+    polarity_mode: PolarityMode,
+    /// The last value of every variable the search backtracked over, in `Saved` mode.
+    saved_phases: Vec<Option<bool>>,
+    branching_model: Option<Arc<dyn BranchingModel>>,
+    occurrences: RefCell<OccurrenceHeap>,
+    /// Kept once the heuristic was set to [`SolverHeuristic::Vsids`].
+    activities: RefCell<Option<Activities>>,
+    /// The variables by decreasing initial activity, see [`Expression::import_ordering`].
+    ordering: Vec<Variable>,
+    /// The groups of variables the search branches on in order, see
+    /// [`Expression::set_variable_groups`].
+    groups: Vec<Vec<Variable>>,
+    /// Set in paranoid mode, see [`Expression::set_paranoid`].
+    paranoid: Option<Box<ParanoidState>>,
+    /// The native cardinality constraints, see [`Expression::add_at_most`].
+    cardinality_constraints: Vec<CardinalityConstraint>,
+    literal_to_cardinality: FnvHashMap<Literal, Vec<usize>>,
+    /// The constraints with as many true literals as their bound and some literals unassigned,
+    /// which are implied false.
+    saturated_constraints: FnvHashSet<usize>,
+    /// The constraints that some assignment of the unassigned literals would still violate.
+    num_active_constraints: usize,
+    num_violated_constraints: usize,
+    /// The native parity constraints, see [`Expression::add_xor`].
+    #[cfg(feature = "xor")]
+    xor_constraints: Vec<XorConstraint>,
+    #[cfg(feature = "xor")]
+    xor_variables: FnvHashSet<Variable>,
+    /// The elimination of the parity constraints under the current assignment, computed when
+    /// first needed and dropped by every assignment and backtrack.
+    #[cfg(feature = "xor")]
+    xor_elimination: RefCell<Option<XorElimination>>,
+
+    comments: Vec<String>,
+    metadata: Vec<(String, String)>,
+}
+
+/// A native constraint that at most `bound` of the literals hold, with the counts of the literals
+/// that are currently true and false.
+#[derive(Clone, Debug)]
+struct CardinalityConstraint {
+    literals: Vec<Literal>,
+    bound: usize,
+    num_true: usize,
+    num_false: usize,
+}
+
+impl CardinalityConstraint {
+    fn is_active(&self) -> bool {
+        self.literals.len() - self.num_false > self.bound
+    }
+
+    fn is_saturated(&self) -> bool {
+        self.num_true == self.bound && self.num_true + self.num_false < self.literals.len()
+    }
+
+    fn is_violated(&self) -> bool {
+        self.num_true > self.bound
+    }
+}
+
+/// A native constraint that an odd number of the variables hold if `parity` is true, and an even
+/// number otherwise.
+#[cfg(feature = "xor")]
+#[derive(Clone, Debug)]
+struct XorConstraint {
+    variables: Vec<Variable>,
+    parity: bool,
+}
+
+/// What the Gauss-Jordan elimination of the parity constraints, with the assigned variables
+/// substituted, tells about the current assignment.
+#[cfg(feature = "xor")]
+#[derive(Clone, Debug, Default)]
+struct XorElimination {
+    /// Some constraint reduced to `0 = 1`.
+    conflict: bool,
+    /// The unassigned literals that the constraints together imply, one per row of a single
+    /// variable.
+    implied: Vec<Literal>,
+    /// An unassigned variable that the constraints leave free, in a row of several variables.
+    open: Option<Variable>,
+}
+
+#[cfg(feature = "xor")]
+impl XorElimination {
+    fn is_decided(&self) -> bool {
+        !self.conflict && self.implied.is_empty() && self.open.is_none()
+    }
+}
+
+/// Lazily updated max-heaps of the occurrence counts of the literals and variables, so the
+/// occurrence heuristics do not rescan every literal at each decision.
+///
+/// Changes to the occurrence lists only mark the literal as dirty. Before a decision, the dirty
+/// literals are pushed with their current counts, and entries whose count is out of date are
+/// dropped as they reach the top. Ties go to the smaller variable, and to its positive literal.
+#[derive(Clone, Debug, Default)]
+struct OccurrenceHeap {
+    literals: BinaryHeap<(usize, Reverse<Variable>, bool)>,
+    variables: BinaryHeap<(usize, Reverse<Variable>)>,
+    dirty: Vec<Literal>,
+    /// Whether each literal is in `dirty`, indexed by [`literal_index`].
+    is_dirty: Vec<bool>,
+    /// Whether the dirty literals were last pushed onto the heap of variables, if ever.
+    by_variable: Option<bool>,
+}
+
+impl OccurrenceHeap {
+    fn memory_bytes(&self) -> usize {
+        self.literals.capacity() * std::mem::size_of::<(usize, Reverse<Variable>, bool)>()
+            + self.variables.capacity() * std::mem::size_of::<(usize, Reverse<Variable>)>()
+            + vec_bytes(&self.dirty)
+            + vec_bytes(&self.is_dirty)
+    }
+
+    fn mark(&mut self, literal: Literal) {
+        let index = literal_index(literal);
+        if index >= self.is_dirty.len() {
+            self.is_dirty.resize(index + 2, false);
+        }
+        if !self.is_dirty[index] {
+            self.is_dirty[index] = true;
+            self.dirty.push(literal);
+        }
+    }
+}
+
+/// The factor by which the activities of the variables decay at every conflict.
+pub(crate) const ACTIVITY_DECAY: f64 = 0.95;
+
+/// The activities of the variables for [`SolverHeuristic::Vsids`].
+///
+/// Rather than decaying every activity at each conflict, the bump grows by `1 / ACTIVITY_DECAY`,
+/// and all the activities are scaled down once they get too large. The heap is updated lazily
+/// like [`OccurrenceHeap`]: bumped and unassigned variables are pushed again, and entries of
+/// assigned variables or with an old activity are dropped as they reach the top.
+#[derive(Clone, Debug)]
+struct Activities {
+    /// Indexed by variable.
+    scores: Vec<f64>,
+    bump: f64,
+    /// The activities as bits, which order like the non-negative activities themselves.
+    heap: BinaryHeap<(u64, Reverse<Variable>)>,
+    /// The literals of every clause, which the search removes from the clauses themselves.
+    clauses: ClauseArena,
+}
+
+impl Activities {
+    fn push(&mut self, variable: Variable) {
+        let index = variable as usize;
+        if index >= self.scores.len() {
+            self.scores.resize(index + 1, 0.0);
+        }
+        self.heap
+            .push((self.scores[index].to_bits(), Reverse(variable)));
+    }
+
+    /// Bumps the variables of the clause that became empty, and decays the others.
+    fn bump_clause(&mut self, clause_id: ClauseId) {
+        for position in 0..self.clauses.get(clause_id as usize).len() {
+            let variable = to_variable(self.clauses.get(clause_id as usize)[position]);
+            self.scores[variable as usize] += self.bump;
+            if self.scores[variable as usize] > 1e100 {
+                for score in &mut self.scores {
+                    *score *= 1e-100;
+                }
+                self.bump *= 1e-100;
+                let variables: Vec<Variable> = self
+                    .heap
+                    .drain()
+                    .map(|(_, Reverse(variable))| variable)
+                    .collect();
+                for variable in variables {
+                    self.push(variable);
+                }
+            }
+            self.push(variable);
+        }
+        self.bump /= ACTIVITY_DECAY;
+    }
+
+    fn memory_bytes(&self) -> usize {
+        vec_bytes(&self.scores)
+            + self.heap.capacity() * std::mem::size_of::<(u64, Reverse<Variable>)>()
+            + self.clauses.get_allocated_bytes()
+    }
 }
 
 impl Clone for Expression {
     fn clone(&self) -> Self {
+        let mut new_expression = self.clone_clauses();
+        for constraint in &self.cardinality_constraints {
+            new_expression.add_at_most(&constraint.literals, constraint.bound);
+        }
+        self.copy_xor_constraints(&mut new_expression);
+        new_expression
+    }
+}
+
+impl Expression {
+    /// Returns a copy of the expression and its settings, without its native constraints.
+    fn clone_clauses(&self) -> Expression {
         let mut new_expression = Expression::new();
         for clause in &self.clauses {
             new_expression.add_clause(clause.clone());
         }
+        new_expression.comments = self.comments.clone();
+        new_expression.metadata = self.metadata.clone();
+        new_expression.phases = self.phases.clone();
+        new_expression.branching_model = self.branching_model.clone();
+        new_expression.set_pure_literal_mode(self.pure_literal_mode);
+        new_expression.polarity_mode = self.polarity_mode;
+        new_expression.ordering = self.ordering.clone();
+        new_expression.groups = self.groups.clone();
+        if let Some(paranoid) = &self.paranoid {
+            new_expression.set_paranoid(Some(paranoid.policy));
+        }
 
         new_expression
     }
@@ -53,17 +327,43 @@ impl Expression {
     pub fn new() -> Expression {
         Expression {
             clauses: Vec::new(),
-            variables: HashSet::new(),
+            variables: FnvHashSet::default(),
             actions: Arc::new(RwLock::new(Stack::new(0))),
-            assignments: HashMap::new(),
+            trail: Trail::new(),
 
-            literal_to_clause: HashMap::new(),
-            unit_clauses: HashSet::new(),
-            pure_literals: HashSet::new(),
+            literal_to_clause: FnvHashMap::default(),
+            unit_clauses: FnvHashSet::default(),
+            pure_literals: FnvHashSet::default(),
+            pure_literal_mode: PureLiteralMode::Dynamic,
+            tracks_pure_literals: true,
             num_active_clauses: 0,
             num_empty_clauses: 0,
             max_clause_length: 0,
             heuristic: SolverHeuristic::MostLiteralOccurances,
+            rng: RefCell::new(Rng::new(0)),
+            phases: FnvHashMap::default(),
+            polarity_mode: PolarityMode::Heuristic,
+            saved_phases: Vec::new(),
+            branching_model: None,
+            occurrences: RefCell::new(OccurrenceHeap::default()),
+            activities: RefCell::new(None),
+            ordering: Vec::new(),
+            groups: Vec::new(),
+            paranoid: None,
+            cardinality_constraints: Vec::new(),
+            literal_to_cardinality: FnvHashMap::default(),
+            saturated_constraints: FnvHashSet::default(),
+            num_active_constraints: 0,
+            num_violated_constraints: 0,
+            #[cfg(feature = "xor")]
+            xor_constraints: Vec::new(),
+            #[cfg(feature = "xor")]
+            xor_variables: FnvHashSet::default(),
+            #[cfg(feature = "xor")]
+            xor_elimination: RefCell::new(None),
+
+            comments: Vec::new(),
+            metadata: Vec::new(),
         }
     }
 
@@ -76,38 +376,743 @@ impl Expression {
         expression
     }
 
-    pub fn from_cnf_file(file_name: &str) -> Expression {
-        return parse_dimacs(file_name);
+    /// Builds the expression from clauses over the variables `1..=max_variable`, sizing the
+    /// occurrence lists up front. Gives the same expression as [`Expression::from_clauses`].
+    pub(crate) fn from_clauses_sized(clauses: Vec<Clause>, max_variable: Variable) -> Expression {
+        let mut occurrences = vec![0usize; 2 * max_variable as usize + 2];
+        for clause in &clauses {
+            for literal in clause.literals() {
+                occurrences[literal_index(*literal)] += 1;
+            }
+        }
+
+        let mut expression = Expression::new();
+        expression.literal_to_clause.reserve(2 * max_variable as usize);
+        for variable in 1..=max_variable {
+            let literal = variable as Literal;
+            let positive = occurrences[literal_index(literal)];
+            let negative = occurrences[literal_index(-literal)];
+            if positive + negative == 0 {
+                continue;
+            }
+            expression.variables.insert(variable);
+            for (literal, count) in [(literal, positive), (-literal, negative)] {
+                let clauses = FnvHashSet::with_capacity_and_hasher(count, Default::default());
+                expression.literal_to_clause.insert(literal, clauses);
+                expression.occurrences.get_mut().mark(literal);
+            }
+            if positive == 0 || negative == 0 {
+                expression
+                    .pure_literals
+                    .insert(if negative == 0 { literal } else { -literal });
+            }
+        }
+
+        for (clause_id, clause) in clauses.iter().enumerate() {
+            for literal in clause.literals() {
+                let literal_clauses = expression.literal_to_clause.get_mut(literal).unwrap();
+                literal_clauses.insert(clause_id as ClauseId);
+            }
+            if clause.len() == 1 {
+                expression.unit_clauses.insert(clause_id as ClauseId);
+            }
+            if clause.is_empty() {
+                expression.num_empty_clauses += 1;
+            }
+            expression.max_clause_length = expression.max_clause_length.max(clause.len());
+        }
+        expression.num_active_clauses = clauses.len() as u32;
+        expression.clauses = clauses;
+        expression
+    }
+
+    pub fn from_cnf_file(file_name: &str) -> Result<Expression, ParseError> {
+        parse_dimacs(file_name)
     }
 
     pub fn get_clauses(&self) -> Vec<Clause> {
         self.clauses.clone()
     }
 
+    /// Returns the free-form comments attached to the expression (e.g. the `c` lines of a DIMACS file).
+    pub fn get_comments(&self) -> &Vec<String> {
+        &self.comments
+    }
+
+    pub fn add_comment(&mut self, comment: String) {
+        self.comments.push(comment);
+    }
+
+    /// Returns the key-value metadata attached to the expression, in insertion order.
+    /// In DIMACS files, metadata is stored in `c meta key=value` comment lines.
+    pub fn get_metadata(&self) -> &Vec<(String, String)> {
+        &self.metadata
+    }
+
+    pub fn get_metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a metadata entry, replacing the value of an existing entry with the same key.
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        match self.metadata.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => self.metadata.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Returns the largest variable referenced by the expression, or 0 if there are none.
+    pub fn get_max_variable(&self) -> Variable {
+        self.variables.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Returns the canonical form of the expression: literals are sorted by variable (negative first)
+    /// and deduplicated within each clause, tautological clauses are dropped, and the remaining
+    /// clauses are sorted lexicographically and deduplicated.
+    /// Two expressions with the same canonical form are equal up to clause and literal order.
+    pub fn normalize(&self) -> Expression {
+        let mut clauses: Vec<Vec<Literal>> = self
+            .clauses
+            .iter()
+            .map(|clause| {
+                let mut literals = clause.literals().clone();
+                literals.sort_unstable_by_key(|literal| (to_variable(*literal), *literal));
+                literals.dedup();
+                literals
+            })
+            .filter(|literals| !literals.windows(2).any(|pair| pair[0] == negate(pair[1])))
+            .collect();
+        clauses.sort_unstable();
+        clauses.dedup();
+
+        let mut normalized = Expression::new();
+        for literals in clauses {
+            let mut clause = Clause::new();
+            for literal in literals {
+                clause.insert(literal);
+            }
+            normalized.add_clause(clause);
+        }
+        normalized.comments = self.comments.clone();
+        normalized.metadata = self.metadata.clone();
+
+        normalized
+    }
+
+    /// Returns a hash of the canonical form of the expression, which is stable across runs,
+    /// platforms and crate versions, and can be used as a cache key or fixture fingerprint.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = FnvHasher::new();
+        for clause in self.normalize().clauses {
+            for literal in clause.literals() {
+                hasher.write(&(*literal as i64).to_le_bytes());
+            }
+            hasher.write(&0i64.to_le_bytes());
+        }
+
+        hasher.finish()
+    }
+
     pub fn set_heuristic(&mut self, heuristic: SolverHeuristic) {
         self.heuristic = heuristic;
+        if matches!(heuristic, SolverHeuristic::Vsids) && self.activities.get_mut().is_none() {
+            let mut activities = Activities {
+                scores: vec![0.0; self.get_max_variable() as usize + 1],
+                bump: 1.0,
+                heap: BinaryHeap::new(),
+                clauses: ClauseArena::new(),
+            };
+            let num_ordered = self.ordering.len();
+            for (rank, variable) in self.ordering.iter().enumerate() {
+                if let Some(score) = activities.scores.get_mut(*variable as usize) {
+                    // Below the first bump, so the conflicts of the search soon take over
+                    *score = (num_ordered - rank) as f64 / (num_ordered + 1) as f64;
+                }
+            }
+            for clause in &self.clauses {
+                activities.clauses.push(clause.literals());
+            }
+            for variable in &self.variables {
+                activities.push(*variable);
+            }
+            *self.activities.get_mut() = Some(activities);
+        }
+    }
+
+    /// Returns the variables by decreasing activity of [`SolverHeuristic::Vsids`], ties going to
+    /// the smaller variable, for a later search of a similar expression to start from with
+    /// [`Expression::import_ordering`]. Without the activities of that heuristic, this returns the
+    /// imported ordering.
+    pub fn export_ordering(&self) -> Vec<Variable> {
+        let activities = self.activities.borrow();
+        let Some(activities) = activities.as_ref() else {
+            return self.ordering.clone();
+        };
+        let mut variables: Vec<Variable> = self.variables.iter().copied().collect();
+        variables.sort_unstable_by(|first, second| {
+            let score = |variable: &Variable| activities.scores[*variable as usize];
+            score(second)
+                .total_cmp(&score(first))
+                .then(first.cmp(second))
+        });
+        variables
+    }
+
+    /// Makes [`SolverHeuristic::Vsids`] start from the ordering, as exported by
+    /// [`Expression::export_ordering`]: the earlier a variable comes, the higher its initial
+    /// activity, and variables left out start lowest. The conflicts of the search then change the
+    /// ordering as usual. Importing resets the activities, so it belongs before the search.
+    pub fn import_ordering(&mut self, ordering: &[Variable]) {
+        self.ordering = ordering.to_vec();
+        if self.activities.get_mut().take().is_some() {
+            self.set_heuristic(SolverHeuristic::Vsids);
+        }
+    }
+
+    /// Sets when the search assigns pure literals. Enabling them again finds the pure literals
+    /// of the remaining clauses.
+    pub fn set_pure_literal_mode(&mut self, mode: PureLiteralMode) {
+        if mode == self.pure_literal_mode {
+            return;
+        }
+        self.pure_literal_mode = mode;
+        self.tracks_pure_literals = mode != PureLiteralMode::Disabled;
+        self.pure_literals.clear();
+        if self.tracks_pure_literals {
+            let mut variables: Vec<Variable> = self.variables.iter().copied().collect();
+            variables.sort_unstable();
+            for variable in variables {
+                if !self.trail.is_assigned(variable) {
+                    self.check_pure_literal(variable as Literal);
+                }
+            }
+        }
+    }
+
+    pub fn get_pure_literal_mode(&self) -> PureLiteralMode {
+        self.pure_literal_mode
+    }
+
+    /// Seeds the generator used by the randomized parts of the search.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = RefCell::new(Rng::new(seed));
+    }
+
+    /// Sets the preferred value of a variable: the search tries it first whenever it branches on
+    /// the variable, and gives it to the variable if no clause constrains it any more.
+    pub fn set_phase(&mut self, variable: Variable, value: bool) {
+        self.phases.insert(variable, value);
+    }
+
+    /// Sets which value the search tries first when it branches, see [`PolarityMode`]. Leaving
+    /// `Saved` mode forgets the saved values.
+    pub fn set_polarity_mode(&mut self, mode: PolarityMode) {
+        self.polarity_mode = mode;
+        if mode != PolarityMode::Saved {
+            self.saved_phases = Vec::new();
+        }
+    }
+
+    pub fn get_polarity_mode(&self) -> PolarityMode {
+        self.polarity_mode
+    }
+
+    /// Makes the search branch on the variables the model scores highest, instead of following
+    /// the heuristic. The model is shared with the clones of the expression.
+    pub fn set_branching_model(&mut self, model: Arc<dyn BranchingModel>) {
+        self.branching_model = Some(model);
+    }
+
+    /// Makes the search branch on the variables of the first group until each of them is assigned
+    /// or occurs in no remaining clause, then on the variables of the second, and so on, before
+    /// the variables in no group. Within a group, it branches on the variable with the most
+    /// occurrences in the remaining clauses, the first one in case of ties. This suits encodings
+    /// unrolled over time steps, such as planning and bounded model checking, with a group per
+    /// step. The groups come before the heuristic and the branching model, which choose among the
+    /// variables in no group.
+    pub fn set_variable_groups(&mut self, groups: Vec<Vec<Variable>>) {
+        self.groups = groups;
+    }
+
+    pub fn get_variable_groups(&self) -> &[Vec<Variable>] {
+        &self.groups
+    }
+
+    /// Adds the native constraint that at most `bound` of the literals hold, taken as a set.
+    ///
+    /// The DPLL search propagates the constraint by counting its true and false literals rather
+    /// than through clauses: once `bound` of them are true, the others are implied false, and one
+    /// more true is a conflict. Encoding it to clauses instead takes a number of clauses or
+    /// auxiliary variables growing with the product of the number of literals and the bound. The
+    /// CDCL engine, like the other users of [`Expression::get_clauses`], sees the constraints
+    /// through [`Expression::encode_cardinality_constraints`]. Must be called before the search.
+    pub fn add_at_most(&mut self, literals: &[Literal], bound: usize) {
+        assert!(
+            self.trail.is_empty(),
+            "The cardinality constraints must be added before the search"
+        );
+        let mut unique: Vec<Literal> = Vec::with_capacity(literals.len());
+        for literal in literals {
+            if !unique.contains(literal) {
+                unique.push(*literal);
+            }
+        }
+        if unique.len() <= bound {
+            return;
+        }
+
+        let id = self.cardinality_constraints.len();
+        for literal in &unique {
+            self.variables.insert(to_variable(*literal));
+            self.literal_to_cardinality
+                .entry(*literal)
+                .or_default()
+                .push(id);
+            // Making a literal of the constraint true can violate it, so it is never pure
+            self.pure_literals.remove(literal);
+        }
+        let constraint = CardinalityConstraint {
+            literals: unique,
+            bound,
+            num_true: 0,
+            num_false: 0,
+        };
+        if constraint.is_saturated() {
+            self.saturated_constraints.insert(id);
+        }
+        self.num_active_constraints += 1;
+        self.cardinality_constraints.push(constraint);
+    }
+
+    /// Adds the native constraint that at least `bound` of the literals hold, as the constraint
+    /// that at most all but `bound` of their negations hold. See [`Expression::add_at_most`].
+    pub fn add_at_least(&mut self, literals: &[Literal], bound: usize) {
+        let mut negations: Vec<Literal> = Vec::with_capacity(literals.len());
+        for literal in literals {
+            if !negations.contains(&negate(*literal)) {
+                negations.push(negate(*literal));
+            }
+        }
+        match negations.len().checked_sub(bound) {
+            Some(bound) => self.add_at_most(&negations, bound),
+            None => self.add_clause(Clause::new()),
+        }
+    }
+
+    /// Returns the literals and the bound of every cardinality constraint.
+    pub fn get_cardinality_constraints(&self) -> Vec<(Vec<Literal>, usize)> {
+        self.cardinality_constraints
+            .iter()
+            .map(|constraint| (constraint.literals.clone(), constraint.bound))
+            .collect()
+    }
+
+    /// Returns true if the assignment satisfies every cardinality constraint. Variables missing
+    /// from the assignment are treated as false.
+    pub fn satisfies_cardinality_constraints(&self, assignment: &Assignment) -> bool {
+        self.cardinality_constraints.iter().all(|constraint| {
+            let num_true = constraint
+                .literals
+                .iter()
+                .filter(|literal| {
+                    assignment
+                        .get(&to_variable(**literal))
+                        .copied()
+                        .unwrap_or(false)
+                        == (**literal > 0)
+                })
+                .count();
+            num_true <= constraint.bound
+        })
+    }
+
+    /// Returns the expression with its cardinality constraints encoded to clauses by the
+    /// sequential counter of Sinz, for the users of the clauses alone. At most `k` of `n` literals
+    /// take `(n - 1)·k` auxiliary variables, numbered after the variables of the expression.
+    pub fn encode_cardinality_constraints(&self) -> Expression {
+        let mut encoded = self.clone_clauses();
+        self.copy_xor_constraints(&mut encoded);
+        let mut next_variable = self.get_max_variable() as usize + 1;
+        let mut add = |literals: &[Literal]| encoded.add_clause(Clause::from_literals(literals));
+        for constraint in &self.cardinality_constraints {
+            let (literals, bound) = (&constraint.literals, constraint.bound);
+            if bound == 0 {
+                for literal in literals {
+                    add(&[negate(*literal)]);
+                }
+                continue;
+            }
+            // counters[i][j] is true if more than j of the first i + 1 literals hold
+            let counters: Vec<Vec<Literal>> = (0..literals.len() - 1)
+                .map(|_| {
+                    let first = next_variable;
+                    next_variable += bound;
+                    assert!(
+                        next_variable <= Literal::MAX as usize + 1,
+                        "The cardinality constraints need more variables than are available"
+                    );
+                    (first..first + bound)
+                        .map(|variable| variable as Literal)
+                        .collect()
+                })
+                .collect();
+            for (i, literal) in literals.iter().enumerate() {
+                if i + 1 < literals.len() {
+                    add(&[-literal, counters[i][0]]);
+                }
+                if i == 0 {
+                    continue;
+                }
+                let previous = &counters[i - 1];
+                // The last counter of the previous literals is full, so the literal must be false
+                add(&[-literal, -previous[bound - 1]]);
+                if i + 1 < literals.len() {
+                    for j in 0..bound {
+                        add(&[-previous[j], counters[i][j]]);
+                        if j > 0 {
+                            add(&[-literal, -previous[j - 1], counters[i][j]]);
+                        }
+                    }
+                }
+            }
+        }
+        encoded
+    }
+
+    /// Enables the paranoid mode of [`crate::paranoid`] with the policy, or disables it. The
+    /// expression keeps a copy of its clauses to re-derive its state from, so the search must not
+    /// have started yet.
+    pub fn set_paranoid(&mut self, policy: Option<ParanoidPolicy>) {
+        assert!(
+            self.trail.is_empty(),
+            "The paranoid mode must be set before the search"
+        );
+        self.paranoid =
+            policy.map(|policy| Box::new(ParanoidState::new(policy, self.clauses.clone())));
+    }
+
+    /// Returns the divergences the paranoid mode found so far, empty if it is disabled.
+    pub fn get_divergences(&self) -> &[Divergence] {
+        self.paranoid
+            .as_ref()
+            .map_or(&[], |paranoid| paranoid.divergences.as_slice())
+    }
+
+    /// Checks the state of the expression now, in paranoid mode, recording a divergence if it
+    /// differs from the state re-derived from scratch. Does nothing if the mode is disabled.
+    pub fn check_cached_state(&mut self) {
+        if let Some(mut paranoid) = self.paranoid.take() {
+            let problems = self.find_divergences(&paranoid.original);
+            paranoid.report(problems);
+            self.paranoid = Some(paranoid);
+        }
+    }
+
+    /// Records the operation in paranoid mode, and runs a check if the policy asks for one.
+    fn paranoid_step(&mut self, operation: impl FnOnce() -> String) {
+        let num_actions = self.actions.read().unwrap().len();
+        let due = match &mut self.paranoid {
+            Some(paranoid) => paranoid.record(format!("{} ({} actions)", operation(), num_actions)),
+            None => false,
+        };
+        if due {
+            self.check_cached_state();
+        }
+    }
+
+    /// Re-derives the clauses, the occurrence lists, the unit clauses, the pure literals, the
+    /// clause counters, the counts of the cardinality constraints, the elimination of the parity
+    /// constraints and the occurrence heaps from the original clauses and the trail, and describes
+    /// every way the maintained versions differ from them.
+    fn find_divergences(&self, original: &[Clause]) -> Vec<String> {
+        let mut problems = Vec::new();
+        let value = |literal: Literal| self.trail.literal_value(literal);
+
+        let mut occurrences: FnvHashMap<Literal, FnvHashSet<ClauseId>> = FnvHashMap::default();
+        let mut unit_clauses: FnvHashSet<ClauseId> = FnvHashSet::default();
+        let mut num_active_clauses = 0;
+        let mut num_empty_clauses = 0;
+        for (clause_id, clause) in original.iter().enumerate() {
+            let clause_id = clause_id as ClauseId;
+            // The literals of a satisfied clause stay as they were when it was removed
+            if clause
+                .literals()
+                .iter()
+                .any(|literal| value(*literal) == Some(true))
+            {
+                continue;
+            }
+            num_active_clauses += 1;
+            let mut free: Vec<Literal> = clause
+                .literals()
+                .iter()
+                .copied()
+                .filter(|literal| value(*literal).is_none())
+                .collect();
+            free.sort_unstable();
+            free.dedup();
+            let mut current = self.clauses[clause_id as usize].literals().clone();
+            current.sort_unstable();
+            current.dedup();
+            if current != free {
+                problems.push(format!(
+                    "clause {} has the literals {:?} instead of {:?}",
+                    clause_id, current, free
+                ));
+            }
+            match free.len() {
+                0 => num_empty_clauses += 1,
+                1 => {
+                    unit_clauses.insert(clause_id);
+                }
+                _ => {}
+            }
+            for literal in free {
+                occurrences.entry(literal).or_default().insert(clause_id);
+            }
+        }
+
+        if self.num_active_clauses as usize != num_active_clauses {
+            problems.push(format!(
+                "{} active clauses instead of {}",
+                self.num_active_clauses, num_active_clauses
+            ));
+        }
+        if self.num_empty_clauses != num_empty_clauses {
+            problems.push(format!(
+                "{} empty clauses instead of {}",
+                self.num_empty_clauses, num_empty_clauses
+            ));
+        }
+        let sorted = |set: &FnvHashSet<ClauseId>| {
+            let mut ids: Vec<ClauseId> = set.iter().copied().collect();
+            ids.sort_unstable();
+            ids
+        };
+        if self.unit_clauses != unit_clauses {
+            problems.push(format!(
+                "the unit clauses are {:?} instead of {:?}",
+                sorted(&self.unit_clauses),
+                sorted(&unit_clauses)
+            ));
+        }
+
+        let empty = FnvHashSet::default();
+        let mut literals: Vec<Literal> = self
+            .literal_to_clause
+            .keys()
+            .chain(occurrences.keys())
+            .copied()
+            .collect();
+        literals.sort_unstable();
+        literals.dedup();
+        for literal in &literals {
+            let current = self.literal_to_clause.get(literal).unwrap_or(&empty);
+            let expected = occurrences.get(literal).unwrap_or(&empty);
+            if current != expected {
+                problems.push(format!(
+                    "{} occurs in the clauses {:?} instead of {:?}",
+                    literal,
+                    sorted(current),
+                    sorted(expected)
+                ));
+            }
+        }
+
+        let count = |literal: Literal| occurrences.get(&literal).map_or(0, |clauses| clauses.len());
+        let pure_literals: FnvHashSet<Literal> = if self.tracks_pure_literals {
+            literals
+                .iter()
+                .copied()
+                .filter(|literal| count(*literal) > 0 && count(-literal) == 0)
+                .filter(|literal| !self.literal_to_cardinality.contains_key(literal))
+                .filter(|literal| !self.is_xor_variable(to_variable(*literal)))
+                .collect()
+        } else {
+            FnvHashSet::default()
+        };
+        if self.pure_literals != pure_literals {
+            let mut current: Vec<Literal> = self.pure_literals.iter().copied().collect();
+            let mut expected: Vec<Literal> = pure_literals.into_iter().collect();
+            current.sort_unstable();
+            expected.sort_unstable();
+            problems.push(format!(
+                "the pure literals are {:?} instead of {:?}",
+                current, expected
+            ));
+        }
+
+        let mut num_active_constraints = 0;
+        let mut num_violated_constraints = 0;
+        let mut saturated_constraints = FnvHashSet::default();
+        for (id, constraint) in self.cardinality_constraints.iter().enumerate() {
+            let count = |expected: bool| {
+                constraint
+                    .literals
+                    .iter()
+                    .filter(|literal| value(**literal) == Some(expected))
+                    .count()
+            };
+            let expected = CardinalityConstraint {
+                num_true: count(true),
+                num_false: count(false),
+                ..constraint.clone()
+            };
+            if (constraint.num_true, constraint.num_false)
+                != (expected.num_true, expected.num_false)
+            {
+                problems.push(format!(
+                    "constraint {} counts {} true and {} false literals instead of {} and {}",
+                    id,
+                    constraint.num_true,
+                    constraint.num_false,
+                    expected.num_true,
+                    expected.num_false
+                ));
+            }
+            num_active_constraints += expected.is_active() as usize;
+            num_violated_constraints += expected.is_violated() as usize;
+            if expected.is_saturated() {
+                saturated_constraints.insert(id);
+            }
+        }
+        if (self.num_active_constraints, self.num_violated_constraints)
+            != (num_active_constraints, num_violated_constraints)
+        {
+            problems.push(format!(
+                "{} active and {} violated constraints instead of {} and {}",
+                self.num_active_constraints,
+                self.num_violated_constraints,
+                num_active_constraints,
+                num_violated_constraints
+            ));
+        }
+        if self.saturated_constraints != saturated_constraints {
+            let mut current: Vec<usize> = self.saturated_constraints.iter().copied().collect();
+            let mut expected: Vec<usize> = saturated_constraints.into_iter().collect();
+            current.sort_unstable();
+            expected.sort_unstable();
+            problems.push(format!(
+                "the saturated constraints are {:?} instead of {:?}",
+                current, expected
+            ));
+        }
+
+        problems.extend(self.find_xor_divergence());
+
+        // Every unassigned literal counted since it was last marked is on the heap it was pushed to
+        let heap = self.occurrences.borrow();
+        let is_dirty = |literal: Literal| {
+            heap.is_dirty
+                .get(literal_index(literal))
+                .copied()
+                .unwrap_or(false)
+        };
+        match heap.by_variable {
+            Some(false) => {
+                let entries: FnvHashSet<_> = heap.literals.iter().copied().collect();
+                for literal in &literals {
+                    let entry = (
+                        count(*literal),
+                        Reverse(to_variable(*literal)),
+                        *literal > 0,
+                    );
+                    if entry.0 > 0 && !is_dirty(*literal) && !entries.contains(&entry) {
+                        problems.push(format!(
+                            "{} is missing from the occurrence heap with the count {}",
+                            literal, entry.0
+                        ));
+                    }
+                }
+            }
+            Some(true) => {
+                let entries: FnvHashSet<_> = heap.variables.iter().copied().collect();
+                let mut variables: Vec<Variable> = literals
+                    .iter()
+                    .map(|literal| to_variable(*literal))
+                    .collect();
+                variables.dedup();
+                for variable in variables {
+                    let literal = variable as Literal;
+                    let entry = (count(literal) + count(-literal), Reverse(variable));
+                    let clean = !is_dirty(literal) && !is_dirty(-literal);
+                    if entry.0 > 0 && clean && !entries.contains(&entry) {
+                        problems.push(format!(
+                            "{} is missing from the occurrence heap with the count {}",
+                            variable, entry.0
+                        ));
+                    }
+                }
+            }
+            None => {}
+        }
+
+        problems
+    }
+
+    /// Returns the current assignment of the search, with the decision level of every assigned
+    /// variable and the clause that propagated it.
+    pub fn get_trail(&self) -> &Trail {
+        &self.trail
+    }
+
+    /// Returns the approximate memory used by the expression and its search state.
+    pub fn memory_report(&self) -> MemoryUsage {
+        let clause_database = vec_bytes(&self.clauses)
+            + self
+                .clauses
+                .iter()
+                .map(|clause| vec_bytes(clause.literals()))
+                .sum::<usize>();
+        let occurrence_lists = map_bytes(&self.literal_to_clause)
+            + self.literal_to_clause.values().map(set_bytes).sum::<usize>();
+        let trail = self.actions.read().unwrap().capacity() * std::mem::size_of::<Action>()
+            + self.trail.memory_bytes();
+        let other = set_bytes(&self.variables)
+            + set_bytes(&self.unit_clauses)
+            + set_bytes(&self.pure_literals)
+            + vec_bytes(&self.cardinality_constraints)
+            + self
+                .cardinality_constraints
+                .iter()
+                .map(|constraint| vec_bytes(&constraint.literals))
+                .sum::<usize>()
+            + self.xor_memory_bytes()
+            + map_bytes(&self.phases)
+            + vec_bytes(&self.saved_phases)
+            + self.occurrences.borrow().memory_bytes()
+            + self
+                .activities
+                .borrow()
+                .as_ref()
+                .map_or(0, |activities| activities.memory_bytes());
+
+        MemoryUsage {
+            clause_database,
+            occurrence_lists,
+            learned_clauses: 0,
+            trail,
+            other,
+        }
     }
 
     /// Softly removes a clause from the expression.
     /// This means that the clause is not actually removed from the expression vector,
     /// but all references to it have been removed from the literals map, so it is unreferenced.
     fn remove_clause(&mut self, clause_id: ClauseId) {
-        // Remove all of the literals in the clause from the variable_to_clause map
+        // Remove the clause from the literal_to_clause entries of all of its literals
         for i in 0..self.clauses[clause_id as usize].len() {
             let literal = unsafe { self.clauses.get_unchecked(clause_id as usize).get(i) };
             let literal_clauses = self.literal_to_clause.get_mut(&literal).unwrap();
+            literal_clauses.remove(&clause_id);
+            self.occurrences.get_mut().mark(literal);
 
-            // If there are no more clauses that contain the literal, the negation is a pure literal
+            // If there are no more clauses that contain the literal, the negation may be a pure literal
             if literal_clauses.is_empty() {
-                // This literal has no more instances.
-                // If its negation has some number of instances, add it to the pure_literals set.
-                let negated_literal = negate(literal);
-                let negated_literal_clauses = self.literal_to_clause.get_mut(&negated_literal);
-
-                if negated_literal_clauses.is_none() || negated_literal_clauses.unwrap().is_empty()
-                {
-                    self.pure_literals.insert(negated_literal);
-                }
+                self.check_pure_literal(literal);
             }
         }
 
@@ -136,6 +1141,7 @@ impl Expression {
                 literal_clauses.insert(clause_id);
                 should_check_pure_literal = literal_clauses.len() == 1;
             }
+            self.occurrences.get_mut().mark(literal);
 
             if should_check_pure_literal {
                 // TODO: Can we avoid doing this check again? Does it do too much?
@@ -146,7 +1152,7 @@ impl Expression {
 
     /// Removes a literal from all of the clauses that it is in
     fn remove_literal_from_clauses(&mut self, literal: Literal) {
-        let clauses_result = self.literal_to_clause.get(&literal);
+        let clauses_result = self.literal_to_clause.get_mut(&literal);
         if clauses_result.is_none() {
             return;
         }
@@ -156,8 +1162,10 @@ impl Expression {
 
         actions.push(Action::RemoveLiteralFromClausesStart());
 
-        let literal_clauses = clauses_result.unwrap();
-        for clause_id in literal_clauses {
+        // The literal no longer occurs in any clause, the entries are restored when undoing
+        let literal_clauses = std::mem::take(clauses_result.unwrap());
+        self.occurrences.get_mut().mark(literal);
+        for clause_id in &literal_clauses {
             let clause = &mut self.clauses[*clause_id as usize];
             clause.remove(literal);
 
@@ -168,6 +1176,9 @@ impl Expression {
             if clause.is_empty() {
                 self.num_empty_clauses += 1;
                 self.unit_clauses.remove(clause_id);
+                if let Some(activities) = self.activities.get_mut() {
+                    activities.bump_clause(*clause_id);
+                }
             }
 
             actions.push(Action::RemoveLiteralFromClause(*clause_id));
@@ -194,6 +1205,9 @@ impl Expression {
     }
 
     fn check_pure_literal(&mut self, literal: Literal) {
+        if !self.tracks_pure_literals {
+            return;
+        }
         let negated_literal = negate(literal);
         let literal_clauses = self.literal_to_clause.get(&literal);
         let has_instances = literal_clauses.is_some() && !literal_clauses.unwrap().is_empty();
@@ -202,10 +1216,14 @@ impl Expression {
         let negated_has_instances =
             negated_literal_clauses.is_some() && !negated_literal_clauses.unwrap().is_empty();
 
-        if has_instances && !negated_has_instances {
+        let in_constraints = |literal: Literal| {
+            self.literal_to_cardinality.contains_key(&literal)
+                || self.is_xor_variable(to_variable(literal))
+        };
+        if has_instances && !negated_has_instances && !in_constraints(literal) {
             self.pure_literals.insert(literal);
             self.pure_literals.remove(&negated_literal);
-        } else if !has_instances && negated_has_instances {
+        } else if !has_instances && negated_has_instances && !in_constraints(negated_literal) {
             self.pure_literals.insert(negated_literal);
             self.pure_literals.remove(&literal);
         } else {
@@ -214,19 +1232,27 @@ impl Expression {
         }
     }
 
-    fn assign_variable(&mut self, variable: Variable, value: bool) {
-
-        self.assignments.insert(variable, value);
-        self.actions
-            .write()
-            .unwrap()
-            .push(Action::AssignVariable(variable));
+    fn assign_variable(&mut self, variable: Variable, value: bool, reason: Option<ClauseId>) {
         let literal = if value {
             variable as Literal
         } else {
             -(variable as Literal)
         };
+        self.trail
+            .assign(literal, reason.map(|clause_id| clause_id as usize));
+        if self.is_xor_variable(variable) {
+            self.forget_xor_elimination();
+        }
         let negated_literal = negate(literal);
+        if self.literal_to_cardinality.contains_key(&literal)
+            || self.literal_to_cardinality.contains_key(&negated_literal)
+        {
+            self.count_cardinality_literal(literal, true);
+            self.actions
+                .write()
+                .unwrap()
+                .push(Action::CountCardinalityLiteral(literal));
+        }
         self.remove_clauses_with_literal(literal);
         self.remove_literal_from_clauses(negated_literal);
 
@@ -234,8 +1260,71 @@ impl Expression {
         self.pure_literals.remove(&negated_literal);
     }
 
-    fn unassign_variable(&mut self, variable: Variable) {
-        self.assignments.remove(&variable);
+    /// Counts the literal as true, and its negation as false, in the cardinality constraints over
+    /// them, or takes them out of the counts when undoing.
+    fn count_cardinality_literal(&mut self, literal: Literal, assigned: bool) {
+        for (literal, value) in [(literal, true), (negate(literal), false)] {
+            let Some(ids) = self.literal_to_cardinality.get(&literal) else {
+                continue;
+            };
+            for id in ids {
+                let constraint = &mut self.cardinality_constraints[*id];
+                let was_active = constraint.is_active();
+                let was_violated = constraint.is_violated();
+                let count = if value {
+                    &mut constraint.num_true
+                } else {
+                    &mut constraint.num_false
+                };
+                if assigned {
+                    *count += 1;
+                } else {
+                    *count -= 1;
+                }
+
+                match (was_active, constraint.is_active()) {
+                    (true, false) => self.num_active_constraints -= 1,
+                    (false, true) => self.num_active_constraints += 1,
+                    _ => {}
+                }
+                match (was_violated, constraint.is_violated()) {
+                    (false, true) => self.num_violated_constraints += 1,
+                    (true, false) => self.num_violated_constraints -= 1,
+                    _ => {}
+                }
+                if constraint.is_saturated() {
+                    self.saturated_constraints.insert(*id);
+                } else {
+                    self.saturated_constraints.remove(id);
+                }
+            }
+        }
+    }
+
+    /// Makes an unassigned literal of a saturated cardinality constraint false, returning its
+    /// negation, or `None` if no constraint is saturated. This is the unit propagation of the
+    /// constraints, which the DPLL search runs after the one of the clauses.
+    pub fn remove_cardinality_implication(&mut self) -> Option<Literal> {
+        let id = *self.saturated_constraints.iter().next()?;
+        let literal = *self.cardinality_constraints[id]
+            .literals
+            .iter()
+            .find(|literal| self.trail.literal_value(**literal).is_none())
+            .unwrap();
+
+        self.assign_variable(to_variable(literal), literal < 0, None);
+        self.paranoid_step(|| format!("propagate {} from constraint {}", -literal, id));
+        Some(negate(literal))
+    }
+
+    /// Returns an unassigned literal of an active cardinality constraint, to make false.
+    fn get_cardinality_literal(&self) -> Option<Literal> {
+        self.cardinality_constraints
+            .iter()
+            .filter(|constraint| constraint.is_active())
+            .flat_map(|constraint| constraint.literals.iter())
+            .find(|literal| self.trail.literal_value(**literal).is_none())
+            .copied()
     }
 
     pub fn optimize(&mut self) {
@@ -246,81 +1335,267 @@ impl Expression {
     }
 
     pub fn is_satisfied_by(&self, assignment: &Assignment) -> bool {
-        for clause in &self.clauses {
-            let mut satisfied = false;
-            for literal in clause.literals() {
-                let variable = to_variable(*literal);
-                let value = assignment.get(&variable);
-                if value.is_none() {
-                    continue;
-                }
+        if !self.satisfies_cardinality_constraints(assignment)
+            || !self.satisfies_xor_constraints(assignment)
+        {
+            return false;
+        }
+        self.clauses
+            .iter()
+            .all(|clause| clause.is_satisfied_by(assignment))
+    }
+
+    /// Returns true if every model of the expression satisfies the clause, so adding it would not
+    /// change the set of models. This takes a SAT call on the expression with the clause negated.
+    pub fn is_implied(&self, clause: &Clause) -> bool {
+        let mut negation = self.clone();
+        for literal in clause.literals() {
+            let mut unit = Clause::new();
+            unit.insert(negate(*literal));
+            negation.add_clause(unit);
+        }
+
+        negation.optimize();
+        solve_dpll(&mut negation).is_none()
+    }
+
+    /// Returns true if some clause of the expression is a subset of the clause. Unlike
+    /// [`Expression::is_implied`], this is a cheap syntactic check.
+    pub fn is_subsumed(&self, clause: &Clause) -> bool {
+        self.clauses.iter().any(|existing| {
+            existing
+                .literals()
+                .iter()
+                .all(|literal| clause.contains(*literal))
+        })
+    }
+
+    /// Returns the number of active clauses the literal occurs in.
+    fn count_occurrences(&self, literal: Literal) -> usize {
+        self.literal_to_clause
+            .get(&literal)
+            .map_or(0, |clauses| clauses.len())
+    }
 
-                if *value.unwrap() == (*literal > 0) {
-                    satisfied = true;
-                    break;
+    /// Pushes the dirty literals (or their variables) with their current counts onto the heap of
+    /// literals (or of variables), rebuilding it instead once the out-of-date entries outnumber
+    /// the literals. Only the heap the heuristic uses is kept up to date.
+    fn update_occurrences(&self, by_variable: bool) {
+        let mut occurrences = self.occurrences.borrow_mut();
+        let occurrences = &mut *occurrences;
+        let limit = 4 * self.literal_to_clause.len() + 64;
+        let heap_len = if by_variable {
+            occurrences.variables.len()
+        } else {
+            occurrences.literals.len()
+        };
+        let rebuild = occurrences.by_variable != Some(by_variable)
+            || heap_len + occurrences.dirty.len() > limit;
+        occurrences.by_variable = Some(by_variable);
+        let literals: Vec<Literal> = if rebuild {
+            occurrences.literals.clear();
+            occurrences.variables.clear();
+            for literal in occurrences.dirty.drain(..) {
+                occurrences.is_dirty[literal_index(literal)] = false;
+            }
+            self.literal_to_clause.keys().copied().collect()
+        } else {
+            let dirty = std::mem::take(&mut occurrences.dirty);
+            for literal in &dirty {
+                occurrences.is_dirty[literal_index(*literal)] = false;
+            }
+            dirty
+        };
+
+        if !by_variable {
+            for literal in literals {
+                let count = self.count_occurrences(literal);
+                if count > 0 {
+                    occurrences
+                        .literals
+                        .push((count, Reverse(to_variable(literal)), literal > 0));
                 }
             }
+            return;
+        }
+
+        let mut variables: Vec<Variable> = literals.into_iter().map(to_variable).collect();
+        variables.sort_unstable();
+        variables.dedup();
+        for variable in variables {
+            let literal = variable as Literal;
+            let count = self.count_occurrences(literal) + self.count_occurrences(-literal);
+            if count > 0 {
+                occurrences.variables.push((count, Reverse(variable)));
+            }
+        }
+    }
+
+    fn get_most_literal_occurances(&self) -> (Variable, bool) {
+        self.update_occurrences(false);
+        let mut occurrences = self.occurrences.borrow_mut();
+        while let Some((count, Reverse(variable), positive)) = occurrences.literals.peek().copied()
+        {
+            let literal = if positive {
+                variable as Literal
+            } else {
+                -(variable as Literal)
+            };
+            if count == self.count_occurrences(literal) && !self.trail.is_assigned(variable) {
+                return (variable, positive);
+            }
+            occurrences.literals.pop();
+        }
 
-            if !satisfied {
-                return false;
+        panic!("No branch variable found");
+    }
+
+    fn get_most_variable_occurances(&self) -> (Variable, bool) {
+        self.update_occurrences(true);
+        let mut occurrences = self.occurrences.borrow_mut();
+        while let Some((count, Reverse(variable))) = occurrences.variables.peek().copied() {
+            let literal = variable as Literal;
+            let current = self.count_occurrences(literal) + self.count_occurrences(-literal);
+            if count == current && !self.trail.is_assigned(variable) {
+                return (variable, true);
             }
+            occurrences.variables.pop();
         }
 
-        true
+        panic!("No branch variable found");
     }
 
-    fn get_most_literal_occurances(&self) -> (Variable, bool) {
-        let mut max_occurances = 0;
-        let mut best_literal = 0;
+    fn get_model_variable(&self, model: &dyn BranchingModel) -> (Variable, bool) {
+        let occurrences = |literal: Literal| {
+            self.literal_to_clause
+                .get(&literal)
+                .map_or(0, |clauses| clauses.len())
+        };
+        let binary_occurrences = |literal: Literal| {
+            self.literal_to_clause.get(&literal).map_or(0, |clauses| {
+                clauses
+                    .iter()
+                    .filter(|clause_id| self.clauses[**clause_id as usize].len() == 2)
+                    .count()
+            })
+        };
 
-        for literal_clause in &self.literal_to_clause {
-            let literal = literal_clause.0;
-            if literal_clause.1.is_empty() || self.assignments.contains_key(&to_variable(*literal))
-            {
+        let mut best: Option<(f64, VariableSnapshot)> = None;
+        for variable in &self.variables {
+            if self.trail.is_assigned(*variable) {
                 continue;
             }
 
-            let occurances = literal_clause.1.len();
-            if occurances > max_occurances {
-                max_occurances = occurances;
-                best_literal = *literal;
+            let literal = *variable as Literal;
+            let positive_occurrences = occurrences(literal);
+            let negative_occurrences = occurrences(-literal);
+            let snapshot = VariableSnapshot {
+                variable: *variable,
+                positive_occurrences,
+                negative_occurrences,
+                binary_occurrences: binary_occurrences(literal) + binary_occurrences(-literal),
+                depth: self.trail.len(),
+                base_score: (positive_occurrences + negative_occurrences) as f64,
+            };
+            let score = model.score(&snapshot);
+            if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+                best = Some((score, snapshot));
             }
         }
 
-        if best_literal != 0 {
-            return (to_variable(best_literal), best_literal > 0);
+        match best {
+            Some((_, snapshot)) => (snapshot.variable, model.value(&snapshot)),
+            None => panic!("No branch variable found"),
         }
+    }
 
-        panic!("No branch variable found");
+    /// Returns the variable of the first group that is not exhausted with the most occurrences,
+    /// and the value satisfying more clauses, or `None` once every group is.
+    fn get_group_variable(&self) -> Option<(Variable, bool)> {
+        for group in &self.groups {
+            let mut best: Option<(usize, Variable, bool)> = None;
+            for variable in group {
+                if self.trail.is_assigned(*variable) {
+                    continue;
+                }
+                let positive = self.count_occurrences(*variable as Literal);
+                let negative = self.count_occurrences(-(*variable as Literal));
+                if positive + negative > best.map_or(0, |(count, _, _)| count) {
+                    best = Some((positive + negative, *variable, positive >= negative));
+                }
+            }
+            if let Some((_, variable, value)) = best {
+                return Some((variable, value));
+            }
+        }
+        None
     }
 
-    fn get_most_variable_occurances(&self) -> (Variable, bool) {
-        let mut max_occurances = 0;
-        let mut best_variable = 0;
+    /// Returns the value to try first for the variable, given the one the heuristic proposes.
+    fn get_phase(&self, variable: Variable, value: bool) -> bool {
+        let saved = || self.saved_phases.get(variable as usize).copied().flatten();
+        self.phases
+            .get(&variable)
+            .copied()
+            .or_else(saved)
+            .unwrap_or(value)
+    }
 
-        for variable in &self.variables {
-            let positive_literal = *variable as Literal;
-            let negative_literal = -positive_literal;
+    fn get_most_active_variable(&self) -> (Variable, bool) {
+        let mut activities = self.activities.borrow_mut();
+        let activities = activities
+            .as_mut()
+            .expect("The activities are kept from setting the heuristic");
+        if activities.heap.len() > 4 * activities.scores.len() + 64 {
+            let variables: Vec<Variable> = activities
+                .heap
+                .drain()
+                .map(|(_, Reverse(variable))| variable)
+                .filter(|variable| !self.trail.is_assigned(*variable))
+                .collect();
+            for variable in variables {
+                activities.push(variable);
+            }
+        }
 
-            if self.assignments.contains_key(variable) {
+        // Variables that occur in no remaining clause are skipped, but kept for later
+        let mut skipped = Vec::new();
+        let mut best = None;
+        while let Some((bits, Reverse(variable))) = activities.heap.peek().copied() {
+            if self.trail.is_assigned(variable)
+                || bits != activities.scores[variable as usize].to_bits()
+            {
+                activities.heap.pop();
                 continue;
             }
-
-            let positive_occurances = self.literal_to_clause.get(&positive_literal).unwrap().len();
-            let negative_occurances = self.literal_to_clause.get(&negative_literal).unwrap().len();
-
-            let occurances = positive_occurances + negative_occurances;
-            if occurances > max_occurances {
-                max_occurances = occurances;
-                best_variable = *variable;
+            let literal = variable as Literal;
+            let positive = self.count_occurrences(literal);
+            let negative = self.count_occurrences(-literal);
+            if positive + negative > 0 {
+                best = Some((variable, positive >= negative));
+                break;
             }
+            skipped.push(activities.heap.pop().unwrap());
         }
+        activities.heap.extend(skipped);
+
+        best.expect("No branch variable found")
+    }
 
-        if best_variable != 0 {
-            return (best_variable, true);
+    fn get_random_variable(&self) -> (Variable, bool) {
+        let unassigned: Vec<Variable> = self
+            .variables
+            .iter()
+            .filter(|variable| !self.trail.is_assigned(**variable))
+            .copied()
+            .collect();
+        if unassigned.is_empty() {
+            panic!("No branch variable found");
         }
 
-        panic!("No branch variable found");
+        let mut rng = self.rng.borrow_mut();
+        let variable = unassigned[rng.next_below(unassigned.len() as u64) as usize];
+        (variable, rng.next_bool())
     }
 
     const ALPHA: usize = 1;
@@ -329,7 +1604,7 @@ impl Expression {
         let mut best_variables = self
             .variables
             .iter()
-            .filter(|x| !self.assignments.contains_key(*x))
+            .filter(|x| !self.trail.is_assigned(**x))
             .collect::<Vec<&Variable>>();
 
         for clause_size in 2..5 {
@@ -389,6 +1664,331 @@ impl Expression {
     }
 }
 
+/// The native parity constraints and their Gauss-Jordan elimination.
+#[cfg(feature = "xor")]
+impl Expression {
+    /// Adds the native constraint that the number of true literals is odd if `parity` is true,
+    /// and even otherwise. A literal appearing twice cancels out, as in `x ⊕ x = 0`.
+    ///
+    /// Parity constraints are the bulk of cryptographic and circuit instances, and their clauses
+    /// are hopeless for the DPLL search: a parity over `n` variables takes `2^(n - 1)` clauses,
+    /// or a chain of auxiliary variables that no clause propagates through until it is almost
+    /// fully assigned. The search instead runs a Gauss-Jordan elimination of all the parity
+    /// constraints after every assignment, so it finds the literals they imply together, and the
+    /// assignments they contradict, as soon as they follow. The CDCL engine, like the other users
+    /// of [`Expression::get_clauses`], sees the constraints through
+    /// [`Expression::encode_xor_constraints`]. Must be called before the search.
+    pub fn add_xor(&mut self, literals: &[Literal], parity: bool) {
+        let mut parity = parity;
+        let mut variables: Vec<Variable> = Vec::with_capacity(literals.len());
+        for literal in literals {
+            parity ^= *literal < 0;
+            let variable = to_variable(*literal);
+            match variables.iter().position(|other| *other == variable) {
+                Some(position) => {
+                    variables.remove(position);
+                }
+                None => variables.push(variable),
+            }
+        }
+        self.add_xor_variables(&variables, parity);
+    }
+
+    /// Adds the parity constraint over distinct variables.
+    fn add_xor_variables(&mut self, variables: &[Variable], parity: bool) {
+        assert!(
+            self.trail.is_empty(),
+            "The parity constraints must be added before the search"
+        );
+        if variables.is_empty() {
+            if parity {
+                self.add_clause(Clause::new());
+            }
+            return;
+        }
+
+        for variable in variables {
+            self.variables.insert(*variable);
+            self.xor_variables.insert(*variable);
+            // Either value of a variable of the constraint can violate it, so it is never pure
+            self.pure_literals.remove(&(*variable as Literal));
+            self.pure_literals.remove(&-(*variable as Literal));
+        }
+        self.xor_constraints.push(XorConstraint {
+            variables: variables.to_vec(),
+            parity,
+        });
+        self.forget_xor_elimination();
+    }
+
+    /// Returns the variables and the parity of every parity constraint.
+    pub fn get_xor_constraints(&self) -> Vec<(Vec<Variable>, bool)> {
+        self.xor_constraints
+            .iter()
+            .map(|constraint| (constraint.variables.clone(), constraint.parity))
+            .collect()
+    }
+
+    /// Returns true if the assignment satisfies every parity constraint. Variables missing from
+    /// the assignment are treated as false.
+    pub fn satisfies_xor_constraints(&self, assignment: &Assignment) -> bool {
+        self.xor_constraints.iter().all(|constraint| {
+            let num_true = constraint
+                .variables
+                .iter()
+                .filter(|variable| assignment.get(*variable).copied().unwrap_or(false))
+                .count();
+            (num_true % 2 == 1) == constraint.parity
+        })
+    }
+
+    /// Returns the expression with its parity constraints encoded to clauses by
+    /// [`crate::encodings::add_xor`]. A parity over `n > 3` variables takes `n - 3` auxiliary
+    /// variables, numbered after the variables of the expression, and `4·(n - 2)` clauses.
+    pub fn encode_xor_constraints(&self) -> Expression {
+        let mut encoded = self.clone_clauses();
+        for constraint in &self.cardinality_constraints {
+            encoded.add_at_most(&constraint.literals, constraint.bound);
+        }
+        let mut allocator = VariableAllocator::after(self);
+        for constraint in &self.xor_constraints {
+            let literals: Vec<Literal> = constraint
+                .variables
+                .iter()
+                .map(|variable| *variable as Literal)
+                .collect();
+            add_xor(&mut encoded, &mut allocator, &literals, constraint.parity);
+        }
+        encoded
+    }
+
+    /// Returns the elimination of the parity constraints under the current assignment, running
+    /// it if an assignment or backtrack made the last one stale.
+    fn get_xor_elimination(&self) -> Ref<'_, XorElimination> {
+        if self.xor_elimination.borrow().is_none() {
+            let elimination = self.eliminate_xors();
+            *self.xor_elimination.borrow_mut() = Some(elimination);
+        }
+        Ref::map(self.xor_elimination.borrow(), |elimination| {
+            elimination.as_ref().unwrap()
+        })
+    }
+
+    /// Runs a Gauss-Jordan elimination over GF(2) of the parity constraints, with a row per
+    /// constraint and a column per unassigned variable, after substituting the assigned variables
+    /// into the parities. Once the matrix is in reduced row echelon form, a zero row of parity one
+    /// is a conflict, and a row of a single variable implies its value.
+    fn eliminate_xors(&self) -> XorElimination {
+        let mut columns: Vec<Variable> = Vec::new();
+        let mut column_of: FnvHashMap<Variable, usize> = FnvHashMap::default();
+        for constraint in &self.xor_constraints {
+            for variable in &constraint.variables {
+                if self.trail.value(*variable).is_none() && !column_of.contains_key(variable) {
+                    column_of.insert(*variable, columns.len());
+                    columns.push(*variable);
+                }
+            }
+        }
+
+        let num_words = columns.len().div_ceil(64);
+        let mut rows: Vec<(Vec<u64>, bool)> = self
+            .xor_constraints
+            .iter()
+            .map(|constraint| {
+                let mut bits = vec![0u64; num_words];
+                let mut parity = constraint.parity;
+                for variable in &constraint.variables {
+                    match self.trail.value(*variable) {
+                        Some(value) => parity ^= value,
+                        None => {
+                            let column = column_of[variable];
+                            bits[column / 64] |= 1 << (column % 64);
+                        }
+                    }
+                }
+                (bits, parity)
+            })
+            .collect();
+
+        let mut pivots = Vec::new();
+        for column in 0..columns.len() {
+            let (word, bit) = (column / 64, 1u64 << (column % 64));
+            let rank = pivots.len();
+            let Some(pivot) = (rank..rows.len()).find(|row| rows[*row].0[word] & bit != 0) else {
+                continue;
+            };
+            rows.swap(rank, pivot);
+            let (pivot_bits, pivot_parity) = rows[rank].clone();
+            for (index, (bits, parity)) in rows.iter_mut().enumerate() {
+                if index != rank && bits[word] & bit != 0 {
+                    for (bits, pivot_bits) in bits.iter_mut().zip(&pivot_bits) {
+                        *bits ^= pivot_bits;
+                    }
+                    *parity ^= pivot_parity;
+                }
+            }
+            pivots.push(column);
+        }
+
+        let mut elimination = XorElimination {
+            conflict: rows[pivots.len()..].iter().any(|(_, parity)| *parity),
+            ..XorElimination::default()
+        };
+        if elimination.conflict {
+            return elimination;
+        }
+        for ((bits, parity), pivot) in rows.iter().zip(&pivots) {
+            let variable = columns[*pivot];
+            if bits.iter().map(|bits| bits.count_ones()).sum::<u32>() == 1 {
+                let literal = variable as Literal;
+                elimination
+                    .implied
+                    .push(if *parity { literal } else { -literal });
+            } else if elimination.open.is_none() {
+                // The pivot of a row of several variables is determined by the others
+                let free = (0..columns.len())
+                    .find(|column| *column != *pivot && bits[column / 64] >> (column % 64) & 1 == 1)
+                    .unwrap();
+                elimination.open = Some(columns[free]);
+            }
+        }
+        elimination
+    }
+
+    /// Assigns a literal that the parity constraints imply together, returning it, or `None` if
+    /// they imply none. The DPLL search runs it after the propagation of the clauses and of the
+    /// cardinality constraints.
+    pub fn remove_xor_implication(&mut self) -> Option<Literal> {
+        if !self.has_xor_implication() {
+            return None;
+        }
+        let mut elimination = self.xor_elimination.get_mut().take().unwrap();
+        let literal = elimination.implied.pop().unwrap();
+
+        self.assign_variable(to_variable(literal), literal > 0, None);
+        // In reduced form, the variable occurs in its own row alone, so the rest still holds
+        *self.xor_elimination.get_mut() = Some(elimination);
+        self.paranoid_step(|| format!("propagate {} from the parity constraints", literal));
+        Some(literal)
+    }
+
+    /// Copies the parity constraints into an expression over the same variables.
+    fn copy_xor_constraints(&self, into: &mut Expression) {
+        for constraint in &self.xor_constraints {
+            into.add_xor_variables(&constraint.variables, constraint.parity);
+        }
+    }
+
+    fn is_xor_variable(&self, variable: Variable) -> bool {
+        self.xor_variables.contains(&variable)
+    }
+
+    /// Drops the elimination, after an assignment of a variable of the constraints or a backtrack.
+    fn forget_xor_elimination(&mut self) {
+        *self.xor_elimination.get_mut() = None;
+    }
+
+    fn has_xor_conflict(&self) -> bool {
+        !self.xor_constraints.is_empty() && self.get_xor_elimination().conflict
+    }
+
+    fn has_xor_implication(&self) -> bool {
+        !self.xor_constraints.is_empty() && !self.get_xor_elimination().implied.is_empty()
+    }
+
+    fn are_xor_constraints_decided(&self) -> bool {
+        self.xor_constraints.is_empty() || self.get_xor_elimination().is_decided()
+    }
+
+    /// Returns an unassigned variable that the parity constraints leave free, to branch on.
+    fn get_xor_variable(&self) -> Option<Variable> {
+        if self.xor_constraints.is_empty() {
+            return None;
+        }
+        self.get_xor_elimination().open
+    }
+
+    fn xor_memory_bytes(&self) -> usize {
+        vec_bytes(&self.xor_constraints)
+            + self
+                .xor_constraints
+                .iter()
+                .map(|constraint| vec_bytes(&constraint.variables))
+                .sum::<usize>()
+            + set_bytes(&self.xor_variables)
+    }
+
+    /// Compares the kept elimination, if any, with one from scratch. It may be in another reduced
+    /// form, but must imply the same literals.
+    fn find_xor_divergence(&self) -> Option<String> {
+        let current = self.xor_elimination.borrow();
+        let current = current.as_ref()?;
+        let expected = self.eliminate_xors();
+        let mut current_implied = current.implied.clone();
+        let mut expected_implied = expected.implied.clone();
+        current_implied.sort_unstable();
+        expected_implied.sort_unstable();
+        if (current.conflict, current_implied, current.open.is_some())
+            == (expected.conflict, expected_implied, expected.open.is_some())
+        {
+            return None;
+        }
+        Some(format!(
+            "the parity constraints are {:?} instead of {:?}",
+            current, expected
+        ))
+    }
+}
+
+/// Without the `xor` feature, an expression has no parity constraints, and the search runs
+/// without the elimination.
+#[cfg(not(feature = "xor"))]
+impl Expression {
+    pub fn get_xor_constraints(&self) -> Vec<(Vec<Variable>, bool)> {
+        Vec::new()
+    }
+
+    pub fn satisfies_xor_constraints(&self, _assignment: &Assignment) -> bool {
+        true
+    }
+
+    pub fn remove_xor_implication(&mut self) -> Option<Literal> {
+        None
+    }
+
+    fn copy_xor_constraints(&self, _into: &mut Expression) {}
+
+    fn is_xor_variable(&self, _variable: Variable) -> bool {
+        false
+    }
+
+    fn forget_xor_elimination(&mut self) {}
+
+    fn has_xor_conflict(&self) -> bool {
+        false
+    }
+
+    fn has_xor_implication(&self) -> bool {
+        false
+    }
+
+    fn are_xor_constraints_decided(&self) -> bool {
+        true
+    }
+
+    fn get_xor_variable(&self) -> Option<Variable> {
+        None
+    }
+
+    fn xor_memory_bytes(&self) -> usize {
+        0
+    }
+
+    fn find_xor_divergence(&self) -> Option<String> {
+        None
+    }
+}
+
 impl CNF for Expression {
     fn add_clause(&mut self, clause: Clause) {
         let clause_id = self.clauses.len() as ClauseId;
@@ -399,16 +1999,17 @@ impl CNF for Expression {
                 self.variables.insert(variable);
 
                 if !self.literal_to_clause.contains_key(literal) {
-                    self.literal_to_clause.insert(*literal, HashSet::new());
+                    self.literal_to_clause.insert(*literal, FnvHashSet::default());
                 }
 
                 if !self.literal_to_clause.contains_key(&negate(*literal)) {
                     self.literal_to_clause
-                        .insert(negate(*literal), HashSet::new());
+                        .insert(negate(*literal), FnvHashSet::default());
                 }
 
                 let literal_clauses = self.literal_to_clause.get_mut(literal).unwrap();
                 literal_clauses.insert(clause_id);
+                self.occurrences.get_mut().mark(*literal);
             }
             // Check if the literal is a pure literal
             self.check_pure_literal(*literal);
@@ -419,10 +2020,22 @@ impl CNF for Expression {
             self.unit_clauses.insert(clause_id);
         }
 
+        // An empty clause can never be satisfied
+        if clause.is_empty() {
+            self.num_empty_clauses += 1;
+        }
+
         if clause.len() > self.max_clause_length {
             self.max_clause_length = clause.len();
         }
 
+        if let Some(activities) = self.activities.get_mut() {
+            activities.clauses.push(clause.literals());
+            for literal in clause.literals() {
+                activities.push(to_variable(*literal));
+            }
+        }
+
         self.clauses.push(clause);
         self.num_active_clauses += 1;
     }
@@ -436,7 +2049,8 @@ impl CNF for Expression {
 
         let literal = unsafe { self.clauses.get_unchecked(clause_id as usize).literals()[0] };
 
-        self.assign_variable(to_variable(literal), literal > 0);
+        self.assign_variable(to_variable(literal), literal > 0, Some(clause_id));
+        self.paranoid_step(|| format!("propagate {} from clause {}", literal, clause_id));
         Some(clause_id)
     }
 
@@ -445,24 +2059,28 @@ impl CNF for Expression {
             return None;
         }
 
-        let literal: Literal = *self.pure_literals.iter().next().unwrap();
+        // Prefer the pure literals agreeing with the preferred values, see set_phase
+        let agrees = |literal: &&Literal| {
+            self.phases.get(&to_variable(**literal)) != Some(&(**literal < 0))
+        };
+        let literal: Literal = match self.pure_literals.iter().find(agrees) {
+            Some(literal) => *literal,
+            None => *self.pure_literals.iter().next().unwrap(),
+        };
 
-        self.assign_variable(to_variable(literal), literal > 0);
+        self.assign_variable(to_variable(literal), literal > 0, None);
+        self.paranoid_step(|| format!("assign the pure literal {}", literal));
         Some(literal)
     }
 
     fn construct_assignment(&mut self) -> Assignment {
-        let mut assignments = HashMap::new();
+        let mut assignments = self.trail.to_assignment();
 
-        // Copy the existing assignments array to another one
-        for (k, v) in self.assignments.iter() {
-            assignments.insert(*k, *v);
-        }
-
-        // Assign all of the remaining variables to true
+        // Assign all of the remaining variables to their preferred value, or true
         for variable in &self.variables {
             if !assignments.contains_key(variable) {
-                assignments.insert(*variable, true);
+                let value = self.phases.get(variable).copied().unwrap_or(true);
+                assignments.insert(*variable, value);
             }
         }
         assignments
@@ -471,38 +2089,75 @@ impl CNF for Expression {
     #[inline]
     fn is_satisfied(&self) -> bool {
         self.num_active_clauses == 0
+            && self.num_active_constraints == 0
+            && self.are_xor_constraints_decided()
     }
 
     #[inline]
     fn is_unsatisfiable(&self) -> bool {
-        self.num_empty_clauses > 0
+        self.num_empty_clauses > 0 || self.num_violated_constraints > 0 || self.has_xor_conflict()
     }
 
     fn get_branch_variable(&self) -> (Variable, bool) {
-        match self.heuristic {
+        // Once the clauses are satisfied, only the native constraints are left to decide
+        if self.num_active_clauses == 0 {
+            if let Some(literal) = self.get_cardinality_literal() {
+                return (to_variable(literal), literal < 0);
+            }
+            if let Some(variable) = self.get_xor_variable() {
+                return (variable, self.get_phase(variable, true));
+            }
+        }
+        if let Some((variable, value)) = self.get_group_variable() {
+            return (variable, self.get_phase(variable, value));
+        }
+        if let Some(model) = &self.branching_model {
+            let (variable, value) = self.get_model_variable(model.as_ref());
+            return (variable, self.get_phase(variable, value));
+        }
+
+        let (variable, value) = match self.heuristic {
             SolverHeuristic::MostLiteralOccurances => self.get_most_literal_occurances(),
             SolverHeuristic::MostVariableOccurances => self.get_most_variable_occurances(),
             SolverHeuristic::MinimizeClauseLength => {
                 self.get_lexicographically_maximizing_literal()
             }
-        }
+            SolverHeuristic::Random => self.get_random_variable(),
+            SolverHeuristic::Vsids => self.get_most_active_variable(),
+        };
+        (variable, self.get_phase(variable, value))
     }
 
     fn branch_variable(&mut self, variable: Variable, value: bool) {
-        self.assign_variable(variable, value);
+        if self.pure_literal_mode == PureLiteralMode::Preprocessing && self.tracks_pure_literals {
+            self.tracks_pure_literals = false;
+            self.pure_literals.clear();
+        }
+        self.trail.new_decision_level();
+        self.assign_variable(variable, value, None);
+        self.paranoid_step(|| {
+            let literal = variable as Literal;
+            format!("decide {}", if value { literal } else { -literal })
+        });
     }
 
     fn get_action_state(&self) -> ActionState {
-        return self.actions.read().unwrap().len();
+        ActionState {
+            actions: self.actions.read().unwrap().len(),
+            trail: self.trail.len(),
+        }
     }
 
     fn restore_action_state(&mut self, state: ActionState) {
         let actions = self.actions.clone();
         let mut actions = actions.write().unwrap();
-        while actions.len() > state {
+        while actions.len() > state.actions {
             let action = actions.pop().unwrap();
             match action {
                 Action::RemoveClause(clause_id) => self.enable_clause(clause_id),
+                Action::CountCardinalityLiteral(literal) => {
+                    self.count_cardinality_literal(literal, false)
+                }
                 Action::RemoveLiteralFromClausesEnd(literal) => {
                     let removing_literal_clauses =
                         self.literal_to_clause.get_mut(&literal).unwrap();
@@ -531,20 +2186,564 @@ impl CNF for Expression {
                             _ => panic!("Did not encounter a start literal!"),
                         }
                     }
-                }
-                Action::AssignVariable(variable) => {
-                    self.unassign_variable(variable);
+
+                    self.occurrences.get_mut().mark(literal);
+                    self.check_pure_literal(literal);
                 }
                 _ => break,
             }
         }
+        drop(actions);
+        let undone = &self.trail.get_literals()[state.trail.min(self.trail.len())..];
+        if let Some(activities) = self.activities.get_mut() {
+            for literal in undone {
+                activities.push(to_variable(*literal));
+            }
+        }
+        if self.polarity_mode == PolarityMode::Saved {
+            for literal in undone {
+                let index = to_variable(*literal) as usize;
+                if index >= self.saved_phases.len() {
+                    self.saved_phases.resize(index + 1, None);
+                }
+                self.saved_phases[index] = Some(*literal > 0);
+            }
+        }
+        self.trail.truncate(state.trail);
+        self.forget_xor_elimination();
+        self.paranoid_step(|| format!("backtrack to {} assignments", state.trail));
     }
 
     /// Inference is possibly when there are some "Active" clauses, 
-    /// and either pure literals or unit clauses.
+    /// and either pure literals or unit clauses, or when a cardinality constraint is saturated,
+    /// or when the parity constraints imply a literal.
     fn is_inference_possible(&self) -> bool {
-        self.num_empty_clauses == 0
-            && self.num_active_clauses > 0
-            && (!self.pure_literals.is_empty() || !self.unit_clauses.is_empty())
+        !self.is_unsatisfiable()
+            && ((self.num_active_clauses > 0
+                && (!self.pure_literals.is_empty() || !self.unit_clauses.is_empty()))
+                || !self.saturated_constraints.is_empty()
+                || self.has_xor_implication())
+    }
+}
+
+/// Returns the position of the literal in a vector indexed by literals: `2v` for `v` and `2v + 1`
+/// for `-v`.
+fn literal_index(literal: Literal) -> usize {
+    2 * to_variable(literal) as usize + (literal < 0) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::dpll::solve_dpll;
+
+    #[test]
+    fn test_undo_repeated_solves() {
+        // A refutation undoes all of its actions, so solving again starts from the same state
+        let mut expression = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        let num_active_clauses = expression.num_active_clauses;
+        for _ in 0..3 {
+            assert!(solve_dpll(&mut expression).is_none());
+            assert_eq!(expression.num_active_clauses, num_active_clauses);
+            assert!(!expression.is_unsatisfiable());
+            for (literal, clauses) in &expression.literal_to_clause {
+                for clause_id in clauses {
+                    assert!(expression.clauses[*clause_id as usize].contains(*literal));
+                }
+            }
+        }
+
+        // An empty clause makes the expression unsatisfiable as soon as it is added
+        let mut expression = Expression::from_cnf_file("examples/cnf/quinn.cnf").unwrap();
+        expression.add_clause(Clause::new());
+        assert!(expression.is_unsatisfiable());
+        assert!(solve_dpll(&mut expression).is_none());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let expression = parse_dimacs_str("p cnf 3 4\n3 -1 0\n2 -2 0\n-1 3 0\n2 -3 1 0\n");
+        let clauses = expression.normalize().get_clauses();
+
+        assert_eq!(clauses.len(), 2);
+        assert_eq!(clauses[0].literals(), &vec![-1, 3]);
+        assert_eq!(clauses[1].literals(), &vec![1, 2, -3]);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let a = parse_dimacs_str("p cnf 3 2\n1 -2 0\n3 2 0\n");
+        let b = parse_dimacs_str("p cnf 3 3\n2 3 0\n-2 1 0\n1 -2 0\n");
+        let c = parse_dimacs_str("p cnf 3 2\n1 2 0\n3 2 0\n");
+
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(a.content_hash(), c.content_hash());
+        assert_eq!(Expression::new().content_hash(), 0xcbf29ce484222325);
+    }
+
+    #[test]
+    fn test_occurrence_heuristics() {
+        let maximum = |expression: &Expression, by_variable: bool| {
+            let mut best = 0;
+            for variable in &expression.variables {
+                if expression.trail.is_assigned(*variable) {
+                    continue;
+                }
+                let positive = expression.count_occurrences(*variable as Literal);
+                let negative = expression.count_occurrences(-(*variable as Literal));
+                best = best.max(if by_variable {
+                    positive + negative
+                } else {
+                    positive.max(negative)
+                });
+            }
+            best
+        };
+
+        for (heuristic, by_variable) in [
+            (SolverHeuristic::MostLiteralOccurances, false),
+            (SolverHeuristic::MostVariableOccurances, true),
+        ] {
+            let mut expression = crate::benchmarks::random_k_sat(30, 100, 3, 5);
+            expression.optimize();
+            expression.set_heuristic(heuristic);
+            let start = expression.get_action_state();
+            for step in 0..12 {
+                if step % 4 == 3 {
+                    expression.restore_action_state(start);
+                }
+                let (variable, value) = expression.get_branch_variable();
+                let literal = if value {
+                    variable as Literal
+                } else {
+                    -(variable as Literal)
+                };
+                let count = if by_variable {
+                    expression.count_occurrences(literal) + expression.count_occurrences(-literal)
+                } else {
+                    expression.count_occurrences(literal)
+                };
+                assert_eq!(count, maximum(&expression, by_variable));
+                expression.branch_variable(variable, value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_vsids_heuristic() {
+        // Deciding -1 and -3 leaves 2 and -2, and deciding -2 then empties the first clause
+        let mut expression = parse_dimacs_str("p cnf 4 4\n1 2 3 0\n1 -2 3 0\n-1 4 0\n-4 3 0\n");
+        expression.set_heuristic(SolverHeuristic::Vsids);
+        let start = expression.get_action_state();
+        expression.branch_variable(4, false);
+        expression.branch_variable(1, false);
+        expression.branch_variable(3, false);
+        expression.branch_variable(2, false);
+        assert_eq!(expression.num_empty_clauses, 1);
+        expression.restore_action_state(start);
+
+        // The conflict bumped 1, 2 and 3, and the first of them wins a tie
+        let activities = expression.activities.borrow().clone().unwrap();
+        assert_eq!(activities.scores[4], 0.0);
+        assert!(activities.scores[1] > 0.0 && activities.scores[1] == activities.scores[3]);
+        assert_eq!(expression.get_branch_variable(), (1, true));
+
+        for name in ["quinn", "aim-50-1_6-yes1-4", "hole6"] {
+            let benchmark = crate::benchmarks::generate(name).unwrap();
+            let mut expression = benchmark.expression.clone();
+            expression.set_heuristic(SolverHeuristic::Vsids);
+            let model = solve_dpll(&mut expression);
+            assert_eq!(model.is_some(), benchmark.expected.unwrap());
+            if let Some(model) = model {
+                assert!(benchmark.expression.is_satisfied_by(&model));
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_and_import_ordering() {
+        let benchmark = crate::benchmarks::generate("hole6").unwrap();
+        let mut expression = benchmark.expression.clone();
+        expression.set_heuristic(SolverHeuristic::Vsids);
+        assert!(solve_dpll(&mut expression).is_none());
+        let ordering = expression.export_ordering();
+        let mut variables = ordering.clone();
+        variables.sort_unstable();
+        let all: Vec<Variable> = (1..=expression.get_max_variable()).collect();
+        assert_eq!(variables, all);
+        let activities = expression.activities.borrow().clone().unwrap();
+        let score = |variable: Variable| activities.scores[variable as usize];
+        assert!(ordering
+            .windows(2)
+            .all(|pair| score(pair[0]) >= score(pair[1])));
+
+        // The imported ordering decides the first branch, whether imported before or after
+        // setting the heuristic
+        let mut reversed = ordering.clone();
+        reversed.reverse();
+        for import_first in [true, false] {
+            let mut expression = benchmark.expression.clone();
+            if import_first {
+                expression.import_ordering(&reversed);
+                assert_eq!(expression.export_ordering(), reversed);
+            }
+            expression.set_heuristic(SolverHeuristic::Vsids);
+            if !import_first {
+                expression.import_ordering(&reversed);
+            }
+            assert_eq!(expression.get_branch_variable().0, reversed[0]);
+            assert_eq!(expression.export_ordering(), reversed);
+            assert!(solve_dpll(&mut expression).is_none());
+        }
+    }
+
+    #[test]
+    fn test_phase_saving() {
+        let mut expression = parse_dimacs_str("p cnf 3 3\n1 2 0\n1 3 0\n-2 -3 0\n");
+        assert_eq!(expression.get_branch_variable(), (1, true));
+        expression.set_polarity_mode(PolarityMode::Saved);
+        let start = expression.get_action_state();
+        expression.branch_variable(1, false);
+        expression.branch_variable(2, true);
+        expression.restore_action_state(start);
+        assert_eq!(expression.get_branch_variable(), (1, false));
+        assert_eq!(expression.saved_phases[2], Some(true));
+
+        // The preferred values come first, and leaving the mode forgets the saved values
+        expression.set_phase(1, true);
+        assert_eq!(expression.get_branch_variable(), (1, true));
+        expression.phases.clear();
+        expression.set_polarity_mode(PolarityMode::Heuristic);
+        assert_eq!(expression.get_branch_variable(), (1, true));
+
+        for name in ["quinn", "aim-50-1_6-yes1-4", "hole6"] {
+            let benchmark = crate::benchmarks::generate(name).unwrap();
+            let mut expression = benchmark.expression.clone();
+            expression.set_polarity_mode(PolarityMode::Saved);
+            let model = solve_dpll(&mut expression);
+            assert_eq!(model.is_some(), benchmark.expected.unwrap());
+            if let Some(model) = model {
+                assert!(benchmark.expression.is_satisfied_by(&model));
+            }
+        }
+    }
+
+    #[test]
+    fn test_variable_groups() {
+        let mut expression =
+            parse_dimacs_str("p cnf 6 6\n1 2 0\n1 3 0\n1 -4 0\n-1 5 0\n5 6 0\n-5 -6 0\n");
+        assert_eq!(expression.get_branch_variable(), (1, true));
+        expression.set_variable_groups(vec![vec![6, 5], vec![2, 3]]);
+        assert_eq!(expression.get_branch_variable(), (5, true));
+        expression.branch_variable(5, true);
+        // 6 still occurs in the clause of 5 and 6 that is not satisfied
+        assert_eq!(expression.get_branch_variable(), (6, false));
+        expression.branch_variable(6, false);
+        assert_eq!(expression.get_branch_variable(), (2, true));
+        expression.branch_variable(2, true);
+        expression.branch_variable(3, true);
+        // Once every group is exhausted, the heuristic takes over
+        assert_eq!(expression.get_branch_variable(), (1, true));
+        assert_eq!(expression.clone().get_variable_groups().len(), 2);
+
+        // Groups change the order of the decisions, not the answers
+        for name in ["quinn", "aim-50-1_6-yes1-4", "hole6"] {
+            let benchmark = crate::benchmarks::generate(name).unwrap();
+            let mut expression = benchmark.expression.clone();
+            let variables: Vec<Variable> = (1..=expression.get_max_variable()).rev().collect();
+            expression.set_variable_groups(variables.chunks(7).map(<[Variable]>::to_vec).collect());
+            let model = solve_dpll(&mut expression);
+            assert_eq!(model.is_some(), benchmark.expected.unwrap());
+            if let Some(model) = model {
+                assert!(benchmark.expression.is_satisfied_by(&model));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_clauses_sized() {
+        let mut clauses = parse_dimacs_str("p cnf 5 3\n1 -2 0\n-1 -2 4 0\n2 0\n").get_clauses();
+        clauses.push(Clause::new());
+        let expression = Expression::from_clauses(clauses.clone());
+        let sized = Expression::from_clauses_sized(clauses, 5);
+
+        assert_eq!(sized.get_clauses(), expression.get_clauses());
+        assert_eq!(sized.variables, expression.variables);
+        assert_eq!(sized.literal_to_clause, expression.literal_to_clause);
+        assert_eq!(sized.unit_clauses, expression.unit_clauses);
+        assert_eq!(sized.pure_literals, expression.pure_literals);
+        assert_eq!(sized.num_empty_clauses, 1);
+        assert_eq!(sized.max_clause_length, 3);
+        assert_eq!(sized.num_active_clauses, 4);
+    }
+
+    #[test]
+    fn test_trail() {
+        let mut expression = parse_dimacs_str("p cnf 3 3\n1 2 0\n-1 3 0\n-2 -3 0\n");
+        expression.set_pure_literal_mode(PureLiteralMode::Disabled);
+        let start = expression.get_action_state();
+        expression.branch_variable(1, true);
+        assert_eq!(expression.remove_unit_clause(), Some(1));
+        assert_eq!(expression.remove_unit_clause(), Some(2));
+
+        let trail = expression.get_trail();
+        assert_eq!(trail.get_literals(), &[1, 3, -2]);
+        assert_eq!(trail.get_decision_level(), 1);
+        assert_eq!(trail.reason(1), None);
+        assert_eq!(trail.reason(3), Some(1));
+        assert_eq!(trail.decision_level(2), Some(1));
+        assert!(expression.is_satisfied());
+
+        expression.restore_action_state(start);
+        assert!(expression.get_trail().is_empty());
+        assert_eq!(expression.get_trail().get_decision_level(), 0);
+        assert_eq!(expression.unit_clauses.len(), 0);
+    }
+
+    #[test]
+    fn test_many_clauses() {
+        // More clauses than a 16-bit identifier can tell apart, with the unit clause that
+        // starts the chain of implications last
+        let mut expression = Expression::new();
+        for _ in 0..70 {
+            for variable in 1..1000 {
+                let mut clause = Clause::new();
+                clause.insert(-(variable as Literal));
+                clause.insert(variable as Literal + 1);
+                expression.add_clause(clause);
+            }
+        }
+        let mut unit = Clause::new();
+        unit.insert(1);
+        expression.add_clause(unit);
+        assert_eq!(expression.num_active_clauses, 69_931);
+        assert_eq!(expression.clone().remove_unit_clause(), Some(69_930));
+
+        let model = crate::dpll::solve_dpll(&mut expression).unwrap();
+        assert!((1..=1000).all(|variable| model.get(&variable) == Some(&true)));
+    }
+
+    #[test]
+    fn test_pure_literal_modes() {
+        let dimacs = "p cnf 4 5\n1 2 0\n-1 -2 0\n-1 3 0\n1 -3 0\n1 4 0\n";
+        let pure_literals = |expression: &Expression| {
+            let mut literals: Vec<Literal> = expression.pure_literals.iter().copied().collect();
+            literals.sort_unstable();
+            literals
+        };
+
+        // Only 4 is pure at first, and setting 1 makes -2 and 3 pure
+        let expected = [
+            (PureLiteralMode::Disabled, vec![], vec![]),
+            (PureLiteralMode::Preprocessing, vec![4], vec![]),
+            (PureLiteralMode::Dynamic, vec![4], vec![-2, 3]),
+        ];
+        for (mode, before, after) in expected {
+            let mut expression = parse_dimacs_str(dimacs);
+            expression.set_pure_literal_mode(mode);
+            assert_eq!(pure_literals(&expression), before);
+            let start = expression.get_action_state();
+            expression.branch_variable(1, true);
+            assert_eq!(pure_literals(&expression), after);
+            expression.restore_action_state(start);
+            assert!(expression.clone().get_pure_literal_mode() == mode);
+
+            expression.set_pure_literal_mode(PureLiteralMode::Dynamic);
+            assert_eq!(pure_literals(&expression), vec![4]);
+        }
+    }
+    #[test]
+    fn test_check_cached_state() {
+        let mut expression = parse_dimacs_str("p cnf 3 3\n1 2 0\n-1 3 0\n-2 -3 0\n");
+        expression.set_paranoid(Some(ParanoidPolicy { interval: 1 }));
+        expression.branch_variable(1, true);
+        let state = expression.get_action_state();
+        expression.remove_unit_clause();
+        assert!(expression.get_divergences().is_empty());
+
+        // A unit clause left behind by a faulty undo is caught at the next operation
+        expression.restore_action_state(state);
+        expression.unit_clauses.insert(0);
+        expression.branch_variable(2, false);
+        let divergences = expression.get_divergences();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].num_operations, 4);
+        assert_eq!(
+            divergences[0].problems,
+            vec!["the unit clauses are [0, 1] instead of [1]"]
+        );
+        assert_eq!(divergences[0].history, vec!["decide -2 (7 actions)"]);
+    }
+
+    #[test]
+    fn test_cardinality_constraints() {
+        let mut rng = Rng::new(3);
+        let random_literal = |rng: &mut Rng| {
+            let literal = 1 + rng.next_below(8) as Literal;
+            if rng.next_bool() {
+                literal
+            } else {
+                -literal
+            }
+        };
+        for _ in 0..60 {
+            let mut expression = Expression::new();
+            for _ in 0..6 {
+                let mut clause = Clause::new();
+                for _ in 0..3 {
+                    clause.insert_checked(random_literal(&mut rng));
+                }
+                expression.add_clause(clause);
+            }
+            for _ in 0..2 {
+                let literals: Vec<Literal> = (0..5).map(|_| random_literal(&mut rng)).collect();
+                let bound = rng.next_below(4) as usize;
+                if rng.next_bool() {
+                    expression.add_at_most(&literals, bound);
+                } else {
+                    expression.add_at_least(&literals, bound);
+                }
+            }
+            let satisfiable = (0..1u32 << 8).any(|mask| {
+                let model: Assignment = (1..=8)
+                    .map(|variable| (variable, mask >> (variable - 1) & 1 == 1))
+                    .collect();
+                expression.is_satisfied_by(&model)
+            });
+
+            let mut working = expression.clone();
+            working.optimize();
+            working.set_paranoid(Some(ParanoidPolicy { interval: 1 }));
+            let model = solve_dpll(&mut working);
+            working.check_cached_state();
+            assert_eq!(working.get_divergences(), &[]);
+            assert_eq!(model.is_some(), satisfiable);
+            if let Some(model) = model {
+                assert!(expression.is_satisfied_by(&model));
+            }
+
+            let model = crate::cdcl::solve_cdcl(&expression);
+            assert_eq!(model.is_some(), satisfiable);
+            if let Some(model) = model {
+                assert!(expression.is_satisfied_by(&model));
+            }
+        }
+
+        // Seven pigeons in six holes, with a native constraint per hole instead of the binary
+        // clauses of every pair of pigeons
+        let mut expression = Expression::new();
+        let sits = |pigeon: usize, hole: usize| (pigeon * 6 + hole + 1) as Literal;
+        for pigeon in 0..7 {
+            let mut clause = Clause::new();
+            for hole in 0..6 {
+                clause.insert(sits(pigeon, hole));
+            }
+            expression.add_clause(clause);
+        }
+        for hole in 0..6 {
+            let pigeons: Vec<Literal> = (0..7).map(|pigeon| sits(pigeon, hole)).collect();
+            expression.add_at_most(&pigeons, 1);
+        }
+        let mut working = expression.clone();
+        assert_eq!(solve_dpll(&mut working), None);
+        assert_eq!(expression.get_clauses().len(), 7);
+
+        // Exactly half of 100 variables, which clauses would take thousands of variables for
+        let literals: Vec<Literal> = (1..=100).collect();
+        let mut expression = Expression::new();
+        expression.add_at_most(&literals, 50);
+        expression.add_at_least(&literals, 50);
+        let mut clause = Clause::new();
+        clause.insert(-1);
+        clause.insert(-2);
+        expression.add_clause(clause);
+        let model = solve_dpll(&mut expression.clone()).unwrap();
+        assert!(expression.is_satisfied_by(&model));
+        assert_eq!(model.values().filter(|value| **value).count(), 50);
+    }
+    #[test]
+    #[cfg(feature = "xor")]
+    fn test_xor_constraints() {
+        use crate::dpll::{solve_dpll_traced, SearchTrace};
+
+        let mut rng = Rng::new(5);
+        let random_literal = |rng: &mut Rng| {
+            let literal = 1 + rng.next_below(8) as Literal;
+            if rng.next_bool() {
+                literal
+            } else {
+                -literal
+            }
+        };
+        for _ in 0..60 {
+            let mut expression = Expression::new();
+            for _ in 0..4 {
+                let mut clause = Clause::new();
+                for _ in 0..3 {
+                    clause.insert_checked(random_literal(&mut rng));
+                }
+                expression.add_clause(clause);
+            }
+            for _ in 0..3 {
+                let length = 2 + rng.next_below(4) as usize;
+                let literals: Vec<Literal> =
+                    (0..length).map(|_| random_literal(&mut rng)).collect();
+                expression.add_xor(&literals, rng.next_bool());
+            }
+            if rng.next_bool() {
+                expression.add_at_most(&[1, 2, 3, 4], 2);
+            }
+            let satisfiable = (0..1u32 << 8).any(|mask| {
+                let model: Assignment = (1..=8)
+                    .map(|variable| (variable, mask >> (variable - 1) & 1 == 1))
+                    .collect();
+                expression.is_satisfied_by(&model)
+            });
+
+            let mut working = expression.clone();
+            working.optimize();
+            working.set_paranoid(Some(ParanoidPolicy { interval: 1 }));
+            let model = solve_dpll(&mut working);
+            working.check_cached_state();
+            assert_eq!(working.get_divergences(), &[]);
+            assert_eq!(model.is_some(), satisfiable);
+            if let Some(model) = model {
+                assert!(expression.is_satisfied_by(&model));
+            }
+
+            let model = crate::cdcl::solve_cdcl(&expression);
+            assert_eq!(model.is_some(), satisfiable);
+            if let Some(model) = model {
+                assert!(expression.is_satisfied_by(&model));
+            }
+        }
+
+        // Random parities of five of 80 variables through a hidden model, which the elimination
+        // solves without a conflict, where their clauses would take thousands
+        let hidden: Vec<bool> = (0..80).map(|_| rng.next_bool()).collect();
+        let mut expression = Expression::new();
+        let mut rows = Vec::new();
+        for _ in 0..60 {
+            let variables: Vec<Literal> =
+                (0..5).map(|_| 1 + rng.next_below(80) as Literal).collect();
+            let parity = variables.iter().fold(false, |parity, variable| {
+                parity ^ hidden[*variable as usize - 1]
+            });
+            expression.add_xor(&variables, parity);
+            rows.push((variables, parity));
+        }
+        let model = solve_dpll(&mut expression.clone()).unwrap();
+        assert!(expression.is_satisfied_by(&model));
+
+        // The sum of two of the parities with the wrong parity contradicts them, which only
+        // their combination shows
+        let (first, first_parity) = &rows[0];
+        let (second, second_parity) = &rows[1];
+        let sum: Vec<Literal> = first.iter().chain(second).copied().collect();
+        expression.add_xor(&sum, !(first_parity ^ second_parity));
+        let mut trace = SearchTrace::new();
+        assert_eq!(solve_dpll_traced(&mut expression.clone(), &mut trace), None);
+        assert_eq!(trace.num_decisions, 0);
     }
-}
\ No newline at end of file
+}
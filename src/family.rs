@@ -0,0 +1,234 @@
+//! Solving a family of instances that share a large core of clauses and differ in small deltas,
+//! as in parameter sweeps checking different properties of the same circuit.
+//!
+//! The core is loaded once into a [`CdclSolver`]. Every delta is a group of clauses guarded by
+//! an activation literal, as for the groups of
+//! [`IncrementalExpression`](crate::incremental::IncrementalExpression): the solver holds `-a | C`
+//! for every clause `C` of the delta with activation literal `a`, and each query assumes `a` for
+//! the deltas it applies and `-a` for the others. The guarded clauses hold whichever deltas a
+//! query applies, and so do the clauses learned from them under any assumptions, so every query
+//! starts from the clauses learned by the previous ones.
+
+use crate::cdcl::CdclSolver;
+use crate::cnf::{to_variable, Clause, Literal, Variable};
+use crate::dpll::SearchResult;
+use crate::expression::Expression;
+
+/// A handle to a delta of an [`InstanceFamily`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Delta(usize);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DeltaState {
+    Applied,
+    Retracted,
+    /// The delta was dropped for good, and its clauses are satisfied by its activation literal.
+    Removed,
+}
+
+/// A shared core of clauses, with deltas applied and retracted between queries.
+#[derive(Clone, Debug)]
+pub struct InstanceFamily {
+    solver: CdclSolver,
+    /// The largest variable the core and the deltas can use. The activation literals come after.
+    max_variable: Variable,
+    states: Vec<DeltaState>,
+}
+
+impl InstanceFamily {
+    /// Loads the core, for deltas over the variables of the core.
+    pub fn new(core: &Expression) -> InstanceFamily {
+        InstanceFamily::with_max_variable(core, core.get_max_variable())
+    }
+
+    /// Loads the core, for deltas over the variables up to `max_variable`, which may go past the
+    /// variables of the core.
+    pub fn with_max_variable(core: &Expression, max_variable: Variable) -> InstanceFamily {
+        assert!(
+            max_variable >= core.get_max_variable(),
+            "The core uses variables past {}",
+            max_variable
+        );
+        InstanceFamily {
+            solver: CdclSolver::new(core),
+            max_variable,
+            states: Vec::new(),
+        }
+    }
+
+    fn get_activation_literal(&self, delta: Delta) -> Literal {
+        (self.max_variable as usize + 1 + delta.0) as Literal
+    }
+
+    /// Adds a delta, which starts out applied. Panics if a clause uses variables past the largest
+    /// variable of the family.
+    pub fn add_delta(&mut self, clauses: &[Clause]) -> Delta {
+        let delta = Delta(self.states.len());
+        let activation = self.get_activation_literal(delta);
+        for clause in clauses {
+            let mut guarded: Vec<Literal> = clause.literals().to_vec();
+            assert!(
+                guarded
+                    .iter()
+                    .all(|literal| to_variable(*literal) <= self.max_variable),
+                "A delta uses variables past {}",
+                self.max_variable
+            );
+            guarded.push(-activation);
+            self.solver.add_clause(&guarded);
+        }
+        self.states.push(DeltaState::Applied);
+        delta
+    }
+
+    /// Applies the delta to the next queries.
+    pub fn apply(&mut self, delta: Delta) {
+        self.set_state(delta, DeltaState::Applied);
+    }
+
+    /// Leaves the delta out of the next queries.
+    pub fn retract(&mut self, delta: Delta) {
+        self.set_state(delta, DeltaState::Retracted);
+    }
+
+    /// Drops the delta for good, which lets the solver simplify its clauses away.
+    pub fn remove(&mut self, delta: Delta) {
+        self.set_state(delta, DeltaState::Removed);
+        self.solver
+            .add_clause(&[-self.get_activation_literal(delta)]);
+    }
+
+    fn set_state(&mut self, delta: Delta, state: DeltaState) {
+        assert!(
+            self.states[delta.0] != DeltaState::Removed,
+            "A removed delta cannot be applied or retracted"
+        );
+        self.states[delta.0] = state;
+    }
+
+    pub fn is_applied(&self, delta: Delta) -> bool {
+        self.states[delta.0] == DeltaState::Applied
+    }
+
+    /// Solves the core together with the applied deltas. The model does not contain the
+    /// activation literals.
+    pub fn solve(&mut self) -> SearchResult {
+        let assumptions: Vec<Literal> = (0..self.states.len())
+            .filter(|index| self.states[*index] != DeltaState::Removed)
+            .map(|index| {
+                let activation = self.get_activation_literal(Delta(index));
+                if self.states[index] == DeltaState::Applied {
+                    activation
+                } else {
+                    -activation
+                }
+            })
+            .collect();
+
+        let mut result = self.solver.solve_with_assumptions(&assumptions);
+        if let SearchResult::Satisfiable(model) = &mut result {
+            model.retain(|variable, _| *variable <= self.max_variable);
+        }
+        result
+    }
+
+    /// Returns the solver holding the core and the deltas, with the clauses learned so far.
+    pub fn get_solver(&self) -> &CdclSolver {
+        &self.solver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::random_k_sat;
+    use crate::cnf::CNF;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::rng::Rng;
+    use crate::solver::solve_with_assumptions;
+
+    #[test]
+    fn test_instance_family() {
+        let core = parse_dimacs_str("p cnf 3 2\n1 2 0\n-1 3 0\n");
+        let mut family = InstanceFamily::new(&core);
+        let not_two = family.add_delta(&[Clause::from_literals(&[-2])]);
+        let not_three = family.add_delta(&[Clause::from_literals(&[-3])]);
+
+        // Both deltas force 1 and then 3, against the second delta
+        assert_eq!(family.solve(), SearchResult::Unsatisfiable);
+        family.retract(not_three);
+        let SearchResult::Satisfiable(model) = family.solve() else {
+            panic!("expected a model");
+        };
+        assert_eq!(model.len(), 3);
+        assert!(core.is_satisfied_by(&model) && model[&1] && !model[&2]);
+
+        family.apply(not_three);
+        family.remove(not_two);
+        assert!(!family.is_applied(not_two));
+        assert!(matches!(family.solve(), SearchResult::Satisfiable(model) if !model[&3]));
+    }
+
+    #[test]
+    fn test_instance_family_shares_learned_clauses() {
+        let core = random_k_sat(40, 160, 3, 1);
+        let mut family = InstanceFamily::new(&core);
+        let mut rng = Rng::new(2);
+        let mut guarded = core.clone();
+        let mut deltas = Vec::new();
+        for index in 0..12 {
+            let literals: Vec<Literal> = (0..3)
+                .map(|_| {
+                    let variable = rng.next_below(40) as Literal + 1;
+                    if rng.next_bool() {
+                        variable
+                    } else {
+                        -variable
+                    }
+                })
+                .collect();
+            let units: Vec<Clause> = literals
+                .iter()
+                .map(|literal| Clause::from_literals(&[*literal]))
+                .collect();
+            let delta = family.add_delta(&units);
+            family.retract(delta);
+            for literal in &literals {
+                guarded.add_clause(Clause::from_literals(&[*literal, -(41 + index)]));
+            }
+            deltas.push((delta, literals));
+        }
+
+        let mut num_learned_clauses = 0;
+        for (delta, literals) in deltas {
+            family.apply(delta);
+            let result = family.solve();
+            family.retract(delta);
+            let expected = solve_with_assumptions(&core, &literals);
+            match result {
+                SearchResult::Satisfiable(model) => {
+                    assert!(expected.is_some());
+                    assert!(core.is_satisfied_by(&model));
+                    assert!(literals
+                        .iter()
+                        .all(|literal| model[&to_variable(*literal)] == (*literal > 0)));
+                }
+                result => {
+                    assert_eq!(result, SearchResult::Unsatisfiable);
+                    assert!(expected.is_none());
+                }
+            }
+
+            // The clauses learned by the previous queries are kept
+            let learned = family.get_solver().get_statistics().num_learned_clauses;
+            assert!(learned >= num_learned_clauses);
+            num_learned_clauses = learned;
+        }
+        assert!(num_learned_clauses > 0);
+
+        for learned in family.get_solver().get_learned_clauses() {
+            let negation: Vec<Literal> = learned.iter().map(|literal| -literal).collect();
+            assert!(solve_with_assumptions(&guarded, &negation).is_none());
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Features describing the structure of an instance, in the style of SATzilla, for predicting its
+//! hardness or selecting a solver configuration per instance.
+//!
+//! The features are cheap to compute compared to solving: a pass over the clauses, the variable
+//! interaction graph (variables are adjacent if they share a clause), and unit propagation of
+//! both values of a sample of the variables.
+
+use crate::cnf::{to_variable, Literal, Variable};
+use crate::expression::Expression;
+use crate::hash::FnvHashSet;
+use crate::propagation::Propagator;
+
+/// The number of variables probed by unit propagation.
+const NUM_PROBES: usize = 64;
+
+/// Summary statistics of a list of values.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    /// Summarizes the values, all zero if there are none.
+    pub fn new(values: &[f64]) -> Summary {
+        if values.is_empty() {
+            return Summary::default();
+        }
+
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / values.len() as f64;
+        Summary {
+            mean,
+            std_dev: variance.sqrt(),
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// The feature vector of an instance.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Features {
+    /// The number of variables occurring in some clause.
+    pub num_variables: usize,
+    pub num_clauses: usize,
+    pub clause_variable_ratio: f64,
+    pub clause_lengths: Summary,
+    /// The number of clauses each variable occurs in.
+    pub variable_occurrences: Summary,
+    /// The degree of each variable in the variable interaction graph.
+    pub variable_degrees: Summary,
+    /// The fraction of the literal occurrences that are positive.
+    pub positive_fraction: f64,
+    pub binary_fraction: f64,
+    pub ternary_fraction: f64,
+    /// The fraction of clauses with at most one positive literal.
+    pub horn_fraction: f64,
+    /// The fraction of the variables assigned by unit propagation after assigning a value to one
+    /// of the probed variables (spread evenly over the variables), without conflict.
+    pub probe_propagations: Summary,
+    /// The fraction of the probed literals whose propagation leads to a conflict.
+    pub failed_literal_fraction: f64,
+}
+
+impl Features {
+    /// Returns the names of the entries of [`Features::to_vec`].
+    pub fn names() -> Vec<String> {
+        let mut names: Vec<String> = ["num_variables", "num_clauses", "clause_variable_ratio"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let summaries = ["clause_lengths", "variable_occurrences", "variable_degrees"];
+        for summary in summaries {
+            for statistic in ["mean", "std_dev", "min", "max"] {
+                names.push(format!("{}_{}", summary, statistic));
+            }
+        }
+        for name in [
+            "positive_fraction",
+            "binary_fraction",
+            "ternary_fraction",
+            "horn_fraction",
+        ] {
+            names.push(name.to_string());
+        }
+        for statistic in ["mean", "std_dev", "min", "max"] {
+            names.push(format!("probe_propagations_{}", statistic));
+        }
+        names.push("failed_literal_fraction".to_string());
+        names
+    }
+
+    /// Returns the features as a flat vector, in the order of [`Features::names`].
+    pub fn to_vec(&self) -> Vec<f64> {
+        let mut values = vec![
+            self.num_variables as f64,
+            self.num_clauses as f64,
+            self.clause_variable_ratio,
+        ];
+        for summary in [
+            &self.clause_lengths,
+            &self.variable_occurrences,
+            &self.variable_degrees,
+        ] {
+            values.extend([summary.mean, summary.std_dev, summary.min, summary.max]);
+        }
+        values.extend([
+            self.positive_fraction,
+            self.binary_fraction,
+            self.ternary_fraction,
+            self.horn_fraction,
+        ]);
+        let probes = &self.probe_propagations;
+        values.extend([probes.mean, probes.std_dev, probes.min, probes.max]);
+        values.push(self.failed_literal_fraction);
+        values
+    }
+}
+
+fn fraction(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Computes the features of the expression.
+pub fn compute_features(expression: &Expression) -> Features {
+    let clauses = expression.get_clauses();
+    let max_variable = expression.get_max_variable() as usize;
+    let mut occurrences = vec![0usize; max_variable + 1];
+    let mut neighbours: Vec<FnvHashSet<Variable>> = vec![FnvHashSet::default(); max_variable + 1];
+    let mut propagator = Propagator::new();
+    let mut num_literals = 0;
+    let mut num_positive = 0;
+    let mut num_binary = 0;
+    let mut num_ternary = 0;
+    let mut num_horn = 0;
+
+    for clause in &clauses {
+        let literals = clause.literals();
+        propagator.add_clause(literals);
+        num_literals += literals.len();
+        let positive = literals.iter().filter(|literal| **literal > 0).count();
+        num_positive += positive;
+        num_horn += (positive <= 1) as usize;
+        num_binary += (literals.len() == 2) as usize;
+        num_ternary += (literals.len() == 3) as usize;
+
+        let variables: FnvHashSet<Variable> = literals.iter().map(|l| to_variable(*l)).collect();
+        for variable in &variables {
+            occurrences[*variable as usize] += 1;
+            neighbours[*variable as usize].extend(variables.iter().filter(|v| *v != variable));
+        }
+    }
+
+    let variables: Vec<usize> = (1..=max_variable)
+        .filter(|variable| occurrences[*variable] > 0)
+        .collect();
+    let per_variable = |value: &dyn Fn(usize) -> usize| -> Summary {
+        let values: Vec<f64> = variables.iter().map(|v| value(*v) as f64).collect();
+        Summary::new(&values)
+    };
+    let lengths: Vec<f64> = clauses.iter().map(|clause| clause.len() as f64).collect();
+
+    let mut probes = Vec::new();
+    let mut num_failed = 0;
+    let step = variables.len().div_ceil(NUM_PROBES).max(1);
+    let top_level_conflict = propagator.propagate_units().is_some();
+    if !top_level_conflict {
+        for variable in variables.iter().step_by(step) {
+            for literal in [*variable as Literal, -(*variable as Literal)] {
+                propagator.propagate_units();
+                if propagator.assume(literal).is_some() {
+                    num_failed += 1;
+                } else {
+                    probes.push(fraction(propagator.get_trail().len(), variables.len()));
+                }
+            }
+        }
+    }
+
+    Features {
+        num_variables: variables.len(),
+        num_clauses: clauses.len(),
+        clause_variable_ratio: fraction(clauses.len(), variables.len()),
+        clause_lengths: Summary::new(&lengths),
+        variable_occurrences: per_variable(&|variable| occurrences[variable]),
+        variable_degrees: per_variable(&|variable| neighbours[variable].len()),
+        positive_fraction: fraction(num_positive, num_literals),
+        binary_fraction: fraction(num_binary, clauses.len()),
+        ternary_fraction: fraction(num_ternary, clauses.len()),
+        horn_fraction: fraction(num_horn, clauses.len()),
+        probe_propagations: Summary::new(&probes),
+        failed_literal_fraction: fraction(num_failed, probes.len() + num_failed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_compute_features() {
+        // 1 -> 2 -> 3, and 4 is forced false by 1 | -4 together with -1 | -4
+        let expression = parse_dimacs_str("p cnf 4 4\n-1 2 0\n-2 3 0\n1 -4 0\n-1 -4 0\n");
+        let features = compute_features(&expression);
+        assert_eq!(features.num_variables, 4);
+        assert_eq!(features.num_clauses, 4);
+        assert_eq!(features.clause_variable_ratio, 1.0);
+        assert_eq!(features.clause_lengths, Summary::new(&[2.0; 4]));
+        assert_eq!(features.variable_occurrences.max, 3.0);
+        assert_eq!(features.variable_degrees.max, 2.0);
+        assert_eq!(features.positive_fraction, 0.375);
+        assert_eq!(features.binary_fraction, 1.0);
+        assert_eq!(features.horn_fraction, 1.0);
+        // Only assigning 4 true fails, and assigning 1 true assigns every variable
+        assert_eq!(features.failed_literal_fraction, 0.125);
+        assert_eq!(features.probe_propagations.max, 1.0);
+        assert_eq!(features.probe_propagations.min, 0.25);
+
+        assert_eq!(Features::names().len(), features.to_vec().len());
+    }
+}
@@ -0,0 +1,266 @@
+use hashbrown::HashMap;
+
+use crate::cnf::{Assignment, Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+
+/// A propositional formula over numbered variables.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Formula {
+    Constant(bool),
+    Variable(Variable),
+    Not(Box<Formula>),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    Iff(Box<Formula>, Box<Formula>),
+    Xor(Box<Formula>, Box<Formula>),
+}
+
+/// Maps the names used in a textual formula to variables, numbered from 1 in order of appearance.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    names: Vec<String>,
+    variables: HashMap<String, Variable>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            names: Vec::new(),
+            variables: HashMap::new(),
+        }
+    }
+
+    /// Returns the variable for the name, allocating the next variable if the name is new.
+    pub fn get_or_insert(&mut self, name: &str) -> Variable {
+        if let Some(variable) = self.variables.get(name) {
+            return *variable;
+        }
+
+        self.names.push(name.to_string());
+        let variable = self.names.len() as Variable;
+        self.variables.insert(name.to_string(), variable);
+        variable
+    }
+
+    pub fn variable(&self, name: &str) -> Option<Variable> {
+        self.variables.get(name).copied()
+    }
+
+    pub fn name(&self, variable: Variable) -> Option<&str> {
+        self.names
+            .get((variable as usize).wrapping_sub(1))
+            .map(|name| name.as_str())
+    }
+
+    /// Returns the number of named variables.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns the value of every named variable in the assignment, keyed by name.
+    pub fn named_values(&self, assignment: &Assignment) -> Vec<(String, bool)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let variable = index as Variable + 1;
+                (
+                    name.clone(),
+                    assignment.get(&variable).copied().unwrap_or(false),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Formula {
+    /// Evaluates the formula, treating variables missing from the assignment as false.
+    pub fn evaluate(&self, assignment: &Assignment) -> bool {
+        match self {
+            Formula::Constant(value) => *value,
+            Formula::Variable(variable) => assignment.get(variable).copied().unwrap_or(false),
+            Formula::Not(inner) => !inner.evaluate(assignment),
+            Formula::And(children) => children.iter().all(|child| child.evaluate(assignment)),
+            Formula::Or(children) => children.iter().any(|child| child.evaluate(assignment)),
+            Formula::Implies(left, right) => {
+                !left.evaluate(assignment) || right.evaluate(assignment)
+            }
+            Formula::Iff(left, right) => left.evaluate(assignment) == right.evaluate(assignment),
+            Formula::Xor(left, right) => left.evaluate(assignment) != right.evaluate(assignment),
+        }
+    }
+
+    /// Returns the largest variable referenced by the formula, or 0 if there are none.
+    pub fn get_max_variable(&self) -> Variable {
+        match self {
+            Formula::Constant(_) => 0,
+            Formula::Variable(variable) => *variable,
+            Formula::Not(inner) => inner.get_max_variable(),
+            Formula::And(children) | Formula::Or(children) => children
+                .iter()
+                .map(|child| child.get_max_variable())
+                .max()
+                .unwrap_or(0),
+            Formula::Implies(left, right)
+            | Formula::Iff(left, right)
+            | Formula::Xor(left, right) => left.get_max_variable().max(right.get_max_variable()),
+        }
+    }
+
+    /// Converts the formula to an equisatisfiable CNF expression with the Tseitin transformation.
+    /// Variables up to `num_variables` keep their meaning, auxiliary variables are numbered after them.
+    pub fn to_expression(&self, num_variables: Variable) -> Expression {
+        let mut expression = Expression::new();
+        let mut next_variable = num_variables.max(self.get_max_variable()) + 1;
+        self.assert_into(&mut expression, &mut next_variable);
+        expression
+    }
+
+    /// Adds clauses to the expression requiring the formula to be true,
+    /// allocating auxiliary variables starting at `next_variable`.
+    pub fn assert_into(&self, expression: &mut Expression, next_variable: &mut Variable) {
+        match self {
+            Formula::Constant(true) => {}
+            Formula::Constant(false) => expression.add_clause(Clause::new()),
+            Formula::And(children) => {
+                for child in children {
+                    child.assert_into(expression, next_variable);
+                }
+            }
+            Formula::Or(children) => {
+                let mut clause = Clause::new();
+                for child in children {
+                    match child.encode(expression, next_variable) {
+                        Encoded::Literal(literal) => clause.insert_checked(literal),
+                        Encoded::Constant(true) => return,
+                        Encoded::Constant(false) => {}
+                    }
+                }
+                expression.add_clause(clause);
+            }
+            _ => match self.encode(expression, next_variable) {
+                Encoded::Literal(literal) => {
+                    expression.add_clause(Clause::from_literals(&[literal]))
+                }
+                Encoded::Constant(true) => {}
+                Encoded::Constant(false) => expression.add_clause(Clause::new()),
+            },
+        }
+    }
+
+    /// Returns a literal equivalent to the formula, adding the defining clauses of any new
+    /// auxiliary variables to the expression.
+    fn encode(&self, expression: &mut Expression, next_variable: &mut Variable) -> Encoded {
+        match self {
+            Formula::Constant(value) => Encoded::Constant(*value),
+            Formula::Variable(variable) => Encoded::Literal(*variable as Literal),
+            Formula::Not(inner) => match inner.encode(expression, next_variable) {
+                Encoded::Literal(literal) => Encoded::Literal(-literal),
+                Encoded::Constant(value) => Encoded::Constant(!value),
+            },
+            Formula::And(children) => {
+                let mut literals = Vec::new();
+                for child in children {
+                    match child.encode(expression, next_variable) {
+                        Encoded::Literal(literal) => literals.push(literal),
+                        Encoded::Constant(false) => return Encoded::Constant(false),
+                        Encoded::Constant(true) => {}
+                    }
+                }
+                define_and(&literals, expression, next_variable)
+            }
+            Formula::Or(children) => {
+                let mut literals = Vec::new();
+                for child in children {
+                    match child.encode(expression, next_variable) {
+                        Encoded::Literal(literal) => literals.push(-literal),
+                        Encoded::Constant(true) => return Encoded::Constant(true),
+                        Encoded::Constant(false) => {}
+                    }
+                }
+                // a | b is !(!a & !b)
+                match define_and(&literals, expression, next_variable) {
+                    Encoded::Literal(literal) => Encoded::Literal(-literal),
+                    Encoded::Constant(value) => Encoded::Constant(!value),
+                }
+            }
+            Formula::Implies(left, right) => {
+                Formula::Or(vec![Formula::Not(left.clone()), (**right).clone()])
+                    .encode(expression, next_variable)
+            }
+            Formula::Iff(left, right) => {
+                match Formula::Xor(left.clone(), right.clone()).encode(expression, next_variable) {
+                    Encoded::Literal(literal) => Encoded::Literal(-literal),
+                    Encoded::Constant(value) => Encoded::Constant(!value),
+                }
+            }
+            Formula::Xor(left, right) => {
+                let left = left.encode(expression, next_variable);
+                let right = right.encode(expression, next_variable);
+                match (left, right) {
+                    (Encoded::Constant(a), Encoded::Constant(b)) => Encoded::Constant(a != b),
+                    (Encoded::Constant(value), Encoded::Literal(literal))
+                    | (Encoded::Literal(literal), Encoded::Constant(value)) => {
+                        Encoded::Literal(if value { -literal } else { literal })
+                    }
+                    (Encoded::Literal(a), Encoded::Literal(b)) => {
+                        let output = allocate(next_variable);
+                        expression.add_clause(Clause::from_literals(&[-output, a, b]));
+                        expression.add_clause(Clause::from_literals(&[-output, -a, -b]));
+                        expression.add_clause(Clause::from_literals(&[output, -a, b]));
+                        expression.add_clause(Clause::from_literals(&[output, a, -b]));
+                        Encoded::Literal(output)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The result of encoding a sub-formula: either a literal, or a constant it simplified to.
+enum Encoded {
+    Literal(Literal),
+    Constant(bool),
+}
+
+fn allocate(next_variable: &mut Variable) -> Literal {
+    let variable = *next_variable;
+    *next_variable += 1;
+    variable as Literal
+}
+
+/// Defines a fresh variable equivalent to the conjunction of the literals.
+fn define_and(
+    literals: &[Literal],
+    expression: &mut Expression,
+    next_variable: &mut Variable,
+) -> Encoded {
+    match literals {
+        [] => Encoded::Constant(true),
+        [literal] => Encoded::Literal(*literal),
+        _ => {
+            let output = allocate(next_variable);
+            let mut long_clause = vec![output];
+            for literal in literals {
+                expression.add_clause(Clause::from_literals(&[-output, *literal]));
+                long_clause.push(-literal);
+            }
+            expression.add_clause(Clause::from_literals(&long_clause));
+            Encoded::Literal(output)
+        }
+    }
+}
+
+/// Restricts an assignment to the variables up to `num_variables`, dropping auxiliary variables.
+pub fn project_assignment(assignment: &Assignment, num_variables: Variable) -> Assignment {
+    assignment
+        .iter()
+        .filter(|(variable, _)| **variable <= num_variables)
+        .map(|(variable, value)| (*variable, *value))
+        .collect()
+}
@@ -0,0 +1,347 @@
+//! Builds formulas in Rust code with the usual operators, `&`, `|`, `^` and `!`, so that
+//! `(a | !b) & (c ^ d)` is a [`Formula`] to solve or turn into CNF.
+//!
+//! The operators take formulas by value or by reference, and flatten nested conjunctions and
+//! disjunctions as they go. A [`FormulaBuilder`] hands out the variables, fresh or by name, and
+//! collects the constraints, whose Tseitin encoding numbers its auxiliary variables after all of
+//! those handed out. Models are projected back onto those variables.
+
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+use hashbrown::HashMap;
+
+use crate::cnf::{Assignment, Literal, Variable};
+use crate::dpll::solve_dpll;
+use crate::expression::Expression;
+use crate::formula::{project_assignment, Formula};
+
+impl Formula {
+    /// Returns the formula of a single variable.
+    pub fn variable(variable: Variable) -> Formula {
+        Formula::Variable(variable)
+    }
+
+    /// Returns the formula of a DIMACS literal, negated if the literal is negative.
+    pub fn literal(literal: Literal) -> Formula {
+        let variable = Formula::Variable(literal.unsigned_abs());
+        if literal < 0 {
+            !variable
+        } else {
+            variable
+        }
+    }
+
+    /// Returns the conjunction of the formulas, true if there are none.
+    pub fn all(formulas: impl IntoIterator<Item = Formula>) -> Formula {
+        Formula::And(formulas.into_iter().collect())
+    }
+
+    /// Returns the disjunction of the formulas, false if there are none.
+    pub fn any(formulas: impl IntoIterator<Item = Formula>) -> Formula {
+        Formula::Or(formulas.into_iter().collect())
+    }
+
+    /// Returns the formula that `self` implies `other`.
+    pub fn implies(self, other: Formula) -> Formula {
+        Formula::Implies(Box::new(self), Box::new(other))
+    }
+
+    /// Returns the formula that `self` and `other` are equal.
+    pub fn iff(self, other: Formula) -> Formula {
+        Formula::Iff(Box::new(self), Box::new(other))
+    }
+}
+
+impl BitAnd for Formula {
+    type Output = Formula;
+
+    fn bitand(self, other: Formula) -> Formula {
+        let mut children = match self {
+            Formula::And(children) => children,
+            formula => vec![formula],
+        };
+        match other {
+            Formula::And(others) => children.extend(others),
+            formula => children.push(formula),
+        }
+        Formula::And(children)
+    }
+}
+
+impl BitOr for Formula {
+    type Output = Formula;
+
+    fn bitor(self, other: Formula) -> Formula {
+        let mut children = match self {
+            Formula::Or(children) => children,
+            formula => vec![formula],
+        };
+        match other {
+            Formula::Or(others) => children.extend(others),
+            formula => children.push(formula),
+        }
+        Formula::Or(children)
+    }
+}
+
+impl BitXor for Formula {
+    type Output = Formula;
+
+    fn bitxor(self, other: Formula) -> Formula {
+        Formula::Xor(Box::new(self), Box::new(other))
+    }
+}
+
+/// Negates the formula, removing a double negation.
+impl Not for Formula {
+    type Output = Formula;
+
+    fn not(self) -> Formula {
+        match self {
+            Formula::Not(inner) => *inner,
+            Formula::Constant(value) => Formula::Constant(!value),
+            formula => Formula::Not(Box::new(formula)),
+        }
+    }
+}
+
+impl Not for &Formula {
+    type Output = Formula;
+
+    fn not(self) -> Formula {
+        !self.clone()
+    }
+}
+
+/// Implements a binary operator for every mix of owned and borrowed formulas, by cloning the
+/// borrowed ones.
+macro_rules! borrowed_operator {
+    ($trait:ident, $method:ident) => {
+        impl $trait<&Formula> for Formula {
+            type Output = Formula;
+
+            fn $method(self, other: &Formula) -> Formula {
+                self.$method(other.clone())
+            }
+        }
+
+        impl $trait<Formula> for &Formula {
+            type Output = Formula;
+
+            fn $method(self, other: Formula) -> Formula {
+                self.clone().$method(other)
+            }
+        }
+
+        impl $trait<&Formula> for &Formula {
+            type Output = Formula;
+
+            fn $method(self, other: &Formula) -> Formula {
+                self.clone().$method(other.clone())
+            }
+        }
+    };
+}
+
+borrowed_operator!(BitAnd, bitand);
+borrowed_operator!(BitOr, bitor);
+borrowed_operator!(BitXor, bitxor);
+
+/// Hands out the variables of a problem and collects the formulas it requires.
+#[derive(Clone, Debug, Default)]
+pub struct FormulaBuilder {
+    num_variables: Variable,
+    names: HashMap<String, Variable>,
+    constraints: Vec<Formula>,
+}
+
+impl FormulaBuilder {
+    pub fn new() -> FormulaBuilder {
+        FormulaBuilder {
+            num_variables: 0,
+            names: HashMap::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Returns a new variable. Panics if the variables run out.
+    pub fn fresh(&mut self) -> Formula {
+        assert!(
+            self.num_variables < Literal::MAX as Variable,
+            "No variables are left to allocate"
+        );
+        self.num_variables += 1;
+        Formula::Variable(self.num_variables)
+    }
+
+    /// Returns `count` new variables.
+    pub fn fresh_many(&mut self, count: usize) -> Vec<Formula> {
+        (0..count).map(|_| self.fresh()).collect()
+    }
+
+    /// Returns the variable of the name, new the first time the name is used.
+    pub fn named(&mut self, name: &str) -> Formula {
+        if let Some(variable) = self.names.get(name) {
+            return Formula::Variable(*variable);
+        }
+        let formula = self.fresh();
+        self.names.insert(name.to_string(), self.num_variables);
+        formula
+    }
+
+    /// Returns the variable of the name, if it was used.
+    pub fn get_variable(&self, name: &str) -> Option<Variable> {
+        self.names.get(name).copied()
+    }
+
+    /// Returns the number of variables handed out, which are `1..=num_variables`.
+    pub fn get_num_variables(&self) -> Variable {
+        self.num_variables
+    }
+
+    /// Requires the formula to hold.
+    pub fn add(&mut self, formula: Formula) {
+        self.constraints.push(formula);
+    }
+
+    /// Requires the two formulas to be equal.
+    pub fn add_equal(&mut self, left: Formula, right: Formula) {
+        self.add(left.iff(right));
+    }
+
+    /// Requires the first formula to imply the second.
+    pub fn add_implication(&mut self, left: Formula, right: Formula) {
+        self.add(left.implies(right));
+    }
+
+    /// Returns a variable defined equal to the formula, to share it between constraints without
+    /// repeating it.
+    pub fn define(&mut self, formula: Formula) -> Formula {
+        let variable = self.fresh();
+        self.add_equal(variable.clone(), formula);
+        variable
+    }
+
+    pub fn get_constraints(&self) -> &[Formula] {
+        &self.constraints
+    }
+
+    /// Returns the conjunction of the constraints.
+    pub fn to_formula(&self) -> Formula {
+        Formula::all(self.constraints.iter().cloned())
+    }
+
+    /// Returns the Tseitin encoding of the constraints, whose auxiliary variables follow those
+    /// handed out.
+    pub fn to_expression(&self) -> Expression {
+        self.to_formula().to_expression(self.num_variables)
+    }
+
+    /// Solves the constraints with the DPLL search, returning a model of the variables handed
+    /// out, or `None` if there is none.
+    pub fn solve(&self) -> Option<Assignment> {
+        let mut expression = self.to_expression();
+        expression.optimize();
+        let model = solve_dpll(&mut expression)?;
+        let mut model = project_assignment(&model, self.num_variables);
+        // Variables the constraints do not mention take any value
+        for variable in 1..=self.num_variables {
+            model.entry(variable).or_insert(false);
+        }
+        Some(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::ClauseSink;
+
+    #[test]
+    fn test_operators() {
+        let mut builder = FormulaBuilder::new();
+        let [a, b, c, d] = [
+            builder.named("a"),
+            builder.named("b"),
+            builder.named("c"),
+            builder.named("d"),
+        ];
+        assert_eq!(builder.named("c"), c);
+        assert_eq!(builder.get_variable("d"), Some(4));
+
+        let formula = (&a | !&b) & (&c ^ &d);
+        assert_eq!(
+            formula,
+            Formula::And(vec![
+                Formula::Or(vec![a.clone(), Formula::Not(Box::new(b.clone()))]),
+                Formula::Xor(Box::new(c.clone()), Box::new(d.clone())),
+            ])
+        );
+        assert_eq!(
+            &a & &b & &c,
+            Formula::And(vec![a.clone(), b.clone(), c.clone()])
+        );
+        assert_eq!(!!a.clone(), a);
+        assert_eq!(Formula::literal(-3), !c.clone());
+
+        // The encoding has the same models on the variables handed out
+        builder.add(formula.clone());
+        let expression = builder.to_expression();
+        for mask in 0..1u32 << 4 {
+            let model: Assignment = (1..=4)
+                .map(|variable| (variable, mask >> (variable - 1) & 1 == 1))
+                .collect();
+            let mut fixed = expression.clone();
+            for (variable, value) in &model {
+                let literal = *variable as Literal;
+                fixed.emit(&[if *value { literal } else { -literal }]);
+            }
+            fixed.optimize();
+            assert_eq!(
+                solve_dpll(&mut fixed).is_some(),
+                formula.evaluate(&model),
+                "{:b}",
+                mask
+            );
+        }
+    }
+
+    #[test]
+    fn test_formula_builder() {
+        // A ripple-carry adder of two 3-bit numbers, asked for a sum of 11
+        let mut builder = FormulaBuilder::new();
+        let x = builder.fresh_many(3);
+        let y = builder.fresh_many(3);
+        let mut carry = Formula::Constant(false);
+        let mut sum = Vec::new();
+        for (x, y) in x.iter().zip(&y) {
+            sum.push(builder.define(x ^ y ^ &carry));
+            carry = builder.define((x & y) | (&carry & (x ^ y)));
+        }
+        sum.push(carry);
+        for (bit, formula) in sum.iter().enumerate() {
+            let formula = formula.clone();
+            builder.add(if 11 >> bit & 1 == 1 {
+                formula
+            } else {
+                !formula
+            });
+        }
+        builder.add_implication(x[0].clone(), y[2].clone());
+
+        let model = builder.solve().unwrap();
+        assert_eq!(model.len(), builder.get_num_variables() as usize);
+        assert!(builder.to_formula().evaluate(&model));
+        let number = |bits: &[Formula]| {
+            bits.iter()
+                .enumerate()
+                .map(|(bit, formula)| (formula.evaluate(&model) as usize) << bit)
+                .sum::<usize>()
+        };
+        assert_eq!(number(&x) + number(&y), 11);
+
+        builder.add_equal(x[0].clone(), !y[2].clone());
+        builder.add(x[0].clone());
+        assert_eq!(builder.solve(), None);
+    }
+}
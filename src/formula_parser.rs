@@ -0,0 +1,434 @@
+use std::fmt;
+
+use crate::cnf::Variable;
+use crate::expression::Expression;
+use crate::formula::{Formula, SymbolTable};
+
+/// An error in the text of a formula.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FormulaParseError {
+    /// The byte offset of the problem in the input.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FormulaParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for FormulaParseError {}
+
+/// Parses an infix boolean formula such as `(a | !b) & (c -> d) & xor(e, f)`.
+///
+/// From lowest to highest precedence, the operators are `<->` (also `<=>`), `->` (also `=>`, right
+/// associative), `|` (also `||`), `^`, `&` (also `&&`), and the prefix negations `!` and `~`.
+/// Atoms are names (letters, digits and `_`, not starting with a digit), `true`, `false`,
+/// parenthesized formulas, and the functions `and`, `or`, `xor`, `not`, `implies` and `iff`.
+///
+/// Names are numbered in order of first appearance and returned in the symbol table.
+pub fn parse_formula(input: &str) -> Result<(Formula, SymbolTable), FormulaParseError> {
+    let mut symbols = SymbolTable::new();
+    let formula = parse_formula_with_symbols(input, &mut symbols)?;
+    Ok((formula, symbols))
+}
+
+/// Parses a formula, resolving names through (and adding new names to) an existing symbol table,
+/// so that several formulas can share variables.
+pub fn parse_formula_with_symbols(
+    input: &str,
+    symbols: &mut SymbolTable,
+) -> Result<Formula, FormulaParseError> {
+    let mut parser = FormulaParser {
+        tokens: tokenize(input)?,
+        position: 0,
+        end: input.len(),
+        symbols,
+    };
+
+    let formula = parser.parse_iff()?;
+    if let Some((token, position)) = parser.tokens.get(parser.position) {
+        return Err(FormulaParseError {
+            position: *position,
+            message: format!("Unexpected {}", token),
+        });
+    }
+
+    Ok(formula)
+}
+
+/// Parses a formula and converts it to CNF. The named variables keep the numbers from the returned
+/// symbol table, and Tseitin auxiliary variables are numbered after them.
+pub fn parse_formula_to_cnf(input: &str) -> Result<(Expression, SymbolTable), FormulaParseError> {
+    let (formula, symbols) = parse_formula(input)?;
+    let expression = formula.to_expression(symbols.len() as Variable);
+    Ok((expression, symbols))
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Name(String),
+    Not,
+    And,
+    Or,
+    Xor,
+    Implies,
+    Iff,
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "name '{}'", name),
+            Token::Not => write!(f, "'!'"),
+            Token::And => write!(f, "'&'"),
+            Token::Or => write!(f, "'|'"),
+            Token::Xor => write!(f, "'^'"),
+            Token::Implies => write!(f, "'->'"),
+            Token::Iff => write!(f, "'<->'"),
+            Token::LeftParen => write!(f, "'('"),
+            Token::RightParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, FormulaParseError> {
+    let mut tokens = Vec::new();
+    let bytes = input.as_bytes();
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let start = position;
+        let rest = &input[position..];
+        let symbols: [(&str, Token); 13] = [
+            ("<->", Token::Iff),
+            ("<=>", Token::Iff),
+            ("->", Token::Implies),
+            ("=>", Token::Implies),
+            ("&&", Token::And),
+            ("||", Token::Or),
+            ("&", Token::And),
+            ("|", Token::Or),
+            ("^", Token::Xor),
+            ("!", Token::Not),
+            ("~", Token::Not),
+            ("(", Token::LeftParen),
+            (")", Token::RightParen),
+        ];
+
+        if bytes[position].is_ascii_whitespace() {
+            position += 1;
+            continue;
+        }
+
+        if let Some((symbol, token)) = symbols.iter().find(|(symbol, _)| rest.starts_with(symbol)) {
+            tokens.push((token.clone(), start));
+            position += symbol.len();
+            continue;
+        }
+
+        if bytes[position] == b',' {
+            tokens.push((Token::Comma, start));
+            position += 1;
+            continue;
+        }
+
+        if bytes[position].is_ascii_alphabetic() || bytes[position] == b'_' {
+            while position < bytes.len()
+                && (bytes[position].is_ascii_alphanumeric() || bytes[position] == b'_')
+            {
+                position += 1;
+            }
+            tokens.push((Token::Name(input[start..position].to_string()), start));
+            continue;
+        }
+
+        return Err(FormulaParseError {
+            position: start,
+            message: format!("Unexpected character '{}'", rest.chars().next().unwrap()),
+        });
+    }
+
+    Ok(tokens)
+}
+
+struct FormulaParser<'a> {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    end: usize,
+    symbols: &'a mut SymbolTable,
+}
+
+impl FormulaParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn current_position(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map(|(_, position)| *position)
+            .unwrap_or(self.end)
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, FormulaParseError> {
+        Err(FormulaParseError {
+            position: self.current_position(),
+            message,
+        })
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), FormulaParseError> {
+        if self.eat(token) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(found) => self.error(format!("Expected {} but found {}", token, found)),
+            None => self.error(format!(
+                "Expected {} but reached the end of the input",
+                token
+            )),
+        }
+    }
+
+    fn parse_iff(&mut self) -> Result<Formula, FormulaParseError> {
+        let mut left = self.parse_implies()?;
+        while self.eat(&Token::Iff) {
+            let right = self.parse_implies()?;
+            left = Formula::Iff(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_implies(&mut self) -> Result<Formula, FormulaParseError> {
+        let left = self.parse_or()?;
+        if self.eat(&Token::Implies) {
+            let right = self.parse_implies()?;
+            return Ok(Formula::Implies(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<Formula, FormulaParseError> {
+        let mut children = vec![self.parse_xor()?];
+        while self.eat(&Token::Or) {
+            children.push(self.parse_xor()?);
+        }
+        Ok(if children.len() == 1 {
+            children.pop().unwrap()
+        } else {
+            Formula::Or(children)
+        })
+    }
+
+    fn parse_xor(&mut self) -> Result<Formula, FormulaParseError> {
+        let mut left = self.parse_and()?;
+        while self.eat(&Token::Xor) {
+            let right = self.parse_and()?;
+            left = Formula::Xor(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Formula, FormulaParseError> {
+        let mut children = vec![self.parse_unary()?];
+        while self.eat(&Token::And) {
+            children.push(self.parse_unary()?);
+        }
+        Ok(if children.len() == 1 {
+            children.pop().unwrap()
+        } else {
+            Formula::And(children)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Formula, FormulaParseError> {
+        if self.eat(&Token::Not) {
+            return Ok(Formula::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Formula, FormulaParseError> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return self.error("Unexpected end of the input".to_string()),
+        };
+
+        match token {
+            Token::LeftParen => {
+                self.position += 1;
+                let formula = self.parse_iff()?;
+                self.expect(&Token::RightParen)?;
+                Ok(formula)
+            }
+            Token::Name(name) => {
+                let name_position = self.current_position();
+                self.position += 1;
+
+                if self.peek() == Some(&Token::LeftParen) {
+                    return self.parse_call(&name, name_position);
+                }
+
+                Ok(match name.as_str() {
+                    "true" => Formula::Constant(true),
+                    "false" => Formula::Constant(false),
+                    _ => Formula::Variable(self.symbols.get_or_insert(&name)),
+                })
+            }
+            other => self.error(format!("Unexpected {}", other)),
+        }
+    }
+
+    fn parse_call(
+        &mut self,
+        name: &str,
+        name_position: usize,
+    ) -> Result<Formula, FormulaParseError> {
+        self.expect(&Token::LeftParen)?;
+        let mut arguments = Vec::new();
+        if !self.eat(&Token::RightParen) {
+            loop {
+                arguments.push(self.parse_iff()?);
+                if self.eat(&Token::RightParen) {
+                    break;
+                }
+                self.expect(&Token::Comma)?;
+            }
+        }
+
+        let arity_error = |expected: &str| {
+            Err(FormulaParseError {
+                position: name_position,
+                message: format!("Function '{}' expects {} arguments", name, expected),
+            })
+        };
+
+        match name {
+            "and" => Ok(Formula::And(arguments)),
+            "or" => Ok(Formula::Or(arguments)),
+            "not" if arguments.len() == 1 => Ok(Formula::Not(Box::new(arguments.pop().unwrap()))),
+            "not" => arity_error("1"),
+            "xor" if !arguments.is_empty() => {
+                let mut arguments = arguments.into_iter();
+                let first = arguments.next().unwrap();
+                Ok(arguments.fold(first, |left, right| {
+                    Formula::Xor(Box::new(left), Box::new(right))
+                }))
+            }
+            "xor" => arity_error("at least 1"),
+            "implies" | "iff" if arguments.len() == 2 => {
+                let right = Box::new(arguments.pop().unwrap());
+                let left = Box::new(arguments.pop().unwrap());
+                Ok(if name == "implies" {
+                    Formula::Implies(left, right)
+                } else {
+                    Formula::Iff(left, right)
+                })
+            }
+            "implies" | "iff" => arity_error("2"),
+            _ => Err(FormulaParseError {
+                position: name_position,
+                message: format!("Unknown function '{}'", name),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cnf::{Assignment, Clause, Literal, Variable, CNF};
+    use crate::formula::project_assignment;
+    use crate::solver::solve;
+
+    /// Checks that the satisfying assignments of the CNF are exactly those of the formula.
+    fn assert_equivalent(input: &str) {
+        let (formula, symbols) = parse_formula(input).unwrap();
+        let (expression, _) = parse_formula_to_cnf(input).unwrap();
+        let num_variables = symbols.len() as Variable;
+
+        for mask in 0..(1u32 << num_variables) {
+            let mut assignment = Assignment::new();
+            let mut restricted = expression.clone();
+            for variable in 1..=num_variables {
+                let value = (mask >> (variable - 1)) & 1 == 1;
+                assignment.insert(variable, value);
+                let mut unit = Clause::new();
+                let literal = variable as Literal;
+                unit.insert(if value { literal } else { -literal });
+                restricted.add_clause(unit);
+            }
+
+            let result = solve(restricted, false, true);
+            assert_eq!(
+                result.is_some(),
+                formula.evaluate(&assignment),
+                "{} under {:?}",
+                input,
+                assignment
+            );
+            if let Some(model) = result {
+                assert_eq!(project_assignment(&model, num_variables), assignment);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_formula_precedence() {
+        let (formula, symbols) = parse_formula("!a & b | c -> d <-> e").unwrap();
+        let (a, b, c, d, e) = (1, 2, 3, 4, 5);
+        let variable = |v| Box::new(Formula::Variable(v));
+
+        assert_eq!(symbols.name(a), Some("a"));
+        assert_eq!(symbols.variable("e"), Some(e));
+        assert_eq!(
+            formula,
+            Formula::Iff(
+                Box::new(Formula::Implies(
+                    Box::new(Formula::Or(vec![
+                        Formula::And(vec![Formula::Not(variable(a)), Formula::Variable(b)]),
+                        Formula::Variable(c),
+                    ])),
+                    variable(d),
+                )),
+                variable(e),
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_formula_to_cnf_is_equivalent() {
+        assert_equivalent("(a | !b) & (c -> d) & xor(e, f)");
+        assert_equivalent("a <-> (b ^ !c)");
+        assert_equivalent("!(a & b) | implies(c, false) | iff(a, c)");
+        assert_equivalent("xor(a, b, c) & (true | a) && ~(b || false)");
+    }
+
+    #[test]
+    fn test_parse_formula_errors() {
+        assert_eq!(
+            parse_formula("a & (b | c").unwrap_err(),
+            FormulaParseError {
+                position: 10,
+                message: "Expected ')' but reached the end of the input".to_string(),
+            }
+        );
+        assert_eq!(parse_formula("a $ b").unwrap_err().position, 2);
+        assert_eq!(parse_formula("a & nope(b)").unwrap_err().position, 4);
+        assert_eq!(parse_formula("a b").unwrap_err().position, 2);
+    }
+}
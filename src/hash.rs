@@ -0,0 +1,47 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A 64-bit FNV-1a hasher. Unlike the default hasher, its output is fixed across runs,
+/// platforms and releases, so it is used for persistent hashes such as instance fingerprints.
+#[derive(Clone, Copy, Debug)]
+pub struct FnvHasher {
+    state: u64,
+}
+
+impl FnvHasher {
+    pub fn new() -> FnvHasher {
+        FnvHasher {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+pub type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
+
+/// A hash map whose iteration order only depends on the operations performed on it,
+/// which keeps the solver's search deterministic from one run to the next.
+pub type FnvHashMap<K, V> = hashbrown::HashMap<K, V, FnvBuildHasher>;
+
+/// The set counterpart of [`FnvHashMap`].
+pub type FnvHashSet<T> = hashbrown::HashSet<T, FnvBuildHasher>;
@@ -0,0 +1,279 @@
+//! Groups of clauses that can be switched on and off between solves through activation literals,
+//! and helpers to unroll a clause template over several time frames, as used by incremental
+//! bounded model checking.
+//!
+//! Every clause `C` of a group is added as `-a | C` for the activation literal `a` of the group:
+//! solving under the assumption `a` enables the group, and under `-a` satisfies all of its clauses.
+//! Groups can be passed explicitly to [`IncrementalExpression::solve`], or be enabled and disabled
+//! beforehand for [`IncrementalExpression::solve_enabled`], and committed once they should hold for
+//! good.
+
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+use crate::solver::solve_with_assumptions;
+
+/// A handle to a group of clauses of an [`IncrementalExpression`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ClauseGroup(usize);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum GroupState {
+    Enabled,
+    Disabled,
+    /// The clauses of the group were made permanent.
+    Committed,
+}
+
+/// Permanent clauses, plus groups of clauses enabled per solve.
+#[derive(Clone, Debug, Default)]
+pub struct IncrementalExpression {
+    clauses: Vec<Clause>,
+    groups: Vec<Vec<Clause>>,
+    states: Vec<GroupState>,
+    max_variable: Variable,
+}
+
+impl IncrementalExpression {
+    pub fn new() -> IncrementalExpression {
+        IncrementalExpression::default()
+    }
+
+    /// Starts from the clauses of the expression, as permanent clauses.
+    pub fn from_expression(expression: &Expression) -> IncrementalExpression {
+        let mut incremental = IncrementalExpression::new();
+        for clause in expression.get_clauses() {
+            incremental.add_clause(clause);
+        }
+        incremental
+    }
+
+    fn reserve(&mut self, clause: &Clause) {
+        for literal in clause.literals() {
+            self.max_variable = self.max_variable.max(to_variable(*literal));
+        }
+    }
+
+    /// Adds a clause that holds in every solve.
+    pub fn add_clause(&mut self, clause: Clause) {
+        self.reserve(&clause);
+        self.clauses.push(clause);
+    }
+
+    /// Creates a new, empty group, which starts enabled.
+    pub fn new_group(&mut self) -> ClauseGroup {
+        self.groups.push(Vec::new());
+        self.states.push(GroupState::Enabled);
+        ClauseGroup(self.groups.len() - 1)
+    }
+
+    /// Adds a clause that only holds in the solves enabling the group, or in every solve if the
+    /// group was committed.
+    pub fn add_group_clause(&mut self, group: ClauseGroup, clause: Clause) {
+        if self.states[group.0] == GroupState::Committed {
+            self.add_clause(clause);
+        } else {
+            self.reserve(&clause);
+            self.groups[group.0].push(clause);
+        }
+    }
+
+    /// Enables the group for the next calls to [`IncrementalExpression::solve_enabled`].
+    pub fn enable(&mut self, group: ClauseGroup) {
+        self.set_enabled(group, true);
+    }
+
+    /// Disables the group for the next calls to [`IncrementalExpression::solve_enabled`].
+    /// Panics if the group was committed.
+    pub fn disable(&mut self, group: ClauseGroup) {
+        self.set_enabled(group, false);
+    }
+
+    fn set_enabled(&mut self, group: ClauseGroup, enabled: bool) {
+        assert!(
+            self.states[group.0] != GroupState::Committed,
+            "A committed group cannot be disabled or enabled"
+        );
+        self.states[group.0] = if enabled {
+            GroupState::Enabled
+        } else {
+            GroupState::Disabled
+        };
+    }
+
+    /// Returns true if the group is enabled or committed.
+    pub fn is_enabled(&self, group: ClauseGroup) -> bool {
+        self.states[group.0] != GroupState::Disabled
+    }
+
+    /// Turns the clauses of the group into permanent clauses, dropping their activation literal.
+    /// This cannot be undone.
+    pub fn commit(&mut self, group: ClauseGroup) {
+        let clauses = std::mem::take(&mut self.groups[group.0]);
+        self.clauses.extend(clauses);
+        self.states[group.0] = GroupState::Committed;
+    }
+
+    pub fn get_num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Returns the largest variable of the clauses, not counting the activation literals.
+    pub fn get_max_variable(&self) -> Variable {
+        self.max_variable
+    }
+
+    /// Returns the activation literal of the group in [`IncrementalExpression::to_expression`].
+    /// The activation literals are numbered after the other variables, so they change when
+    /// clauses with larger variables are added.
+    pub fn get_activation_literal(&self, group: ClauseGroup) -> Literal {
+        (self.max_variable as usize + 1 + group.0) as Literal
+    }
+
+    /// Returns the permanent clauses and the clauses of every group guarded by their activation
+    /// literals.
+    pub fn to_expression(&self) -> Expression {
+        let mut expression = Expression::new();
+        for clause in &self.clauses {
+            expression.add_clause(clause.clone());
+        }
+        for (index, clauses) in self.groups.iter().enumerate() {
+            let activation = self.get_activation_literal(ClauseGroup(index));
+            for clause in clauses {
+                let mut guarded = clause.clone();
+                guarded.insert(-activation);
+                expression.add_clause(guarded);
+            }
+        }
+        expression
+    }
+
+    /// Returns the assumptions enabling exactly the given groups (and the committed ones).
+    pub fn get_assumptions(&self, enabled: &[ClauseGroup]) -> Vec<Literal> {
+        (0..self.groups.len())
+            .filter(|index| self.states[*index] != GroupState::Committed)
+            .map(|index| {
+                let activation = self.get_activation_literal(ClauseGroup(index));
+                if enabled.contains(&ClauseGroup(index)) {
+                    activation
+                } else {
+                    -activation
+                }
+            })
+            .collect()
+    }
+
+    /// Solves the permanent clauses together with the enabled groups. The model does not contain
+    /// the activation literals.
+    pub fn solve(&self, enabled: &[ClauseGroup]) -> Option<Assignment> {
+        let mut model =
+            solve_with_assumptions(&self.to_expression(), &self.get_assumptions(enabled))?;
+        model.retain(|variable, _| *variable <= self.max_variable);
+        Some(model)
+    }
+
+    /// Solves the permanent clauses together with the groups that are currently enabled.
+    pub fn solve_enabled(&self) -> Option<Assignment> {
+        let enabled: Vec<ClauseGroup> = (0..self.groups.len())
+            .map(ClauseGroup)
+            .filter(|group| self.is_enabled(*group))
+            .collect();
+        self.solve(&enabled)
+    }
+}
+
+/// Returns the clause with every variable increased by the offset, keeping the polarities.
+pub fn shift_clause(clause: &Clause, offset: Variable) -> Clause {
+    let mut shifted = Clause::new();
+    for literal in clause.literals() {
+        let variable = (to_variable(*literal) + offset) as Literal;
+        shifted.insert(if *literal > 0 { variable } else { -variable });
+    }
+    shifted
+}
+
+/// Instantiates the template once per frame, shifting its variables by `stride` from one frame to
+/// the next. For a transition relation over the current state variables `1..=k` and the next
+/// state variables `k + 1..=2k`, a stride of `k` chains the frames together.
+pub fn unroll(template: &[Clause], stride: Variable, frames: usize) -> Vec<Clause> {
+    (0..frames)
+        .flat_map(|frame| {
+            template
+                .iter()
+                .map(move |clause| shift_clause(clause, frame as Variable * stride))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unroll() {
+        let template = vec![
+            Clause::from_literals(&[1, -3]),
+            Clause::from_literals(&[2, 4]),
+        ];
+        let unrolled = unroll(&template, 2, 3);
+        assert_eq!(unrolled.len(), 6);
+        assert_eq!(unrolled[2].literals(), &vec![3, -5]);
+        assert_eq!(unrolled[5].literals(), &vec![6, 8]);
+    }
+
+    #[test]
+    fn test_bounded_model_checking() {
+        // A bit that starts false and flips in every step: x(t + 1) = -x(t), with x(t) = t + 1
+        let mut bmc = IncrementalExpression::new();
+        bmc.add_clause(Clause::from_literals(&[-1]));
+        let transition = vec![
+            Clause::from_literals(&[1, 2]),
+            Clause::from_literals(&[-1, -2]),
+        ];
+        for step in unroll(&transition, 1, 3) {
+            bmc.add_clause(step);
+        }
+
+        // Is the bit true at frame t? Only the property of the current frame is enabled
+        let properties: Vec<ClauseGroup> = (0..4)
+            .map(|frame| {
+                let group = bmc.new_group();
+                bmc.add_group_clause(group, Clause::from_literals(&[frame + 1]));
+                group
+            })
+            .collect();
+        assert_eq!(bmc.get_activation_literal(properties[0]), 5);
+
+        let reachable: Vec<bool> = properties
+            .iter()
+            .map(|group| bmc.solve(&[*group]).is_some())
+            .collect();
+        assert_eq!(reachable, vec![false, true, false, true]);
+
+        let model = bmc.solve(&[properties[3]]).unwrap();
+        assert_eq!(model.len(), 4);
+        assert!(bmc.solve(&[properties[0], properties[1]]).is_none());
+        assert!(bmc.solve(&[]).is_some());
+    }
+
+    #[test]
+    fn test_enable_disable_and_commit_groups() {
+        let mut expression = IncrementalExpression::new();
+        expression.add_clause(Clause::from_literals(&[1, 2]));
+        let first = expression.new_group();
+        expression.add_group_clause(first, Clause::from_literals(&[-1]));
+        let second = expression.new_group();
+        expression.add_group_clause(second, Clause::from_literals(&[-2]));
+
+        assert!(expression.solve_enabled().is_none());
+        expression.disable(second);
+        assert!(expression.solve_enabled().unwrap()[&2]);
+
+        expression.commit(first);
+        assert!(expression.is_enabled(first));
+        expression.enable(second);
+        assert!(expression.solve_enabled().is_none());
+        expression.disable(second);
+        assert!(expression.solve(&[]).unwrap()[&2]);
+        assert_eq!(expression.to_expression().get_clauses().len(), 3);
+    }
+}
@@ -0,0 +1,1011 @@
+use std::fmt;
+
+use crate::cnf::{to_variable, Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+use crate::weighted_expression::{Weight, WeightedExpression};
+
+/// Identifies documents in the interchange format.
+pub const FORMAT_NAME: &str = "microsat-instance";
+
+/// The version of the interchange schema written by this crate.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// A clause of an [`Instance`], with the optional information DIMACS cannot carry.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct InstanceClause {
+    pub literals: Vec<Literal>,
+    /// The weight of a soft clause, or `None` for a hard clause.
+    pub weight: Option<Weight>,
+    pub label: Option<String>,
+    pub group: Option<String>,
+}
+
+/// The native, lossless interchange representation of a problem instance.
+///
+/// Instances are exchanged as JSON or CBOR documents with the same structure. In JSON:
+///
+/// ```text
+/// {
+///   "format": "microsat-instance",
+///   "version": 1,
+///   "variables": 3,
+///   "clauses": [
+///     { "literals": [1, -2] },
+///     { "literals": [3], "weight": 5, "label": "prefer-3", "group": "preferences" }
+///   ],
+///   "metadata": { "generator": "example" }
+/// }
+/// ```
+///
+/// `variables` is the number of variables (every literal must reference a variable between 1 and
+/// this number), `weight` is only present on soft clauses, and `label`, `group` and `metadata`
+/// are optional. In CBOR, maps are keyed by the same text strings and integers are encoded natively.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Instance {
+    pub num_variables: Variable,
+    pub clauses: Vec<InstanceClause>,
+    /// Ordered key-value pairs describing the instance (provenance, expected status, ...).
+    pub metadata: Vec<(String, String)>,
+}
+
+/// An error encountered while importing an instance document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InterchangeError {
+    /// The document is not well-formed JSON or CBOR; `offset` is the byte offset of the problem.
+    Syntax { offset: usize, message: String },
+    /// The document is well-formed but does not follow the instance schema.
+    /// `path` locates the offending value, such as `$.clauses[2].literals[0]`.
+    Schema { path: String, message: String },
+}
+
+impl fmt::Display for InterchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterchangeError::Syntax { offset, message } => {
+                write!(f, "syntax error at byte {}: {}", offset, message)
+            }
+            InterchangeError::Schema { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for InterchangeError {}
+
+/// The document model shared by the JSON and CBOR encodings.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    /// An integer, wide enough for both the weights up to `u64::MAX` and the negative literals.
+    Integer(i128),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(String, Value)>),
+    /// A well-formed value the schema never uses (booleans, null, floats, byte strings, ...).
+    Unsupported(&'static str),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "an integer",
+            Value::Text(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Map(_) => "an object",
+            Value::Unsupported(kind) => kind,
+        }
+    }
+}
+
+fn schema_error<T>(path: &str, message: String) -> Result<T, InterchangeError> {
+    Err(InterchangeError::Schema {
+        path: path.to_string(),
+        message,
+    })
+}
+
+fn expect_integer(value: &Value, path: &str) -> Result<i128, InterchangeError> {
+    match value {
+        Value::Integer(integer) => Ok(*integer),
+        other => schema_error(path, format!("expected an integer, found {}", other.kind())),
+    }
+}
+
+fn expect_text(value: &Value, path: &str) -> Result<String, InterchangeError> {
+    match value {
+        Value::Text(text) => Ok(text.clone()),
+        other => schema_error(path, format!("expected a string, found {}", other.kind())),
+    }
+}
+
+fn expect_array<'a>(value: &'a Value, path: &str) -> Result<&'a Vec<Value>, InterchangeError> {
+    match value {
+        Value::Array(items) => Ok(items),
+        other => schema_error(path, format!("expected an array, found {}", other.kind())),
+    }
+}
+
+/// Checks that the value is an object with no duplicate or unknown keys, and returns its fields.
+fn expect_map<'a>(
+    value: &'a Value,
+    path: &str,
+    allowed_keys: Option<&[&str]>,
+) -> Result<&'a Vec<(String, Value)>, InterchangeError> {
+    let fields = match value {
+        Value::Map(fields) => fields,
+        other => return schema_error(path, format!("expected an object, found {}", other.kind())),
+    };
+
+    for (index, (key, _)) in fields.iter().enumerate() {
+        if fields[..index].iter().any(|(previous, _)| previous == key) {
+            return schema_error(path, format!("duplicate key \"{}\"", key));
+        }
+        if let Some(allowed_keys) = allowed_keys {
+            if !allowed_keys.contains(&key.as_str()) {
+                return schema_error(path, format!("unknown key \"{}\"", key));
+            }
+        }
+    }
+
+    Ok(fields)
+}
+
+fn get_field<'a>(fields: &'a [(String, Value)], key: &str) -> Option<&'a Value> {
+    fields
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value)
+}
+
+impl Instance {
+    pub fn new() -> Instance {
+        Instance {
+            num_variables: 0,
+            clauses: Vec::new(),
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Builds an instance with one hard clause per clause of the expression.
+    pub fn from_expression(expression: &Expression) -> Instance {
+        let mut instance = Instance::new();
+        for clause in expression.get_clauses() {
+            instance.add_clause(clause.literals().clone(), None);
+        }
+        instance.metadata = expression.get_metadata().clone();
+        instance
+    }
+
+    /// Builds an instance from the hard and soft clauses of a weighted expression.
+    pub fn from_weighted_expression(expression: &WeightedExpression) -> Instance {
+        let mut instance = Instance::new();
+        for clause in expression.get_hard_clauses() {
+            instance.add_clause(clause.literals().clone(), None);
+        }
+        for (clause, weight) in expression.get_soft_clauses() {
+            instance.add_clause(clause.literals().clone(), Some(*weight));
+        }
+        instance
+    }
+
+    /// Appends a clause, growing the variable count to cover its literals.
+    pub fn add_clause(
+        &mut self,
+        literals: Vec<Literal>,
+        weight: Option<Weight>,
+    ) -> &mut InstanceClause {
+        for literal in &literals {
+            self.num_variables = self.num_variables.max(to_variable(*literal));
+        }
+        self.clauses.push(InstanceClause {
+            literals,
+            weight,
+            label: None,
+            group: None,
+        });
+        self.clauses.last_mut().unwrap()
+    }
+
+    /// Returns an expression containing the hard clauses of the instance.
+    pub fn to_expression(&self) -> Expression {
+        let mut expression = Expression::new();
+        for clause in self.clauses.iter().filter(|clause| clause.weight.is_none()) {
+            expression.add_clause(Clause::from_literals(&clause.literals));
+        }
+        for (key, value) in &self.metadata {
+            expression.set_metadata(key, value);
+        }
+        expression
+    }
+
+    /// Returns a weighted expression containing both the hard and soft clauses of the instance.
+    pub fn to_weighted_expression(&self) -> WeightedExpression {
+        let mut expression = WeightedExpression::new();
+        for clause in &self.clauses {
+            match clause.weight {
+                Some(weight) => {
+                    expression.add_soft_clause(Clause::from_literals(&clause.literals), weight)
+                }
+                None => expression.add_hard_clause(Clause::from_literals(&clause.literals)),
+            }
+        }
+        expression
+    }
+
+    /// Imports an instance from a JSON document, validating it against the schema.
+    pub fn from_json(contents: &str) -> Result<Instance, InterchangeError> {
+        let mut parser = JsonParser {
+            bytes: contents.as_bytes(),
+            position: 0,
+        };
+        let value = parser.parse_document()?;
+        Instance::from_value(&value)
+    }
+
+    /// Imports an instance from a CBOR document, validating it against the schema.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Instance, InterchangeError> {
+        let mut decoder = CborDecoder { bytes, position: 0 };
+        let value = decoder.decode(0)?;
+        if decoder.position != bytes.len() {
+            return Err(InterchangeError::Syntax {
+                offset: decoder.position,
+                message: "trailing bytes after the document".to_string(),
+            });
+        }
+        Instance::from_value(&value)
+    }
+
+    pub(crate) fn from_value(value: &Value) -> Result<Instance, InterchangeError> {
+        let fields = expect_map(
+            value,
+            "$",
+            Some(&["format", "version", "variables", "clauses", "metadata"]),
+        )?;
+
+        match get_field(fields, "format") {
+            Some(format) => {
+                let format = expect_text(format, "$.format")?;
+                if format != FORMAT_NAME {
+                    return schema_error(
+                        "$.format",
+                        format!("expected \"{}\", found \"{}\"", FORMAT_NAME, format),
+                    );
+                }
+            }
+            None => return schema_error("$", "missing key \"format\"".to_string()),
+        }
+
+        let version = match get_field(fields, "version") {
+            Some(version) => expect_integer(version, "$.version")?,
+            None => return schema_error("$", "missing key \"version\"".to_string()),
+        };
+        if version < 1 || version > SCHEMA_VERSION as i128 {
+            return schema_error(
+                "$.version",
+                format!("unsupported schema version {}", version),
+            );
+        }
+
+        let num_variables = match get_field(fields, "variables") {
+            Some(variables) => expect_integer(variables, "$.variables")?,
+            None => return schema_error("$", "missing key \"variables\"".to_string()),
+        };
+        if num_variables < 0 || num_variables > Literal::MAX as i128 {
+            return schema_error(
+                "$.variables",
+                format!("variable count {} is out of range", num_variables),
+            );
+        }
+
+        let mut instance = Instance::new();
+        instance.num_variables = num_variables as Variable;
+
+        let clauses = match get_field(fields, "clauses") {
+            Some(clauses) => expect_array(clauses, "$.clauses")?,
+            None => return schema_error("$", "missing key \"clauses\"".to_string()),
+        };
+        for (index, clause) in clauses.iter().enumerate() {
+            let path = format!("$.clauses[{}]", index);
+            instance
+                .clauses
+                .push(Self::clause_from_value(clause, &path, num_variables)?);
+        }
+
+        if let Some(metadata) = get_field(fields, "metadata") {
+            for (key, value) in expect_map(metadata, "$.metadata", None)? {
+                let value = expect_text(value, &format!("$.metadata.{}", key))?;
+                instance.metadata.push((key.clone(), value));
+            }
+        }
+
+        Ok(instance)
+    }
+
+    fn clause_from_value(
+        value: &Value,
+        path: &str,
+        num_variables: i128,
+    ) -> Result<InstanceClause, InterchangeError> {
+        let fields = expect_map(value, path, Some(&["literals", "weight", "label", "group"]))?;
+
+        let literals_path = format!("{}.literals", path);
+        let literals = match get_field(fields, "literals") {
+            Some(literals) => expect_array(literals, &literals_path)?,
+            None => return schema_error(path, "missing key \"literals\"".to_string()),
+        };
+
+        let mut clause = InstanceClause::default();
+        for (index, literal) in literals.iter().enumerate() {
+            let literal_path = format!("{}[{}]", literals_path, index);
+            let literal = expect_integer(literal, &literal_path)?;
+            if literal == 0 || literal.unsigned_abs() > num_variables as u128 {
+                return schema_error(
+                    &literal_path,
+                    format!(
+                        "literal {} does not reference a variable between 1 and {}",
+                        literal, num_variables
+                    ),
+                );
+            }
+            clause.literals.push(literal as Literal);
+        }
+
+        if let Some(weight) = get_field(fields, "weight") {
+            let weight_path = format!("{}.weight", path);
+            let weight = expect_integer(weight, &weight_path)?;
+            if weight < 0 {
+                return schema_error(&weight_path, format!("weight {} is negative", weight));
+            }
+            match Weight::try_from(weight) {
+                Ok(weight) => clause.weight = Some(weight),
+                Err(_) => {
+                    return schema_error(&weight_path, format!("weight {} is too large", weight))
+                }
+            }
+        }
+        if let Some(label) = get_field(fields, "label") {
+            clause.label = Some(expect_text(label, &format!("{}.label", path))?);
+        }
+        if let Some(group) = get_field(fields, "group") {
+            clause.group = Some(expect_text(group, &format!("{}.group", path))?);
+        }
+
+        Ok(clause)
+    }
+
+    /// Serializes the instance as a JSON document.
+    pub fn to_json(&self) -> String {
+        let mut output = String::new();
+        write_json(&self.to_value(), &mut output);
+        output
+    }
+
+    /// Serializes the instance as a CBOR document.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        write_cbor(&self.to_value(), &mut output);
+        output
+    }
+
+    pub(crate) fn to_value(&self) -> Value {
+        let clauses = self
+            .clauses
+            .iter()
+            .map(|clause| {
+                let literals = clause
+                    .literals
+                    .iter()
+                    .map(|literal| Value::Integer(*literal as i128))
+                    .collect();
+                let mut fields = vec![("literals".to_string(), Value::Array(literals))];
+                if let Some(weight) = clause.weight {
+                    fields.push(("weight".to_string(), Value::Integer(weight as i128)));
+                }
+                if let Some(label) = &clause.label {
+                    fields.push(("label".to_string(), Value::Text(label.clone())));
+                }
+                if let Some(group) = &clause.group {
+                    fields.push(("group".to_string(), Value::Text(group.clone())));
+                }
+                Value::Map(fields)
+            })
+            .collect();
+
+        let metadata = self
+            .metadata
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::Text(value.clone())))
+            .collect();
+
+        Value::Map(vec![
+            ("format".to_string(), Value::Text(FORMAT_NAME.to_string())),
+            (
+                "version".to_string(),
+                Value::Integer(SCHEMA_VERSION as i128),
+            ),
+            (
+                "variables".to_string(),
+                Value::Integer(self.num_variables as i128),
+            ),
+            ("clauses".to_string(), Value::Array(clauses)),
+            ("metadata".to_string(), Value::Map(metadata)),
+        ])
+    }
+}
+
+fn write_json(value: &Value, output: &mut String) {
+    match value {
+        Value::Integer(integer) => output.push_str(&integer.to_string()),
+        Value::Text(text) => write_json_string(text, output),
+        Value::Array(items) => {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_json(item, output);
+            }
+            output.push(']');
+        }
+        Value::Unsupported(kind) => panic!("Cannot encode {}", kind),
+        Value::Map(fields) => {
+            output.push('{');
+            for (index, (key, item)) in fields.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_json_string(key, output);
+                output.push(':');
+                write_json(item, output);
+            }
+            output.push('}');
+        }
+    }
+}
+
+fn write_json_string(text: &str, output: &mut String) {
+    output.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+/// Writes a CBOR data item header with the given major type and argument.
+fn write_cbor_header(major: u8, argument: u64, output: &mut Vec<u8>) {
+    let major = major << 5;
+    if argument < 24 {
+        output.push(major | argument as u8);
+    } else if argument <= u8::MAX as u64 {
+        output.push(major | 24);
+        output.push(argument as u8);
+    } else if argument <= u16::MAX as u64 {
+        output.push(major | 25);
+        output.extend_from_slice(&(argument as u16).to_be_bytes());
+    } else if argument <= u32::MAX as u64 {
+        output.push(major | 26);
+        output.extend_from_slice(&(argument as u32).to_be_bytes());
+    } else {
+        output.push(major | 27);
+        output.extend_from_slice(&argument.to_be_bytes());
+    }
+}
+
+fn write_cbor(value: &Value, output: &mut Vec<u8>) {
+    match value {
+        Value::Integer(integer) if *integer >= 0 => write_cbor_header(0, *integer as u64, output),
+        Value::Integer(integer) => write_cbor_header(1, (-1 - *integer) as u64, output),
+        Value::Text(text) => {
+            write_cbor_header(3, text.len() as u64, output);
+            output.extend_from_slice(text.as_bytes());
+        }
+        Value::Array(items) => {
+            write_cbor_header(4, items.len() as u64, output);
+            for item in items {
+                write_cbor(item, output);
+            }
+        }
+        Value::Map(fields) => {
+            write_cbor_header(5, fields.len() as u64, output);
+            for (key, item) in fields {
+                write_cbor(&Value::Text(key.clone()), output);
+                write_cbor(item, output);
+            }
+        }
+        Value::Unsupported(kind) => panic!("Cannot encode {}", kind),
+    }
+}
+
+/// A minimal recursive-descent parser for the subset of JSON the schema needs.
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn error<T>(&self, message: &str) -> Result<T, InterchangeError> {
+        Err(InterchangeError::Syntax {
+            offset: self.position,
+            message: message.to_string(),
+        })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.position).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), InterchangeError> {
+        if self.peek() != Some(byte) {
+            return self.error(&format!("expected '{}'", byte as char));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_document(&mut self) -> Result<Value, InterchangeError> {
+        let value = self.parse_value(0)?;
+        if self.peek().is_some() {
+            return self.error("trailing characters after the document");
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, InterchangeError> {
+        if depth > MAX_NESTING {
+            return self.error("document is nested too deeply");
+        }
+
+        match self.peek() {
+            Some(b'{') => {
+                self.position += 1;
+                let mut fields = Vec::new();
+                if self.peek() == Some(b'}') {
+                    self.position += 1;
+                    return Ok(Value::Map(fields));
+                }
+                loop {
+                    if self.peek() != Some(b'"') {
+                        return self.error("expected a string key");
+                    }
+                    let key = self.parse_string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.parse_value(depth + 1)?));
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        Some(b'}') => {
+                            self.position += 1;
+                            return Ok(Value::Map(fields));
+                        }
+                        _ => return self.error("expected ',' or '}'"),
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.position += 1;
+                let mut items = Vec::new();
+                if self.peek() == Some(b']') {
+                    self.position += 1;
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.parse_value(depth + 1)?);
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        Some(b']') => {
+                            self.position += 1;
+                            return Ok(Value::Array(items));
+                        }
+                        _ => return self.error("expected ',' or ']'"),
+                    }
+                }
+            }
+            Some(b'"') => Ok(Value::Text(self.parse_string()?)),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(b't') => self.parse_keyword("true", "a boolean"),
+            Some(b'f') => self.parse_keyword("false", "a boolean"),
+            Some(b'n') => self.parse_keyword("null", "null"),
+            Some(_) => self.error("unexpected character"),
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    fn parse_keyword(
+        &mut self,
+        keyword: &str,
+        kind: &'static str,
+    ) -> Result<Value, InterchangeError> {
+        if !self.bytes[self.position..].starts_with(keyword.as_bytes()) {
+            return self.error("unexpected character");
+        }
+        self.position += keyword.len();
+        Ok(Value::Unsupported(kind))
+    }
+
+    fn parse_number(&mut self) -> Result<Value, InterchangeError> {
+        let start = self.position;
+        if self.bytes[self.position] == b'-' {
+            self.position += 1;
+        }
+        while self.position < self.bytes.len()
+            && matches!(
+                self.bytes[self.position],
+                b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-'
+            )
+        {
+            self.position += 1;
+        }
+
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).unwrap();
+        if text.contains(['.', 'e', 'E']) {
+            return match text.parse::<f64>() {
+                Ok(_) => Ok(Value::Unsupported("a non-integer number")),
+                Err(_) => Err(InterchangeError::Syntax {
+                    offset: start,
+                    message: format!("invalid number '{}'", text),
+                }),
+            };
+        }
+
+        match text.parse::<i128>() {
+            Ok(integer) => Ok(Value::Integer(integer)),
+            Err(_) => Err(InterchangeError::Syntax {
+                offset: start,
+                message: format!("invalid or out of range integer '{}'", text),
+            }),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, InterchangeError> {
+        self.position += 1; // Skip the opening quote
+        let mut bytes = Vec::new();
+        loop {
+            let byte = match self.bytes.get(self.position) {
+                Some(byte) => *byte,
+                None => return self.error("unterminated string"),
+            };
+            self.position += 1;
+
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = match self.bytes.get(self.position) {
+                        Some(escape) => *escape,
+                        None => return self.error("unterminated string"),
+                    };
+                    self.position += 1;
+                    let character = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.parse_unicode_escape()?,
+                        _ => return self.error("invalid escape sequence"),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes());
+                }
+                byte if byte < 0x20 => return self.error("control character in string"),
+                byte => bytes.push(byte),
+            }
+        }
+
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(text),
+            Err(_) => self.error("invalid UTF-8 in string"),
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, InterchangeError> {
+        let digits = match self.bytes.get(self.position..self.position + 4) {
+            Some(digits) => digits,
+            None => return self.error("truncated unicode escape"),
+        };
+        let value = std::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok());
+        match value {
+            Some(value) => {
+                self.position += 4;
+                Ok(value)
+            }
+            None => self.error("invalid unicode escape"),
+        }
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, InterchangeError> {
+        let mut code = self.parse_hex4()?;
+
+        // Characters outside the basic multilingual plane are written as surrogate pairs
+        if (0xd800..0xdc00).contains(&code) {
+            if !self.bytes[self.position..].starts_with(b"\\u") {
+                return self.error("unpaired surrogate in unicode escape");
+            }
+            self.position += 2;
+            let low = self.parse_hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return self.error("invalid low surrogate in unicode escape");
+            }
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+
+        match char::from_u32(code) {
+            Some(character) => Ok(character),
+            None => self.error("invalid unicode escape"),
+        }
+    }
+}
+
+const MAX_NESTING: usize = 64;
+
+/// A decoder for the definite-length subset of CBOR written by [`Instance::to_cbor`].
+struct CborDecoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl CborDecoder<'_> {
+    fn error<T>(&self, offset: usize, message: &str) -> Result<T, InterchangeError> {
+        Err(InterchangeError::Syntax {
+            offset,
+            message: message.to_string(),
+        })
+    }
+
+    fn take(&mut self, count: usize) -> Result<&[u8], InterchangeError> {
+        if self.bytes.len() - self.position < count {
+            return self.error(self.position, "unexpected end of input");
+        }
+        let slice = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(slice)
+    }
+
+    /// Reads a data item header, returning its major type and argument.
+    fn read_header(&mut self) -> Result<(u8, u64), InterchangeError> {
+        let start = self.position;
+        let initial = self.take(1)?[0];
+        let major = initial >> 5;
+        let additional = initial & 0x1f;
+
+        let argument = match additional {
+            0..=23 => additional as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            31 => return self.error(start, "indefinite lengths are not supported"),
+            _ => return self.error(start, "reserved additional information value"),
+        };
+
+        Ok((major, argument))
+    }
+
+    fn read_length(&self, argument: u64, start: usize) -> Result<usize, InterchangeError> {
+        // Every item takes at least one byte, so longer lengths can only be corrupt
+        if argument > (self.bytes.len() - self.position) as u64 {
+            return self.error(start, "length exceeds the remaining input");
+        }
+        Ok(argument as usize)
+    }
+
+    fn decode(&mut self, depth: usize) -> Result<Value, InterchangeError> {
+        let start = self.position;
+        if depth > MAX_NESTING {
+            return self.error(start, "document is nested too deeply");
+        }
+
+        let (major, argument) = self.read_header()?;
+        match major {
+            0 => Ok(Value::Integer(argument as i128)),
+            1 => Ok(Value::Integer(-1 - argument as i128)),
+            2 => {
+                let length = self.read_length(argument, start)?;
+                self.take(length)?;
+                Ok(Value::Unsupported("a byte string"))
+            }
+            3 => {
+                let length = self.read_length(argument, start)?;
+                match String::from_utf8(self.take(length)?.to_vec()) {
+                    Ok(text) => Ok(Value::Text(text)),
+                    Err(_) => self.error(start, "invalid UTF-8 in text string"),
+                }
+            }
+            4 => {
+                let length = self.read_length(argument, start)?;
+                let mut items = Vec::with_capacity(length);
+                for _ in 0..length {
+                    items.push(self.decode(depth + 1)?);
+                }
+                Ok(Value::Array(items))
+            }
+            5 => {
+                let length = self.read_length(argument, start)?;
+                let mut fields = Vec::with_capacity(length);
+                for _ in 0..length {
+                    let key_start = self.position;
+                    let key = match self.decode(depth + 1)? {
+                        Value::Text(key) => key,
+                        _ => return self.error(key_start, "map keys must be text strings"),
+                    };
+                    fields.push((key, self.decode(depth + 1)?));
+                }
+                Ok(Value::Map(fields))
+            }
+            6 => {
+                // Tags are skipped, their content is validated by the schema
+                self.decode(depth + 1)?;
+                Ok(Value::Unsupported("a tagged value"))
+            }
+            _ => match argument {
+                20 | 21 => Ok(Value::Unsupported("a boolean")),
+                22 | 23 => Ok(Value::Unsupported("null")),
+                _ => Ok(Value::Unsupported("a floating point or simple value")),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_instance() -> Instance {
+        let mut instance = Instance::new();
+        instance.add_clause(vec![1, -2], None);
+        let soft = instance.add_clause(vec![3], Some(5));
+        soft.label = Some("prefer \"3\"".to_string());
+        soft.group = Some("preferences".to_string());
+        instance
+            .metadata
+            .push(("generator".to_string(), "example".to_string()));
+        instance
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            example_instance().to_json(),
+            "{\"format\":\"microsat-instance\",\"version\":1,\"variables\":3,\"clauses\":[\
+             {\"literals\":[1,-2]},\
+             {\"literals\":[3],\"weight\":5,\"label\":\"prefer \\\"3\\\"\",\"group\":\"preferences\"}],\
+             \"metadata\":{\"generator\":\"example\"}}"
+        );
+    }
+
+    #[test]
+    fn test_to_cbor_integers() {
+        let mut output = Vec::new();
+        write_cbor(
+            &Value::Array(vec![Value::Integer(-2), Value::Integer(500)]),
+            &mut output,
+        );
+        assert_eq!(output, vec![0x82, 0x21, 0x19, 0x01, 0xf4]);
+    }
+
+    #[test]
+    fn test_from_weighted_expression() {
+        let instance = example_instance();
+        let weighted = instance.to_weighted_expression();
+
+        assert_eq!(weighted.get_hard_clauses().len(), 1);
+        assert_eq!(weighted.total_soft_weight(), 5);
+        assert_eq!(
+            Instance::from_weighted_expression(&weighted).clauses[1].literals,
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let instance = example_instance();
+        assert_eq!(Instance::from_json(&instance.to_json()).unwrap(), instance);
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let instance = example_instance();
+        assert_eq!(Instance::from_cbor(&instance.to_cbor()).unwrap(), instance);
+    }
+
+    #[test]
+    fn test_largest_weight_round_trip() {
+        let mut instance = Instance::new();
+        instance.add_clause(vec![1], Some(Weight::MAX));
+        assert_eq!(Instance::from_json(&instance.to_json()).unwrap(), instance);
+        assert_eq!(Instance::from_cbor(&instance.to_cbor()).unwrap(), instance);
+
+        let document = r#"{"format": "microsat-instance", "version": 1, "variables": 1,
+            "clauses": [{"literals": [1], "weight": 18446744073709551616}]}"#;
+        assert!(matches!(
+            Instance::from_json(document),
+            Err(InterchangeError::Schema { path, .. }) if path == "$.clauses[0].weight"
+        ));
+    }
+
+    #[test]
+    fn test_from_json_reports_schema_path() {
+        let document = r#"{"format": "microsat-instance", "version": 1, "variables": 2,
+            "clauses": [{"literals": [1, 2]}, {"literals": [1, 3]}]}"#;
+        let error = Instance::from_json(document).unwrap_err();
+
+        assert_eq!(
+            error,
+            InterchangeError::Schema {
+                path: "$.clauses[1].literals[1]".to_string(),
+                message: "literal 3 does not reference a variable between 1 and 2".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_json_rejects_smallest_integer() {
+        let document = r#"{"format": "microsat-instance", "version": 1, "variables": 2,
+            "clauses": [{"literals": [-9223372036854775808]}]}"#;
+        assert!(matches!(
+            Instance::from_json(document),
+            Err(InterchangeError::Schema { path, .. }) if path == "$.clauses[0].literals[0]"
+        ));
+
+        let mut instance = Instance::new();
+        instance.add_clause(vec![-1], None);
+        let mut bytes = instance.to_cbor();
+        // The literal -1 becomes the negative integer of argument i64::MAX, which is i64::MIN
+        let position = bytes.iter().position(|byte| *byte == 0x20).unwrap();
+        bytes.splice(
+            position..=position,
+            [0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+        );
+        assert!(matches!(
+            Instance::from_cbor(&bytes),
+            Err(InterchangeError::Schema { path, .. }) if path == "$.clauses[0].literals[0]"
+        ));
+    }
+
+    #[test]
+    fn test_from_json_rejects_unknown_keys_and_types() {
+        let unknown = r#"{"format": "microsat-instance", "version": 1, "variables": 1, "clauses": [], "extra": 1}"#;
+        assert!(matches!(
+            Instance::from_json(unknown),
+            Err(InterchangeError::Schema { path, .. }) if path == "$"
+        ));
+
+        let wrong_type = r#"{"format": "microsat-instance", "version": 1, "variables": 1,
+            "clauses": [{"literals": [1], "weight": true}]}"#;
+        assert!(matches!(
+            Instance::from_json(wrong_type),
+            Err(InterchangeError::Schema { path, .. }) if path == "$.clauses[0].weight"
+        ));
+    }
+
+    #[test]
+    fn test_from_json_reports_syntax_offset() {
+        let error = Instance::from_json("{\"format\": \"microsat-instance\",}").unwrap_err();
+        assert!(matches!(error, InterchangeError::Syntax { offset: 31, .. }));
+    }
+
+    #[test]
+    fn test_from_cbor_truncated() {
+        let bytes = example_instance().to_cbor();
+        assert!(matches!(
+            Instance::from_cbor(&bytes[..bytes.len() - 3]),
+            Err(InterchangeError::Syntax { .. })
+        ));
+    }
+}
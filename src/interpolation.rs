@@ -0,0 +1,373 @@
+//! Craig interpolants between the frames of an unsatisfiable bounded query, for the
+//! interpolation-based and IC3-style model checking loops built on incremental unrolling.
+//!
+//! The frames `F_0, ..., F_n` (usually the initial states, the unrolled transitions and the
+//! negated property, as produced by [`unroll`](crate::incremental::unroll)) are solved together
+//! by the [`CdclSolver`], keeping every learned clause. Each learned clause follows from the
+//! clauses before it by unit propagation, so it is derived again as a chain of resolutions
+//! following the reasons of a [`Propagator`], and so is the empty clause at the end. Together the
+//! chains make a resolution refutation, and McMillan's interpolation system labels each of its
+//! clauses with a partial interpolant for every cut between two frames: the clauses of the frames
+//! up to the cut are labeled with their literals over the variables shared with the later frames,
+//! the other clauses with `true`, and a resolution on a variable only occurring up to the cut
+//! takes the disjunction of the labels, any other the conjunction.
+//!
+//! The labels are built as a circuit with shared subterms, which is expanded into a [`Formula`]
+//! for every cut; refutations with long chains can make the formulas large.
+
+use std::collections::BinaryHeap;
+
+use crate::cdcl::{CdclSolver, DeletionPolicy};
+use crate::cnf::{to_variable, Clause, Literal, Variable, CNF};
+use crate::dpll::SearchResult;
+use crate::expression::Expression;
+use crate::formula::Formula;
+use crate::hash::{FnvHashMap, FnvHashSet};
+use crate::propagation::{Conflict, Propagator};
+
+/// A clause of the refutation, derived from the clause `start` by resolving it, in order, on
+/// each variable with the clause that implied the variable.
+struct Derivation {
+    start: usize,
+    steps: Vec<(Variable, usize)>,
+    literals: Vec<Literal>,
+}
+
+/// Derives the clause that the conflict (or the implied literal) of the clause `start` proves,
+/// by resolving away every literal falsified by propagation rather than by an assumption.
+fn derive(propagator: &Propagator, start: usize) -> Derivation {
+    let positions: FnvHashMap<Variable, usize> = propagator
+        .get_trail()
+        .iter()
+        .enumerate()
+        .map(|(position, literal)| (to_variable(*literal), position))
+        .collect();
+    let mut seen = FnvHashSet::default();
+    let mut pending = BinaryHeap::new();
+    let mut literals = Vec::new();
+    let mut steps = Vec::new();
+    let (mut id, mut pivot) = (start, None);
+    loop {
+        for literal in propagator.get_clause(id) {
+            let variable = to_variable(*literal);
+            if Some(variable) == pivot || !seen.insert(variable) {
+                continue;
+            }
+            if propagator.value(*literal) == Some(false) && propagator.reason(variable).is_some() {
+                pending.push(positions[&variable]);
+            } else {
+                literals.push(*literal);
+            }
+        }
+
+        // The latest assignment goes first, so the clauses it pulls in only add earlier ones
+        let Some(position) = pending.pop() else {
+            break;
+        };
+        let variable = to_variable(propagator.get_trail()[position]);
+        id = propagator.reason(variable).unwrap();
+        pivot = Some(variable);
+        steps.push((variable, id));
+    }
+
+    literals.sort_unstable();
+    Derivation {
+        start,
+        steps,
+        literals,
+    }
+}
+
+/// A node of the circuit of partial interpolants.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Node {
+    Constant(bool),
+    Literal(Literal),
+    And(usize, usize),
+    Or(usize, usize),
+}
+
+/// The partial interpolants, with equal subterms shared and constants folded away.
+#[derive(Default)]
+struct Circuit {
+    nodes: Vec<Node>,
+    ids: FnvHashMap<Node, usize>,
+}
+
+impl Circuit {
+    fn add(&mut self, node: Node) -> usize {
+        if let Some(id) = self.ids.get(&node) {
+            return *id;
+        }
+        self.nodes.push(node);
+        self.ids.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Combines the nodes with a conjunction, or a disjunction for `disjunction`.
+    fn combine(&mut self, first: usize, second: usize, disjunction: bool) -> usize {
+        match (self.nodes[first], self.nodes[second]) {
+            _ if first == second => first,
+            // The absorbing constant wins, the neutral one disappears
+            (Node::Constant(value), _) => {
+                if value == disjunction {
+                    first
+                } else {
+                    second
+                }
+            }
+            (_, Node::Constant(value)) => {
+                if value == disjunction {
+                    second
+                } else {
+                    first
+                }
+            }
+            _ if disjunction => self.add(Node::Or(first.min(second), first.max(second))),
+            _ => self.add(Node::And(first.min(second), first.max(second))),
+        }
+    }
+
+    fn to_formula(&self, id: usize) -> Formula {
+        match self.nodes[id] {
+            Node::Constant(value) => Formula::Constant(value),
+            Node::Literal(literal) if literal > 0 => Formula::Variable(literal as Variable),
+            Node::Literal(literal) => {
+                Formula::Not(Box::new(Formula::Variable(to_variable(literal))))
+            }
+            Node::And(_, _) => Formula::And(self.flatten(id, false)),
+            Node::Or(_, _) => Formula::Or(self.flatten(id, true)),
+        }
+    }
+
+    /// Returns the operands of the nested conjunctions (or disjunctions) starting at the node.
+    fn flatten(&self, id: usize, disjunction: bool) -> Vec<Formula> {
+        match self.nodes[id] {
+            Node::And(first, second) if !disjunction => {
+                let mut operands = self.flatten(first, disjunction);
+                operands.extend(self.flatten(second, disjunction));
+                operands
+            }
+            Node::Or(first, second) if disjunction => {
+                let mut operands = self.flatten(first, disjunction);
+                operands.extend(self.flatten(second, disjunction));
+                operands
+            }
+            _ => vec![self.to_formula(id)],
+        }
+    }
+}
+
+/// Computes an interpolant for every cut between two consecutive frames, if the frames together
+/// are unsatisfiable.
+///
+/// The interpolant `I_k` at index `k` is for the cut after frame `k`: the frames up to `k` imply
+/// it, it contradicts the frames after `k`, and it only uses variables occurring on both sides.
+/// The interpolants come from a single refutation, so they also form an interpolation sequence:
+/// `I_k` and frame `k + 1` together imply `I_{k + 1}`. Returns `None` if the frames are
+/// satisfiable.
+pub fn compute_interpolants(frames: &[Vec<Clause>]) -> Option<Vec<Formula>> {
+    let mut expression = Expression::new();
+    let mut propagator = Propagator::new();
+    let mut frame_of = Vec::new();
+    // The last frame each variable occurs in
+    let mut last_frames: FnvHashMap<Variable, usize> = FnvHashMap::default();
+    for (index, frame) in frames.iter().enumerate() {
+        for clause in frame {
+            expression.add_clause(clause.clone());
+            propagator.add_clause(clause.literals());
+            frame_of.push(index);
+            for literal in clause.literals() {
+                last_frames.insert(to_variable(*literal), index);
+            }
+        }
+    }
+
+    let mut solver = CdclSolver::new(&expression);
+    solver.set_deletion(DeletionPolicy {
+        interval: None,
+        keep_ratio: 1.0,
+    });
+    match solver.solve() {
+        SearchResult::Unsatisfiable => {}
+        _ => return None,
+    }
+
+    // Derive the learned clauses in order, until propagation alone refutes the frames
+    let mut derivations: Vec<Option<Derivation>> = frame_of.iter().map(|_| None).collect();
+    for learned in solver.get_learned_clauses() {
+        if propagator.propagate_units().is_some() {
+            break;
+        }
+        let conflict = learned
+            .iter()
+            .find_map(|literal| propagator.assume(-literal))
+            .expect("A learned clause follows from the clauses before it by propagation");
+        let start = match conflict {
+            Conflict::Clause(id) => id,
+            Conflict::Assumption(literal) => propagator.reason(to_variable(literal)).unwrap(),
+        };
+        let derivation = derive(&propagator, start);
+        // The derived clause can be shorter than the learned one, and replaces it
+        propagator.add_clause(&derivation.literals);
+        derivations.push(Some(derivation));
+    }
+    let refutation = match propagator.propagate_units() {
+        Some(Conflict::Clause(id)) => derive(&propagator, id),
+        _ => panic!("The learned clauses refute the frames by propagation"),
+    };
+    debug_assert!(refutation.literals.is_empty());
+
+    // Only the clauses the refutation depends on are labeled
+    let mut needed = vec![false; derivations.len()];
+    let mut stack: Vec<usize> = std::iter::once(refutation.start)
+        .chain(refutation.steps.iter().map(|(_, id)| *id))
+        .collect();
+    while let Some(id) = stack.pop() {
+        if std::mem::replace(&mut needed[id], true) {
+            continue;
+        }
+        if let Some(derivation) = &derivations[id] {
+            stack.push(derivation.start);
+            stack.extend(derivation.steps.iter().map(|(_, id)| *id));
+        }
+    }
+
+    let mut circuit = Circuit::default();
+    let mut interpolants = Vec::new();
+    for cut in 0..frames.len().saturating_sub(1) {
+        // The variables of the clauses up to the cut are shared if they occur after it too
+        let is_local = |variable: Variable| last_frames[&variable] <= cut;
+        let mut labels = vec![0; derivations.len()];
+        let label = |circuit: &mut Circuit, labels: &[usize], derivation: &Derivation| {
+            let mut node = labels[derivation.start];
+            for (pivot, id) in &derivation.steps {
+                node = circuit.combine(node, labels[*id], is_local(*pivot));
+            }
+            node
+        };
+
+        for id in 0..derivations.len() {
+            if !needed[id] {
+                continue;
+            }
+            labels[id] = match &derivations[id] {
+                Some(derivation) => label(&mut circuit, &labels, derivation),
+                None if frame_of[id] <= cut => {
+                    let mut node = circuit.add(Node::Constant(false));
+                    for literal in propagator.get_clause(id) {
+                        if !is_local(to_variable(*literal)) {
+                            let literal = circuit.add(Node::Literal(*literal));
+                            node = circuit.combine(node, literal, true);
+                        }
+                    }
+                    node
+                }
+                None => circuit.add(Node::Constant(true)),
+            };
+        }
+        let root = label(&mut circuit, &labels, &refutation);
+        interpolants.push(circuit.to_formula(root));
+    }
+    Some(interpolants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::random_k_sat;
+    use crate::cdcl::solve_cdcl;
+
+    /// Returns true if the clauses of the frames and the formulas together are unsatisfiable.
+    fn contradict(frames: &[Vec<Clause>], formulas: &[&Formula]) -> bool {
+        let mut expression = Expression::new();
+        for clause in frames.iter().flatten() {
+            expression.add_clause(clause.clone());
+        }
+        let mut next_variable = 1000;
+        for formula in formulas {
+            formula.assert_into(&mut expression, &mut next_variable);
+        }
+        solve_cdcl(&expression).is_none()
+    }
+
+    fn collect_variables(formula: &Formula, variables: &mut Vec<Variable>) {
+        match formula {
+            Formula::Variable(variable) => variables.push(*variable),
+            Formula::Not(inner) => collect_variables(inner, variables),
+            Formula::And(children) | Formula::Or(children) => {
+                for child in children {
+                    collect_variables(child, variables);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_interpolants(frames: &[Vec<Clause>], interpolants: &[Formula]) {
+        assert_eq!(interpolants.len(), frames.len() - 1);
+        for (cut, interpolant) in interpolants.iter().enumerate() {
+            let negation = Formula::Not(Box::new(interpolant.clone()));
+            assert!(contradict(&frames[..=cut], &[&negation]));
+            assert!(contradict(&frames[cut + 1..], &[interpolant]));
+            if cut > 0 {
+                let previous = &interpolants[cut - 1];
+                assert!(contradict(&frames[cut..=cut], &[previous, &negation]));
+            }
+
+            let mut variables = Vec::new();
+            collect_variables(interpolant, &mut variables);
+            let occurs = |frames: &[Vec<Clause>], variable: Variable| {
+                frames.iter().flatten().any(|clause| {
+                    clause
+                        .literals()
+                        .iter()
+                        .any(|l| to_variable(*l) == variable)
+                })
+            };
+            for variable in variables {
+                assert!(occurs(&frames[..=cut], variable) && occurs(&frames[cut + 1..], variable));
+            }
+        }
+    }
+
+    #[test]
+    fn test_interpolants_of_a_chain() {
+        // 1 holds initially and every frame passes it on, until the last one forbids 4
+        let frames = vec![
+            vec![Clause::from_literals(&[1])],
+            vec![Clause::from_literals(&[-1, 2])],
+            vec![Clause::from_literals(&[-2, 3])],
+            vec![
+                Clause::from_literals(&[-3, 4]),
+                Clause::from_literals(&[-4]),
+            ],
+        ];
+        let interpolants = compute_interpolants(&frames).unwrap();
+        assert_eq!(
+            interpolants,
+            vec![
+                Formula::Variable(1),
+                Formula::Variable(2),
+                Formula::Variable(3)
+            ]
+        );
+        check_interpolants(&frames, &interpolants);
+
+        assert!(compute_interpolants(&frames[..3]).is_none());
+    }
+
+    #[test]
+    fn test_interpolants_of_random_frames() {
+        let mut num_checked = 0;
+        for seed in 0..20 {
+            let clauses = random_k_sat(25, 130, 3, seed).get_clauses();
+            let frames: Vec<Vec<Clause>> = clauses.chunks(45).map(|chunk| chunk.to_vec()).collect();
+            if let Some(interpolants) = compute_interpolants(&frames) {
+                check_interpolants(&frames, &interpolants);
+                num_checked += 1;
+            }
+        }
+        assert!(num_checked > 5);
+    }
+}
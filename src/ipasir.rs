@@ -0,0 +1,185 @@
+//! The IPASIR C interface, the standard interface of incremental SAT solvers, over [`Solver`].
+//!
+//! With the `ipasir` feature, the crate exports the functions of `ipasir.h`, so C and C++ tools
+//! and benchmark harnesses can link against microsat like against any other IPASIR solver, for
+//! example after building a static library with
+//! `cargo rustc --release --features ipasir --crate-type staticlib`.
+//!
+//! Clauses are added one literal at a time and closed by `0`, and every solve runs under the
+//! assumptions made since the previous one, as [`Solver::solve_with_assumptions`]. The literals
+//! are those of [`Literal`], so a literal past its range aborts, as does a null solver.
+//! Termination and learned clause callbacks are not supported, so `ipasir_set_terminate` and
+//! `ipasir_set_learn` are not exported.
+
+use std::ffi::{c_char, c_int, c_void};
+
+use crate::cnf::{to_variable, Literal};
+use crate::dpll::SearchResult;
+use crate::solver::Solver;
+
+/// The state behind an IPASIR solver pointer.
+struct IpasirSolver {
+    solver: Solver,
+    /// The literals of the clause being added, until its `0`.
+    clause: Vec<Literal>,
+    /// The assumptions of the next solve.
+    assumptions: Vec<Literal>,
+}
+
+fn to_literal(literal: c_int) -> Literal {
+    Literal::try_from(literal)
+        .ok()
+        .filter(|literal| *literal != Literal::MIN)
+        .expect("The literal is out of range")
+}
+
+/// # Safety
+///
+/// The solver must come from [`ipasir_init`] and not have been released.
+unsafe fn get_solver<'a>(solver: *mut c_void) -> &'a mut IpasirSolver {
+    assert!(!solver.is_null(), "The solver is null");
+    &mut *(solver as *mut IpasirSolver)
+}
+
+/// Returns the name and version of the solver.
+#[no_mangle]
+pub extern "C" fn ipasir_signature() -> *const c_char {
+    concat!("microsat ", env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Creates a solver without clauses, to be released with [`ipasir_release`].
+#[no_mangle]
+pub extern "C" fn ipasir_init() -> *mut c_void {
+    let solver = IpasirSolver {
+        solver: Solver::new(),
+        clause: Vec::new(),
+        assumptions: Vec::new(),
+    };
+    Box::into_raw(Box::new(solver)) as *mut c_void
+}
+
+/// Releases the solver.
+///
+/// # Safety
+///
+/// The solver must come from [`ipasir_init`], and is not used again.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_release(solver: *mut c_void) {
+    if !solver.is_null() {
+        drop(Box::from_raw(solver as *mut IpasirSolver));
+    }
+}
+
+/// Adds the literal to the clause being added, or adds the clause on `0`.
+///
+/// # Safety
+///
+/// The solver must come from [`ipasir_init`] and not have been released.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_add(solver: *mut c_void, lit_or_zero: c_int) {
+    let solver = get_solver(solver);
+    if lit_or_zero == 0 {
+        solver.solver.add_clause(&solver.clause);
+        solver.clause.clear();
+    } else {
+        solver.clause.push(to_literal(lit_or_zero));
+    }
+}
+
+/// Assumes the literal for the next solve only.
+///
+/// # Safety
+///
+/// The solver must come from [`ipasir_init`] and not have been released.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_assume(solver: *mut c_void, lit: c_int) {
+    assert!(lit != 0, "0 is not a literal");
+    get_solver(solver).assumptions.push(to_literal(lit));
+}
+
+/// Solves the clauses under the assumptions made since the last solve, returning 10 if they are
+/// satisfiable, 20 if they are not, and 0 if the solve was interrupted.
+///
+/// # Safety
+///
+/// The solver must come from [`ipasir_init`] and not have been released.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_solve(solver: *mut c_void) -> c_int {
+    let solver = get_solver(solver);
+    let assumptions = std::mem::take(&mut solver.assumptions);
+    match solver.solver.solve_with_assumptions(&assumptions) {
+        SearchResult::Satisfiable(_) => 10,
+        SearchResult::Unsatisfiable => 20,
+        SearchResult::BudgetExhausted => 0,
+    }
+}
+
+/// Returns the literal if it is true in the model of the last solve, which must have been
+/// satisfiable, and its negation if it is false.
+///
+/// # Safety
+///
+/// The solver must come from [`ipasir_init`] and not have been released.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_val(solver: *mut c_void, lit: c_int) -> c_int {
+    let literal = to_literal(lit);
+    let value = get_solver(solver)
+        .solver
+        .value(to_variable(literal))
+        .expect("The last solve was not satisfiable");
+    if value == (literal > 0) {
+        lit
+    } else {
+        -lit
+    }
+}
+
+/// Returns 1 if the assumed literal is among the assumptions that the last solve, which must
+/// have been unsatisfiable, depends on, and 0 otherwise.
+///
+/// # Safety
+///
+/// The solver must come from [`ipasir_init`] and not have been released.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_failed(solver: *mut c_void, lit: c_int) -> c_int {
+    let literal = to_literal(lit);
+    get_solver(solver)
+        .solver
+        .get_failed_assumptions()
+        .contains(&literal) as c_int
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_ipasir() {
+        let signature = unsafe { CStr::from_ptr(ipasir_signature()) };
+        assert!(signature.to_str().unwrap().starts_with("microsat "));
+
+        let solver = ipasir_init();
+        unsafe {
+            for literal in [1, 2, 0, -1, 3, 0, -2, 3, 0] {
+                ipasir_add(solver, literal);
+            }
+            ipasir_assume(solver, -3);
+            assert_eq!(ipasir_solve(solver), 20);
+            assert_eq!(ipasir_failed(solver, -3), 1);
+
+            // The assumptions only hold for one solve
+            assert_eq!(ipasir_solve(solver), 10);
+            assert_eq!(ipasir_val(solver, 3), 3);
+            // -3 is false, so its negation is true
+            assert_eq!(ipasir_val(solver, -3), 3);
+
+            ipasir_add(solver, -3);
+            ipasir_add(solver, 0);
+            ipasir_assume(solver, 4);
+            assert_eq!(ipasir_solve(solver), 20);
+            assert_eq!(ipasir_failed(solver, 4), 0);
+            ipasir_release(solver);
+        }
+    }
+}
@@ -1,6 +1,76 @@
+// Casts between literals and wider integers are needed at the narrower widths of a literal
+#![cfg_attr(
+    feature = "literals-64",
+    allow(clippy::unnecessary_cast, clippy::unnecessary_fallible_conversions)
+)]
+
 pub mod stack;
+pub mod arena;
+pub mod archive;
+pub mod trail;
 pub mod solver;
 pub mod expression;
+pub mod hash;
+pub mod incremental;
+pub mod interpolation;
+pub mod interchange;
 pub mod dpll;
+pub mod cdcl;
+pub mod drat;
 pub mod cnf;
-pub mod dimacs_parser;
\ No newline at end of file
+pub mod control;
+pub mod diff;
+pub mod dont_care;
+pub mod enumerate;
+pub mod counting;
+pub mod dimacs_parser;
+pub mod dimacs_writer;
+pub mod binary_format;
+pub mod qbf;
+pub mod weighted_expression;
+pub mod variable_map;
+pub mod template;
+pub mod encodings;
+pub mod formula;
+pub mod formula_parser;
+pub mod formula_builder;
+pub mod aiger;
+pub mod smtlib;
+pub mod rng;
+pub mod benchmarks;
+pub mod branching;
+pub mod testing;
+pub mod memory;
+pub mod metrics;
+pub mod metamorphic;
+pub mod minimal_model;
+pub mod maxsat;
+pub mod pb;
+pub mod mus;
+pub mod crosscheck;
+pub mod verification;
+pub mod regress;
+pub mod experiments;
+pub mod family;
+pub mod features;
+pub mod profile;
+pub mod paranoid;
+pub mod preference;
+pub mod propagation;
+pub mod explain;
+pub mod simplify;
+pub mod equivalence;
+pub mod symmetry;
+pub mod watcher;
+pub mod clause_usage;
+pub mod clause_database;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "batched")]
+pub mod batched;
+#[cfg(feature = "ipasir")]
+pub mod ipasir;
+#[cfg(feature = "mmap")]
+pub mod dimacs_mmap;
+#[cfg(feature = "packages")]
+pub mod packages;
\ No newline at end of file
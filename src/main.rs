@@ -0,0 +1,417 @@
+use microsat::{
+    cdcl::CdclSolver,
+    cnf::Assignment,
+    crosscheck::{crosscheck, ExternalSolver},
+    diff::diff,
+    dimacs_parser::{parse_dimacs_reader_with_options, ParseOptions},
+    dont_care::find_dont_cares,
+    dpll::SearchResult,
+    drat::{check_drat, Proof, ProofLogger},
+    expression::Expression,
+    paranoid::{solve_paranoid, ParanoidPolicy},
+    regress::{run_corpus, RegressOptions},
+    smtlib::run_smtlib,
+    solver::{solve, solve_with_config, Engine, SolverConfig, SolverStatistics},
+    verification::{verify_result, VerificationPolicy},
+};
+use std::time::Instant;
+
+// Files are memory-mapped with the mmap feature
+#[cfg(feature = "mmap")]
+use microsat::dimacs_mmap::parse_dimacs_mmap_with_options as parse_dimacs_with_options;
+#[cfg(not(feature = "mmap"))]
+use microsat::dimacs_parser::parse_dimacs_with_options;
+
+extern crate microsat;
+
+const USAGE: &str = "Usage:
+    microsat <file.cnf>
+    microsat solve [--strict] [--stats] [--dont-cares] [--break-symmetries] [--strengthen] [--cdcl] [--proof <file.drat>] [--verify] [--paranoid] [--paranoid-interval <operations>] <file.cnf | ->
+    microsat check <file.cnf> <file.drat>
+    microsat diff <a.cnf> <b.cnf>
+    microsat crosscheck [--minisat] <solver> <file.cnf>
+    microsat regress [--json] [--time-factor <factor>] <manifest>
+    microsat smt <file.smt2>";
+
+const SUBCOMMANDS: [&str; 6] = ["solve", "check", "diff", "crosscheck", "regress", "smt"];
+
+/// Command-line front-end for the solver and the instance tooling
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+
+    let code = match args.as_slice() {
+        ["solve", flags @ .., file] => match parse_solve_flags(flags) {
+            Some(options) => run_solve(file, &options),
+            None => {
+                eprintln!("{}", USAGE);
+                2
+            }
+        },
+        ["check", file, proof] => run_check(file, proof),
+        ["diff", a, b] => run_diff(a, b),
+        ["crosscheck", solver, file] => run_crosscheck(&ExternalSolver::new(solver), file),
+        ["crosscheck", "--minisat", solver, file] => {
+            run_crosscheck(&ExternalSolver::minisat(solver), file)
+        }
+        ["regress", options @ .., manifest] => run_regress(options, manifest),
+        ["smt", file] => run_smt(file),
+        [file] if !file.starts_with('-') && !SUBCOMMANDS.contains(file) => {
+            run_solve(file, &SolveOptions::default())
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    };
+
+    std::process::exit(code);
+}
+
+#[derive(Default)]
+struct SolveOptions {
+    strict: bool,
+    /// Print the search statistics and the time spent in each phase
+    stats: bool,
+    /// Print the variables whose values in the model are arbitrary
+    dont_cares: bool,
+    /// Add symmetry-breaking constraints before searching
+    break_symmetries: bool,
+    /// Remove redundant literals from the clauses before searching
+    strengthen: bool,
+    /// Search with the CDCL solver instead of DPLL
+    cdcl: bool,
+    /// Write a DRAT proof to this file if the instance is unsatisfiable, which implies CDCL
+    proof: Option<String>,
+    /// Check the answer in every verification tier, and its proof if one is written
+    verify: bool,
+    /// Search with DPLL, re-deriving its cached state from scratch at intervals
+    paranoid: Option<ParanoidPolicy>,
+}
+
+fn parse_solve_flags(flags: &[&str]) -> Option<SolveOptions> {
+    let mut options = SolveOptions::default();
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match *flag {
+            "--strict" => options.strict = true,
+            "--stats" => options.stats = true,
+            "--dont-cares" => options.dont_cares = true,
+            "--break-symmetries" => options.break_symmetries = true,
+            "--strengthen" => options.strengthen = true,
+            "--cdcl" => options.cdcl = true,
+            "--proof" => options.proof = Some(flags.next()?.to_string()),
+            "--verify" => options.verify = true,
+            "--paranoid" => options.paranoid = Some(options.paranoid.unwrap_or_default()),
+            "--paranoid-interval" => {
+                let interval = flags.next()?.parse().ok()?;
+                if interval == 0 {
+                    return None;
+                }
+                options.paranoid = Some(ParanoidPolicy { interval });
+            }
+            _ => return None,
+        }
+    }
+    // The paranoid mode checks the state of the DPLL search
+    if options.paranoid.is_some() && (options.cdcl || options.proof.is_some()) {
+        return None;
+    }
+    // The proof refers to the clauses as given
+    if options.proof.is_some() && (options.break_symmetries || options.strengthen) {
+        return None;
+    }
+    Some(options)
+}
+
+/// Solves the expression with the CDCL solver, writing the proof of unsatisfiability to the file
+fn solve_with_proof(expression: &Expression, file: &str, stats: bool) -> Option<Assignment> {
+    let proof = match ProofLogger::create(file) {
+        Ok(proof) => proof,
+        Err(error) => {
+            eprintln!("{}: {}", file, error);
+            std::process::exit(2);
+        }
+    };
+    let start = Instant::now();
+    let mut solver = CdclSolver::new(expression);
+    solver.set_proof(proof.clone());
+    let result = solver.solve();
+    let time = start.elapsed();
+    if let Err(error) = proof.finish() {
+        eprintln!("{}: {}", file, error);
+        std::process::exit(2);
+    }
+    if stats {
+        let cdcl = solver.get_statistics();
+        let statistics = SolverStatistics {
+            num_decisions: cdcl.num_decisions,
+            num_propagations: cdcl.num_propagations,
+            num_conflicts: cdcl.num_conflicts,
+            memory: solver.memory_report(),
+            time,
+            ..SolverStatistics::default()
+        };
+        println!("{}", statistics);
+    }
+    match result {
+        SearchResult::Satisfiable(assignment) => Some(assignment),
+        _ => None,
+    }
+}
+
+/// Solves the file and prints the result in the SAT competition output format
+fn run_solve(file: &str, options: &SolveOptions) -> i32 {
+    let start = Instant::now();
+    let parse_options = ParseOptions {
+        strict: options.strict,
+        ..ParseOptions::default()
+    };
+    // A file of '-' is read from stdin
+    let parsed = if file == "-" {
+        parse_dimacs_reader_with_options(std::io::stdin().lock(), &parse_options)
+    } else {
+        parse_dimacs_with_options(file, &parse_options)
+    };
+    let (expression, warnings) = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+    let parsing = start.elapsed();
+    for warning in warnings {
+        println!(
+            "c warning: line {}, column {}: {}",
+            warning.line, warning.column, warning.message
+        );
+    }
+
+    let original = options.dont_cares.then(|| expression.clone());
+    let checked = options.verify.then(|| expression.clone());
+    let solution = if let Some(proof) = &options.proof {
+        solve_with_proof(&expression, proof, options.stats)
+    } else if let Some(policy) = &options.paranoid {
+        let (solution, divergences) = solve_paranoid(&expression, policy);
+        for divergence in &divergences {
+            println!("{}", divergence);
+        }
+        if !divergences.is_empty() {
+            println!("c the search state diverged from the state derived from scratch");
+            return 1;
+        }
+        solution
+    } else if options.stats || options.break_symmetries || options.strengthen || options.cdcl {
+        // The statistics come from a single deterministic search rather than the racing threads
+        let config = SolverConfig {
+            profile: options.stats,
+            break_symmetries: options.break_symmetries,
+            strengthen: options.strengthen,
+            engine: if options.cdcl {
+                Engine::Cdcl
+            } else {
+                Engine::Dpll
+            },
+            ..SolverConfig::default()
+        };
+        let (result, mut statistics) = solve_with_config(&expression, &config);
+        statistics.phases.parsing = parsing;
+        if options.stats {
+            println!("{}", statistics);
+        }
+        match result {
+            SearchResult::Satisfiable(assignment) => Some(assignment),
+            _ => None,
+        }
+    } else {
+        solve(expression, true, true)
+    };
+
+    if let Some(expression) = checked {
+        let result = match &solution {
+            Some(assignment) => SearchResult::Satisfiable(assignment.clone()),
+            None => SearchResult::Unsatisfiable,
+        };
+        // The proof was written in full before the solve returned
+        let proof = options.proof.as_ref().and_then(|file| {
+            let contents = std::fs::read_to_string(file).ok()?;
+            Proof::parse(&contents).ok()
+        });
+        let report = verify_result(
+            &expression,
+            &result,
+            proof.as_ref(),
+            &VerificationPolicy::default(),
+        );
+        println!("{}", report);
+        if !report.is_ok() {
+            println!("c the answer failed verification");
+            return 1;
+        }
+    }
+
+    match solution {
+        Some(assignment) => {
+            if let Some(partial) = original.and_then(|e| find_dont_cares(&e, &assignment)) {
+                let variables: Vec<String> =
+                    partial.dont_cares.iter().map(|v| v.to_string()).collect();
+                println!("c dont-cares: {}", variables.join(" "));
+            }
+            println!("s SATISFIABLE");
+            let mut variables: Vec<_> = assignment.keys().copied().collect();
+            variables.sort_unstable();
+
+            let mut line = String::from("v");
+            for variable in variables {
+                let literal = if assignment[&variable] {
+                    variable as i64
+                } else {
+                    -(variable as i64)
+                };
+                line.push_str(&format!(" {}", literal));
+            }
+            println!("{} 0", line);
+            10
+        }
+        None => {
+            println!("s UNSATISFIABLE");
+            20
+        }
+    }
+}
+
+/// Parses the DIMACS file, printing the error if it is unreadable
+fn parse_cnf(file: &str) -> Option<Expression> {
+    match Expression::from_cnf_file(file) {
+        Ok(expression) => Some(expression),
+        Err(error) => {
+            eprintln!("{}", error);
+            None
+        }
+    }
+}
+
+/// Checks the DRAT proof of unsatisfiability of the file, returning 1 if it is invalid
+fn run_check(file: &str, proof: &str) -> i32 {
+    let contents = match std::fs::read_to_string(proof) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!("{}: {}", proof, error);
+            return 2;
+        }
+    };
+    let proof = match Proof::parse(&contents) {
+        Ok(proof) => proof,
+        Err(error) => {
+            eprintln!("{}: {}", proof, error);
+            return 2;
+        }
+    };
+    let Some(expression) = parse_cnf(file) else {
+        return 2;
+    };
+    match check_drat(&expression, &proof) {
+        Ok(()) => {
+            println!("s VERIFIED");
+            0
+        }
+        Err(error) => {
+            println!("c {}", error);
+            println!("s NOT VERIFIED");
+            1
+        }
+    }
+}
+
+/// Prints the clauses that differ between the two files, returning 1 if there are any
+fn run_diff(a: &str, b: &str) -> i32 {
+    let (Some(a), Some(b)) = (parse_cnf(a), parse_cnf(b)) else {
+        return 2;
+    };
+    let result = diff(&a, &b);
+    print!("{}", result);
+    if result.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Runs an SMT-LIB script, printing its responses
+fn run_smt(file: &str) -> i32 {
+    let script = match std::fs::read_to_string(file) {
+        Ok(script) => script,
+        Err(error) => {
+            eprintln!("{}: {}", file, error);
+            return 2;
+        }
+    };
+    match run_smtlib(&script) {
+        Ok(responses) => {
+            print!("{}", responses);
+            0
+        }
+        Err(error) => {
+            eprintln!("{}: {}", file, error);
+            2
+        }
+    }
+}
+
+/// Compares the answer of an external solver with ours, returning 1 if they disagree
+fn run_crosscheck(solver: &ExternalSolver, file: &str) -> i32 {
+    match crosscheck(file, solver) {
+        Ok(report) => {
+            println!("{}", report);
+            if report.agrees() {
+                0
+            } else {
+                1
+            }
+        }
+        Err(error) => {
+            eprintln!("{}: {}", solver.program, error);
+            2
+        }
+    }
+}
+
+/// Runs the corpus described by the manifest, returning 1 if any instance fails
+fn run_regress(flags: &[&str], manifest: &str) -> i32 {
+    let mut options = RegressOptions::default();
+    let mut json = false;
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match (*flag, flags.clone().next()) {
+            ("--json", _) => json = true,
+            ("--time-factor", Some(factor)) if factor.parse::<f64>().is_ok() => {
+                options.time_factor = factor.parse().unwrap();
+                flags.next();
+            }
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    match run_corpus(manifest, &options) {
+        Ok(report) => {
+            if json {
+                println!("{}", report.to_json());
+            } else {
+                println!("{}", report);
+            }
+            if report.passed() {
+                0
+            } else {
+                1
+            }
+        }
+        Err(error) => {
+            eprintln!("{}: {}", manifest, error);
+            2
+        }
+    }
+}
@@ -0,0 +1,487 @@
+//! Partial weighted MaxSAT: the hard clauses must hold, and the solver looks for an assignment
+//! violating soft clauses of the least total weight. Every soft clause gets a fresh relaxation
+//! variable, added to it, which may be true to give the clause up.
+//!
+//! [`solve_maxsat`] searches linearly from above. Each model found bounds the cost: a generalized
+//! totalizer sums the weights of the true relaxation variables, and its outputs at or above the
+//! cost of the model are made false, so the next model must be strictly cheaper. The last model
+//! found when the bound cannot be met any more is optimal. The bounds only tighten, so they are
+//! added as clauses, and the solver keeps its learned clauses from one call to the next.
+//!
+//! [`solve_maxsat_core_guided`] searches from below, after OLL (Morgado, Dodaro and Marques-Silva)
+//! as RC2 implements it. It assumes every soft clause holds, and each unsatisfiable core of the
+//! assumptions raises the lower bound by the least weight in it. That weight is taken off every
+//! soft clause of the core, and a [`Totalizer`] over them lets one of them go for it: a new soft
+//! constraint, that at most one of them is violated, carries the weight, and is itself relaxed to
+//! at most two when it shows up in a later core, and so on. The first model is optimal.
+//!
+//! The weights are arbitrary positive integers. With widely spread weights, the core-guided search
+//! is stratified: it assumes the heaviest soft constraints first, and moves on to lighter ones
+//! once they are satisfiable. The model of each stratum is an upper bound on the cost, and the
+//! soft constraints heavier than the gap between the bounds are hardened, made hard clauses, since
+//! no optimal model violates them. The linear search hardens the soft clauses as heavy as the cost
+//! of its last model likewise.
+
+use hashbrown::HashMap;
+
+use crate::cnf::{Assignment, Literal, Variable};
+use crate::dpll::SearchResult;
+use crate::encodings::{Totalizer, VariableAllocator};
+use crate::solver::Solver;
+use crate::weighted_expression::{Weight, WeightedExpression};
+
+/// An optimal assignment of a MaxSAT instance, with the weight of the soft clauses it violates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaxSatSolution {
+    pub model: Assignment,
+    pub cost: Weight,
+}
+
+/// The sums of weights a totalizer node can reach, sorted, each with the literal that is true if
+/// the weights below the node add up to at least the sum.
+type Sums = Vec<(Weight, Literal)>;
+
+/// Merges the sums of two totalizer nodes, with the sums from `cap` on collapsed into it.
+fn merge(solver: &mut Solver, left: &Sums, right: &Sums, cap: Weight) -> Sums {
+    let mut reachable: Vec<Weight> = Vec::new();
+    for (a, _) in left.iter().chain(right) {
+        reachable.push((*a).min(cap));
+    }
+    for (a, _) in left {
+        for (b, _) in right {
+            reachable.push((a + b).min(cap));
+        }
+    }
+    reachable.sort_unstable();
+    reachable.dedup();
+    let sums: Sums = reachable
+        .into_iter()
+        .map(|sum| (sum, solver.new_var() as Literal))
+        .collect();
+    let output = |sum: Weight| sums[sums.partition_point(|(s, _)| *s < sum.min(cap))].1;
+
+    for (a, literal) in left.iter().chain(right) {
+        solver.add_clause(&[-literal, output(*a)]);
+    }
+    for (a, left_literal) in left {
+        for (b, right_literal) in right {
+            solver.add_clause(&[-left_literal, -right_literal, output(a + b)]);
+        }
+    }
+    sums
+}
+
+/// Builds a totalizer over the weighted relaxation literals, and returns the sums of its root.
+fn build_totalizer(solver: &mut Solver, relaxations: &[(Literal, Weight)], cap: Weight) -> Sums {
+    let mut nodes: Vec<Sums> = relaxations
+        .iter()
+        .map(|(literal, weight)| vec![((*weight).min(cap), *literal)])
+        .collect();
+    while nodes.len() > 1 {
+        let mut merged = Vec::with_capacity(nodes.len().div_ceil(2));
+        for pair in nodes.chunks(2) {
+            match pair {
+                [left, right] => merged.push(merge(solver, left, right, cap)),
+                [single] => merged.push(single.clone()),
+                _ => unreachable!(),
+            }
+        }
+        nodes = merged;
+    }
+    nodes.pop().unwrap_or_default()
+}
+
+/// Adds the hard clauses and the relaxed soft clauses to a new solver, and returns it with the
+/// relaxation literals and their weights.
+fn relax(expression: &WeightedExpression) -> (Solver, Vec<(Literal, Weight)>) {
+    let max_variable = expression.get_max_variable();
+    let mut solver = Solver::new();
+    for clause in expression.get_hard_clauses() {
+        solver.add_clause(clause.literals());
+    }
+    while solver.get_num_variables() < max_variable {
+        solver.new_var();
+    }
+
+    // Soft clauses without weight cost nothing when violated, so they are left out
+    let mut relaxations: Vec<(Literal, Weight)> = Vec::new();
+    for (clause, weight) in expression.get_soft_clauses() {
+        if *weight == 0 {
+            continue;
+        }
+        let relaxation = solver.new_var() as Literal;
+        let mut literals = clause.literals().clone();
+        literals.push(relaxation);
+        solver.add_clause(&literals);
+        relaxations.push((relaxation, *weight));
+    }
+    (solver, relaxations)
+}
+
+/// Returns a solution with the model restricted to the variables of the expression.
+fn to_solution(expression: &WeightedExpression, model: &Assignment) -> MaxSatSolution {
+    let model: Assignment = (1..=expression.get_max_variable())
+        .map(|variable: Variable| (variable, model.get(&variable).copied().unwrap_or(false)))
+        .collect();
+    let cost = expression.cost(&model);
+    MaxSatSolution { model, cost }
+}
+
+/// Returns an assignment satisfying the hard clauses of the expression while violating soft
+/// clauses of the least total weight, or `None` if the hard clauses are unsatisfiable. The model
+/// covers the variables of the expression. See the module documentation for the search.
+pub fn solve_maxsat(expression: &WeightedExpression) -> Option<MaxSatSolution> {
+    let (mut solver, relaxations) = relax(expression);
+    let mut best = match solver.solve_with_assumptions(&[]) {
+        SearchResult::Satisfiable(model) => to_solution(expression, &model),
+        _ => return None,
+    };
+
+    if best.cost == 0 {
+        return Some(best);
+    }
+    // No sum needs telling apart above the cost of the first model
+    let sums = build_totalizer(&mut solver, &relaxations, best.cost);
+    while best.cost > 0 {
+        for (sum, literal) in &sums {
+            if *sum >= best.cost {
+                solver.add_clause(&[-literal]);
+            }
+        }
+        // A soft clause as heavy as the cost cannot be violated by a cheaper model
+        for (relaxation, weight) in &relaxations {
+            if *weight >= best.cost {
+                solver.add_clause(&[-relaxation]);
+            }
+        }
+        // No budget is set, so only unsatisfiability ends the search
+        match solver.solve_with_assumptions(&[]) {
+            SearchResult::Satisfiable(model) => {
+                let solution = to_solution(expression, &model);
+                assert!(
+                    solution.cost < best.cost,
+                    "The bound on the cost was not kept"
+                );
+                best = solution;
+            }
+            _ => break,
+        }
+    }
+    Some(best)
+}
+
+/// An assumption of the core-guided search: a soft clause that holds, or a bound on the
+/// violated soft clauses of a core, with the weight that is paid if it is given up.
+struct Assumption {
+    literal: Literal,
+    weight: Weight,
+    /// The totalizer and the number of violations this bound allows, for a bound.
+    bound: Option<(usize, usize)>,
+}
+
+/// The refinements of the core-guided search of [`solve_maxsat_core_guided_with_config`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CoreGuidedConfig {
+    /// Whether to assume the heaviest soft constraints first, and the lighter ones only once the
+    /// heavier ones are satisfiable, so the cores found first are made of the costly violations.
+    pub stratification: bool,
+    /// Whether to make the soft constraints that the best model found so far rules out violating
+    /// hard: violating one costs more on top of the lower bound than that model does. Only the
+    /// models of the strata give upper bounds, so this needs stratification to have any effect.
+    pub hardening: bool,
+}
+
+impl Default for CoreGuidedConfig {
+    fn default() -> Self {
+        CoreGuidedConfig {
+            stratification: true,
+            hardening: true,
+        }
+    }
+}
+
+/// Counters describing the work done by the core-guided search.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MaxSatStatistics {
+    pub num_solves: usize,
+    pub num_cores: usize,
+    /// The weights the stratification assumed the soft constraints from, one after the other.
+    pub num_strata: usize,
+    pub num_hardened: usize,
+}
+
+/// Returns the same as [`solve_maxsat`], searching by unsatisfiable cores instead, with the
+/// default configuration. See the module documentation for the search.
+pub fn solve_maxsat_core_guided(expression: &WeightedExpression) -> Option<MaxSatSolution> {
+    solve_maxsat_core_guided_with_config(expression, &CoreGuidedConfig::default()).0
+}
+
+/// Returns the same as [`solve_maxsat_core_guided`], refined as configured, together with the
+/// statistics of the search.
+pub fn solve_maxsat_core_guided_with_config(
+    expression: &WeightedExpression,
+    config: &CoreGuidedConfig,
+) -> (Option<MaxSatSolution>, MaxSatStatistics) {
+    let (mut solver, relaxations) = relax(expression);
+    let mut assumptions: Vec<Assumption> = relaxations
+        .iter()
+        .map(|(relaxation, weight)| Assumption {
+            literal: -relaxation,
+            weight: *weight,
+            bound: None,
+        })
+        .collect();
+    // The totalizers over the violated soft constraints of every core so far
+    let mut totalizers: Vec<Totalizer> = Vec::new();
+    let mut lower_bound: Weight = 0;
+    let mut best: Option<MaxSatSolution> = None;
+    let mut statistics = MaxSatStatistics::default();
+
+    // Without stratification, every soft constraint is assumed at once
+    let next_stratum = |assumptions: &[Assumption], below: Weight| {
+        assumptions
+            .iter()
+            .map(|assumption| assumption.weight)
+            .filter(|weight| *weight > 0 && *weight < below)
+            .max()
+            .map(|weight| if config.stratification { weight } else { 1 })
+    };
+    let Some(mut stratum) = next_stratum(&assumptions, Weight::MAX) else {
+        // No soft constraint has a weight, so any model is optimal
+        statistics.num_solves += 1;
+        let solution = match solver.solve_with_assumptions(&[]) {
+            SearchResult::Satisfiable(model) => Some(to_solution(expression, &model)),
+            _ => None,
+        };
+        return (solution, statistics);
+    };
+    statistics.num_strata += 1;
+
+    loop {
+        let literals: Vec<Literal> = assumptions
+            .iter()
+            .filter(|assumption| assumption.weight >= stratum)
+            .map(|assumption| assumption.literal)
+            .collect();
+        statistics.num_solves += 1;
+        // No budget is set, so the solve either finds a model or a core
+        if let SearchResult::Satisfiable(model) = solver.solve_with_assumptions(&literals) {
+            let solution = to_solution(expression, &model);
+            if best.as_ref().is_none_or(|best| solution.cost < best.cost) {
+                best = Some(solution);
+            }
+            let upper_bound = best.as_ref().unwrap().cost;
+            let Some(lower) = next_stratum(&assumptions, stratum) else {
+                // Every soft constraint was assumed, so the model is optimal
+                assert_eq!(upper_bound, lower_bound, "The cores overestimated the cost");
+                return (best, statistics);
+            };
+            if upper_bound == lower_bound {
+                return (best, statistics);
+            }
+            if config.hardening {
+                for assumption in &mut assumptions {
+                    if assumption.weight > upper_bound - lower_bound {
+                        solver.add_clause(&[assumption.literal]);
+                        assumption.weight = 0;
+                        statistics.num_hardened += 1;
+                    }
+                }
+            }
+            // The hardening may have taken every lighter one, which are then assumed at once
+            stratum = next_stratum(&assumptions, stratum).unwrap_or(lower);
+            statistics.num_strata += 1;
+            continue;
+        }
+        let core = solver.get_failed_assumptions().to_vec();
+        if core.is_empty() {
+            return (None, statistics);
+        }
+        statistics.num_cores += 1;
+
+        let index: HashMap<Literal, usize> = assumptions
+            .iter()
+            .enumerate()
+            .filter(|(_, assumption)| assumption.weight >= stratum)
+            .map(|(position, assumption)| (assumption.literal, position))
+            .collect();
+        let core: Vec<usize> = core.iter().map(|literal| index[literal]).collect();
+        let weight = core
+            .iter()
+            .map(|position| assumptions[*position].weight)
+            .min()
+            .unwrap();
+        lower_bound += weight;
+        for position in &core {
+            assumptions[*position].weight -= weight;
+            // A violated bound gives way to the next one, which pays for the next violation
+            if let Some((index, bound)) = assumptions[*position].bound {
+                if let Some(literal) = totalizers[index].at_most(bound + 1) {
+                    assumptions.push(Assumption {
+                        literal,
+                        weight,
+                        bound: Some((index, bound + 1)),
+                    });
+                }
+            }
+        }
+
+        if core.len() > 1 {
+            let violations: Vec<Literal> = core
+                .iter()
+                .map(|position| -assumptions[*position].literal)
+                .collect();
+            let mut allocator = VariableAllocator::new(solver.get_num_variables());
+            let totalizer =
+                Totalizer::new(&mut solver, &mut allocator, &violations, violations.len());
+            // One violation is paid for by the lower bound, so the first bound allows it
+            assumptions.push(Assumption {
+                literal: totalizer.at_most(1).unwrap(),
+                weight,
+                bound: Some((totalizers.len(), 1)),
+            });
+            totalizers.push(totalizer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::random_k_sat;
+    use crate::cnf::Clause;
+
+    fn brute_force(expression: &WeightedExpression) -> Option<Weight> {
+        let max_variable = expression.get_max_variable();
+        (0..1u32 << max_variable)
+            .map(|mask| -> Assignment {
+                (1..=max_variable)
+                    .map(|variable| (variable, mask >> (variable - 1) & 1 == 1))
+                    .collect()
+            })
+            .filter(|model| expression.satisfies_hard_clauses(model))
+            .map(|model| expression.cost(&model))
+            .min()
+    }
+
+    fn check_solver(solve: fn(&WeightedExpression) -> Option<MaxSatSolution>) {
+        // 1 and 2 cannot both hold, and giving up 2 is cheaper
+        let mut expression = WeightedExpression::new();
+        expression.add_hard_clause(Clause::from_literals(&[-1, -2]));
+        expression.add_soft_clause(Clause::from_literals(&[1]), 3);
+        expression.add_soft_clause(Clause::from_literals(&[2]), 2);
+        expression.add_soft_clause(Clause::from_literals(&[3]), 0);
+        let solution = solve(&expression).unwrap();
+        assert_eq!(solution.cost, 2);
+        assert!(solution.model[&1]);
+        assert!(!solution.model[&2]);
+
+        expression.add_hard_clause(Clause::from_literals(&[2]));
+        expression.add_hard_clause(Clause::from_literals(&[-2]));
+        assert_eq!(solve(&expression), None);
+
+        for seed in 0..10 {
+            let mut expression = WeightedExpression::new();
+            for (index, clause) in random_k_sat(8, 30, 3, seed)
+                .get_clauses()
+                .iter()
+                .enumerate()
+            {
+                if index < 6 {
+                    expression.add_hard_clause(clause.clone());
+                } else {
+                    expression.add_soft_clause(clause.clone(), 1 + index as Weight % 4);
+                }
+            }
+            let solution = solve(&expression);
+            assert_eq!(
+                solution.as_ref().map(|solution| solution.cost),
+                brute_force(&expression)
+            );
+            if let Some(solution) = solution {
+                assert!(expression.satisfies_hard_clauses(&solution.model));
+                assert_eq!(expression.cost(&solution.model), solution.cost);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_maxsat() {
+        check_solver(solve_maxsat);
+    }
+
+    #[test]
+    fn test_solve_maxsat_core_guided() {
+        check_solver(solve_maxsat_core_guided);
+
+        // At most one of eight unit soft clauses can hold, so the bounds on the cores have to give
+        // way again and again
+        let mut expression = WeightedExpression::new();
+        for first in 1..=8 {
+            expression.add_soft_clause(Clause::from_literals(&[first]), 1);
+            for second in first + 1..=8 {
+                expression.add_hard_clause(Clause::from_literals(&[-first, -second]));
+            }
+        }
+        assert_eq!(solve_maxsat_core_guided(&expression).unwrap().cost, 7);
+    }
+
+    #[test]
+    fn test_solve_maxsat_weighted() {
+        let configs = [(false, false), (false, true), (true, false), (true, true)].map(
+            |(stratification, hardening)| CoreGuidedConfig {
+                stratification,
+                hardening,
+            },
+        );
+        for seed in 0..10 {
+            // Weights on very different scales, few enough for the totalizer of the linear search
+            let mut expression = WeightedExpression::new();
+            for (index, clause) in random_k_sat(9, 30, 3, seed)
+                .get_clauses()
+                .iter()
+                .enumerate()
+            {
+                if index < 4 {
+                    expression.add_hard_clause(clause.clone());
+                } else {
+                    let weight = [1, 30, 900][index % 3];
+                    expression.add_soft_clause(clause.clone(), weight);
+                }
+            }
+            let cost = brute_force(&expression);
+            assert_eq!(
+                solve_maxsat(&expression).map(|solution| solution.cost),
+                cost
+            );
+            for config in &configs {
+                let (solution, statistics) =
+                    solve_maxsat_core_guided_with_config(&expression, config);
+                assert_eq!(solution.map(|solution| solution.cost), cost, "{:?}", config);
+                if !config.stratification {
+                    assert_eq!(statistics.num_strata, 1);
+                }
+                if !config.hardening {
+                    assert_eq!(statistics.num_hardened, 0);
+                }
+            }
+        }
+
+        // The first stratum satisfies 1, and its model violating every light clause bounds the cost
+        // at 13, so 1 is hardened, heavier than the gap to the lower bound of 0
+        let mut expression = WeightedExpression::new();
+        expression.add_soft_clause(Clause::from_literals(&[1]), 100);
+        expression.add_soft_clause(Clause::from_literals(&[-1]), 10);
+        for variable in 2..=4 {
+            expression.add_soft_clause(Clause::from_literals(&[variable]), 1);
+            expression.add_soft_clause(Clause::from_literals(&[-variable]), 1);
+        }
+        let (solution, statistics) =
+            solve_maxsat_core_guided_with_config(&expression, &CoreGuidedConfig::default());
+        let solution = solution.unwrap();
+        assert_eq!(solution.cost, 13);
+        assert!(solution.model[&1]);
+        assert_eq!(statistics.num_strata, 3);
+        assert!(statistics.num_hardened > 0);
+    }
+}
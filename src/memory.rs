@@ -0,0 +1,96 @@
+//! Approximate memory usage of the data structures of the solver, for capacity planning.
+//!
+//! The sizes are computed from the capacities of the containers and the sizes of their entries,
+//! without asking the allocator, so they leave out the allocator's own overhead.
+
+use std::fmt;
+use std::mem::size_of;
+
+use crate::hash::{FnvHashMap, FnvHashSet};
+
+/// The approximate number of bytes held by each part of the solver.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryUsage {
+    /// The clauses and their literals.
+    pub clause_database: usize,
+    /// The clauses each literal occurs in.
+    pub occurrence_lists: usize,
+    /// The clauses learned by the search. The DPLL search does not learn any.
+    pub learned_clauses: usize,
+    /// The stack of actions to undo on backtracking, and the current assignment.
+    pub trail: usize,
+    /// The sets of variables, unit clauses and pure literals, and the preferred values.
+    pub other: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.clause_database
+            + self.occurrence_lists
+            + self.learned_clauses
+            + self.trail
+            + self.other
+    }
+}
+
+/// Formats the usage as DIMACS comment lines, in KiB.
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            ("clause database", self.clause_database),
+            ("occurrence lists", self.occurrence_lists),
+            ("learned clauses", self.learned_clauses),
+            ("trail", self.trail),
+            ("other", self.other),
+            ("total", self.total()),
+        ];
+        for (index, (name, bytes)) in parts.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "c {:<18} {:>12.1} KiB", name, *bytes as f64 / 1024.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the bytes of the buffer of the vector.
+pub(crate) fn vec_bytes<T>(vector: &Vec<T>) -> usize {
+    vector.capacity() * size_of::<T>()
+}
+
+/// Returns the bytes of the table of the map: an entry and a control byte per bucket.
+pub(crate) fn map_bytes<K, V>(map: &FnvHashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+/// Returns the bytes of the table of the set: an entry and a control byte per bucket.
+pub(crate) fn set_bytes<T>(set: &FnvHashSet<T>) -> usize {
+    set.capacity() * (size_of::<T>() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dpll::solve_dpll;
+    use crate::expression::Expression;
+    use crate::solver::{solve_with_config, SolverConfig};
+
+    #[test]
+    fn test_memory_report() {
+        let small = Expression::from_cnf_file("examples/cnf/simple_v3_c2.cnf").unwrap();
+        let large = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        let report = large.memory_report();
+        assert!(report.clause_database > small.memory_report().clause_database);
+        assert!(report.occurrence_lists > 0);
+        assert_eq!(report.learned_clauses, 0);
+
+        let mut working = large.clone();
+        working.optimize();
+        solve_dpll(&mut working);
+        assert!(working.memory_report().trail > report.trail);
+
+        let (_, statistics) = solve_with_config(&large, &SolverConfig::default());
+        assert!(statistics.memory.total() >= report.clause_database);
+        assert!(statistics.to_string().contains("c memory"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Satisfiability-preserving mutations of instances, for metamorphic testing: the solver must give
+//! the same answer for a mutated instance, and its models must map back to models of the original.
+
+use std::fmt;
+
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+use crate::rng::Rng;
+use crate::solver::solve;
+
+/// A transformation that keeps the satisfiability of an instance.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mutation {
+    /// Renames the variables with a random permutation.
+    PermuteVariables,
+    /// Negates every occurrence of a random subset of the variables.
+    FlipPolarity,
+    /// Shuffles the clauses and the literals inside them.
+    ShuffleClauses,
+    /// Adds copies of random clauses.
+    DuplicateClauses,
+    /// Adds random supersets of existing clauses, which they subsume.
+    AddSubsumedClauses,
+}
+
+pub const ALL_MUTATIONS: [Mutation; 5] = [
+    Mutation::PermuteVariables,
+    Mutation::FlipPolarity,
+    Mutation::ShuffleClauses,
+    Mutation::DuplicateClauses,
+    Mutation::AddSubsumedClauses,
+];
+
+/// A mutated instance, together with how its variables relate to the original's.
+pub struct MutatedExpression {
+    pub expression: Expression,
+    /// For every original variable (indexed from 1), the literal of the mutated instance it became.
+    renaming: Vec<Literal>,
+}
+
+impl MutatedExpression {
+    /// Translates a model of the mutated instance into a model of the original.
+    pub fn map_model_back(&self, model: &Assignment) -> Assignment {
+        let mut original = Assignment::new();
+        for (variable, literal) in self.renaming.iter().enumerate().skip(1) {
+            if let Some(value) = model.get(&to_variable(*literal)) {
+                original.insert(variable as Variable, *value == (*literal > 0));
+            }
+        }
+        original
+    }
+}
+
+/// Applies the mutation, using the seed for its random choices.
+pub fn mutate(expression: &Expression, mutation: Mutation, seed: u64) -> MutatedExpression {
+    let mut rng = Rng::new(seed);
+    let max_variable = expression.get_max_variable();
+    let mut renaming: Vec<Literal> = (0..=max_variable).map(|v| v as Literal).collect();
+    let mut clauses: Vec<Vec<Literal>> = expression
+        .get_clauses()
+        .iter()
+        .map(|clause| clause.literals().clone())
+        .collect();
+
+    match mutation {
+        Mutation::PermuteVariables => {
+            shuffle(&mut renaming[1..], &mut rng);
+        }
+        Mutation::FlipPolarity => {
+            for literal in renaming.iter_mut().skip(1) {
+                if rng.next_bool() {
+                    *literal = -*literal;
+                }
+            }
+        }
+        Mutation::ShuffleClauses => {
+            shuffle(&mut clauses, &mut rng);
+            for clause in clauses.iter_mut() {
+                shuffle(clause, &mut rng);
+            }
+        }
+        Mutation::DuplicateClauses => {
+            for _ in 0..clauses.len().div_ceil(4) {
+                let index = rng.next_below(clauses.len() as u64) as usize;
+                clauses.push(clauses[index].clone());
+            }
+        }
+        Mutation::AddSubsumedClauses if max_variable > 0 => {
+            for _ in 0..clauses.len().div_ceil(4) {
+                let mut clause = clauses[rng.next_below(clauses.len() as u64) as usize].clone();
+                let variable = rng.next_below(max_variable as u64) as Literal + 1;
+                if !clause.contains(&variable) && !clause.contains(&-variable) {
+                    clause.push(if rng.next_bool() { variable } else { -variable });
+                }
+                clauses.push(clause);
+            }
+        }
+        Mutation::AddSubsumedClauses => {}
+    }
+
+    let mut mutated = Expression::new();
+    for literals in clauses {
+        let mut clause = Clause::new();
+        for literal in literals {
+            let renamed = renaming[to_variable(literal) as usize];
+            clause.insert(if literal > 0 { renamed } else { -renamed });
+        }
+        mutated.add_clause(clause);
+    }
+
+    MutatedExpression {
+        expression: mutated,
+        renaming,
+    }
+}
+
+/// Fisher-Yates shuffle.
+fn shuffle<T>(items: &mut [T], rng: &mut Rng) {
+    for i in (1..items.len()).rev() {
+        let j = rng.next_below(i as u64 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// A mutation under which the solver behaved differently than on the original instance.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MetamorphicFailure {
+    /// The mutation that was applied, or `None` if the solver failed on the original instance.
+    pub mutation: Option<Mutation>,
+    pub seed: u64,
+    pub message: String,
+}
+
+impl fmt::Display for MetamorphicFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mutation {
+            Some(mutation) => write!(
+                f,
+                "{:?} with seed {}: {}",
+                mutation, self.seed, self.message
+            ),
+            None => write!(f, "original instance: {}", self.message),
+        }
+    }
+}
+
+/// Checks that the solver gives the same answer on the expression and on each mutation of it,
+/// and that the models of the mutated instances map back to models of the original.
+pub fn check_invariance(
+    expression: &Expression,
+    mutations: &[Mutation],
+    seed: u64,
+) -> Result<(), MetamorphicFailure> {
+    let expected = solve(expression.clone(), false, false);
+    if let Some(model) = &expected {
+        if !expression.is_satisfied_by(model) {
+            return Err(MetamorphicFailure {
+                mutation: None,
+                seed,
+                message: "the model of the original instance does not verify".to_string(),
+            });
+        }
+    }
+
+    for (index, mutation) in mutations.iter().enumerate() {
+        let mutation_seed = seed.wrapping_add(index as u64);
+        let mutated = mutate(expression, *mutation, mutation_seed);
+        let failure = |message: &str| MetamorphicFailure {
+            mutation: Some(*mutation),
+            seed: mutation_seed,
+            message: message.to_string(),
+        };
+
+        match (&expected, solve(mutated.expression.clone(), false, false)) {
+            (Some(_), None) => {
+                return Err(failure(
+                    "the original is SAT but the mutated instance is UNSAT",
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(failure(
+                    "the original is UNSAT but the mutated instance is SAT",
+                ))
+            }
+            (Some(_), Some(model)) => {
+                if !mutated.expression.is_satisfied_by(&model) {
+                    return Err(failure("the model of the mutated instance does not verify"));
+                }
+                if !expression.is_satisfied_by(&mutated.map_model_back(&model)) {
+                    return Err(failure(
+                        "the model of the mutated instance does not map back to a model of the original",
+                    ));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::generate;
+
+    #[test]
+    fn test_mutations_preserve_structure() {
+        let expression = generate("random-3sat-20-4.26").unwrap().expression;
+        let num_clauses = expression.get_clauses().len();
+
+        let permuted = mutate(&expression, Mutation::PermuteVariables, 3);
+        assert_eq!(permuted.expression.get_clauses().len(), num_clauses);
+        assert_eq!(permuted.expression.get_max_variable(), 20);
+
+        let flipped = mutate(&expression, Mutation::FlipPolarity, 3);
+        let mut model = Assignment::new();
+        for variable in 1..=20 {
+            model.insert(variable, variable % 3 == 0);
+        }
+        let mapped = flipped.map_model_back(&model);
+        assert_eq!(
+            flipped.expression.is_satisfied_by(&model),
+            expression.is_satisfied_by(&mapped)
+        );
+
+        let duplicated = mutate(&expression, Mutation::DuplicateClauses, 3);
+        assert!(duplicated.expression.get_clauses().len() > num_clauses);
+    }
+
+    #[test]
+    fn test_solver_is_invariant_under_mutations() {
+        for name in [
+            "quinn",
+            "pigeonhole-3",
+            "random-3sat-20-4.26",
+            "random-3sat-40-4.26",
+            "parity-8",
+        ] {
+            let expression = generate(name).unwrap().expression;
+            for seed in 0..3 {
+                if let Err(failure) = check_invariance(&expression, &ALL_MUTATIONS, seed) {
+                    panic!("{}: {}", name, failure);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Counters and gauges for monitoring a long-lived solver service, in the Prometheus text format.
+//!
+//! A service shares one [`Metrics`] between the threads answering queries, records every solve
+//! with [`Metrics::record`] (or solves through [`Metrics::solve`]), and keeps the queue depth up to
+//! date. The metrics can be pulled with [`Metrics::render`], or scraped over HTTP from the
+//! `/metrics` endpoint started by [`serve_metrics`].
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::dpll::SearchResult;
+use crate::expression::Expression;
+use crate::solver::{solve_with_config, SolverConfig, SolverStatistics};
+
+/// The metrics of a solver service. All the methods take `&self`, so the metrics can be shared
+/// through an `Arc`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    num_queries: AtomicU64,
+    num_satisfiable: AtomicU64,
+    num_unsatisfiable: AtomicU64,
+    num_unknown: AtomicU64,
+    num_decisions: AtomicU64,
+    num_conflicts: AtomicU64,
+    solve_nanos: AtomicU64,
+    memory_bytes: AtomicU64,
+    queue_depth: AtomicU64,
+}
+
+/// The values of the metrics at one point in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub num_queries: u64,
+    pub num_satisfiable: u64,
+    pub num_unsatisfiable: u64,
+    /// The queries that ran out of budget.
+    pub num_unknown: u64,
+    pub num_decisions: u64,
+    pub num_conflicts: u64,
+    /// The time spent solving, summed over the queries.
+    pub solve_seconds: f64,
+    /// The conflicts per second of solving, over all the queries so far.
+    pub conflicts_per_second: f64,
+    /// The memory held by the solver at the end of the last query.
+    pub memory_bytes: u64,
+    /// The queries waiting to be solved.
+    pub queue_depth: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Records a query answered with the result and statistics.
+    pub fn record(&self, result: &SearchResult, statistics: &SolverStatistics) {
+        let outcome = match result {
+            SearchResult::Satisfiable(_) => &self.num_satisfiable,
+            SearchResult::Unsatisfiable => &self.num_unsatisfiable,
+            SearchResult::BudgetExhausted => &self.num_unknown,
+        };
+        outcome.fetch_add(1, Ordering::Relaxed);
+        self.num_queries.fetch_add(1, Ordering::Relaxed);
+        self.num_decisions
+            .fetch_add(statistics.num_decisions as u64, Ordering::Relaxed);
+        self.num_conflicts
+            .fetch_add(statistics.num_conflicts as u64, Ordering::Relaxed);
+        self.solve_nanos
+            .fetch_add(statistics.time.as_nanos() as u64, Ordering::Relaxed);
+        self.memory_bytes
+            .store(statistics.memory.total() as u64, Ordering::Relaxed);
+    }
+
+    /// Solves the expression with [`solve_with_config`] and records the query.
+    pub fn solve(
+        &self,
+        expression: &Expression,
+        config: &SolverConfig,
+    ) -> (SearchResult, SolverStatistics) {
+        let (result, statistics) = solve_with_config(expression, config);
+        self.record(&result, &statistics);
+        (result, statistics)
+    }
+
+    pub fn set_queue_depth(&self, queue_depth: usize) {
+        self.queue_depth
+            .store(queue_depth as u64, Ordering::Relaxed);
+    }
+
+    pub fn get_snapshot(&self) -> MetricsSnapshot {
+        let num_conflicts = self.num_conflicts.load(Ordering::Relaxed);
+        let solve_seconds = self.solve_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        MetricsSnapshot {
+            num_queries: self.num_queries.load(Ordering::Relaxed),
+            num_satisfiable: self.num_satisfiable.load(Ordering::Relaxed),
+            num_unsatisfiable: self.num_unsatisfiable.load(Ordering::Relaxed),
+            num_unknown: self.num_unknown.load(Ordering::Relaxed),
+            num_decisions: self.num_decisions.load(Ordering::Relaxed),
+            num_conflicts,
+            solve_seconds,
+            conflicts_per_second: if solve_seconds > 0.0 {
+                num_conflicts as f64 / solve_seconds
+            } else {
+                0.0
+            },
+            memory_bytes: self.memory_bytes.load(Ordering::Relaxed),
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let snapshot = self.get_snapshot();
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+            text.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n",
+                name, help, name, kind
+            ));
+            for (labels, value) in samples {
+                text.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+
+        metric(
+            "microsat_queries_total",
+            "counter",
+            "Queries answered, by result.",
+            &[
+                ("{result=\"sat\"}", snapshot.num_satisfiable as f64),
+                ("{result=\"unsat\"}", snapshot.num_unsatisfiable as f64),
+                ("{result=\"unknown\"}", snapshot.num_unknown as f64),
+            ],
+        );
+        metric(
+            "microsat_decisions_total",
+            "counter",
+            "Decisions made by the search.",
+            &[("", snapshot.num_decisions as f64)],
+        );
+        metric(
+            "microsat_conflicts_total",
+            "counter",
+            "Conflicts found by the search.",
+            &[("", snapshot.num_conflicts as f64)],
+        );
+        metric(
+            "microsat_solve_seconds_total",
+            "counter",
+            "Time spent solving.",
+            &[("", snapshot.solve_seconds)],
+        );
+        metric(
+            "microsat_conflicts_per_second",
+            "gauge",
+            "Conflicts per second of solving, over all the queries.",
+            &[("", snapshot.conflicts_per_second)],
+        );
+        metric(
+            "microsat_memory_bytes",
+            "gauge",
+            "Memory held by the solver at the end of the last query.",
+            &[("", snapshot.memory_bytes as f64)],
+        );
+        metric(
+            "microsat_queue_depth",
+            "gauge",
+            "Queries waiting to be solved.",
+            &[("", snapshot.queue_depth as f64)],
+        );
+        text
+    }
+}
+
+/// Answers HTTP requests for `/metrics` on the listener with the rendered metrics, on a
+/// background thread, until the listener fails. Other paths get a 404.
+pub fn serve_metrics(metrics: Arc<Metrics>, listener: TcpListener) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                return;
+            };
+            // A client that goes away mid-request only loses its own response
+            let _ = answer(&metrics, stream);
+        }
+    })
+}
+
+fn answer(metrics: &Metrics, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Skip the headers
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", metrics.render())
+    } else {
+        ("404 Not Found", String::new())
+    };
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::dpll::SearchBudget;
+    use std::io::Read;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::new();
+        let config = SolverConfig::default();
+        metrics.solve(&parse_dimacs_str("p cnf 2 1\n1 2 0\n"), &config);
+        metrics.solve(&parse_dimacs_str("p cnf 1 2\n1 0\n-1 0\n"), &config);
+        let budget = SolverConfig {
+            budget: SearchBudget {
+                max_decisions: Some(1),
+                max_conflicts: None,
+            },
+            ..SolverConfig::default()
+        };
+        metrics.solve(
+            &Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap(),
+            &budget,
+        );
+        metrics.set_queue_depth(4);
+
+        let snapshot = metrics.get_snapshot();
+        assert_eq!(snapshot.num_queries, 3);
+        assert_eq!(snapshot.num_satisfiable, 1);
+        assert_eq!(snapshot.num_unsatisfiable, 1);
+        assert_eq!(snapshot.num_unknown, 1);
+        assert_eq!(snapshot.queue_depth, 4);
+        assert!(snapshot.memory_bytes > 0);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE microsat_queries_total counter\n"));
+        assert!(text.contains("microsat_queries_total{result=\"unsat\"} 1\n"));
+        assert!(text.contains("microsat_queue_depth 4\n"));
+    }
+
+    #[test]
+    fn test_serve_metrics() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.set_queue_depth(2);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        serve_metrics(metrics.clone(), listener);
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&metrics.render()));
+        assert!(get("/other").starts_with("HTTP/1.1 404"));
+    }
+}
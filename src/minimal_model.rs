@@ -0,0 +1,157 @@
+//! Models with as few true variables as possible, as needed for diagnosis and abduction, where
+//! the true variables stand for faults or hypotheses.
+
+use crate::cnf::{Assignment, Literal, Variable};
+use crate::dpll::solve_dpll;
+use crate::encodings::ClauseSink;
+use crate::expression::Expression;
+
+/// Which notion of minimality [`solve_minimal_model`] optimizes for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Minimality {
+    /// No model has a strict subset of the true variables of the result.
+    Subset,
+    /// No model has fewer true variables than the result.
+    Cardinality,
+}
+
+fn solve(expression: &Expression) -> Option<Assignment> {
+    let mut working = expression.clone();
+    working.optimize();
+    solve_dpll(&mut working)
+}
+
+fn true_variables(model: &Assignment, max_variable: Variable) -> Vec<Variable> {
+    let mut variables: Vec<Variable> = model
+        .iter()
+        .filter(|(variable, value)| **value && **variable <= max_variable)
+        .map(|(variable, _)| *variable)
+        .collect();
+    variables.sort_unstable();
+    variables
+}
+
+/// Adds clauses allowing at most `k` of the literals to be true (a sequential counter), using
+/// auxiliary variables from `next_variable` on.
+fn add_at_most(
+    expression: &mut Expression,
+    literals: &[Literal],
+    k: usize,
+    next_variable: &mut usize,
+) {
+    if k == 0 {
+        for literal in literals {
+            expression.emit(&[-literal]);
+        }
+        return;
+    }
+    assert!(
+        *next_variable + literals.len() * k <= Literal::MAX as usize,
+        "The cardinality constraint needs more variables than are available"
+    );
+
+    // counter[j] is true if at least j + 1 of the literals so far are true
+    let mut previous: Vec<Literal> = Vec::new();
+    for (index, literal) in literals.iter().enumerate() {
+        let counter: Vec<Literal> = (0..k).map(|j| (*next_variable + j) as Literal).collect();
+        *next_variable += k;
+
+        expression.emit(&[-literal, counter[0]]);
+        if index > 0 {
+            for j in 0..k {
+                expression.emit(&[-previous[j], counter[j]]);
+            }
+            for j in 1..k {
+                expression.emit(&[-literal, -previous[j - 1], counter[j]]);
+            }
+            expression.emit(&[-literal, -previous[k - 1]]);
+        }
+        previous = counter;
+    }
+}
+
+/// Returns a model of the expression with a minimal set of true variables, or `None` if it is
+/// unsatisfiable.
+///
+/// Both notions are computed by iterative tightening: after each model, the next solve requires a
+/// better one (a strict subset of the true variables, or fewer true variables by a cardinality
+/// constraint), until no better model exists.
+pub fn solve_minimal_model(expression: &Expression, minimality: Minimality) -> Option<Assignment> {
+    let max_variable = expression.get_max_variable();
+    let mut model = solve(expression)?;
+
+    loop {
+        let trues = true_variables(&model, max_variable);
+        if trues.is_empty() {
+            break;
+        }
+
+        let mut tightened = expression.clone();
+        match minimality {
+            Minimality::Subset => {
+                for variable in 1..=max_variable {
+                    if model.get(&variable) == Some(&false) {
+                        tightened.emit(&[-(variable as Literal)]);
+                    }
+                }
+                let negated: Vec<Literal> = trues.iter().map(|v| -(*v as Literal)).collect();
+                tightened.emit(&negated);
+            }
+            Minimality::Cardinality => {
+                let variables: Vec<Literal> = (1..=max_variable)
+                    .filter(|variable| model.contains_key(variable))
+                    .map(|variable| variable as Literal)
+                    .collect();
+                let mut next_variable = max_variable as usize + 1;
+                add_at_most(
+                    &mut tightened,
+                    &variables,
+                    trues.len() - 1,
+                    &mut next_variable,
+                );
+            }
+        }
+
+        match solve(&tightened) {
+            Some(better) => model = better,
+            None => break,
+        }
+    }
+
+    model.retain(|variable, _| *variable <= max_variable);
+    Some(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_solve_minimal_model() {
+        // The subset-minimal models are {1} and {2, 3, 4}, only {1} is cardinality-minimal
+        let expression = parse_dimacs_str("p cnf 4 3\n1 2 0\n1 3 0\n1 4 0\n");
+
+        let model = solve_minimal_model(&expression, Minimality::Cardinality).unwrap();
+        assert!(expression.is_satisfied_by(&model));
+        assert_eq!(true_variables(&model, 4), vec![1]);
+
+        let model = solve_minimal_model(&expression, Minimality::Subset).unwrap();
+        let trues = true_variables(&model, 4);
+        assert!(trues == vec![1] || trues == vec![2, 3, 4], "{:?}", trues);
+
+        let unsatisfiable = parse_dimacs_str("p cnf 1 2\n1 0\n-1 0\n");
+        assert_eq!(
+            solve_minimal_model(&unsatisfiable, Minimality::Subset),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cardinality_constraint() {
+        // Each clause needs a true variable, and 1, 3 and 5 cannot all be true
+        let expression = parse_dimacs_str("p cnf 6 4\n1 2 0\n3 4 0\n5 6 0\n-1 -3 -5 0\n");
+        let model = solve_minimal_model(&expression, Minimality::Cardinality).unwrap();
+        assert_eq!(true_variables(&model, 6).len(), 3);
+    }
+}
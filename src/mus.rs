@@ -0,0 +1,196 @@
+//! Minimal unsatisfiable subsets (MUSes) of the clauses of an unsatisfiable expression, the
+//! explanations of why it has no model.
+//!
+//! [`find_mus`] returns a subset-minimal MUS: removing any of its clauses makes it satisfiable.
+//! [`find_smallest_mus`] returns a MUS with as few clauses as possible, which takes many more
+//! solves and is therefore bounded by a time budget.
+
+use std::time::{Duration, Instant};
+
+use crate::cnf::{Assignment, Clause, Literal, Variable, CNF};
+use crate::dpll::solve_dpll;
+use crate::expression::Expression;
+use crate::minimal_model::{solve_minimal_model, Minimality};
+
+fn solve_subset(clauses: &[Clause], subset: &[usize]) -> Option<Assignment> {
+    let mut working = Expression::new();
+    for index in subset {
+        working.add_clause(clauses[*index].clone());
+    }
+    working.optimize();
+    solve_dpll(&mut working)
+}
+
+/// Shrinks an unsatisfiable subset of the clauses to a subset-minimal one, by dropping every
+/// clause without which the rest stays unsatisfiable.
+fn shrink(clauses: &[Clause], mut subset: Vec<usize>) -> Vec<usize> {
+    let mut position = 0;
+    while position < subset.len() {
+        let removed = subset.remove(position);
+        if solve_subset(clauses, &subset).is_some() {
+            subset.insert(position, removed);
+            position += 1;
+        }
+    }
+    subset
+}
+
+/// Returns the indices (into [`Expression::get_clauses`]) of a subset-minimal unsatisfiable
+/// subset of the clauses, or `None` if the expression is satisfiable.
+pub fn find_mus(expression: &Expression) -> Option<Vec<usize>> {
+    let clauses = expression.get_clauses();
+    let all: Vec<usize> = (0..clauses.len()).collect();
+    if solve_subset(&clauses, &all).is_some() {
+        return None;
+    }
+
+    Some(shrink(&clauses, all))
+}
+
+/// The result of [`find_smallest_mus`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SmallestMus {
+    /// The indices of the clauses of the smallest MUS found, in increasing order.
+    pub clauses: Vec<usize>,
+    /// Whether no MUS has fewer clauses. This is false if the time budget ran out first.
+    pub optimal: bool,
+}
+
+/// Returns a smallest set of clauses with at least one clause of every correction set, solved as
+/// a cardinality-minimal model with one variable per clause involved. Returns `None` if the
+/// cardinality constraint would need more variables than are available.
+fn smallest_hitting_set(corrections: &[Vec<usize>], num_clauses: usize) -> Option<Vec<usize>> {
+    let mut variables: Vec<Option<Variable>> = vec![None; num_clauses];
+    let mut clauses: Vec<usize> = Vec::new();
+    let mut problem = Expression::new();
+    for correction in corrections {
+        let mut clause = Clause::new();
+        for index in correction {
+            let variable = *variables[*index].get_or_insert_with(|| {
+                clauses.push(*index);
+                clauses.len() as Variable
+            });
+            clause.insert(variable as Literal);
+        }
+        problem.add_clause(clause);
+    }
+    if clauses.len() * (clauses.len() + 1) >= Literal::MAX as usize {
+        return None;
+    }
+
+    let model = solve_minimal_model(&problem, Minimality::Cardinality)?;
+    let mut hitting_set: Vec<usize> = (0..clauses.len())
+        .filter(|variable| model.get(&(*variable as Variable + 1)) == Some(&true))
+        .map(|variable| clauses[variable])
+        .collect();
+    hitting_set.sort_unstable();
+    Some(hitting_set)
+}
+
+/// Returns a MUS with the fewest clauses, or `None` if the expression is satisfiable.
+///
+/// Every MUS contains at least one clause of each correction set (a set of clauses whose removal
+/// makes the rest satisfiable), so the algorithm alternates between a smallest hitting set of the
+/// correction sets found so far and, while that hitting set is satisfiable, extending it to a
+/// maximal satisfiable subset whose complement is a new correction set. The first unsatisfiable
+/// hitting set is a smallest MUS.
+///
+/// The search starts from the MUS of [`find_mus`]. Once `budget` has elapsed, it stops and returns
+/// the smallest MUS found so far, marked as not optimal; the time is checked between solves, so it
+/// can be exceeded by the duration of one solve. The search also stops early if the hitting set
+/// problem grows beyond what the cardinality constraint can encode.
+pub fn find_smallest_mus(expression: &Expression, budget: Duration) -> Option<SmallestMus> {
+    let start = Instant::now();
+    let clauses = expression.get_clauses();
+    let mut best = SmallestMus {
+        clauses: find_mus(expression)?,
+        optimal: false,
+    };
+    if best.clauses.len() <= 1 {
+        best.optimal = true;
+        return Some(best);
+    }
+
+    let mut corrections: Vec<Vec<usize>> = Vec::new();
+    while start.elapsed() < budget {
+        let Some(hitting_set) = smallest_hitting_set(&corrections, clauses.len()) else {
+            break;
+        };
+        if hitting_set.len() == best.clauses.len() {
+            // The smallest hitting set is a lower bound on the size of a MUS
+            best.optimal = true;
+            break;
+        }
+        let Some(mut model) = solve_subset(&clauses, &hitting_set) else {
+            best.clauses = hitting_set;
+            best.optimal = true;
+            break;
+        };
+
+        // Grow the hitting set into a maximal satisfiable subset
+        let mut satisfiable = hitting_set;
+        let mut correction = Vec::new();
+        for index in 0..clauses.len() {
+            if satisfiable.contains(&index) {
+                continue;
+            }
+            if clauses[index].is_satisfied_by(&model) {
+                satisfiable.push(index);
+                continue;
+            }
+            satisfiable.push(index);
+            match solve_subset(&clauses, &satisfiable) {
+                Some(extended) => model = extended,
+                None => {
+                    satisfiable.pop();
+                    correction.push(index);
+                }
+            }
+        }
+
+        corrections.push(correction);
+    }
+
+    Some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_find_mus() {
+        let expression = parse_dimacs_str("p cnf 3 5\n1 0\n2 3 0\n-1 2 0\n-2 0\n3 0\n");
+        assert_eq!(find_mus(&expression), Some(vec![0, 2, 3]));
+
+        let satisfiable = parse_dimacs_str("p cnf 2 1\n1 2 0\n");
+        assert_eq!(find_mus(&satisfiable), None);
+        assert_eq!(
+            find_smallest_mus(&satisfiable, Duration::from_secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_smallest_mus() {
+        // Deletion in order drops 5 and -5 first and keeps the chain 1 -> 2 -> 3 -> 4 -> -1,
+        // although 5 and -5 are contradictory on their own
+        let expression =
+            parse_dimacs_str("p cnf 5 7\n5 0\n-5 0\n1 0\n-1 2 0\n-2 3 0\n-3 4 0\n-4 -1 0\n");
+        assert_eq!(find_mus(&expression).unwrap().len(), 5);
+
+        let smallest = find_smallest_mus(&expression, Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            smallest,
+            SmallestMus {
+                clauses: vec![0, 1],
+                optimal: true
+            }
+        );
+
+        let best_so_far = find_smallest_mus(&expression, Duration::ZERO).unwrap();
+        assert_eq!(best_so_far.clauses.len(), 5);
+        assert!(!best_so_far.optimal);
+    }
+}
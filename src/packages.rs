@@ -0,0 +1,493 @@
+//! Package dependency resolution, as an example application of the solver: which version of which
+//! package to install so that every requirement is met, every dependency is installed and no two
+//! conflicting releases are.
+//!
+//! Enabled with the `packages` feature, and demonstrated by `cargo run --features packages
+//! --example package_resolver`. The releases of every package are an array of variables of an
+//! instance template (see [`crate::template`]), of which at most one is true. A release implies one
+//! of the releases matching each of its dependencies, and excludes the releases it conflicts with.
+//!
+//! Every requirement is guarded by a selector variable, and the clauses are first solved with all
+//! the selectors assumed. If they cannot hold together, the failed assumptions are shrunk to a
+//! minimal set of requirements that conflict with each other. Otherwise, the install plan is
+//! optimized as a MaxSAT instance, where installing a release is a violated soft clause weighing
+//! more for older versions, so the plan installs as few packages as it can, as new as it can.
+
+use std::fmt;
+
+use hashbrown::HashMap;
+
+use crate::cnf::{Clause, Literal, Variable};
+use crate::dpll::SearchResult;
+use crate::maxsat::solve_maxsat_core_guided;
+use crate::solver::Solver;
+use crate::template::{instantiate, IndexMap, VariableArray};
+use crate::weighted_expression::{Weight, WeightedExpression};
+
+/// A version number of the form `major.minor.patch`, ordered numerically.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub fn new(major: u32, minor: u32, patch: u32) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses a version from up to three numbers separated by dots, the missing ones being 0.
+    pub fn parse(version: &str) -> Option<Version> {
+        let mut numbers = [0; 3];
+        let mut parts = version.split('.');
+        for number in &mut numbers {
+            match parts.next() {
+                Some(part) => *number = part.parse().ok()?,
+                None => break,
+            }
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Version::new(numbers[0], numbers[1], numbers[2]))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A package together with the range of its versions that will do, from `min` included up to
+/// `max` excluded. Either bound may be left open.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dependency {
+    pub package: String,
+    pub min: Option<Version>,
+    pub max: Option<Version>,
+}
+
+impl Dependency {
+    /// Any version of the package.
+    pub fn any(package: &str) -> Dependency {
+        Dependency {
+            package: package.to_string(),
+            min: None,
+            max: None,
+        }
+    }
+
+    /// The versions of the package from `min` included up to `max` excluded.
+    pub fn range(package: &str, min: Version, max: Version) -> Dependency {
+        Dependency {
+            package: package.to_string(),
+            min: Some(min),
+            max: Some(max),
+        }
+    }
+
+    pub fn matches(&self, package: &str, version: Version) -> bool {
+        self.package == package
+            && self.min.is_none_or(|min| min <= version)
+            && self.max.is_none_or(|max| version < max)
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.package)?;
+        if let Some(min) = self.min {
+            write!(f, " >={}", min)?;
+        }
+        if let Some(max) = self.max {
+            write!(f, " <{}", max)?;
+        }
+        Ok(())
+    }
+}
+
+/// A version of a package, with what it needs installed alongside it and what it cannot be
+/// installed with.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Release {
+    pub package: String,
+    pub version: Version,
+    pub dependencies: Vec<Dependency>,
+    pub conflicts: Vec<Dependency>,
+}
+
+impl Release {
+    pub fn new(package: &str, version: Version) -> Release {
+        Release {
+            package: package.to_string(),
+            version,
+            dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
+
+    pub fn depends_on(mut self, dependency: Dependency) -> Release {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    pub fn conflicts_with(mut self, conflict: Dependency) -> Release {
+        self.conflicts.push(conflict);
+        self
+    }
+}
+
+/// The releases available for installation.
+#[derive(Clone, Debug, Default)]
+pub struct Repository {
+    releases: Vec<Release>,
+}
+
+impl Repository {
+    pub fn new() -> Repository {
+        Repository::default()
+    }
+
+    pub fn add_release(&mut self, release: Release) {
+        self.releases.push(release);
+    }
+
+    pub fn get_releases(&self) -> &[Release] {
+        &self.releases
+    }
+
+    /// Returns the packages with their releases, oldest first, sorted by name.
+    fn by_package(&self) -> Vec<(&str, Vec<&Release>)> {
+        let mut packages: HashMap<&str, Vec<&Release>> = HashMap::new();
+        for release in &self.releases {
+            packages
+                .entry(release.package.as_str())
+                .or_default()
+                .push(release);
+        }
+        let mut packages: Vec<(&str, Vec<&Release>)> = packages.into_iter().collect();
+        packages.sort_unstable_by_key(|(package, _)| *package);
+        for (_, releases) in &mut packages {
+            releases.sort_unstable_by_key(|release| release.version);
+            releases.dedup_by_key(|release| release.version);
+        }
+        packages
+    }
+}
+
+/// The releases to install, sorted by package, and the weight of the preferences they give up.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InstallPlan {
+    pub releases: Vec<(String, Version)>,
+    pub cost: Weight,
+}
+
+impl fmt::Display for InstallPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (package, version)) in self.releases.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "install {} {}", package, version)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ResolutionError {
+    /// No release of the package matches the version range the requirement asks for.
+    Unavailable(Dependency),
+    /// The requirements at these indices cannot all be met together, though the others of them
+    /// can be once any one is dropped.
+    Conflict(Vec<usize>),
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolutionError::Unavailable(dependency) => {
+                write!(f, "no release matches {}", dependency)
+            }
+            ResolutionError::Conflict(requirements) => {
+                let requirements: Vec<String> = requirements
+                    .iter()
+                    .map(|requirement| (requirement + 1).to_string())
+                    .collect();
+                write!(
+                    f,
+                    "the requirements {} cannot be met together",
+                    requirements.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolutionError {}
+
+/// The clauses of a resolution problem, over the releases of every package and a selector for
+/// every requirement.
+struct Encoding {
+    clauses: Vec<Clause>,
+    map: IndexMap,
+    /// The selector of every requirement.
+    selectors: Vec<Literal>,
+    /// The literal of every release with its position among the releases of its package, oldest
+    /// first, and the number of releases of the package.
+    releases: Vec<(Literal, usize, usize)>,
+}
+
+fn encode(
+    repository: &Repository,
+    requirements: &[Dependency],
+) -> Result<Encoding, ResolutionError> {
+    let packages = repository.by_package();
+    let mut missing = None;
+    let mut selectors = Vec::new();
+    let mut literals = Vec::new();
+    let (expression, map) = instantiate(|builder| {
+        let arrays: Vec<VariableArray> = packages
+            .iter()
+            .map(|(package, releases)| builder.add_array(package, &[releases.len()]))
+            .collect();
+        // No package name starts with a space
+        let requirement_array = builder.add_array(" requirements", &[requirements.len()]);
+        let matching = |dependency: &Dependency| -> Vec<Literal> {
+            let Ok(index) = packages.binary_search_by_key(&dependency.package.as_str(), |p| p.0)
+            else {
+                return Vec::new();
+            };
+            packages[index]
+                .1
+                .iter()
+                .enumerate()
+                .filter(|(_, release)| dependency.matches(&release.package, release.version))
+                .map(|(position, _)| arrays[index].at(&[position]))
+                .collect()
+        };
+
+        for ((_, releases), array) in packages.iter().zip(&arrays) {
+            let versions: Vec<Literal> = (0..releases.len())
+                .map(|position| array.at(&[position]))
+                .collect();
+            builder.add_at_most_one(&versions);
+            for (position, release) in releases.iter().enumerate() {
+                let installed = array.at(&[position]);
+                literals.push((installed, position, releases.len()));
+                for dependency in &release.dependencies {
+                    let mut clause = vec![-installed];
+                    clause.extend(matching(dependency));
+                    builder.add_clause(&clause);
+                }
+                for conflict in &release.conflicts {
+                    for other in matching(conflict) {
+                        if other != installed {
+                            builder.add_clause(&[-installed, -other]);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (index, requirement) in requirements.iter().enumerate() {
+            let options = matching(requirement);
+            if options.is_empty() && missing.is_none() {
+                missing = Some(requirement.clone());
+            }
+            let selector = requirement_array.at(&[index]);
+            let mut clause = vec![-selector];
+            clause.extend(options);
+            builder.add_clause(&clause);
+            selectors.push(selector);
+        }
+    });
+    if let Some(dependency) = missing {
+        return Err(ResolutionError::Unavailable(dependency));
+    }
+    Ok(Encoding {
+        clauses: expression.get_clauses(),
+        map,
+        selectors,
+        releases: literals,
+    })
+}
+
+/// Shrinks the failed selectors to a subset-minimal set the clauses refute together, by dropping
+/// every selector without which the rest is still refuted.
+fn minimize_core(solver: &mut Solver, core: &[Literal]) -> Vec<Literal> {
+    let mut core = core.to_vec();
+    let mut position = 0;
+    while position < core.len() {
+        let mut without = core.clone();
+        without.remove(position);
+        match solver.solve_with_assumptions(&without) {
+            SearchResult::Unsatisfiable => {
+                // The failed assumptions are a subset of the rest, and so is the next core
+                let failed = solver.get_failed_assumptions();
+                core.retain(|literal| failed.contains(literal));
+            }
+            _ => position += 1,
+        }
+    }
+    core
+}
+
+/// Returns the install plan meeting every requirement with the fewest and newest releases, or the
+/// reason the requirements cannot be met. See the module documentation for the encoding.
+pub fn resolve(
+    repository: &Repository,
+    requirements: &[Dependency],
+) -> Result<InstallPlan, ResolutionError> {
+    let encoding = encode(repository, requirements)?;
+
+    let mut solver = Solver::new();
+    for clause in &encoding.clauses {
+        solver.add_clause(clause.literals());
+    }
+    if solver.solve_with_assumptions(&encoding.selectors) == SearchResult::Unsatisfiable {
+        let core = solver.get_failed_assumptions().to_vec();
+        let core = minimize_core(&mut solver, &core);
+        let mut requirements: Vec<usize> = core
+            .iter()
+            .map(|selector| {
+                encoding
+                    .selectors
+                    .iter()
+                    .position(|s| s == selector)
+                    .unwrap()
+            })
+            .collect();
+        requirements.sort_unstable();
+        return Err(ResolutionError::Conflict(requirements));
+    }
+
+    let mut expression = WeightedExpression::new();
+    for clause in encoding.clauses {
+        expression.add_hard_clause(clause);
+    }
+    for selector in &encoding.selectors {
+        let mut clause = Clause::new();
+        clause.insert(*selector);
+        expression.add_hard_clause(clause);
+    }
+    // Installing anything costs 1, and every newer release passed over costs one more
+    for (literal, position, num_releases) in &encoding.releases {
+        let mut clause = Clause::new();
+        clause.insert(-literal);
+        expression.add_soft_clause(clause, (num_releases - position) as Weight);
+    }
+    let solution = solve_maxsat_core_guided(&expression)
+        .expect("The requirements were satisfiable under assumptions");
+
+    let mut releases: Vec<(String, Version)> = Vec::new();
+    let packages = repository.by_package();
+    for (literal, position, _) in &encoding.releases {
+        if solution.model[&(*literal as Variable)] {
+            let (package, _) = encoding.map.get_index(*literal as Variable).unwrap();
+            let index = packages.binary_search_by_key(&package, |p| p.0).unwrap();
+            releases.push((package.to_string(), packages[index].1[*position].version));
+        }
+    }
+    Ok(InstallPlan {
+        releases,
+        cost: solution.cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    fn repository() -> Repository {
+        let mut repository = Repository::new();
+        repository.add_release(
+            Release::new("app", version("1.0")).depends_on(Dependency::range(
+                "lib",
+                version("1.0"),
+                version("2.0"),
+            )),
+        );
+        repository.add_release(
+            Release::new("app", version("2.0")).depends_on(Dependency::range(
+                "lib",
+                version("2.0"),
+                version("3.0"),
+            )),
+        );
+        repository.add_release(Release::new("lib", version("1.4")));
+        repository.add_release(Release::new("lib", version("1.5")));
+        repository
+            .add_release(Release::new("lib", version("2.1")).depends_on(Dependency::any("log")));
+        repository.add_release(Release::new("log", version("0.3")));
+        repository.add_release(
+            Release::new("legacy", version("1.0")).conflicts_with(Dependency::range(
+                "lib",
+                version("2.0"),
+                version("3.0"),
+            )),
+        );
+        repository
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(Version::parse("1.2"), Some(Version::new(1, 2, 0)));
+        assert_eq!(Version::parse("1.2.3.4"), None);
+        assert_eq!(Version::parse("1.x"), None);
+        assert!(version("1.10") > version("1.9"));
+        assert_eq!(version("3").to_string(), "3.0.0");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let repository = repository();
+        let plan = resolve(&repository, &[Dependency::any("app")]).unwrap();
+        let expected = vec![
+            ("app".to_string(), version("2.0")),
+            ("lib".to_string(), version("2.1")),
+            ("log".to_string(), version("0.3")),
+        ];
+        assert_eq!(plan.releases, expected);
+        assert_eq!(plan.cost, 3);
+
+        // The legacy package holds the library back, and with it the application
+        let requirements = [Dependency::any("app"), Dependency::any("legacy")];
+        let plan = resolve(&repository, &requirements).unwrap();
+        let expected = vec![
+            ("app".to_string(), version("1.0")),
+            ("legacy".to_string(), version("1.0")),
+            ("lib".to_string(), version("1.5")),
+        ];
+        assert_eq!(plan.releases, expected);
+        assert_eq!(plan.to_string().lines().next(), Some("install app 1.0.0"));
+
+        let requirements = [
+            Dependency::any("log"),
+            Dependency::range("app", version("2.0"), version("3.0")),
+            Dependency::any("legacy"),
+        ];
+        let error = resolve(&repository, &requirements).unwrap_err();
+        assert_eq!(error, ResolutionError::Conflict(vec![1, 2]));
+        assert_eq!(
+            error.to_string(),
+            "the requirements 2, 3 cannot be met together"
+        );
+
+        let missing = Dependency::range("lib", version("3.0"), version("4.0"));
+        assert_eq!(
+            resolve(&repository, std::slice::from_ref(&missing)),
+            Err(ResolutionError::Unavailable(missing))
+        );
+    }
+}
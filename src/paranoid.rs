@@ -0,0 +1,153 @@
+//! A sanitizer for the DPLL search, which catches the state-restoration bugs in the undo machinery
+//! of [`Expression`] that only show up under real workloads.
+//!
+//! The expression maintains its occurrence lists, unit clauses, pure literals, clause counters and
+//! occurrence heaps incrementally, and restores them from its stack of actions on backtracking. In
+//! paranoid mode (see [`Expression::set_paranoid`]), it also keeps the clauses it started from,
+//! and every so many operations re-derives all of this state from them and the trail alone. Any
+//! difference is recorded as a [`Divergence`], with the operations since the previous check, the
+//! last moment the state was known to be consistent.
+
+use std::fmt;
+
+use crate::cnf::{Assignment, Clause};
+use crate::dpll::solve_dpll;
+use crate::expression::Expression;
+
+/// How often the paranoid mode checks the state of the expression.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParanoidPolicy {
+    /// The number of operations (decisions, propagations, pure literals and backtracks) between
+    /// two checks. A check after every operation pins a divergence down to the operation causing
+    /// it, at the price of a search slower by a factor of the size of the instance.
+    pub interval: usize,
+}
+
+impl Default for ParanoidPolicy {
+    fn default() -> Self {
+        ParanoidPolicy { interval: 64 }
+    }
+}
+
+/// A difference between the state of the expression and the state re-derived from scratch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Divergence {
+    /// The number of operations of the search when the check ran.
+    pub num_operations: usize,
+    /// Every part of the state that differed, described.
+    pub problems: Vec<String>,
+    /// The operations since the previous check, oldest first.
+    pub history: Vec<String>,
+}
+
+/// Formats the divergence as DIMACS comment lines.
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "c divergence after {} operations:", self.num_operations)?;
+        for problem in &self.problems {
+            write!(f, "\nc   {}", problem)?;
+        }
+        write!(f, "\nc operations since the last check:")?;
+        for operation in &self.history {
+            write!(f, "\nc   {}", operation)?;
+        }
+        Ok(())
+    }
+}
+
+/// What the paranoid mode keeps in the expression between checks.
+#[derive(Clone, Debug)]
+pub(crate) struct ParanoidState {
+    pub(crate) policy: ParanoidPolicy,
+    /// The clauses before the search removed any literal from them.
+    pub(crate) original: Vec<Clause>,
+    num_operations: usize,
+    history: Vec<String>,
+    pub(crate) divergences: Vec<Divergence>,
+}
+
+impl ParanoidState {
+    pub(crate) fn new(policy: ParanoidPolicy, original: Vec<Clause>) -> ParanoidState {
+        ParanoidState {
+            policy,
+            original,
+            num_operations: 0,
+            history: Vec::new(),
+            divergences: Vec::new(),
+        }
+    }
+
+    /// Records the operation, and returns true if a check is due after it.
+    pub(crate) fn record(&mut self, operation: String) -> bool {
+        self.num_operations += 1;
+        self.history.push(operation);
+        self.num_operations
+            .is_multiple_of(self.policy.interval.max(1))
+    }
+
+    /// Records the outcome of a check, which starts a new history.
+    pub(crate) fn report(&mut self, problems: Vec<String>) {
+        let history = std::mem::take(&mut self.history);
+        if !problems.is_empty() {
+            self.divergences.push(Divergence {
+                num_operations: self.num_operations,
+                problems,
+                history,
+            });
+        }
+    }
+}
+
+/// Solves the expression with the DPLL search in paranoid mode, checking its state once more when
+/// the search is over. Returns the model, if any, and the divergences found.
+pub fn solve_paranoid(
+    expression: &Expression,
+    policy: &ParanoidPolicy,
+) -> (Option<Assignment>, Vec<Divergence>) {
+    let mut working = expression.clone();
+    working.optimize();
+    // Cloning keeps the settings of the search but the heuristic
+    working.set_heuristic(expression.heuristic);
+    working.set_paranoid(Some(*policy));
+    let model = solve_dpll(&mut working);
+    working.check_cached_state();
+    (model, working.get_divergences().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::generate;
+    use crate::expression::{PureLiteralMode, SolverHeuristic};
+
+    #[test]
+    fn test_solve_paranoid() {
+        let policy = ParanoidPolicy { interval: 1 };
+        for name in [
+            "quinn",
+            "pigeonhole-3",
+            "parity-unsat-6",
+            "random-3sat-20-4.26",
+        ] {
+            for (mode, heuristic) in [
+                (
+                    PureLiteralMode::Dynamic,
+                    SolverHeuristic::MostLiteralOccurances,
+                ),
+                (
+                    PureLiteralMode::Preprocessing,
+                    SolverHeuristic::MostVariableOccurances,
+                ),
+            ] {
+                let mut expression = generate(name).unwrap().expression;
+                expression.set_pure_literal_mode(mode);
+                expression.set_heuristic(heuristic);
+                let (model, divergences) = solve_paranoid(&expression, &policy);
+                assert_eq!(divergences, Vec::new(), "{} {:?}", name, mode);
+                if let Some(model) = model {
+                    assert!(expression.is_satisfied_by(&model));
+                }
+            }
+        }
+    }
+}
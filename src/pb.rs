@@ -0,0 +1,847 @@
+//! Linear pseudo-Boolean constraints, `a1·l1 + a2·l2 + ... >= k` over literals with integer
+//! coefficients, their encodings to CNF, and the OPB file format of the pseudo-Boolean
+//! competitions.
+//!
+//! Every constraint is kept normalized: the coefficients are positive, every variable shows up at
+//! most once, and no coefficient exceeds the bound. Two encodings are available:
+//!
+//! - [`PbEncoding::Bdd`] builds the decision diagram of the constraint, the terms taken heaviest
+//!   first, with one auxiliary variable per node. Propagation on it is complete, but it can grow
+//!   with the product of the number of terms and the bound.
+//! - [`PbEncoding::Adder`] sums the terms with a network of full and half adders over the binary
+//!   representation of the coefficients, and compares the sum bit by bit with the bound. It stays
+//!   small whatever the coefficients, at the price of weaker propagation.
+//!
+//! An instance with an objective to minimize maps onto the MaxSAT layer: the constraints are hard
+//! clauses, and every term of the objective is a soft unit clause weighing its coefficient.
+
+use std::fmt;
+
+use hashbrown::HashMap;
+
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable, CNF};
+use crate::encodings::{add_xor, VariableAllocator};
+use crate::expression::Expression;
+use crate::formula::project_assignment;
+use crate::maxsat::solve_maxsat_core_guided;
+use crate::weighted_expression::{Weight, WeightedExpression};
+
+/// The coefficient of a term, or the bound of a constraint.
+pub type Coefficient = i64;
+
+/// How a pseudo-Boolean constraint is translated to clauses.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PbEncoding {
+    #[default]
+    Bdd,
+    Adder,
+}
+
+/// A normalized pseudo-Boolean constraint: the sum of the coefficients of the true literals is at
+/// least the bound.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PbConstraint {
+    /// The terms, heaviest first, over distinct variables.
+    terms: Vec<(Coefficient, Literal)>,
+    bound: Coefficient,
+}
+
+impl PbConstraint {
+    /// Returns the constraint that the sum of the terms is at least the bound. The terms may have
+    /// any sign and repeat variables. Panics if the magnitudes of the coefficients and the bound
+    /// add up to more than `Coefficient::MAX`, which [`parse_opb_str`] rejects.
+    pub fn at_least(terms: &[(Coefficient, Literal)], bound: Coefficient) -> PbConstraint {
+        assert!(
+            magnitude(terms, bound).is_some(),
+            "The coefficients of the constraint are too large"
+        );
+        // The coefficient of every variable as a positive literal, with ¬x written 1 - x
+        let mut bound = bound;
+        let mut coefficients: Vec<(Variable, Coefficient)> = Vec::new();
+        for (coefficient, literal) in terms {
+            let variable = to_variable(*literal);
+            let coefficient = if *literal > 0 {
+                *coefficient
+            } else {
+                bound -= coefficient;
+                -coefficient
+            };
+            match coefficients
+                .iter_mut()
+                .find(|(other, _)| *other == variable)
+            {
+                Some((_, sum)) => *sum += coefficient,
+                None => coefficients.push((variable, coefficient)),
+            }
+        }
+
+        let mut terms = Vec::new();
+        for (variable, coefficient) in coefficients {
+            let literal = variable as Literal;
+            if coefficient > 0 {
+                terms.push((coefficient, literal));
+            } else if coefficient < 0 {
+                bound -= coefficient;
+                terms.push((-coefficient, -literal));
+            }
+        }
+        // A term heavier than the bound satisfies the constraint as well with the bound as weight
+        if bound > 0 {
+            for (coefficient, _) in &mut terms {
+                *coefficient = (*coefficient).min(bound);
+            }
+        }
+        terms.sort_by_key(|(coefficient, literal)| (-coefficient, to_variable(*literal)));
+        PbConstraint { terms, bound }
+    }
+
+    /// Returns the constraint that the sum of the terms is at most the bound.
+    pub fn at_most(terms: &[(Coefficient, Literal)], bound: Coefficient) -> PbConstraint {
+        let negated: Vec<(Coefficient, Literal)> = terms
+            .iter()
+            .map(|(coefficient, literal)| (-coefficient, *literal))
+            .collect();
+        PbConstraint::at_least(&negated, -bound)
+    }
+
+    pub fn get_terms(&self) -> &[(Coefficient, Literal)] {
+        &self.terms
+    }
+
+    pub fn get_bound(&self) -> Coefficient {
+        self.bound
+    }
+
+    /// Returns true if every assignment satisfies the constraint.
+    pub fn is_trivial(&self) -> bool {
+        self.bound <= 0
+    }
+
+    /// Returns true if no assignment satisfies the constraint.
+    pub fn is_unsatisfiable(&self) -> bool {
+        self.terms
+            .iter()
+            .map(|(coefficient, _)| *coefficient as i128)
+            .sum::<i128>()
+            < self.bound as i128
+    }
+
+    /// Returns true if the assignment satisfies the constraint. Variables missing from the
+    /// assignment are treated as false.
+    pub fn is_satisfied_by(&self, assignment: &Assignment) -> bool {
+        let sum: Coefficient = self
+            .terms
+            .iter()
+            .filter(|(_, literal)| is_true(*literal, assignment))
+            .map(|(coefficient, _)| coefficient)
+            .sum();
+        sum >= self.bound
+    }
+
+    /// Adds clauses to the expression requiring the constraint to hold, allocating auxiliary
+    /// variables starting at `next_variable`.
+    pub fn encode_into(
+        &self,
+        encoding: PbEncoding,
+        expression: &mut Expression,
+        next_variable: &mut Variable,
+    ) {
+        if self.is_trivial() {
+            return;
+        }
+        if self.is_unsatisfiable() {
+            expression.add_clause(Clause::new());
+            return;
+        }
+        // Clauses, such as cardinality constraints of weight one, need no auxiliary variables
+        if self
+            .terms
+            .iter()
+            .all(|(coefficient, _)| *coefficient == self.bound)
+        {
+            let literals: Vec<Literal> = self.terms.iter().map(|(_, literal)| *literal).collect();
+            expression.add_clause(Clause::from_literals(&literals));
+            return;
+        }
+        let mut encoder = Encoder {
+            expression,
+            next_variable,
+        };
+        match encoding {
+            PbEncoding::Bdd => {
+                let mut suffix_sums = vec![0; self.terms.len() + 1];
+                for (position, (coefficient, _)) in self.terms.iter().enumerate().rev() {
+                    suffix_sums[position] = suffix_sums[position + 1] + coefficient;
+                }
+                let root = encoder.bdd_node(
+                    &self.terms,
+                    &suffix_sums,
+                    0,
+                    self.bound,
+                    &mut HashMap::new(),
+                );
+                encoder.assert(root);
+            }
+            PbEncoding::Adder => encoder.adder(&self.terms, self.bound),
+        }
+    }
+}
+
+/// Formats the constraint in the OPB syntax.
+impl fmt::Display for PbConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (coefficient, literal) in &self.terms {
+            write!(f, "{} ", format_term(*coefficient, *literal))?;
+        }
+        write!(f, ">= {} ;", self.bound)
+    }
+}
+
+fn format_term(coefficient: Coefficient, literal: Literal) -> String {
+    let negation = if literal < 0 { "~" } else { "" };
+    format!("{:+} {}x{}", coefficient, negation, to_variable(literal))
+}
+
+fn is_true(literal: Literal, assignment: &Assignment) -> bool {
+    let value = assignment
+        .get(&to_variable(literal))
+        .copied()
+        .unwrap_or(false);
+    value == (literal > 0)
+}
+
+/// A node of an encoding, which is either known or stands for a literal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Node {
+    Constant(bool),
+    Literal(Literal),
+}
+
+struct Encoder<'a> {
+    expression: &'a mut Expression,
+    next_variable: &'a mut Variable,
+}
+
+impl Encoder<'_> {
+    fn new_literal(&mut self) -> Literal {
+        let literal = *self.next_variable as Literal;
+        *self.next_variable += 1;
+        literal
+    }
+
+    /// Adds the clause of the nodes, which is dropped if a node is true.
+    fn add_clause(&mut self, nodes: &[Node]) {
+        let mut clause = Clause::new();
+        for node in nodes {
+            match node {
+                Node::Constant(true) => return,
+                Node::Constant(false) => {}
+                Node::Literal(literal) => clause.insert_checked(*literal),
+            }
+        }
+        self.expression.add_clause(clause);
+    }
+
+    fn assert(&mut self, node: Node) {
+        self.add_clause(&[node]);
+    }
+
+    /// Returns the node holding if the terms from the position on sum to at least the bound. The
+    /// node only implies it, which is all the constraint needs once its root is asserted.
+    fn bdd_node(
+        &mut self,
+        terms: &[(Coefficient, Literal)],
+        suffix_sums: &[Coefficient],
+        position: usize,
+        bound: Coefficient,
+        nodes: &mut HashMap<(usize, Coefficient), Node>,
+    ) -> Node {
+        if bound <= 0 {
+            return Node::Constant(true);
+        }
+        if suffix_sums[position] < bound {
+            return Node::Constant(false);
+        }
+        if let Some(node) = nodes.get(&(position, bound)) {
+            return *node;
+        }
+        let (coefficient, literal) = terms[position];
+        let high = self.bdd_node(terms, suffix_sums, position + 1, bound - coefficient, nodes);
+        let low = self.bdd_node(terms, suffix_sums, position + 1, bound, nodes);
+        let node = if high == low {
+            high
+        } else {
+            // The low child implies the high one, so the node implies the high child, and the
+            // low child unless the literal is true
+            let node = Node::Literal(self.new_literal());
+            self.add_clause(&[negate(node), high]);
+            self.add_clause(&[negate(node), Node::Literal(literal), low]);
+            node
+        };
+        nodes.insert((position, bound), node);
+        node
+    }
+
+    /// Returns a literal equivalent to the exclusive or of the literals.
+    fn xor(&mut self, literals: &[Literal]) -> Literal {
+        let output = self.new_literal();
+        // The output makes the parity of all of them even
+        let mut inputs = literals.to_vec();
+        inputs.push(output);
+        let mut allocator = VariableAllocator::new(*self.next_variable - 1);
+        add_xor(self.expression, &mut allocator, &inputs, false);
+        *self.next_variable = allocator.get_num_variables() + 1;
+        output
+    }
+
+    /// Returns a literal equivalent to at least two of the literals holding, for two or three.
+    fn carry(&mut self, literals: &[Literal]) -> Literal {
+        let output = self.new_literal();
+        for (index, first) in literals.iter().enumerate() {
+            for second in &literals[index + 1..] {
+                self.expression
+                    .add_clause(Clause::from_literals(&[-first, -second, output]));
+                // Of three, two hold once any pair has one holding
+                if literals.len() == 3 {
+                    self.expression
+                        .add_clause(Clause::from_literals(&[*first, *second, -output]));
+                }
+            }
+            if literals.len() == 2 {
+                self.expression
+                    .add_clause(Clause::from_literals(&[*first, -output]));
+            }
+        }
+        output
+    }
+
+    /// Adds the sum of the terms in binary, and the comparison of the sum with the bound.
+    fn adder(&mut self, terms: &[(Coefficient, Literal)], bound: Coefficient) {
+        // The literals adding 2^bit to the sum, for every bit
+        let mut buckets: Vec<Vec<Literal>> = Vec::new();
+        for (coefficient, literal) in terms {
+            for bit in 0..Coefficient::BITS - coefficient.leading_zeros() {
+                if coefficient >> bit & 1 == 1 {
+                    let bit = bit as usize;
+                    if buckets.len() <= bit {
+                        buckets.resize(bit + 1, Vec::new());
+                    }
+                    buckets[bit].push(*literal);
+                }
+            }
+        }
+
+        let mut bits = Vec::new();
+        let mut bit = 0;
+        while bit < buckets.len() {
+            // Full adders, then a half adder, reduce the bucket to a single literal
+            while buckets[bit].len() > 1 {
+                let take = buckets[bit].len().min(3);
+                let inputs: Vec<Literal> = buckets[bit].drain(..take).collect();
+                let sum = self.xor(&inputs);
+                let carry = self.carry(&inputs);
+                buckets[bit].push(sum);
+                if buckets.len() <= bit + 1 {
+                    buckets.push(Vec::new());
+                }
+                buckets[bit + 1].push(carry);
+            }
+            bits.push(buckets[bit].first().copied());
+            bit += 1;
+        }
+
+        // The sum is below the bound if it agrees with it on the bits above one where the bound
+        // has a one and the sum a zero, which is ruled out for every such bit
+        let width = bits
+            .len()
+            .max((Coefficient::BITS - bound.leading_zeros()) as usize);
+        let sum_bit = |bit: usize| match bits.get(bit).copied().flatten() {
+            Some(literal) => Node::Literal(literal),
+            None => Node::Constant(false),
+        };
+        for bit in 0..width {
+            if bound >> bit & 1 == 0 {
+                continue;
+            }
+            let mut clause = vec![sum_bit(bit)];
+            for higher in bit + 1..width {
+                if bound >> higher & 1 == 1 {
+                    clause.push(negate(sum_bit(higher)));
+                } else {
+                    clause.push(sum_bit(higher));
+                }
+            }
+            self.add_clause(&clause);
+        }
+    }
+}
+
+fn negate(node: Node) -> Node {
+    match node {
+        Node::Constant(value) => Node::Constant(!value),
+        Node::Literal(literal) => Node::Literal(-literal),
+    }
+}
+
+/// A pseudo-Boolean instance: constraints that must hold, and optionally an objective, a sum of
+/// terms to minimize.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PbInstance {
+    num_variables: Variable,
+    constraints: Vec<PbConstraint>,
+    objective: Option<Vec<(Coefficient, Literal)>>,
+}
+
+impl PbInstance {
+    pub fn new() -> PbInstance {
+        PbInstance::default()
+    }
+
+    pub fn add_constraint(&mut self, constraint: PbConstraint) {
+        self.declare(constraint.get_terms());
+        self.constraints.push(constraint);
+    }
+
+    /// Adds the constraints that the sum of the terms equals the bound.
+    pub fn add_equality(&mut self, terms: &[(Coefficient, Literal)], bound: Coefficient) {
+        self.add_constraint(PbConstraint::at_least(terms, bound));
+        self.add_constraint(PbConstraint::at_most(terms, bound));
+    }
+
+    pub fn set_objective(&mut self, terms: &[(Coefficient, Literal)]) {
+        self.declare(terms);
+        self.objective = Some(terms.to_vec());
+    }
+
+    /// Makes sure the instance covers the variables up to `num_variables`, even if no constraint
+    /// mentions them.
+    pub fn set_num_variables(&mut self, num_variables: Variable) {
+        self.num_variables = self.num_variables.max(num_variables);
+    }
+
+    fn declare(&mut self, terms: &[(Coefficient, Literal)]) {
+        for (_, literal) in terms {
+            self.set_num_variables(to_variable(*literal));
+        }
+    }
+
+    pub fn get_num_variables(&self) -> Variable {
+        self.num_variables
+    }
+
+    pub fn get_constraints(&self) -> &[PbConstraint] {
+        &self.constraints
+    }
+
+    pub fn get_objective(&self) -> Option<&[(Coefficient, Literal)]> {
+        self.objective.as_deref()
+    }
+
+    /// Returns true if the assignment satisfies every constraint.
+    pub fn is_satisfied_by(&self, assignment: &Assignment) -> bool {
+        self.constraints
+            .iter()
+            .all(|constraint| constraint.is_satisfied_by(assignment))
+    }
+
+    /// Returns the value of the objective for the assignment, or 0 without an objective.
+    pub fn evaluate_objective(&self, assignment: &Assignment) -> Coefficient {
+        self.get_objective()
+            .unwrap_or(&[])
+            .iter()
+            .filter(|(_, literal)| is_true(*literal, assignment))
+            .map(|(coefficient, _)| coefficient)
+            .sum()
+    }
+
+    /// Encodes the constraints to an expression. The auxiliary variables of the encodings are
+    /// numbered after the variables of the instance.
+    pub fn to_expression(&self, encoding: PbEncoding) -> Expression {
+        let mut expression = Expression::new();
+        let mut next_variable = self.num_variables + 1;
+        for constraint in &self.constraints {
+            constraint.encode_into(encoding, &mut expression, &mut next_variable);
+        }
+        expression
+    }
+
+    /// Encodes the instance as MaxSAT: the constraints are the hard clauses, and violating a soft
+    /// clause pays for a term of the objective. Returns the weighted expression and the constant
+    /// to add to its cost for the value of the objective, which is negative for negative
+    /// coefficients.
+    pub fn to_weighted_expression(
+        &self,
+        encoding: PbEncoding,
+    ) -> (WeightedExpression, Coefficient) {
+        let mut weighted = WeightedExpression::new();
+        for clause in self.to_expression(encoding).get_clauses() {
+            weighted.add_hard_clause(clause.clone());
+        }
+        let mut offset = 0;
+        for (coefficient, literal) in self.get_objective().unwrap_or(&[]) {
+            // a·l is paid when ¬l is violated, and a·l = a - a·¬l for a negative
+            if *coefficient > 0 {
+                weighted
+                    .add_soft_clause(Clause::from_literals(&[-literal]), *coefficient as Weight);
+            } else if *coefficient < 0 {
+                weighted.add_soft_clause(
+                    Clause::from_literals(&[*literal]),
+                    coefficient.unsigned_abs(),
+                );
+                offset += coefficient;
+            }
+        }
+        (weighted, offset)
+    }
+}
+
+/// An assignment satisfying a pseudo-Boolean instance, with the value of its objective.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PbSolution {
+    pub model: Assignment,
+    /// The least value of the objective, or 0 without an objective.
+    pub objective: Coefficient,
+}
+
+/// Solves the instance, minimizing its objective through the core-guided MaxSAT search. Returns
+/// `None` if the constraints are unsatisfiable. The model covers the variables of the instance.
+pub fn solve_pb(instance: &PbInstance, encoding: PbEncoding) -> Option<PbSolution> {
+    let (weighted, offset) = instance.to_weighted_expression(encoding);
+    let solution = solve_maxsat_core_guided(&weighted)?;
+    let mut model = project_assignment(&solution.model, instance.num_variables);
+    for variable in 1..=instance.num_variables {
+        model.entry(variable).or_insert(false);
+    }
+    Some(PbSolution {
+        model,
+        objective: solution.cost as Coefficient + offset,
+    })
+}
+
+/// An error in an OPB file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpbParseError {
+    /// The 1-based line number of the problem, or 0 if the file cannot be read.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for OpbParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}", self.message);
+        }
+        write!(f, "Invalid OPB on line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for OpbParseError {}
+
+/// Returns the sum of the magnitudes of the coefficients and the bound, or `None` if it exceeds
+/// `Coefficient::MAX`. Below it, normalizing and summing the terms cannot overflow.
+fn magnitude(terms: &[(Coefficient, Literal)], bound: Coefficient) -> Option<Coefficient> {
+    terms
+        .iter()
+        .try_fold(bound.unsigned_abs(), |sum, (coefficient, _)| {
+            sum.checked_add(coefficient.unsigned_abs())
+        })
+        .and_then(|sum| Coefficient::try_from(sum).ok())
+}
+
+/// Parses a pseudo-Boolean instance from an OPB file.
+/// See [`parse_opb_str`] for the format.
+pub fn parse_opb(filename: &str) -> Result<PbInstance, OpbParseError> {
+    let file = std::fs::read_to_string(filename).map_err(|error| OpbParseError {
+        line: 0,
+        message: format!("Cannot read {}: {}", filename, error),
+    })?;
+    parse_opb_str(&file)
+}
+
+/// Parses a pseudo-Boolean instance in the linear OPB format. Lines starting with `*` are
+/// comments, and a `* #variable= n` header declares the variables. Then come an optional
+/// objective, `min: +3 x1 -2 x2 ;` (or `max:`, which is minimized negated), and the constraints,
+/// `+1 x1 +2 ~x3 >= 2 ;`, with the relations `>=`, `<=` and `=`. Statements end with a `;`, and
+/// may span several lines. Products of literals are not supported.
+pub fn parse_opb_str(contents: &str) -> Result<PbInstance, OpbParseError> {
+    let mut instance = PbInstance::new();
+    let mut statement = String::new();
+    let mut first_line = 1;
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('*') {
+            let mut parts = line.split_whitespace();
+            while let Some(part) = parts.next() {
+                if part == "#variable=" {
+                    let num_variables = parts
+                        .next()
+                        .and_then(|value| value.parse::<Variable>().ok())
+                        .ok_or_else(|| OpbParseError {
+                            line: index + 1,
+                            message: "Invalid number of variables".to_string(),
+                        })?;
+                    instance.set_num_variables(num_variables);
+                }
+            }
+            continue;
+        }
+
+        if statement.trim().is_empty() {
+            first_line = index + 1;
+        }
+        let mut rest = line;
+        while let Some(end) = rest.find(';') {
+            statement.push_str(&rest[..end]);
+            parse_statement(&statement, first_line, &mut instance)?;
+            statement.clear();
+            first_line = index + 1;
+            rest = &rest[end + 1..];
+        }
+        statement.push_str(rest);
+        statement.push(' ');
+    }
+
+    if !statement.trim().is_empty() {
+        return Err(OpbParseError {
+            line: first_line,
+            message: "Missing ; at the end of the statement".to_string(),
+        });
+    }
+    Ok(instance)
+}
+
+fn parse_statement(
+    statement: &str,
+    line: usize,
+    instance: &mut PbInstance,
+) -> Result<(), OpbParseError> {
+    let error = |message: String| Err(OpbParseError { line, message });
+    let mut tokens: Vec<&str> = statement.split_whitespace().collect();
+    if tokens.is_empty() {
+        return Ok(());
+    }
+
+    let objective = match tokens[0] {
+        "min:" => Some(1),
+        "max:" => Some(-1),
+        _ => None,
+    };
+    if let Some(sign) = objective {
+        if instance.objective.is_some() {
+            return error("The objective is given twice".to_string());
+        }
+        let terms = parse_terms(&tokens[1..], line)?;
+        if magnitude(&terms, 0).is_none() {
+            return error("The coefficients of the objective are too large".to_string());
+        }
+        let terms: Vec<(Coefficient, Literal)> = terms
+            .into_iter()
+            .map(|(coefficient, literal)| (sign * coefficient, literal))
+            .collect();
+        instance.set_objective(&terms);
+        return Ok(());
+    }
+
+    if tokens.len() < 2 {
+        return error(format!(
+            "Expected a relation and a bound in {}",
+            statement.trim()
+        ));
+    }
+    let bound = tokens.pop().unwrap();
+    let Ok(bound) = bound.parse::<Coefficient>() else {
+        return error(format!("Invalid bound {}", bound));
+    };
+    let relation = tokens.pop().unwrap();
+    let terms = parse_terms(&tokens, line)?;
+    if magnitude(&terms, bound).is_none() {
+        return error("The coefficients of the constraint are too large".to_string());
+    }
+    match relation {
+        ">=" => instance.add_constraint(PbConstraint::at_least(&terms, bound)),
+        "<=" => instance.add_constraint(PbConstraint::at_most(&terms, bound)),
+        "=" => instance.add_equality(&terms, bound),
+        _ => return error(format!("Invalid relation {}", relation)),
+    }
+    Ok(())
+}
+
+/// Parses the terms, each a coefficient followed by a literal, `x3` or `~x3`.
+fn parse_terms(tokens: &[&str], line: usize) -> Result<Vec<(Coefficient, Literal)>, OpbParseError> {
+    let error = |message: String| Err(OpbParseError { line, message });
+    if tokens.len() % 2 == 1 {
+        return error(format!(
+            "Expected terms of a coefficient and a literal in {}",
+            tokens.join(" ")
+        ));
+    }
+    let mut terms = Vec::new();
+    for term in tokens.chunks(2) {
+        let Ok(coefficient) = term[0].parse::<Coefficient>() else {
+            return error(format!("Invalid coefficient {}", term[0]));
+        };
+        let (negated, name) = match term[1].strip_prefix('~') {
+            Some(name) => (true, name),
+            None => (false, term[1]),
+        };
+        let variable = name
+            .strip_prefix('x')
+            .and_then(|number| number.parse::<Literal>().ok())
+            .filter(|variable| *variable > 0);
+        let Some(variable) = variable else {
+            return error(format!("Invalid literal {}", term[1]));
+        };
+        terms.push((coefficient, if negated { -variable } else { variable }));
+    }
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::solver::solve;
+
+    fn models(num_variables: Variable) -> impl Iterator<Item = Assignment> {
+        (0..1u32 << num_variables).map(move |mask| {
+            (1..=num_variables)
+                .map(|variable| (variable, mask >> (variable - 1) & 1 == 1))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_normalize() {
+        // 2x1 - 3x2 + x1 >= -1 is 3x1 + 3¬x2 >= 2, saturated to 2x1 + 2¬x2 >= 2
+        let constraint = PbConstraint::at_least(&[(2, 1), (-3, 2), (1, 1)], -1);
+        assert_eq!(constraint.get_terms(), &[(2, 1), (2, -2)]);
+        assert_eq!(constraint.get_bound(), 2);
+        assert_eq!(constraint.to_string(), "+2 x1 +2 ~x2 >= 2 ;");
+
+        // x1 + x2 <= 1 is ¬x1 + ¬x2 >= 1
+        let constraint = PbConstraint::at_most(&[(1, 1), (1, 2)], 1);
+        assert_eq!(constraint.get_terms(), &[(1, -1), (1, -2)]);
+        assert_eq!(constraint.get_bound(), 1);
+        assert!(PbConstraint::at_most(&[(1, 1)], 1).is_trivial());
+        assert!(PbConstraint::at_least(&[(1, 1), (2, 2)], 4).is_unsatisfiable());
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut rng = Rng::new(7);
+        for encoding in [PbEncoding::Bdd, PbEncoding::Adder] {
+            for _ in 0..40 {
+                let terms: Vec<(Coefficient, Literal)> = (1..=5)
+                    .map(|variable| {
+                        let coefficient = rng.next_below(13) as Coefficient - 6;
+                        let literal = if rng.next_bool() { variable } else { -variable };
+                        (coefficient, literal)
+                    })
+                    .collect();
+                let bound = rng.next_below(21) as Coefficient - 10;
+                let constraint = PbConstraint::at_least(&terms, bound);
+                let mut instance = PbInstance::new();
+                instance.add_constraint(constraint.clone());
+                instance.set_num_variables(5);
+                let expression = instance.to_expression(encoding);
+
+                // The encoding holds for exactly the models of the constraint
+                for model in models(5) {
+                    let mut fixed = expression.clone();
+                    for (variable, value) in &model {
+                        let literal = *variable as Literal;
+                        fixed.add_clause(Clause::from_literals(&[if *value {
+                            literal
+                        } else {
+                            -literal
+                        }]));
+                    }
+                    assert_eq!(
+                        solve(fixed, false, true).is_some(),
+                        constraint.is_satisfied_by(&model),
+                        "{} {:?} {:?}",
+                        constraint,
+                        encoding,
+                        model
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_opb() {
+        let contents = "* #variable= 4 #constraint= 3\n\
+                        min: +2 x1 -1 x2\n+3 x3 ;\n\
+                        +1 x1 +1 x2 +1 ~x3 >= 2 ;\n\
+                        * a comment\n\
+                        +1 x1 +1 x4 = 1 ; +1 x2 -1 x3 <= 0 ;\n";
+        let instance = parse_opb_str(contents).unwrap();
+        assert_eq!(instance.get_num_variables(), 4);
+        assert_eq!(
+            instance.get_objective(),
+            Some(&[(2, 1), (-1, 2), (3, 3)][..])
+        );
+        assert_eq!(instance.get_constraints().len(), 4);
+
+        let brute_force = models(4)
+            .filter(|model| instance.is_satisfied_by(model))
+            .map(|model| instance.evaluate_objective(&model))
+            .min();
+        for encoding in [PbEncoding::Bdd, PbEncoding::Adder] {
+            let solution = solve_pb(&instance, encoding).unwrap();
+            assert!(instance.is_satisfied_by(&solution.model));
+            assert_eq!(
+                instance.evaluate_objective(&solution.model),
+                solution.objective
+            );
+            assert_eq!(Some(solution.objective), brute_force);
+        }
+
+        // Without an objective, and unsatisfiable
+        let instance = parse_opb_str("+2 x1 +1 x2 >= 3 ;\n+1 ~x1 >= 1 ;\n").unwrap();
+        assert_eq!(solve_pb(&instance, PbEncoding::Adder), None);
+
+        assert_eq!(
+            parse_opb_str("+1 x1\n+1 y2 >= 1 ;\n"),
+            Err(OpbParseError {
+                line: 1,
+                message: "Invalid literal y2".to_string()
+            })
+        );
+        assert_eq!(parse_opb_str("+1 x1 x2 >= 1 ;").unwrap_err().line, 1);
+        assert_eq!(parse_opb_str("+1 x1 >= 1").unwrap_err().line, 1);
+        assert_eq!(parse_opb("examples/missing.opb").unwrap_err().line, 0);
+    }
+
+    #[test]
+    fn test_parse_opb_large_coefficients() {
+        // Up to Coefficient::MAX in all, the arithmetic of the constraints fits
+        let instance = parse_opb_str(
+            "+3074457345618258602 x1 +3074457345618258602 ~x2 >= 3074457345618258603 ;\n\
+             +9223372036854775807 x1 >= 0 ;",
+        )
+        .unwrap();
+        let constraint = &instance.get_constraints()[0];
+        assert!(!constraint.is_trivial() && !constraint.is_unsatisfiable());
+        let model = solve_pb(&instance, PbEncoding::Bdd).unwrap().model;
+        assert!(model[&1] && !model[&2]);
+
+        for contents in [
+            "+9223372036854775807 x1 +1 x2 >= 1 ;",
+            "+1 x1 >= -9223372036854775808 ;",
+            "-9223372036854775808 ~x1 >= 1 ;",
+            "max: -9223372036854775808 x1 ;",
+            "min: +9223372036854775807 x1 +9223372036854775807 x2 ;",
+        ] {
+            assert!(
+                parse_opb_str(contents)
+                    .unwrap_err()
+                    .message
+                    .contains("too large"),
+                "{}",
+                contents
+            );
+        }
+    }
+}
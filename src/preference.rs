@@ -0,0 +1,143 @@
+//! Models that follow preferred values of the variables as far as the constraints allow, as
+//! needed by interactive configuration, where the best solution is the one closest to the
+//! current choices of the user.
+
+use crate::cnf::{Assignment, Literal, Variable};
+use crate::dpll::solve_dpll;
+use crate::expression::Expression;
+use crate::solver::solve_with_assumptions;
+
+/// Preferred values of variables, each with a weight telling how much it matters.
+#[derive(Clone, Debug, Default)]
+pub struct Preferences {
+    preferences: Vec<(Variable, bool, u64)>,
+}
+
+impl Preferences {
+    pub fn new() -> Preferences {
+        Preferences::default()
+    }
+
+    /// Prefers the variable to take the value, replacing an earlier preference for it.
+    pub fn prefer(&mut self, variable: Variable, value: bool, weight: u64) {
+        self.preferences.retain(|(other, _, _)| *other != variable);
+        self.preferences.push((variable, value, weight));
+    }
+
+    pub fn get_num_preferences(&self) -> usize {
+        self.preferences.len()
+    }
+
+    /// Returns the total weight of the preferences the model follows.
+    pub fn get_score(&self, model: &Assignment) -> u64 {
+        self.preferences
+            .iter()
+            .filter(|(variable, value, _)| model.get(variable) == Some(value))
+            .map(|(_, _, weight)| weight)
+            .sum()
+    }
+
+    /// Returns the variables whose value in the model differs from the preferred one.
+    pub fn get_violations(&self, model: &Assignment) -> Vec<Variable> {
+        self.preferences
+            .iter()
+            .filter(|(variable, value, _)| model.get(variable) != Some(value))
+            .map(|(variable, _, _)| *variable)
+            .collect()
+    }
+}
+
+fn to_literal(variable: Variable, value: bool) -> Literal {
+    if value {
+        variable as Literal
+    } else {
+        -(variable as Literal)
+    }
+}
+
+/// Returns a model of the expression close to the preferences, or `None` if it is unsatisfiable.
+///
+/// The search first tries the preferred value of every variable it branches on. The preferences
+/// are then gone through from the heaviest to the lightest, keeping the ones the model follows.
+/// For up to `max_improvements` of the ones it breaks, one more solve looks for a model following
+/// the preference together with the heavier ones kept so far, and keeps it if there is one. With
+/// enough improvements, every broken preference conflicts with heavier preferences the model
+/// follows, but the score is not necessarily the best possible.
+pub fn solve_with_preferences(
+    expression: &Expression,
+    preferences: &Preferences,
+    max_improvements: usize,
+) -> Option<Assignment> {
+    let mut biased = expression.clone();
+    for (variable, value, _) in &preferences.preferences {
+        biased.set_phase(*variable, *value);
+    }
+
+    let mut working = biased.clone();
+    working.optimize();
+    let mut model = solve_dpll(&mut working)?;
+
+    let mut ordered = preferences.preferences.clone();
+    ordered.sort_by_key(|(_, _, weight)| std::cmp::Reverse(*weight));
+    let mut kept: Vec<Literal> = Vec::new();
+    let mut remaining = max_improvements;
+    for (variable, value, _) in &ordered {
+        let literal = to_literal(*variable, *value);
+        if model.get(variable) == Some(value) {
+            kept.push(literal);
+            continue;
+        }
+        if remaining == 0 {
+            continue;
+        }
+
+        remaining -= 1;
+        kept.push(literal);
+        match solve_with_assumptions(&biased, &kept) {
+            Some(better) => model = better,
+            None => {
+                kept.pop();
+            }
+        }
+    }
+
+    Some(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+
+    #[test]
+    fn test_solve_with_preferences() {
+        // At most one of 1, 2 and 3, and 4 if 1
+        let expression = parse_dimacs_str("p cnf 4 4\n-1 -2 0\n-1 -3 0\n-2 -3 0\n-1 4 0\n");
+        let mut preferences = Preferences::new();
+        preferences.prefer(1, true, 1);
+        preferences.prefer(2, true, 5);
+        preferences.prefer(4, false, 2);
+
+        let model = solve_with_preferences(&expression, &preferences, 10).unwrap();
+        assert!(expression.is_satisfied_by(&model));
+        assert!(model[&2] && !model[&4]);
+        assert_eq!(preferences.get_score(&model), 7);
+        assert_eq!(preferences.get_violations(&model), vec![1]);
+
+        preferences.prefer(2, false, 5);
+        let model = solve_with_preferences(&expression, &preferences, 10).unwrap();
+        assert!(!model[&2] && !model[&4]);
+        assert_eq!(preferences.get_score(&model), 7);
+    }
+
+    #[test]
+    fn test_unconstrained_variables_take_the_preferred_value() {
+        let expression = parse_dimacs_str("p cnf 3 1\n1 2 3 0\n");
+        let mut preferences = Preferences::new();
+        for variable in 1..=3 {
+            preferences.prefer(variable, variable == 3, 1);
+        }
+        let model = solve_with_preferences(&expression, &preferences, 0).unwrap();
+        assert_eq!(preferences.get_score(&model), 3);
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Where the time of a solve went. Phases that a solver does not have (such as conflict analysis for
+/// DPLL, which simply backtracks) stay at zero.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PhaseTimes {
+    pub parsing: Duration,
+    pub preprocessing: Duration,
+    pub propagation: Duration,
+    pub decision: Duration,
+    pub conflict_analysis: Duration,
+    pub backtracking: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Phase {
+    Parsing,
+    Preprocessing,
+    Propagation,
+    Decision,
+    ConflictAnalysis,
+    Backtracking,
+}
+
+impl PhaseTimes {
+    pub fn get(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Parsing => self.parsing,
+            Phase::Preprocessing => self.preprocessing,
+            Phase::Propagation => self.propagation,
+            Phase::Decision => self.decision,
+            Phase::ConflictAnalysis => self.conflict_analysis,
+            Phase::Backtracking => self.backtracking,
+        }
+    }
+
+    pub fn add(&mut self, phase: Phase, duration: Duration) {
+        let time = match phase {
+            Phase::Parsing => &mut self.parsing,
+            Phase::Preprocessing => &mut self.preprocessing,
+            Phase::Propagation => &mut self.propagation,
+            Phase::Decision => &mut self.decision,
+            Phase::ConflictAnalysis => &mut self.conflict_analysis,
+            Phase::Backtracking => &mut self.backtracking,
+        };
+        *time += duration;
+    }
+
+    /// Adds the time elapsed since `start`, if it was started (see [`start_timer`]).
+    pub fn stop_timer(&mut self, phase: Phase, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.add(phase, start.elapsed());
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        PHASES.iter().map(|phase| self.get(*phase)).sum()
+    }
+}
+
+pub const PHASES: [Phase; 6] = [
+    Phase::Parsing,
+    Phase::Preprocessing,
+    Phase::Propagation,
+    Phase::Decision,
+    Phase::ConflictAnalysis,
+    Phase::Backtracking,
+];
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Phase::Parsing => "parsing",
+            Phase::Preprocessing => "preprocessing",
+            Phase::Propagation => "propagation",
+            Phase::Decision => "decision",
+            Phase::ConflictAnalysis => "conflict analysis",
+            Phase::Backtracking => "backtracking",
+        };
+        f.pad(name)
+    }
+}
+
+/// Formats the breakdown as DIMACS comment lines, one per phase with its share of the total.
+impl fmt::Display for PhaseTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total().as_secs_f64();
+        for (index, phase) in PHASES.iter().enumerate() {
+            let time = self.get(*phase);
+            let share = if total > 0.0 {
+                100.0 * time.as_secs_f64() / total
+            } else {
+                0.0
+            };
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "c {:<18} {:>12.3} ms {:>5.1}%",
+                phase,
+                time.as_secs_f64() * 1000.0,
+                share
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Starts a timer if profiling is enabled. Timers that were not started cost nothing to stop.
+pub fn start_timer(enabled: bool) -> Option<Instant> {
+    enabled.then(Instant::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_times() {
+        let mut times = PhaseTimes::default();
+        times.add(Phase::Propagation, Duration::from_millis(3));
+        times.add(Phase::Decision, Duration::from_millis(1));
+        times.stop_timer(Phase::Backtracking, start_timer(false));
+
+        assert_eq!(times.total(), Duration::from_millis(4));
+        assert_eq!(times.backtracking, Duration::ZERO);
+        let report = times.to_string();
+        assert_eq!(report.lines().count(), 6);
+        assert!(report.contains("c propagation"));
+        assert!(report.contains("75.0%"));
+    }
+}
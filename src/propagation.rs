@@ -0,0 +1,518 @@
+//! A standalone unit propagator over a database of clauses, using two watched literals. Binary
+//! clauses are only kept in implication lists instead, an entry for each of their two literals, so
+//! they take no room in the clause arena and propagate without reading it.
+//!
+//! Unlike [`Expression`](crate::expression::Expression), which rewrites its clauses during the
+//! search, the propagator keeps every clause intact and only records which literals are assigned
+//! and why, so clauses can be added, deactivated and reactivated between propagations. This is what
+//! proof checking needs, where the same database is propagated many times under different
+//! assumptions. The [`CdclSolver`](crate::cdcl::CdclSolver) searches on a propagator too, with
+//! its decisions as decision levels of the trail.
+
+use crate::arena::{ClauseAlloc, ClauseArena, SystemAlloc};
+use crate::cnf::{to_variable, Literal, Variable};
+use crate::memory::{vec_bytes, MemoryUsage};
+use crate::trail::Trail;
+
+/// The reason a propagation failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Conflict {
+    /// Every literal of the clause with this id is false.
+    Clause(usize),
+    /// The assumed literal was already false.
+    Assumption(Literal),
+}
+
+/// Where the literals of a clause are kept.
+#[derive(Clone, Copy, Debug)]
+enum Location {
+    /// The index of the clause in the arena.
+    Arena(usize),
+    /// The implication list and the position in it of one of the two entries of a binary clause.
+    Binary(usize, usize),
+}
+
+/// A binary clause in the implication list of one of its literals, which is the second one.
+#[derive(Clone, Copy, Debug)]
+struct Implication {
+    /// The literal that must be true once the other is false, then the other.
+    literals: [Literal; 2],
+    id: usize,
+}
+
+/// The propagator stores its clauses other than the binary ones in a [`ClauseArena`], allocated
+/// with `A`.
+#[derive(Clone, Debug, Default)]
+pub struct Propagator<A: ClauseAlloc = SystemAlloc> {
+    clauses: ClauseArena<A>,
+    locations: Vec<Location>,
+    active: Vec<bool>,
+    /// For every literal, the clauses watching it: the first two literals of a clause are watched.
+    watches: Vec<Vec<usize>>,
+    /// For every literal, the binary clauses implying another literal once it is false.
+    binaries: Vec<Vec<Implication>>,
+    /// The units are kept apart, since they have no second literal to watch.
+    units: Vec<usize>,
+    num_empty: usize,
+
+    /// The reason of an implied literal is a clause whose first literal is the implied one, and
+    /// assumptions have none.
+    trail: Trail,
+    num_propagated: usize,
+}
+
+#[inline]
+pub(crate) fn watch_index(literal: Literal) -> usize {
+    2 * to_variable(literal) as usize + (literal < 0) as usize
+}
+
+impl Propagator {
+    pub fn new() -> Propagator {
+        Propagator::default()
+    }
+}
+
+impl<A: ClauseAlloc> Propagator<A> {
+    /// Creates a propagator storing its clauses in memory from the allocator.
+    pub fn with_allocator(alloc: A) -> Propagator<A> {
+        Propagator {
+            clauses: ClauseArena::with_allocator(alloc),
+            locations: Vec::new(),
+            active: Vec::new(),
+            watches: Vec::new(),
+            binaries: Vec::new(),
+            units: Vec::new(),
+            num_empty: 0,
+            trail: Trail::new(),
+            num_propagated: 0,
+        }
+    }
+
+    /// Adds an (active) clause and returns its id. Ids are assigned consecutively from 0.
+    /// Duplicate literals are removed.
+    pub fn add_clause(&mut self, literals: &[Literal]) -> usize {
+        let mut clause: Vec<Literal> = Vec::with_capacity(literals.len());
+        for literal in literals {
+            if !clause.contains(literal) {
+                clause.push(*literal);
+            }
+        }
+
+        let id = self.locations.len();
+        for literal in &clause {
+            self.reserve(to_variable(*literal));
+        }
+        match clause.len() {
+            0 => self.num_empty += 1,
+            1 => self.units.push(id),
+            2 => {
+                let (first, second) = (clause[0], clause[1]);
+                self.binaries[watch_index(first)].push(Implication {
+                    literals: [second, first],
+                    id,
+                });
+                let implications = &mut self.binaries[watch_index(second)];
+                implications.push(Implication {
+                    literals: [first, second],
+                    id,
+                });
+                self.locations.push(Location::Binary(
+                    watch_index(second),
+                    implications.len() - 1,
+                ));
+                self.active.push(true);
+                return id;
+            }
+            _ => {
+                self.watches[watch_index(clause[0])].push(id);
+                self.watches[watch_index(clause[1])].push(id);
+            }
+        }
+        self.locations
+            .push(Location::Arena(self.clauses.push(&clause)));
+        self.active.push(true);
+        id
+    }
+
+    /// Makes room for the variable.
+    pub(crate) fn reserve(&mut self, variable: Variable) {
+        let size = variable as usize + 1;
+        if self.watches.len() < 2 * size {
+            self.watches.resize(2 * size, Vec::new());
+            self.binaries.resize(2 * size, Vec::new());
+        }
+    }
+
+    /// Returns the number of variables there is room for, counting the unused variable 0.
+    pub(crate) fn get_variable_capacity(&self) -> usize {
+        self.watches.len() / 2
+    }
+
+    /// Returns the literals of the clause. Their order changes as the watched literals move.
+    pub fn get_clause(&self, id: usize) -> &[Literal] {
+        match self.locations[id] {
+            Location::Arena(index) => self.clauses.get(index),
+            Location::Binary(list, position) => &self.binaries[list][position].literals,
+        }
+    }
+
+    /// Returns the index in the arena of a clause that is not binary.
+    fn get_arena_index(&self, id: usize) -> usize {
+        match self.locations[id] {
+            Location::Arena(index) => index,
+            Location::Binary(..) => unreachable!("Binary clauses are not in the arena"),
+        }
+    }
+
+    pub fn get_num_clauses(&self) -> usize {
+        self.locations.len()
+    }
+
+    pub fn is_active(&self, id: usize) -> bool {
+        self.active[id]
+    }
+
+    /// Activates or deactivates the clause. Inactive clauses are ignored by propagation.
+    /// Changes only take effect from the next [`Propagator::propagate_units`].
+    pub fn set_active(&mut self, id: usize, active: bool) {
+        if self.active[id] != active && self.get_clause(id).is_empty() {
+            if active {
+                self.num_empty += 1;
+            } else {
+                self.num_empty -= 1;
+            }
+        }
+        self.active[id] = active;
+    }
+
+    /// Returns the value of the literal under the current assignment.
+    pub fn value(&self, literal: Literal) -> Option<bool> {
+        self.trail.literal_value(literal)
+    }
+
+    /// Returns the clause that implied the value of the variable, or `None` if the variable was
+    /// assumed or is unassigned.
+    pub fn reason(&self, variable: Variable) -> Option<usize> {
+        self.trail.reason(variable)
+    }
+
+    /// Returns the assigned literals, in the order they were assigned.
+    pub fn get_trail(&self) -> &[Literal] {
+        self.trail.get_literals()
+    }
+
+    /// Returns the assignment with its decision levels and reasons.
+    pub(crate) fn get_assignment(&self) -> &Trail {
+        &self.trail
+    }
+
+    /// Clears the assignment.
+    pub fn reset(&mut self) {
+        self.trail.truncate(0);
+        self.num_propagated = 0;
+    }
+
+    /// Starts a new decision level of the trail, for a search deciding on the propagator.
+    pub(crate) fn new_decision_level(&mut self) {
+        self.trail.new_decision_level();
+    }
+
+    /// Undoes the assignments above the decision level.
+    pub(crate) fn backtrack(&mut self, level: usize) {
+        self.trail.backtrack(level);
+        self.num_propagated = self.num_propagated.min(self.trail.len());
+    }
+
+    /// Returns true if every assignment has been propagated.
+    pub(crate) fn is_propagated(&self) -> bool {
+        self.num_propagated == self.trail.len()
+    }
+
+    /// Clears the assignment, then assigns the active unit clauses and propagates them.
+    pub fn propagate_units(&mut self) -> Option<Conflict> {
+        self.reset();
+        if self.num_empty > 0 {
+            let id = (0..self.get_num_clauses())
+                .find(|id| self.active[*id] && self.get_clause(*id).is_empty())
+                .unwrap();
+            return Some(Conflict::Clause(id));
+        }
+
+        for index in 0..self.units.len() {
+            let id = self.units[index];
+            if !self.active[id] {
+                continue;
+            }
+            let literal = self.get_clause(id)[0];
+            match self.value(literal) {
+                Some(true) => {}
+                Some(false) => return Some(Conflict::Clause(id)),
+                None => self.enqueue(literal, Some(id)),
+            }
+        }
+        self.propagate().map(Conflict::Clause)
+    }
+
+    /// Assigns the literal as an assumption (without a reason) and propagates it.
+    pub fn assume(&mut self, literal: Literal) -> Option<Conflict> {
+        match self.value(literal) {
+            Some(true) => None,
+            Some(false) => Some(Conflict::Assumption(literal)),
+            None => {
+                self.reserve(to_variable(literal));
+                self.enqueue(literal, None);
+                self.propagate().map(Conflict::Clause)
+            }
+        }
+    }
+
+    /// Makes the unassigned literal true at the current decision level, to be propagated by the
+    /// next [`Propagator::propagate`]. The reason, if any, starts with the literal.
+    pub(crate) fn enqueue(&mut self, literal: Literal, reason: Option<usize>) {
+        self.reserve(to_variable(literal));
+        self.trail.assign(literal, reason);
+    }
+
+    /// Propagates the assignments that have not been propagated yet, returning the clause all of
+    /// whose literals are false on a conflict.
+    pub(crate) fn propagate(&mut self) -> Option<usize> {
+        while self.num_propagated < self.trail.len() {
+            let falsified = -self.trail.get_literals()[self.num_propagated];
+            self.num_propagated += 1;
+
+            let list = watch_index(falsified);
+            for position in 0..self.binaries[list].len() {
+                let Implication { literals, id } = self.binaries[list][position];
+                if !self.active[id] {
+                    continue;
+                }
+                match self.value(literals[0]) {
+                    Some(true) => {}
+                    Some(false) => return Some(id),
+                    None => {
+                        // The reason of an implied literal starts with it, as this entry does
+                        self.locations[id] = Location::Binary(list, position);
+                        self.enqueue(literals[0], Some(id));
+                    }
+                }
+            }
+
+            let mut watchers = std::mem::take(&mut self.watches[watch_index(falsified)]);
+            let mut conflict = None;
+            let mut index = 0;
+            while index < watchers.len() {
+                let id = watchers[index];
+                if !self.active[id] || conflict.is_some() {
+                    index += 1;
+                    continue;
+                }
+                let clause = self.get_arena_index(id);
+
+                // Keep the falsified watch in the second position
+                if self.clauses.get(clause)[0] == falsified {
+                    self.clauses.get_mut(clause).swap(0, 1);
+                }
+                let first = self.clauses.get(clause)[0];
+                if self.value(first) == Some(true) {
+                    index += 1;
+                    continue;
+                }
+
+                let replacement = (2..self.clauses.get(clause).len())
+                    .find(|k| self.value(self.clauses.get(clause)[*k]) != Some(false));
+                match replacement {
+                    Some(k) => {
+                        self.clauses.get_mut(clause).swap(1, k);
+                        self.watches[watch_index(self.clauses.get(clause)[1])].push(id);
+                        watchers.swap_remove(index);
+                    }
+                    None => {
+                        if self.value(first).is_none() {
+                            self.enqueue(first, Some(id));
+                        } else {
+                            conflict = Some(id);
+                        }
+                        index += 1;
+                    }
+                }
+            }
+            self.watches[watch_index(falsified)] = watchers;
+
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+
+        None
+    }
+
+    /// Removes the clauses marked as deleted, none of which may be the reason of an assignment,
+    /// and renumbers the others in order. Returns the new id of every clause, or `None` for the
+    /// removed ones.
+    pub(crate) fn remove_clauses(&mut self, deleted: &[bool]) -> Vec<Option<usize>> {
+        self.num_empty -= (0..self.get_num_clauses())
+            .filter(|id| deleted[*id] && self.active[*id] && self.get_clause(*id).is_empty())
+            .count();
+        let mut num_kept = 0;
+        let ids: Vec<Option<usize>> = deleted
+            .iter()
+            .map(|deleted| {
+                let id = (!deleted).then_some(num_kept);
+                num_kept += id.is_some() as usize;
+                id
+            })
+            .collect();
+
+        let mut in_arena = Vec::with_capacity(self.clauses.len());
+        for (id, location) in self.locations.iter().enumerate() {
+            if let Location::Arena(_) = location {
+                in_arena.push(ids[id].is_some());
+            }
+        }
+        let indices = self.clauses.retain(|index| in_arena[index]);
+        self.locations = self
+            .locations
+            .iter()
+            .zip(&ids)
+            .filter(|(_, id)| id.is_some())
+            .map(|(location, _)| match location {
+                Location::Arena(index) => Location::Arena(indices[*index].unwrap()),
+                binary => *binary,
+            })
+            .collect();
+
+        let renumber = |id: &mut usize| match ids[*id] {
+            Some(new_id) => {
+                *id = new_id;
+                true
+            }
+            None => false,
+        };
+        for watchers in &mut self.watches {
+            watchers.retain_mut(renumber);
+        }
+        for implications in &mut self.binaries {
+            implications.retain_mut(|implication| renumber(&mut implication.id));
+        }
+        self.units.retain_mut(renumber);
+        let mut id = 0;
+        self.active.retain(|_| {
+            id += 1;
+            !deleted[id - 1]
+        });
+        self.trail.map_reasons(|id| ids[id].unwrap());
+
+        // The binary clauses moved within their lists, and a reason still starts with the
+        // literal it implied
+        let mut placed = vec![false; self.locations.len()];
+        for (list, implications) in self.binaries.iter().enumerate() {
+            for (position, implication) in implications.iter().enumerate() {
+                let id = implication.id;
+                let implied = to_variable(implication.literals[0]);
+                if !placed[id] || self.trail.reason(implied) == Some(id) {
+                    self.locations[id] = Location::Binary(list, position);
+                    placed[id] = true;
+                }
+            }
+        }
+        ids
+    }
+
+    /// Returns the memory held by the clauses, their watches and implications, and the trail.
+    pub(crate) fn memory_report(&self) -> MemoryUsage {
+        MemoryUsage {
+            clause_database: self.clauses.get_allocated_bytes()
+                + vec_bytes(&self.locations)
+                + vec_bytes(&self.active)
+                + vec_bytes(&self.units),
+            occurrence_lists: vec_bytes(&self.watches)
+                + self.watches.iter().map(vec_bytes).sum::<usize>()
+                + vec_bytes(&self.binaries)
+                + self.binaries.iter().map(vec_bytes).sum::<usize>(),
+            learned_clauses: 0,
+            trail: self.trail.memory_bytes(),
+            other: 0,
+        }
+    }
+
+    /// Returns the ids of the clauses involved in deriving the conflict, by following the reasons
+    /// of the assignments back to the assumptions. The ids are in no particular order.
+    pub fn analyze(&self, conflict: Conflict) -> Vec<usize> {
+        let mut seen = vec![false; self.get_variable_capacity()];
+        let mut involved = Vec::new();
+        let mut pending: Vec<Literal> = match conflict {
+            Conflict::Clause(id) => {
+                involved.push(id);
+                self.get_clause(id).to_vec()
+            }
+            Conflict::Assumption(literal) => vec![literal],
+        };
+
+        while let Some(literal) = pending.pop() {
+            let variable = to_variable(literal);
+            if seen[variable as usize] {
+                continue;
+            }
+            seen[variable as usize] = true;
+            if let Some(reason) = self.trail.reason(variable) {
+                involved.push(reason);
+                pending.extend(
+                    self.get_clause(reason)
+                        .iter()
+                        .filter(|l| to_variable(**l) != variable),
+                );
+            }
+        }
+
+        involved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_propagation_and_analysis() {
+        let mut propagator = Propagator::new();
+        let a = propagator.add_clause(&[1]);
+        let b = propagator.add_clause(&[-1, 2]);
+        let c = propagator.add_clause(&[-2, -3, 4]);
+        let d = propagator.add_clause(&[-4, -2, 5]);
+        propagator.add_clause(&[6, 7]);
+
+        assert_eq!(propagator.propagate_units(), None);
+        assert_eq!(propagator.get_trail(), &[1, 2]);
+        assert_eq!(propagator.assume(3), None);
+        assert_eq!(propagator.value(5), Some(true));
+
+        let conflict = propagator.assume(-5).unwrap();
+        assert_eq!(conflict, Conflict::Assumption(-5));
+        let mut involved = propagator.analyze(conflict);
+        involved.sort_unstable();
+        assert_eq!(involved, vec![a, b, c, d]);
+
+        // Without the clause implying 2, nothing follows
+        propagator.set_active(b, false);
+        assert_eq!(propagator.propagate_units(), None);
+        assert_eq!(propagator.assume(3), None);
+        assert_eq!(propagator.value(5), None);
+
+        propagator.set_active(b, true);
+        let e = propagator.add_clause(&[-5]);
+        let f = propagator.add_clause(&[3]);
+        let conflict = propagator.propagate_units().unwrap();
+        let mut involved = propagator.analyze(conflict);
+        involved.sort_unstable();
+        assert_eq!(involved, vec![a, b, c, d, e, f]);
+
+        // Removing a clause renumbers the ones after it
+        let ids = propagator.remove_clauses(&[false, false, false, false, true, false, false]);
+        assert_eq!(ids[f], Some(f - 1));
+        assert_eq!(propagator.get_num_clauses(), 6);
+        let conflict = propagator.propagate_units().unwrap();
+        let mut involved = propagator.analyze(conflict);
+        involved.sort_unstable();
+        assert_eq!(involved, vec![a, b, c, d, e - 1, f - 1]);
+    }
+}
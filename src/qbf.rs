@@ -0,0 +1,390 @@
+use hashbrown::HashSet;
+
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable, CNF};
+use crate::dimacs_parser::{parse_qdimacs, ParseError};
+use crate::dpll::SearchResult;
+use crate::expression::Expression;
+use crate::formula::Formula;
+use crate::solver::{solve, Solver};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Quantifier {
+    Exists,
+    Forall,
+}
+
+/// A block of variables sharing the same quantifier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QuantifierBlock {
+    pub quantifier: Quantifier,
+    pub variables: Vec<Variable>,
+}
+
+/// A quantified boolean formula in prenex normal form: a quantifier prefix (outermost block first)
+/// and a CNF matrix. Variables of the matrix that do not appear in the prefix are free, and are
+/// treated as existentially quantified in the outermost block (as specified by QDIMACS).
+#[derive(Clone)]
+pub struct QuantifiedExpression {
+    prefix: Vec<QuantifierBlock>,
+    matrix: Expression,
+}
+
+/// The outcome of solving a 2QBF instance.
+#[derive(Clone, Debug)]
+pub enum QbfResult {
+    /// For every assignment of the universal variables there is a satisfying existential assignment.
+    True,
+    /// The formula is false, witnessed by an assignment of the universal variables
+    /// under which the matrix is unsatisfiable.
+    False(Assignment),
+}
+
+impl QuantifiedExpression {
+    pub fn new(prefix: Vec<QuantifierBlock>, matrix: Expression) -> QuantifiedExpression {
+        QuantifiedExpression { prefix, matrix }
+    }
+
+    pub fn from_qdimacs_file(file_name: &str) -> Result<QuantifiedExpression, ParseError> {
+        parse_qdimacs(file_name)
+    }
+
+    pub fn get_prefix(&self) -> &Vec<QuantifierBlock> {
+        &self.prefix
+    }
+
+    pub fn get_matrix(&self) -> &Expression {
+        &self.matrix
+    }
+
+    /// Returns the variables of the matrix that are not bound by the prefix.
+    pub fn get_free_variables(&self) -> Vec<Variable> {
+        let bound: HashSet<Variable> = self
+            .prefix
+            .iter()
+            .flat_map(|block| block.variables.iter().copied())
+            .collect();
+
+        let mut free: Vec<Variable> = self
+            .matrix
+            .get_clauses()
+            .iter()
+            .flat_map(|clause| clause.literals().clone())
+            .map(to_variable)
+            .filter(|variable| !bound.contains(variable))
+            .collect::<HashSet<Variable>>()
+            .into_iter()
+            .collect();
+        free.sort_unstable();
+        free
+    }
+
+    /// Splits the prefix into its universal and existential variables if it has the
+    /// shape `∀X ∃Y` (either block may be empty), or returns `None` otherwise.
+    pub fn as_two_qbf(&self) -> Option<(Vec<Variable>, Vec<Variable>)> {
+        let mut universal = Vec::new();
+        let mut existential = Vec::new();
+
+        for block in self
+            .prefix
+            .iter()
+            .filter(|block| !block.variables.is_empty())
+        {
+            match block.quantifier {
+                // A universal block is only allowed before any existential one
+                Quantifier::Forall if existential.is_empty() => {
+                    universal.extend(block.variables.iter().copied())
+                }
+                Quantifier::Exists => existential.extend(block.variables.iter().copied()),
+                Quantifier::Forall => return None,
+            }
+        }
+
+        // Free variables are existential in the outermost block, which must then not precede a universal block
+        let free = self.get_free_variables();
+        if !free.is_empty() && !universal.is_empty() {
+            return None;
+        }
+        existential.extend(free);
+
+        Some((universal, existential))
+    }
+
+    pub fn is_two_qbf(&self) -> bool {
+        self.as_two_qbf().is_some()
+    }
+}
+
+/// Solves a `∀X ∃Y φ` formula with counterexample-guided abstraction refinement.
+///
+/// An abstraction over the universal variables proposes candidate counterexamples `x`, and the
+/// matrix is checked under each candidate. If `φ(x, Y)` is satisfied by some `y`, the abstraction is
+/// refined so that every later candidate must falsify `φ(·, y)`. The formula is true once the
+/// abstraction runs out of candidates.
+///
+/// Panics if the prefix is not of the `∀∃` shape (see [`QuantifiedExpression::as_two_qbf`]).
+pub fn solve_2qbf(expression: &QuantifiedExpression) -> QbfResult {
+    let (universal, _) = expression
+        .as_two_qbf()
+        .expect("The quantifier prefix is not of the form forall-exists");
+    let universal_set: HashSet<Variable> = universal.iter().copied().collect();
+    let matrix_clauses = expression.matrix.get_clauses();
+
+    let mut abstraction = Expression::new();
+    let mut next_auxiliary = expression.matrix.get_max_variable() + 1;
+
+    loop {
+        // Find a candidate counterexample over the universal variables
+        let candidate = match solve(abstraction.clone(), false, false) {
+            Some(assignment) => assignment,
+            None => return QbfResult::True,
+        };
+
+        let mut counterexample = Assignment::new();
+        for variable in &universal {
+            counterexample.insert(*variable, candidate.get(variable).copied().unwrap_or(false));
+        }
+
+        // Check whether the existential player can answer the candidate
+        let mut instance = expression.matrix.clone();
+        for (variable, value) in &counterexample {
+            let mut unit = Clause::new();
+            unit.insert(if *value {
+                *variable as Literal
+            } else {
+                -(*variable as Literal)
+            });
+            instance.add_clause(unit);
+        }
+
+        let response = match solve(instance, false, false) {
+            Some(assignment) => assignment,
+            None => return QbfResult::False(counterexample),
+        };
+
+        // Refine: later candidates must falsify some clause not already satisfied by the response
+        let mut refinement = Clause::new();
+        for clause in &matrix_clauses {
+            let satisfied_by_response = clause.literals().iter().any(|literal| {
+                let variable = to_variable(*literal);
+                !universal_set.contains(&variable)
+                    && response.get(&variable).copied().unwrap_or(false) == (*literal > 0)
+            });
+            if satisfied_by_response {
+                continue;
+            }
+
+            // The auxiliary variable implies that all universal literals of the clause are false
+            let auxiliary = next_auxiliary as Literal;
+            next_auxiliary += 1;
+            for literal in clause.literals() {
+                if universal_set.contains(&to_variable(*literal)) {
+                    let mut implication = Clause::new();
+                    implication.insert(-auxiliary);
+                    implication.insert(-*literal);
+                    abstraction.add_clause(implication);
+                }
+            }
+            refinement.insert(auxiliary);
+        }
+
+        // An empty refinement means the response works for every candidate
+        abstraction.add_clause(refinement);
+    }
+}
+
+/// The outcome of solving an `∃X ∀Y φ` problem.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExistsForallResult {
+    /// An assignment of the existential variables under which the formula holds for every
+    /// assignment of the universal variables.
+    Satisfiable(Assignment),
+    /// Every assignment of the existential variables is refuted by some universal assignment.
+    Unsatisfiable,
+}
+
+/// Replaces the assigned variables of the formula by their values.
+fn substitute(formula: &Formula, values: &Assignment) -> Formula {
+    let substitute_all = |children: &Vec<Formula>| {
+        children
+            .iter()
+            .map(|child| substitute(child, values))
+            .collect()
+    };
+    let substitute_boxed = |inner: &Formula| Box::new(substitute(inner, values));
+    match formula {
+        Formula::Constant(value) => Formula::Constant(*value),
+        Formula::Variable(variable) => match values.get(variable) {
+            Some(value) => Formula::Constant(*value),
+            None => Formula::Variable(*variable),
+        },
+        Formula::Not(inner) => Formula::Not(substitute_boxed(inner)),
+        Formula::And(children) => Formula::And(substitute_all(children)),
+        Formula::Or(children) => Formula::Or(substitute_all(children)),
+        Formula::Implies(left, right) => {
+            Formula::Implies(substitute_boxed(left), substitute_boxed(right))
+        }
+        Formula::Iff(left, right) => Formula::Iff(substitute_boxed(left), substitute_boxed(right)),
+        Formula::Xor(left, right) => Formula::Xor(substitute_boxed(left), substitute_boxed(right)),
+    }
+}
+
+fn collect_variables(formula: &Formula, variables: &mut Vec<Variable>) {
+    match formula {
+        Formula::Constant(_) => {}
+        Formula::Variable(variable) => variables.push(*variable),
+        Formula::Not(inner) => collect_variables(inner, variables),
+        Formula::And(children) | Formula::Or(children) => {
+            for child in children {
+                collect_variables(child, variables);
+            }
+        }
+        Formula::Implies(left, right) | Formula::Iff(left, right) | Formula::Xor(left, right) => {
+            collect_variables(left, variables);
+            collect_variables(right, variables);
+        }
+    }
+}
+
+/// Adds the Tseitin clauses of the formula to the solver, numbering the auxiliary variables from
+/// `next_variable`.
+fn assert_formula(solver: &mut Solver, formula: &Formula, next_variable: &mut Variable) {
+    let mut expression = Expression::new();
+    formula.assert_into(&mut expression, next_variable);
+    for clause in expression.get_clauses() {
+        solver.add_clause(clause.literals());
+    }
+}
+
+/// Finds an assignment of the existential variables under which the formula holds whatever the
+/// values of the universal variables, for small synthesis and parameter-finding problems, with
+/// counterexample-guided abstraction refinement.
+///
+/// A synthesis solver proposes candidate existential assignments `x`, and a verifier looks for a
+/// counterexample: a universal assignment `y` with `¬φ(x, y)`, solving the negated formula under
+/// `x` as assumptions. Each counterexample adds `φ(X, y)` to the synthesis solver, so later
+/// candidates must answer every counterexample found so far; a candidate without a
+/// counterexample is the solution, and running out of candidates shows there is none. Both
+/// solvers are incremental, so the clauses they learn carry over between iterations. The number
+/// of iterations can be exponential in the number of universal variables.
+///
+/// Panics if the formula uses a variable that is neither existential nor universal.
+pub fn solve_exists_forall(
+    exists: &[Variable],
+    forall: &[Variable],
+    formula: &Formula,
+) -> ExistsForallResult {
+    let max_variable = exists
+        .iter()
+        .chain(forall)
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(formula.get_max_variable());
+    let mut variables = Vec::new();
+    collect_variables(formula, &mut variables);
+    for variable in variables {
+        assert!(
+            exists.contains(&variable) || forall.contains(&variable),
+            "{} is neither existential nor universal",
+            variable
+        );
+    }
+
+    let mut verifier = Solver::new();
+    let mut next_verifier_variable = max_variable + 1;
+    let negation = Formula::Not(Box::new(formula.clone()));
+    assert_formula(&mut verifier, &negation, &mut next_verifier_variable);
+
+    let mut synthesis = Solver::new();
+    let mut next_synthesis_variable = max_variable + 1;
+    loop {
+        let SearchResult::Satisfiable(_) = synthesis.solve_with_assumptions(&[]) else {
+            return ExistsForallResult::Unsatisfiable;
+        };
+        let candidate: Assignment = exists
+            .iter()
+            .map(|variable| (*variable, synthesis.value(*variable).unwrap()))
+            .collect();
+
+        let assumptions: Vec<Literal> = exists
+            .iter()
+            .map(|variable| {
+                let literal = *variable as Literal;
+                if candidate[variable] {
+                    literal
+                } else {
+                    -literal
+                }
+            })
+            .collect();
+        let SearchResult::Satisfiable(_) = verifier.solve_with_assumptions(&assumptions) else {
+            return ExistsForallResult::Satisfiable(candidate);
+        };
+        let counterexample: Assignment = forall
+            .iter()
+            .map(|variable| (*variable, verifier.value(*variable).unwrap()))
+            .collect();
+
+        // Later candidates must satisfy the formula under this counterexample too
+        let refinement = substitute(formula, &counterexample);
+        assert_formula(&mut synthesis, &refinement, &mut next_synthesis_variable);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_qdimacs_str;
+    use crate::formula_parser::parse_formula;
+
+    #[test]
+    fn test_solve_2qbf_true() {
+        // For every x there is a y = !x
+        let expression = parse_qdimacs_str("p cnf 2 2\na 1 0\ne 2 0\n1 2 0\n-1 -2 0\n").unwrap();
+        assert!(matches!(solve_2qbf(&expression), QbfResult::True));
+    }
+
+    #[test]
+    fn test_solve_2qbf_false() {
+        // There is no y satisfying both clauses when x is false
+        let expression = parse_qdimacs_str("p cnf 2 2\na 1 0\ne 2 0\n1 2 0\n1 -2 0\n").unwrap();
+        match solve_2qbf(&expression) {
+            QbfResult::False(counterexample) => assert!(!counterexample[&1]),
+            QbfResult::True => panic!("Expected the formula to be false"),
+        }
+    }
+
+    #[test]
+    fn test_solve_2qbf_multiple_universals() {
+        // y must equal x1 xor x2, which is always possible
+        let expression = parse_qdimacs_str(
+            "p cnf 3 4\na 1 2 0\ne 3 0\n-1 -2 -3 0\n1 2 -3 0\n1 -2 3 0\n-1 2 3 0\n",
+        )
+        .unwrap();
+        assert!(matches!(solve_2qbf(&expression), QbfResult::True));
+    }
+
+    #[test]
+    fn test_solve_exists_forall() {
+        // A multiplexer with constant inputs computing the negation of its selector
+        let (formula, symbols) = parse_formula("(c1 & x | c0 & !x) <-> !x").unwrap();
+        let [c1, x, c0] = ["c1", "x", "c0"].map(|name| symbols.variable(name).unwrap());
+        assert_eq!(
+            solve_exists_forall(&[c1, c0], &[x], &formula),
+            ExistsForallResult::Satisfiable(Assignment::from_iter([(c1, false), (c0, true)]))
+        );
+
+        let (formula, symbols) = parse_formula("(a | x) & (b | !x) & !(a & b)").unwrap();
+        let [a, x, b] = ["a", "x", "b"].map(|name| symbols.variable(name).unwrap());
+        assert_eq!(
+            solve_exists_forall(&[a, b], &[x], &formula),
+            ExistsForallResult::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn test_as_two_qbf_rejects_other_prefixes() {
+        let expression = parse_qdimacs_str("p cnf 3 1\ne 1 0\na 2 0\ne 3 0\n1 2 3 0\n").unwrap();
+        assert!(!expression.is_two_qbf());
+    }
+}
@@ -0,0 +1,320 @@
+//! Runs a corpus of instances with recorded expected answers, as a regression gate.
+//!
+//! The corpus is described by a manifest file with one instance per line:
+//!
+//! ```text
+//! # file                expected  baseline time in milliseconds (optional)
+//! quinn.cnf             SAT       2.5
+//! hole6.cnf             UNSAT
+//! ```
+//!
+//! Files are relative to the directory containing the manifest. Lines starting with `#` are comments.
+
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::crosscheck::Status;
+use crate::dimacs_parser::{parse_dimacs_str_with_options, ParseError, ParseOptions};
+use crate::solver::solve;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ManifestEntry {
+    pub file: String,
+    pub expected: Status,
+    /// The time the instance is expected to take, used to detect timing regressions.
+    pub baseline: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub enum RegressError {
+    Io(std::io::Error),
+    Manifest { line: usize, message: String },
+    Instance { file: String, error: ParseError },
+}
+
+impl fmt::Display for RegressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegressError::Io(error) => write!(f, "{}", error),
+            RegressError::Manifest { line, message } => {
+                write!(f, "invalid manifest on line {}: {}", line, message)
+            }
+            RegressError::Instance { file, error } => write!(f, "{}: {}", file, error),
+        }
+    }
+}
+
+impl std::error::Error for RegressError {}
+
+impl From<std::io::Error> for RegressError {
+    fn from(error: std::io::Error) -> Self {
+        RegressError::Io(error)
+    }
+}
+
+/// Parses the contents of a manifest file.
+pub fn parse_manifest(contents: &str) -> Result<Vec<ManifestEntry>, RegressError> {
+    let mut entries = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: String| RegressError::Manifest {
+            line: index + 1,
+            message,
+        };
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 2 && parts.len() != 3 {
+            return Err(error(format!(
+                "expected 2 or 3 columns but found {}",
+                parts.len()
+            )));
+        }
+
+        let expected = match parts[1] {
+            "SAT" | "SATISFIABLE" => Status::Satisfiable,
+            "UNSAT" | "UNSATISFIABLE" => Status::Unsatisfiable,
+            status => return Err(error(format!("unknown status '{}'", status))),
+        };
+        let baseline = match parts.get(2) {
+            Some(milliseconds) => match milliseconds.parse::<f64>() {
+                Ok(milliseconds) if milliseconds >= 0.0 => {
+                    Some(Duration::from_secs_f64(milliseconds / 1000.0))
+                }
+                _ => return Err(error(format!("invalid time '{}'", milliseconds))),
+            },
+            None => None,
+        };
+
+        entries.push(ManifestEntry {
+            file: parts[0].to_string(),
+            expected,
+            baseline,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RegressOptions {
+    /// An instance regresses if it takes longer than its baseline times this factor...
+    pub time_factor: f64,
+    /// ...and at least this much longer than its baseline, to ignore noise on fast instances.
+    pub min_slowdown: Duration,
+}
+
+impl Default for RegressOptions {
+    fn default() -> Self {
+        RegressOptions {
+            time_factor: 1.5,
+            min_slowdown: Duration::from_millis(50),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Pass,
+    /// The solver's answer differs from the expected status.
+    Mismatch,
+    /// The solver's answer is right but its model does not satisfy the instance.
+    InvalidModel,
+    /// The answer is right but the instance got slower than its baseline allows.
+    TimingRegression,
+}
+
+impl Outcome {
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Mismatch => "mismatch",
+            Outcome::InvalidModel => "invalid-model",
+            Outcome::TimingRegression => "timing-regression",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EntryResult {
+    pub entry: ManifestEntry,
+    pub actual: Status,
+    pub time: Duration,
+    pub outcome: Outcome,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CorpusReport {
+    pub results: Vec<EntryResult>,
+}
+
+impl CorpusReport {
+    pub fn passed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| result.outcome == Outcome::Pass)
+    }
+
+    pub fn failures(&self) -> Vec<&EntryResult> {
+        self.results
+            .iter()
+            .filter(|result| result.outcome != Outcome::Pass)
+            .collect()
+    }
+
+    /// Renders the report as JSON, for consumption by CI scripts.
+    pub fn to_json(&self) -> String {
+        let mut results = Vec::new();
+        for result in &self.results {
+            let baseline = match result.entry.baseline {
+                Some(baseline) => format!("{:.3}", baseline.as_secs_f64() * 1000.0),
+                None => "null".to_string(),
+            };
+            results.push(format!(
+                "{{\"file\":{},\"expected\":\"{}\",\"actual\":\"{}\",\"time_ms\":{:.3},\"baseline_ms\":{},\"outcome\":\"{}\"}}",
+                json_string(&result.entry.file),
+                result.entry.expected,
+                result.actual,
+                result.time.as_secs_f64() * 1000.0,
+                baseline,
+                result.outcome.name()
+            ));
+        }
+
+        format!(
+            "{{\"passed\":{},\"num_failures\":{},\"results\":[{}]}}",
+            self.passed(),
+            self.failures().len(),
+            results.join(",")
+        )
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            writeln!(
+                f,
+                "{:<18} {:<32} expected {:<13} got {:<13} in {:?}",
+                result.outcome.name(),
+                result.entry.file,
+                result.entry.expected.to_string(),
+                result.actual.to_string(),
+                result.time
+            )?;
+        }
+        write!(
+            f,
+            "{} of {} instances passed",
+            self.results.len() - self.failures().len(),
+            self.results.len()
+        )
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Solves every instance of the manifest and compares the results to the expectations.
+pub fn run_corpus(manifest: &str, options: &RegressOptions) -> Result<CorpusReport, RegressError> {
+    let entries = parse_manifest(&std::fs::read_to_string(manifest)?)?;
+    let directory = Path::new(manifest).parent().unwrap_or(Path::new(""));
+
+    let mut report = CorpusReport::default();
+    for entry in entries {
+        let contents = std::fs::read_to_string(directory.join(&entry.file))?;
+        let (expression, _) = parse_dimacs_str_with_options(&contents, &ParseOptions::default())
+            .map_err(|error| RegressError::Instance {
+                file: entry.file.clone(),
+                error,
+            })?;
+
+        let start = Instant::now();
+        let model = solve(expression.clone(), true, false);
+        let time = start.elapsed();
+
+        let actual = if model.is_some() {
+            Status::Satisfiable
+        } else {
+            Status::Unsatisfiable
+        };
+        let outcome = if actual != entry.expected {
+            Outcome::Mismatch
+        } else if model.is_some_and(|model| !expression.is_satisfied_by(&model)) {
+            Outcome::InvalidModel
+        } else if entry.baseline.is_some_and(|baseline| {
+            time.as_secs_f64() > baseline.as_secs_f64() * options.time_factor
+                && time > baseline + options.min_slowdown
+        }) {
+            Outcome::TimingRegression
+        } else {
+            Outcome::Pass
+        };
+
+        report.results.push(EntryResult {
+            entry,
+            actual,
+            time,
+            outcome,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let entries =
+            parse_manifest("# comment\n\nquinn.cnf SAT 2.5\nhole6.cnf UNSATISFIABLE\n").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].expected, Status::Satisfiable);
+        assert_eq!(entries[0].baseline, Some(Duration::from_micros(2500)));
+        assert_eq!(entries[1].expected, Status::Unsatisfiable);
+        assert_eq!(entries[1].baseline, None);
+
+        match parse_manifest("quinn.cnf SAT\nhole6.cnf MAYBE\n") {
+            Err(RegressError::Manifest { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("Expected a manifest error"),
+        }
+    }
+
+    #[test]
+    fn test_run_corpus_reports_mismatches() {
+        let directory =
+            std::env::temp_dir().join(format!("microsat-regress-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("sat.cnf"), "p cnf 2 2\n1 2 0\n-1 0\n").unwrap();
+        std::fs::write(directory.join("unsat.cnf"), "p cnf 1 2\n1 0\n-1 0\n").unwrap();
+        let manifest = directory.join("manifest.txt");
+        std::fs::write(&manifest, "sat.cnf SAT 1000\nunsat.cnf SAT\n").unwrap();
+
+        let report = run_corpus(manifest.to_str().unwrap(), &RegressOptions::default()).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(!report.passed());
+        assert_eq!(report.results[0].outcome, Outcome::Pass);
+        assert_eq!(report.results[1].outcome, Outcome::Mismatch);
+        assert_eq!(report.results[1].actual, Status::Unsatisfiable);
+        assert!(report
+            .to_json()
+            .starts_with("{\"passed\":false,\"num_failures\":1,"));
+    }
+}
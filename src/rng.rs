@@ -0,0 +1,64 @@
+/// A small, seedable pseudo-random number generator (SplitMix64).
+///
+/// It is not suitable for cryptography, but its output depends only on the seed,
+/// so generated instances and randomized runs can be reproduced exactly.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value uniformly distributed in `0..bound`. Panics if `bound` is 0.
+    pub fn next_below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "The bound must be positive");
+        // Rejection sampling avoids the bias of a plain modulo
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+
+    /// Returns a new generator seeded from this one, for handing an independent stream to a
+    /// component while keeping everything derived from one seed.
+    pub fn split(&mut self) -> Rng {
+        Rng::new(self.next_u64())
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_reproducible() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            assert!(rng.next_below(10) < 10);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Simplifications that rewrite the clauses of an expression into an equivalent, easier one
+//! before (or between) searches.
+
+use crate::cnf::{Clause, Literal, CNF};
+use crate::expression::Expression;
+use crate::propagation::Propagator;
+
+/// Strengthens the clauses of the expression by asymmetric literal elimination, returning the
+/// strengthened expression and the number of literals removed.
+///
+/// For a clause `C` and one of its literals `l`, the other literals of `C` are assumed false and
+/// propagated through the rest of the clauses. This assigns the hidden literals that `C` could be
+/// extended with; if it also makes `l` false, or leads to a conflict, the rest of the expression
+/// implies `C` without `l`, so `l` is removed. The expression keeps its models.
+pub fn strengthen_clauses(expression: &Expression) -> (Expression, usize) {
+    let clauses = expression.get_clauses();
+    let mut propagator = Propagator::new();
+    let mut ids: Vec<usize> = clauses
+        .iter()
+        .map(|clause| propagator.add_clause(clause.literals()))
+        .collect();
+    // The propagator reorders the literals it watches, so the clauses are kept apart
+    let mut strengthened: Vec<Vec<Literal>> = clauses
+        .iter()
+        .map(|clause| clause.literals().clone())
+        .collect();
+
+    let mut num_removed = 0;
+    if propagator.propagate_units().is_none() {
+        for (id, literals) in ids.iter_mut().zip(strengthened.iter_mut()) {
+            if literals.len() < 2 {
+                continue;
+            }
+            propagator.set_active(*id, false);
+
+            let mut position = 0;
+            while position < literals.len() && literals.len() > 1 {
+                let literal = literals[position];
+                let conflict = propagator.propagate_units().is_some()
+                    || literals
+                        .iter()
+                        .filter(|other| **other != literal)
+                        .any(|other| propagator.assume(-other).is_some());
+                if conflict || propagator.value(literal) == Some(false) {
+                    literals.remove(position);
+                    num_removed += 1;
+                } else {
+                    position += 1;
+                }
+            }
+
+            // The strengthened clause replaces the original one in the next propagations
+            *id = propagator.add_clause(literals);
+        }
+    }
+
+    let mut simplified = Expression::new();
+    for literals in strengthened {
+        let mut clause = Clause::new();
+        for literal in literals {
+            clause.insert(literal);
+        }
+        simplified.add_clause(clause);
+    }
+    (simplified, num_removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::enumerate::enumerate_models;
+
+    #[test]
+    fn test_strengthen_clauses() {
+        // With 1 -> 2 and 2 -> 3, the clause 1 | 3 | 4 implies 3 | 4 on its own
+        let expression = parse_dimacs_str("p cnf 4 3\n-1 2 0\n-2 3 0\n1 3 4 0\n");
+        let (strengthened, num_removed) = strengthen_clauses(&expression);
+        assert_eq!(num_removed, 1);
+        assert_eq!(strengthened.get_clauses()[2].literals(), &vec![3, 4]);
+        assert_eq!(
+            enumerate_models(&strengthened, None).len(),
+            enumerate_models(&expression, None).len()
+        );
+
+        // Nothing to remove
+        let expression = parse_dimacs_str("p cnf 3 2\n1 2 0\n-1 3 0\n");
+        let (strengthened, num_removed) = strengthen_clauses(&expression);
+        assert_eq!(num_removed, 0);
+        assert_eq!(strengthened.get_clauses(), expression.get_clauses());
+    }
+}
@@ -0,0 +1,737 @@
+//! The propositional fragment of SMT-LIB 2, the input language of SMT solvers, which teaching
+//! material and scripts often produce instead of DIMACS.
+//!
+//! A script declares Boolean constants, asserts terms over them, and asks for answers:
+//!
+//! ```text
+//! (set-logic QF_UF)
+//! (declare-const p Bool)
+//! (declare-const q Bool)
+//! (assert (and (or p q) (=> p (not q))))
+//! (check-sat)
+//! (get-model)
+//! ```
+//!
+//! The terms are `true`, `false`, the constants, and the applications of `not`, `and`, `or`,
+//! `xor`, `=>` (associating to the right), `=` (chained), `distinct` and `ite`, along with `let`
+//! bindings and `!` annotations, whose `:named` attribute defines a name for the term. Functions
+//! with no arguments may be defined with `define-fun`. Every `check-sat` encodes the assertions
+//! of the current scope with the Tseitin transformation of [`Formula::to_expression`] and solves
+//! them. The session answers `check-sat`, `get-model`, `get-value` and `get-assertions` in the
+//! syntax of SMT-LIB, supports `push`, `pop`, `reset-assertions`, `reset` and `exit`, and
+//! silently accepts `set-logic`, `set-info` and `set-option`. Other commands answer
+//! `unsupported`.
+
+use std::fmt;
+
+use hashbrown::HashMap;
+
+use crate::cnf::{Assignment, Variable};
+use crate::dpll::solve_dpll;
+use crate::formula::Formula;
+
+/// A problem in an SMT-LIB script.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SmtlibError {
+    /// The 1-based line number of the problem.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SmtlibError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid SMT-LIB on line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SmtlibError {}
+
+/// An s-expression with the line it starts on. Atoms are symbols, keywords, numerals and string
+/// literals, kept as written except for the bars of quoted symbols.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum SExpr {
+    Atom(String, usize),
+    List(Vec<SExpr>, usize),
+}
+
+impl SExpr {
+    fn line(&self) -> usize {
+        match self {
+            SExpr::Atom(_, line) | SExpr::List(_, line) => *line,
+        }
+    }
+
+    fn as_atom(&self) -> Option<&str> {
+        match self {
+            SExpr::Atom(atom, _) => Some(atom),
+            SExpr::List(..) => None,
+        }
+    }
+}
+
+/// Prints the s-expression on one line, quoting symbols that need it.
+impl fmt::Display for SExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SExpr::Atom(atom, _) if needs_quotes(atom) => write!(f, "|{}|", atom),
+            SExpr::Atom(atom, _) => write!(f, "{}", atom),
+            SExpr::List(items, _) => {
+                write!(f, "(")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn needs_quotes(atom: &str) -> bool {
+    !atom.starts_with('"')
+        && (atom.is_empty()
+            || atom
+                .chars()
+                .any(|c| c.is_whitespace() || "()|;\"".contains(c)))
+}
+
+/// Splits the script into its top-level s-expressions.
+fn parse_sexprs(contents: &str) -> Result<Vec<SExpr>, SmtlibError> {
+    let error = |line: usize, message: &str| {
+        Err(SmtlibError {
+            line,
+            message: message.to_string(),
+        })
+    };
+    let mut line = 1;
+    let mut chars = contents.chars().peekable();
+    // The lists being read, innermost last, with the lines they start on
+    let mut open: Vec<(Vec<SExpr>, usize)> = Vec::new();
+    let mut top = Vec::new();
+    while let Some(c) = chars.next() {
+        let item = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            ';' => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '(' => {
+                open.push((Vec::new(), line));
+                continue;
+            }
+            ')' => match open.pop() {
+                Some((items, start)) => SExpr::List(items, start),
+                None => return error(line, "Unbalanced )"),
+            },
+            '|' => {
+                let start = line;
+                let mut symbol = String::new();
+                loop {
+                    match chars.next() {
+                        Some('|') => break,
+                        Some(c) => {
+                            line += (c == '\n') as usize;
+                            symbol.push(c);
+                        }
+                        None => return error(start, "Unterminated quoted symbol"),
+                    }
+                }
+                SExpr::Atom(symbol, start)
+            }
+            '"' => {
+                let start = line;
+                let mut literal = String::from('"');
+                loop {
+                    match chars.next() {
+                        // A doubled quote stands for a quote
+                        Some('"') if chars.next_if_eq(&'"').is_some() => literal.push_str("\"\""),
+                        Some('"') => break,
+                        Some(c) => {
+                            line += (c == '\n') as usize;
+                            literal.push(c);
+                        }
+                        None => return error(start, "Unterminated string literal"),
+                    }
+                }
+                literal.push('"');
+                SExpr::Atom(literal, start)
+            }
+            c => {
+                let mut atom = String::from(c);
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"()|;\"".contains(*c))
+                {
+                    atom.push(c);
+                }
+                SExpr::Atom(atom, line)
+            }
+        };
+        match open.last_mut() {
+            Some((items, _)) => items.push(item),
+            None => top.push(item),
+        }
+    }
+    if let Some((_, start)) = open.last() {
+        return error(*start, "Unbalanced (");
+    }
+    Ok(top)
+}
+
+/// What the search last answered, and the model if it found one.
+#[derive(Clone, Debug)]
+enum Answer {
+    Unknown,
+    Unsatisfiable,
+    Satisfiable(Assignment),
+}
+
+/// An SMT-LIB session, which keeps its declarations and assertions from one script to the next.
+#[derive(Clone, Debug)]
+pub struct SmtSession {
+    num_variables: Variable,
+    /// The declared constants and defined names in scope, in order of declaration, with the
+    /// variable of every constant.
+    names: Vec<(String, Formula, Option<Variable>)>,
+    name_index: HashMap<String, usize>,
+    assertions: Vec<(Formula, SExpr)>,
+    /// The numbers of names and assertions when every scope was pushed.
+    scopes: Vec<(usize, usize)>,
+    answer: Answer,
+    exited: bool,
+}
+
+impl Default for SmtSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SmtSession {
+    pub fn new() -> SmtSession {
+        SmtSession {
+            num_variables: 0,
+            names: Vec::new(),
+            name_index: HashMap::new(),
+            assertions: Vec::new(),
+            scopes: Vec::new(),
+            answer: Answer::Unknown,
+            exited: false,
+        }
+    }
+
+    /// Runs the commands of the script, returning the responses, one per line. Stops at an
+    /// `exit`, after which later scripts do nothing.
+    pub fn execute(&mut self, script: &str) -> Result<String, SmtlibError> {
+        let mut output = String::new();
+        for command in parse_sexprs(script)? {
+            if self.exited {
+                break;
+            }
+            if let Some(response) = self.execute_command(&command)? {
+                output.push_str(&response);
+                output.push('\n');
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns the answer of the last `check-sat` since the assertions changed, if any.
+    pub fn is_satisfiable(&self) -> Option<bool> {
+        match self.answer {
+            Answer::Unknown => None,
+            Answer::Unsatisfiable => Some(false),
+            Answer::Satisfiable(_) => Some(true),
+        }
+    }
+
+    /// Returns the value of the constant in the model of the last `check-sat`, if it found one.
+    pub fn get_value(&self, name: &str) -> Option<bool> {
+        let Answer::Satisfiable(model) = &self.answer else {
+            return None;
+        };
+        let (_, formula, _) = &self.names[*self.name_index.get(name)?];
+        Some(formula.evaluate(model))
+    }
+
+    fn execute_command(&mut self, command: &SExpr) -> Result<Option<String>, SmtlibError> {
+        let line = command.line();
+        let error = |message: String| Err(SmtlibError { line, message });
+        let SExpr::List(items, _) = command else {
+            return error(format!("Expected a command instead of {}", command));
+        };
+        let Some(name) = items.first().and_then(|item| item.as_atom()) else {
+            return error(format!("Expected a command instead of {}", command));
+        };
+        let arguments = &items[1..];
+        match (name, arguments) {
+            ("set-logic" | "set-info" | "set-option", _) => Ok(None),
+            ("declare-const", [SExpr::Atom(symbol, _), sort]) => {
+                check_bool(sort)?;
+                self.declare(symbol, line)?;
+                Ok(None)
+            }
+            ("declare-fun", [SExpr::Atom(symbol, _), SExpr::List(parameters, _), sort]) => {
+                if !parameters.is_empty() {
+                    return error(format!(
+                        "{} takes arguments, which is not supported",
+                        symbol
+                    ));
+                }
+                check_bool(sort)?;
+                self.declare(symbol, line)?;
+                Ok(None)
+            }
+            ("define-fun", [SExpr::Atom(symbol, _), SExpr::List(parameters, _), sort, body]) => {
+                if !parameters.is_empty() {
+                    return error(format!(
+                        "{} takes arguments, which is not supported",
+                        symbol
+                    ));
+                }
+                check_bool(sort)?;
+                let formula = self.term(body, &HashMap::new())?;
+                self.define(symbol, formula, None, line)?;
+                Ok(None)
+            }
+            ("assert", [term]) => {
+                let formula = self.term(term, &HashMap::new())?;
+                self.assertions.push((formula, term.clone()));
+                self.answer = Answer::Unknown;
+                Ok(None)
+            }
+            ("check-sat", []) => {
+                let formula = Formula::And(
+                    self.assertions
+                        .iter()
+                        .map(|(formula, _)| formula.clone())
+                        .collect(),
+                );
+                let mut expression = formula.to_expression(self.num_variables);
+                expression.optimize();
+                self.answer = match solve_dpll(&mut expression) {
+                    Some(model) => Answer::Satisfiable(model),
+                    None => Answer::Unsatisfiable,
+                };
+                Ok(Some(
+                    match self.answer {
+                        Answer::Satisfiable(_) => "sat",
+                        _ => "unsat",
+                    }
+                    .to_string(),
+                ))
+            }
+            ("get-model", []) => {
+                let Answer::Satisfiable(model) = &self.answer else {
+                    return Ok(Some(no_model()));
+                };
+                let mut response = String::from("(");
+                for (symbol, formula, variable) in &self.names {
+                    if variable.is_some() {
+                        let symbol = SExpr::Atom(symbol.clone(), 0);
+                        let value = formula.evaluate(model);
+                        response
+                            .push_str(&format!("\n  (define-fun {} () Bool {})", symbol, value));
+                    }
+                }
+                response.push_str("\n)");
+                Ok(Some(response))
+            }
+            ("get-value", [SExpr::List(terms, _)]) if !terms.is_empty() => {
+                let mut values = Vec::new();
+                for term in terms {
+                    values.push((term, self.term(term, &HashMap::new())?));
+                }
+                let Answer::Satisfiable(model) = &self.answer else {
+                    return Ok(Some(no_model()));
+                };
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|(term, formula)| format!("({} {})", term, formula.evaluate(model)))
+                    .collect();
+                Ok(Some(format!("({})", values.join(" "))))
+            }
+            ("get-assertions", []) => {
+                let terms: Vec<String> = self
+                    .assertions
+                    .iter()
+                    .map(|(_, term)| term.to_string())
+                    .collect();
+                Ok(Some(format!("({})", terms.join(" "))))
+            }
+            ("push", _) => {
+                for _ in 0..scope_count(arguments, line)? {
+                    self.scopes.push((self.names.len(), self.assertions.len()));
+                }
+                Ok(None)
+            }
+            ("pop", _) => {
+                let count = scope_count(arguments, line)?;
+                if count > self.scopes.len() {
+                    return error(format!(
+                        "Cannot pop {} of {} scopes",
+                        count,
+                        self.scopes.len()
+                    ));
+                }
+                let (num_names, num_assertions) = self.scopes[self.scopes.len() - count];
+                self.scopes.truncate(self.scopes.len() - count);
+                self.truncate(num_names, num_assertions);
+                Ok(None)
+            }
+            // Without global declarations, the names go along with the assertions
+            ("reset-assertions", []) => {
+                self.scopes.clear();
+                self.truncate(0, 0);
+                Ok(None)
+            }
+            ("reset", []) => {
+                *self = SmtSession::new();
+                Ok(None)
+            }
+            ("exit", []) => {
+                self.exited = true;
+                Ok(None)
+            }
+            _ => Ok(Some("unsupported".to_string())),
+        }
+    }
+
+    /// Declares a new Boolean constant, with a variable of its own.
+    fn declare(&mut self, symbol: &str, line: usize) -> Result<(), SmtlibError> {
+        let variable = self.num_variables + 1;
+        self.define(symbol, Formula::Variable(variable), Some(variable), line)?;
+        self.num_variables = variable;
+        Ok(())
+    }
+
+    fn define(
+        &mut self,
+        symbol: &str,
+        formula: Formula,
+        variable: Option<Variable>,
+        line: usize,
+    ) -> Result<(), SmtlibError> {
+        if self.name_index.contains_key(symbol) || is_reserved(symbol) {
+            return Err(SmtlibError {
+                line,
+                message: format!("{} is already defined", symbol),
+            });
+        }
+        self.name_index.insert(symbol.to_string(), self.names.len());
+        self.names.push((symbol.to_string(), formula, variable));
+        Ok(())
+    }
+
+    /// Drops the names and assertions of the popped scopes.
+    fn truncate(&mut self, num_names: usize, num_assertions: usize) {
+        for (symbol, _, _) in self.names.drain(num_names..) {
+            self.name_index.remove(&symbol);
+        }
+        self.assertions.truncate(num_assertions);
+        self.answer = Answer::Unknown;
+    }
+
+    /// Converts a term to a formula. Names bound by `let` come first, then the declared ones.
+    fn term(
+        &mut self,
+        term: &SExpr,
+        bound: &HashMap<String, Formula>,
+    ) -> Result<Formula, SmtlibError> {
+        let line = term.line();
+        let error = |message: String| Err(SmtlibError { line, message });
+        let items = match term {
+            SExpr::Atom(atom, _) => {
+                return match atom.as_str() {
+                    "true" => Ok(Formula::Constant(true)),
+                    "false" => Ok(Formula::Constant(false)),
+                    _ => match bound.get(atom) {
+                        Some(formula) => Ok(formula.clone()),
+                        None => match self.name_index.get(atom) {
+                            Some(index) => Ok(self.names[*index].1.clone()),
+                            None => error(format!("Unknown symbol {}", atom)),
+                        },
+                    },
+                };
+            }
+            SExpr::List(items, _) => items,
+        };
+        let Some(head) = items.first().and_then(|item| item.as_atom()) else {
+            return error(format!("Expected an application instead of {}", term));
+        };
+        let arguments = &items[1..];
+
+        match head {
+            "let" => {
+                let [SExpr::List(bindings, _), body] = arguments else {
+                    return error(format!("Invalid let {}", term));
+                };
+                // The bindings are parallel, so each is read in the outer scope
+                let mut inner = bound.clone();
+                for binding in bindings {
+                    let SExpr::List(binding, _) = binding else {
+                        return error(format!("Invalid binding {}", binding));
+                    };
+                    let [SExpr::Atom(symbol, _), value] = &binding[..] else {
+                        return error(format!("Invalid binding in {}", term));
+                    };
+                    inner.insert(symbol.clone(), self.term(value, bound)?);
+                }
+                return self.term(body, &inner);
+            }
+            "!" => {
+                let Some((inner, attributes)) = arguments.split_first() else {
+                    return error(format!("Invalid annotation {}", term));
+                };
+                let formula = self.term(inner, bound)?;
+                for pair in attributes.chunks(2) {
+                    if let [SExpr::Atom(keyword, _), SExpr::Atom(symbol, _)] = pair {
+                        if keyword == ":named" {
+                            self.define(symbol, formula.clone(), None, line)?;
+                        }
+                    }
+                }
+                return Ok(formula);
+            }
+            _ => {}
+        }
+
+        let mut formulas = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            formulas.push(self.term(argument, bound)?);
+        }
+        let arity = |min: usize, max: usize| {
+            if formulas.len() >= min && formulas.len() <= max {
+                return Ok(());
+            }
+            let message = if min == max {
+                format!("{} expects {} arguments", head, min)
+            } else {
+                format!("{} expects at least {} arguments", head, min)
+            };
+            Err(SmtlibError { line, message })
+        };
+        let pair =
+            |left: &Formula, right: &Formula| (Box::new(left.clone()), Box::new(right.clone()));
+        match head {
+            "not" => {
+                arity(1, 1)?;
+                Ok(Formula::Not(Box::new(formulas.remove(0))))
+            }
+            "and" => Ok(Formula::And(formulas)),
+            "or" => Ok(Formula::Or(formulas)),
+            "xor" => {
+                arity(2, usize::MAX)?;
+                let mut formulas = formulas.into_iter();
+                let first = formulas.next().unwrap();
+                Ok(formulas.fold(first, |left, right| {
+                    Formula::Xor(Box::new(left), Box::new(right))
+                }))
+            }
+            "=>" => {
+                arity(2, usize::MAX)?;
+                let last = formulas.pop().unwrap();
+                Ok(formulas.into_iter().rev().fold(last, |right, left| {
+                    Formula::Implies(Box::new(left), Box::new(right))
+                }))
+            }
+            "=" => {
+                arity(2, usize::MAX)?;
+                Ok(Formula::And(
+                    formulas
+                        .windows(2)
+                        .map(|window| {
+                            let (left, right) = pair(&window[0], &window[1]);
+                            Formula::Iff(left, right)
+                        })
+                        .collect(),
+                ))
+            }
+            "distinct" => {
+                arity(2, usize::MAX)?;
+                let mut different = Vec::new();
+                for (index, left) in formulas.iter().enumerate() {
+                    for right in &formulas[index + 1..] {
+                        let (left, right) = pair(left, right);
+                        different.push(Formula::Xor(left, right));
+                    }
+                }
+                Ok(Formula::And(different))
+            }
+            "ite" => {
+                arity(3, 3)?;
+                let (condition, then) = pair(&formulas[0], &formulas[1]);
+                // Either the condition holds or the else branch does
+                Ok(Formula::And(vec![
+                    Formula::Implies(condition, then),
+                    Formula::Or(vec![formulas[0].clone(), formulas[2].clone()]),
+                ]))
+            }
+            _ => error(format!("Unknown function {}", head)),
+        }
+    }
+}
+
+/// Runs an SMT-LIB script in a new session, returning the responses, one per line.
+pub fn run_smtlib(script: &str) -> Result<String, SmtlibError> {
+    SmtSession::new().execute(script)
+}
+
+fn check_bool(sort: &SExpr) -> Result<(), SmtlibError> {
+    match sort.as_atom() {
+        Some("Bool") => Ok(()),
+        _ => Err(SmtlibError {
+            line: sort.line(),
+            message: format!("Only the sort Bool is supported, not {}", sort),
+        }),
+    }
+}
+
+fn is_reserved(symbol: &str) -> bool {
+    matches!(
+        symbol,
+        "true"
+            | "false"
+            | "not"
+            | "and"
+            | "or"
+            | "xor"
+            | "=>"
+            | "="
+            | "distinct"
+            | "ite"
+            | "let"
+            | "!"
+    )
+}
+
+/// Returns the number of scopes of a `push` or `pop`, one if it is omitted.
+fn scope_count(arguments: &[SExpr], line: usize) -> Result<usize, SmtlibError> {
+    match arguments {
+        [] => Ok(1),
+        [SExpr::Atom(count, _)] if count.parse::<usize>().is_ok() => Ok(count.parse().unwrap()),
+        _ => Err(SmtlibError {
+            line,
+            message: "Expected a number of scopes".to_string(),
+        }),
+    }
+}
+
+fn no_model() -> String {
+    "(error \"No model is available\")".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_smtlib() {
+        let script = "(set-logic QF_UF)
+            ; p or q, but not both
+            (declare-const p Bool)
+            (declare-fun |q r| () Bool)
+            (define-fun both () Bool (and p |q r|))
+            (assert (or p |q r|))
+            (assert (not both))
+            (assert (! (=> p false) :named no_p))
+            (check-sat)
+            (get-model)
+            (get-value (p (xor p |q r|) no_p))
+            (get-info :version)
+            (push 1)
+            (assert (= p |q r| true))
+            (check-sat)
+            (get-model)
+            (pop)
+            (check-sat)
+            (exit)
+            (check-sat)";
+        assert_eq!(
+            run_smtlib(script).unwrap(),
+            "sat
+(
+  (define-fun p () Bool false)
+  (define-fun |q r| () Bool true)
+)
+((p false) ((xor p |q r|) true) (no_p true))
+unsupported
+unsat
+(error \"No model is available\")
+sat
+"
+        );
+    }
+
+    #[test]
+    fn test_terms() {
+        // Every term against its truth table over a and b
+        for (term, table) in [
+            ("(ite a b (not b))", [true, false, false, true]),
+            ("(distinct a b)", [false, true, true, false]),
+            ("(=> a b)", [true, true, false, true]),
+            ("(=> a b a)", [true, true, true, true]),
+            ("(= a b false)", [true, false, false, false]),
+            ("(xor a b true)", [true, false, false, true]),
+            (
+                "(let ((a b) (b a)) (and a (not b)))",
+                [false, true, false, false],
+            ),
+            ("(and)", [true, true, true, true]),
+            ("(or)", [false, false, false, false]),
+        ] {
+            for (index, expected) in table.iter().enumerate() {
+                let (a, b) = (index >> 1 & 1 == 1, index & 1 == 1);
+                let script = format!(
+                    "(declare-const a Bool)(declare-const b Bool)
+                     (assert (= a {}))(assert (= b {}))(assert {})(check-sat)",
+                    a, b, term
+                );
+                let answer = if *expected { "sat\n" } else { "unsat\n" };
+                assert_eq!(run_smtlib(&script).unwrap(), answer, "{} {} {}", term, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_smtlib_errors() {
+        for (script, line) in [
+            ("(declare-const p Int)", 1),
+            ("(declare-const p Bool)\n(declare-const p Bool)", 2),
+            ("(assert\n  (and p))", 2),
+            ("(assert (not true false))", 1),
+            ("(check-sat))", 1),
+            ("\n(assert (or true)", 2),
+            ("(pop 1)", 1),
+        ] {
+            assert_eq!(run_smtlib(script).unwrap_err().line, line, "{}", script);
+        }
+
+        // Declarations go away with their scope
+        let mut session = SmtSession::new();
+        session
+            .execute("(push)(declare-const p Bool)(assert p)(check-sat)")
+            .unwrap();
+        assert_eq!(session.get_value("p"), Some(true));
+        session.execute("(pop)").unwrap();
+        assert_eq!(session.is_satisfiable(), None);
+        assert!(session.execute("(assert p)").is_err());
+        assert_eq!(session.execute("(declare-const p Bool)").unwrap(), "");
+
+        // And all of them with the assertions
+        session
+            .execute("(push)(declare-const q Bool)(assert (and p q))(reset-assertions)")
+            .unwrap();
+        assert_eq!(session.execute("(get-assertions)").unwrap(), "()\n");
+        assert_eq!(
+            session
+                .execute("(declare-const p Bool)(declare-const q Bool)(check-sat)")
+                .unwrap(),
+            "sat\n"
+        );
+    }
+}
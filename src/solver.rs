@@ -1,9 +1,25 @@
-use crate::cnf::{to_variable, Assignment};
-use crate::dpll::solve_dpll;
-use crate::expression::{self, Expression};
+use crate::cdcl::{CdclSolver, DeletionPolicy, PropagationResult, RestartPolicy};
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable, CNF};
+use crate::control::SolverControl;
+use crate::dpll::{
+    solve_dpll, solve_dpll_with_budget, solve_dpll_with_control, SearchBudget, SearchResult,
+    SearchTrace,
+};
+use crate::expression::{self, Expression, PolarityMode, PureLiteralMode, SolverHeuristic};
+use crate::memory::MemoryUsage;
+use crate::profile::{start_timer, Phase, PhaseTimes};
+use crate::simplify::strengthen_clauses;
+use crate::symmetry::{break_symmetries, find_symmetries};
+use std::fmt;
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 fn verify_assignment(expression: &Expression, assignment: &Assignment) -> bool {
+    if !expression.satisfies_cardinality_constraints(assignment)
+        || !expression.satisfies_xor_constraints(assignment)
+    {
+        return false;
+    }
     for clause in expression.get_clauses() {
         let mut satisfied = false;
         for literal in clause.literals() {
@@ -24,7 +40,7 @@ fn verify_assignment(expression: &Expression, assignment: &Assignment) -> bool {
         }
     }
 
-    return true;
+    true
 }
 
 pub fn solve(expression: Expression, use_multiple_threads: bool, verify: bool) -> Option<Assignment> {
@@ -62,7 +78,524 @@ pub fn solve(expression: Expression, use_multiple_threads: bool, verify: bool) -
         }
     }
 
-    return solution;
+    solution
+}
+
+/// Solves the expression with the assumed literals fixed to true, without changing it.
+pub fn solve_with_assumptions(
+    expression: &Expression,
+    assumptions: &[Literal],
+) -> Option<Assignment> {
+    let mut working = expression.clone();
+    for literal in assumptions {
+        let mut unit = Clause::new();
+        unit.insert(*literal);
+        working.add_clause(unit);
+    }
+
+    working.optimize();
+    solve_dpll(&mut working)
+}
+
+/// The search algorithm of a solve.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Engine {
+    /// The DPLL search of [`crate::dpll`].
+    #[default]
+    Dpll,
+    /// The conflict-driven clause learning solver of [`crate::cdcl`]. It makes its own decisions,
+    /// so it ignores the heuristic, the seed and the variable groups of the expression.
+    Cdcl,
+}
+
+/// Configures a single-threaded, deterministic solve.
+#[derive(Clone, Copy, Debug)]
+pub struct SolverConfig {
+    pub engine: Engine,
+    pub heuristic: SolverHeuristic,
+    /// When the DPLL search assigns pure literals, see [`PureLiteralMode`].
+    pub pure_literals: PureLiteralMode,
+    /// Which value the search tries first when it branches, see [`PolarityMode`].
+    pub polarity: PolarityMode,
+    pub budget: SearchBudget,
+    /// Whether to measure the time spent in each phase of the solve, see [`SolverStatistics::phases`].
+    pub profile: bool,
+    /// The seed of every randomized choice of the solve, such as the `Random` heuristic.
+    /// Solves with the same configuration take exactly the same steps.
+    pub seed: u64,
+    /// Whether to detect the symmetries of the instance and add lex-leader constraints for them
+    /// before searching, see [`crate::symmetry`].
+    pub break_symmetries: bool,
+    /// Whether to strengthen the clauses by asymmetric literal elimination before searching, see
+    /// [`strengthen_clauses`].
+    pub strengthen: bool,
+    /// Whether the CDCL engine minimizes its learned clauses, see [`CdclSolver::set_minimize`].
+    pub minimize_learned_clauses: bool,
+    /// When the CDCL engine restarts its search. The DPLL search learns no clauses, so restarting
+    /// would only repeat its steps, and it ignores this.
+    pub restarts: RestartPolicy,
+    /// When the CDCL engine deletes the learned clauses with the highest LBD.
+    pub clause_deletion: DeletionPolicy,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            engine: Engine::Dpll,
+            heuristic: SolverHeuristic::MostLiteralOccurances,
+            pure_literals: PureLiteralMode::Dynamic,
+            polarity: PolarityMode::Heuristic,
+            budget: SearchBudget::default(),
+            profile: false,
+            seed: 0,
+            break_symmetries: false,
+            strengthen: false,
+            minimize_learned_clauses: true,
+            restarts: RestartPolicy::default(),
+            clause_deletion: DeletionPolicy::default(),
+        }
+    }
+}
+
+/// Counters describing the work done by a solve.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SolverStatistics {
+    pub num_decisions: usize,
+    pub num_propagations: usize,
+    pub num_pure_literals: usize,
+    pub num_conflicts: usize,
+    /// The literals removed from the clauses before searching.
+    pub num_strengthened_literals: usize,
+    pub time: Duration,
+    /// The memory held by the solver at the end of the search.
+    pub memory: MemoryUsage,
+    /// The time per phase, if profiling was enabled. Parsing happens before the solver is called,
+    /// so callers that parse the instance fill it in themselves.
+    pub phases: PhaseTimes,
+}
+
+/// Formats the statistics as DIMACS comment lines.
+impl fmt::Display for SolverStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "c decisions          {:>12}", self.num_decisions)?;
+        writeln!(f, "c propagations       {:>12}", self.num_propagations)?;
+        writeln!(f, "c pure literals      {:>12}", self.num_pure_literals)?;
+        writeln!(f, "c conflicts          {:>12}", self.num_conflicts)?;
+        writeln!(f, "c removed literals   {:>12}", self.num_strengthened_literals)?;
+        writeln!(f, "c memory             {:>12.1} KiB", self.memory.total() as f64 / 1024.0)?;
+        write!(f, "c solve time         {:>12.3} ms", self.time.as_secs_f64() * 1000.0)?;
+        if self.phases.total() > Duration::ZERO {
+            write!(f, "\n{}", self.phases)?;
+        }
+        Ok(())
+    }
+}
+
+/// Solves the expression according to the configuration, returning `BudgetExhausted` if the
+/// budget runs out first. Models are always verified.
+pub fn solve_with_config(
+    expression: &Expression,
+    config: &SolverConfig,
+) -> (SearchResult, SolverStatistics) {
+    solve_configured(expression, config, None)
+}
+
+/// Solves the expression like [`solve_with_config`], steered by the control: the budget comes
+/// from the settings of the control rather than from the configuration, and follows their changes
+/// during the search. See [`SolverControl::from_config`].
+pub fn solve_with_control(
+    expression: &Expression,
+    config: &SolverConfig,
+    control: &SolverControl,
+) -> (SearchResult, SolverStatistics) {
+    solve_configured(expression, config, Some(control))
+}
+
+fn solve_configured(
+    expression: &Expression,
+    config: &SolverConfig,
+    control: Option<&SolverControl>,
+) -> (SearchResult, SolverStatistics) {
+    let start = Instant::now();
+    let mut phases = PhaseTimes::default();
+
+    let timer = start_timer(config.profile);
+    let mut working = if config.break_symmetries {
+        break_symmetries(expression, &find_symmetries(expression))
+    } else {
+        expression.clone()
+    };
+    let mut num_strengthened_literals = 0;
+    if config.strengthen {
+        (working, num_strengthened_literals) = strengthen_clauses(&working);
+    }
+    working.optimize();
+    working.set_heuristic(config.heuristic);
+    working.set_seed(config.seed);
+    working.set_pure_literal_mode(config.pure_literals);
+    working.set_polarity_mode(config.polarity);
+    phases.stop_timer(Phase::Preprocessing, timer);
+
+    let mut statistics = SolverStatistics {
+        num_strengthened_literals,
+        ..SolverStatistics::default()
+    };
+    let mut result = match config.engine {
+        Engine::Dpll => {
+            let mut trace = SearchTrace::new();
+            let profile = config.profile.then_some(&mut phases);
+            let result = match control {
+                Some(control) => {
+                    solve_dpll_with_control(&mut working, &mut trace, control, profile)
+                }
+                None => solve_dpll_with_budget(&mut working, &mut trace, &config.budget, profile),
+            };
+            statistics.num_decisions = trace.num_decisions;
+            statistics.num_propagations = trace.num_propagations;
+            statistics.num_pure_literals = trace.num_pure_literals;
+            statistics.num_conflicts = trace.num_conflicts;
+            statistics.memory = working.memory_report();
+            result
+        }
+        Engine::Cdcl => {
+            let mut solver = CdclSolver::new(&working);
+            solver.set_budget(config.budget);
+            solver.set_minimize(config.minimize_learned_clauses);
+            solver.set_polarity_mode(config.polarity);
+            solver.set_restarts(config.restarts);
+            solver.set_deletion(config.clause_deletion);
+            if let Some(control) = control {
+                solver.set_control(control);
+            }
+            // The phases of the CDCL search are interleaved too finely to time separately
+            let timer = start_timer(config.profile);
+            let result = solver.solve();
+            phases.stop_timer(Phase::Decision, timer);
+            let cdcl = solver.get_statistics();
+            statistics.num_decisions = cdcl.num_decisions;
+            statistics.num_propagations = cdcl.num_propagations;
+            statistics.num_conflicts = cdcl.num_conflicts;
+            statistics.memory = solver.memory_report();
+            result
+        }
+    };
+    if let SearchResult::Satisfiable(assignment) = &mut result {
+        // Drop the auxiliary variables of the symmetry-breaking constraints
+        let max_variable = expression.get_max_variable();
+        assignment.retain(|variable, _| *variable <= max_variable);
+        if config.strengthen {
+            // Strengthening can remove a variable from every clause, leaving it arbitrary
+            for clause in expression.get_clauses() {
+                for literal in clause.literals() {
+                    assignment.entry(to_variable(*literal)).or_insert(true);
+                }
+            }
+        }
+    }
+    statistics.time = start.elapsed();
+    statistics.phases = phases;
+
+    if let SearchResult::Satisfiable(assignment) = &result {
+        if !verify_assignment(expression, assignment) {
+            panic!("Solution is invalid!");
+        }
+    }
+
+    (result, statistics)
+}
+
+/// A solver that clauses are added to between solves, for callers that build their instance up
+/// as they go instead of handing a finished [`Expression`] to [`solve_with_config`].
+///
+/// Every [`Solver::solve`] solves the clauses added so far, and the model it finds is kept for
+/// [`Solver::value`] until the next solve. Solves under assumptions always use a [`CdclSolver`],
+/// which is kept with its learned clauses from one such solve to the next.
+#[derive(Clone, Default)]
+pub struct Solver {
+    expression: Expression,
+    config: SolverConfig,
+    num_variables: Variable,
+    /// Created by the first solve under assumptions, and given every clause added since.
+    cdcl: Option<CdclSolver>,
+    /// The variables guarding the blocking clauses of [`Solver::models`], left out of the models.
+    selectors: Vec<Variable>,
+    model: Option<Assignment>,
+    /// The model of the solve before the last one, for [`Solver::get_model_delta`].
+    previous_model: Option<Assignment>,
+    failed_assumptions: Vec<Literal>,
+    statistics: SolverStatistics,
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver::default()
+    }
+
+    /// Creates a solver whose solves follow the configuration.
+    pub fn with_config(config: SolverConfig) -> Solver {
+        Solver {
+            config,
+            ..Solver::default()
+        }
+    }
+
+    pub fn get_config(&self) -> &SolverConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: SolverConfig) {
+        self.config = config;
+    }
+
+    /// Returns a variable that no clause uses yet.
+    pub fn new_var(&mut self) -> Variable {
+        assert!(
+            self.num_variables < Literal::MAX as Variable,
+            "The solver is out of variables"
+        );
+        self.num_variables += 1;
+        self.num_variables
+    }
+
+    /// Returns the number of variables, which is the largest one handed out by
+    /// [`Solver::new_var`] or used by a clause.
+    pub fn get_num_variables(&self) -> Variable {
+        self.num_variables
+    }
+
+    /// Adds the clause, whose variables need not come from [`Solver::new_var`]: the variables up
+    /// to the largest one it uses are taken. Duplicate literals are removed.
+    pub fn add_clause(&mut self, literals: &[Literal]) {
+        let mut clause = Clause::new();
+        for literal in literals {
+            assert!(*literal != 0, "0 is not a literal");
+            clause.insert_checked(*literal);
+            self.num_variables = self.num_variables.max(to_variable(*literal));
+        }
+        if let Some(cdcl) = &mut self.cdcl {
+            cdcl.add_clause(clause.literals());
+        }
+        self.expression.add_clause(clause);
+    }
+
+    /// Solves the clauses added so far. The model of a satisfiable result is kept for
+    /// [`Solver::value`], and any other result clears it.
+    pub fn solve(&mut self) -> SearchResult {
+        let (result, statistics) = solve_with_config(&self.expression, &self.config);
+        self.previous_model = self.model.take();
+        self.model = match &result {
+            SearchResult::Satisfiable(model) => Some(model.clone()),
+            _ => None,
+        };
+        self.failed_assumptions.clear();
+        self.statistics = statistics;
+        result
+    }
+
+    /// Solves the clauses added so far with the assumed literals fixed to true, for this solve
+    /// only. If the result is unsatisfiable, [`Solver::get_failed_assumptions`] returns the
+    /// assumptions it depends on. The configuration sets the budget and the learned clause
+    /// policies, whatever its engine.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Literal]) -> SearchResult {
+        let start = Instant::now();
+        for literal in assumptions {
+            assert!(*literal != 0, "0 is not a literal");
+            self.num_variables = self.num_variables.max(to_variable(*literal));
+        }
+        let expression = &self.expression;
+        let cdcl = self.cdcl.get_or_insert_with(|| CdclSolver::new(expression));
+        cdcl.set_budget(self.config.budget);
+        cdcl.set_minimize(self.config.minimize_learned_clauses);
+        cdcl.set_restarts(self.config.restarts);
+        cdcl.set_deletion(self.config.clause_deletion);
+
+        let before = cdcl.get_statistics();
+        let mut result = cdcl.solve_with_assumptions(assumptions);
+        if let SearchResult::Satisfiable(model) = &mut result {
+            for selector in &self.selectors {
+                model.remove(selector);
+            }
+        }
+        let after = cdcl.get_statistics();
+        self.statistics = SolverStatistics {
+            num_decisions: after.num_decisions - before.num_decisions,
+            num_propagations: after.num_propagations - before.num_propagations,
+            num_conflicts: after.num_conflicts - before.num_conflicts,
+            memory: cdcl.memory_report(),
+            time: start.elapsed(),
+            ..SolverStatistics::default()
+        };
+        self.failed_assumptions = cdcl.get_failed_assumptions().to_vec();
+        self.previous_model = self.model.take();
+        self.model = match &result {
+            SearchResult::Satisfiable(model) => {
+                if !verify_assignment(&self.expression, model) {
+                    panic!("Solution is invalid!");
+                }
+                Some(model.clone())
+            }
+            _ => None,
+        };
+        result
+    }
+
+    /// Returns the assumptions that the last solve, if it was unsatisfiable under assumptions,
+    /// depends on: the clauses cannot be satisfied with all of them true. Empty if the clauses
+    /// are unsatisfiable by themselves. See [`CdclSolver::get_failed_assumptions`].
+    pub fn get_failed_assumptions(&self) -> &[Literal] {
+        &self.failed_assumptions
+    }
+
+    /// Runs unit propagation with the assumed literals true, without searching, over the clauses
+    /// added so far and the clauses learned by the solves under assumptions. The model of the last
+    /// solve is kept. See [`CdclSolver::propagate_assumptions`].
+    pub fn propagate_assumptions(&mut self, assumptions: &[Literal]) -> PropagationResult {
+        for literal in assumptions {
+            assert!(*literal != 0, "0 is not a literal");
+            self.num_variables = self.num_variables.max(to_variable(*literal));
+        }
+        let expression = &self.expression;
+        self.cdcl
+            .get_or_insert_with(|| CdclSolver::new(expression))
+            .propagate_assumptions(assumptions)
+    }
+
+    /// Returns the value of the variable in the model of the last solve, or `None` if the last
+    /// solve found no model. Variables that no clause uses are false.
+    pub fn value(&self, variable: Variable) -> Option<bool> {
+        self.model
+            .as_ref()
+            .map(|model| model.get(&variable).copied().unwrap_or(false))
+    }
+
+    /// Returns the model of the last solve, if it found one.
+    pub fn get_model(&self) -> Option<&Assignment> {
+        self.model.as_ref()
+    }
+
+    /// Returns how the model of the last solve differs from the model of the solve before, for
+    /// showing what changed after clauses were added, or `None` if the last solve found no model.
+    /// Variables keep their numbers from one solve to the next, so they are compared as they are.
+    /// If the solve before found no model, every variable is newly assigned.
+    pub fn get_model_delta(&self) -> Option<ModelDelta> {
+        let model = self.model.as_ref()?;
+        let empty = Assignment::new();
+        let previous = self.previous_model.as_ref().unwrap_or(&empty);
+        let mut delta = ModelDelta::default();
+        for (variable, value) in model {
+            let literal = if *value {
+                *variable as Literal
+            } else {
+                -(*variable as Literal)
+            };
+            match previous.get(variable) {
+                Some(previous_value) if previous_value == value => {}
+                Some(_) => delta.flipped.push(literal),
+                None => delta.assigned.push(literal),
+            }
+        }
+        for variable in previous.keys() {
+            if !model.contains_key(variable) {
+                delta.unassigned.push(*variable);
+            }
+        }
+        delta
+            .flipped
+            .sort_unstable_by_key(|literal| to_variable(*literal));
+        delta
+            .assigned
+            .sort_unstable_by_key(|literal| to_variable(*literal));
+        delta.unassigned.sort_unstable();
+        Some(delta)
+    }
+
+    /// Returns the statistics of the last solve.
+    pub fn get_statistics(&self) -> &SolverStatistics {
+        &self.statistics
+    }
+
+    /// Returns the clauses added so far.
+    pub fn get_expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// Returns an iterator over every model of the clauses added so far (AllSAT), each over the
+    /// variables the clauses use. Every model found is blocked by a clause before the next solve,
+    /// which runs under assumptions, so each model comes once and the clauses learned carry over.
+    /// The blocking clauses are guarded by a variable of their own, which is turned off when the
+    /// iterator is dropped, so the solver keeps every model afterwards. The iteration stops early
+    /// if the budget of the configuration runs out.
+    pub fn models(&mut self) -> Models<'_> {
+        let selector = self.new_var();
+        self.selectors.push(selector);
+        Models {
+            solver: self,
+            selector,
+            done: false,
+        }
+    }
+}
+
+/// The difference between the models of two solves, from [`Solver::get_model_delta`]. The
+/// literals are true in the newer model, and every list is sorted by variable.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModelDelta {
+    /// The variables assigned by both models, with the value they changed to.
+    pub flipped: Vec<Literal>,
+    /// The variables only the newer model assigns, with their value.
+    pub assigned: Vec<Literal>,
+    /// The variables only the older model assigns.
+    pub unassigned: Vec<Variable>,
+}
+
+impl ModelDelta {
+    /// Returns whether the models are the same.
+    pub fn is_empty(&self) -> bool {
+        self.flipped.is_empty() && self.assigned.is_empty() && self.unassigned.is_empty()
+    }
+}
+
+/// The iterator of [`Solver::models`].
+pub struct Models<'a> {
+    solver: &'a mut Solver,
+    /// Assumed true by the solves of the iteration, and false in every blocking clause.
+    selector: Variable,
+    done: bool,
+}
+
+impl Iterator for Models<'_> {
+    type Item = Assignment;
+
+    fn next(&mut self) -> Option<Assignment> {
+        if self.done {
+            return None;
+        }
+        let selector = self.selector as Literal;
+        let SearchResult::Satisfiable(model) = self.solver.solve_with_assumptions(&[selector])
+        else {
+            self.done = true;
+            return None;
+        };
+
+        let mut blocking = vec![-selector];
+        for (variable, value) in &model {
+            let literal = *variable as Literal;
+            blocking.push(if *value { -literal } else { literal });
+        }
+        // The blocking clauses only go to the CDCL solver, so the expression stays as it was
+        if let Some(cdcl) = &mut self.solver.cdcl {
+            cdcl.add_clause(&blocking);
+        }
+        Some(model)
+    }
+}
+
+impl Drop for Models<'_> {
+    fn drop(&mut self) {
+        if let Some(cdcl) = &mut self.solver.cdcl {
+            cdcl.add_clause(&[-(self.selector as Literal)]);
+        }
+    }
 }
 
 // Tests
@@ -70,7 +603,7 @@ pub fn solve(expression: Expression, use_multiple_threads: bool, verify: bool) -
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cnf::{Clause, CNF};
+    use crate::cnf::AssignmentExt;
     use crate::expression::Expression;
 
     #[test]
@@ -147,4 +680,315 @@ mod tests {
         assert!(verify_assignment(&expression, &assignment));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_is_implied_and_is_subsumed() {
+        let expression =
+            crate::dimacs_parser::parse_dimacs_str("p cnf 3 3\n1 2 0\n-1 3 0\n-2 3 0\n");
+
+        assert!(expression.is_implied(&Clause::from_literals(&[3])));
+        assert!(!expression.is_subsumed(&Clause::from_literals(&[3])));
+        assert!(expression.is_implied(&Clause::from_literals(&[1, 2, 3])));
+        assert!(expression.is_subsumed(&Clause::from_literals(&[1, 2, 3])));
+        assert!(expression.is_implied(&Clause::from_literals(&[1, -1])));
+        assert!(!expression.is_implied(&Clause::from_literals(&[1])));
+        assert!(!expression.is_implied(&Clause::from_literals(&[-3])));
+    }
+
+    #[test]
+    fn test_solve_requires_backtracking() {
+        let expression = Expression::from_cnf_file("examples/cnf/quinn.cnf").unwrap();
+        assert!(solve(expression, false, true).is_some());
+    }
+
+    #[test]
+    fn test_solve_unsatisfiable() {
+        let expression = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        assert!(solve(expression, true, true).is_none());
+    }
+
+    #[test]
+    fn test_solve_with_config() {
+        let expression = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        let (result, statistics) = solve_with_config(&expression, &SolverConfig::default());
+        assert_eq!(result, SearchResult::Unsatisfiable);
+        assert!(statistics.num_decisions > 0);
+
+        let config = SolverConfig {
+            budget: SearchBudget {
+                max_decisions: Some(5),
+                max_conflicts: None,
+            },
+            ..SolverConfig::default()
+        };
+        let (result, statistics) = solve_with_config(&expression, &config);
+        assert_eq!(result, SearchResult::BudgetExhausted);
+        assert_eq!(statistics.num_decisions, 5);
+    }
+
+    #[test]
+    fn test_solve_with_config_random_heuristic() {
+        let expression = Expression::from_cnf_file("examples/cnf/quinn.cnf").unwrap();
+        let config = |seed| SolverConfig {
+            heuristic: SolverHeuristic::Random,
+            seed,
+            ..SolverConfig::default()
+        };
+
+        for seed in 0..4 {
+            let (result, statistics) = solve_with_config(&expression, &config(seed));
+            assert!(matches!(result, SearchResult::Satisfiable(_)));
+            // Solving again with the same seed takes the same steps
+            let (_, again) = solve_with_config(&expression, &config(seed));
+            assert_eq!(statistics.num_decisions, again.num_decisions);
+            assert_eq!(statistics.num_propagations, again.num_propagations);
+        }
+
+        let hole6 = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        let (result, _) = solve_with_config(&hole6, &config(7));
+        assert_eq!(result, SearchResult::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_solve_with_config_break_symmetries() {
+        let config = SolverConfig {
+            break_symmetries: true,
+            ..SolverConfig::default()
+        };
+
+        let hole6 = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        let (result, statistics) = solve_with_config(&hole6, &config);
+        assert_eq!(result, SearchResult::Unsatisfiable);
+        let (_, unbroken) = solve_with_config(&hole6, &SolverConfig::default());
+        assert!(statistics.num_conflicts < unbroken.num_conflicts);
+
+        let quinn = Expression::from_cnf_file("examples/cnf/quinn.cnf").unwrap();
+        match solve_with_config(&quinn, &config).0 {
+            SearchResult::Satisfiable(model) => assert_eq!(model.len(), 16),
+            result => panic!("Expected a model, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_solve_with_config_strengthen() {
+        let config = SolverConfig {
+            strengthen: true,
+            ..SolverConfig::default()
+        };
+        for file in ["quinn", "aim-50-1_6-yes1-4"] {
+            let expression =
+                Expression::from_cnf_file(&format!("examples/cnf/{}.cnf", file)).unwrap();
+            let (result, statistics) = solve_with_config(&expression, &config);
+            assert!(matches!(result, SearchResult::Satisfiable(_)));
+            assert!(statistics.num_strengthened_literals > 0);
+        }
+    }
+
+    #[test]
+    fn test_solve_with_config_cdcl() {
+        let config = SolverConfig {
+            engine: Engine::Cdcl,
+            ..SolverConfig::default()
+        };
+        // Dubois instances take the DPLL search much longer
+        for (file, satisfiable) in [("dubois20", false), ("hole6", false), ("quinn", true)] {
+            let expression =
+                Expression::from_cnf_file(&format!("examples/cnf/{}.cnf", file)).unwrap();
+            let (result, statistics) = solve_with_config(&expression, &config);
+            assert_eq!(matches!(result, SearchResult::Satisfiable(_)), satisfiable);
+            assert!(statistics.num_decisions > 0);
+        }
+    }
+
+    #[test]
+    fn test_solve_with_config_pure_literals() {
+        for (file, satisfiable) in [
+            ("quinn", true),
+            ("hole6", false),
+            ("aim-50-1_6-yes1-4", true),
+        ] {
+            let expression =
+                Expression::from_cnf_file(&format!("examples/cnf/{}.cnf", file)).unwrap();
+            let mut num_pure_literals = Vec::new();
+            for pure_literals in [
+                PureLiteralMode::Disabled,
+                PureLiteralMode::Preprocessing,
+                PureLiteralMode::Dynamic,
+            ] {
+                for engine in [Engine::Dpll, Engine::Cdcl] {
+                    let config = SolverConfig {
+                        engine,
+                        pure_literals,
+                        ..SolverConfig::default()
+                    };
+                    let (result, statistics) = solve_with_config(&expression, &config);
+                    assert_eq!(matches!(result, SearchResult::Satisfiable(_)), satisfiable);
+                    if engine == Engine::Dpll {
+                        num_pure_literals.push(statistics.num_pure_literals);
+                    } else {
+                        assert_eq!(statistics.num_pure_literals, 0);
+                    }
+                }
+            }
+            assert_eq!(num_pure_literals[0], 0);
+            assert!(num_pure_literals[1] <= num_pure_literals[2]);
+        }
+    }
+
+    #[test]
+    fn test_solve_with_config_profile() {
+        let expression = Expression::from_cnf_file("examples/cnf/hole6.cnf").unwrap();
+        let (_, statistics) = solve_with_config(&expression, &SolverConfig::default());
+        assert_eq!(statistics.phases, PhaseTimes::default());
+
+        let config = SolverConfig {
+            profile: true,
+            ..SolverConfig::default()
+        };
+        let (_, statistics) = solve_with_config(&expression, &config);
+        assert!(statistics.phases.propagation > Duration::ZERO);
+        assert!(statistics.phases.backtracking > Duration::ZERO);
+        assert!(statistics.phases.total() <= statistics.time);
+        assert!(statistics.to_string().contains("c backtracking"));
+    }
+
+    #[test]
+    fn test_incremental_solver() {
+        let mut solver = Solver::new();
+        let (a, b, c) = (solver.new_var(), solver.new_var(), solver.new_var());
+        let (a, b, c) = (a as Literal, b as Literal, c as Literal);
+        solver.add_clause(&[a, b]);
+        solver.add_clause(&[-a, c]);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+        assert!(solver.value(1) == Some(true) || solver.value(2) == Some(true));
+
+        // The clauses added since are solved together with the earlier ones
+        solver.add_clause(&[-c]);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+        assert_eq!(solver.value(1), Some(false));
+        assert_eq!(solver.value(2), Some(true));
+        assert_eq!(solver.value(3), Some(false));
+
+        // A variable no clause uses takes a value too, until a solve finds no model
+        let d = solver.new_var();
+        assert_eq!(d, 4);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+        assert_eq!(solver.value(d), Some(false));
+        solver.add_clause(&[-b, a]);
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+        assert_eq!(solver.value(1), None);
+        assert!(solver.get_model().is_none());
+
+        let mut solver = Solver::with_config(SolverConfig {
+            engine: Engine::Cdcl,
+            ..SolverConfig::default()
+        });
+        solver.add_clause(&[5, -5, 2]);
+        solver.add_clause(&[-2]);
+        assert_eq!(solver.get_num_variables(), 5);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+        assert_eq!(solver.value(2), Some(false));
+        assert_eq!(solver.get_statistics().num_conflicts, 0);
+    }
+
+    #[test]
+    fn test_solver_models() {
+        let mut solver = Solver::new();
+        solver.add_clause(&[1, 2]);
+        solver.add_clause(&[-1, -2, 3]);
+        let mut models: Vec<Assignment> = solver.models().collect();
+        assert_eq!(models.len(), 5);
+        for model in &models {
+            assert_eq!(model.len(), 3);
+            assert!(verify_assignment(solver.get_expression(), model));
+        }
+        models.sort_by_key(|model| model.to_literal_vec());
+        models.dedup();
+        assert_eq!(models.len(), 5);
+
+        // The models are not blocked once the iteration is over
+        assert_eq!(solver.models().take(2).count(), 2);
+        solver.add_clause(&[-3]);
+        let models: Vec<Assignment> = solver.models().collect();
+        assert_eq!(models.len(), 2);
+        assert!(models.iter().all(|model| model.len() == 3));
+        solver.add_clause(&[-1]);
+        solver.add_clause(&[-2]);
+        assert_eq!(solver.models().count(), 0);
+    }
+
+    #[test]
+    fn test_model_delta() {
+        let mut solver = Solver::new();
+        solver.add_clause(&[1, 2]);
+        assert_eq!(solver.get_model_delta(), None);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+        let delta = solver.get_model_delta().unwrap();
+        assert_eq!(delta.assigned, solver.get_model().unwrap().to_literal_vec());
+        assert!(delta.flipped.is_empty() && delta.unassigned.is_empty());
+
+        // Forcing the other value of a variable flips it, and new variables are newly assigned
+        let first = solver.get_model().unwrap().clone();
+        let literal = if first[&1] { -1 } else { 1 };
+        solver.add_clause(&[literal]);
+        solver.add_clause(&[3, -4]);
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+        let delta = solver.get_model_delta().unwrap();
+        assert_eq!(delta.flipped.first(), Some(&literal));
+        assert!(delta.flipped.iter().all(|literal| literal.abs() <= 2));
+        assert_eq!(
+            delta
+                .assigned
+                .iter()
+                .map(|literal| literal.abs())
+                .collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+
+        // The same model again differs in nothing
+        assert!(matches!(solver.solve(), SearchResult::Satisfiable(_)));
+        assert!(solver.get_model_delta().unwrap().is_empty());
+
+        solver.add_clause(&[-literal]);
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+        assert_eq!(solver.get_model_delta(), None);
+    }
+
+    #[test]
+    fn test_incremental_solver_assumptions() {
+        let mut solver = Solver::new();
+        solver.add_clause(&[-1, -2, 3]);
+        solver.add_clause(&[-3, 4]);
+        solver.add_clause(&[5, 6]);
+        assert_eq!(
+            solver.solve_with_assumptions(&[5, 1, 2, -4]),
+            SearchResult::Unsatisfiable
+        );
+        assert_eq!(solver.get_failed_assumptions(), &[1, 2, -4]);
+        assert_eq!(solver.value(1), None);
+        assert_eq!(
+            solver.propagate_assumptions(&[1, 2]),
+            PropagationResult::Implied(vec![1, 2, 3, 4])
+        );
+
+        // The assumptions are dropped after the solve, and clauses added since are kept
+        assert!(matches!(
+            solver.solve_with_assumptions(&[1, -4]),
+            SearchResult::Satisfiable(_)
+        ));
+        assert_eq!(solver.value(2), Some(false));
+        assert!(solver.get_failed_assumptions().is_empty());
+        solver.add_clause(&[-5]);
+        assert!(matches!(
+            solver.solve_with_assumptions(&[]),
+            SearchResult::Satisfiable(_)
+        ));
+        assert_eq!(solver.value(6), Some(true));
+        solver.add_clause(&[-6]);
+        assert_eq!(
+            solver.solve_with_assumptions(&[1]),
+            SearchResult::Unsatisfiable
+        );
+        assert!(solver.get_failed_assumptions().is_empty());
+        assert_eq!(solver.solve(), SearchResult::Unsatisfiable);
+    }
+}
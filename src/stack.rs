@@ -1,3 +1,5 @@
+use crate::cnf::Variable;
+
 /// A simple stack implementation.
 #[derive(Clone)]
 pub struct Stack<T> {
@@ -30,6 +32,12 @@ impl<T> Stack<T> {
         self.stack.len()
     }
 
+    /// Returns the number of values the stack can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.stack.capacity()
+    }
+
     /// Returns true if the stack is empty.
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -52,15 +60,15 @@ impl<T> Stack<T> {
     }
 }
 
-impl Stack<(u16, bool)> {
-    pub fn iter(&self) -> std::slice::Iter<(u16, bool)> {
+impl Stack<(Variable, bool)> {
+    pub fn iter(&self) -> std::slice::Iter<'_, (Variable, bool)> {
         self.stack.iter()
     }
 }
 
-impl IntoIterator for Stack<(u16, bool)> {
-    type Item = (u16, bool);
-    type IntoIter = std::vec::IntoIter<(u16, bool)>;
+impl IntoIterator for Stack<(Variable, bool)> {
+    type Item = (Variable, bool);
+    type IntoIter = std::vec::IntoIter<(Variable, bool)>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.stack.into_iter()
@@ -0,0 +1,443 @@
+//! Detects symmetries of an instance and breaks them with lex-leader constraints.
+//!
+//! A symmetry is a permutation of the literals (commuting with negation) that maps the set of
+//! clauses onto itself, so it maps models to models. The symmetries are found as automorphisms of
+//! the clause-literal graph, with a lightweight individualization-refinement search in the style
+//! of saucy and bliss. Adding a lex-leader constraint for each symmetry keeps only the smallest
+//! model of every orbit, which cuts the search space of instances such as pigeonhole formulas
+//! from exponential to polynomial.
+
+use crate::cnf::{to_variable, Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+use crate::hash::FnvHashSet;
+
+/// The maximum number of partitions the search refines before giving up on finding more
+/// symmetries, which keeps the detection cheap on large instances.
+const MAX_REFINEMENTS: usize = 5000;
+
+/// A symmetry of an expression, as the image of every positive literal.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Symmetry {
+    /// The image of the literal `v` for every variable `v`, at index `v`.
+    image: Vec<Literal>,
+}
+
+impl Symmetry {
+    /// Returns the literal the symmetry maps the literal to.
+    pub fn apply(&self, literal: Literal) -> Literal {
+        match self.image.get(to_variable(literal) as usize) {
+            Some(image) if literal > 0 => *image,
+            Some(image) => -*image,
+            None => literal,
+        }
+    }
+
+    /// Returns the variables moved by the symmetry, in increasing order.
+    pub fn get_support(&self) -> Vec<Variable> {
+        (1..self.image.len())
+            .filter(|variable| self.image[*variable] != *variable as Literal)
+            .map(|variable| variable as Variable)
+            .collect()
+    }
+}
+
+/// The clause-literal graph: a vertex for every literal, linked to its negation and to a vertex
+/// for every clause containing it.
+struct Graph {
+    num_variables: usize,
+    neighbours: Vec<Vec<usize>>,
+    clauses: FnvHashSet<Vec<Literal>>,
+}
+
+#[inline]
+fn literal_vertex(literal: Literal) -> usize {
+    2 * (to_variable(literal) as usize - 1) + (literal < 0) as usize
+}
+
+#[inline]
+fn vertex_literal(vertex: usize) -> Literal {
+    let variable = (vertex / 2 + 1) as Literal;
+    if vertex.is_multiple_of(2) {
+        variable
+    } else {
+        -variable
+    }
+}
+
+/// Returns the number of vertices of every color.
+fn color_sizes(colors: &[usize]) -> Vec<usize> {
+    let mut sizes = vec![0; colors.iter().max().map_or(0, |max| max + 1)];
+    for color in colors {
+        sizes[*color] += 1;
+    }
+    sizes
+}
+
+/// Renames the signatures to dense colors, in the order of the signatures. This is canonical:
+/// isomorphic colorings are renamed alike, so colors can be compared between the branches of the
+/// search.
+fn relabel<T: Ord + Clone>(signatures: &[T]) -> Vec<usize> {
+    let mut unique = signatures.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+    signatures
+        .iter()
+        .map(|signature| unique.binary_search(signature).unwrap())
+        .collect()
+}
+
+impl Graph {
+    fn new(expression: &Expression) -> Graph {
+        let normalized = expression.normalize();
+        let num_variables = normalized.get_max_variable() as usize;
+        let clauses = normalized.get_clauses();
+
+        let mut neighbours = vec![Vec::new(); 2 * num_variables + clauses.len()];
+        for vertex in (0..2 * num_variables).step_by(2) {
+            neighbours[vertex].push(vertex + 1);
+            neighbours[vertex + 1].push(vertex);
+        }
+        for (index, clause) in clauses.iter().enumerate() {
+            let clause_vertex = 2 * num_variables + index;
+            for literal in clause.literals() {
+                neighbours[literal_vertex(*literal)].push(clause_vertex);
+                neighbours[clause_vertex].push(literal_vertex(*literal));
+            }
+        }
+
+        Graph {
+            num_variables,
+            neighbours,
+            clauses: clauses
+                .iter()
+                .map(|clause| sorted(clause.literals()))
+                .collect(),
+        }
+    }
+
+    fn initial_colors(&self) -> Vec<usize> {
+        // Literals and clauses never map onto each other, and the variables that occur in no
+        // clause are fixed, so they do not show up as symmetries
+        let signatures: Vec<(usize, usize)> = (0..self.neighbours.len())
+            .map(|vertex| {
+                if vertex >= 2 * self.num_variables {
+                    (1, 0)
+                } else if self.neighbours[vertex].len() + self.neighbours[vertex ^ 1].len() == 2 {
+                    (2, vertex)
+                } else {
+                    (0, 0)
+                }
+            })
+            .collect();
+        self.refine(relabel(&signatures))
+    }
+
+    /// Refines the coloring until vertices of the same color have the same number of neighbours of
+    /// every color.
+    fn refine(&self, mut colors: Vec<usize>) -> Vec<usize> {
+        let mut num_colors = color_sizes(&colors).len();
+        loop {
+            let signatures: Vec<(usize, Vec<usize>)> = (0..colors.len())
+                .map(|vertex| {
+                    let mut neighbour_colors: Vec<usize> = self.neighbours[vertex]
+                        .iter()
+                        .map(|neighbour| colors[*neighbour])
+                        .collect();
+                    neighbour_colors.sort_unstable();
+                    (colors[vertex], neighbour_colors)
+                })
+                .collect();
+            let refined = relabel(&signatures);
+            let num_refined = color_sizes(&refined).len();
+            colors = refined;
+            if num_refined == num_colors {
+                return colors;
+            }
+            num_colors = num_refined;
+        }
+    }
+
+    /// Gives the vertex a color of its own, and refines the coloring.
+    fn individualize(&self, colors: &[usize], vertex: usize) -> Vec<usize> {
+        let signatures: Vec<(usize, bool)> = colors
+            .iter()
+            .enumerate()
+            .map(|(other, color)| (*color, other == vertex))
+            .collect();
+        self.refine(relabel(&signatures))
+    }
+
+    /// Returns the first color shared by several literals, if any.
+    fn target_color(&self, colors: &[usize]) -> Option<usize> {
+        let sizes = color_sizes(&colors[..2 * self.num_variables]);
+        (0..sizes.len()).find(|color| sizes[*color] > 1)
+    }
+
+    /// Turns two discrete colorings into the permutation mapping the vertices of one to the
+    /// vertices of the same color in the other, if that is a symmetry.
+    fn to_symmetry(&self, from: &[usize], to: &[usize]) -> Option<Symmetry> {
+        let mut vertex_of_color = vec![0; to.len()];
+        for (vertex, color) in to.iter().enumerate() {
+            vertex_of_color[*color] = vertex;
+        }
+        let map = |vertex: usize| vertex_literal(vertex_of_color[from[vertex]]);
+
+        let mut image = vec![0];
+        for variable in 1..=self.num_variables {
+            let positive = map(literal_vertex(variable as Literal));
+            if map(literal_vertex(-(variable as Literal))) != -positive {
+                return None;
+            }
+            image.push(positive);
+        }
+
+        let symmetry = Symmetry { image };
+        let is_symmetry = self.clauses.iter().all(|clause| {
+            let mapped: Vec<Literal> = clause.iter().map(|l| symmetry.apply(*l)).collect();
+            self.clauses.contains(&sorted(&mapped))
+        });
+        is_symmetry.then_some(symmetry)
+    }
+}
+
+fn sorted(literals: &[Literal]) -> Vec<Literal> {
+    let mut literals = literals.to_vec();
+    literals.sort_unstable();
+    literals.dedup();
+    literals
+}
+
+/// A level of the first path of the search: the coloring, and the literal individualized in it.
+struct Level {
+    colors: Vec<usize>,
+    target: usize,
+    vertex: usize,
+}
+
+struct Search<'a> {
+    graph: &'a Graph,
+    /// The first path, ending in a discrete coloring.
+    path: Vec<Level>,
+    leaf: Vec<usize>,
+    num_refinements: usize,
+}
+
+impl<'a> Search<'a> {
+    /// Follows the first path from the given level, mapping the individualized literals to the
+    /// literals of the same colors, until the coloring is discrete and can be compared with the
+    /// leaf of the first path.
+    fn descend(&mut self, colors: Vec<usize>, depth: usize) -> Option<Symmetry> {
+        let expected = match self.path.get(depth) {
+            Some(level) => &level.colors,
+            None => &self.leaf,
+        };
+        if color_sizes(&colors) != color_sizes(expected) {
+            return None;
+        }
+        let Some(level) = self.path.get(depth) else {
+            return self.graph.to_symmetry(&self.leaf, &colors);
+        };
+
+        let target = level.target;
+        for vertex in 0..2 * self.graph.num_variables {
+            if colors[vertex] != target || self.num_refinements >= MAX_REFINEMENTS {
+                continue;
+            }
+            self.num_refinements += 1;
+            let refined = self.graph.individualize(&colors, vertex);
+            if let Some(symmetry) = self.descend(refined, depth + 1) {
+                return Some(symmetry);
+            }
+        }
+
+        None
+    }
+}
+
+/// Disjoint sets of literal vertices, for the orbits of the symmetries found so far.
+struct Orbits {
+    parents: Vec<usize>,
+}
+
+impl Orbits {
+    fn find(&mut self, vertex: usize) -> usize {
+        let mut root = vertex;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        self.parents[vertex] = root;
+        root
+    }
+
+    fn add(&mut self, symmetry: &Symmetry) {
+        for vertex in 0..self.parents.len() {
+            let image = literal_vertex(symmetry.apply(vertex_literal(vertex)));
+            let (a, b) = (self.find(vertex), self.find(image));
+            self.parents[a] = b;
+        }
+    }
+}
+
+/// Finds symmetries of the expression that generate (a large part of) its symmetry group.
+/// The search is incomplete: it gives up on finding more symmetries after a fixed amount of work.
+pub fn find_symmetries(expression: &Expression) -> Vec<Symmetry> {
+    let graph = Graph::new(expression);
+    let mut search = Search {
+        graph: &graph,
+        path: Vec::new(),
+        leaf: graph.initial_colors(),
+        num_refinements: 0,
+    };
+
+    // Build the first path by individualizing the first literal of the first non-singleton color
+    while let Some(target) = graph.target_color(&search.leaf) {
+        let vertex = search.leaf.iter().position(|c| *c == target).unwrap();
+        let refined = graph.individualize(&search.leaf, vertex);
+        let colors = std::mem::replace(&mut search.leaf, refined);
+        search.path.push(Level {
+            colors,
+            target,
+            vertex,
+        });
+    }
+
+    // Try to map the literal individualized at each level to the other literals of its color,
+    // from the bottom up: the symmetries found below a level fix the literals individualized
+    // above it, so literals already in the same orbit can be skipped
+    let mut symmetries = Vec::new();
+    let mut orbits = Orbits {
+        parents: (0..2 * graph.num_variables).collect(),
+    };
+    for depth in (0..search.path.len()).rev() {
+        let level = &search.path[depth];
+        let (colors, target, vertex) = (level.colors.clone(), level.target, level.vertex);
+        for other in 0..2 * graph.num_variables {
+            if colors[other] != target || orbits.find(other) == orbits.find(vertex) {
+                continue;
+            }
+            if search.num_refinements >= MAX_REFINEMENTS {
+                return symmetries;
+            }
+
+            search.num_refinements += 1;
+            let refined = graph.individualize(&colors, other);
+            if let Some(symmetry) = search.descend(refined, depth + 1) {
+                orbits.add(&symmetry);
+                symmetries.push(symmetry);
+            }
+        }
+    }
+
+    symmetries
+}
+
+/// Returns the expression with lex-leader constraints added for the symmetries: with the variables
+/// in increasing order, only models that are lexicographically smallest (false before true) among
+/// their images under each symmetry remain. The result is satisfiable if and only if the
+/// expression is, and its models restricted to the original variables are models of the
+/// expression.
+///
+/// The constraints use auxiliary variables numbered after the largest variable of the expression.
+/// Symmetries are skipped once the variables run out.
+pub fn break_symmetries(expression: &Expression, symmetries: &[Symmetry]) -> Expression {
+    let mut broken = expression.clone();
+    let mut next_variable = i128::from(expression.get_max_variable()) + 1;
+    let add = |broken: &mut Expression, literals: &[Literal]| {
+        broken.add_clause(Clause::from_literals(literals))
+    };
+
+    for symmetry in symmetries {
+        let support = symmetry.get_support();
+        if next_variable + support.len() as i128 > i128::from(Literal::MAX) {
+            break;
+        }
+
+        // `equal` is true if the model and its image agree on the variables before the current one
+        let mut equal: Option<Literal> = None;
+        for (index, variable) in support.iter().enumerate() {
+            let x = *variable as Literal;
+            let y = symmetry.apply(x);
+            let prefix: Vec<Literal> = equal.map(|e| -e).into_iter().collect();
+
+            if y == -x {
+                // x <= -x forces x to false, after which the model and its image differ
+                add(&mut broken, &[prefix.as_slice(), &[-x]].concat());
+                break;
+            }
+            add(&mut broken, &[prefix.as_slice(), &[-x, y]].concat());
+            if index + 1 == support.len() {
+                break;
+            }
+
+            let next = next_variable as Literal;
+            next_variable += 1;
+            add(&mut broken, &[prefix.as_slice(), &[-x, next]].concat());
+            add(&mut broken, &[prefix.as_slice(), &[y, next]].concat());
+            equal = Some(next);
+        }
+    }
+
+    broken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::pigeonhole;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::dpll::{solve_dpll_traced, SearchTrace};
+
+    fn num_conflicts(expression: &Expression) -> (bool, usize) {
+        let mut working = expression.clone();
+        working.optimize();
+        let mut trace = SearchTrace::new();
+        let satisfiable = solve_dpll_traced(&mut working, &mut trace).is_some();
+        (satisfiable, trace.num_conflicts)
+    }
+
+    #[test]
+    fn test_find_symmetries() {
+        let expression = pigeonhole(4);
+        let symmetries = find_symmetries(&expression);
+        assert!(!symmetries.is_empty());
+
+        let clauses: FnvHashSet<Vec<Literal>> = expression
+            .get_clauses()
+            .iter()
+            .map(|clause| sorted(clause.literals()))
+            .collect();
+        for symmetry in &symmetries {
+            for clause in &clauses {
+                let mapped: Vec<Literal> = clause.iter().map(|l| symmetry.apply(*l)).collect();
+                assert!(clauses.contains(&sorted(&mapped)));
+            }
+        }
+
+        // The variable 3 occurs in no clause, and 1 and 2 only together
+        let expression = parse_dimacs_str("p cnf 3 2\n1 2 0\n-1 -2 0\n");
+        let symmetries = find_symmetries(&expression);
+        assert!(symmetries.iter().all(|s| s.apply(3) == 3));
+        assert!(!symmetries.is_empty());
+    }
+
+    #[test]
+    fn test_break_symmetries() {
+        let expression = pigeonhole(6);
+        let broken = break_symmetries(&expression, &find_symmetries(&expression));
+        let (satisfiable, conflicts) = num_conflicts(&broken);
+        assert!(!satisfiable);
+        assert!(conflicts * 10 < num_conflicts(&expression).1);
+
+        for contents in [
+            "p cnf 3 1\n1 2 3 0\n",
+            "p cnf 4 4\n1 2 0\n3 4 0\n-1 -3 0\n-2 -4 0\n",
+        ] {
+            let expression = parse_dimacs_str(contents);
+            let broken = break_symmetries(&expression, &find_symmetries(&expression));
+            let mut working = broken.clone();
+            working.optimize();
+            let model = crate::dpll::solve_dpll(&mut working).unwrap();
+            assert!(expression.is_satisfied_by(&model));
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! Instance templates: clauses written over named arrays of variables, such as `x[i][j]`, and
+//! instantiated for concrete sizes into an [`Expression`].
+//!
+//! A template is a function of its sizes that declares its arrays on a [`TemplateBuilder`] and
+//! adds clauses over their elements. The builder numbers the elements of every array densely in
+//! row-major order, after the arrays declared before it, and checks every index against the
+//! dimensions of its array, so encodings need no hand-written numbering formula. Instantiating a
+//! template returns the [`IndexMap`] from the indices to the variables alongside the expression,
+//! to read the models back in terms of the arrays.
+
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+
+/// An array of variables of a template, numbered in row-major order from its first variable.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VariableArray {
+    name: String,
+    dimensions: Vec<usize>,
+    /// The variable before the first element.
+    offset: Variable,
+}
+
+impl VariableArray {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_dimensions(&self) -> &[usize] {
+        &self.dimensions
+    }
+
+    /// Returns the number of elements of the array, the product of its dimensions.
+    pub fn len(&self) -> usize {
+        self.dimensions.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the array has an element at the indices, one per dimension.
+    pub fn contains(&self, indices: &[usize]) -> bool {
+        indices.len() == self.dimensions.len()
+            && indices
+                .iter()
+                .zip(&self.dimensions)
+                .all(|(index, dimension)| index < dimension)
+    }
+
+    /// Returns the positive literal of the element at the indices, one per dimension. Panics if
+    /// an index is out of bounds.
+    pub fn at(&self, indices: &[usize]) -> Literal {
+        assert!(
+            self.contains(indices),
+            "{}{:?} is out of bounds for the dimensions {:?}",
+            self.name,
+            indices,
+            self.dimensions
+        );
+        let position = indices
+            .iter()
+            .zip(&self.dimensions)
+            .fold(0, |position, (index, dimension)| {
+                position * dimension + index
+            });
+        (self.offset as usize + position + 1) as Literal
+    }
+
+    /// Returns the indices of the element the variable stands for, or `None` if it is not an
+    /// element of the array.
+    pub fn get_indices(&self, variable: Variable) -> Option<Vec<usize>> {
+        let mut position = (variable as usize).checked_sub(self.offset as usize + 1)?;
+        if position >= self.len() {
+            return None;
+        }
+        let mut indices = vec![0; self.dimensions.len()];
+        for (index, dimension) in indices.iter_mut().zip(&self.dimensions).rev() {
+            *index = position % dimension;
+            position /= dimension;
+        }
+        Some(indices)
+    }
+}
+
+/// Collects the arrays and the clauses of a template instance.
+#[derive(Clone, Default)]
+pub struct TemplateBuilder {
+    arrays: Vec<VariableArray>,
+    expression: Expression,
+    num_variables: Variable,
+}
+
+impl TemplateBuilder {
+    pub fn new() -> TemplateBuilder {
+        TemplateBuilder::default()
+    }
+
+    /// Declares an array with the dimensions, whose elements take the variables after those
+    /// declared so far. Panics if the name is taken, or if the variables run out.
+    pub fn add_array(&mut self, name: &str, dimensions: &[usize]) -> VariableArray {
+        assert!(
+            self.arrays.iter().all(|array| array.name != name),
+            "The array {} is declared twice",
+            name
+        );
+        let array = VariableArray {
+            name: name.to_string(),
+            dimensions: dimensions.to_vec(),
+            offset: self.num_variables,
+        };
+        let num_variables = self.num_variables as usize + array.len();
+        assert!(
+            num_variables <= Literal::MAX as usize,
+            "The array {} needs more variables than are available",
+            name
+        );
+        self.num_variables = num_variables as Variable;
+        self.arrays.push(array.clone());
+        array
+    }
+
+    pub fn get_num_variables(&self) -> Variable {
+        self.num_variables
+    }
+
+    pub fn add_clause(&mut self, literals: &[Literal]) {
+        let mut clause = Clause::new();
+        for literal in literals {
+            assert!(
+                to_variable(*literal) <= self.num_variables,
+                "{} is not an element of any array",
+                literal
+            );
+            clause.insert_checked(*literal);
+        }
+        self.expression.add_clause(clause);
+    }
+
+    /// Adds a binary clause for every pair of the literals, so at most one of them holds.
+    pub fn add_at_most_one(&mut self, literals: &[Literal]) {
+        for (position, first) in literals.iter().enumerate() {
+            for second in &literals[position + 1..] {
+                self.add_clause(&[-first, -second]);
+            }
+        }
+    }
+
+    /// Adds the clauses making exactly one of the literals hold.
+    pub fn add_exactly_one(&mut self, literals: &[Literal]) {
+        self.add_clause(literals);
+        self.add_at_most_one(literals);
+    }
+
+    /// Returns the expression of the clauses added, and the map from the indices of the arrays to
+    /// their variables.
+    pub fn finish(self) -> (Expression, IndexMap) {
+        (
+            self.expression,
+            IndexMap {
+                arrays: self.arrays,
+            },
+        )
+    }
+}
+
+/// Instantiates the template, a function adding arrays and clauses to the builder, typically a
+/// closure over the sizes of the instance.
+pub fn instantiate(template: impl FnOnce(&mut TemplateBuilder)) -> (Expression, IndexMap) {
+    let mut builder = TemplateBuilder::new();
+    template(&mut builder);
+    builder.finish()
+}
+
+/// The arrays of a template instance, mapping their indices to the variables of the expression
+/// and back.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IndexMap {
+    arrays: Vec<VariableArray>,
+}
+
+impl IndexMap {
+    /// Returns the arrays, in the order they were declared.
+    pub fn get_arrays(&self) -> &[VariableArray] {
+        &self.arrays
+    }
+
+    pub fn get_array(&self, name: &str) -> Option<&VariableArray> {
+        self.arrays.iter().find(|array| array.name == name)
+    }
+
+    /// Returns the variable of the element of the named array at the indices, or `None` if there
+    /// is no such array or element.
+    pub fn get_variable(&self, name: &str, indices: &[usize]) -> Option<Variable> {
+        let array = self.get_array(name)?;
+        array
+            .contains(indices)
+            .then(|| array.at(indices) as Variable)
+    }
+
+    /// Returns the name of the array and the indices of the element the variable stands for.
+    pub fn get_index(&self, variable: Variable) -> Option<(&str, Vec<usize>)> {
+        self.arrays.iter().find_map(|array| {
+            array
+                .get_indices(variable)
+                .map(|indices| (array.name.as_str(), indices))
+        })
+    }
+
+    /// Returns the indices of the elements of the named array that are true in the model, in
+    /// row-major order. Elements missing from the model are false.
+    pub fn get_true_indices(&self, name: &str, model: &Assignment) -> Vec<Vec<usize>> {
+        let Some(array) = self.get_array(name) else {
+            return Vec::new();
+        };
+        (1..=array.len())
+            .map(|position| array.offset + position as Variable)
+            .filter(|variable| model.get(variable).copied().unwrap_or(false))
+            .filter_map(|variable| array.get_indices(variable))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::solve;
+
+    #[test]
+    fn test_variable_array() {
+        let mut builder = TemplateBuilder::new();
+        let x = builder.add_array("x", &[2, 3]);
+        let y = builder.add_array("y", &[4]);
+        assert_eq!(x.at(&[0, 0]), 1);
+        assert_eq!(x.at(&[1, 2]), 6);
+        assert_eq!(y.at(&[0]), 7);
+        assert_eq!(builder.get_num_variables(), 10);
+        assert_eq!(x.get_indices(5), Some(vec![1, 1]));
+        assert_eq!(x.get_indices(7), None);
+
+        let (_, map) = builder.finish();
+        assert_eq!(map.get_variable("y", &[3]), Some(10));
+        assert_eq!(map.get_variable("y", &[4]), None);
+        assert_eq!(map.get_variable("x", &[1]), None);
+        assert_eq!(map.get_index(8), Some(("y", vec![1])));
+        assert_eq!(map.get_index(11), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "x[2, 0] is out of bounds")]
+    fn test_variable_array_out_of_bounds() {
+        let mut builder = TemplateBuilder::new();
+        let x = builder.add_array("x", &[2, 3]);
+        x.at(&[2, 0]);
+    }
+
+    #[test]
+    fn test_instantiate() {
+        // n queens, one per row and column, and at most one per diagonal
+        let queens = |n: usize| {
+            instantiate(|builder| {
+                let q = builder.add_array("q", &[n, n]);
+                for i in 0..n {
+                    let row: Vec<Literal> = (0..n).map(|j| q.at(&[i, j])).collect();
+                    builder.add_exactly_one(&row);
+                    let column: Vec<Literal> = (0..n).map(|j| q.at(&[j, i])).collect();
+                    builder.add_exactly_one(&column);
+                }
+                for first in 0..n * n {
+                    for second in first + 1..n * n {
+                        let (i, j) = (first / n, first % n);
+                        let (k, l) = (second / n, second % n);
+                        if i.abs_diff(k) == j.abs_diff(l) {
+                            builder.add_clause(&[-q.at(&[i, j]), -q.at(&[k, l])]);
+                        }
+                    }
+                }
+            })
+        };
+
+        let (expression, _) = queens(3);
+        assert_eq!(solve(expression, false, true), None);
+
+        let (expression, map) = queens(5);
+        assert_eq!(expression.get_max_variable(), 25);
+        let model = solve(expression, false, true).unwrap();
+        let placed = map.get_true_indices("q", &model);
+        assert_eq!(placed.len(), 5);
+        for (first, a) in placed.iter().enumerate() {
+            for b in &placed[first + 1..] {
+                assert_ne!(a[0], b[0]);
+                assert_ne!(a[1], b[1]);
+                assert_ne!(a[0].abs_diff(b[0]), a[1].abs_diff(b[1]));
+            }
+        }
+    }
+}
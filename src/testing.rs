@@ -0,0 +1,349 @@
+//! Helpers behind the [`assert_sat!`](crate::assert_sat), [`assert_unsat!`](crate::assert_unsat)
+//! and [`assert_model!`](crate::assert_model) macros, for testing encodings built on this crate.
+
+use crate::cnf::{to_variable, Assignment, Clause, Literal, CNF};
+use crate::dimacs_parser::parse_dimacs_str;
+use crate::dpll::{solve_dpll_traced, SearchTrace};
+use crate::expression::{Expression, SolverHeuristic};
+use crate::solver::solve;
+
+/// Inputs accepted by the assertion macros.
+pub trait IntoExpression {
+    fn into_expression(self) -> Expression;
+}
+
+impl IntoExpression for Expression {
+    fn into_expression(self) -> Expression {
+        self
+    }
+}
+
+impl IntoExpression for &Expression {
+    fn into_expression(self) -> Expression {
+        self.clone()
+    }
+}
+
+/// DIMACS text.
+impl IntoExpression for &str {
+    fn into_expression(self) -> Expression {
+        parse_dimacs_str(self)
+    }
+}
+
+impl IntoExpression for &String {
+    fn into_expression(self) -> Expression {
+        parse_dimacs_str(self)
+    }
+}
+
+impl IntoExpression for Vec<Vec<Literal>> {
+    fn into_expression(self) -> Expression {
+        let mut expression = Expression::new();
+        for literals in self {
+            let mut clause = Clause::new();
+            for literal in literals {
+                assert!(literal != 0, "Literals cannot be 0");
+                clause.insert(literal);
+            }
+            expression.add_clause(clause);
+        }
+        expression
+    }
+}
+
+fn format_clause(literals: &[Literal]) -> String {
+    let literals: Vec<String> = literals.iter().map(|l| l.to_string()).collect();
+    format!("[{}]", literals.join(", "))
+}
+
+/// Formats the assignment as a sorted list of literals.
+fn format_assignment(assignment: &Assignment) -> String {
+    let mut literals: Vec<Literal> = assignment
+        .iter()
+        .map(|(variable, value)| {
+            if *value {
+                *variable as Literal
+            } else {
+                -(*variable as Literal)
+            }
+        })
+        .collect();
+    literals.sort_by_key(|literal| (literal.unsigned_abs(), *literal));
+    format_clause(&literals)
+}
+
+/// Returns the first clause of the expression that the assignment does not satisfy.
+fn unsatisfied_clause(expression: &Expression, assignment: &Assignment) -> Option<Clause> {
+    expression.get_clauses().into_iter().find(|clause| {
+        !clause
+            .literals()
+            .iter()
+            .any(|literal| assignment.get(&to_variable(*literal)).copied() == Some(*literal > 0))
+    })
+}
+
+fn check_model(expression: &Expression, model: &Assignment, description: &str) {
+    if let Some(clause) = unsatisfied_clause(expression, model) {
+        panic!(
+            "assertion failed: the model returned for `{}` does not satisfy clause {}\n  model: {}",
+            description,
+            format_clause(clause.literals()),
+            format_assignment(model)
+        );
+    }
+}
+
+#[doc(hidden)]
+pub fn assert_sat(expression: Expression, description: &str) -> Assignment {
+    match solve(expression.clone(), false, false) {
+        Some(model) => {
+            check_model(&expression, &model, description);
+            model
+        }
+        None => panic!(
+            "assertion failed: `{}` is satisfiable\n  the solver reported UNSAT for {} clauses",
+            description,
+            expression.get_clauses().len()
+        ),
+    }
+}
+
+#[doc(hidden)]
+pub fn assert_unsat(expression: Expression, description: &str) {
+    if let Some(model) = solve(expression.clone(), false, false) {
+        let verified = unsatisfied_clause(&expression, &model).is_none();
+        panic!(
+            "assertion failed: `{}` is unsatisfiable\n  the solver found a model ({}): {}",
+            description,
+            if verified {
+                "verified"
+            } else {
+                "which does not verify"
+            },
+            format_assignment(&model)
+        );
+    }
+}
+
+#[doc(hidden)]
+pub fn assert_model(expression: Expression, literals: &[Literal], description: &str) -> Assignment {
+    let mut constrained = expression.clone();
+    for literal in literals {
+        let mut clause = Clause::new();
+        clause.insert(*literal);
+        constrained.add_clause(clause);
+    }
+
+    match solve(constrained, false, false) {
+        Some(model) => {
+            check_model(&expression, &model, description);
+            model
+        }
+        None => {
+            let mut partial = Assignment::new();
+            for literal in literals {
+                partial.insert(to_variable(*literal), *literal > 0);
+            }
+            // Only blame the literals if they alone falsify a clause
+            let falsified = expression.get_clauses().into_iter().find(|clause| {
+                clause.literals().iter().all(|literal| {
+                    partial.get(&to_variable(*literal)).copied() == Some(*literal < 0)
+                })
+            });
+            match falsified {
+                Some(clause) => panic!(
+                    "assertion failed: `{}` has a model extending {}\n  the literals falsify clause {}",
+                    description,
+                    format_clause(literals),
+                    format_clause(clause.literals())
+                ),
+                None => panic!(
+                    "assertion failed: `{}` has a model extending {}\n  the solver reported UNSAT",
+                    description,
+                    format_clause(literals)
+                ),
+            }
+        }
+    }
+}
+
+/// Runs a single-threaded, deterministic DPLL search on the formula and returns its trace.
+pub fn search_trace(formula: impl IntoExpression, heuristic: SolverHeuristic) -> SearchTrace {
+    let mut expression = formula.into_expression();
+    expression.set_heuristic(heuristic);
+    let mut trace = SearchTrace::new();
+    solve_dpll_traced(&mut expression, &mut trace);
+    trace
+}
+
+#[doc(hidden)]
+pub fn assert_search_digest(
+    expression: Expression,
+    heuristic: SolverHeuristic,
+    expected: u64,
+    description: &str,
+) {
+    let trace = search_trace(expression, heuristic);
+    if trace.digest() != expected {
+        panic!(
+            "assertion failed: the search on `{}` with {:?} has digest {:#018x}\n  actual: {:#018x} ({} decisions, {} propagations, {} pure literals, {} conflicts)\n  update the expected digest if the change in search behavior is intended",
+            description,
+            heuristic,
+            expected,
+            trace.digest(),
+            trace.num_decisions,
+            trace.num_propagations,
+            trace.num_pure_literals,
+            trace.num_conflicts
+        );
+    }
+}
+
+/// Asserts that a formula is satisfiable and that the model found satisfies every clause,
+/// returning the model.
+///
+/// The formula can be written as a list of clauses, such as `assert_sat!([[1, 2], [-1]])`, or be
+/// any expression implementing [`IntoExpression`], such as a DIMACS string or an `Expression`.
+#[macro_export]
+macro_rules! assert_sat {
+    ([$([$($literal:expr),* $(,)?]),* $(,)?]) => {
+        $crate::testing::assert_sat(
+            $crate::testing::IntoExpression::into_expression(
+                vec![$(vec![$($literal as $crate::cnf::Literal),*]),*]
+            ),
+            stringify!([$([$($literal),*]),*]),
+        )
+    };
+    ($formula:expr) => {
+        $crate::testing::assert_sat(
+            $crate::testing::IntoExpression::into_expression($formula),
+            stringify!($formula),
+        )
+    };
+}
+
+/// Asserts that a formula is unsatisfiable, accepting the same inputs as
+/// [`assert_sat!`](crate::assert_sat).
+#[macro_export]
+macro_rules! assert_unsat {
+    ([$([$($literal:expr),* $(,)?]),* $(,)?]) => {
+        $crate::testing::assert_unsat(
+            $crate::testing::IntoExpression::into_expression(
+                vec![$(vec![$($literal as $crate::cnf::Literal),*]),*]
+            ),
+            stringify!([$([$($literal),*]),*]),
+        )
+    };
+    ($formula:expr) => {
+        $crate::testing::assert_unsat(
+            $crate::testing::IntoExpression::into_expression($formula),
+            stringify!($formula),
+        )
+    };
+}
+
+/// Asserts that a formula has a model containing the given literals, such as
+/// `assert_model!(formula, [1, -3])`, and returns the model after verifying it.
+#[macro_export]
+macro_rules! assert_model {
+    ([$([$($literal:expr),* $(,)?]),* $(,)?], [$($assumption:expr),* $(,)?]) => {
+        $crate::testing::assert_model(
+            $crate::testing::IntoExpression::into_expression(
+                vec![$(vec![$($literal as $crate::cnf::Literal),*]),*]
+            ),
+            &[$($assumption as $crate::cnf::Literal),*],
+            stringify!([$([$($literal),*]),*]),
+        )
+    };
+    ($formula:expr, [$($assumption:expr),* $(,)?]) => {
+        $crate::testing::assert_model(
+            $crate::testing::IntoExpression::into_expression($formula),
+            &[$($assumption as $crate::cnf::Literal),*],
+            stringify!($formula),
+        )
+    };
+}
+
+/// Asserts that the deterministic DPLL search on a formula produces the expected digest, such as
+/// `assert_search_digest!(formula, 0x1234_5678_9abc_def0)`. The heuristic defaults to
+/// `MostLiteralOccurances` and can be given as a third argument.
+///
+/// Pinning digests in tests catches changes to data structures that alter the search order.
+#[macro_export]
+macro_rules! assert_search_digest {
+    ($formula:expr, $expected:expr) => {
+        $crate::assert_search_digest!(
+            $formula,
+            $expected,
+            $crate::expression::SolverHeuristic::MostLiteralOccurances
+        )
+    };
+    ($formula:expr, $expected:expr, $heuristic:expr) => {
+        $crate::testing::assert_search_digest(
+            $crate::testing::IntoExpression::into_expression($formula),
+            $heuristic,
+            $expected,
+            stringify!($formula),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::benchmarks::{generate, pigeonhole};
+    use crate::expression::SolverHeuristic;
+
+    #[test]
+    fn test_assert_sat_and_unsat() {
+        let model = assert_sat!([[1, 2], [-1], [2, 3]]);
+        assert!(model[&2]);
+
+        assert_sat!("p cnf 2 2\n1 2 0\n-1 0\n");
+        assert_unsat!([[1], [-1, 2], [-2]]);
+        assert_unsat!(pigeonhole(3));
+    }
+
+    #[test]
+    fn test_assert_model() {
+        let model = assert_model!([[1, 2, 3], [-1, -2]], [-1, -3]);
+        assert!(model[&2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "`[[1], [-1]]` is satisfiable")]
+    fn test_assert_sat_failure_message() {
+        assert_sat!([[1], [-1]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the literals falsify clause [1, 2]")]
+    fn test_assert_model_failure_message() {
+        assert_model!([[1, 2], [3]], [-1, -2]);
+    }
+
+    #[test]
+    fn test_search_digest_snapshots() {
+        // The digests hash the literals, and the order of hash sets of them, so they change with
+        // the width of a literal
+        #[cfg(all(feature = "literals-16", not(feature = "literals-64")))]
+        let digests = [0x48661bcf95f044ff, 0xa6f73e3a5a6186aa];
+        #[cfg(not(any(feature = "literals-16", feature = "literals-64")))]
+        let digests = [0x41b637ba46d91679, 0x87e1da5418f0a68e];
+        #[cfg(feature = "literals-64")]
+        let digests = [0x85d4a57d86d8b6ab, 0x0a5baf2f26cb441a];
+
+        assert_search_digest!(generate("pigeonhole-4").unwrap().expression, digests[0]);
+        assert_search_digest!(
+            generate("random-3sat-40-4.26").unwrap().expression,
+            digests[1],
+            SolverHeuristic::MinimizeClauseLength
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "update the expected digest")]
+    fn test_search_digest_mismatch() {
+        assert_search_digest!("p cnf 2 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n", 0);
+    }
+}
@@ -0,0 +1,203 @@
+//! The assignment of a search, kept as a trail: the assigned literals in the order they were
+//! assigned, grouped into decision levels, with the clause that implied each of them.
+//!
+//! A decision level starts with a decision and holds the literals assigned because of it, so
+//! backtracking to a level (or to any earlier length of the trail) is a truncation. The values,
+//! levels and reasons are indexed by variable, which makes looking up a variable a vector access.
+//! Both the DPLL search of [`Expression`](crate::expression::Expression) and the
+//! [`CdclSolver`](crate::cdcl::CdclSolver) keep their assignment on a trail; the CDCL solver also
+//! uses the levels and reasons for conflict analysis and backjumping.
+
+use crate::cnf::{to_variable, Assignment, Literal, Variable};
+use crate::memory::vec_bytes;
+
+#[derive(Clone, Debug, Default)]
+pub struct Trail {
+    literals: Vec<Literal>,
+    /// The position in `literals` where each decision level above 0 starts.
+    limits: Vec<usize>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+}
+
+impl Trail {
+    pub fn new() -> Trail {
+        Trail::default()
+    }
+
+    /// Creates an empty trail with room for the variables up to `max_variable`.
+    pub fn with_max_variable(max_variable: Variable) -> Trail {
+        let mut trail = Trail::new();
+        trail.reserve(max_variable);
+        trail
+    }
+
+    fn reserve(&mut self, variable: Variable) {
+        let len = variable as usize + 1;
+        if len > self.values.len() {
+            self.values.resize(len, None);
+            self.levels.resize(len, 0);
+            self.reasons.resize(len, None);
+        }
+    }
+
+    /// Returns the number of assigned variables.
+    pub fn len(&self) -> usize {
+        self.literals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.literals.is_empty()
+    }
+
+    /// Returns the assigned literals, in the order they were assigned.
+    pub fn get_literals(&self) -> &[Literal] {
+        &self.literals
+    }
+
+    /// Returns the value of the variable, or `None` if it is unassigned.
+    pub fn value(&self, variable: Variable) -> Option<bool> {
+        self.values.get(variable as usize).copied().flatten()
+    }
+
+    /// Returns the value of the literal under the assignment.
+    pub fn literal_value(&self, literal: Literal) -> Option<bool> {
+        self.value(to_variable(literal))
+            .map(|value| value == (literal > 0))
+    }
+
+    pub fn is_assigned(&self, variable: Variable) -> bool {
+        self.value(variable).is_some()
+    }
+
+    /// Returns the current decision level: the number of decisions on the trail.
+    pub fn get_decision_level(&self) -> usize {
+        self.limits.len()
+    }
+
+    /// Returns the decision level at which the variable was assigned, or `None` if it is
+    /// unassigned.
+    pub fn decision_level(&self, variable: Variable) -> Option<usize> {
+        self.value(variable).map(|_| self.levels[variable as usize])
+    }
+
+    /// Returns the clause that implied the value of the variable, or `None` if the variable was
+    /// not implied by a clause (a decision, for example) or is unassigned.
+    pub fn reason(&self, variable: Variable) -> Option<usize> {
+        self.reasons.get(variable as usize).copied().flatten()
+    }
+
+    /// Starts a new decision level, whose first assignment is the decision.
+    pub fn new_decision_level(&mut self) {
+        self.limits.push(self.literals.len());
+    }
+
+    /// Makes the unassigned literal true at the current decision level.
+    pub fn assign(&mut self, literal: Literal, reason: Option<usize>) {
+        let variable = to_variable(literal);
+        self.reserve(variable);
+        let index = variable as usize;
+        debug_assert!(
+            self.values[index].is_none(),
+            "{} is assigned twice",
+            variable
+        );
+        self.values[index] = Some(literal > 0);
+        self.levels[index] = self.limits.len();
+        self.reasons[index] = reason;
+        self.literals.push(literal);
+    }
+
+    /// Undoes the assignments from position `len` of the trail on, along with the decision levels
+    /// they started.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.literals.len() {
+            return;
+        }
+        self.unassign_from(len);
+        while self.limits.last().is_some_and(|limit| *limit >= len) {
+            self.limits.pop();
+        }
+    }
+
+    /// Undoes the assignments above the decision level. Unlike [`Trail::truncate`], this keeps
+    /// the levels up to `level` even if they are empty.
+    pub fn backtrack(&mut self, level: usize) {
+        if let Some(start) = self.limits.get(level).copied() {
+            self.unassign_from(start);
+            self.limits.truncate(level);
+        }
+    }
+
+    fn unassign_from(&mut self, len: usize) {
+        for literal in self.literals.drain(len..) {
+            let index = to_variable(literal) as usize;
+            self.values[index] = None;
+            self.reasons[index] = None;
+        }
+    }
+
+    /// Replaces the reasons of the assigned variables, for when the clauses are renumbered.
+    pub(crate) fn map_reasons(&mut self, map: impl Fn(usize) -> usize) {
+        for literal in &self.literals {
+            let index = to_variable(*literal) as usize;
+            self.reasons[index] = self.reasons[index].map(&map);
+        }
+    }
+
+    /// Returns the assigned variables with their values.
+    pub fn to_assignment(&self) -> Assignment {
+        self.literals
+            .iter()
+            .map(|literal| (to_variable(*literal), *literal > 0))
+            .collect()
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        vec_bytes(&self.literals)
+            + vec_bytes(&self.limits)
+            + vec_bytes(&self.values)
+            + vec_bytes(&self.levels)
+            + vec_bytes(&self.reasons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trail() {
+        let mut trail = Trail::with_max_variable(3);
+        trail.assign(1, Some(0));
+        trail.new_decision_level();
+        trail.assign(-2, None);
+        trail.assign(3, Some(4));
+        trail.new_decision_level();
+        trail.assign(5, None);
+
+        assert_eq!(trail.get_literals(), &[1, -2, 3, 5]);
+        assert_eq!(trail.get_decision_level(), 2);
+        assert_eq!(trail.value(2), Some(false));
+        assert_eq!(trail.literal_value(-2), Some(true));
+        assert_eq!(trail.literal_value(4), None);
+        assert_eq!(trail.decision_level(1), Some(0));
+        assert_eq!(trail.decision_level(3), Some(1));
+        assert_eq!(trail.reason(3), Some(4));
+        assert_eq!(trail.reason(2), None);
+
+        trail.backtrack(1);
+        assert_eq!(trail.get_literals(), &[1, -2, 3]);
+        trail.new_decision_level();
+        trail.new_decision_level();
+        trail.backtrack(2);
+        assert_eq!(trail.get_decision_level(), 2);
+        trail.backtrack(1);
+        assert_eq!(trail.decision_level(5), None);
+        trail.truncate(1);
+        assert_eq!(trail.get_decision_level(), 0);
+        assert_eq!(trail.reason(3), None);
+        assert_eq!(trail.to_assignment(), Assignment::from_iter([(1, true)]));
+    }
+}
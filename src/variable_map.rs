@@ -0,0 +1,197 @@
+use hashbrown::HashMap;
+
+use crate::cnf::{to_variable, Assignment, Clause, Literal, Variable, CNF};
+use crate::expression::Expression;
+
+pub(crate) const VARIABLE_MAP_BEGIN: &str = "microsat-map begin";
+pub(crate) const VARIABLE_MAP_END: &str = "microsat-map end";
+
+/// Records how the variables of a simplified formula relate to the original formula,
+/// so that models of the simplified formula can be mapped back to the original variables.
+///
+/// Besides the renaming, the map keeps a reconstruction stack of clauses over the original
+/// variables for variables that were eliminated. The first literal of each reconstruction clause is
+/// its witness: when mapping a model back, the stack is replayed in reverse and the witness is set to
+/// true whenever its clause would otherwise be falsified.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VariableMap {
+    original_to_new: HashMap<Variable, Variable>,
+    reconstruction: Vec<Clause>,
+}
+
+impl VariableMap {
+    pub fn new() -> VariableMap {
+        VariableMap {
+            original_to_new: HashMap::new(),
+            reconstruction: Vec::new(),
+        }
+    }
+
+    /// Renumbers the variables of the expression densely (starting at 1, in increasing order of the
+    /// original variables), returning the renamed expression together with the map.
+    pub fn compact(expression: &Expression) -> (Expression, VariableMap) {
+        let clauses = expression.get_clauses();
+        let mut variables: Vec<Variable> = clauses
+            .iter()
+            .flat_map(|clause| {
+                clause
+                    .literals()
+                    .iter()
+                    .map(|literal| to_variable(*literal))
+            })
+            .collect();
+        variables.sort_unstable();
+        variables.dedup();
+
+        let mut map = VariableMap::new();
+        for (index, variable) in variables.iter().enumerate() {
+            map.insert(*variable, index as Variable + 1);
+        }
+
+        let mut compacted = Expression::new();
+        for clause in &clauses {
+            let mut renamed = Clause::new();
+            for literal in clause.literals() {
+                renamed.insert(map.to_new_literal(*literal).unwrap());
+            }
+            compacted.add_clause(renamed);
+        }
+
+        (compacted, map)
+    }
+
+    /// Records that the original variable is represented by the new variable.
+    pub fn insert(&mut self, original: Variable, new: Variable) {
+        self.original_to_new.insert(original, new);
+    }
+
+    /// Pushes a reconstruction clause over the original variables, whose first literal is the witness.
+    pub fn add_reconstruction_clause(&mut self, clause: Clause) {
+        assert!(
+            !clause.is_empty(),
+            "Reconstruction clauses need a witness literal"
+        );
+        self.reconstruction.push(clause);
+    }
+
+    pub fn get_reconstruction_clauses(&self) -> &Vec<Clause> {
+        &self.reconstruction
+    }
+
+    pub fn to_new(&self, original: Variable) -> Option<Variable> {
+        self.original_to_new.get(&original).copied()
+    }
+
+    pub fn to_new_literal(&self, literal: Literal) -> Option<Literal> {
+        self.to_new(to_variable(literal)).map(|variable| {
+            if literal > 0 {
+                variable as Literal
+            } else {
+                -(variable as Literal)
+            }
+        })
+    }
+
+    /// Returns the (original, new) pairs sorted by the original variable.
+    pub fn mappings(&self) -> Vec<(Variable, Variable)> {
+        let mut mappings: Vec<(Variable, Variable)> =
+            self.original_to_new.iter().map(|(k, v)| (*k, *v)).collect();
+        mappings.sort_unstable();
+        mappings
+    }
+
+    /// Translates a model of the simplified formula into a model over the original variables.
+    pub fn map_model_back(&self, model: &Assignment) -> Assignment {
+        let mut original = Assignment::new();
+        for (original_variable, new_variable) in &self.original_to_new {
+            if let Some(value) = model.get(new_variable) {
+                original.insert(*original_variable, *value);
+            }
+        }
+
+        for clause in self.reconstruction.iter().rev() {
+            let satisfied = clause.literals().iter().any(|literal| {
+                original
+                    .get(&to_variable(*literal))
+                    .copied()
+                    .unwrap_or(false)
+                    == (*literal > 0)
+            });
+
+            if !satisfied {
+                let witness = clause.get(0);
+                original.insert(to_variable(witness), witness > 0);
+            }
+        }
+
+        original
+    }
+
+    /// Renders the map as a block of DIMACS comment lines (without a trailing newline).
+    pub fn to_dimacs_comments(&self) -> String {
+        let mut lines = vec![format!("c {}", VARIABLE_MAP_BEGIN)];
+        for (original, new) in self.mappings() {
+            lines.push(format!("c map {} {}", original, new));
+        }
+        for clause in &self.reconstruction {
+            let literals: Vec<String> = clause.literals().iter().map(|l| l.to_string()).collect();
+            lines.push(format!("c reconstruct {} 0", literals.join(" ")));
+        }
+        lines.push(format!("c {}", VARIABLE_MAP_END));
+        lines.join("\n")
+    }
+
+    /// Reads back a map written by [`VariableMap::to_dimacs_comments`] from the contents of a
+    /// DIMACS file, or returns `None` if the file has no map block.
+    pub fn from_dimacs_comments(contents: &str) -> Option<VariableMap> {
+        let mut map = VariableMap::new();
+        let mut in_block = false;
+        let mut found = false;
+
+        for line in contents.lines() {
+            let comment = match line.trim().strip_prefix('c') {
+                Some(comment) => comment.trim(),
+                None => continue,
+            };
+
+            if comment == VARIABLE_MAP_BEGIN {
+                in_block = true;
+                found = true;
+                continue;
+            }
+            if comment == VARIABLE_MAP_END {
+                in_block = false;
+                continue;
+            }
+            if !in_block {
+                continue;
+            }
+
+            let mut parts = comment.split_whitespace();
+            match parts.next() {
+                Some("map") => {
+                    let original = parts.next()?.parse::<Variable>().ok()?;
+                    let new = parts.next()?.parse::<Variable>().ok()?;
+                    map.insert(original, new);
+                }
+                Some("reconstruct") => {
+                    let mut clause = Clause::new();
+                    for literal in parts {
+                        let value = literal.parse::<Literal>().ok()?;
+                        if value == 0 {
+                            break;
+                        }
+                        clause.insert(value);
+                    }
+                    if clause.is_empty() {
+                        return None;
+                    }
+                    map.add_reconstruction_clause(clause);
+                }
+                _ => return None,
+            }
+        }
+
+        found.then_some(map)
+    }
+}
@@ -0,0 +1,409 @@
+//! Checks of a solver's answer in tiers of increasing cost, each with a time budget of its own,
+//! reporting which of them ran and what they found.
+//!
+//! - [`Check::Model`] evaluates every clause under the model of a satisfiable answer.
+//! - [`Check::Proof`] checks the DRAT proof of an unsatisfiable answer with
+//!   [`check_drat_with_deadline`], if the solve logged one.
+//! - [`Check::BruteForce`] tries every assignment of a small instance, so it confirms an answer
+//!   either way without trusting the solver at all.
+//!
+//! [`solve`](crate::solver::solve) with `verify` set only runs the first tier, and panics if it
+//! fails. [`verify_result`] runs all of them and returns a [`VerificationReport`] instead, which
+//! prints as DIMACS comment lines.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::cnf::{to_variable, Variable};
+use crate::dpll::SearchResult;
+use crate::drat::{check_drat_with_deadline, DratError, Proof};
+use crate::expression::Expression;
+
+/// A tier of [`verify_result`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Check {
+    Model,
+    Proof,
+    BruteForce,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Padded, so the report lines up
+        f.pad(match self {
+            Check::Model => "model",
+            Check::Proof => "proof",
+            Check::BruteForce => "brute force",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CheckOutcome {
+    Passed,
+    /// The check shows the answer is wrong, for the reason given.
+    Failed(String),
+    /// The budget of the check ran out before it was done.
+    TimedOut,
+    /// The check did not run, for the reason given.
+    Skipped(String),
+}
+
+impl fmt::Display for CheckOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckOutcome::Passed => write!(f, "passed"),
+            CheckOutcome::Failed(reason) => write!(f, "FAILED: {}", reason),
+            CheckOutcome::TimedOut => write!(f, "timed out"),
+            CheckOutcome::Skipped(reason) => write!(f, "skipped: {}", reason),
+        }
+    }
+}
+
+/// The time budgets of the tiers, which are skipped if `None`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VerificationPolicy {
+    pub model: Option<Duration>,
+    pub proof: Option<Duration>,
+    pub brute_force: Option<Duration>,
+    /// The most variables an instance can have for the brute-force check to run.
+    pub brute_force_max_variables: usize,
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        VerificationPolicy {
+            model: Some(Duration::from_secs(10)),
+            proof: Some(Duration::from_secs(60)),
+            brute_force: Some(Duration::from_secs(1)),
+            brute_force_max_variables: 20,
+        }
+    }
+}
+
+/// What one tier found, and how long it took.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CheckReport {
+    pub check: Check,
+    pub outcome: CheckOutcome,
+    pub time: Duration,
+}
+
+/// The outcome of every tier, in the order they ran.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerificationReport {
+    pub checks: Vec<CheckReport>,
+}
+
+impl VerificationReport {
+    /// Returns the outcome of the tier.
+    pub fn get(&self, check: Check) -> Option<&CheckOutcome> {
+        self.checks
+            .iter()
+            .find(|report| report.check == check)
+            .map(|report| &report.outcome)
+    }
+
+    /// Returns whether no tier found the answer wrong.
+    pub fn is_ok(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|report| matches!(report.outcome, CheckOutcome::Failed(_)))
+    }
+
+    /// Returns whether some tier passed, so the answer was confirmed.
+    pub fn is_confirmed(&self) -> bool {
+        self.is_ok()
+            && self
+                .checks
+                .iter()
+                .any(|report| report.outcome == CheckOutcome::Passed)
+    }
+}
+
+/// Formats the report as DIMACS comment lines, one per tier.
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, report) in self.checks.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "c check {:<12} {} ({:.3} ms)",
+                report.check,
+                report.outcome,
+                report.time.as_secs_f64() * 1000.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the result of solving the expression in every tier the policy gives a budget, using the
+/// proof of an unsatisfiable result if there is one. A result of `BudgetExhausted` claims
+/// nothing, so every tier is skipped.
+pub fn verify_result(
+    expression: &Expression,
+    result: &SearchResult,
+    proof: Option<&Proof>,
+    policy: &VerificationPolicy,
+) -> VerificationReport {
+    let tiers: [(Check, Option<Duration>); 3] = [
+        (Check::Model, policy.model),
+        (Check::Proof, policy.proof),
+        (Check::BruteForce, policy.brute_force),
+    ];
+    let mut report = VerificationReport::default();
+    for (check, budget) in tiers {
+        let start = Instant::now();
+        let outcome = match (budget, result) {
+            (None, _) => CheckOutcome::Skipped("no budget".to_string()),
+            (_, SearchResult::BudgetExhausted) => {
+                CheckOutcome::Skipped("the solve gave no answer".to_string())
+            }
+            (Some(budget), _) => {
+                let deadline = start + budget;
+                match check {
+                    Check::Model => check_model(expression, result, deadline),
+                    Check::Proof => check_proof(expression, result, proof, deadline),
+                    Check::BruteForce => check_brute_force(
+                        expression,
+                        result,
+                        policy.brute_force_max_variables,
+                        deadline,
+                    ),
+                }
+            }
+        };
+        report.checks.push(CheckReport {
+            check,
+            outcome,
+            time: start.elapsed(),
+        });
+    }
+    report
+}
+
+fn check_model(expression: &Expression, result: &SearchResult, deadline: Instant) -> CheckOutcome {
+    let SearchResult::Satisfiable(model) = result else {
+        return CheckOutcome::Skipped("there is no model".to_string());
+    };
+    for (index, clause) in expression.get_clauses().iter().enumerate() {
+        if index % 1024 == 0 && Instant::now() >= deadline {
+            return CheckOutcome::TimedOut;
+        }
+        let satisfied = clause
+            .literals()
+            .iter()
+            .any(|literal| model.get(&to_variable(*literal)).copied() == Some(*literal > 0));
+        if !satisfied {
+            return CheckOutcome::Failed(format!("clause {} is not satisfied", index + 1));
+        }
+    }
+    CheckOutcome::Passed
+}
+
+fn check_proof(
+    expression: &Expression,
+    result: &SearchResult,
+    proof: Option<&Proof>,
+    deadline: Instant,
+) -> CheckOutcome {
+    if *result != SearchResult::Unsatisfiable {
+        return CheckOutcome::Skipped("there is nothing to refute".to_string());
+    }
+    let Some(proof) = proof else {
+        return CheckOutcome::Skipped("no proof was logged".to_string());
+    };
+    match check_drat_with_deadline(expression, proof, Some(deadline)) {
+        Ok(()) => CheckOutcome::Passed,
+        Err(DratError::TimedOut) => CheckOutcome::TimedOut,
+        Err(error) => CheckOutcome::Failed(error.to_string()),
+    }
+}
+
+fn check_brute_force(
+    expression: &Expression,
+    result: &SearchResult,
+    max_variables: usize,
+    deadline: Instant,
+) -> CheckOutcome {
+    let mut variables: Vec<Variable> = Vec::new();
+    for clause in expression.get_clauses() {
+        variables.extend(
+            clause
+                .literals()
+                .iter()
+                .map(|literal| to_variable(*literal)),
+        );
+    }
+    variables.sort_unstable();
+    variables.dedup();
+    // The assignments are the bits of a u64
+    let max_variables = max_variables.min(63);
+    if variables.len() > max_variables {
+        return CheckOutcome::Skipped(format!(
+            "{} variables, past the limit of {}",
+            variables.len(),
+            max_variables
+        ));
+    }
+
+    match (find_model(expression, &variables, deadline), result) {
+        (None, _) => CheckOutcome::TimedOut,
+        (Some(true), SearchResult::Unsatisfiable) => {
+            CheckOutcome::Failed("an assignment satisfies the clauses".to_string())
+        }
+        (Some(false), SearchResult::Satisfiable(_)) => {
+            CheckOutcome::Failed("no assignment satisfies the clauses".to_string())
+        }
+        _ => CheckOutcome::Passed,
+    }
+}
+
+/// Tries every assignment of the variables, returning whether one satisfies the expression, or
+/// `None` if the deadline passes first.
+fn find_model(expression: &Expression, variables: &[Variable], deadline: Instant) -> Option<bool> {
+    // The clauses as bit masks over the positions of their variables, true and false
+    let clauses: Vec<(u64, u64)> = expression
+        .get_clauses()
+        .iter()
+        .map(|clause| {
+            let mut masks = (0, 0);
+            for literal in clause.literals() {
+                let position = variables.binary_search(&to_variable(*literal)).unwrap();
+                if *literal > 0 {
+                    masks.0 |= 1 << position;
+                } else {
+                    masks.1 |= 1 << position;
+                }
+            }
+            masks
+        })
+        .collect();
+    let satisfies = |assignment: u64| {
+        clauses
+            .iter()
+            .all(|(positive, negative)| assignment & positive != 0 || !assignment & negative != 0)
+    };
+
+    for assignment in 0..1u64 << variables.len() {
+        if assignment % 4096 == 0 && Instant::now() >= deadline {
+            return None;
+        }
+        if satisfies(assignment) {
+            return Some(true);
+        }
+    }
+    Some(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::generate;
+    use crate::cdcl::CdclSolver;
+    use crate::cnf::Assignment;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::drat::ProofLogger;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_verify_result() {
+        let policy = VerificationPolicy::default();
+        let expression = parse_dimacs_str("p cnf 3 2\n1 2 0\n-1 3 0\n");
+        let model: Assignment = [(1, true), (2, false), (3, true)].into_iter().collect();
+        let report = verify_result(
+            &expression,
+            &SearchResult::Satisfiable(model),
+            None,
+            &policy,
+        );
+        assert_eq!(report.get(Check::Model), Some(&CheckOutcome::Passed));
+        assert!(matches!(
+            report.get(Check::Proof),
+            Some(CheckOutcome::Skipped(_))
+        ));
+        assert_eq!(report.get(Check::BruteForce), Some(&CheckOutcome::Passed));
+        assert!(report.is_confirmed());
+        assert_eq!(report.to_string().lines().count(), 3);
+
+        // A wrong model and a wrong refutation are both caught
+        let model: Assignment = [(1, true), (2, false), (3, false)].into_iter().collect();
+        let report = verify_result(
+            &expression,
+            &SearchResult::Satisfiable(model),
+            None,
+            &policy,
+        );
+        assert_eq!(
+            report.get(Check::Model),
+            Some(&CheckOutcome::Failed(
+                "clause 2 is not satisfied".to_string()
+            ))
+        );
+        let report = verify_result(&expression, &SearchResult::Unsatisfiable, None, &policy);
+        assert!(matches!(
+            report.get(Check::BruteForce),
+            Some(CheckOutcome::Failed(_))
+        ));
+        assert!(!report.is_ok());
+        assert!(report.to_string().contains("FAILED"));
+
+        // The proof of a refutation is checked, and the brute force is skipped past its limit
+        let hole6 = generate("hole6").unwrap().expression;
+        let buffer = SharedBuffer::default();
+        let mut solver = CdclSolver::new(&hole6);
+        solver.set_proof(ProofLogger::new(buffer.clone()));
+        let result = solver.solve();
+        let proof = Proof::parse(&String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap());
+        let proof = proof.unwrap();
+        let report = verify_result(&hole6, &result, Some(&proof), &policy);
+        assert_eq!(report.get(Check::Proof), Some(&CheckOutcome::Passed));
+        assert!(matches!(
+            report.get(Check::BruteForce),
+            Some(CheckOutcome::Skipped(_))
+        ));
+        assert!(report.is_confirmed());
+
+        let policy = VerificationPolicy {
+            model: None,
+            proof: Some(Duration::ZERO),
+            ..VerificationPolicy::default()
+        };
+        let report = verify_result(&hole6, &result, Some(&proof), &policy);
+        assert_eq!(
+            report.get(Check::Model),
+            Some(&CheckOutcome::Skipped("no budget".to_string()))
+        );
+        assert_eq!(report.get(Check::Proof), Some(&CheckOutcome::TimedOut));
+        assert!(report.is_ok() && !report.is_confirmed());
+    }
+
+    #[test]
+    fn test_find_model() {
+        // The variables are not contiguous, so they take the bits by position
+        let variables = [3, 5, 9];
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let expression = parse_dimacs_str("p cnf 9 3\n3 9 0\n-3 0\n-9 5 0\n");
+        assert_eq!(find_model(&expression, &variables, deadline), Some(true));
+        let expression = parse_dimacs_str("p cnf 9 4\n3 9 0\n-3 0\n-9 5 0\n-5 0\n");
+        assert_eq!(find_model(&expression, &variables, deadline), Some(false));
+        assert_eq!(find_model(&expression, &variables, Instant::now()), None);
+    }
+}
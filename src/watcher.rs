@@ -0,0 +1,203 @@
+//! Notifications about user constraints while the DPLL search runs, for front-ends that show how
+//! the search treats them.
+//!
+//! A [`ClauseWatcher`] holds the clauses and variables an embedder is interested in, and is passed
+//! as the hook of [`solve_dpll_with_hook`](crate::dpll::solve_dpll_with_hook). After every step
+//! and backtrack of the search, it looks at the variables whose value changed and calls its
+//! callback once for every watched clause whose status changed and every watched variable that
+//! was assigned or unassigned. The watched clauses do not need to be clauses of the expression.
+
+use crate::cnf::{to_variable, Literal, Variable};
+use crate::dpll::{SearchEvent, SearchHook};
+use crate::hash::FnvHashMap;
+use crate::trail::Trail;
+
+/// The status of a clause under a partial assignment.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClauseStatus {
+    /// No literal is true, and at least two are unassigned.
+    Open,
+    /// No literal is true, and only this literal is unassigned.
+    Unit(Literal),
+    Satisfied,
+    Falsified,
+}
+
+/// A change reported by a [`ClauseWatcher`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WatchEvent {
+    /// The watched clause with this index, as returned by [`ClauseWatcher::watch_clause`], has a
+    /// new status.
+    Clause(usize, ClauseStatus),
+    /// The watched variable was assigned this value, or unassigned for `None`.
+    Variable(Variable, Option<bool>),
+}
+
+/// Calls its callback with a [`WatchEvent`] whenever a watched clause or variable changes during
+/// the search.
+pub struct ClauseWatcher<F: FnMut(WatchEvent)> {
+    clauses: Vec<Vec<Literal>>,
+    statuses: Vec<ClauseStatus>,
+    /// The watched clauses each variable occurs in.
+    occurrences: FnvHashMap<Variable, Vec<usize>>,
+    variables: FnvHashMap<Variable, Option<bool>>,
+    /// The assignment as of the last notification, to find what changed since.
+    assigned: Vec<Literal>,
+    callback: F,
+}
+
+fn status(literals: &[Literal], trail: &Trail) -> ClauseStatus {
+    let mut unassigned = None;
+    let mut num_unassigned = 0;
+    for literal in literals {
+        match trail.literal_value(*literal) {
+            Some(true) => return ClauseStatus::Satisfied,
+            Some(false) => {}
+            None => {
+                unassigned = Some(*literal);
+                num_unassigned += 1;
+            }
+        }
+    }
+    match (num_unassigned, unassigned) {
+        (0, _) => ClauseStatus::Falsified,
+        (1, Some(literal)) => ClauseStatus::Unit(literal),
+        _ => ClauseStatus::Open,
+    }
+}
+
+impl<F: FnMut(WatchEvent)> ClauseWatcher<F> {
+    pub fn new(callback: F) -> ClauseWatcher<F> {
+        ClauseWatcher {
+            clauses: Vec::new(),
+            statuses: Vec::new(),
+            occurrences: FnvHashMap::default(),
+            variables: FnvHashMap::default(),
+            assigned: Vec::new(),
+            callback,
+        }
+    }
+
+    /// Watches the clause and returns its index in the events. Its status starts out as the one
+    /// under the empty assignment, without an event.
+    pub fn watch_clause(&mut self, literals: &[Literal]) -> usize {
+        let index = self.clauses.len();
+        for literal in literals {
+            let clauses = self.occurrences.entry(to_variable(*literal)).or_default();
+            if clauses.last() != Some(&index) {
+                clauses.push(index);
+            }
+        }
+        self.statuses.push(status(literals, &Trail::new()));
+        self.clauses.push(literals.to_vec());
+        index
+    }
+
+    pub fn watch_variable(&mut self, variable: Variable) {
+        self.variables.insert(variable, None);
+    }
+
+    /// Returns the status of the watched clause as of the last notification.
+    pub fn get_status(&self, index: usize) -> ClauseStatus {
+        self.statuses[index]
+    }
+
+    /// Reports the changes to the watched clauses and variables of the changed variables.
+    fn update(&mut self, changed: Vec<Literal>, trail: &Trail) {
+        let mut clauses = Vec::new();
+        for literal in &changed {
+            let variable = to_variable(*literal);
+            if let Some(value) = self.variables.get_mut(&variable) {
+                *value = trail.value(variable);
+                (self.callback)(WatchEvent::Variable(variable, *value));
+            }
+            if let Some(occurrences) = self.occurrences.get(&variable) {
+                clauses.extend_from_slice(occurrences);
+            }
+        }
+
+        clauses.sort_unstable();
+        clauses.dedup();
+        for index in clauses {
+            let status = status(&self.clauses[index], trail);
+            if status != self.statuses[index] {
+                self.statuses[index] = status;
+                (self.callback)(WatchEvent::Clause(index, status));
+            }
+        }
+    }
+}
+
+impl<F: FnMut(WatchEvent)> SearchHook for ClauseWatcher<F> {
+    fn on_event(&mut self, _event: SearchEvent, trail: &Trail) {
+        let changed = trail.get_literals()[self.assigned.len()..].to_vec();
+        self.assigned.extend_from_slice(&changed);
+        self.update(changed, trail);
+    }
+
+    fn on_backtrack(&mut self, trail: &Trail) {
+        let changed = self
+            .assigned
+            .split_off(trail.len().min(self.assigned.len()));
+        self.update(changed, trail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs_parser::parse_dimacs_str;
+    use crate::dpll::solve_dpll_with_hook;
+
+    #[test]
+    fn test_clause_watcher() {
+        let mut expression = parse_dimacs_str("p cnf 3 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 3 0\n");
+        let mut events = Vec::new();
+        let mut watcher = ClauseWatcher::new(|event| events.push(event));
+        let watched = watcher.watch_clause(&[-2, -3]);
+        watcher.watch_variable(3);
+        assert_eq!(watcher.get_status(watched), ClauseStatus::Open);
+
+        let model = solve_dpll_with_hook(&mut expression, &mut watcher).unwrap();
+        assert!(model[&1] && model[&2] && model[&3]);
+        assert_eq!(watcher.get_status(watched), ClauseStatus::Falsified);
+        drop(watcher);
+
+        // 3 is pure, so the search sets it first and the clause becomes unit, then falsified
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::Variable(3, Some(true)),
+                WatchEvent::Clause(watched, ClauseStatus::Unit(-2)),
+                WatchEvent::Clause(watched, ClauseStatus::Falsified),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clause_watcher_backtracking() {
+        // Unsatisfiable, so the search backtracks to the empty assignment in the end
+        let mut expression = parse_dimacs_str("p cnf 2 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n");
+        let mut events = Vec::new();
+        let mut watcher = ClauseWatcher::new(|event| events.push(event));
+        let watched = watcher.watch_clause(&[1, 2]);
+        watcher.watch_variable(1);
+
+        assert!(solve_dpll_with_hook(&mut expression, &mut watcher).is_none());
+        assert_eq!(watcher.get_status(watched), ClauseStatus::Open);
+        drop(watcher);
+        assert!(events.contains(&WatchEvent::Clause(watched, ClauseStatus::Satisfied)));
+
+        // Backtracking past the propagation of 2 makes the clause unit again, then past the
+        // decision on 1 open
+        let end = events.len() - 3;
+        assert_eq!(
+            events[end..],
+            [
+                WatchEvent::Clause(watched, ClauseStatus::Unit(2)),
+                WatchEvent::Variable(1, None),
+                WatchEvent::Clause(watched, ClauseStatus::Open),
+            ]
+        );
+    }
+}
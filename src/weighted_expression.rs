@@ -0,0 +1,87 @@
+use crate::cnf::{to_variable, Assignment, Clause, Variable, CNF};
+use crate::dimacs_parser::{parse_wcnf, ParseError};
+use crate::expression::Expression;
+
+/// The weight of a soft clause.
+pub type Weight = u64;
+
+/// A weighted (partial) MaxSAT instance: a set of hard clauses that must be satisfied,
+/// and a set of soft clauses that each carry the cost paid when they are violated.
+#[derive(Clone, Debug, Default)]
+pub struct WeightedExpression {
+    hard_clauses: Vec<Clause>,
+    soft_clauses: Vec<(Clause, Weight)>,
+}
+
+impl WeightedExpression {
+    pub fn new() -> WeightedExpression {
+        WeightedExpression {
+            hard_clauses: Vec::new(),
+            soft_clauses: Vec::new(),
+        }
+    }
+
+    pub fn from_wcnf_file(file_name: &str) -> Result<WeightedExpression, ParseError> {
+        parse_wcnf(file_name)
+    }
+
+    pub fn add_hard_clause(&mut self, clause: Clause) {
+        self.hard_clauses.push(clause);
+    }
+
+    pub fn add_soft_clause(&mut self, clause: Clause, weight: Weight) {
+        self.soft_clauses.push((clause, weight));
+    }
+
+    pub fn get_hard_clauses(&self) -> &Vec<Clause> {
+        &self.hard_clauses
+    }
+
+    pub fn get_soft_clauses(&self) -> &Vec<(Clause, Weight)> {
+        &self.soft_clauses
+    }
+
+    /// Returns the sum of the weights of all soft clauses.
+    pub fn total_soft_weight(&self) -> Weight {
+        self.soft_clauses.iter().map(|(_, weight)| weight).sum()
+    }
+
+    /// Returns the largest variable referenced by any hard or soft clause.
+    pub fn get_max_variable(&self) -> Variable {
+        self.hard_clauses
+            .iter()
+            .chain(self.soft_clauses.iter().map(|(clause, _)| clause))
+            .flat_map(|clause| clause.literals().iter())
+            .map(|literal| to_variable(*literal))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Builds a plain expression containing only the hard clauses.
+    pub fn hard_expression(&self) -> Expression {
+        let mut expression = Expression::new();
+        for clause in &self.hard_clauses {
+            expression.add_clause(clause.clone());
+        }
+
+        expression
+    }
+
+    /// Returns the sum of the weights of the soft clauses violated by the assignment.
+    /// As in [`Clause::is_satisfied_by`], a variable missing from the assignment satisfies none of
+    /// its literals.
+    pub fn cost(&self, assignment: &Assignment) -> Weight {
+        self.soft_clauses
+            .iter()
+            .filter(|(clause, _)| !clause.is_satisfied_by(assignment))
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    /// Returns true if the assignment satisfies all of the hard clauses.
+    pub fn satisfies_hard_clauses(&self, assignment: &Assignment) -> bool {
+        self.hard_clauses
+            .iter()
+            .all(|clause| clause.is_satisfied_by(assignment))
+    }
+}